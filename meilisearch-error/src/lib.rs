@@ -52,6 +52,8 @@ pub enum Code {
     CreateIndex,
     IndexAlreadyExists,
     IndexNotFound,
+    AliasNotFound,
+    RolloverPolicyNotFound,
     InvalidIndexUid,
     OpenIndex,
 
@@ -61,20 +63,36 @@ pub enum Code {
     PrimaryKeyAlreadyPresent,
 
     MaxFieldsLimitExceeded,
+    DocumentTooLarge,
+    DocumentQuotaExceeded,
+    UnroutableDocument,
     MissingDocumentId,
+    InvalidDocumentId,
+    InvalidMergeOperator,
 
     Facet,
     Filter,
     Sort,
+    SearchDepthExceeded,
+    SearchLimitExceeded,
+    TooManyFacets,
+    TooManyAttributesToRetrieve,
 
     BadParameter,
     BadRequest,
     DocumentNotFound,
+    CheckpointNotFound,
+    ScrollNotFound,
     Internal,
+    InvalidDate,
     InvalidGeoField,
     InvalidRankingRule,
     InvalidToken,
     MissingAuthorizationHeader,
+    ApiKeyNotFound,
+    InvalidApiKey,
+    WebhookNotFound,
+    InvalidWebhook,
     NotFound,
     PayloadTooLarge,
     RetrieveDocument,
@@ -83,11 +101,23 @@ pub enum Code {
 
     DumpAlreadyInProgress,
     DumpProcessFailed,
+    InvalidDump,
 
     InvalidContentType,
     MissingContentType,
     MalformedPayload,
     MissingPayload,
+    InvalidUpdateMetadata,
+    UpdatePayloadNotFound,
+    UpdateNotRetryable,
+    UpdateArchiveDisabled,
+
+    UpdateWaitTimeout,
+    SearchOverloaded,
+    IndexUnavailable,
+    UpdatePanicked,
+
+    ProfileInProgress,
 }
 
 impl Code {
@@ -102,6 +132,10 @@ impl Code {
             IndexAlreadyExists => ErrCode::invalid("index_already_exists", StatusCode::BAD_REQUEST),
             // thrown when requesting an unexisting index
             IndexNotFound => ErrCode::invalid("index_not_found", StatusCode::NOT_FOUND),
+            AliasNotFound => ErrCode::invalid("alias_not_found", StatusCode::NOT_FOUND),
+            RolloverPolicyNotFound => {
+                ErrCode::invalid("rollover_policy_not_found", StatusCode::NOT_FOUND)
+            }
             InvalidIndexUid => ErrCode::invalid("invalid_index_uid", StatusCode::BAD_REQUEST),
             OpenIndex => {
                 ErrCode::internal("index_not_accessible", StatusCode::INTERNAL_SERVER_ERROR)
@@ -122,7 +156,16 @@ impl Code {
             MaxFieldsLimitExceeded => {
                 ErrCode::invalid("max_fields_limit_exceeded", StatusCode::BAD_REQUEST)
             }
+            DocumentTooLarge => ErrCode::invalid("document_too_large", StatusCode::BAD_REQUEST),
+            DocumentQuotaExceeded => {
+                ErrCode::invalid("document_quota_exceeded", StatusCode::BAD_REQUEST)
+            }
+            UnroutableDocument => ErrCode::invalid("unroutable_document", StatusCode::BAD_REQUEST),
             MissingDocumentId => ErrCode::invalid("missing_document_id", StatusCode::BAD_REQUEST),
+            InvalidDocumentId => ErrCode::invalid("invalid_document_id", StatusCode::BAD_REQUEST),
+            InvalidMergeOperator => {
+                ErrCode::invalid("invalid_merge_operator", StatusCode::BAD_REQUEST)
+            }
 
             // error related to facets
             Facet => ErrCode::invalid("invalid_facet", StatusCode::BAD_REQUEST),
@@ -130,11 +173,24 @@ impl Code {
             Filter => ErrCode::invalid("invalid_filter", StatusCode::BAD_REQUEST),
             // error related to sorts
             Sort => ErrCode::invalid("invalid_sort", StatusCode::BAD_REQUEST),
+            SearchDepthExceeded => {
+                ErrCode::invalid("search_depth_exceeded", StatusCode::BAD_REQUEST)
+            }
+            SearchLimitExceeded => {
+                ErrCode::invalid("search_limit_exceeded", StatusCode::BAD_REQUEST)
+            }
+            TooManyFacets => ErrCode::invalid("too_many_facets", StatusCode::BAD_REQUEST),
+            TooManyAttributesToRetrieve => {
+                ErrCode::invalid("too_many_attributes_to_retrieve", StatusCode::BAD_REQUEST)
+            }
 
             BadParameter => ErrCode::invalid("bad_parameter", StatusCode::BAD_REQUEST),
             BadRequest => ErrCode::invalid("bad_request", StatusCode::BAD_REQUEST),
             DocumentNotFound => ErrCode::invalid("document_not_found", StatusCode::NOT_FOUND),
+            CheckpointNotFound => ErrCode::invalid("checkpoint_not_found", StatusCode::NOT_FOUND),
+            ScrollNotFound => ErrCode::invalid("scroll_not_found", StatusCode::NOT_FOUND),
             Internal => ErrCode::internal("internal", StatusCode::INTERNAL_SERVER_ERROR),
+            InvalidDate => ErrCode::invalid("invalid_date", StatusCode::BAD_REQUEST),
             InvalidGeoField => {
                 ErrCode::authentication("invalid_geo_field", StatusCode::BAD_REQUEST)
             }
@@ -142,6 +198,10 @@ impl Code {
             MissingAuthorizationHeader => {
                 ErrCode::authentication("missing_authorization_header", StatusCode::UNAUTHORIZED)
             }
+            ApiKeyNotFound => ErrCode::invalid("api_key_not_found", StatusCode::NOT_FOUND),
+            InvalidApiKey => ErrCode::invalid("invalid_api_key", StatusCode::BAD_REQUEST),
+            WebhookNotFound => ErrCode::invalid("webhook_not_found", StatusCode::NOT_FOUND),
+            InvalidWebhook => ErrCode::invalid("invalid_webhook", StatusCode::BAD_REQUEST),
             NotFound => ErrCode::invalid("not_found", StatusCode::NOT_FOUND),
             PayloadTooLarge => ErrCode::invalid("payload_too_large", StatusCode::PAYLOAD_TOO_LARGE),
             RetrieveDocument => {
@@ -159,6 +219,7 @@ impl Code {
             DumpProcessFailed => {
                 ErrCode::internal("dump_process_failed", StatusCode::INTERNAL_SERVER_ERROR)
             }
+            InvalidDump => ErrCode::invalid("invalid_dump", StatusCode::BAD_REQUEST),
             MissingContentType => {
                 ErrCode::invalid("missing_content_type", StatusCode::UNSUPPORTED_MEDIA_TYPE)
             }
@@ -167,6 +228,31 @@ impl Code {
                 ErrCode::invalid("invalid_content_type", StatusCode::UNSUPPORTED_MEDIA_TYPE)
             }
             MissingPayload => ErrCode::invalid("missing_payload", StatusCode::BAD_REQUEST),
+            InvalidUpdateMetadata => {
+                ErrCode::invalid("invalid_update_metadata", StatusCode::BAD_REQUEST)
+            }
+            UpdatePayloadNotFound => {
+                ErrCode::invalid("update_payload_not_found", StatusCode::NOT_FOUND)
+            }
+            UpdateNotRetryable => ErrCode::invalid("update_not_retryable", StatusCode::BAD_REQUEST),
+            UpdateArchiveDisabled => {
+                ErrCode::invalid("update_archive_disabled", StatusCode::BAD_REQUEST)
+            }
+
+            UpdateWaitTimeout => {
+                ErrCode::internal("update_wait_timeout", StatusCode::SERVICE_UNAVAILABLE)
+            }
+            SearchOverloaded => {
+                ErrCode::internal("search_overloaded", StatusCode::SERVICE_UNAVAILABLE)
+            }
+            IndexUnavailable => {
+                ErrCode::internal("index_unavailable", StatusCode::SERVICE_UNAVAILABLE)
+            }
+            UpdatePanicked => {
+                ErrCode::internal("update_panicked", StatusCode::INTERNAL_SERVER_ERROR)
+            }
+
+            ProfileInProgress => ErrCode::invalid("profile_in_progress", StatusCode::CONFLICT),
         }
     }
 
@@ -1,24 +1,79 @@
+//! The usage events of the instance: an hourly heartbeat and the searches, sent to the anonymized
+//! telemetry of Meili and, when `--analytics-sink` is set, to a local file or endpoint. Each
+//! category of events can be disabled on its own.
+
+#[cfg(feature = "analytics")]
 use std::hash::{Hash, Hasher};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use log::debug;
+use log::{debug, warn};
+use meilisearch_lib::index::{SearchQuery, SearchResult};
 use meilisearch_lib::MeiliSearch;
 use serde::Serialize;
+#[cfg(feature = "analytics")]
 use siphasher::sip::SipHasher;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
 
 use crate::Opt;
 
+#[cfg(all(not(debug_assertions), feature = "analytics"))]
 const AMPLITUDE_API_KEY: &str = "f7fba398780e06d8fe6666a9be7e3d47";
 
-#[derive(Debug, Serialize)]
-struct EventProperties {
+/// How often the heartbeat is sent.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(3600);
+/// How often the pending search events are sent.
+const SEARCH_EVENTS_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+/// The number of pending search events that triggers a send before the end of the interval.
+const MAX_SEARCH_EVENTS_BATCH: usize = 100;
+/// The number of search events waiting to be batched. The new ones are dropped when it is
+/// reached, which never slows the searches down.
+const SEARCH_EVENTS_CHANNEL_CAPACITY: usize = 1000;
+
+/// Where the events are written locally, the URLs receiving them as a JSON array and the paths
+/// of files having them appended as NDJSON.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnalyticsSink {
+    File(PathBuf),
+    Endpoint(String),
+}
+
+impl FromStr for AnalyticsSink {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            anyhow::bail!("The analytics sink can't be empty.");
+        }
+        if s.starts_with("http://") || s.starts_with("https://") {
+            Ok(AnalyticsSink::Endpoint(s.to_string()))
+        } else {
+            Ok(AnalyticsSink::File(PathBuf::from(s)))
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Destination {
+    #[cfg(all(not(debug_assertions), feature = "analytics"))]
+    Amplitude,
+    File(PathBuf),
+    #[cfg(feature = "analytics")]
+    Endpoint(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct HeartbeatProperties {
     database_size: u64,
     last_update_timestamp: Option<i64>, //timestamp
     number_of_documents: Vec<u64>,
 }
 
-impl EventProperties {
-    async fn from(data: MeiliSearch) -> anyhow::Result<EventProperties> {
+impl HeartbeatProperties {
+    async fn from(data: MeiliSearch) -> anyhow::Result<HeartbeatProperties> {
         let stats = data.get_all_stats().await?;
 
         let database_size = stats.database_size;
@@ -29,7 +84,7 @@ impl EventProperties {
             .map(|index| index.number_of_documents)
             .collect();
 
-        Ok(EventProperties {
+        Ok(HeartbeatProperties {
             database_size,
             last_update_timestamp,
             number_of_documents,
@@ -37,90 +92,347 @@ impl EventProperties {
     }
 }
 
-#[derive(Debug, Serialize)]
-struct UserProperties<'a> {
-    env: &'a str,
+/// The shape of a search, neither its query nor its index are part of it.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchEvent {
+    query_words: usize,
+    filter: bool,
+    sort: bool,
+    facets_distribution: bool,
+    limit: usize,
+    offset: usize,
+    nb_hits: u64,
+    processing_time_ms: u128,
+}
+
+impl SearchEvent {
+    /// Describes the query, its limit and the figures of the result being set by
+    /// `Analytics::search`.
+    pub fn new(query: &SearchQuery) -> Self {
+        Self {
+            query_words: query
+                .q
+                .as_deref()
+                .map_or(0, |q| q.split_whitespace().count()),
+            filter: query.filter.is_some(),
+            sort: query.sort.is_some(),
+            facets_distribution: query.facets_distribution.is_some(),
+            limit: 0,
+            offset: query.offset.unwrap_or_default(),
+            nb_hits: 0,
+            processing_time_ms: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+enum EventProperties {
+    Heartbeat(HeartbeatProperties),
+    Search(SearchEvent),
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct UserProperties {
+    env: String,
     start_since_days: u64,
     user_email: Option<String>,
     server_provider: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
-struct Event<'a> {
-    user_id: &'a str,
-    event_type: &'a str,
-    device_id: &'a str,
+#[derive(Debug, Clone, Serialize)]
+struct Event {
+    user_id: String,
+    event_type: &'static str,
+    device_id: String,
     time: u64,
-    app_version: &'a str,
-    user_properties: UserProperties<'a>,
+    app_version: &'static str,
+    user_properties: UserProperties,
     event_properties: Option<EventProperties>,
 }
 
+#[cfg(all(not(debug_assertions), feature = "analytics"))]
 #[derive(Debug, Serialize)]
 struct AmplitudeRequest<'a> {
     api_key: &'a str,
-    events: Vec<Event<'a>>,
+    events: &'a [Event],
+}
+
+/// The handle the routes record the search events with, a no-op when they are disabled.
+#[derive(Debug, Clone, Default)]
+pub struct Analytics {
+    search_sender: Option<mpsc::Sender<SearchEvent>>,
+}
+
+impl Analytics {
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    pub fn search(&self, mut event: SearchEvent, result: &SearchResult) {
+        if let Some(ref sender) = self.search_sender {
+            // The limit of the query may have been omitted, and the default one of the index
+            // applied.
+            event.limit = result.limit;
+            event.nb_hits = result.nb_hits;
+            event.processing_time_ms = result.processing_time_ms;
+            if sender.try_send(event).is_err() {
+                debug!("Dropped a search event, the analytics sender is lagging behind.");
+            }
+        }
+    }
 }
 
-pub async fn analytics_sender(data: MeiliSearch, opt: Opt) {
-    let username = whoami::username();
-    let hostname = whoami::hostname();
-    let platform = whoami::platform();
+/// Spawns the task sending the events, if any of them has a destination.
+pub fn setup_analytics(data: MeiliSearch, opt: &Opt) -> anyhow::Result<Analytics> {
+    let mut destinations = Vec::new();
+    #[cfg(all(not(debug_assertions), feature = "analytics"))]
+    if !opt.no_analytics {
+        destinations.push(Destination::Amplitude);
+    }
+    match opt.analytics_sink {
+        Some(AnalyticsSink::File(ref path)) => destinations.push(Destination::File(path.clone())),
+        #[cfg(feature = "analytics")]
+        Some(AnalyticsSink::Endpoint(ref url)) => {
+            destinations.push(Destination::Endpoint(url.clone()))
+        }
+        #[cfg(not(feature = "analytics"))]
+        Some(AnalyticsSink::Endpoint(_)) => anyhow::bail!(
+            "Sending the analytics to an endpoint requires the `analytics` feature, use a file instead."
+        ),
+        None => (),
+    }
+
+    let heartbeat = !opt.no_analytics_heartbeat;
+    let searches = !opt.no_analytics_search;
+    if destinations.is_empty() || !(heartbeat || searches) {
+        return Ok(Analytics::disabled());
+    }
+
+    let (sender, receiver) = mpsc::channel(SEARCH_EVENTS_CHANNEL_CAPACITY);
+    let analytics_sender = AnalyticsSender {
+        data,
+        destinations,
+        heartbeat,
+        env: opt.env.clone(),
+        user_email: opt
+            .analytics_user_email
+            .then(|| std::env::var("MEILI_USER_EMAIL").ok())
+            .flatten(),
+        user_id: user_id(),
+        device_id: device_id(),
+        first_start: Instant::now(),
+    };
+    tokio::task::spawn(analytics_sender.run(receiver));
+
+    Ok(Analytics {
+        search_sender: if searches { Some(sender) } else { None },
+    })
+}
 
-    let uid = username + &hostname + &platform.to_string();
+/// The anonymized identifier of the machine.
+#[cfg(feature = "analytics")]
+fn user_id() -> String {
+    let uid = whoami::username() + &whoami::hostname() + &whoami::platform().to_string();
 
     let mut hasher = SipHasher::new();
     uid.hash(&mut hasher);
-    let hash = hasher.finish();
-
-    let uid = format!("{:X}", hash);
-    let platform = platform.to_string();
-    let first_start = Instant::now();
-
-    loop {
-        let n = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-        let user_id = &uid;
-        let device_id = &platform;
-        let time = n.as_secs();
-        let event_type = "runtime_tick";
-        let elapsed_since_start = first_start.elapsed().as_secs() / 86_400; // One day
-        let event_properties = EventProperties::from(data.clone()).await.ok();
-        let app_version = env!("CARGO_PKG_VERSION").to_string();
-        let app_version = app_version.as_str();
-        let user_email = std::env::var("MEILI_USER_EMAIL").ok();
-        let server_provider = std::env::var("MEILI_SERVER_PROVIDER").ok();
+    format!("{:X}", hasher.finish())
+}
+
+#[cfg(feature = "analytics")]
+fn device_id() -> String {
+    whoami::platform().to_string()
+}
+
+/// The machine can't be identified without `whoami`, the events of the local sink are still
+/// written.
+#[cfg(not(feature = "analytics"))]
+fn user_id() -> String {
+    String::from("unknown")
+}
+
+#[cfg(not(feature = "analytics"))]
+fn device_id() -> String {
+    String::from("unknown")
+}
+
+struct AnalyticsSender {
+    data: MeiliSearch,
+    destinations: Vec<Destination>,
+    heartbeat: bool,
+    env: String,
+    /// The email of the user, only when they opted in to send it.
+    user_email: Option<String>,
+    user_id: String,
+    device_id: String,
+    first_start: Instant,
+}
+
+impl AnalyticsSender {
+    async fn run(self, mut receiver: mpsc::Receiver<SearchEvent>) {
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        let mut flush = tokio::time::interval(SEARCH_EVENTS_FLUSH_INTERVAL);
+        let mut searches_open = true;
+        let mut pending = Vec::new();
+
+        loop {
+            tokio::select! {
+                _ = heartbeat.tick(), if self.heartbeat => {
+                    let properties = HeartbeatProperties::from(self.data.clone())
+                        .await
+                        .ok()
+                        .map(EventProperties::Heartbeat);
+                    let event = self.event("runtime_tick", properties);
+                    self.send(&[event]).await;
+                }
+                search = receiver.recv(), if searches_open => match search {
+                    Some(search) => {
+                        pending.push(self.event("search", Some(EventProperties::Search(search))));
+                        if pending.len() >= MAX_SEARCH_EVENTS_BATCH {
+                            self.send(&mem::take(&mut pending)).await;
+                        }
+                    }
+                    None => searches_open = false,
+                },
+                _ = flush.tick(), if !pending.is_empty() => {
+                    self.send(&mem::take(&mut pending)).await;
+                }
+                else => break,
+            }
+        }
+    }
+
+    fn event(&self, event_type: &'static str, properties: Option<EventProperties>) -> Event {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
         let user_properties = UserProperties {
-            env: &opt.env,
-            start_since_days: elapsed_since_start,
-            user_email,
-            server_provider,
+            env: self.env.clone(),
+            start_since_days: self.first_start.elapsed().as_secs() / 86_400, // One day
+            user_email: self.user_email.clone(),
+            server_provider: std::env::var("MEILI_SERVER_PROVIDER").ok(),
         };
 
-        let event = Event {
-            user_id,
+        Event {
+            user_id: self.user_id.clone(),
             event_type,
-            device_id,
-            time,
-            app_version,
+            device_id: self.device_id.clone(),
+            time: time.as_secs(),
+            app_version: env!("CARGO_PKG_VERSION"),
             user_properties,
-            event_properties,
-        };
+            event_properties: properties,
+        }
+    }
+
+    async fn send(&self, events: &[Event]) {
+        for destination in &self.destinations {
+            match destination {
+                #[cfg(all(not(debug_assertions), feature = "analytics"))]
+                Destination::Amplitude => {
+                    let request = AmplitudeRequest {
+                        api_key: AMPLITUDE_API_KEY,
+                        events,
+                    };
+                    let response = reqwest::Client::new()
+                        .post("https://api2.amplitude.com/2/httpapi")
+                        .timeout(Duration::from_secs(60)) // 1 minute max
+                        .json(&request)
+                        .send()
+                        .await;
+                    if let Err(e) = response {
+                        debug!("Unsuccessful call to Amplitude: {}", e);
+                    }
+                }
+                Destination::File(path) => {
+                    if let Err(e) = append_to_file(path, events).await {
+                        warn!("Error while writing the analytics to {:?}: {}", path, e);
+                    }
+                }
+                #[cfg(feature = "analytics")]
+                Destination::Endpoint(url) => {
+                    let response = reqwest::Client::new()
+                        .post(url)
+                        .timeout(Duration::from_secs(60))
+                        .json(events)
+                        .send()
+                        .await
+                        .and_then(|response| response.error_for_status());
+                    if let Err(e) = response {
+                        warn!("Error while sending the analytics to {}: {}", url, e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn append_to_file(path: &Path, events: &[Event]) -> anyhow::Result<()> {
+    let mut lines = Vec::new();
+    for event in events {
+        serde_json::to_writer(&mut lines, event)?;
+        lines.push(b'\n');
+    }
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(&lines).await?;
+    file.flush().await?;
+    Ok(())
+}
 
-        let request = AmplitudeRequest {
-            api_key: AMPLITUDE_API_KEY,
-            events: vec![event],
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_sink() {
+        assert_eq!(
+            "https://telemetry.example.com/events"
+                .parse::<AnalyticsSink>()
+                .unwrap(),
+            AnalyticsSink::Endpoint(String::from("https://telemetry.example.com/events"))
+        );
+        assert_eq!(
+            "/var/log/meilisearch/analytics.ndjson"
+                .parse::<AnalyticsSink>()
+                .unwrap(),
+            AnalyticsSink::File(PathBuf::from("/var/log/meilisearch/analytics.ndjson"))
+        );
+        assert!("".parse::<AnalyticsSink>().is_err());
+    }
+
+    #[actix_rt::test]
+    async fn append_events_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("analytics.ndjson");
+        let event = Event {
+            user_id: user_id(),
+            event_type: "runtime_tick",
+            device_id: device_id(),
+            time: 0,
+            app_version: env!("CARGO_PKG_VERSION"),
+            user_properties: UserProperties {
+                env: String::from("development"),
+                start_since_days: 0,
+                user_email: None,
+                server_provider: None,
+            },
+            event_properties: None,
         };
 
-        let response = reqwest::Client::new()
-            .post("https://api2.amplitude.com/2/httpapi")
-            .timeout(Duration::from_secs(60)) // 1 minute max
-            .json(&request)
-            .send()
-            .await;
-        if let Err(e) = response {
-            debug!("Unsuccessful call to Amplitude: {}", e);
-        }
+        append_to_file(&path, &[event.clone(), event.clone()])
+            .await
+            .unwrap();
+        append_to_file(&path, &[event]).await.unwrap();
 
-        tokio::time::sleep(Duration::from_secs(3600)).await;
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[2]["event_type"], "runtime_tick");
     }
 }
@@ -0,0 +1,189 @@
+//! The `meilisearch bench` mode, replaying a log of searches against the local database, without
+//! going through HTTP, and reporting the latencies and the throughput at each concurrency level.
+//!
+//! Each line of the log is the body of a search with the uid of the searched index:
+//!
+//! ```json
+//! {"indexUid": "movies", "q": "harry potter", "limit": 10}
+//! ```
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use meilisearch_lib::index_controller::multi_search::IndexSearchQuery;
+use meilisearch_lib::MeiliSearch;
+use structopt::StructOpt;
+
+use crate::{setup_meilisearch, Opt};
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct BenchOpt {
+    /// The NDJSON file of the searches to replay, one search body with its `indexUid` per line.
+    #[structopt(long, parse(from_os_str))]
+    pub queries: PathBuf,
+
+    /// The numbers of searches run concurrently, each of them measured separately.
+    #[structopt(long, use_delimiter = true, default_value = "1,2,4,8")]
+    pub concurrency: Vec<usize>,
+
+    /// The number of times the searches are replayed at each concurrency level.
+    #[structopt(long, default_value = "1")]
+    pub passes: usize,
+
+    #[structopt(flatten)]
+    pub opt: Opt,
+}
+
+/// The measures of the replay at one concurrency level.
+#[derive(Debug)]
+struct BenchReport {
+    concurrency: usize,
+    searches: usize,
+    errors: usize,
+    elapsed: Duration,
+    latencies: Vec<Duration>,
+}
+
+pub async fn run(opt: BenchOpt) -> anyhow::Result<()> {
+    let queries = Arc::new(read_queries(&opt.queries)?);
+    if queries.is_empty() {
+        anyhow::bail!("No search to replay in {:?}", opt.queries);
+    }
+
+    let meilisearch = setup_meilisearch(&opt.opt)?;
+
+    eprintln!(
+        "Replaying {} searches {} time(s) against {:?}.",
+        queries.len(),
+        opt.passes,
+        opt.opt.db_path
+    );
+    println!("concurrency\tsearches\terrors\tsearches/s\tp50\tp90\tp99\tmax");
+    for &concurrency in &opt.concurrency {
+        let report = replay(
+            &meilisearch,
+            queries.clone(),
+            concurrency.max(1),
+            opt.passes,
+        )
+        .await;
+        println!("{}", report);
+    }
+
+    Ok(())
+}
+
+fn read_queries(path: &Path) -> anyhow::Result<Vec<IndexSearchQuery>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut queries = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let query = serde_json::from_str(&line)
+            .map_err(|e| anyhow::anyhow!("Invalid search at line {}: {}", i + 1, e))?;
+        queries.push(query);
+    }
+    Ok(queries)
+}
+
+async fn replay(
+    meilisearch: &MeiliSearch,
+    queries: Arc<Vec<IndexSearchQuery>>,
+    concurrency: usize,
+    passes: usize,
+) -> BenchReport {
+    let total = queries.len() * passes;
+    let next = Arc::new(AtomicUsize::new(0));
+    let start = Instant::now();
+
+    let workers: Vec<_> = (0..concurrency)
+        .map(|_| {
+            let meilisearch = meilisearch.clone();
+            let queries = queries.clone();
+            let next = next.clone();
+            tokio::spawn(async move {
+                let mut latencies = Vec::new();
+                let mut errors = 0;
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    if i >= total {
+                        break;
+                    }
+                    let IndexSearchQuery { index_uid, query } = queries[i % queries.len()].clone();
+                    let search_start = Instant::now();
+                    match meilisearch.search(index_uid, query).await {
+                        Ok(_) => latencies.push(search_start.elapsed()),
+                        Err(_) => errors += 1,
+                    }
+                }
+                (latencies, errors)
+            })
+        })
+        .collect();
+
+    let mut latencies = Vec::with_capacity(total);
+    let mut errors = 0;
+    for worker in workers {
+        if let Ok((worker_latencies, worker_errors)) = worker.await {
+            latencies.extend(worker_latencies);
+            errors += worker_errors;
+        }
+    }
+    latencies.sort_unstable();
+
+    BenchReport {
+        concurrency,
+        searches: total,
+        errors,
+        elapsed: start.elapsed(),
+        latencies,
+    }
+}
+
+/// The latency under which `percent` percents of the sorted `latencies` are.
+fn percentile(latencies: &[Duration], percent: f64) -> Duration {
+    if latencies.is_empty() {
+        return Duration::default();
+    }
+    let rank = (percent / 100.0 * latencies.len() as f64).ceil() as usize;
+    latencies[rank.clamp(1, latencies.len()) - 1]
+}
+
+impl std::fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let throughput = self.latencies.len() as f64 / self.elapsed.as_secs_f64();
+        write!(
+            f,
+            "{}\t{}\t{}\t{:.1}\t{:?}\t{:?}\t{:?}\t{:?}",
+            self.concurrency,
+            self.searches,
+            self.errors,
+            throughput,
+            percentile(&self.latencies, 50.0),
+            percentile(&self.latencies, 90.0),
+            percentile(&self.latencies, 99.0),
+            self.latencies.last().copied().unwrap_or_default(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn percentiles() {
+        let latencies: Vec<_> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&latencies, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&latencies, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&latencies, 100.0), Duration::from_millis(100));
+        assert_eq!(percentile(&latencies, 0.0), Duration::from_millis(1));
+        assert_eq!(percentile(&[], 50.0), Duration::default());
+    }
+}
@@ -0,0 +1,92 @@
+//! The registry of the deprecated routes and parameters of the API, kept working until the next
+//! major version. The responses of the requests using one of them carry a `Warning` header for
+//! each, with the `299` code of the persistent warnings, and a `_warnings` array when their body
+//! is a JSON object, for the clients to be migrated before they break.
+
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::{HttpResponse, HttpResponseBuilder};
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Deprecation {
+    /// The deprecated route or parameter.
+    pub name: &'static str,
+    /// What to use instead.
+    pub replacement: &'static str,
+    /// The version it was deprecated in.
+    pub since: &'static str,
+}
+
+pub const UPDATE_STATUS_ROUTE: Deprecation = Deprecation {
+    name: "GET /indexes/{indexUid}/updates/{updateId}",
+    replacement: "GET /tasks/{taskUid}",
+    since: "0.23.0",
+};
+
+/// The routes and settings of the compatibility layer, see [`crate::routes::CompatApiVersion`].
+pub const UPDATES_LIST_ROUTE: Deprecation = Deprecation {
+    name: "GET /indexes/{indexUid}/updates",
+    replacement: "GET /tasks?indexUid={indexUid}",
+    since: "0.23.0",
+};
+
+pub const ASC_DESC_RANKING_RULES: Deprecation = Deprecation {
+    name: "asc(attribute) and desc(attribute) ranking rules",
+    replacement: "attribute:asc and attribute:desc",
+    since: "0.23.0",
+};
+
+/// All the deprecations, listed by `GET /instance`.
+pub const DEPRECATIONS: &[Deprecation] = &[
+    UPDATE_STATUS_ROUTE,
+    UPDATES_LIST_ROUTE,
+    ASC_DESC_RANKING_RULES,
+];
+
+impl Deprecation {
+    pub fn message(&self) -> String {
+        format!(
+            "`{}` is deprecated since v{} and will be removed in the next major version, use `{}` instead.",
+            self.name, self.since, self.replacement
+        )
+    }
+
+    fn warning(&self) -> String {
+        format!("299 meilisearch \"{}\"", self.message())
+    }
+}
+
+/// Adds the `Warning` headers of the `deprecations` used by the request to an already built
+/// `response`.
+pub fn add_warnings(response: &mut HttpResponse, deprecations: &[Deprecation]) {
+    for deprecation in deprecations {
+        if let Ok(warning) = HeaderValue::from_str(&deprecation.warning()) {
+            response.headers_mut().append(header::WARNING, warning);
+        }
+    }
+}
+
+/// Builds the response holding `body`, warning about the `deprecations` used by the request.
+pub fn json_with_warnings(
+    mut response: HttpResponseBuilder,
+    deprecations: &[Deprecation],
+    body: impl Serialize,
+) -> HttpResponse {
+    for deprecation in deprecations {
+        response.append_header((header::WARNING, deprecation.warning()));
+    }
+
+    match serde_json::to_value(&body) {
+        Ok(Value::Object(mut object)) if !deprecations.is_empty() => {
+            let warnings = deprecations
+                .iter()
+                .map(|deprecation| Value::String(deprecation.message()))
+                .collect();
+            object.insert(String::from("_warnings"), Value::Array(warnings));
+            response.json(object)
+        }
+        _ => response.json(body),
+    }
+}
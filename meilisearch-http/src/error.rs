@@ -3,18 +3,48 @@ use std::fmt;
 
 use actix_web as aweb;
 use actix_web::body::Body;
-use actix_web::http::StatusCode;
-use actix_web::HttpResponseBuilder;
+use actix_web::dev::ServiceResponse;
+use actix_web::http::{header, StatusCode};
+use actix_web::{HttpResponse, HttpResponseBuilder};
 use aweb::error::{JsonPayloadError, QueryPayloadError};
 use meilisearch_error::{Code, ErrorCode};
 use serde::{Deserialize, Serialize};
 
+/// The delay, in seconds, after which the client is told to retry when the service is unavailable.
+const RETRY_AFTER_SECS: u64 = 1;
+
 #[derive(Debug, thiserror::Error)]
 pub enum MeilisearchHttpError {
     #[error("A Content-Type header is missing. Accepted values for the Content-Type header are: \"application/json\", \"application/x-ndjson\", \"text/csv\"")]
     MissingContentType,
     #[error("The Content-Type \"{0}\" is invalid. Accepted values for the Content-Type header are: \"application/json\", \"application/x-ndjson\", \"text/csv\"")]
     InvalidContentType(String),
+    #[error("Allocator error: {0}")]
+    Allocator(String),
+    #[error("The metadata of the update is invalid: {0}")]
+    InvalidUpdateMetadata(String),
+    #[error("`{0}` is not a valid list of update ids, expected comma separated integers.")]
+    InvalidUpdateIds(String),
+    #[error("`{0}` is not a valid list of statuses to cancel, expected comma separated `enqueued` and `processing`.")]
+    InvalidCancelStatuses(String),
+    #[error("At least one of the `uids`, `indexUids` and `statuses` filters is required to cancel updates.")]
+    MissingCancelFilters,
+    #[error("`{0}` is not a valid list of task statuses, expected comma separated `enqueued`, `processing`, `processed`, `failed`, `aborted` and `canceled`.")]
+    InvalidTaskStatuses(String),
+    #[error("`{0}` is not a valid list of statuses to delete, expected comma separated `processed`, `failed` and `canceled`.")]
+    InvalidDeleteStatuses(String),
+    #[error("`{0}` is not a valid list of update types, expected comma separated `ClearAll`, `DocumentsAddition`, `DocumentsPartial`, `DocumentsDeletion` and `Settings`.")]
+    InvalidUpdateTypes(String),
+    #[error("At least one of the `statuses`, `types`, `indexUids` and `beforeFinishedAt` filters is required to delete updates.")]
+    MissingDeleteFilters,
+    #[error("The API key is not allowed to search the index `{0}`.")]
+    IndexNotAllowed(String),
+    #[error("The duration of a profile must be between 1 and {0} seconds.")]
+    InvalidProfileDuration(u64),
+    #[error("A profile is already being captured.")]
+    ProfileInProgress,
+    #[error("Profiler error: {0}")]
+    Profiler(String),
 }
 
 impl ErrorCode for MeilisearchHttpError {
@@ -22,6 +52,19 @@ impl ErrorCode for MeilisearchHttpError {
         match self {
             MeilisearchHttpError::MissingContentType => Code::MissingContentType,
             MeilisearchHttpError::InvalidContentType(_) => Code::InvalidContentType,
+            MeilisearchHttpError::Allocator(_) => Code::Internal,
+            MeilisearchHttpError::InvalidUpdateMetadata(_) => Code::InvalidUpdateMetadata,
+            MeilisearchHttpError::InvalidUpdateIds(_) => Code::BadRequest,
+            MeilisearchHttpError::InvalidCancelStatuses(_) => Code::BadRequest,
+            MeilisearchHttpError::MissingCancelFilters => Code::BadRequest,
+            MeilisearchHttpError::InvalidTaskStatuses(_) => Code::BadRequest,
+            MeilisearchHttpError::InvalidDeleteStatuses(_) => Code::BadRequest,
+            MeilisearchHttpError::InvalidUpdateTypes(_) => Code::BadRequest,
+            MeilisearchHttpError::MissingDeleteFilters => Code::BadRequest,
+            MeilisearchHttpError::IndexNotAllowed(_) => Code::InvalidToken,
+            MeilisearchHttpError::InvalidProfileDuration(_) => Code::BadRequest,
+            MeilisearchHttpError::ProfileInProgress => Code::ProfileInProgress,
+            MeilisearchHttpError::Profiler(_) => Code::Internal,
         }
     }
 }
@@ -58,12 +101,115 @@ where
     }
 }
 
+/// The formats of the error responses, negotiated from the `Accept` header of their request. The
+/// errors are in JSON unless one of the other formats is preferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Json,
+    /// The problem details of RFC 7807, with the fields of the JSON errors as extension members.
+    ProblemJson,
+    Text,
+}
+
+impl ErrorFormat {
+    pub fn from_accept(accept: Option<&str>) -> Self {
+        let mut best = (ErrorFormat::Json, 0.);
+        for media_range in accept.unwrap_or_default().split(',') {
+            let mut params = media_range.split(';').map(str::trim);
+            let format = match params.next().unwrap_or_default() {
+                "application/json" | "application/*" | "*/*" => ErrorFormat::Json,
+                "application/problem+json" => ErrorFormat::ProblemJson,
+                "text/plain" | "text/*" => ErrorFormat::Text,
+                _ => continue,
+            };
+            let quality = params
+                .find_map(|param| param.strip_prefix("q="))
+                .map_or(Some(1.), |q| q.parse::<f64>().ok())
+                .unwrap_or(0.);
+            if quality > best.1 {
+                best = (format, quality);
+            }
+        }
+        best.0
+    }
+}
+
+impl ResponseError {
+    /// The summary of the type of the error, its code written as a sentence, such as
+    /// `Index not found` for `index_not_found`.
+    fn title(&self) -> String {
+        let words = self.error_code.replace('_', " ");
+        let mut chars = words.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => words,
+        }
+    }
+
+    fn response(&self, format: ErrorFormat) -> HttpResponse<Body> {
+        let mut response = HttpResponseBuilder::new(self.status_code());
+        if self.code == StatusCode::SERVICE_UNAVAILABLE {
+            response.insert_header((header::RETRY_AFTER, RETRY_AFTER_SECS));
+        }
+        match format {
+            ErrorFormat::Json => {
+                let json = serde_json::to_vec(self).unwrap();
+                response.content_type("application/json").body(json)
+            }
+            ErrorFormat::ProblemJson => {
+                let problem = serde_json::json!({
+                    "type": self.error_link,
+                    "title": self.title(),
+                    "status": self.code.as_u16(),
+                    "detail": self.message,
+                    "errorCode": self.error_code,
+                    "errorType": self.error_type,
+                    "errorLink": self.error_link,
+                });
+                let json = serde_json::to_vec(&problem).unwrap();
+                response.content_type("application/problem+json").body(json)
+            }
+            ErrorFormat::Text => {
+                let text = format!(
+                    "{}\n\ncode: {}\ntype: {}\nlink: {}\n",
+                    self.message, self.error_code, self.error_type, self.error_link
+                );
+                response
+                    .content_type("text/plain; charset=utf-8")
+                    .body(text)
+            }
+        }
+    }
+}
+
+/// Rewrites an error response in the format asked for by the `Accept` header of its request. The
+/// errors are built without their request, in JSON, by [`aweb::error::ResponseError`]. The headers
+/// set by the handler are kept, except the ones describing the body.
+pub fn negotiate_error_format(res: ServiceResponse) -> ServiceResponse {
+    let accept = res.request().headers().get(header::ACCEPT);
+    let format = ErrorFormat::from_accept(accept.and_then(|accept| accept.to_str().ok()));
+    if format == ErrorFormat::Json {
+        return res;
+    }
+    let mut response = match res.response().error() {
+        Some(error) => match error.as_error::<ResponseError>() {
+            Some(error) => error.response(format),
+            None => return res,
+        },
+        None => return res,
+    };
+    for (name, value) in res.response().headers() {
+        let describes_body = *name == header::CONTENT_TYPE || *name == header::CONTENT_LENGTH;
+        if !describes_body && !response.headers().contains_key(name) {
+            response.headers_mut().append(name.clone(), value.clone());
+        }
+    }
+    res.into_response(response)
+}
+
 impl aweb::error::ResponseError for ResponseError {
     fn error_response(&self) -> aweb::HttpResponse<Body> {
-        let json = serde_json::to_vec(self).unwrap();
-        HttpResponseBuilder::new(self.status_code())
-            .content_type("application/json")
-            .body(json)
+        self.response(ErrorFormat::Json)
     }
 
     fn status_code(&self) -> StatusCode {
@@ -127,3 +273,37 @@ where
 {
     err.into().into()
 }
+
+#[cfg(test)]
+mod test {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    #[test]
+    fn negotiated_errors_keep_the_headers() {
+        let error = ResponseError::from(MeilisearchHttpError::MissingContentType);
+        let mut response = HttpResponse::from_error(error);
+        response.headers_mut().insert(
+            header::HeaderName::from_static("x-request-id"),
+            header::HeaderValue::from_static("import-42"),
+        );
+        let req = TestRequest::default()
+            .insert_header((header::ACCEPT, "application/problem+json"))
+            .to_http_request();
+
+        let response = negotiate_error_format(ServiceResponse::new(req, response));
+        let value = |name| response.response().headers().get(name).unwrap().to_str();
+        assert_eq!(value("x-request-id").unwrap(), "import-42");
+        assert_eq!(
+            value(header::CONTENT_TYPE.as_str()).unwrap(),
+            "application/problem+json"
+        );
+    }
+
+    #[test]
+    fn problem_titles() {
+        let error = ResponseError::from(MeilisearchHttpError::MissingContentType);
+        assert_eq!(error.title(), "Missing content type");
+    }
+}
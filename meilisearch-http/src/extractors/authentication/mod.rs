@@ -5,11 +5,17 @@ use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::ops::Deref;
 
-use actix_web::FromRequest;
+use actix_web::http::header;
+use actix_web::{FromRequest, HttpRequest};
 use futures::future::err;
 use futures::future::{ok, Ready};
+use log::error;
+use meilisearch_lib::index_controller::api_keys::Action;
+use meilisearch_lib::MeiliSearch;
 
+use super::tenant_token;
 use crate::error::ResponseError;
+use crate::ApiKeys;
 use error::AuthenticationError;
 
 macro_rules! create_policies {
@@ -44,7 +50,7 @@ macro_rules! create_policies {
     };
 }
 
-create_policies!(Public, Private, Admin);
+create_policies!(Public, Search, Private, Admin);
 
 /// Instanciate a `Policies`, filled with the given policies.
 macro_rules! init_policies {
@@ -157,9 +163,12 @@ impl<P: Policy + 'static, D: 'static + Clone> FromRequest for GuardedData<P, D>
                     None => err(AuthenticationError::IrretrievableState.into()),
                 },
                 AuthConfig::Auth(policies) => match policies.get::<P>() {
-                    Some(policy) => match req.headers().get("x-meili-api-key") {
+                    Some(policy) => match api_key(req) {
                         Some(token) => {
-                            if policy.authenticate(token.as_bytes()) {
+                            if policy.authenticate(token)
+                                || authorize_api_key::<P>(req, token)
+                                || authorize_tenant_token::<P>(req, token)
+                            {
                                 match req.app_data::<D>().cloned() {
                                     Some(data) => ok(Self {
                                         data,
@@ -180,3 +189,134 @@ impl<P: Policy + 'static, D: 'static + Clone> FromRequest for GuardedData<P, D>
         }
     }
 }
+
+/// The key the request is authenticated with, from its `Authorization: Bearer` header or else from
+/// its `X-Meili-API-Key` one.
+pub(crate) fn api_key(req: &HttpRequest) -> Option<&[u8]> {
+    let bearer = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.as_bytes().strip_prefix(b"Bearer "));
+    bearer.or_else(|| {
+        req.headers()
+            .get("x-meili-api-key")
+            .map(|key| key.as_bytes())
+    })
+}
+
+/// The key of the API key store a search whose indexes are in its body, such as a multi-search,
+/// is authenticated with. The key is checked against each of these indexes by
+/// [`SearchRestrictions::for_index`](super::key_restrictions::SearchRestrictions::for_index).
+pub struct SearchApiKey(pub String);
+
+/// Whether `token` is a key of the API key store allowed to perform the action of the request on
+/// its index. The routes of the `Admin` policy are reserved to the master key.
+fn authorize_api_key<P: 'static>(req: &HttpRequest, token: &[u8]) -> bool {
+    if TypeId::of::<P>() == TypeId::of::<policies::Admin>() {
+        return false;
+    }
+    let (meilisearch, key, action) = match (
+        req.app_data::<MeiliSearch>(),
+        std::str::from_utf8(token),
+        request_action(req),
+    ) {
+        (Some(meilisearch), Ok(key), Some(action)) => (meilisearch, key, action),
+        _ => return false,
+    };
+    let authorized =
+        match req.match_info().get("index_uid") {
+            None if action == Action::Search => meilisearch
+                .authorize_api_key_action(key, action)
+                .map(|authorized| {
+                    if authorized {
+                        req.extensions_mut().insert(SearchApiKey(key.to_string()));
+                    }
+                    authorized
+                }),
+            index_uid => meilisearch.authorize_api_key(key, action, index_uid),
+        };
+    authorized.unwrap_or_else(|e| {
+        error!("The API key couldn't be checked: {}", e);
+        false
+    })
+}
+
+/// Whether `token` is a tenant token signed by a key allowed to search the index of the request.
+/// Its claims are kept in the extensions of the request, for the search routes to apply its rules.
+fn authorize_tenant_token<P: 'static>(req: &HttpRequest, token: &[u8]) -> bool {
+    if TypeId::of::<P>() != TypeId::of::<policies::Search>() {
+        return false;
+    }
+    let (token, claims) = match std::str::from_utf8(token)
+        .ok()
+        .and_then(|token| Some((token, tenant_token::unverified_claims(token)?)))
+        .filter(|(_, claims)| claims.api_key_prefix.len() == tenant_token::API_KEY_PREFIX_LENGTH)
+    {
+        Some(token) => token,
+        None => return false,
+    };
+
+    // The master key can't sign tenant tokens, it isn't meant to be shared with the backends.
+    let mut signing_keys: Vec<String> = match req.app_data::<ApiKeys>() {
+        Some(keys) => keys.private.iter().chain(&keys.public).cloned().collect(),
+        None => Vec::new(),
+    };
+    if let Some(meilisearch) = req.app_data::<MeiliSearch>() {
+        let index_uid = req.match_info().get("index_uid");
+        match meilisearch.api_keys_with_prefix(&claims.api_key_prefix) {
+            Ok(keys) => signing_keys.extend(keys.into_iter().filter(|key| {
+                meilisearch
+                    .authorize_api_key(key, Action::Search, index_uid)
+                    .unwrap_or(false)
+            })),
+            Err(e) => error!("The API keys couldn't be looked up: {}", e),
+        }
+    }
+
+    match signing_keys
+        .iter()
+        .find_map(|key| tenant_token::verify(token, key))
+    {
+        Some(claims) => {
+            req.extensions_mut().insert(claims);
+            true
+        }
+        None => false,
+    }
+}
+
+/// The action performed by the request, `None` for the routes that can't be accessed with the
+/// keys of the API key store.
+fn request_action(req: &HttpRequest) -> Option<Action> {
+    let pattern = req.match_pattern()?;
+    let segments: Vec<_> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    let action = match (segments.as_slice(), req.method().as_str()) {
+        (["indexes"], "POST") => Action::IndexesCreate,
+        (["indexes"], "GET") | (["indexes", _], "GET") => Action::IndexesGet,
+        (["indexes", _], "PUT") => Action::IndexesUpdate,
+        (["indexes", _], "DELETE") => Action::IndexesDelete,
+        (["indexes", _, "search", ..], _)
+        | (["indexes", _, "facet-search"], _)
+        | (["indexes", _, "aggregate"], _)
+        | (["multi-search"], _) => Action::Search,
+        (["indexes", _, "documents", ..], "GET") => Action::DocumentsGet,
+        (["indexes", _, "documents", "delete-batch"], _)
+        | (["indexes", _, "documents", "delete"], _)
+        | (["indexes", _, "documents", ..], "DELETE") => Action::DocumentsDelete,
+        (["indexes", _, "documents"], _) | (["documents", "route"], _) => Action::DocumentsAdd,
+        (["indexes", _, "settings", ..], "GET") => Action::SettingsGet,
+        (["indexes", _, "settings", ..], _) => Action::SettingsUpdate,
+        (["indexes", _, "updates"], "GET")
+        | (["indexes", _, "updates", "wait"], _)
+        | (["indexes", _, "updates", _], "GET") => Action::TasksGet,
+        (["tasks", ..], "GET") => Action::TasksGet,
+        (["tasks", "cancel"], _) => Action::TasksCancel,
+        (["tasks"], "DELETE") => Action::TasksDelete,
+        (["indexes", _, "stats"], _) | (["stats"], _) => Action::StatsGet,
+        (["dumps"], "POST") => Action::DumpsCreate,
+        (["dumps", ..], "GET") => Action::DumpsGet,
+        (["version"], _) => Action::Version,
+        _ => return None,
+    };
+    Some(action)
+}
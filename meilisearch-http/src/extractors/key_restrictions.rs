@@ -0,0 +1,300 @@
+//! The search keys declared in the file given by `--restricted-keys-path`, each of them only
+//! allowed to search the indexes it lists, with the restrictions of these indexes applying to
+//! every search made with it.
+//!
+//! ```json
+//! {
+//!   "d0552b41536279a0ad88bd595327b96f": {
+//!     "indexes": {
+//!       "products": { "allowedFields": ["name", "price"], "filter": "tenant = acme" }
+//!     }
+//!   }
+//! }
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use actix_web::{dev, FromRequest, HttpRequest};
+use futures::future::{ready, Ready};
+use log::error;
+use meilisearch_lib::index::{FacetSearchQuery, SearchQuery};
+use meilisearch_lib::index_controller::api_keys::Action;
+use meilisearch_lib::MeiliSearch;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::authentication::{api_key, SearchApiKey};
+use super::tenant_token::TenantClaims;
+use crate::error::{MeilisearchHttpError, ResponseError};
+
+/// The index whose restrictions apply to the indexes that are not listed.
+pub(crate) const ANY_INDEX: &str = "*";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct IndexRestrictions {
+    /// The only fields the hits can contain, whatever the `attributesToRetrieve` of the search.
+    pub allowed_fields: Option<BTreeSet<String>>,
+    /// The filter ANDed to the filter of every search, in the same format. Its attributes must be
+    /// filterable.
+    pub filter: Option<Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct KeyRestrictions {
+    /// The restrictions by index uid, the key can't search the indexes that are not listed.
+    pub indexes: BTreeMap<String, IndexRestrictions>,
+}
+
+impl KeyRestrictions {
+    fn index(&self, index_uid: &str) -> Option<&IndexRestrictions> {
+        self.indexes
+            .get(index_uid)
+            .or_else(|| self.indexes.get(ANY_INDEX))
+    }
+}
+
+/// The restricted keys, read once at launch.
+#[derive(Clone, Default)]
+pub struct RestrictedKeys {
+    keys: Arc<HashMap<String, KeyRestrictions>>,
+}
+
+impl RestrictedKeys {
+    pub fn new(keys: HashMap<String, KeyRestrictions>) -> Self {
+        Self {
+            keys: Arc::new(keys),
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.keys.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+/// Reads the restricted keys from the file at this path.
+impl FromStr for RestrictedKeys {
+    type Err = anyhow::Error;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let file = std::fs::File::open(path)?;
+        let keys = serde_json::from_reader(std::io::BufReader::new(file))?;
+        Ok(Self::new(keys))
+    }
+}
+
+/// The keys are secrets, only their number is shown.
+impl fmt::Debug for RestrictedKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RestrictedKeys({} keys)", self.keys.len())
+    }
+}
+
+/// The restrictions applying to the search of the index of the path with the key of the
+/// request, none for the keys that are not restricted.
+pub struct SearchRestrictions {
+    restrictions: Option<IndexRestrictions>,
+    api_key: Option<String>,
+}
+
+impl SearchRestrictions {
+    /// Whether the key of the request is restricted.
+    pub fn is_restricted(&self) -> bool {
+        self.restrictions.is_some()
+    }
+
+    /// The key of the request, that owns the scrolls it starts.
+    pub fn api_key(&self) -> Option<String> {
+        self.api_key.clone()
+    }
+
+    /// Restricts the query to what the key is allowed to see.
+    pub fn apply(self, query: &mut SearchQuery) {
+        query.api_key = self.api_key;
+        if let Some(restrictions) = self.restrictions {
+            query.allowed_fields = restrictions.allowed_fields;
+            if let Some(forced) = restrictions.filter {
+                query.filter = Some(and_filters(forced, query.filter.take()));
+            }
+        }
+    }
+
+    /// Restricts the facet search to the values of the documents the key is allowed to see.
+    pub fn apply_to_facet_search(self, query: &mut FacetSearchQuery) {
+        if let Some(restrictions) = self.restrictions {
+            query.allowed_fields = restrictions.allowed_fields;
+            if let Some(forced) = restrictions.filter {
+                query.filter = Some(and_filters(forced, query.filter.take()));
+            }
+        }
+    }
+}
+
+/// Combines a forced filter with the filter of a search, the elements of the filter arrays being
+/// ANDed together.
+fn and_filters(forced: Value, filter: Option<Value>) -> Value {
+    let elements = |filter| match filter {
+        Value::Array(elements) => elements,
+        filter => vec![filter],
+    };
+
+    match filter {
+        Some(filter) => {
+            let mut combined = elements(forced);
+            combined.extend(elements(filter));
+            Value::Array(combined)
+        }
+        None => forced,
+    }
+}
+
+impl FromRequest for SearchRestrictions {
+    type Config = ();
+
+    type Error = ResponseError;
+
+    type Future = Ready<Result<SearchRestrictions, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut dev::Payload) -> Self::Future {
+        let index_uid = req.match_info().get("index_uid").unwrap_or_default();
+        ready(SearchRestrictions::for_index(req, index_uid).map_err(ResponseError::from))
+    }
+}
+
+impl SearchRestrictions {
+    /// The restrictions applying to the search of `index_uid` with the key of the request, for
+    /// the routes searching indexes that are not in their path.
+    pub fn for_index(
+        req: &HttpRequest,
+        index_uid: &str,
+    ) -> Result<SearchRestrictions, MeilisearchHttpError> {
+        let api_key = api_key(req).map(|key| String::from_utf8_lossy(key).into_owned());
+        Ok(SearchRestrictions {
+            restrictions: Self::index_restrictions(req, index_uid)?,
+            api_key,
+        })
+    }
+
+    fn index_restrictions(
+        req: &HttpRequest,
+        index_uid: &str,
+    ) -> Result<Option<IndexRestrictions>, MeilisearchHttpError> {
+        // The claims of the tenant token the request is authenticated with, if any.
+        if let Some(claims) = req.extensions().get::<TenantClaims>() {
+            return match claims.search_rules.index(index_uid) {
+                Some(restrictions) => Ok(Some(restrictions)),
+                None => Err(MeilisearchHttpError::IndexNotAllowed(index_uid.to_string())),
+            };
+        }
+
+        let search_key = req
+            .extensions()
+            .get::<SearchApiKey>()
+            .map(|SearchApiKey(key)| key.clone());
+        if let (Some(key), Some(meilisearch)) = (search_key, req.app_data::<MeiliSearch>()) {
+            let allowed = meilisearch
+                .authorize_api_key(&key, Action::Search, Some(index_uid))
+                .unwrap_or_else(|e| {
+                    error!("The API key couldn't be checked: {}", e);
+                    false
+                });
+            if !allowed {
+                return Err(MeilisearchHttpError::IndexNotAllowed(index_uid.to_string()));
+            }
+        }
+
+        let restricted_keys = match req.app_data::<RestrictedKeys>() {
+            Some(restricted_keys) => restricted_keys,
+            None => return Ok(None),
+        };
+        // The key is read as the authentication reads it, from either of its headers.
+        let restrictions = api_key(req)
+            .and_then(|key| std::str::from_utf8(key).ok())
+            .and_then(|key| restricted_keys.keys.get(key));
+        let restrictions = match restrictions {
+            Some(restrictions) => restrictions,
+            None => return Ok(None),
+        };
+
+        match restrictions.index(index_uid) {
+            Some(index_restrictions) => Ok(Some(index_restrictions.clone())),
+            None => Err(MeilisearchHttpError::IndexNotAllowed(index_uid.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn index_restrictions() {
+        let restrictions: KeyRestrictions = serde_json::from_value(json!({
+            "indexes": {
+                "products": { "allowedFields": ["name", "price"] },
+                "*": {},
+            }
+        }))
+        .unwrap();
+
+        let products = restrictions.index("products").unwrap();
+        assert_eq!(
+            products.allowed_fields.as_ref().unwrap(),
+            &["name", "price"]
+                .iter()
+                .map(|field| field.to_string())
+                .collect()
+        );
+        assert!(restrictions
+            .index("movies")
+            .unwrap()
+            .allowed_fields
+            .is_none());
+
+        let restrictions = KeyRestrictions {
+            indexes: BTreeMap::new(),
+        };
+        assert!(restrictions.index("products").is_none());
+    }
+
+    #[test]
+    fn forced_filters() {
+        assert_eq!(
+            and_filters(json!("tenant = acme"), None),
+            json!("tenant = acme")
+        );
+        assert_eq!(
+            and_filters(
+                json!("tenant = acme"),
+                Some(json!("price < 10 OR sale = true"))
+            ),
+            json!(["tenant = acme", "price < 10 OR sale = true"])
+        );
+        assert_eq!(
+            and_filters(
+                json!(["tenant = acme", ["region = eu", "region = us"]]),
+                Some(json!(["price < 10", ["color = red", "color = blue"]]))
+            ),
+            json!([
+                "tenant = acme",
+                ["region = eu", "region = us"],
+                "price < 10",
+                ["color = red", "color = blue"]
+            ])
+        );
+    }
+}
@@ -1,3 +1,6 @@
+pub mod key_restrictions;
 pub mod payload;
+pub mod tenant_token;
+pub mod update_metadata;
 #[macro_use]
 pub mod authentication;
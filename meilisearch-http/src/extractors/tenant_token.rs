@@ -0,0 +1,137 @@
+//! The tenant tokens, JWTs generated by the backends of multi-tenant applications from one of
+//! their search keys, without any request to the instance. Their `searchRules` restrict the
+//! searches made with them to the documents of their tenant, the filter of the rules of an index
+//! being ANDed to the filter of every search of this index.
+//!
+//! ```json
+//! {
+//!   "searchRules": { "products": { "filter": "tenant = acme" } },
+//!   "apiKeyPrefix": "d0552b41",
+//!   "exp": 1641835850
+//! }
+//! ```
+//!
+//! The tokens are signed with HS256, HS384 or HS512 by the key starting with `apiKeyPrefix`, the
+//! public or private key or a key of the API key store allowed to search the index. The rules
+//! can also be the array of the indexes that can be searched without restriction.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::Utc;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+
+use super::key_restrictions::{IndexRestrictions, ANY_INDEX};
+
+/// The length of the `apiKeyPrefix` identifying the key that signed a token.
+pub const API_KEY_PREFIX_LENGTH: usize = 8;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TenantClaims {
+    pub search_rules: SearchRules,
+    pub api_key_prefix: String,
+    /// The expiration date of the token, as a UNIX timestamp.
+    pub exp: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum SearchRules {
+    /// The indexes that can be searched without restriction.
+    Indexes(BTreeSet<String>),
+    /// The restrictions by index uid, `null` for an index searched without restriction.
+    Restrictions(BTreeMap<String, Option<IndexRestrictions>>),
+}
+
+impl SearchRules {
+    /// The restrictions of the searches of `index_uid`, `None` if the index can't be searched.
+    pub fn index(&self, index_uid: &str) -> Option<IndexRestrictions> {
+        match self {
+            SearchRules::Indexes(indexes) => (indexes.contains(index_uid)
+                || indexes.contains(ANY_INDEX))
+            .then(IndexRestrictions::default),
+            SearchRules::Restrictions(restrictions) => restrictions
+                .get(index_uid)
+                .or_else(|| restrictions.get(ANY_INDEX))
+                .map(|restrictions| restrictions.clone().unwrap_or_default()),
+        }
+    }
+}
+
+/// Reads the claims of `token` without checking its signature, to find the key that signed it.
+pub fn unverified_claims(token: &str) -> Option<TenantClaims> {
+    jsonwebtoken::dangerous_insecure_decode(token)
+        .ok()
+        .map(|data| data.claims)
+}
+
+/// Returns the claims of `token` if it is signed by `key` and hasn't expired.
+pub fn verify(token: &str, key: &str) -> Option<TenantClaims> {
+    let validation = Validation {
+        // The tokens without expiration date are valid, the dates are checked below.
+        validate_exp: false,
+        algorithms: vec![Algorithm::HS256, Algorithm::HS384, Algorithm::HS512],
+        ..Validation::default()
+    };
+    let claims: TenantClaims = jsonwebtoken::decode(
+        token,
+        &DecodingKey::from_secret(key.as_bytes()),
+        &validation,
+    )
+    .ok()?
+    .claims;
+
+    let signed_by_key = key.get(..API_KEY_PREFIX_LENGTH) == Some(claims.api_key_prefix.as_str());
+    let expired = claims
+        .exp
+        .map_or(false, |exp| exp <= Utc::now().timestamp());
+    (signed_by_key && !expired).then(|| claims)
+}
+
+#[cfg(test)]
+mod test {
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    const KEY: &str = "d0552b41536279a0ad88bd595327b96f";
+
+    fn token(claims: Value, key: &str) -> String {
+        jsonwebtoken::encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(key.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn verify_tokens() {
+        let claims = json!({
+            "searchRules": { "products": { "filter": "tenant = acme" } },
+            "apiKeyPrefix": &KEY[..API_KEY_PREFIX_LENGTH],
+        });
+        let claims = verify(&token(claims, KEY), KEY).unwrap();
+        let products = claims.search_rules.index("products").unwrap();
+        assert_eq!(products.filter, Some(json!("tenant = acme")));
+        assert!(claims.search_rules.index("movies").is_none());
+
+        let claims = json!({ "searchRules": ["*"], "apiKeyPrefix": &KEY[..API_KEY_PREFIX_LENGTH] });
+        assert!(verify(&token(claims.clone(), "another key"), KEY).is_none());
+        let claims = verify(&token(claims, KEY), KEY).unwrap();
+        assert!(claims.search_rules.index("movies").is_some());
+
+        let expired = json!({
+            "searchRules": ["*"],
+            "apiKeyPrefix": &KEY[..API_KEY_PREFIX_LENGTH],
+            "exp": Utc::now().timestamp() - 60,
+        });
+        assert!(unverified_claims(&token(expired.clone(), KEY)).is_some());
+        assert!(verify(&token(expired, KEY), KEY).is_none());
+
+        let wrong_prefix = json!({ "searchRules": ["*"], "apiKeyPrefix": "aaaaaaaa" });
+        assert!(verify(&token(wrong_prefix, KEY), KEY).is_none());
+    }
+}
@@ -0,0 +1,86 @@
+use actix_web::{dev, FromRequest, HttpRequest};
+use futures::future::{ready, Ready};
+use meilisearch_lib::index_controller::updates::status::{TraceContext, UpdateContext};
+use serde_json::Value;
+
+use crate::error::{MeilisearchHttpError, ResponseError};
+
+/// The header holding the metadata of an update, a JSON object such as
+/// `{ "jobId": "nightly-import" }`, returned along the update in the update listings.
+pub const UPDATE_METADATA_HEADER: &str = "X-Meili-Update-Metadata";
+/// The maximum size of the metadata, in bytes.
+const MAX_UPDATE_METADATA_SIZE: usize = 1024;
+/// The headers of the trace of the request, stored with the update for it to be followed up to
+/// the end of its processing.
+const TRACEPARENT_HEADER: &str = "traceparent";
+const REQUEST_ID_HEADER: &str = "X-Request-Id";
+/// The maximum size of the trace headers kept, in bytes, the longer ones being ignored.
+const MAX_TRACE_HEADER_SIZE: usize = 256;
+
+/// The metadata attached by the client to the update it enqueues, if any, and the trace of its
+/// request.
+pub struct UpdateMetadata(pub UpdateContext);
+
+impl UpdateMetadata {
+    pub fn into_inner(self) -> UpdateContext {
+        self.0
+    }
+}
+
+impl FromRequest for UpdateMetadata {
+    type Config = ();
+
+    type Error = ResponseError;
+
+    type Future = Ready<Result<UpdateMetadata, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut dev::Payload) -> Self::Future {
+        let context = parse_update_metadata(req).map(|metadata| UpdateContext {
+            metadata,
+            trace: parse_trace(req),
+        });
+        ready(context.map(UpdateMetadata).map_err(ResponseError::from))
+    }
+}
+
+fn parse_trace(req: &HttpRequest) -> Option<TraceContext> {
+    let header = |name| {
+        req.headers()
+            .get(name)
+            .filter(|value| value.len() <= MAX_TRACE_HEADER_SIZE)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from)
+    };
+    match (header(TRACEPARENT_HEADER), header(REQUEST_ID_HEADER)) {
+        (None, None) => None,
+        (traceparent, request_id) => Some(TraceContext {
+            traceparent,
+            request_id,
+        }),
+    }
+}
+
+fn parse_update_metadata(req: &HttpRequest) -> Result<Option<Value>, MeilisearchHttpError> {
+    let header = match req.headers().get(UPDATE_METADATA_HEADER) {
+        Some(header) => header,
+        None => return Ok(None),
+    };
+
+    if header.len() > MAX_UPDATE_METADATA_SIZE {
+        return Err(MeilisearchHttpError::InvalidUpdateMetadata(format!(
+            "it must not exceed {} bytes.",
+            MAX_UPDATE_METADATA_SIZE
+        )));
+    }
+
+    let metadata = header
+        .to_str()
+        .ok()
+        .and_then(|header| serde_json::from_str(header).ok());
+    match metadata {
+        Some(metadata @ Value::Object(_)) => Ok(Some(metadata)),
+        _ => Err(MeilisearchHttpError::InvalidUpdateMetadata(String::from(
+            "it must be a JSON object.",
+        ))),
+    }
+}
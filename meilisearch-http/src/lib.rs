@@ -3,15 +3,22 @@
 pub mod error;
 #[macro_use]
 pub mod extractors;
-#[cfg(all(not(debug_assertions), feature = "analytics"))]
 pub mod analytics;
+pub mod bench;
+pub mod deprecation;
 pub mod helpers;
+pub mod logging;
 pub mod option;
 pub mod routes;
+pub mod shadow;
+pub mod webhook;
+use std::num::NonZeroUsize;
 use std::path::Path;
 use std::time::Duration;
 
+use crate::analytics::Analytics;
 use crate::extractors::authentication::AuthConfig;
+use crate::shadow::ShadowTraffic;
 pub use option::Opt;
 
 use actix_web::web;
@@ -53,8 +60,10 @@ pub fn setup_meilisearch(opt: &Opt) -> anyhow::Result<MeiliSearch> {
         .set_ignore_missing_snapshot(opt.ignore_missing_snapshot)
         .set_ignore_snapshot_if_db_exists(opt.ignore_snapshot_if_db_exists)
         .set_dump_dst(opt.dumps_dir.clone())
+        .set_ignore_dump_import_errors(opt.ignore_dump_import_errors)
         .set_snapshot_interval(Duration::from_secs(opt.snapshot_interval_sec))
-        .set_snapshot_dir(opt.snapshot_dir.clone());
+        .set_snapshot_dir(opt.snapshot_dir.clone())
+        .set_warmup(opt.warmup);
 
     if let Some(ref path) = opt.import_snapshot {
         meilisearch.set_import_snapshot(path.clone());
@@ -64,11 +73,63 @@ pub fn setup_meilisearch(opt: &Opt) -> anyhow::Result<MeiliSearch> {
         meilisearch.set_dump_src(path.clone());
     }
 
+    if let Some(ref path) = opt.update_archive_dir {
+        meilisearch.set_update_archive_dir(path.clone());
+    }
+
+    if let Some(until) = opt.replay_archive_until {
+        meilisearch.set_replay_archive_until(until);
+    }
+
+    if let Some(max_fields) = opt.max_fields_per_document {
+        meilisearch.set_max_fields_per_document(max_fields);
+    }
+
+    if let Some(max_size) = opt.max_document_size {
+        meilisearch.set_max_document_size(max_size.get_bytes() as usize);
+    }
+
+    if let Some(mbps) = opt.snapshot_io_throttle_mbps {
+        meilisearch.set_snapshot_io_throttle(mbps.get().saturating_mul(1_000_000));
+    }
+
+    if let Some(interval) = opt.bloat_check_interval_sec {
+        meilisearch.set_bloat_check_interval(Duration::from_secs(interval));
+    }
+
+    if let Some(days) = opt.task_retention_days {
+        meilisearch
+            .set_task_retention(Duration::from_secs(days.get().saturating_mul(24 * 60 * 60)));
+    }
+
+    if let Some(max_concurrent_searches) = opt.max_concurrent_searches {
+        meilisearch
+            .set_max_concurrent_searches(max_concurrent_searches)
+            .set_search_queue_timeout(Duration::from_millis(opt.search_queue_timeout_ms));
+    }
+
     if opt.schedule_snapshot {
         meilisearch.set_schedule_snapshot();
     }
 
-    meilisearch.build(opt.db_path.clone(), opt.indexer_options.clone())
+    let mut indexer_options = opt.indexer_options.clone();
+    indexer_options.max_facet_cardinality = opt.max_facet_cardinality;
+    indexer_options.max_documents_per_index = opt.max_documents_per_index;
+    indexer_options.max_documents = opt.max_documents;
+    indexer_options.deterministic_indexing = opt.deterministic_indexing;
+    indexer_options.max_search_depth = Some(opt.max_search_depth);
+    indexer_options.max_search_limit = opt.max_search_limit;
+    indexer_options.max_search_facets = opt.max_search_facets;
+    indexer_options.max_attributes_to_retrieve = opt.max_attributes_to_retrieve;
+    indexer_options.max_batch_size = Some(opt.max_batch_size.get());
+    indexer_options.dump_import_jobs = opt.dump_import_jobs.map(NonZeroUsize::get);
+    indexer_options.dump_import_prefix = opt.import_dump_prefix.clone();
+
+    if opt.db_in_memory {
+        meilisearch.build_in_temp_dir(indexer_options)
+    } else {
+        meilisearch.build(opt.db_path.clone(), indexer_options)
+    }
 }
 
 /// Cleans and setup the temporary file folder in the database directory. This must be done after
@@ -92,7 +153,12 @@ pub fn setup_temp_dir(db_path: impl AsRef<Path>) -> anyhow::Result<()> {
     Ok(())
 }
 
-pub fn configure_data(config: &mut web::ServiceConfig, data: MeiliSearch, opt: &Opt) {
+pub fn configure_data(
+    config: &mut web::ServiceConfig,
+    data: MeiliSearch,
+    opt: &Opt,
+    analytics: Analytics,
+) {
     let http_payload_size_limit = opt.http_payload_size_limit.get_bytes() as usize;
     config
         .app_data(data)
@@ -103,6 +169,9 @@ pub fn configure_data(config: &mut web::ServiceConfig, data: MeiliSearch, opt: &
                 .error_handler(|err, _req| error::payload_error_handler(err).into()),
         )
         .app_data(PayloadConfig::new(http_payload_size_limit))
+        .app_data(routes::InstanceInfo::new(opt))
+        .app_data(web::Data::new(analytics))
+        .app_data(web::Data::new(ShadowTraffic::new(opt)))
         .app_data(
             web::QueryConfig::default()
                 .error_handler(|err, _req| error::payload_error_handler(err).into()),
@@ -117,23 +186,31 @@ pub fn configure_auth(config: &mut web::ServiceConfig, opts: &Opt) {
     };
 
     keys.generate_missing_api_keys();
+    let restricted_keys = opts.restricted_keys.clone().unwrap_or_default();
 
     let auth_config = if let Some(ref master_key) = keys.master {
         let private_key = keys.private.as_ref().unwrap();
         let public_key = keys.public.as_ref().unwrap();
-        let mut policies = init_policies!(Public, Private, Admin);
+        let mut policies = init_policies!(Public, Search, Private, Admin);
         create_users!(
             policies,
-            master_key.as_bytes() => { Admin, Private, Public },
-            private_key.as_bytes() => { Private, Public },
-            public_key.as_bytes() => { Public }
+            master_key.as_bytes() => { Admin, Private, Search, Public },
+            private_key.as_bytes() => { Private, Search, Public },
+            public_key.as_bytes() => { Search, Public }
         );
+        // The restricted keys can only search, their restrictions are applied by the search routes.
+        for key in restricted_keys.keys() {
+            create_users!(policies, key.as_bytes() => { Search });
+        }
         AuthConfig::Auth(policies)
     } else {
         AuthConfig::NoAuth
     };
 
-    config.app_data(auth_config).app_data(keys);
+    config
+        .app_data(auth_config)
+        .app_data(keys)
+        .app_data(restricted_keys);
 }
 
 #[cfg(feature = "mini-dashboard")]
@@ -177,28 +254,48 @@ pub fn dashboard(config: &mut web::ServiceConfig, _enable_frontend: bool) {
 
 #[macro_export]
 macro_rules! create_app {
-    ($data:expr, $enable_frontend:expr, $opt:expr) => {{
+    ($data:expr, $enable_frontend:expr, $opt:expr) => {
+        $crate::create_app!(
+            $data,
+            $enable_frontend,
+            $opt,
+            $crate::analytics::Analytics::disabled()
+        )
+    };
+    ($data:expr, $enable_frontend:expr, $opt:expr, $analytics:expr) => {{
         use actix_cors::Cors;
+        use actix_web::dev::Service;
         use actix_web::middleware::TrailingSlash;
         use actix_web::App;
         use actix_web::{middleware, web};
+        use meilisearch_http::logging::AccessLog;
         use meilisearch_http::routes;
         use meilisearch_http::{configure_auth, configure_data, dashboard};
 
+        let log_format = $opt.log_format;
         App::new()
-            .configure(|s| configure_data(s, $data.clone(), &$opt))
+            .configure(|s| configure_data(s, $data.clone(), &$opt, $analytics.clone()))
             .configure(|s| configure_auth(s, &$opt))
-            .configure(routes::configure)
+            .configure(|s| routes::configure(s, &$opt))
             .configure(|s| dashboard(s, $enable_frontend))
+            .wrap_fn(|req, srv| {
+                let response = srv.call(req);
+                async move { response.await.map($crate::error::negotiate_error_format) }
+            })
             .wrap(
                 Cors::default()
                     .send_wildcard()
-                    .allowed_headers(vec!["content-type", "x-meili-api-key"])
+                    .allowed_headers(vec!["content-type", "x-meili-api-key", "authorization"])
                     .allow_any_origin()
                     .allow_any_method()
                     .max_age(86_400), // 24h
             )
             .wrap(middleware::Logger::default())
+            .wrap_fn(move |req, srv| {
+                let access = AccessLog::start(&req);
+                let response = srv.call(req);
+                async move { access.finish(response.await, log_format) }
+            })
             .wrap(middleware::Compress::default())
             .wrap(middleware::NormalizePath::new(
                 middleware::TrailingSlash::Trim,
@@ -0,0 +1,198 @@
+//! The structured logs of `--log-format json`, a JSON object by line, for the log pipelines to
+//! index their fields instead of parsing the human readable lines. In this format, the access
+//! logs of the HTTP requests replace the ones of the actix logger, with the request id, route,
+//! status and duration of each request as fields.
+//!
+//! Whatever the format, the responses hold the `X-Request-Id` of their request, generated when
+//! the request has none.
+
+use std::io::Write;
+use std::str::FromStr;
+use std::time::Instant;
+
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::http::StatusCode;
+use chrono::{SecondsFormat, Utc};
+use log::{info, Record};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+/// The target of the access logs, whose message is the JSON object of their fields.
+const ACCESS_LOG_TARGET: &str = "meilisearch_http::access";
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Human,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(LogFormat::Human),
+            "json" => Ok(LogFormat::Json),
+            _ => anyhow::bail!("Unknown log format `{}`, expected `human` or `json`.", s),
+        }
+    }
+}
+
+/// Writes `record` as a JSON line, the fields of the access logs being merged in the line.
+pub fn format_json(buf: &mut env_logger::fmt::Formatter, record: &Record) -> std::io::Result<()> {
+    writeln!(buf, "{}", json_line(record))
+}
+
+fn json_line(record: &Record) -> Value {
+    let mut line = Map::new();
+    line.insert(
+        String::from("timestamp"),
+        json!(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+    );
+    line.insert(String::from("level"), json!(record.level().as_str()));
+    line.insert(
+        String::from("module"),
+        json!(record.module_path().unwrap_or_else(|| record.target())),
+    );
+
+    let message = record.args().to_string();
+    match serde_json::from_str(&message) {
+        Ok(Value::Object(fields)) if record.target() == ACCESS_LOG_TARGET => line.extend(fields),
+        _ => {
+            line.insert(String::from("message"), Value::String(message));
+        }
+    }
+
+    Value::Object(line)
+}
+
+/// A request being answered, logged with its response.
+pub struct AccessLog {
+    start: Instant,
+    method: String,
+    path: String,
+    route: Option<String>,
+    request_id: String,
+}
+
+impl AccessLog {
+    pub fn start(req: &ServiceRequest) -> Self {
+        Self {
+            start: Instant::now(),
+            method: req.method().to_string(),
+            path: req.path().to_string(),
+            route: req.match_pattern(),
+            request_id: req
+                .headers()
+                .get(REQUEST_ID_HEADER)
+                .and_then(|id| id.to_str().ok())
+                .map_or_else(|| Uuid::new_v4().to_string(), String::from),
+        }
+    }
+
+    /// Adds the request id to the response, and logs the request in the `json` format.
+    pub fn finish<B>(
+        self,
+        mut response: Result<ServiceResponse<B>, actix_web::Error>,
+        format: LogFormat,
+    ) -> Result<ServiceResponse<B>, actix_web::Error> {
+        let status = match response {
+            Ok(ref mut response) => {
+                if let Ok(id) = HeaderValue::from_str(&self.request_id) {
+                    response
+                        .headers_mut()
+                        .insert(HeaderName::from_static(REQUEST_ID_HEADER), id);
+                }
+                response.status()
+            }
+            Err(ref e) => e.as_response_error().status_code(),
+        };
+        if format == LogFormat::Json {
+            info!(target: ACCESS_LOG_TARGET, "{}", self.fields(status));
+        }
+        response
+    }
+
+    fn fields(&self, status: StatusCode) -> Value {
+        json!({
+            "message": format!("{} {} {}", self.method, self.path, status.as_u16()),
+            "requestId": self.request_id,
+            "method": self.method,
+            "route": self.route,
+            "path": self.path,
+            "status": status.as_u16(),
+            "durationMs": self.start.elapsed().as_secs_f64() * 1000.,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use actix_web::test::TestRequest;
+    use log::Level;
+
+    use super::*;
+
+    #[test]
+    fn parse_log_formats() {
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert_eq!("human".parse::<LogFormat>().unwrap(), LogFormat::Human);
+        assert!("yaml".parse::<LogFormat>().is_err());
+    }
+
+    #[test]
+    fn generate_missing_request_ids() {
+        let req = TestRequest::default()
+            .insert_header((REQUEST_ID_HEADER, "import-42"))
+            .to_srv_request();
+        assert_eq!(AccessLog::start(&req).request_id, "import-42");
+
+        let req = TestRequest::default().to_srv_request();
+        assert!(Uuid::parse_str(&AccessLog::start(&req).request_id).is_ok());
+    }
+
+    #[test]
+    fn access_log_fields() {
+        let access = AccessLog {
+            start: Instant::now(),
+            method: String::from("GET"),
+            path: String::from("/indexes/movies"),
+            route: Some(String::from("/indexes/{index_uid}")),
+            request_id: String::from("import-42"),
+        };
+        let fields = access.fields(StatusCode::NOT_FOUND);
+        let line = json_line(
+            &Record::builder()
+                .args(format_args!("{}", fields))
+                .level(Level::Info)
+                .target(ACCESS_LOG_TARGET)
+                .build(),
+        );
+
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["module"], ACCESS_LOG_TARGET);
+        assert_eq!(line["message"], "GET /indexes/movies 404");
+        assert_eq!(line["requestId"], "import-42");
+        assert_eq!(line["method"], "GET");
+        assert_eq!(line["route"], "/indexes/{index_uid}");
+        assert_eq!(line["path"], "/indexes/movies");
+        assert_eq!(line["status"], 404);
+        assert!(line["durationMs"].is_f64());
+        assert!(line["timestamp"].is_string());
+
+        // the other logs keep their message
+        let line = json_line(
+            &Record::builder()
+                .args(format_args!("{}", fields))
+                .level(Level::Warn)
+                .target("milli")
+                .build(),
+        );
+        assert_eq!(line["message"], fields.to_string());
+        assert!(line.get("requestId").is_none());
+    }
+}
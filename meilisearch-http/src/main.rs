@@ -1,12 +1,16 @@
+use std::cell::Cell;
 use std::env;
+use std::path::Path;
 
 use actix_web::HttpServer;
+use meilisearch_http::bench::{self, BenchOpt};
 use meilisearch_http::{create_app, setup_meilisearch, Opt};
 use meilisearch_lib::MeiliSearch;
 use structopt::StructOpt;
 
-#[cfg(all(not(debug_assertions), feature = "analytics"))]
-use meilisearch_http::analytics;
+use meilisearch_http::analytics::{self, Analytics};
+use meilisearch_http::logging::{self, LogFormat};
+use meilisearch_http::webhook;
 
 #[cfg(target_os = "linux")]
 #[global_allocator]
@@ -20,16 +24,38 @@ fn setup(opt: &Opt) -> anyhow::Result<()> {
         // if we are in info we only allow the warn log_level for milli
         log_builder.filter_module("milli", log::LevelFilter::Warn);
     }
+    if opt.log_format == LogFormat::Json {
+        // The access logs of the json format replace the ones of the actix logger.
+        log_builder.filter_module("actix_web::middleware::logger", log::LevelFilter::Off);
+        log_builder.format(logging::format_json);
+    }
 
     log_builder.init();
 
     Ok(())
 }
 
-#[actix_web::main]
-async fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<()> {
+    // `meilisearch bench` replays searches instead of starting the server.
+    if env::args().nth(1).as_deref() == Some("bench") {
+        let bench_opt = BenchOpt::from_iter(env::args_os().skip(1));
+        setup(&bench_opt.opt)?;
+        return bench_opt
+            .opt
+            .build_runtime()?
+            .block_on(bench::run(bench_opt));
+    }
+
     let opt = Opt::from_args();
 
+    // The runtime is built beforehand so that its errors are returned, actix calling the
+    // factory only once.
+    let runtime = Cell::new(Some(opt.build_runtime()?));
+    actix_web::rt::System::with_tokio_rt(|| runtime.take().expect("the runtime is only taken once"))
+        .block_on(run(opt))
+}
+
+async fn run(opt: Opt) -> anyhow::Result<()> {
     setup(&opt)?;
 
     match opt.env.as_ref() {
@@ -44,32 +70,50 @@ async fn main() -> anyhow::Result<()> {
         _ => unreachable!(),
     }
 
+    if opt.restricted_keys.is_some() && opt.master_key.is_none() {
+        anyhow::bail!("The restricted keys require a master key, set with MEILI_MASTER_KEY");
+    }
+
     let meilisearch = setup_meilisearch(&opt)?;
 
     // Setup the temp directory to be in the db folder. This is important, since temporary file
     // don't support to be persisted accross filesystem boundaries.
-    meilisearch_http::setup_temp_dir(&opt.db_path)?;
-
-    #[cfg(all(not(debug_assertions), feature = "analytics"))]
-    if !opt.no_analytics {
-        let analytics_data = meilisearch.clone();
-        let analytics_opt = opt.clone();
-        tokio::task::spawn(analytics::analytics_sender(analytics_data, analytics_opt));
+    match meilisearch.temp_dir() {
+        Some(db_path) => meilisearch_http::setup_temp_dir(db_path)?,
+        None => meilisearch_http::setup_temp_dir(&opt.db_path)?,
     }
 
+    let analytics = analytics::setup_analytics(meilisearch.clone(), &opt)?;
+    webhook::setup_task_webhook(meilisearch.clone(), &opt).await?;
+
     print_launch_resume(&opt);
 
-    run_http(meilisearch, opt).await?;
+    match meilisearch.temp_dir().map(Path::to_path_buf) {
+        // The temporary database is removed once the server is stopped with CTRL-C.
+        Some(temp_dir) => {
+            tokio::select! {
+                result = run_http(meilisearch, opt, analytics) => result?,
+                _ = tokio::signal::ctrl_c() => (),
+            }
+            std::fs::remove_dir_all(temp_dir)?;
+        }
+        None => run_http(meilisearch, opt, analytics).await?,
+    }
 
     Ok(())
 }
 
-async fn run_http(data: MeiliSearch, opt: Opt) -> anyhow::Result<()> {
+async fn run_http(data: MeiliSearch, opt: Opt, analytics: Analytics) -> anyhow::Result<()> {
     let _enable_dashboard = &opt.env == "development";
     let opt_clone = opt.clone();
-    let http_server = HttpServer::new(move || create_app!(data, _enable_dashboard, opt_clone))
-        // Disable signals allows the server to terminate immediately when a user enter CTRL-C
-        .disable_signals();
+    let http_server =
+        HttpServer::new(move || create_app!(data, _enable_dashboard, opt_clone, analytics))
+            // Disable signals allows the server to terminate immediately when a user enter CTRL-C
+            .disable_signals();
+    let http_server = match opt.http_workers {
+        Some(workers) => http_server.workers(workers.get()),
+        None => http_server,
+    };
 
     if let Some(config) = opt.get_ssl_config()? {
         http_server
@@ -99,7 +143,11 @@ pub fn print_launch_resume(opt: &Opt) {
 
     eprintln!("{}", ascii_name);
 
-    eprintln!("Database path:\t\t{:?}", opt.db_path);
+    if opt.db_in_memory {
+        eprintln!("Database path:\t\t\"Temporary\"");
+    } else {
+        eprintln!("Database path:\t\t{:?}", opt.db_path);
+    }
     eprintln!("Server listening on:\t\"http://{}\"", opt.http_addr);
     eprintln!("Environment:\t\t{:?}", opt.env);
     eprintln!("Commit SHA:\t\t{:?}", commit_sha.to_string());
@@ -108,6 +156,17 @@ pub fn print_launch_resume(opt: &Opt) {
         "Package version:\t{:?}",
         env!("CARGO_PKG_VERSION").to_string()
     );
+    eprintln!("Indexing memory:\t\"{}\"", opt.indexer_options.max_memory);
+    eprintln!("Indexing threads:\t{}", opt.indexing_threads());
+    if opt.deterministic_indexing {
+        eprintln!("Deterministic indexing:\t\"Enabled\"");
+    }
+    if let Some(workers) = opt.http_workers {
+        eprintln!("HTTP workers:\t\t{}", workers);
+    }
+    if let Some(threads) = opt.runtime_worker_threads {
+        eprintln!("Runtime threads:\t{}", threads);
+    }
 
     #[cfg(all(not(debug_assertions), feature = "analytics"))]
     {
@@ -125,6 +184,10 @@ Anonymous telemetry:   \"Enabled\""
         }
     }
 
+    if let Some(ref sink) = opt.analytics_sink {
+        eprintln!("Analytics sink:\t\t{:?}", sink);
+    }
+
     eprintln!();
 
     if opt.master_key.is_some() {
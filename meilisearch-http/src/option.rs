@@ -1,10 +1,12 @@
 use std::fs;
 use std::io::{BufReader, Read};
+use std::num::{NonZeroU64, NonZeroUsize};
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use byte_unit::Byte;
-use meilisearch_lib::options::IndexerOpts;
+use chrono::{DateTime, Utc};
+use meilisearch_lib::options::{IndexerOpts, WarmupMode};
 use rustls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
 use rustls::{
     AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient, NoClientAuth,
@@ -12,6 +14,11 @@ use rustls::{
 };
 use structopt::StructOpt;
 
+use crate::analytics::AnalyticsSink;
+use crate::extractors::key_restrictions::RestrictedKeys;
+use crate::logging::LogFormat;
+use crate::routes::CompatApiVersion;
+
 const POSSIBLE_ENV: [&str; 2] = ["development", "production"];
 
 #[derive(Debug, Clone, StructOpt)]
@@ -20,6 +27,11 @@ pub struct Opt {
     #[structopt(long, env = "MEILI_DB_PATH", default_value = "./data.ms")]
     pub db_path: PathBuf,
 
+    /// Stores the database in a temporary directory instead of `--db-path`, removed when the
+    /// server is stopped, for the tests that need a throwaway instance.
+    #[structopt(long, env = "MEILI_DB_IN_MEMORY")]
+    pub db_in_memory: bool,
+
     /// The address on which the http server will listen.
     #[structopt(long, env = "MEILI_HTTP_ADDR", default_value = "127.0.0.1:7700")]
     pub http_addr: String,
@@ -35,11 +47,57 @@ pub struct Opt {
     #[structopt(long, env = "MEILI_ENV", default_value = "development", possible_values = &POSSIBLE_ENV)]
     pub env: String,
 
+    /// The JSON file of the restricted search keys, each of them only allowed to search the
+    /// indexes it lists, with restrictions such as the fields the hits can contain. Requires a
+    /// master key.
+    #[structopt(long = "restricted-keys-path", env = "MEILI_RESTRICTED_KEYS_PATH")]
+    pub restricted_keys: Option<RestrictedKeys>,
+
     /// Do not send analytics to Meili.
     #[cfg(all(not(debug_assertions), feature = "analytics"))]
     #[structopt(long, env = "MEILI_NO_ANALYTICS")]
     pub no_analytics: bool,
 
+    /// Also write the analytics events to this file, as NDJSON, or send them to this `http(s)://`
+    /// endpoint, as JSON arrays. The events are written there even if they are not sent to Meili.
+    #[structopt(long, env = "MEILI_ANALYTICS_SINK")]
+    pub analytics_sink: Option<AnalyticsSink>,
+
+    /// Mirrors a part of the searches to the Meilisearch instance at this URL, such as
+    /// `http://127.0.0.1:7701`, logging its latencies next to the ones of this instance. The
+    /// results of the shadow instance are discarded.
+    #[structopt(long, env = "MEILI_SHADOW_URL")]
+    pub shadow_url: Option<String>,
+
+    /// The API key the searches are mirrored to the shadow instance with.
+    #[structopt(long, env = "MEILI_SHADOW_API_KEY")]
+    pub shadow_api_key: Option<String>,
+
+    /// The percentage of the searches mirrored to the shadow instance, from 0 to 100.
+    #[structopt(long, env = "MEILI_SHADOW_TRAFFIC_PERCENTAGE", default_value = "10", parse(try_from_str = parse_percentage))]
+    pub shadow_traffic_percentage: f64,
+
+    /// POSTs each finished task, processed, failed or canceled, as JSON to this URL.
+    #[structopt(long, env = "MEILI_TASK_WEBHOOK_URL")]
+    pub task_webhook_url: Option<String>,
+
+    /// The value of the `Authorization` header the tasks are POSTed to the webhook with.
+    #[structopt(long, env = "MEILI_TASK_WEBHOOK_AUTHORIZATION")]
+    pub task_webhook_authorization: Option<String>,
+
+    /// Do not send the hourly heartbeat, describing the size of the database, with the analytics.
+    #[structopt(long, env = "MEILI_NO_ANALYTICS_HEARTBEAT")]
+    pub no_analytics_heartbeat: bool,
+
+    /// Do not send the search events, describing the shape of the searches, with the analytics.
+    #[structopt(long, env = "MEILI_NO_ANALYTICS_SEARCH")]
+    pub no_analytics_search: bool,
+
+    /// Adds the email of the `MEILI_USER_EMAIL` environment variable to the analytics events, it
+    /// is never sent otherwise.
+    #[structopt(long, env = "MEILI_ANALYTICS_USER_EMAIL")]
+    pub analytics_user_email: bool,
+
     /// The maximum size, in bytes, of the main lmdb database directory
     #[structopt(long, env = "MEILI_MAX_INDEX_SIZE", default_value = "100 GiB")]
     pub max_index_size: Byte,
@@ -52,6 +110,69 @@ pub struct Opt {
     #[structopt(long, env = "MEILI_HTTP_PAYLOAD_SIZE_LIMIT", default_value = "100 MB")]
     pub http_payload_size_limit: Byte,
 
+    /// Also serves the routes and settings names of this older version of the API, translated
+    /// onto the current ones, for the clients not upgraded yet. Only `v0.21` is supported.
+    #[structopt(long, env = "MEILI_COMPAT_API_VERSION")]
+    pub compat_api_version: Option<CompatApiVersion>,
+
+    /// The maximum number of fields of an added document, the fields of its nested objects
+    /// included. The payloads containing a larger document are rejected.
+    #[structopt(long, env = "MEILI_MAX_FIELDS_PER_DOCUMENT")]
+    pub max_fields_per_document: Option<usize>,
+
+    /// The maximum size, in bytes, of an added document once serialized in JSON. The payloads
+    /// containing a larger document are rejected.
+    #[structopt(long, env = "MEILI_MAX_DOCUMENT_SIZE")]
+    pub max_document_size: Option<Byte>,
+
+    /// The maximum number of distinct values of a filterable attribute. The updates making an
+    /// attribute exceed it fail instead of blowing up the size of the index.
+    #[structopt(long, env = "MEILI_MAX_FACET_CARDINALITY")]
+    pub max_facet_cardinality: Option<u64>,
+
+    /// The maximum number of documents of an index. The document additions making an index
+    /// exceed it fail.
+    #[structopt(long, env = "MEILI_MAX_DOCUMENTS_PER_INDEX")]
+    pub max_documents_per_index: Option<u64>,
+
+    /// The maximum number of documents of all the indexes together. The document additions making
+    /// the instance exceed it fail.
+    #[structopt(long, env = "MEILI_MAX_DOCUMENTS")]
+    pub max_documents: Option<u64>,
+
+    /// Makes the indexing deterministic, so that two databases built from the same updates, or
+    /// the same dump, return the same search results, but for their `processingTimeMs`. The
+    /// documents are indexed on a single thread, the document additions one at a time, and the
+    /// indexes of a dump are loaded one after the other.
+    #[structopt(long, env = "MEILI_DETERMINISTIC_INDEXING")]
+    pub deterministic_indexing: bool,
+
+    /// The maximum `offset + limit` of a search. The deeper searches fail, the results past it
+    /// being reached with the `searchAfter` parameter of the sorted searches.
+    #[structopt(long, env = "MEILI_MAX_SEARCH_DEPTH", default_value = "10000")]
+    pub max_search_depth: usize,
+
+    /// The maximum `limit` of a search. The searches asking for more hits fail.
+    #[structopt(long, env = "MEILI_MAX_SEARCH_LIMIT")]
+    pub max_search_limit: Option<usize>,
+
+    /// The maximum number of attributes of the `facetsDistribution` of a search, `*` counting as
+    /// all the filterable attributes. The searches asking for more facets fail.
+    #[structopt(long, env = "MEILI_MAX_SEARCH_FACETS")]
+    pub max_search_facets: Option<usize>,
+
+    /// The maximum number of attributes of the hits of a search, the searches retrieving all the
+    /// displayed attributes, with `*` or without `attributesToRetrieve`, counting all of them.
+    /// The searches retrieving more attributes fail.
+    #[structopt(long, env = "MEILI_MAX_ATTRIBUTES_TO_RETRIEVE")]
+    pub max_attributes_to_retrieve: Option<usize>,
+
+    /// The maximum number of consecutive document additions of an index indexed together, in a
+    /// single indexing pass. `1` indexes each addition on its own, as the deterministic indexing
+    /// does.
+    #[structopt(long, env = "MEILI_MAX_BATCH_SIZE", default_value = "100")]
+    pub max_batch_size: NonZeroUsize,
+
     /// Read server certificates from CERTFILE.
     /// This should contain PEM-format certificates
     /// in the right order (the first certificate should
@@ -112,6 +233,32 @@ pub struct Opt {
     #[structopt(long, env = "MEILI_SNAPSHOT_INTERVAL_SEC", default_value = "86400")] // 24h
     pub snapshot_interval_sec: u64,
 
+    /// The maximum number of megabytes copied per second when the snapshots and dumps are
+    /// created, so that their copies don't evict the databases from the page cache and slow the
+    /// searches down. Unlimited by default.
+    #[structopt(long, env = "MEILI_SNAPSHOT_IO_THROTTLE_MBPS")]
+    pub snapshot_io_throttle_mbps: Option<NonZeroU64>,
+
+    /// Defines time interval, in seconds, between each measure of the space wasted on disk by the
+    /// indexes, reported by the stats route. The space is not measured if unset.
+    #[structopt(long, env = "MEILI_BLOAT_CHECK_INTERVAL_SEC")]
+    pub bloat_check_interval_sec: Option<u64>,
+
+    /// The number of days the processed, failed and canceled updates are kept, before being
+    /// deleted along with their payloads. They are kept forever by default.
+    #[structopt(long, env = "MEILI_TASK_RETENTION_DAYS")]
+    pub task_retention_days: Option<NonZeroU64>,
+
+    /// The maximum number of searches performed concurrently, the other searches wait in a queue.
+    /// A part of the slots is reserved to the searches without facets and with a small limit.
+    #[structopt(long, env = "MEILI_MAX_CONCURRENT_SEARCHES")]
+    pub max_concurrent_searches: Option<usize>,
+
+    /// How long, in milliseconds, a search can wait in the queue before being rejected with a
+    /// `503 Service Unavailable`.
+    #[structopt(long, env = "MEILI_SEARCH_QUEUE_TIMEOUT_MS", default_value = "1000")]
+    pub search_queue_timeout_ms: u64,
+
     /// Folder where dumps are created when the dump route is called.
     #[structopt(long, env = "MEILI_DUMPS_DIR", default_value = "dumps/")]
     pub dumps_dir: PathBuf,
@@ -120,15 +267,107 @@ pub struct Opt {
     #[structopt(long, conflicts_with = "import-snapshot")]
     pub import_dump: Option<PathBuf>,
 
+    /// The number of indexes of the dump loaded in parallel, each of them sharing the indexing
+    /// threads and memory. Defaults to 4.
+    #[structopt(long, env = "MEILI_DUMP_IMPORT_JOBS", requires = "import-dump")]
+    pub dump_import_jobs: Option<NonZeroUsize>,
+
+    /// The prefix added to the uids of the indexes of the dump, such as `staging_`. The dump is
+    /// then loaded next to the indexes of the existing database instead of replacing it, and the
+    /// import fails if one of the prefixed uids is already used.
+    #[structopt(long, env = "MEILI_IMPORT_DUMP_PREFIX", requires = "import-dump")]
+    pub import_dump_prefix: Option<String>,
+
+    /// The indexes of the dump that fail to load are skipped, along with their updates, instead
+    /// of aborting the import. They are listed by the `/dumps/import-report` route.
+    #[structopt(long, requires = "import-dump")]
+    pub ignore_dump_import_errors: bool,
+
+    /// Folder where every accepted update is archived along with its payload, allowing to
+    /// recover the database at any point in time. The `/archive` routes pause and resume the
+    /// archiving, and replay the archive, while the instance runs.
+    #[structopt(long, env = "MEILI_UPDATE_ARCHIVE_DIR")]
+    pub update_archive_dir: Option<PathBuf>,
+
+    /// Re-applies the archived updates enqueued up to this RFC 3339 date that are missing from
+    /// the database, typically after the import of a snapshot.
+    #[structopt(long, requires = "update-archive-dir")]
+    pub replay_archive_until: Option<DateTime<Utc>>,
+
+    /// How much of the indexes is loaded in memory at startup, so that the first searches don't
+    /// have to read them from the disk. The advancement is reported by the health route.
+    #[structopt(long, env = "MEILI_WARMUP", default_value = "none", possible_values = &["none", "meta", "full"])]
+    pub warmup: WarmupMode,
+
+    /// The number of workers serving the HTTP requests, each of them running on its own thread.
+    /// Defaults to the number of physical cores.
+    #[structopt(long, env = "MEILI_HTTP_WORKERS")]
+    pub http_workers: Option<NonZeroUsize>,
+
+    /// The number of threads of the runtime running the indexes, the updates, the dumps and the
+    /// snapshots. By default this runtime runs on the main thread only.
+    #[structopt(long, env = "MEILI_RUNTIME_WORKER_THREADS")]
+    pub runtime_worker_threads: Option<NonZeroUsize>,
+
+    /// The maximum number of threads this runtime spawns for its blocking tasks, such as the
+    /// accesses to the update stores, the dumps and the snapshots. The searches and the reads of
+    /// the documents run on the blocking threads of the HTTP workers. Defaults to 512.
+    #[structopt(long, env = "MEILI_MAX_BLOCKING_THREADS")]
+    pub max_blocking_threads: Option<NonZeroUsize>,
+
     /// Set the log level
     #[structopt(long, env = "MEILI_LOG_LEVEL", default_value = "info")]
     pub log_level: String,
 
+    /// The format of the logs: `human` readable lines, or `json` objects with the request id,
+    /// route, status and duration of the requests as fields of their access logs.
+    #[structopt(long, env = "MEILI_LOG_FORMAT", default_value = "human", possible_values = &["human", "json"])]
+    pub log_format: LogFormat,
+
     #[structopt(skip)]
     pub indexer_options: IndexerOpts,
 }
 
+fn parse_percentage(s: &str) -> anyhow::Result<f64> {
+    let percentage: f64 = s.parse()?;
+    if !(0. ..=100.).contains(&percentage) {
+        anyhow::bail!("The percentage {} is not between 0 and 100.", percentage);
+    }
+    Ok(percentage)
+}
+
 impl Opt {
+    /// The number of threads used to index the documents.
+    pub fn indexing_threads(&self) -> usize {
+        let mut indexer_options = self.indexer_options.clone();
+        indexer_options.deterministic_indexing = self.deterministic_indexing;
+        indexer_options.indexing_threads()
+    }
+
+    /// The maximum number of consecutive document additions of an index indexed together.
+    pub fn batch_size(&self) -> usize {
+        let mut indexer_options = self.indexer_options.clone();
+        indexer_options.max_batch_size = Some(self.max_batch_size.get());
+        indexer_options.deterministic_indexing = self.deterministic_indexing;
+        indexer_options.batch_size()
+    }
+
+    /// Builds the runtime the main thread runs, following the runtime options.
+    pub fn build_runtime(&self) -> std::io::Result<tokio::runtime::Runtime> {
+        let mut builder = match self.runtime_worker_threads {
+            Some(threads) => {
+                let mut builder = tokio::runtime::Builder::new_multi_thread();
+                builder.worker_threads(threads.get());
+                builder
+            }
+            None => tokio::runtime::Builder::new_current_thread(),
+        };
+        if let Some(threads) = self.max_blocking_threads {
+            builder.max_blocking_threads(threads.get());
+        }
+        builder.enable_all().build()
+    }
+
     pub fn get_ssl_config(&self) -> anyhow::Result<Option<rustls::ServerConfig>> {
         if let (Some(cert_path), Some(key_path)) = (&self.ssl_cert_path, &self.ssl_key_path) {
             let client_auth = match &self.ssl_auth_path {
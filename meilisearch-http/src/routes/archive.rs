@@ -0,0 +1,60 @@
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use log::debug;
+use meilisearch_lib::MeiliSearch;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::error::ResponseError;
+use crate::extractors::authentication::{policies::*, GuardedData};
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("")
+            .route(web::get().to(get_archive))
+            .route(web::patch().to(update_archive)),
+    )
+    .service(web::resource("/replay").route(web::post().to(replay_archive)));
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UpdateArchive {
+    /// Whether the accepted updates are archived.
+    archiving: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ReplayArchive {
+    /// The date up to which the archived updates are replayed.
+    until: DateTime<Utc>,
+}
+
+pub async fn get_archive(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+) -> Result<HttpResponse, ResponseError> {
+    let info = meilisearch.archive_info().await?;
+    debug!("returns: {:?}", info);
+    Ok(HttpResponse::Ok().json(info))
+}
+
+pub async fn update_archive(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    body: web::Json<UpdateArchive>,
+) -> Result<HttpResponse, ResponseError> {
+    debug!("called with params: {:?}", body);
+    let info = meilisearch.set_archiving(body.archiving).await?;
+    debug!("returns: {:?}", info);
+    Ok(HttpResponse::Ok().json(info))
+}
+
+pub async fn replay_archive(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    body: web::Json<ReplayArchive>,
+) -> Result<HttpResponse, ResponseError> {
+    debug!("called with params: {:?}", body);
+    let replayed = meilisearch.replay_archive(body.until).await?;
+    debug!("returns: {:?}", replayed);
+    Ok(HttpResponse::Ok().json(json!({ "replayed": replayed })))
+}
@@ -0,0 +1,236 @@
+//! The compatibility layer mounted with `--compat-api-version`, serving the routes and settings
+//! names of an older version of the API on top of the current handlers, for a fleet to upgrade
+//! its engines before all of its clients. The responses of the layer carry the warnings of the
+//! deprecations they use.
+//!
+//! With `v0.21`:
+//! - `GET /indexes/{indexUid}/updates` lists all the updates of the index, as an array.
+//! - The `asc(attribute)` and `desc(attribute)` ranking rules stand for `attribute:asc` and
+//!   `attribute:desc`.
+
+use std::str::FromStr;
+
+use actix_web::error::JsonPayloadError;
+use actix_web::{web, HttpResponse};
+use log::debug;
+use meilisearch_lib::index::{Settings, Unchecked};
+use meilisearch_lib::MeiliSearch;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::indexes::settings;
+use crate::deprecation::{
+    add_warnings, json_with_warnings, ASC_DESC_RANKING_RULES, UPDATES_LIST_ROUTE,
+};
+use crate::error::{PayloadError, ResponseError};
+use crate::extractors::authentication::{policies::*, GuardedData};
+use crate::extractors::update_metadata::UpdateMetadata;
+use crate::routes::{IndexParam, UpdateStatusResponse};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CompatApiVersion {
+    #[serde(rename = "v0.21")]
+    V0_21,
+}
+
+impl FromStr for CompatApiVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_start_matches('v') {
+            "0.21" => Ok(CompatApiVersion::V0_21),
+            _ => anyhow::bail!(
+                "Unsupported API version `{}`, the only compatible version is `v0.21`.",
+                s
+            ),
+        }
+    }
+}
+
+/// Mounts the routes of `version` in the scope of an index, before the current routes sharing
+/// their prefixes.
+pub fn configure_index(cfg: &mut web::ServiceConfig, version: Option<CompatApiVersion>) {
+    match version {
+        Some(CompatApiVersion::V0_21) => {
+            cfg.service(web::resource("/updates").route(web::get().to(list_updates)))
+                .service(
+                    web::resource("/settings")
+                        .route(web::get().to(get_settings))
+                        .route(web::post().to(update_settings))
+                        .route(web::delete().to(settings::delete_all)),
+                )
+                .service(
+                    web::resource("/settings/ranking-rules")
+                        .route(web::get().to(get_ranking_rules))
+                        .route(web::post().to(update_ranking_rules))
+                        .route(web::delete().to(settings::ranking_rules::delete)),
+                );
+        }
+        None => (),
+    }
+}
+
+pub async fn list_updates(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    path: web::Path<IndexParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let updates = meilisearch
+        .all_update_status(path.into_inner().index_uid)
+        .await?
+        .into_iter()
+        .map(UpdateStatusResponse::from)
+        .collect::<Vec<_>>();
+    debug!("returns: {:?}", updates);
+    Ok(json_with_warnings(
+        HttpResponse::Ok(),
+        &[UPDATES_LIST_ROUTE],
+        updates,
+    ))
+}
+
+pub async fn get_settings(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    index_uid: web::Path<String>,
+) -> Result<HttpResponse, ResponseError> {
+    let settings = meilisearch.settings(index_uid.into_inner()).await?;
+    debug!("returns: {:?}", settings);
+    let mut settings = serde_json::json!(&settings);
+    if let Some(Value::Array(rules)) = settings.get_mut("rankingRules") {
+        downgrade_ranking_rules(rules);
+    }
+    Ok(json_with_warnings(
+        HttpResponse::Ok(),
+        &[ASC_DESC_RANKING_RULES],
+        settings,
+    ))
+}
+
+pub async fn update_settings(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    index_uid: web::Path<String>,
+    body: web::Json<Value>,
+    metadata: UpdateMetadata,
+) -> Result<HttpResponse, ResponseError> {
+    let mut body = body.into_inner();
+    let mut used = Vec::new();
+    if let Some(Value::Array(rules)) = body.get_mut("rankingRules") {
+        let rules = rules.iter_mut().filter_map(|rule| match rule {
+            Value::String(rule) => Some(rule),
+            _ => None,
+        });
+        if upgrade_ranking_rules(rules) {
+            used.push(ASC_DESC_RANKING_RULES);
+        }
+    }
+    let settings: Settings<Unchecked> = serde_json::from_value(body)
+        .map_err(|e| PayloadError::from(JsonPayloadError::Deserialize(e)))?;
+
+    let mut response =
+        settings::update_all(meilisearch, index_uid, web::Json(settings), metadata).await?;
+    add_warnings(&mut response, &used);
+    Ok(response)
+}
+
+pub async fn get_ranking_rules(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    index_uid: web::Path<String>,
+) -> Result<HttpResponse, ResponseError> {
+    let settings = meilisearch.settings(index_uid.into_inner()).await?;
+    debug!("returns: {:?}", settings);
+    let mut json = serde_json::json!(&settings);
+    let mut rules = json["rankingRules"].take();
+    if let Value::Array(ref mut rules) = rules {
+        downgrade_ranking_rules(rules);
+    }
+    Ok(json_with_warnings(
+        HttpResponse::Ok(),
+        &[ASC_DESC_RANKING_RULES],
+        rules,
+    ))
+}
+
+pub async fn update_ranking_rules(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    index_uid: web::Path<String>,
+    body: web::Json<Option<Vec<String>>>,
+    metadata: UpdateMetadata,
+) -> Result<HttpResponse, ResponseError> {
+    let mut rules = body.into_inner();
+    let mut used = Vec::new();
+    if let Some(ref mut rules) = rules {
+        if upgrade_ranking_rules(rules.iter_mut()) {
+            used.push(ASC_DESC_RANKING_RULES);
+        }
+    }
+
+    let mut response =
+        settings::ranking_rules::update(meilisearch, index_uid, web::Json(rules), metadata).await?;
+    add_warnings(&mut response, &used);
+    Ok(response)
+}
+
+/// Rewrites the `asc(attribute)` and `desc(attribute)` ranking rules in their current shape,
+/// returning whether there was one.
+fn upgrade_ranking_rules<'a>(rules: impl Iterator<Item = &'a mut String>) -> bool {
+    let mut upgraded = false;
+    for rule in rules {
+        if let Some(current) = upgrade_ranking_rule(rule) {
+            *rule = current;
+            upgraded = true;
+        }
+    }
+    upgraded
+}
+
+fn downgrade_ranking_rules(rules: &mut [Value]) {
+    for rule in rules {
+        if let Some(old) = rule.as_str().and_then(downgrade_ranking_rule) {
+            *rule = Value::String(old);
+        }
+    }
+}
+
+fn upgrade_ranking_rule(rule: &str) -> Option<String> {
+    let (order, rest) = match (rule.strip_prefix("asc("), rule.strip_prefix("desc(")) {
+        (Some(rest), _) => ("asc", rest),
+        (None, Some(rest)) => ("desc", rest),
+        (None, None) => return None,
+    };
+    let attribute = rest.strip_suffix(')')?;
+    Some(format!("{}:{}", attribute, order))
+}
+
+fn downgrade_ranking_rule(rule: &str) -> Option<String> {
+    match rule.rsplit_once(':')? {
+        (attribute, order @ "asc") | (attribute, order @ "desc") => {
+            Some(format!("{}({})", order, attribute))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn translate_ranking_rules() {
+        assert_eq!(
+            upgrade_ranking_rule("asc(price)").as_deref(),
+            Some("price:asc")
+        );
+        assert_eq!(
+            upgrade_ranking_rule("desc(release_date)").as_deref(),
+            Some("release_date:desc")
+        );
+        assert_eq!(upgrade_ranking_rule("typo"), None);
+        assert_eq!(upgrade_ranking_rule("asc(price"), None);
+
+        assert_eq!(
+            downgrade_ranking_rule("price:asc").as_deref(),
+            Some("asc(price)")
+        );
+        assert_eq!(downgrade_ranking_rule("words"), None);
+        assert_eq!(downgrade_ranking_rule("a:b"), None);
+    }
+}
@@ -0,0 +1,268 @@
+//! Introspection of the jemalloc allocator used by the linux builds, and capture of CPU profiles
+//! when built with the `profiling` feature.
+
+use std::ffi::{c_void, CString};
+use std::os::raw::c_char;
+use std::ptr;
+
+use actix_web::{web, HttpResponse};
+use log::debug;
+use meilisearch_lib::MeiliSearch;
+use serde::Serialize;
+
+use crate::error::{MeilisearchHttpError, ResponseError};
+use crate::extractors::authentication::{policies::*, GuardedData};
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/memory").route(web::get().to(get_memory_stats)))
+        .service(web::resource("/memory/heap-profile").route(web::post().to(dump_heap_profile)));
+
+    #[cfg(feature = "profiling")]
+    cfg.service(web::resource("/profile").route(web::post().to(profile::capture_cpu_profile)));
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArenaStats {
+    index: u32,
+    threads: u32,
+    /// Bytes in the pages containing allocations.
+    active: usize,
+    /// Bytes in the unused pages that were not returned to the system yet.
+    dirty: usize,
+    resident: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MemoryStats {
+    allocated: usize,
+    active: usize,
+    metadata: usize,
+    resident: usize,
+    mapped: usize,
+    retained: usize,
+    arenas: Vec<ArenaStats>,
+}
+
+fn allocator_error(name: &str, code: i32) -> MeilisearchHttpError {
+    MeilisearchHttpError::Allocator(format!(
+        "reading `{}` failed with error code {}",
+        name, code
+    ))
+}
+
+/// Reads the value of the jemalloc control `name`.
+fn read<T: Default>(name: &str) -> Result<T, MeilisearchHttpError> {
+    let c_name = CString::new(name).expect("control names don't contain nul bytes");
+    let mut value = T::default();
+    let mut len = std::mem::size_of::<T>();
+    // Safety: `value` is a valid `T` of `len` bytes, the type of the controls read by this module
+    // matches the one documented by jemalloc.
+    let code = unsafe {
+        tikv_jemalloc_sys::mallctl(
+            c_name.as_ptr(),
+            &mut value as *mut T as *mut c_void,
+            &mut len,
+            ptr::null_mut(),
+            0,
+        )
+    };
+
+    match code {
+        0 => Ok(value),
+        code => Err(allocator_error(name, code)),
+    }
+}
+
+/// Writes `value` to the jemalloc control `name`.
+fn write<T>(name: &str, mut value: T) -> Result<(), MeilisearchHttpError> {
+    let c_name = CString::new(name).expect("control names don't contain nul bytes");
+    // Safety: same as in `read`.
+    let code = unsafe {
+        tikv_jemalloc_sys::mallctl(
+            c_name.as_ptr(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            &mut value as *mut T as *mut c_void,
+            std::mem::size_of::<T>(),
+        )
+    };
+
+    match code {
+        0 => Ok(()),
+        code => Err(allocator_error(name, code)),
+    }
+}
+
+fn memory_stats() -> Result<MemoryStats, MeilisearchHttpError> {
+    // The statistics are a snapshot refreshed by advancing the epoch.
+    write("epoch", 1u64)?;
+
+    let page_size: usize = read("arenas.page")?;
+    let narenas: u32 = read("arenas.narenas")?;
+    let mut arenas = Vec::new();
+    for index in 0..narenas {
+        // Uninitialized arenas have no statistics.
+        let threads = match read(&format!("stats.arenas.{}.nthreads", index)) {
+            Ok(threads) => threads,
+            Err(_) => continue,
+        };
+        arenas.push(ArenaStats {
+            index,
+            threads,
+            active: read::<usize>(&format!("stats.arenas.{}.pactive", index))? * page_size,
+            dirty: read::<usize>(&format!("stats.arenas.{}.pdirty", index))? * page_size,
+            resident: read(&format!("stats.arenas.{}.resident", index))?,
+        });
+    }
+
+    Ok(MemoryStats {
+        allocated: read("stats.allocated")?,
+        active: read("stats.active")?,
+        metadata: read("stats.metadata")?,
+        resident: read("stats.resident")?,
+        mapped: read("stats.mapped")?,
+        retained: read("stats.retained")?,
+        arenas,
+    })
+}
+
+async fn get_memory_stats(
+    _meilisearch: GuardedData<Private, MeiliSearch>,
+) -> Result<HttpResponse, ResponseError> {
+    let stats = memory_stats()?;
+
+    debug!("returns: {:?}", stats);
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+/// Returns a heap profile, only available when jemalloc was built with the profiling support and
+/// started with `MALLOC_CONF=prof:true`.
+async fn dump_heap_profile(
+    _meilisearch: GuardedData<Private, MeiliSearch>,
+) -> Result<HttpResponse, ResponseError> {
+    let file = tempfile::NamedTempFile::new()
+        .map_err(|e| MeilisearchHttpError::Allocator(e.to_string()))?;
+    let path = file.path().to_string_lossy().into_owned();
+    let c_path = CString::new(path).map_err(|e| MeilisearchHttpError::Allocator(e.to_string()))?;
+
+    write::<*const c_char>("prof.dump", c_path.as_ptr()).map_err(|_| {
+        MeilisearchHttpError::Allocator(String::from(
+            "heap profiling is not enabled, start meilisearch with `MALLOC_CONF=prof:true`",
+        ))
+    })?;
+
+    let profile =
+        std::fs::read(file.path()).map_err(|e| MeilisearchHttpError::Allocator(e.to_string()))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/octet-stream")
+        .body(profile))
+}
+
+#[cfg(feature = "profiling")]
+mod profile {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    use actix_web::{web, HttpResponse};
+    use meilisearch_lib::MeiliSearch;
+    use pprof::protos::Message;
+    use serde::Deserialize;
+
+    use crate::error::{MeilisearchHttpError, ResponseError};
+    use crate::extractors::authentication::{policies::*, GuardedData};
+
+    /// The number of samples taken per second.
+    const PROFILE_FREQUENCY_HZ: i32 = 100;
+    const DEFAULT_PROFILE_DURATION_SECS: u64 = 30;
+    const MAX_PROFILE_DURATION_SECS: u64 = 300;
+
+    /// Whether a profile is being captured, the profiler sampling the whole process.
+    static PROFILING: AtomicBool = AtomicBool::new(false);
+
+    #[derive(Debug, Clone, Copy, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    enum ProfileFormat {
+        /// An SVG flamegraph, to be opened in a browser.
+        Flamegraph,
+        /// A pprof protobuf, to be opened with `go tool pprof`.
+        Protobuf,
+    }
+
+    impl Default for ProfileFormat {
+        fn default() -> Self {
+            ProfileFormat::Flamegraph
+        }
+    }
+
+    fn default_profile_duration() -> u64 {
+        DEFAULT_PROFILE_DURATION_SECS
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase", deny_unknown_fields)]
+    pub struct ProfileQuery {
+        #[serde(default = "default_profile_duration")]
+        duration_sec: u64,
+        #[serde(default)]
+        format: ProfileFormat,
+    }
+
+    /// Marks a profile as being captured until dropped, even if the request is cancelled.
+    struct ProfilingGuard;
+
+    impl ProfilingGuard {
+        fn acquire() -> Result<Self, MeilisearchHttpError> {
+            match PROFILING.compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => Ok(ProfilingGuard),
+                Err(_) => Err(MeilisearchHttpError::ProfileInProgress),
+            }
+        }
+    }
+
+    impl Drop for ProfilingGuard {
+        fn drop(&mut self) {
+            PROFILING.store(false, Ordering::Release);
+        }
+    }
+
+    fn profiler_error(e: impl ToString) -> MeilisearchHttpError {
+        MeilisearchHttpError::Profiler(e.to_string())
+    }
+
+    /// Samples the CPU usage of the process during `durationSec`, and returns the profile.
+    pub async fn capture_cpu_profile(
+        _meilisearch: GuardedData<Admin, MeiliSearch>,
+        params: web::Query<ProfileQuery>,
+    ) -> Result<HttpResponse, ResponseError> {
+        let params = params.into_inner();
+        if params.duration_sec == 0 || params.duration_sec > MAX_PROFILE_DURATION_SECS {
+            return Err(
+                MeilisearchHttpError::InvalidProfileDuration(MAX_PROFILE_DURATION_SECS).into(),
+            );
+        }
+
+        let _profiling = ProfilingGuard::acquire()?;
+        let profiler = pprof::ProfilerGuard::new(PROFILE_FREQUENCY_HZ).map_err(profiler_error)?;
+        tokio::time::sleep(Duration::from_secs(params.duration_sec)).await;
+        let report = profiler.report().build().map_err(profiler_error)?;
+        drop(profiler);
+
+        let mut body = Vec::new();
+        let content_type = match params.format {
+            ProfileFormat::Flamegraph => {
+                report.flamegraph(&mut body).map_err(profiler_error)?;
+                "image/svg+xml"
+            }
+            ProfileFormat::Protobuf => {
+                let profile = report.pprof().map_err(profiler_error)?;
+                profile.encode(&mut body).map_err(profiler_error)?;
+                "application/octet-stream"
+            }
+        };
+
+        Ok(HttpResponse::Ok().content_type(content_type).body(body))
+    }
+}
@@ -5,9 +5,13 @@ use serde::{Deserialize, Serialize};
 
 use crate::error::ResponseError;
 use crate::extractors::authentication::{policies::*, GuardedData};
+use crate::extractors::payload::Payload;
+use crate::routes::indexes::documents::payload_to_stream;
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(web::resource("").route(web::post().to(create_dump)))
+        .service(web::resource("/import-report").route(web::get().to(get_import_report)))
+        .service(web::resource("/inspect").route(web::post().to(inspect_dump)))
         .service(web::resource("/{dump_uid}/status").route(web::get().to(get_dump_status)));
 }
 
@@ -40,3 +44,37 @@ async fn get_dump_status(
     debug!("returns: {:?}", res);
     Ok(HttpResponse::Ok().json(res))
 }
+
+pub async fn get_import_report(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+) -> Result<HttpResponse, ResponseError> {
+    let res = meilisearch.dump_import_report()?;
+
+    debug!("returns: {:?}", res);
+    Ok(HttpResponse::Ok().json(res))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct InspectDumpQuery {
+    /// The file name of a dump of the dumps directory, inspected instead of an uploaded one.
+    path: Option<String>,
+}
+
+pub async fn inspect_dump(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    params: web::Query<InspectDumpQuery>,
+    body: Payload,
+) -> Result<HttpResponse, ResponseError> {
+    let res = match params.into_inner().path {
+        Some(path) => meilisearch.inspect_dump_file(path).await?,
+        None => {
+            meilisearch
+                .inspect_dump(Box::new(payload_to_stream(body)))
+                .await?
+        }
+    };
+
+    debug!("returns: {:?}", res);
+    Ok(HttpResponse::Ok().json(res))
+}
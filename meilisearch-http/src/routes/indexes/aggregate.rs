@@ -0,0 +1,26 @@
+use actix_web::{web, HttpResponse};
+use log::debug;
+use meilisearch_lib::index::AggregateQuery;
+use meilisearch_lib::MeiliSearch;
+
+use crate::error::ResponseError;
+use crate::extractors::authentication::{policies::*, GuardedData};
+use crate::routes::IndexParam;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("").route(web::post().to(aggregate)));
+}
+
+pub async fn aggregate(
+    meilisearch: GuardedData<Public, MeiliSearch>,
+    path: web::Path<IndexParam>,
+    params: web::Json<AggregateQuery>,
+) -> Result<HttpResponse, ResponseError> {
+    debug!("aggregate called with params: {:?}", params);
+    let result = meilisearch
+        .aggregate(path.into_inner().index_uid, params.into_inner())
+        .await?;
+
+    debug!("returns: {:?}", result);
+    Ok(HttpResponse::Ok().json(result))
+}
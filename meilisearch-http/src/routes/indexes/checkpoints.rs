@@ -0,0 +1,43 @@
+use actix_web::{web, HttpResponse};
+use log::debug;
+use meilisearch_lib::MeiliSearch;
+use serde::Deserialize;
+
+use crate::error::ResponseError;
+use crate::extractors::authentication::{policies::*, GuardedData};
+use crate::routes::IndexParam;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("").route(web::get().to(list_checkpoints)))
+        .service(web::resource("{update_id}/restore").route(web::post().to(restore_checkpoint)));
+}
+
+#[derive(Deserialize)]
+pub struct CheckpointParam {
+    index_uid: String,
+    update_id: u64,
+}
+
+pub async fn list_checkpoints(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    path: web::Path<IndexParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let checkpoints = meilisearch
+        .index_checkpoints(path.into_inner().index_uid)
+        .await?;
+    debug!("returns: {:?}", checkpoints);
+    Ok(HttpResponse::Ok().json(checkpoints))
+}
+
+pub async fn restore_checkpoint(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    path: web::Path<CheckpointParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let CheckpointParam {
+        index_uid,
+        update_id,
+    } = path.into_inner();
+    let meta = meilisearch.restore_checkpoint(index_uid, update_id).await?;
+    debug!("returns: {:?}", meta);
+    Ok(HttpResponse::Ok().json(meta))
+}
@@ -3,6 +3,8 @@ use actix_web::web::Bytes;
 use actix_web::{web, HttpRequest, HttpResponse};
 use futures::{Stream, StreamExt};
 use log::debug;
+use meilisearch_lib::index::DocumentsQuery;
+use meilisearch_lib::index_controller::updates::status::UpdateContext;
 use meilisearch_lib::index_controller::{DocumentAdditionFormat, Update};
 use meilisearch_lib::milli::update::IndexDocumentsMethod;
 use meilisearch_lib::MeiliSearch;
@@ -13,13 +15,16 @@ use tokio::sync::mpsc;
 use crate::error::{MeilisearchHttpError, ResponseError};
 use crate::extractors::authentication::{policies::*, GuardedData};
 use crate::extractors::payload::Payload;
+use crate::extractors::update_metadata::UpdateMetadata;
 use crate::routes::IndexParam;
 
+use super::search::fix_sort_query_parameters;
+
 const DEFAULT_RETRIEVE_DOCUMENTS_OFFSET: usize = 0;
 const DEFAULT_RETRIEVE_DOCUMENTS_LIMIT: usize = 20;
 
 /// This is required because Payload is not Sync nor Send
-fn payload_to_stream(mut payload: Payload) -> impl Stream<Item = Result<Bytes, PayloadError>> {
+pub fn payload_to_stream(mut payload: Payload) -> impl Stream<Item = Result<Bytes, PayloadError>> {
     let (snd, recv) = mpsc::channel(1);
     tokio::task::spawn_local(async move {
         while let Some(data) = payload.next().await {
@@ -45,6 +50,7 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
     )
     // this route needs to be before the /documents/{document_id} to match properly
     .service(web::resource("/delete-batch").route(web::post().to(delete_documents)))
+    .service(web::resource("/delete").route(web::post().to(delete_documents_by_filter)))
     .service(
         web::resource("/{document_id}")
             .route(web::get().to(get_document))
@@ -68,6 +74,7 @@ pub async fn get_document(
 pub async fn delete_document(
     meilisearch: GuardedData<Private, MeiliSearch>,
     path: web::Path<DocumentParam>,
+    metadata: UpdateMetadata,
 ) -> Result<HttpResponse, ResponseError> {
     let DocumentParam {
         document_id,
@@ -75,7 +82,7 @@ pub async fn delete_document(
     } = path.into_inner();
     let update = Update::DeleteDocuments(vec![document_id]);
     let update_status = meilisearch
-        .register_update(index_uid, update, false)
+        .register_update(index_uid, update, false, metadata.into_inner())
         .await?;
     debug!("returns: {:?}", update_status);
     Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() })))
@@ -87,6 +94,10 @@ pub struct BrowseQuery {
     offset: Option<usize>,
     limit: Option<usize>,
     attributes_to_retrieve: Option<String>,
+    /// The comma separated fields of the documents, overriding `attributesToRetrieve`.
+    fields: Option<String>,
+    filter: Option<String>,
+    sort: Option<String>,
 }
 
 pub async fn get_all_documents(
@@ -95,24 +106,39 @@ pub async fn get_all_documents(
     params: web::Query<BrowseQuery>,
 ) -> Result<HttpResponse, ResponseError> {
     debug!("called with params: {:?}", params);
-    let attributes_to_retrieve = params.attributes_to_retrieve.as_ref().and_then(|attrs| {
-        let mut names = Vec::new();
-        for name in attrs.split(',').map(String::from) {
-            if name == "*" {
-                return None;
+    let params = params.into_inner();
+    let fields = params
+        .fields
+        .as_ref()
+        .or_else(|| params.attributes_to_retrieve.as_ref())
+        .and_then(|attrs| {
+            let mut names = Vec::new();
+            for name in attrs.split(',').map(String::from) {
+                if name == "*" {
+                    return None;
+                }
+                names.push(name);
             }
-            names.push(name);
-        }
-        Some(names)
-    });
+            Some(names)
+        });
+
+    let filter = params
+        .filter
+        .map(|filter| match serde_json::from_str(&filter) {
+            Ok(filter) => filter,
+            _ => Value::String(filter),
+        });
+    let sort = params.sort.map(|sort| fix_sort_query_parameters(&sort));
 
+    let query = DocumentsQuery {
+        offset: params.offset.unwrap_or(DEFAULT_RETRIEVE_DOCUMENTS_OFFSET),
+        limit: params.limit.unwrap_or(DEFAULT_RETRIEVE_DOCUMENTS_LIMIT),
+        fields,
+        filter,
+        sort,
+    };
     let documents = meilisearch
-        .documents(
-            path.index_uid.clone(),
-            params.offset.unwrap_or(DEFAULT_RETRIEVE_DOCUMENTS_OFFSET),
-            params.limit.unwrap_or(DEFAULT_RETRIEVE_DOCUMENTS_LIMIT),
-            attributes_to_retrieve,
-        )
+        .browse_documents(path.into_inner().index_uid, query)
         .await?;
     debug!("returns: {:?}", documents);
     Ok(HttpResponse::Ok().json(documents))
@@ -122,6 +148,9 @@ pub async fn get_all_documents(
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct UpdateDocumentsQuery {
     primary_key: Option<String>,
+    /// Only checks the documents, reporting the ones the addition would reject.
+    #[serde(default)]
+    validate_only: bool,
 }
 
 pub async fn add_documents(
@@ -130,17 +159,21 @@ pub async fn add_documents(
     params: web::Query<UpdateDocumentsQuery>,
     body: Payload,
     req: HttpRequest,
+    metadata: UpdateMetadata,
 ) -> Result<HttpResponse, ResponseError> {
     debug!("called with params: {:?}", params);
+    let params = params.into_inner();
     document_addition(
         req.headers()
             .get("Content-type")
             .map(|s| s.to_str().unwrap_or("unkown")),
         meilisearch,
         path.into_inner().index_uid,
-        params.into_inner().primary_key,
+        params.primary_key,
+        params.validate_only,
         body,
         IndexDocumentsMethod::ReplaceDocuments,
+        metadata.into_inner(),
     )
     .await
 }
@@ -151,17 +184,21 @@ pub async fn update_documents(
     params: web::Query<UpdateDocumentsQuery>,
     body: Payload,
     req: HttpRequest,
+    metadata: UpdateMetadata,
 ) -> Result<HttpResponse, ResponseError> {
     debug!("called with params: {:?}", params);
+    let params = params.into_inner();
     document_addition(
         req.headers()
             .get("Content-type")
             .map(|s| s.to_str().unwrap_or("unkown")),
         meilisearch,
         path.into_inner().index_uid,
-        params.into_inner().primary_key,
+        params.primary_key,
+        params.validate_only,
         body,
         IndexDocumentsMethod::UpdateDocuments,
+        metadata.into_inner(),
     )
     .await
 }
@@ -173,8 +210,10 @@ async fn document_addition(
     meilisearch: GuardedData<Private, MeiliSearch>,
     index_uid: String,
     primary_key: Option<String>,
+    validate_only: bool,
     body: Payload,
     method: IndexDocumentsMethod,
+    metadata: UpdateContext,
 ) -> Result<HttpResponse, ResponseError> {
     let format = match content_type {
         Some("application/json") => DocumentAdditionFormat::Json,
@@ -186,6 +225,19 @@ async fn document_addition(
         None => return Err(MeilisearchHttpError::MissingContentType.into()),
     };
 
+    if validate_only {
+        let validation = meilisearch
+            .validate_documents(
+                index_uid,
+                Box::new(payload_to_stream(body)),
+                format,
+                primary_key,
+            )
+            .await?;
+        debug!("returns: {:?}", validation);
+        return Ok(HttpResponse::Ok().json(validation));
+    }
+
     let update = Update::DocumentAddition {
         payload: Box::new(payload_to_stream(body)),
         primary_key,
@@ -193,7 +245,9 @@ async fn document_addition(
         format,
     };
 
-    let update_status = meilisearch.register_update(index_uid, update, true).await?;
+    let update_status = meilisearch
+        .register_update(index_uid, update, true, metadata)
+        .await?;
 
     debug!("returns: {:?}", update_status);
     Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() })))
@@ -203,6 +257,7 @@ pub async fn delete_documents(
     meilisearch: GuardedData<Private, MeiliSearch>,
     path: web::Path<IndexParam>,
     body: web::Json<Vec<Value>>,
+    metadata: UpdateMetadata,
 ) -> Result<HttpResponse, ResponseError> {
     debug!("called with params: {:?}", body);
     let ids = body
@@ -216,7 +271,40 @@ pub async fn delete_documents(
 
     let update = Update::DeleteDocuments(ids);
     let update_status = meilisearch
-        .register_update(path.into_inner().index_uid, update, false)
+        .register_update(
+            path.into_inner().index_uid,
+            update,
+            false,
+            metadata.into_inner(),
+        )
+        .await?;
+    debug!("returns: {:?}", update_status);
+    Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() })))
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeleteByFilter {
+    filter: Value,
+}
+
+pub async fn delete_documents_by_filter(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    path: web::Path<IndexParam>,
+    body: web::Json<DeleteByFilter>,
+    metadata: UpdateMetadata,
+) -> Result<HttpResponse, ResponseError> {
+    debug!("called with params: {:?}", body);
+    let update = Update::DeleteByFilter {
+        filter: body.into_inner().filter,
+    };
+    let update_status = meilisearch
+        .register_update(
+            path.into_inner().index_uid,
+            update,
+            false,
+            metadata.into_inner(),
+        )
         .await?;
     debug!("returns: {:?}", update_status);
     Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() })))
@@ -225,10 +313,16 @@ pub async fn delete_documents(
 pub async fn clear_all_documents(
     meilisearch: GuardedData<Private, MeiliSearch>,
     path: web::Path<IndexParam>,
+    metadata: UpdateMetadata,
 ) -> Result<HttpResponse, ResponseError> {
     let update = Update::ClearDocuments;
     let update_status = meilisearch
-        .register_update(path.into_inner().index_uid, update, false)
+        .register_update(
+            path.into_inner().index_uid,
+            update,
+            false,
+            metadata.into_inner(),
+        )
         .await?;
     debug!("returns: {:?}", update_status);
     Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() })))
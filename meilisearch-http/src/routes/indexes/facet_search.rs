@@ -0,0 +1,30 @@
+use actix_web::{web, HttpResponse};
+use log::debug;
+use meilisearch_lib::index::FacetSearchQuery;
+use meilisearch_lib::MeiliSearch;
+
+use crate::error::ResponseError;
+use crate::extractors::authentication::{policies::*, GuardedData};
+use crate::extractors::key_restrictions::SearchRestrictions;
+use crate::routes::IndexParam;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("").route(web::post().to(facet_search)));
+}
+
+pub async fn facet_search(
+    meilisearch: GuardedData<Search, MeiliSearch>,
+    path: web::Path<IndexParam>,
+    params: web::Json<FacetSearchQuery>,
+    restrictions: SearchRestrictions,
+) -> Result<HttpResponse, ResponseError> {
+    debug!("facet search called with params: {:?}", params);
+    let mut query = params.into_inner();
+    restrictions.apply_to_facet_search(&mut query);
+    let result = meilisearch
+        .facet_search(path.into_inner().index_uid, query)
+        .await?;
+
+    debug!("returns: {:?}", result);
+    Ok(HttpResponse::Ok().json(result))
+}
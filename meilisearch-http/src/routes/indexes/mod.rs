@@ -1,20 +1,24 @@
 use actix_web::{web, HttpResponse};
 use chrono::{DateTime, Utc};
 use log::debug;
+use meilisearch_lib::index::PrimaryKey;
 use meilisearch_lib::index_controller::IndexSettings;
 use meilisearch_lib::MeiliSearch;
 use serde::{Deserialize, Serialize};
 
 use crate::error::ResponseError;
 use crate::extractors::authentication::{policies::*, GuardedData};
-use crate::routes::IndexParam;
+use crate::routes::{compat, CompatApiVersion, IndexParam};
 
+pub mod aggregate;
+pub mod checkpoints;
 pub mod documents;
+pub mod facet_search;
 pub mod search;
 pub mod settings;
 pub mod updates;
 
-pub fn configure(cfg: &mut web::ServiceConfig) {
+pub fn configure(cfg: &mut web::ServiceConfig, compat_api_version: Option<CompatApiVersion>) {
     cfg.service(
         web::resource("")
             .route(web::get().to(list_indexes))
@@ -29,7 +33,11 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                     .route(web::delete().to(delete_index)),
             )
             .service(web::resource("/stats").route(web::get().to(get_index_stats)))
+            .configure(|s| compat::configure_index(s, compat_api_version))
+            .service(web::scope("/aggregate").configure(aggregate::configure))
+            .service(web::scope("/checkpoints").configure(checkpoints::configure))
             .service(web::scope("/documents").configure(documents::configure))
+            .service(web::scope("/facet-search").configure(facet_search::configure))
             .service(web::scope("/search").configure(search::configure))
             .service(web::scope("/updates").configure(updates::configure))
             .service(web::scope("/settings").configure(settings::configure)),
@@ -48,7 +56,7 @@ pub async fn list_indexes(
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct IndexCreateRequest {
     uid: String,
-    primary_key: Option<String>,
+    primary_key: Option<PrimaryKey>,
 }
 
 pub async fn create_index(
@@ -7,6 +7,7 @@ use meilisearch_lib::MeiliSearch;
 
 use crate::error::ResponseError;
 use crate::extractors::authentication::{policies::*, GuardedData};
+use crate::extractors::update_metadata::UpdateMetadata;
 
 #[macro_export]
 macro_rules! make_setting_route {
@@ -20,17 +21,19 @@ macro_rules! make_setting_route {
 
             use crate::error::ResponseError;
             use crate::extractors::authentication::{GuardedData, policies::*};
+            use crate::extractors::update_metadata::UpdateMetadata;
 
             pub async fn delete(
                 meilisearch: GuardedData<Private, MeiliSearch>,
                 index_uid: web::Path<String>,
+                metadata: UpdateMetadata,
             ) -> Result<HttpResponse, ResponseError> {
                 let settings = Settings {
                     $attr: Setting::Reset,
                     ..Default::default()
                 };
                 let update = Update::Settings(settings);
-                let update_status = meilisearch.register_update(index_uid.into_inner(), update, false).await?;
+                let update_status = meilisearch.register_update(index_uid.into_inner(), update, false, metadata.into_inner()).await?;
                 debug!("returns: {:?}", update_status);
                 Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() })))
             }
@@ -39,6 +42,7 @@ macro_rules! make_setting_route {
                 meilisearch: GuardedData<Private, MeiliSearch>,
                 index_uid: actix_web::web::Path<String>,
                 body: actix_web::web::Json<Option<$type>>,
+                metadata: UpdateMetadata,
             ) -> std::result::Result<HttpResponse, ResponseError> {
                 let settings = Settings {
                     $attr: match body.into_inner() {
@@ -49,7 +53,7 @@ macro_rules! make_setting_route {
                 };
 
                 let update = Update::Settings(settings);
-                let update_status = meilisearch.register_update(index_uid.into_inner(), update, true).await?;
+                let update_status = meilisearch.register_update(index_uid.into_inner(), update, true, metadata.into_inner()).await?;
                 debug!("returns: {:?}", update_status);
                 Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() })))
             }
@@ -126,6 +130,69 @@ make_setting_route!(
 
 make_setting_route!("/ranking-rules", Vec<String>, ranking_rules, "rankingRules");
 
+make_setting_route!(
+    "/date-attributes",
+    std::collections::BTreeSet<String>,
+    date_attributes,
+    "dateAttributes"
+);
+
+make_setting_route!(
+    "/flatten-strategy",
+    meilisearch_lib::index::FlattenStrategy,
+    flatten_strategy,
+    "flattenStrategy"
+);
+
+make_setting_route!(
+    "/sort-collation",
+    meilisearch_lib::index::SortCollation,
+    sort_collation,
+    "sortCollation"
+);
+
+make_setting_route!(
+    "/normalized-filterable-attributes",
+    std::collections::BTreeSet<String>,
+    normalized_filterable_attributes,
+    "normalizedFilterableAttributes"
+);
+
+make_setting_route!(
+    "/searchable-numeric-attributes",
+    std::collections::BTreeSet<String>,
+    searchable_numeric_attributes,
+    "searchableNumericAttributes"
+);
+
+make_setting_route!(
+    "/raw-attributes",
+    std::collections::BTreeSet<String>,
+    raw_attributes,
+    "rawAttributes"
+);
+
+make_setting_route!(
+    "/typo-tolerance",
+    meilisearch_lib::index::TypoTolerance,
+    typo_tolerance,
+    "typoTolerance"
+);
+
+make_setting_route!(
+    "/search-defaults",
+    meilisearch_lib::index::SearchDefaults,
+    search_defaults,
+    "searchDefaults"
+);
+
+make_setting_route!(
+    "/checkpoint-before-settings-change",
+    bool,
+    checkpoint_before_settings_change,
+    "checkpointBeforeSettingsChange"
+);
+
 macro_rules! generate_configure {
     ($($mod:ident),*) => {
         pub fn configure(cfg: &mut web::ServiceConfig) {
@@ -134,6 +201,7 @@ macro_rules! generate_configure {
                 .route(web::post().to(update_all))
                 .route(web::get().to(get_all))
                 .route(web::delete().to(delete_all)))
+                .service(web::resource("/lint").route(web::get().to(lint)))
                 $(.service($mod::resources()))*;
         }
     };
@@ -147,19 +215,29 @@ generate_configure!(
     distinct_attribute,
     stop_words,
     synonyms,
-    ranking_rules
+    ranking_rules,
+    date_attributes,
+    flatten_strategy,
+    sort_collation,
+    normalized_filterable_attributes,
+    searchable_numeric_attributes,
+    raw_attributes,
+    checkpoint_before_settings_change,
+    typo_tolerance,
+    search_defaults
 );
 
 pub async fn update_all(
     meilisearch: GuardedData<Private, MeiliSearch>,
     index_uid: web::Path<String>,
     body: web::Json<Settings<Unchecked>>,
+    metadata: UpdateMetadata,
 ) -> Result<HttpResponse, ResponseError> {
     let settings = body.into_inner();
 
     let update = Update::Settings(settings);
     let update_result = meilisearch
-        .register_update(index_uid.into_inner(), update, true)
+        .register_update(index_uid.into_inner(), update, true, metadata.into_inner())
         .await?;
     let json = serde_json::json!({ "updateId": update_result.id() });
     debug!("returns: {:?}", json);
@@ -175,15 +253,25 @@ pub async fn get_all(
     Ok(HttpResponse::Ok().json(settings))
 }
 
+pub async fn lint(
+    data: GuardedData<Private, MeiliSearch>,
+    index_uid: web::Path<String>,
+) -> Result<HttpResponse, ResponseError> {
+    let lint = data.lint_settings(index_uid.into_inner()).await?;
+    debug!("returns: {:?}", lint);
+    Ok(HttpResponse::Ok().json(lint))
+}
+
 pub async fn delete_all(
     data: GuardedData<Private, MeiliSearch>,
     index_uid: web::Path<String>,
+    metadata: UpdateMetadata,
 ) -> Result<HttpResponse, ResponseError> {
     let settings = Settings::cleared();
 
     let update = Update::Settings(settings.into_unchecked());
     let update_result = data
-        .register_update(index_uid.into_inner(), update, false)
+        .register_update(index_uid.into_inner(), update, false, metadata.into_inner())
         .await?;
     let json = serde_json::json!({ "updateId": update_result.id() });
     debug!("returns: {:?}", json);
@@ -1,16 +1,26 @@
+use std::time::Duration;
+
 use actix_web::{web, HttpResponse};
 use chrono::{DateTime, Utc};
+use futures::StreamExt;
 use log::debug;
 use meilisearch_lib::MeiliSearch;
 use serde::{Deserialize, Serialize};
 
-use crate::error::ResponseError;
+use crate::deprecation::{json_with_warnings, UPDATE_STATUS_ROUTE};
+use crate::error::{MeilisearchHttpError, ResponseError};
 use crate::extractors::authentication::{policies::*, GuardedData};
 use crate::routes::{IndexParam, UpdateStatusResponse};
 
+/// How long a client waits for its updates by default, in milliseconds.
+pub(crate) const DEFAULT_WAIT_TIMEOUT_MS: u64 = 5000;
+
 pub fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::resource("").route(web::get().to(get_all_updates_status)))
-        .service(web::resource("{update_id}").route(web::get().to(get_update_status)));
+    // this route needs to be before the /updates/{update_id} to match properly
+    cfg.service(web::resource("wait").route(web::post().to(wait_updates)))
+        .service(web::resource("{update_id}").route(web::get().to(get_update_status)))
+        .service(web::resource("{update_id}/payload").route(web::get().to(get_update_payload)))
+        .service(web::resource("{update_id}/retry").route(web::post().to(retry_update)));
 }
 
 #[derive(Debug, Deserialize)]
@@ -36,25 +46,67 @@ pub struct UpdateParam {
     update_id: u64,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UpdateStatusQuery {
+    /// Holds the request until the update is processed or failed.
+    #[serde(default)]
+    wait_for_completion: bool,
+    /// How long to hold the request, in milliseconds, capped to a minute.
+    timeout_ms: Option<u64>,
+}
+
 pub async fn get_update_status(
     meilisearch: GuardedData<Private, MeiliSearch>,
     path: web::Path<UpdateParam>,
+    query: web::Query<UpdateStatusQuery>,
 ) -> Result<HttpResponse, ResponseError> {
     let params = path.into_inner();
-    let meta = meilisearch
-        .update_status(params.index_uid, params.update_id)
-        .await?;
+    let meta = if query.wait_for_completion {
+        let timeout = Duration::from_millis(query.timeout_ms.unwrap_or(DEFAULT_WAIT_TIMEOUT_MS));
+        meilisearch
+            .wait_update_status(params.index_uid, params.update_id, timeout)
+            .await?
+    } else {
+        meilisearch
+            .update_status(params.index_uid, params.update_id)
+            .await?
+    };
     let meta = UpdateStatusResponse::from(meta);
     debug!("returns: {:?}", meta);
-    Ok(HttpResponse::Ok().json(meta))
+    Ok(json_with_warnings(
+        HttpResponse::Ok(),
+        &[UPDATE_STATUS_ROUTE],
+        meta,
+    ))
 }
 
-pub async fn get_all_updates_status(
+pub(crate) fn parse_update_ids(ids: &str) -> Result<Vec<u64>, MeilisearchHttpError> {
+    ids.split(',')
+        .map(|id| id.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| MeilisearchHttpError::InvalidUpdateIds(ids.to_string()))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct WaitUpdatesRequest {
+    uids: Vec<u64>,
+    /// How long to wait for the updates, in milliseconds, capped to a minute.
+    timeout: Option<u64>,
+}
+
+/// Returns the statuses of the updates once they are all processed or failed, or when the
+/// timeout elapses, letting the clients wait for many updates with a single request.
+pub async fn wait_updates(
     meilisearch: GuardedData<Private, MeiliSearch>,
     path: web::Path<IndexParam>,
+    body: web::Json<WaitUpdatesRequest>,
 ) -> Result<HttpResponse, ResponseError> {
+    let WaitUpdatesRequest { uids, timeout } = body.into_inner();
+    let timeout = Duration::from_millis(timeout.unwrap_or(DEFAULT_WAIT_TIMEOUT_MS));
     let metas = meilisearch
-        .all_update_status(path.into_inner().index_uid)
+        .wait_for_updates(path.into_inner().index_uid, uids, timeout)
         .await?;
     let metas = metas
         .into_iter()
@@ -64,3 +116,33 @@ pub async fn get_all_updates_status(
     debug!("returns: {:?}", metas);
     Ok(HttpResponse::Ok().json(metas))
 }
+
+/// Streams the documents of a pending or failed document addition, as NDJSON.
+pub async fn get_update_payload(
+    meilisearch: GuardedData<Admin, MeiliSearch>,
+    path: web::Path<UpdateParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let params = path.into_inner();
+    let payload = meilisearch
+        .update_payload(params.index_uid, params.update_id)
+        .await?;
+    let payload =
+        payload.map(|chunk| chunk.map_err(|e| actix_web::Error::from(ResponseError::from(e))));
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(Box::pin(payload)))
+}
+
+/// Enqueues a failed update again, with the same payload, as a new update.
+pub async fn retry_update(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    path: web::Path<UpdateParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let params = path.into_inner();
+    let update_status = meilisearch
+        .retry_update(params.index_uid, params.update_id)
+        .await?;
+    debug!("returns: {:?}", update_status);
+    Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() })))
+}
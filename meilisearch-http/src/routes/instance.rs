@@ -0,0 +1,199 @@
+use std::num::{NonZeroU64, NonZeroUsize};
+use std::path::PathBuf;
+
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use meilisearch_lib::options::WarmupMode;
+use serde::Serialize;
+
+use crate::analytics::AnalyticsSink;
+use crate::deprecation::{Deprecation, DEPRECATIONS};
+use crate::extractors::authentication::{policies::*, GuardedData};
+use crate::extractors::key_restrictions::RestrictedKeys;
+use crate::logging::LogFormat;
+use crate::routes::CompatApiVersion;
+use crate::Opt;
+
+/// Replaces the secrets in the summary of the options.
+const REDACTED: &str = "[redacted]";
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("").route(web::get().to(get_instance)));
+}
+
+/// The information printed at launch, for the fleet tooling to inventory the running versions and
+/// configurations.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceInfo {
+    commit_sha: &'static str,
+    commit_date: &'static str,
+    pkg_version: &'static str,
+    db_path: PathBuf,
+    db_in_memory: bool,
+    http_addr: String,
+    env: String,
+    /// The optional features this binary was built with.
+    features: Vec<&'static str>,
+    options: InstanceOptions,
+    /// The deprecated routes and parameters still served.
+    deprecations: &'static [Deprecation],
+}
+
+/// The effective values of the options, the secrets being redacted.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InstanceOptions {
+    master_key: Option<&'static str>,
+    /// The number of restricted keys, the keys themselves are redacted.
+    restricted_keys: usize,
+    /// Whether the anonymous telemetry is sent.
+    analytics: bool,
+    analytics_sink: Option<String>,
+    analytics_heartbeat: bool,
+    analytics_search: bool,
+    analytics_user_email: bool,
+    shadow_url: Option<String>,
+    shadow_api_key: Option<&'static str>,
+    shadow_traffic_percentage: f64,
+    task_webhook_url: Option<String>,
+    task_webhook_authorization: Option<&'static str>,
+    max_index_size: u64,
+    max_udb_size: u64,
+    http_payload_size_limit: u64,
+    compat_api_version: Option<CompatApiVersion>,
+    max_fields_per_document: Option<usize>,
+    max_document_size: Option<u64>,
+    max_facet_cardinality: Option<u64>,
+    max_documents_per_index: Option<u64>,
+    max_documents: Option<u64>,
+    deterministic_indexing: bool,
+    max_search_depth: usize,
+    max_search_limit: Option<usize>,
+    max_search_facets: Option<usize>,
+    max_attributes_to_retrieve: Option<usize>,
+    max_batch_size: usize,
+    ssl_enabled: bool,
+    ssl_require_auth: bool,
+    import_snapshot: Option<PathBuf>,
+    snapshot_dir: PathBuf,
+    schedule_snapshot: bool,
+    snapshot_interval_sec: u64,
+    snapshot_io_throttle_mbps: Option<u64>,
+    bloat_check_interval_sec: Option<u64>,
+    task_retention_days: Option<u64>,
+    max_concurrent_searches: Option<usize>,
+    search_queue_timeout_ms: u64,
+    dumps_dir: PathBuf,
+    import_dump: Option<PathBuf>,
+    dump_import_jobs: Option<usize>,
+    import_dump_prefix: Option<String>,
+    update_archive_dir: Option<PathBuf>,
+    replay_archive_until: Option<DateTime<Utc>>,
+    warmup: WarmupMode,
+    log_level: String,
+    log_format: LogFormat,
+    indexing_memory: String,
+    indexing_threads: usize,
+    http_workers: Option<usize>,
+    runtime_worker_threads: Option<usize>,
+    max_blocking_threads: Option<usize>,
+}
+
+impl InstanceInfo {
+    pub fn new(opt: &Opt) -> Self {
+        let mut features = Vec::new();
+        if cfg!(feature = "analytics") {
+            features.push("analytics");
+        }
+        if cfg!(feature = "mini-dashboard") {
+            features.push("mini-dashboard");
+        }
+        if cfg!(feature = "shadow-traffic") {
+            features.push("shadow-traffic");
+        }
+        if cfg!(feature = "task-webhook") {
+            features.push("task-webhook");
+        }
+
+        #[cfg(all(not(debug_assertions), feature = "analytics"))]
+        let analytics = !opt.no_analytics;
+        #[cfg(not(all(not(debug_assertions), feature = "analytics")))]
+        let analytics = false;
+
+        let options = InstanceOptions {
+            master_key: opt.master_key.as_ref().map(|_| REDACTED),
+            restricted_keys: opt.restricted_keys.as_ref().map_or(0, RestrictedKeys::len),
+            analytics,
+            analytics_sink: opt.analytics_sink.as_ref().map(|sink| match sink {
+                AnalyticsSink::File(path) => path.display().to_string(),
+                AnalyticsSink::Endpoint(url) => url.clone(),
+            }),
+            analytics_heartbeat: !opt.no_analytics_heartbeat,
+            analytics_search: !opt.no_analytics_search,
+            analytics_user_email: opt.analytics_user_email,
+            shadow_url: opt.shadow_url.clone(),
+            shadow_api_key: opt.shadow_api_key.as_ref().map(|_| REDACTED),
+            shadow_traffic_percentage: opt.shadow_traffic_percentage,
+            task_webhook_url: opt.task_webhook_url.clone(),
+            task_webhook_authorization: opt.task_webhook_authorization.as_ref().map(|_| REDACTED),
+            max_index_size: opt.max_index_size.get_bytes(),
+            max_udb_size: opt.max_udb_size.get_bytes(),
+            http_payload_size_limit: opt.http_payload_size_limit.get_bytes(),
+            compat_api_version: opt.compat_api_version,
+            max_fields_per_document: opt.max_fields_per_document,
+            max_document_size: opt.max_document_size.map(|size| size.get_bytes()),
+            max_facet_cardinality: opt.max_facet_cardinality,
+            max_documents_per_index: opt.max_documents_per_index,
+            max_documents: opt.max_documents,
+            deterministic_indexing: opt.deterministic_indexing,
+            max_search_depth: opt.max_search_depth,
+            max_search_limit: opt.max_search_limit,
+            max_search_facets: opt.max_search_facets,
+            max_attributes_to_retrieve: opt.max_attributes_to_retrieve,
+            max_batch_size: opt.batch_size(),
+            ssl_enabled: opt.ssl_cert_path.is_some(),
+            ssl_require_auth: opt.ssl_require_auth,
+            import_snapshot: opt.import_snapshot.clone(),
+            snapshot_dir: opt.snapshot_dir.clone(),
+            schedule_snapshot: opt.schedule_snapshot,
+            snapshot_interval_sec: opt.snapshot_interval_sec,
+            snapshot_io_throttle_mbps: opt.snapshot_io_throttle_mbps.map(NonZeroU64::get),
+            bloat_check_interval_sec: opt.bloat_check_interval_sec,
+            task_retention_days: opt.task_retention_days.map(NonZeroU64::get),
+            max_concurrent_searches: opt.max_concurrent_searches,
+            search_queue_timeout_ms: opt.search_queue_timeout_ms,
+            dumps_dir: opt.dumps_dir.clone(),
+            import_dump: opt.import_dump.clone(),
+            dump_import_jobs: opt.dump_import_jobs.map(NonZeroUsize::get),
+            import_dump_prefix: opt.import_dump_prefix.clone(),
+            update_archive_dir: opt.update_archive_dir.clone(),
+            replay_archive_until: opt.replay_archive_until,
+            warmup: opt.warmup,
+            log_level: opt.log_level.clone(),
+            log_format: opt.log_format,
+            indexing_memory: opt.indexer_options.max_memory.to_string(),
+            indexing_threads: opt.indexing_threads(),
+            http_workers: opt.http_workers.map(NonZeroUsize::get),
+            runtime_worker_threads: opt.runtime_worker_threads.map(NonZeroUsize::get),
+            max_blocking_threads: opt.max_blocking_threads.map(NonZeroUsize::get),
+        };
+
+        Self {
+            commit_sha: option_env!("VERGEN_GIT_SHA").unwrap_or("unknown"),
+            commit_date: option_env!("VERGEN_GIT_COMMIT_TIMESTAMP").unwrap_or("unknown"),
+            pkg_version: env!("CARGO_PKG_VERSION"),
+            db_path: opt.db_path.clone(),
+            db_in_memory: opt.db_in_memory,
+            http_addr: opt.http_addr.clone(),
+            env: opt.env.clone(),
+            features,
+            options,
+            deprecations: DEPRECATIONS,
+        }
+    }
+}
+
+pub async fn get_instance(instance: GuardedData<Admin, InstanceInfo>) -> HttpResponse {
+    HttpResponse::Ok().json(&*instance)
+}
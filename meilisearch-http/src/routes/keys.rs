@@ -0,0 +1,89 @@
+use actix_web::{web, HttpResponse};
+use log::debug;
+use meilisearch_lib::index_controller::api_keys::{ApiKey, CreateApiKey, PatchApiKey};
+use meilisearch_lib::MeiliSearch;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ResponseError;
+use crate::extractors::authentication::{policies::*, GuardedData};
+use crate::ApiKeys;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("")
+            .route(web::get().to(list_keys))
+            .route(web::post().to(create_key)),
+    )
+    .service(
+        web::resource("/{key}")
+            .route(web::get().to(get_key))
+            .route(web::patch().to(update_key))
+            .route(web::delete().to(delete_key)),
+    );
+}
+
+#[derive(Deserialize)]
+pub struct KeyParam {
+    key: String,
+}
+
+#[derive(Serialize)]
+struct KeysResponse {
+    private: Option<String>,
+    public: Option<String>,
+    /// The keys of the API key store, from the most recently created one.
+    results: Vec<ApiKey>,
+}
+
+pub async fn list_keys(
+    api_keys: GuardedData<Admin, ApiKeys>,
+    meilisearch: GuardedData<Admin, MeiliSearch>,
+) -> Result<HttpResponse, ResponseError> {
+    let api_keys = (*api_keys).clone();
+    let results = meilisearch.list_api_keys().await?;
+    Ok(HttpResponse::Ok().json(&KeysResponse {
+        private: api_keys.private,
+        public: api_keys.public,
+        results,
+    }))
+}
+
+pub async fn create_key(
+    meilisearch: GuardedData<Admin, MeiliSearch>,
+    body: web::Json<CreateApiKey>,
+) -> Result<HttpResponse, ResponseError> {
+    debug!("called with params: {:?}", body);
+    let key = meilisearch.create_api_key(body.into_inner()).await?;
+    debug!("returns: {:?}", key);
+    Ok(HttpResponse::Created().json(key))
+}
+
+pub async fn get_key(
+    meilisearch: GuardedData<Admin, MeiliSearch>,
+    path: web::Path<KeyParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let key = meilisearch.get_api_key(path.into_inner().key).await?;
+    debug!("returns: {:?}", key);
+    Ok(HttpResponse::Ok().json(key))
+}
+
+pub async fn update_key(
+    meilisearch: GuardedData<Admin, MeiliSearch>,
+    path: web::Path<KeyParam>,
+    body: web::Json<PatchApiKey>,
+) -> Result<HttpResponse, ResponseError> {
+    debug!("called with params: {:?}", body);
+    let key = meilisearch
+        .update_api_key(path.into_inner().key, body.into_inner())
+        .await?;
+    debug!("returns: {:?}", key);
+    Ok(HttpResponse::Ok().json(key))
+}
+
+pub async fn delete_key(
+    meilisearch: GuardedData<Admin, MeiliSearch>,
+    path: web::Path<KeyParam>,
+) -> Result<HttpResponse, ResponseError> {
+    meilisearch.delete_api_key(path.into_inner().key).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -1,28 +1,59 @@
 use std::time::Duration;
 
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use chrono::{DateTime, Utc};
 use log::debug;
-use meilisearch_lib::index_controller::updates::status::{UpdateResult, UpdateStatus};
+use meilisearch_lib::index_controller::updates::status::{
+    TraceContext, UpdateResult, UpdateStatus,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use meilisearch_lib::index::{Settings, Unchecked};
 use meilisearch_lib::{MeiliSearch, Update};
 
 use crate::error::ResponseError;
 use crate::extractors::authentication::{policies::*, GuardedData};
-use crate::ApiKeys;
+use crate::Opt;
 
+pub use compat::CompatApiVersion;
+pub use instance::InstanceInfo;
+pub use tasks::TaskView;
+
+mod aliases;
+mod archive;
+mod compat;
+#[cfg(target_os = "linux")]
+mod debug;
 mod dump;
 mod indexes;
-
-pub fn configure(cfg: &mut web::ServiceConfig) {
+mod instance;
+mod keys;
+mod multi_search;
+mod rollover;
+mod routing;
+mod tasks;
+mod webhooks;
+
+pub fn configure(cfg: &mut web::ServiceConfig, opt: &Opt) {
+    let compat_api_version = opt.compat_api_version;
     cfg.service(web::resource("/health").route(web::get().to(get_health)))
+        .service(web::scope("/aliases").configure(aliases::configure))
+        .service(web::scope("/archive").configure(archive::configure))
         .service(web::scope("/dumps").configure(dump::configure))
-        .service(web::resource("/keys").route(web::get().to(list_keys)))
+        .service(web::scope("/documents").configure(routing::configure))
+        .service(web::scope("/instance").configure(instance::configure))
+        .service(web::scope("/keys").configure(keys::configure))
+        .service(web::scope("/multi-search").configure(multi_search::configure))
+        .service(web::scope("/rollover").configure(rollover::configure))
         .service(web::resource("/stats").route(web::get().to(get_stats)))
+        .service(web::scope("/tasks").configure(tasks::configure))
         .service(web::resource("/version").route(web::get().to(get_version)))
-        .service(web::scope("/indexes").configure(indexes::configure));
+        .service(web::scope("/webhooks").configure(webhooks::configure))
+        .service(web::scope("/indexes").configure(|s| indexes::configure(s, compat_api_version)));
+
+    #[cfg(target_os = "linux")]
+    cfg.service(web::scope("/debug").configure(debug::configure));
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -76,6 +107,16 @@ impl From<&UpdateStatus> for UpdateType {
             Update::DeleteDocuments(ids) => UpdateType::DocumentsDeletion {
                 number: Some(ids.len()),
             },
+            Update::DeleteByFilter { .. } => {
+                let number = match other {
+                    UpdateStatus::Processed(processed) => match processed.success {
+                        UpdateResult::DocumentDeletion { deleted } => Some(deleted as usize),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                UpdateType::DocumentsDeletion { number }
+            }
         }
     }
 }
@@ -89,6 +130,14 @@ pub struct ProcessedUpdateResult {
     pub duration: f64, // in seconds
     pub enqueued_at: DateTime<Utc>,
     pub processed_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<TraceContext>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_of: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -102,6 +151,12 @@ pub struct FailedUpdateResult {
     pub duration: f64, // in seconds
     pub enqueued_at: DateTime<Utc>,
     pub processed_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<TraceContext>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_of: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -113,6 +168,31 @@ pub struct EnqueuedUpdateResult {
     pub enqueued_at: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub started_processing_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<TraceContext>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_of: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CanceledUpdateResult {
+    pub update_id: u64,
+    #[serde(rename = "type")]
+    pub update_type: UpdateType,
+    pub enqueued_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_processing_at: Option<DateTime<Utc>>,
+    pub canceled_at: DateTime<Utc>,
+    pub canceled_by: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<TraceContext>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_of: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -134,11 +214,18 @@ pub enum UpdateStatusResponse {
         #[serde(flatten)]
         content: ProcessedUpdateResult,
     },
+    Canceled {
+        #[serde(flatten)]
+        content: CanceledUpdateResult,
+    },
 }
 
 impl From<UpdateStatus> for UpdateStatusResponse {
     fn from(other: UpdateStatus) -> Self {
         let update_type = UpdateType::from(&other);
+        let metadata = other.metadata().cloned();
+        let trace = other.trace().cloned();
+        let retry_of = other.retry_of();
 
         match other {
             UpdateStatus::Processing(processing) => {
@@ -147,6 +234,9 @@ impl From<UpdateStatus> for UpdateStatusResponse {
                     update_type,
                     enqueued_at: processing.from.enqueued_at,
                     started_processing_at: Some(processing.started_processing_at),
+                    metadata,
+                    trace,
+                    retry_of,
                 };
                 UpdateStatusResponse::Processing { content }
             }
@@ -156,6 +246,9 @@ impl From<UpdateStatus> for UpdateStatusResponse {
                     update_type,
                     enqueued_at: enqueued.enqueued_at,
                     started_processing_at: None,
+                    metadata,
+                    trace,
+                    retry_of,
                 };
                 UpdateStatusResponse::Enqueued { content }
             }
@@ -174,10 +267,28 @@ impl From<UpdateStatus> for UpdateStatusResponse {
                     duration,
                     enqueued_at: processed.from.from.enqueued_at,
                     processed_at: processed.processed_at,
+                    warnings: processed.warnings,
+                    metadata,
+                    trace,
+                    retry_of,
                 };
                 UpdateStatusResponse::Processed { content }
             }
             UpdateStatus::Aborted(_) => unreachable!(),
+            UpdateStatus::Canceled(canceled) => {
+                let content = CanceledUpdateResult {
+                    update_id: canceled.id(),
+                    update_type,
+                    enqueued_at: canceled.from.enqueued_at,
+                    started_processing_at: canceled.started_processing_at,
+                    canceled_at: canceled.canceled_at,
+                    canceled_by: canceled.canceled_by,
+                    metadata,
+                    trace,
+                    retry_of,
+                };
+                UpdateStatusResponse::Canceled { content }
+            }
             UpdateStatus::Failed(failed) => {
                 let duration = failed
                     .failed_at
@@ -199,6 +310,9 @@ impl From<UpdateStatus> for UpdateStatusResponse {
                     duration,
                     enqueued_at,
                     processed_at,
+                    metadata,
+                    trace,
+                    retry_of,
                 };
                 UpdateStatusResponse::Failed { content }
             }
@@ -261,22 +375,11 @@ async fn get_version(_meilisearch: GuardedData<Private, MeiliSearch>) -> HttpRes
     })
 }
 
-#[derive(Serialize)]
-struct KeysResponse {
-    private: Option<String>,
-    public: Option<String>,
-}
-
-pub async fn list_keys(meilisearch: GuardedData<Admin, ApiKeys>) -> HttpResponse {
-    let api_keys = (*meilisearch).clone();
-    HttpResponse::Ok().json(&KeysResponse {
-        private: api_keys.private,
-        public: api_keys.public,
-    })
-}
-
-pub async fn get_health() -> Result<HttpResponse, ResponseError> {
-    Ok(HttpResponse::Ok().json(serde_json::json!({ "status": "available" })))
+pub async fn get_health(req: HttpRequest) -> Result<HttpResponse, ResponseError> {
+    let warmup = req
+        .app_data::<MeiliSearch>()
+        .map(MeiliSearch::warmup_status);
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "status": "available", "warmup": warmup })))
 }
 
 #[cfg(test)]
@@ -302,6 +405,7 @@ mod test {
     impl_is_policy! {A B}
     impl_is_policy! {A B C}
     impl_is_policy! {A B C D}
+    impl_is_policy! {A B C D E}
 
     /// Emits a compile error if a route doesn't have the correct authentication policy.
     ///
@@ -317,9 +421,18 @@ mod test {
     }
 
     test_auth_routes! {
-        Public => {
+        Search => {
             indexes::search::search_with_url_query,
             indexes::search::search_with_post,
+            indexes::search::scroll_search,
+            indexes::facet_search::facet_search,
+
+            multi_search::multi_search,
+        }
+        Public => {
+            indexes::search::export_search,
+
+            indexes::aggregate::aggregate,
 
             indexes::documents::get_document,
             indexes::documents::get_all_documents,
@@ -336,6 +449,36 @@ mod test {
             indexes::get_index,
 
             dump::create_dump,
+            dump::get_import_report,
+            dump::inspect_dump,
+
+            aliases::list_aliases,
+            aliases::get_alias,
+            aliases::put_alias,
+            aliases::delete_alias,
+
+            rollover::list_rollover_policies,
+            rollover::get_rollover_policy,
+            rollover::put_rollover_policy,
+            rollover::delete_rollover_policy,
+
+            routing::add_routed_documents,
+            routing::update_routed_documents,
+
+            tasks::list_tasks,
+            tasks::get_task,
+            tasks::wait_task,
+
+            compat::list_updates,
+            compat::get_settings,
+            compat::update_settings,
+            compat::get_ranking_rules,
+            compat::update_ranking_rules,
+            compat::get_attributes_for_faceting,
+            compat::update_attributes_for_faceting,
+            compat::delete_attributes_for_faceting,
+            tasks::cancel_tasks,
+            tasks::delete_tasks,
 
             indexes::settings::filterable_attributes::get,
             indexes::settings::displayed_attributes::get,
@@ -355,19 +498,59 @@ mod test {
             indexes::settings::stop_words::delete,
             indexes::settings::synonyms::delete,
             indexes::settings::distinct_attribute::delete,
+            indexes::settings::date_attributes::get,
+            indexes::settings::date_attributes::update,
+            indexes::settings::date_attributes::delete,
+            indexes::settings::flatten_strategy::get,
+            indexes::settings::flatten_strategy::update,
+            indexes::settings::flatten_strategy::delete,
+            indexes::settings::sort_collation::get,
+            indexes::settings::sort_collation::update,
+            indexes::settings::sort_collation::delete,
+            indexes::settings::normalized_filterable_attributes::get,
+            indexes::settings::normalized_filterable_attributes::update,
+            indexes::settings::normalized_filterable_attributes::delete,
+            indexes::settings::searchable_numeric_attributes::get,
+            indexes::settings::searchable_numeric_attributes::update,
+            indexes::settings::searchable_numeric_attributes::delete,
+            indexes::settings::raw_attributes::get,
+            indexes::settings::raw_attributes::update,
+            indexes::settings::raw_attributes::delete,
+            indexes::settings::checkpoint_before_settings_change::get,
+            indexes::settings::checkpoint_before_settings_change::update,
+            indexes::settings::checkpoint_before_settings_change::delete,
+            indexes::settings::typo_tolerance::get,
+            indexes::settings::typo_tolerance::update,
+            indexes::settings::typo_tolerance::delete,
+            indexes::settings::search_defaults::get,
+            indexes::settings::search_defaults::update,
+            indexes::settings::search_defaults::delete,
             indexes::settings::delete_all,
             indexes::settings::get_all,
             indexes::settings::update_all,
 
             indexes::documents::clear_all_documents,
             indexes::documents::delete_documents,
+            indexes::documents::delete_documents_by_filter,
             indexes::documents::update_documents,
             indexes::documents::add_documents,
             indexes::documents::delete_document,
 
-            indexes::updates::get_all_updates_status,
             indexes::updates::get_update_status,
+            indexes::updates::wait_updates,
+            indexes::updates::retry_update,
+
+            indexes::checkpoints::list_checkpoints,
+            indexes::checkpoints::restore_checkpoint,
+        }
+        Admin => {
+            keys::list_keys,
+            keys::create_key,
+            keys::get_key,
+            keys::update_key,
+            keys::delete_key,
+            instance::get_instance,
+            indexes::updates::get_update_payload,
         }
-        Admin => { list_keys, }
     }
 }
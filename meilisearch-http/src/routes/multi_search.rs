@@ -0,0 +1,51 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use log::debug;
+use meilisearch_lib::index_controller::multi_search::IndexSearchQuery;
+use meilisearch_lib::MeiliSearch;
+use serde::{Deserialize, Serialize};
+
+use crate::analytics::{Analytics, SearchEvent};
+use crate::error::ResponseError;
+use crate::extractors::authentication::{policies::*, GuardedData};
+use crate::extractors::key_restrictions::SearchRestrictions;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("").route(web::post().to(multi_search)));
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MultiSearchQuery {
+    queries: Vec<IndexSearchQuery>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MultiSearchResult<T> {
+    results: Vec<T>,
+}
+
+pub async fn multi_search(
+    meilisearch: GuardedData<Search, MeiliSearch>,
+    req: HttpRequest,
+    params: web::Json<MultiSearchQuery>,
+    analytics: web::Data<Analytics>,
+) -> Result<HttpResponse, ResponseError> {
+    debug!("multi-search called with params: {:?}", params);
+    let mut queries = params.into_inner().queries;
+    for query in &mut queries {
+        SearchRestrictions::for_index(&req, &query.index_uid)?.apply(&mut query.query);
+    }
+
+    let search_events: Vec<_> = queries
+        .iter()
+        .map(|query| SearchEvent::new(&query.query))
+        .collect();
+    let results = meilisearch.multi_search(queries).await?;
+    for (search_event, result) in search_events.into_iter().zip(&results) {
+        analytics.search(search_event, &result.result);
+    }
+
+    debug!("returns: {:?}", results);
+    Ok(HttpResponse::Ok().json(MultiSearchResult { results }))
+}
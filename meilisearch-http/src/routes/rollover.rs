@@ -0,0 +1,66 @@
+use actix_web::{web, HttpResponse};
+use log::debug;
+use meilisearch_lib::index_controller::rollover::RolloverPolicy;
+use meilisearch_lib::MeiliSearch;
+use serde::Deserialize;
+
+use crate::error::ResponseError;
+use crate::extractors::authentication::{policies::*, GuardedData};
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("").route(web::get().to(list_rollover_policies)))
+        .service(
+            web::resource("/{name}")
+                .route(web::get().to(get_rollover_policy))
+                .route(web::put().to(put_rollover_policy))
+                .route(web::delete().to(delete_rollover_policy)),
+        );
+}
+
+#[derive(Deserialize)]
+pub struct RolloverParam {
+    name: String,
+}
+
+pub async fn list_rollover_policies(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+) -> Result<HttpResponse, ResponseError> {
+    let policies = meilisearch.list_rollover_policies().await?;
+    debug!("returns: {:?}", policies);
+    Ok(HttpResponse::Ok().json(policies))
+}
+
+pub async fn get_rollover_policy(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    path: web::Path<RolloverParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let policy = meilisearch
+        .get_rollover_policy(path.into_inner().name)
+        .await?;
+    debug!("returns: {:?}", policy);
+    Ok(HttpResponse::Ok().json(policy))
+}
+
+pub async fn put_rollover_policy(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    path: web::Path<RolloverParam>,
+    body: web::Json<RolloverPolicy>,
+) -> Result<HttpResponse, ResponseError> {
+    debug!("called with params: {:?}", body);
+    let policy = body.into_inner();
+    meilisearch
+        .put_rollover_policy(path.into_inner().name, policy.clone())
+        .await?;
+    debug!("returns: {:?}", policy);
+    Ok(HttpResponse::Ok().json(policy))
+}
+
+pub async fn delete_rollover_policy(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    path: web::Path<RolloverParam>,
+) -> Result<HttpResponse, ResponseError> {
+    meilisearch
+        .delete_rollover_policy(path.into_inner().name)
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+
+use actix_web::{web, HttpResponse};
+use log::debug;
+use meilisearch_lib::index::Document;
+use meilisearch_lib::index_controller::routing::DocumentRouting;
+use meilisearch_lib::milli::update::IndexDocumentsMethod;
+use meilisearch_lib::MeiliSearch;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::error::ResponseError;
+use crate::extractors::authentication::{policies::*, GuardedData};
+use crate::extractors::update_metadata::UpdateMetadata;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("/route")
+            .route(web::post().to(add_routed_documents))
+            .route(web::put().to(update_routed_documents)),
+    );
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutedDocuments {
+    #[serde(flatten)]
+    routing: DocumentRouting,
+    documents: Vec<Document>,
+}
+
+pub async fn add_routed_documents(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    body: web::Json<RoutedDocuments>,
+    metadata: UpdateMetadata,
+) -> Result<HttpResponse, ResponseError> {
+    route_documents(
+        meilisearch,
+        body.into_inner(),
+        IndexDocumentsMethod::ReplaceDocuments,
+        metadata,
+    )
+    .await
+}
+
+pub async fn update_routed_documents(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    body: web::Json<RoutedDocuments>,
+    metadata: UpdateMetadata,
+) -> Result<HttpResponse, ResponseError> {
+    route_documents(
+        meilisearch,
+        body.into_inner(),
+        IndexDocumentsMethod::UpdateDocuments,
+        metadata,
+    )
+    .await
+}
+
+async fn route_documents(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    body: RoutedDocuments,
+    method: IndexDocumentsMethod,
+    metadata: UpdateMetadata,
+) -> Result<HttpResponse, ResponseError> {
+    debug!("called with routing: {:?}", body.routing);
+    let updates = meilisearch
+        .route_documents(body.routing, body.documents, method, metadata.into_inner())
+        .await?;
+
+    let updates: BTreeMap<_, _> = updates
+        .into_iter()
+        .map(|(uid, update)| (uid, json!({ "updateId": update.id() })))
+        .collect();
+    debug!("returns: {:?}", updates);
+    Ok(HttpResponse::Accepted().json(json!({ "updates": updates })))
+}
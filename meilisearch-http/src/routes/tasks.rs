@@ -0,0 +1,295 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use log::debug;
+use meilisearch_lib::index_controller::updates::status::StatusKind;
+use meilisearch_lib::index_controller::updates::UpdateKind;
+use meilisearch_lib::index_controller::{Task, TasksQuery};
+use meilisearch_lib::MeiliSearch;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{MeilisearchHttpError, ResponseError};
+use crate::extractors::authentication::{policies::*, GuardedData};
+use crate::routes::indexes::updates::{parse_update_ids, DEFAULT_WAIT_TIMEOUT_MS};
+use crate::routes::UpdateStatusResponse;
+
+const DEFAULT_TASKS_LIMIT: usize = 20;
+const MAX_TASKS_LIMIT: usize = 1000;
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("")
+            .route(web::get().to(list_tasks))
+            .route(web::delete().to(delete_tasks)),
+    )
+    .service(web::resource("/cancel").route(web::post().to(cancel_tasks)))
+    .service(web::resource("/{task_uid}").route(web::get().to(get_task)))
+    .service(web::resource("/{task_uid}/wait").route(web::get().to(wait_task)));
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ListTasksQuery {
+    /// The comma separated uids of the tasks to list.
+    uids: Option<String>,
+    /// The comma separated uids of the indexes whose tasks are listed, or patterns such as
+    /// `logs-2021-*`, `*` matching any sequence of characters.
+    index_uid: Option<String>,
+    /// The comma separated types of the tasks to list, such as `DocumentsAddition`.
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    /// The comma separated statuses of the tasks to list, such as `enqueued`.
+    status: Option<String>,
+    after_enqueued_at: Option<DateTime<Utc>>,
+    before_enqueued_at: Option<DateTime<Utc>>,
+    after_finished_at: Option<DateTime<Utc>>,
+    before_finished_at: Option<DateTime<Utc>>,
+    /// The uid of the first task of the page, the `next` of the previous page.
+    from: Option<u64>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct WaitTaskQuery {
+    /// How long to hold the request, in milliseconds, capped to a minute.
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct TaskParam {
+    task_uid: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskView {
+    uid: u64,
+    index_uid: String,
+    #[serde(flatten)]
+    status: UpdateStatusResponse,
+}
+
+impl From<Task> for TaskView {
+    fn from(task: Task) -> Self {
+        Self {
+            uid: task.uid,
+            index_uid: task.index_uid,
+            status: task.status.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TasksView {
+    results: Vec<TaskView>,
+    limit: usize,
+    from: Option<u64>,
+    next: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CancelTasksQuery {
+    /// The comma separated ids of the updates to cancel.
+    uids: Option<String>,
+    /// The comma separated uids or patterns of the indexes whose updates are canceled.
+    index_uids: Option<String>,
+    /// The comma separated statuses of the updates to cancel, `enqueued` and `processing`.
+    statuses: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeleteTasksQuery {
+    /// The comma separated statuses of the updates to delete, `processed`, `failed` or `canceled`.
+    statuses: Option<String>,
+    /// The comma separated types of the updates to delete, such as `DocumentsAddition`.
+    types: Option<String>,
+    /// The comma separated uids or patterns of the indexes whose updates are deleted.
+    index_uids: Option<String>,
+    before_finished_at: Option<DateTime<Utc>>,
+}
+
+fn parse_index_uids(uids: String) -> Vec<String> {
+    uids.split(',').map(|uid| uid.trim().to_string()).collect()
+}
+
+fn parse_statuses(statuses: &str) -> Result<HashSet<StatusKind>, MeilisearchHttpError> {
+    statuses
+        .split(',')
+        .map(|status| match status.trim() {
+            "enqueued" => Ok(StatusKind::Enqueued),
+            "processing" => Ok(StatusKind::Processing),
+            "processed" => Ok(StatusKind::Processed),
+            "failed" => Ok(StatusKind::Failed),
+            "aborted" => Ok(StatusKind::Aborted),
+            "canceled" => Ok(StatusKind::Canceled),
+            _ => Err(MeilisearchHttpError::InvalidTaskStatuses(
+                statuses.to_string(),
+            )),
+        })
+        .collect()
+}
+
+/// The statuses of the finished updates, the only ones that can be deleted.
+fn parse_finished_statuses(statuses: &str) -> Result<HashSet<StatusKind>, MeilisearchHttpError> {
+    statuses
+        .split(',')
+        .map(|status| match status.trim() {
+            "processed" => Ok(StatusKind::Processed),
+            "failed" => Ok(StatusKind::Failed),
+            "canceled" => Ok(StatusKind::Canceled),
+            _ => Err(MeilisearchHttpError::InvalidDeleteStatuses(
+                statuses.to_string(),
+            )),
+        })
+        .collect()
+}
+
+fn parse_types(types: &str) -> Result<HashSet<UpdateKind>, MeilisearchHttpError> {
+    types
+        .split(',')
+        .map(|name| match name.trim() {
+            "ClearAll" => Ok(UpdateKind::ClearAll),
+            "DocumentsAddition" => Ok(UpdateKind::DocumentsAddition),
+            "DocumentsPartial" => Ok(UpdateKind::DocumentsPartial),
+            "DocumentsDeletion" => Ok(UpdateKind::DocumentsDeletion),
+            "Settings" => Ok(UpdateKind::Settings),
+            _ => Err(MeilisearchHttpError::InvalidUpdateTypes(types.to_string())),
+        })
+        .collect()
+}
+
+/// Whether the enqueued and the processing updates are canceled.
+fn parse_cancel_statuses(statuses: &str) -> Result<(bool, bool), MeilisearchHttpError> {
+    let (mut enqueued, mut processing) = (false, false);
+    for status in statuses.split(',') {
+        match status.trim() {
+            "enqueued" => enqueued = true,
+            "processing" => processing = true,
+            _ => {
+                return Err(MeilisearchHttpError::InvalidCancelStatuses(
+                    statuses.to_string(),
+                ))
+            }
+        }
+    }
+    Ok((enqueued, processing))
+}
+
+pub async fn list_tasks(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    params: web::Query<ListTasksQuery>,
+) -> Result<HttpResponse, ResponseError> {
+    debug!("called with params: {:?}", params);
+    let params = params.into_inner();
+    let query = TasksQuery {
+        uids: params
+            .uids
+            .as_deref()
+            .map(parse_update_ids)
+            .transpose()?
+            .map(|uids| uids.into_iter().collect()),
+        index_uids: params.index_uid.map(parse_index_uids),
+        statuses: params.status.as_deref().map(parse_statuses).transpose()?,
+        kinds: params.kind.as_deref().map(parse_types).transpose()?,
+        enqueued_after: params.after_enqueued_at,
+        enqueued_before: params.before_enqueued_at,
+        finished_after: params.after_finished_at,
+        finished_before: params.before_finished_at,
+    };
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_TASKS_LIMIT)
+        .min(MAX_TASKS_LIMIT);
+
+    let tasks = meilisearch.list_tasks(query, params.from, limit).await?;
+    let results: Vec<TaskView> = tasks.results.into_iter().map(TaskView::from).collect();
+    let tasks = TasksView {
+        from: results.first().map(|task| task.uid),
+        results,
+        limit,
+        next: tasks.next,
+    };
+    debug!("returns: {:?}", tasks);
+    Ok(HttpResponse::Ok().json(tasks))
+}
+
+pub async fn get_task(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    path: web::Path<TaskParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let task = TaskView::from(meilisearch.get_task(path.task_uid).await?);
+    debug!("returns: {:?}", task);
+    Ok(HttpResponse::Ok().json(task))
+}
+
+/// Returns the task once it is processed, failed or canceled, or when the timeout elapses, the
+/// `status` of the task telling which.
+pub async fn wait_task(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    path: web::Path<TaskParam>,
+    query: web::Query<WaitTaskQuery>,
+) -> Result<HttpResponse, ResponseError> {
+    let timeout = Duration::from_millis(query.timeout_ms.unwrap_or(DEFAULT_WAIT_TIMEOUT_MS));
+    let task = TaskView::from(meilisearch.wait_task(path.task_uid, timeout).await?);
+    debug!("returns: {:?}", task);
+    Ok(HttpResponse::Ok().json(task))
+}
+
+pub async fn cancel_tasks(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    query: web::Query<CancelTasksQuery>,
+) -> Result<HttpResponse, ResponseError> {
+    debug!("called with params: {:?}", query);
+    let query = query.into_inner();
+    if query.uids.is_none() && query.index_uids.is_none() && query.statuses.is_none() {
+        return Err(MeilisearchHttpError::MissingCancelFilters.into());
+    }
+
+    let update_ids = query.uids.as_deref().map(parse_update_ids).transpose()?;
+    let index_uids = query.index_uids.map(parse_index_uids);
+    let (enqueued, processing) = match query.statuses {
+        Some(ref statuses) => parse_cancel_statuses(statuses)?,
+        None => (true, true),
+    };
+
+    let cancellation = meilisearch
+        .cancel_updates(index_uids, update_ids, enqueued, processing)
+        .await?;
+    debug!("returns: {:?}", cancellation);
+    Ok(HttpResponse::Accepted().json(cancellation))
+}
+
+pub async fn delete_tasks(
+    meilisearch: GuardedData<Private, MeiliSearch>,
+    query: web::Query<DeleteTasksQuery>,
+) -> Result<HttpResponse, ResponseError> {
+    debug!("called with params: {:?}", query);
+    let query = query.into_inner();
+    if query.statuses.is_none()
+        && query.types.is_none()
+        && query.index_uids.is_none()
+        && query.before_finished_at.is_none()
+    {
+        return Err(MeilisearchHttpError::MissingDeleteFilters.into());
+    }
+
+    let statuses = query
+        .statuses
+        .as_deref()
+        .map(parse_finished_statuses)
+        .transpose()?;
+    let kinds = query.types.as_deref().map(parse_types).transpose()?;
+    let index_uids = query.index_uids.map(parse_index_uids);
+
+    let deletion = meilisearch
+        .delete_updates(index_uids, statuses, kinds, query.before_finished_at)
+        .await?;
+    debug!("returns: {:?}", deletion);
+    Ok(HttpResponse::Ok().json(deletion))
+}
@@ -0,0 +1,127 @@
+use std::collections::BTreeSet;
+
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use log::debug;
+use meilisearch_lib::index_controller::webhooks::{CreateWebhook, PatchWebhook, Webhook};
+use meilisearch_lib::MeiliSearch;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::ResponseError;
+use crate::extractors::authentication::{policies::*, GuardedData};
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::resource("")
+            .route(web::get().to(list_webhooks))
+            .route(web::post().to(create_webhook)),
+    )
+    .service(
+        web::resource("/{uid}")
+            .route(web::get().to(get_webhook))
+            .route(web::patch().to(update_webhook))
+            .route(web::delete().to(delete_webhook)),
+    );
+}
+
+#[derive(Deserialize)]
+pub struct WebhookParam {
+    uid: Uuid,
+}
+
+/// A webhook as returned by the routes, whose authorization is a secret that is only shown masked.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WebhookView {
+    uid: Uuid,
+    url: String,
+    authorization: Option<String>,
+    index_uid: Option<String>,
+    events: BTreeSet<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<Webhook> for WebhookView {
+    fn from(webhook: Webhook) -> Self {
+        Self {
+            uid: webhook.uid,
+            url: webhook.url,
+            authorization: webhook.authorization.as_deref().map(mask_authorization),
+            index_uid: webhook.index_uid,
+            events: webhook.events,
+            created_at: webhook.created_at,
+            updated_at: webhook.updated_at,
+        }
+    }
+}
+
+/// Masks the credentials of an `Authorization` header, only keeping its scheme, such as `Bearer`.
+fn mask_authorization(authorization: &str) -> String {
+    match authorization.split_once(' ') {
+        Some((scheme, _)) => format!("{} ***", scheme),
+        None => String::from("***"),
+    }
+}
+
+#[derive(Serialize)]
+struct WebhooksResponse {
+    /// The webhooks, from the oldest one.
+    results: Vec<WebhookView>,
+}
+
+pub async fn list_webhooks(
+    meilisearch: GuardedData<Admin, MeiliSearch>,
+) -> Result<HttpResponse, ResponseError> {
+    let results = meilisearch
+        .list_webhooks()
+        .await?
+        .into_iter()
+        .map(WebhookView::from)
+        .collect();
+    Ok(HttpResponse::Ok().json(&WebhooksResponse { results }))
+}
+
+pub async fn create_webhook(
+    meilisearch: GuardedData<Admin, MeiliSearch>,
+    body: web::Json<CreateWebhook>,
+) -> Result<HttpResponse, ResponseError> {
+    debug!("called with params: {:?}", body);
+    let webhook = meilisearch.create_webhook(body.into_inner()).await?;
+    let webhook = WebhookView::from(webhook);
+    debug!("returns: {:?}", webhook);
+    Ok(HttpResponse::Created().json(webhook))
+}
+
+pub async fn get_webhook(
+    meilisearch: GuardedData<Admin, MeiliSearch>,
+    path: web::Path<WebhookParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let webhook = meilisearch.get_webhook(path.into_inner().uid).await?;
+    let webhook = WebhookView::from(webhook);
+    debug!("returns: {:?}", webhook);
+    Ok(HttpResponse::Ok().json(webhook))
+}
+
+pub async fn update_webhook(
+    meilisearch: GuardedData<Admin, MeiliSearch>,
+    path: web::Path<WebhookParam>,
+    body: web::Json<PatchWebhook>,
+) -> Result<HttpResponse, ResponseError> {
+    debug!("called with params: {:?}", body);
+    let webhook = meilisearch
+        .update_webhook(path.into_inner().uid, body.into_inner())
+        .await?;
+    let webhook = WebhookView::from(webhook);
+    debug!("returns: {:?}", webhook);
+    Ok(HttpResponse::Ok().json(webhook))
+}
+
+pub async fn delete_webhook(
+    meilisearch: GuardedData<Admin, MeiliSearch>,
+    path: web::Path<WebhookParam>,
+) -> Result<HttpResponse, ResponseError> {
+    meilisearch.delete_webhook(path.into_inner().uid).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,155 @@
+//! The mirroring of a part of the live searches to a shadow instance, to validate an upgrade or a
+//! change of settings against the real traffic before switching to it. The searches are mirrored
+//! in the background once they are answered, the results of the shadow are discarded and only
+//! its latency and number of hits are logged next to the ones of the live search. The requests
+//! are forwarded as the clients sent them, for the shadow to interpret them as its own version
+//! does.
+
+#[cfg(feature = "shadow-traffic")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "shadow-traffic")]
+use std::sync::Arc;
+#[cfg(feature = "shadow-traffic")]
+use std::time::Instant;
+
+use actix_web::web::Bytes;
+#[cfg(not(feature = "shadow-traffic"))]
+use log::warn;
+#[cfg(feature = "shadow-traffic")]
+use log::{info, warn};
+use meilisearch_lib::index::{SearchQuery, SearchResult};
+
+use crate::Opt;
+
+/// The number of mirrored searches waiting for the shadow. The new ones are not mirrored once it
+/// is reached, for a slow shadow never to pile up requests.
+#[cfg(feature = "shadow-traffic")]
+const MAX_IN_FLIGHT_SEARCHES: usize = 64;
+
+/// A search as the client sent it.
+#[derive(Debug, Clone)]
+pub enum RawSearch {
+    /// The body of a `POST` search.
+    Body(Bytes),
+    /// The query string of a `GET` search.
+    QueryString(String),
+}
+
+#[derive(Clone, Default)]
+pub struct ShadowTraffic {
+    #[cfg(feature = "shadow-traffic")]
+    shadow: Option<Shadow>,
+}
+
+#[cfg(feature = "shadow-traffic")]
+#[derive(Clone)]
+struct Shadow {
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+    /// The percentage of the searches that are mirrored.
+    percentage: f64,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl ShadowTraffic {
+    pub fn new(opt: &Opt) -> Self {
+        match opt.shadow_url {
+            #[cfg(feature = "shadow-traffic")]
+            Some(ref url) => Self {
+                shadow: Some(Shadow {
+                    client: reqwest::Client::new(),
+                    url: url.trim_end_matches('/').to_string(),
+                    api_key: opt.shadow_api_key.clone(),
+                    percentage: opt.shadow_traffic_percentage,
+                    in_flight: Arc::new(AtomicUsize::new(0)),
+                }),
+            },
+            #[cfg(not(feature = "shadow-traffic"))]
+            Some(_) => {
+                warn!("The searches are not mirrored, the shadow traffic feature is disabled.");
+                Self::disabled()
+            }
+            None => Self::disabled(),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// Returns the search to mirror if the search `query`, sent as `raw`, is picked. The searches
+    /// of the restricted keys are not mirrored, their restrictions are only known by this
+    /// instance.
+    #[cfg(feature = "shadow-traffic")]
+    pub fn pick(&self, query: &SearchQuery, raw: RawSearch, restricted: bool) -> Option<RawSearch> {
+        let shadow = self.shadow.as_ref()?;
+        // The scrolls can't be mirrored, their cursors are only known by this instance, nor the
+        // searches waiting for an update of this instance, that are not the ones of the shadow.
+        if query.scroll || query.min_update_id.is_some() || restricted {
+            return None;
+        }
+        (rand::random::<f64>() * 100. < shadow.percentage).then(|| raw)
+    }
+
+    #[cfg(not(feature = "shadow-traffic"))]
+    pub fn pick(
+        &self,
+        _query: &SearchQuery,
+        _raw: RawSearch,
+        _restricted: bool,
+    ) -> Option<RawSearch> {
+        None
+    }
+
+    /// Sends the picked search of the index `index_uid` to the shadow, in the background, the
+    /// live search having been answered with `result`.
+    #[cfg(feature = "shadow-traffic")]
+    pub fn mirror(&self, index_uid: &str, search: RawSearch, result: &SearchResult) {
+        let shadow = match self.shadow {
+            Some(ref shadow) => shadow,
+            None => return,
+        };
+        if shadow.in_flight.fetch_add(1, Ordering::Relaxed) >= MAX_IN_FLIGHT_SEARCHES {
+            shadow.in_flight.fetch_sub(1, Ordering::Relaxed);
+            return;
+        }
+
+        let url = format!("{}/indexes/{}/search", shadow.url, index_uid);
+        let mut request = match search {
+            RawSearch::Body(body) => shadow
+                .client
+                .post(&url)
+                .header("Content-Type", "application/json")
+                .body(body),
+            RawSearch::QueryString(query) => shadow.client.get(format!("{}?{}", url, query)),
+        };
+        if let Some(ref api_key) = shadow.api_key {
+            request = request.header("X-Meili-API-Key", api_key);
+        }
+        let (live_ms, live_hits) = (result.processing_time_ms, result.nb_hits);
+        let index_uid = index_uid.to_string();
+        let in_flight = shadow.in_flight.clone();
+
+        tokio::spawn(async move {
+            let start = Instant::now();
+            let response = match request.send().await.and_then(|r| r.error_for_status()) {
+                Ok(response) => response.json::<serde_json::Value>().await,
+                Err(e) => Err(e),
+            };
+            let elapsed = start.elapsed().as_millis();
+            in_flight.fetch_sub(1, Ordering::Relaxed);
+
+            match response {
+                Ok(body) => info!(
+                    "Shadow search on `{}`: {} hits in {}ms ({}ms of processing), live search: {} hits in {}ms.",
+                    index_uid, body["nbHits"], elapsed, body["processingTimeMs"], live_hits, live_ms,
+                ),
+                Err(e) => warn!("Shadow search on `{}` failed: {}", index_uid, e),
+            }
+        });
+    }
+
+    #[cfg(not(feature = "shadow-traffic"))]
+    pub fn mirror(&self, _index_uid: &str, _search: RawSearch, _result: &SearchResult) {}
+}
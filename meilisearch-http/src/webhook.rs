@@ -0,0 +1,237 @@
+//! The notifications of the finished tasks, POSTed as JSON to the `--task-webhook-url` and to the
+//! webhooks registered on `/webhooks` for the indexing pipelines to react to them instead of
+//! polling `/tasks`. Each webhook is notified by its own delivery, for a slow webhook not to delay
+//! the others, one task at a time in the order of their uids. A delivery resumes after the last
+//! task it notified, which is saved with the webhooks. A notification failing is retried a few
+//! times before it is dropped.
+
+#[cfg(feature = "task-webhook")]
+use std::collections::HashSet;
+#[cfg(feature = "task-webhook")]
+use std::time::Duration;
+
+#[cfg(feature = "task-webhook")]
+use futures::StreamExt;
+#[cfg(not(feature = "task-webhook"))]
+use log::warn;
+#[cfg(feature = "task-webhook")]
+use log::{debug, warn};
+#[cfg(feature = "task-webhook")]
+use meilisearch_lib::index_controller::Task;
+use meilisearch_lib::MeiliSearch;
+#[cfg(feature = "task-webhook")]
+use uuid::Uuid;
+
+#[cfg(feature = "task-webhook")]
+use crate::routes::TaskView;
+use crate::Opt;
+
+/// The number of times a notification is sent before it is dropped.
+#[cfg(feature = "task-webhook")]
+const MAX_ATTEMPTS: u32 = 3;
+/// How long to wait before sending a failed notification again, doubled after each attempt.
+#[cfg(feature = "task-webhook")]
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+#[cfg(feature = "task-webhook")]
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// The number of finished tasks listed at once.
+#[cfg(feature = "task-webhook")]
+const BATCH_SIZE: usize = 100;
+/// How often the finished tasks are looked up when no task completes, and the registered
+/// webhooks are looked up for new ones.
+#[cfg(feature = "task-webhook")]
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// The name of the cursor of the `--task-webhook-url`, the registered webhooks using their uid.
+#[cfg(feature = "task-webhook")]
+const CURSOR: &str = "task-webhook-url";
+
+/// A webhook notified by a delivery.
+#[cfg(feature = "task-webhook")]
+#[derive(Clone)]
+enum Target {
+    /// The `--task-webhook-url`, notified of all the tasks.
+    Url {
+        url: String,
+        authorization: Option<String>,
+    },
+    /// A webhook registered on `/webhooks`, looked up before each batch of tasks for its changes
+    /// to apply, its delivery stopping once it is deleted.
+    Registered(Uuid),
+}
+
+#[cfg(feature = "task-webhook")]
+impl Target {
+    fn cursor(&self) -> String {
+        match self {
+            Target::Url { .. } => String::from(CURSOR),
+            Target::Registered(uid) => uid.to_string(),
+        }
+    }
+}
+
+/// Starts notifying the finished tasks to the webhook of `opt`, if there is one, and to the
+/// webhooks registered on `/webhooks` whose index and events match the tasks.
+#[cfg(feature = "task-webhook")]
+pub async fn setup_task_webhook(meilisearch: MeiliSearch, opt: &Opt) -> anyhow::Result<()> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+
+    if let Some(url) = opt.task_webhook_url.clone() {
+        let target = Target::Url {
+            url,
+            authorization: opt.task_webhook_authorization.clone(),
+        };
+        start_delivery(meilisearch.clone(), client.clone(), target).await?;
+    }
+
+    tokio::spawn(async move {
+        let mut delivered = HashSet::new();
+        loop {
+            match meilisearch.list_webhooks().await {
+                Ok(webhooks) => {
+                    let uids: HashSet<_> =
+                        webhooks.into_iter().map(|webhook| webhook.uid).collect();
+                    for uid in uids.difference(&delivered) {
+                        let target = Target::Registered(*uid);
+                        if let Err(e) =
+                            start_delivery(meilisearch.clone(), client.clone(), target).await
+                        {
+                            warn!("The webhook {} couldn't be started: {}", uid, e);
+                        }
+                    }
+                    // The deliveries of the deleted webhooks stop by themselves.
+                    delivered = uids;
+                }
+                Err(e) => warn!("The webhooks couldn't be listed: {}", e),
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+
+    Ok(())
+}
+
+/// Starts notifying the finished tasks to `target`, after the last task it was notified of, or
+/// from the tasks finishing from now on.
+#[cfg(feature = "task-webhook")]
+async fn start_delivery(
+    meilisearch: MeiliSearch,
+    client: reqwest::Client,
+    target: Target,
+) -> anyhow::Result<()> {
+    let mut cursor = match meilisearch.webhook_cursor(target.cursor()).await? {
+        Some(cursor) => Some(cursor),
+        None => meilisearch.last_finished_task_uid().await?,
+    };
+    let completions = meilisearch.task_completions().await?;
+
+    tokio::spawn(async move {
+        futures::pin_mut!(completions);
+        loop {
+            let webhook = match target {
+                Target::Url {
+                    ref url,
+                    ref authorization,
+                } => (url.clone(), authorization.clone(), None),
+                Target::Registered(uid) => match meilisearch.list_webhooks().await {
+                    Ok(webhooks) => match webhooks.into_iter().find(|webhook| webhook.uid == uid) {
+                        Some(webhook) => (
+                            webhook.url.clone(),
+                            webhook.authorization.clone(),
+                            Some(webhook),
+                        ),
+                        None => break,
+                    },
+                    Err(e) => {
+                        warn!("The webhook {} couldn't be looked up: {}", uid, e);
+                        tokio::time::sleep(POLL_INTERVAL).await;
+                        continue;
+                    }
+                },
+            };
+            let (url, authorization, registered) = webhook;
+
+            let (tasks, last) = match meilisearch.finished_tasks_after(cursor, BATCH_SIZE).await {
+                Ok(listed) => listed,
+                Err(e) => {
+                    warn!("The finished tasks couldn't be listed: {}", e);
+                    (Vec::new(), None)
+                }
+            };
+
+            match last {
+                Some(last) => {
+                    let tasks = tasks.into_iter().filter(|task| {
+                        registered
+                            .as_ref()
+                            .map_or(true, |webhook| webhook.matches(task))
+                    });
+                    for task in tasks {
+                        notify_task(&client, &url, authorization.as_deref(), task).await;
+                    }
+                    cursor = Some(last);
+                    if let Err(e) = meilisearch.put_webhook_cursor(target.cursor(), last).await {
+                        warn!("The cursor of the webhook {} couldn't be saved: {}", url, e);
+                    }
+                }
+                // The tasks canceled before they are processed don't send a completion, the
+                // finished tasks are also looked up now and then.
+                None => match tokio::time::timeout(POLL_INTERVAL, completions.next()).await {
+                    Ok(None) => break,
+                    Ok(Some(())) | Err(_) => (),
+                },
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(feature = "task-webhook")]
+async fn notify_task(client: &reqwest::Client, url: &str, authorization: Option<&str>, task: Task) {
+    let uid = task.uid;
+    let task = TaskView::from(task);
+    notify(client, url, authorization, uid, &task).await;
+}
+
+#[cfg(feature = "task-webhook")]
+async fn notify(
+    client: &reqwest::Client,
+    url: &str,
+    authorization: Option<&str>,
+    uid: u64,
+    task: &TaskView,
+) {
+    let mut delay = RETRY_DELAY;
+    for attempt in 1..=MAX_ATTEMPTS {
+        let mut request = client.post(url).json(task);
+        if let Some(authorization) = authorization {
+            request = request.header(reqwest::header::AUTHORIZATION, authorization);
+        }
+        match request.send().await.and_then(|r| r.error_for_status()) {
+            Ok(_) => {
+                debug!("Task {} notified to the webhook {}.", uid, url);
+                break;
+            }
+            Err(e) if attempt == MAX_ATTEMPTS => {
+                warn!(
+                    "Task {} couldn't be notified to the webhook {}: {}",
+                    uid, url, e
+                )
+            }
+            Err(_) => {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "task-webhook"))]
+pub async fn setup_task_webhook(meilisearch: MeiliSearch, opt: &Opt) -> anyhow::Result<()> {
+    if opt.task_webhook_url.is_some() || !meilisearch.list_webhooks().await?.is_empty() {
+        warn!("The finished tasks are not notified, the task webhook feature is disabled.");
+    }
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use serde_json::json;
+
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn aggregate_filtered_documents() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({"filterableAttributes": ["color", "price", "unknown"]}))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "color": "blue", "price": 10 },
+        { "id": 2, "color": "blue", "price": [20, 30] },
+        { "id": 3, "color": "red", "price": 100 },
+        { "id": 4, "color": "blue" },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = index
+        .aggregate(json!({ "filter": "color = blue", "fields": ["price", "unknown"] }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["count"], 3);
+    assert_eq!(response["fields"]["price"]["count"], 2);
+    assert_eq!(response["fields"]["price"]["sum"], 60.0);
+    assert_eq!(response["fields"]["price"]["min"], 10.0);
+    assert_eq!(response["fields"]["price"]["max"], 30.0);
+    assert_eq!(response["fields"]["price"]["avg"], 20.0);
+    assert_eq!(response["fields"]["unknown"]["count"], 0);
+    assert!(response["fields"]["unknown"]["avg"].is_null());
+}
+
+#[actix_rt::test]
+async fn aggregate_not_filterable_field() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .add_documents(json!([{ "id": 1, "price": 10 }]), None)
+        .await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = index.aggregate(json!({ "fields": ["price"] })).await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "invalid_facet");
+}
+
+#[actix_rt::test]
+async fn aggregate_unexisting_index() {
+    let server = Server::new().await;
+    let (response, code) = server.index("test").aggregate(json!({})).await;
+    assert_eq!(code, 404, "{}", response);
+}
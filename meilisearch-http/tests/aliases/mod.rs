@@ -0,0 +1,77 @@
+use serde_json::json;
+
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn search_through_alias() {
+    let server = Server::new().await;
+
+    for (uid, title) in [("products_v12", "old"), ("products_v13", "new")] {
+        let index = server.index(uid);
+        index
+            .add_documents(json!([{ "id": 1, "title": title }]), None)
+            .await;
+        index.wait_update_id(0).await;
+    }
+
+    let (response, code) = server
+        .service
+        .put("/aliases/products", json!({ "indexUid": "products_v12" }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(
+        response,
+        json!({ "alias": "products", "indexUid": "products_v12" })
+    );
+
+    server
+        .index("products")
+        .search(json!({ "q": "" }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert_eq!(response["hits"][0]["title"], "old");
+        })
+        .await;
+
+    // Repointing the alias.
+    let (_, code) = server
+        .service
+        .put("/aliases/products", json!({ "indexUid": "products_v13" }))
+        .await;
+    assert_eq!(code, 200);
+
+    server
+        .index("products")
+        .search(json!({ "q": "" }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert_eq!(response["hits"][0]["title"], "new");
+        })
+        .await;
+
+    let (response, code) = server.service.get("/aliases").await;
+    assert_eq!(code, 200);
+    assert_eq!(
+        response,
+        json!([{ "alias": "products", "indexUid": "products_v13" }])
+    );
+
+    // An index uid can't be an alias, and an alias can't point to a missing index.
+    let (_, code) = server
+        .service
+        .put(
+            "/aliases/products_v12",
+            json!({ "indexUid": "products_v13" }),
+        )
+        .await;
+    assert_eq!(code, 400);
+    let (_, code) = server
+        .service
+        .put("/aliases/items", json!({ "indexUid": "missing" }))
+        .await;
+    assert_eq!(code, 404);
+
+    let (_, code) = server.service.delete("/aliases/products").await;
+    assert_eq!(code, 204);
+    let (response, code) = server.service.get("/aliases/products").await;
+    assert_eq!(code, 404);
+    assert_eq!(response["errorCode"], "alias_not_found");
+}
@@ -7,6 +7,7 @@ use actix_web::http::StatusCode;
 use paste::paste;
 use serde_json::{json, Value};
 use tokio::time::sleep;
+use urlencoding::encode;
 
 use super::service::Service;
 
@@ -103,7 +104,10 @@ impl Index<'_> {
             let (response, status_code) = self.service.get(&url).await;
             assert_eq!(status_code, 200, "response: {}", response);
 
-            if response["status"] == "processed" || response["status"] == "failed" {
+            if response["status"] == "processed"
+                || response["status"] == "failed"
+                || response["status"] == "canceled"
+            {
                 return response;
             }
 
@@ -117,8 +121,8 @@ impl Index<'_> {
         self.service.get(url).await
     }
 
-    pub async fn list_updates(&self) -> (Value, StatusCode) {
-        let url = format!("/indexes/{}/updates", self.uid);
+    pub async fn list_tasks(&self) -> (Value, StatusCode) {
+        let url = format!("/tasks?indexUid={}", self.uid);
         self.service.get(url).await
     }
 
@@ -148,6 +152,18 @@ impl Index<'_> {
             ));
         }
 
+        if let Some(fields) = options.fields {
+            url.push_str(&format!("fields={}&", fields.join(",")));
+        }
+
+        if let Some(filter) = options.filter {
+            url.push_str(&format!("filter={}&", encode(filter)));
+        }
+
+        if let Some(sort) = options.sort {
+            url.push_str(&format!("sort={}&", encode(&sort.join(","))));
+        }
+
         self.service.get(url).await
     }
 
@@ -161,6 +177,11 @@ impl Index<'_> {
         self.service.delete(url).await
     }
 
+    pub async fn delete_by_filter(&self, filter: Value) -> (Value, StatusCode) {
+        let url = format!("/indexes/{}/documents/delete", self.uid);
+        self.service.post(url, json!({ "filter": filter })).await
+    }
+
     pub async fn delete_batch(&self, ids: Vec<u64>) -> (Value, StatusCode) {
         let url = format!("/indexes/{}/documents/delete-batch", self.uid);
         self.service
@@ -219,6 +240,16 @@ impl Index<'_> {
         self.service.get(url).await
     }
 
+    pub async fn aggregate(&self, query: Value) -> (Value, StatusCode) {
+        let url = format!("/indexes/{}/aggregate", self.uid);
+        self.service.post(url, query).await
+    }
+
+    pub async fn facet_search(&self, query: Value) -> (Value, StatusCode) {
+        let url = format!("/indexes/{}/facet-search", self.uid);
+        self.service.post(url, query).await
+    }
+
     make_settings_test_routes!(distinct_attribute);
 }
 
@@ -229,4 +260,7 @@ pub struct GetAllDocumentsOptions {
     pub limit: Option<usize>,
     pub offset: Option<usize>,
     pub attributes_to_retrieve: Option<Vec<&'static str>>,
+    pub fields: Option<Vec<&'static str>>,
+    pub filter: Option<&'static str>,
+    pub sort: Option<Vec<&'static str>>,
 }
@@ -1,7 +1,9 @@
+use std::num::NonZeroUsize;
 use std::path::Path;
 
 use actix_web::http::StatusCode;
 use byte_unit::{Byte, ByteUnit};
+use meilisearch_http::logging::LogFormat;
 use meilisearch_http::setup_meilisearch;
 use meilisearch_lib::options::{IndexerOpts, MaxMemory};
 use once_cell::sync::Lazy;
@@ -83,15 +85,38 @@ impl Server {
 pub fn default_settings(dir: impl AsRef<Path>) -> Opt {
     Opt {
         db_path: dir.as_ref().join("db"),
+        db_in_memory: false,
         dumps_dir: dir.as_ref().join("dump"),
         http_addr: "127.0.0.1:7700".to_owned(),
         master_key: None,
+        restricted_keys: None,
         env: "development".to_owned(),
         #[cfg(all(not(debug_assertions), feature = "analytics"))]
         no_analytics: true,
+        analytics_sink: None,
+        no_analytics_heartbeat: false,
+        no_analytics_search: false,
+        analytics_user_email: false,
+        shadow_url: None,
+        shadow_api_key: None,
+        shadow_traffic_percentage: 10.,
+        task_webhook_url: None,
+        task_webhook_authorization: None,
         max_index_size: Byte::from_unit(4.0, ByteUnit::GiB).unwrap(),
         max_udb_size: Byte::from_unit(4.0, ByteUnit::GiB).unwrap(),
         http_payload_size_limit: Byte::from_unit(10.0, ByteUnit::MiB).unwrap(),
+        compat_api_version: None,
+        max_fields_per_document: None,
+        max_document_size: None,
+        max_facet_cardinality: None,
+        max_documents_per_index: None,
+        max_documents: None,
+        deterministic_indexing: false,
+        max_search_depth: 10000,
+        max_search_limit: None,
+        max_search_facets: None,
+        max_attributes_to_retrieve: None,
+        max_batch_size: NonZeroUsize::new(100).unwrap(),
         ssl_cert_path: None,
         ssl_key_path: None,
         ssl_auth_path: None,
@@ -105,12 +130,27 @@ pub fn default_settings(dir: impl AsRef<Path>) -> Opt {
         snapshot_dir: ".".into(),
         schedule_snapshot: false,
         snapshot_interval_sec: 0,
+        snapshot_io_throttle_mbps: None,
         import_dump: None,
+        dump_import_jobs: None,
+        import_dump_prefix: None,
+        ignore_dump_import_errors: false,
+        update_archive_dir: None,
+        replay_archive_until: None,
+        warmup: Default::default(),
+        bloat_check_interval_sec: None,
+        task_retention_days: None,
+        max_concurrent_searches: None,
+        search_queue_timeout_ms: 1000,
         indexer_options: IndexerOpts {
             // memory has to be unlimited because several meilisearch are running in test context.
             max_memory: MaxMemory::unlimited(),
             ..Default::default()
         },
+        http_workers: None,
+        runtime_worker_threads: None,
+        max_blocking_threads: None,
         log_level: "off".into(),
+        log_format: LogFormat::Human,
     }
 }
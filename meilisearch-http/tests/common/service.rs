@@ -1,4 +1,5 @@
-use actix_web::{http::StatusCode, test};
+use actix_web::http::{HeaderMap, StatusCode};
+use actix_web::test;
 use meilisearch_lib::MeiliSearch;
 use serde_json::Value;
 
@@ -10,10 +11,19 @@ pub struct Service {
 }
 
 impl Service {
+    /// Authenticates the requests with the master key, if the server has one.
+    fn authenticate(&self, req: test::TestRequest) -> test::TestRequest {
+        match self.options.master_key {
+            Some(ref key) => req.insert_header(("X-Meili-API-Key", key.as_str())),
+            None => req,
+        }
+    }
+
     pub async fn post(&self, url: impl AsRef<str>, body: Value) -> (Value, StatusCode) {
         let app = test::init_service(create_app!(&self.meilisearch, true, &self.options)).await;
 
-        let req = test::TestRequest::post()
+        let req = self
+            .authenticate(test::TestRequest::post())
             .uri(url.as_ref())
             .set_json(&body)
             .to_request();
@@ -33,7 +43,8 @@ impl Service {
     ) -> (Value, StatusCode) {
         let app = test::init_service(create_app!(&self.meilisearch, true, &self.options)).await;
 
-        let req = test::TestRequest::post()
+        let req = self
+            .authenticate(test::TestRequest::post())
             .uri(url.as_ref())
             .set_payload(body.as_ref().to_string())
             .insert_header(("content-type", "application/json"))
@@ -46,10 +57,53 @@ impl Service {
         (response, status_code)
     }
 
+    /// Send a test post request with additional headers.
+    pub async fn post_with_headers(
+        &self,
+        url: impl AsRef<str>,
+        body: Value,
+        headers: &[(&str, &str)],
+    ) -> (Value, StatusCode) {
+        let app = test::init_service(create_app!(&self.meilisearch, true, &self.options)).await;
+
+        let mut req = self
+            .authenticate(test::TestRequest::post())
+            .uri(url.as_ref())
+            .set_json(&body);
+        for header in headers {
+            req = req.insert_header(*header);
+        }
+        let res = test::call_service(&app, req.to_request()).await;
+        let status_code = res.status();
+
+        let body = test::read_body(res).await;
+        let response = serde_json::from_slice(&body).unwrap_or_default();
+        (response, status_code)
+    }
+
+    /// Send a test post request, returning the body of the response as text.
+    pub async fn post_raw(&self, url: impl AsRef<str>, body: Value) -> (String, StatusCode) {
+        let app = test::init_service(create_app!(&self.meilisearch, true, &self.options)).await;
+
+        let req = self
+            .authenticate(test::TestRequest::post())
+            .uri(url.as_ref())
+            .set_json(&body)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let status_code = res.status();
+
+        let body = test::read_body(res).await;
+        (String::from_utf8_lossy(&body).into_owned(), status_code)
+    }
+
     pub async fn get(&self, url: impl AsRef<str>) -> (Value, StatusCode) {
         let app = test::init_service(create_app!(&self.meilisearch, true, &self.options)).await;
 
-        let req = test::TestRequest::get().uri(url.as_ref()).to_request();
+        let req = self
+            .authenticate(test::TestRequest::get())
+            .uri(url.as_ref())
+            .to_request();
         let res = test::call_service(&app, req).await;
         let status_code = res.status();
 
@@ -58,10 +112,84 @@ impl Service {
         (response, status_code)
     }
 
+    /// Send a test get request, returning the headers of the response along with its body.
+    pub async fn get_with_headers(&self, url: impl AsRef<str>) -> (Value, StatusCode, HeaderMap) {
+        let app = test::init_service(create_app!(&self.meilisearch, true, &self.options)).await;
+
+        let req = self
+            .authenticate(test::TestRequest::get())
+            .uri(url.as_ref())
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let status_code = res.status();
+        let headers = res.headers().clone();
+
+        let body = test::read_body(res).await;
+        let response = serde_json::from_slice(&body).unwrap_or_default();
+        (response, status_code, headers)
+    }
+
+    /// Send a test get request, returning the body of the response as text.
+    pub async fn get_raw(&self, url: impl AsRef<str>) -> (String, StatusCode) {
+        let app = test::init_service(create_app!(&self.meilisearch, true, &self.options)).await;
+
+        let req = self
+            .authenticate(test::TestRequest::get())
+            .uri(url.as_ref())
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let status_code = res.status();
+
+        let body = test::read_body(res).await;
+        (String::from_utf8_lossy(&body).into_owned(), status_code)
+    }
+
+    /// Send a test get request with an `Accept` header, returning the body of the response as text.
+    pub async fn get_accept(
+        &self,
+        url: impl AsRef<str>,
+        accept: &str,
+    ) -> (String, StatusCode, HeaderMap) {
+        let app = test::init_service(create_app!(&self.meilisearch, true, &self.options)).await;
+
+        let req = self
+            .authenticate(test::TestRequest::get())
+            .uri(url.as_ref())
+            .insert_header(("Accept", accept))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let status_code = res.status();
+        let headers = res.headers().clone();
+
+        let body = test::read_body(res).await;
+        (
+            String::from_utf8_lossy(&body).into_owned(),
+            status_code,
+            headers,
+        )
+    }
+
     pub async fn put(&self, url: impl AsRef<str>, body: Value) -> (Value, StatusCode) {
         let app = test::init_service(create_app!(&self.meilisearch, true, &self.options)).await;
 
-        let req = test::TestRequest::put()
+        let req = self
+            .authenticate(test::TestRequest::put())
+            .uri(url.as_ref())
+            .set_json(&body)
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let status_code = res.status();
+
+        let body = test::read_body(res).await;
+        let response = serde_json::from_slice(&body).unwrap_or_default();
+        (response, status_code)
+    }
+
+    pub async fn patch(&self, url: impl AsRef<str>, body: Value) -> (Value, StatusCode) {
+        let app = test::init_service(create_app!(&self.meilisearch, true, &self.options)).await;
+
+        let req = self
+            .authenticate(test::TestRequest::patch())
             .uri(url.as_ref())
             .set_json(&body)
             .to_request();
@@ -76,7 +204,10 @@ impl Service {
     pub async fn delete(&self, url: impl AsRef<str>) -> (Value, StatusCode) {
         let app = test::init_service(create_app!(&self.meilisearch, true, &self.options)).await;
 
-        let req = test::TestRequest::delete().uri(url.as_ref()).to_request();
+        let req = self
+            .authenticate(test::TestRequest::delete())
+            .uri(url.as_ref())
+            .to_request();
         let res = test::call_service(&app, req).await;
         let status_code = res.status();
 
@@ -1,7 +1,8 @@
+use crate::common::server::default_settings;
 use crate::common::{GetAllDocumentsOptions, Server};
 use actix_web::test;
 use chrono::DateTime;
-use meilisearch_http::create_app;
+use meilisearch_http::{create_app, Opt};
 use serde_json::{json, Value};
 
 /// This is the basic usage of our API and every other tests uses the content-type application/json
@@ -442,3 +443,189 @@ async fn update_documents_bad_primary_key() {
     assert_eq!(code, 200);
     assert_eq!(response["status"], "failed");
 }
+
+#[actix_rt::test]
+async fn add_documents_exceeding_the_limits() {
+    let temp = tempfile::tempdir().unwrap();
+    let options = Opt {
+        max_fields_per_document: Some(3),
+        ..default_settings(temp.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+
+    let documents = json!([
+        { "id": 1, "title": "Carol" },
+        { "id": 2, "title": "Kate", "meta": { "author": "Johnson" } },
+    ]);
+    let (response, code) = index.add_documents(documents, None).await;
+    assert_eq!(code, 400);
+    assert_eq!(response["errorCode"], "max_fields_limit_exceeded");
+    assert_eq!(
+        response["message"],
+        "The document at position 1 of the payload has 4 fields, but documents are limited to 3 fields."
+    );
+
+    let (_, code) = index.get_update(0).await;
+    assert_eq!(code, 404);
+}
+
+#[actix_rt::test]
+async fn route_documents() {
+    let server = Server::new().await;
+
+    let body = json!({
+        "rules": [{ "field": "lang", "indexUid": "articles-{lang}" }],
+        "fallbackIndexUid": "articles",
+        "template": {
+            "primaryKey": "id",
+            "settings": { "filterableAttributes": ["lang"] },
+        },
+        "documents": [
+            { "id": 1, "lang": "en" },
+            { "id": 2, "lang": "fr" },
+            { "id": 3, "lang": "en" },
+            { "id": 4 },
+        ],
+    });
+    let (response, code) = server.service.post("/documents/route", body).await;
+    assert_eq!(code, 202, "{}", response);
+    assert_eq!(response["updates"]["articles-en"]["updateId"], 1);
+    assert_eq!(response["updates"]["articles"]["updateId"], 1);
+
+    let index = server.index("articles-en");
+    let response = index.wait_update_id(1).await;
+    assert_eq!(response["status"], "processed", "{}", response);
+    let (response, code) = index
+        .get_all_documents(GetAllDocumentsOptions::default())
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(
+        response,
+        json!([{ "id": 1, "lang": "en" }, { "id": 3, "lang": "en" }])
+    );
+
+    let (response, _) = index.settings().await;
+    assert_eq!(response["filterableAttributes"], json!(["lang"]));
+
+    let (response, code) = server
+        .service
+        .post(
+            "/documents/route",
+            json!({
+                "rules": [{ "field": "lang", "indexUid": "articles-{lang}" }],
+                "documents": [{ "id": 5 }],
+            }),
+        )
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "unroutable_document");
+
+    // The target uids are checked before any index is created.
+    let (response, code) = server
+        .service
+        .post(
+            "/documents/route",
+            json!({
+                "rules": [{ "field": "lang", "indexUid": "articles-{lang}" }],
+                "documents": [{ "id": 6, "lang": "de" }, { "id": 7, "lang": "zz!" }],
+            }),
+        )
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "invalid_index_uid");
+    let (_response, code) = server.index("articles-de").get().await;
+    assert_eq!(code, 404);
+}
+
+#[actix_rt::test]
+async fn validate_documents_without_adding_them() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = json!([
+        { "id": 1, "title": "Carol" },
+        { "id": "not valid", "title": "Kate" },
+        { "title": "Johnson" },
+    ]);
+    let (response, code) = index
+        .service
+        .post("/indexes/test/documents?validateOnly=true", documents)
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["valid"], false);
+    assert_eq!(response["documents"], 3);
+    assert_eq!(response["primaryKey"], "id");
+    let errors = response["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0]["position"], 1);
+    assert_eq!(errors[0]["documentId"], "not valid");
+    assert_eq!(errors[0]["errorCode"], "invalid_document_id");
+    assert_eq!(errors[1]["position"], 2);
+    assert_eq!(errors[1]["errorCode"], "missing_document_id");
+
+    // Nothing was enqueued, the index wasn't even created.
+    let (_, code) = index.get_update(0).await;
+    assert_eq!(code, 404);
+    let (_, code) = index.get().await;
+    assert_eq!(code, 404);
+
+    let (response, code) = index
+        .service
+        .post(
+            "/indexes/test/documents?validateOnly=true",
+            json!([{ "id": 1, "title": "Carol" }]),
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["valid"], true);
+    assert_eq!(response["errors"], json!([]));
+}
+
+#[actix_rt::test]
+async fn update_documents_with_merge_operators() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = json!([{ "id": 1, "views": 41, "tags": ["new"] }]);
+    index.add_documents(documents, Some("id")).await;
+    index.wait_update_id(0).await;
+
+    let documents = json!([
+        {
+            "id": 1,
+            "views": { "$inc": 1 },
+            "tags": { "$append": ["sale"] },
+            "createdAt": { "$setIfMissing": 1635000000 },
+        },
+        { "id": 1, "views": { "$inc": 1 }, "createdAt": { "$setIfMissing": 0 } },
+    ]);
+    let (_, code) = index.update_documents(documents, None).await;
+    assert_eq!(code, 202);
+    let response = index.wait_update_id(1).await;
+    assert_eq!(response["status"], "processed", "{}", response);
+
+    let (response, code) = index.get_document(1, None).await;
+    assert_eq!(code, 200);
+    assert_eq!(
+        response,
+        json!({ "id": 1, "views": 43, "tags": ["new", "sale"], "createdAt": 1635000000 })
+    );
+
+    let (_, code) = index
+        .update_documents(json!([{ "id": 1, "tags": { "$inc": 1 } }]), None)
+        .await;
+    assert_eq!(code, 202);
+    let response = index.wait_update_id(2).await;
+    assert_eq!(response["status"], "failed", "{}", response);
+    assert_eq!(response["errorCode"], "invalid_merge_operator");
+
+    // The replacements can't use the operators.
+    let (_, code) = index
+        .add_documents(json!([{ "id": 1, "views": { "$inc": 1 } }]), None)
+        .await;
+    assert_eq!(code, 202);
+    let response = index.wait_update_id(3).await;
+    assert_eq!(response["status"], "failed", "{}", response);
+    assert_eq!(response["errorCode"], "invalid_merge_operator");
+}
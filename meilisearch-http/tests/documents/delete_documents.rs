@@ -123,3 +123,65 @@ async fn delete_no_document_batch() {
     assert_eq!(code, 200);
     assert_eq!(response.as_array().unwrap().len(), 3);
 }
+
+#[actix_rt::test]
+async fn delete_documents_by_filter() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .update_settings(json!({ "filterableAttributes": ["status", "date"] }))
+        .await;
+    index
+        .add_documents(
+            json!([
+                { "id": 1, "status": "archived", "date": 1500000000 },
+                { "id": 2, "status": "archived", "date": 1700000000 },
+                { "id": 3, "status": "published", "date": 1500000000 },
+            ]),
+            Some("id"),
+        )
+        .await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = index
+        .delete_by_filter(json!("status = archived AND date < 1600000000"))
+        .await;
+    assert_eq!(code, 202, "{}", response);
+    let response = index.wait_update_id(2).await;
+    assert_eq!(response["status"], "processed", "{}", response);
+    assert_eq!(response["type"]["name"], "DocumentsDeletion");
+    assert_eq!(response["type"]["number"], 1);
+
+    let (response, code) = index
+        .get_all_documents(GetAllDocumentsOptions::default())
+        .await;
+    assert_eq!(code, 200);
+    let ids: Vec<_> = response
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|document| document["id"].as_u64().unwrap())
+        .collect();
+    assert_eq!(ids, [2, 3]);
+}
+
+#[actix_rt::test]
+async fn delete_documents_by_invalid_filter() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .add_documents(json!([{ "id": 1, "status": "archived" }]), Some("id"))
+        .await;
+    index.wait_update_id(0).await;
+
+    // the attribute is not filterable
+    index.delete_by_filter(json!("status = archived")).await;
+    let response = index.wait_update_id(1).await;
+    assert_eq!(response["status"], "failed", "{}", response);
+    assert_eq!(response["errorCode"], "invalid_filter");
+
+    index.delete_by_filter(json!([])).await;
+    let response = index.wait_update_id(2).await;
+    assert_eq!(response["status"], "failed", "{}", response);
+    assert_eq!(response["errorCode"], "invalid_filter");
+}
@@ -284,3 +284,78 @@ async fn get_documents_displayed_attributes() {
     assert_eq!(response.as_object().unwrap().keys().count(), 1);
     assert!(response.as_object().unwrap().get("gender").is_some());
 }
+
+#[actix_rt::test]
+async fn get_document_with_composite_primary_key() {
+    let server = Server::new().await;
+    let (_, code) = server
+        .service
+        .post(
+            "/indexes",
+            json!({ "uid": "test", "primaryKey": ["tenant_id", "sku"] }),
+        )
+        .await;
+    assert_eq!(code, 201);
+
+    let index = server.index("test");
+    let documents = json!([
+        { "tenant_id": "acme", "sku": 1, "content": "foo" },
+        { "tenant_id": "globex", "sku": 1, "content": "bar" },
+    ]);
+    let (_, code) = index.add_documents(documents, None).await;
+    assert_eq!(code, 202);
+    let response = index.wait_update_id(0).await;
+    assert_eq!(response["status"], "processed");
+
+    let (response, code) = server.service.get("/indexes/test/documents/acme,1").await;
+    assert_eq!(code, 200);
+    assert_eq!(response["_compositeId"], "acme-1");
+    assert_eq!(response["content"], "foo");
+
+    let (response, code) = server.service.get("/indexes/test").await;
+    assert_eq!(code, 200);
+    assert_eq!(response["primaryKey"], "_compositeId");
+    assert_eq!(response["compositePrimaryKey"], json!(["tenant_id", "sku"]));
+}
+
+#[actix_rt::test]
+async fn get_documents_with_filter_sort_and_fields() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .update_settings(json!({
+            "filterableAttributes": ["status"],
+            "sortableAttributes": ["price"],
+        }))
+        .await;
+    let documents = json!([
+        { "id": 1, "status": "archived", "price": 30, "title": "a" },
+        { "id": 2, "status": "published", "price": 10, "title": "b" },
+        { "id": 3, "status": "published", "price": 20, "title": "c" },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = index
+        .get_all_documents(GetAllDocumentsOptions {
+            filter: Some("status = published"),
+            sort: Some(vec!["price:desc"]),
+            fields: Some(vec!["id", "price"]),
+            ..Default::default()
+        })
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(
+        response,
+        json!([{ "id": 3, "price": 20 }, { "id": 2, "price": 10 }])
+    );
+
+    let (response, code) = index
+        .get_all_documents(GetAllDocumentsOptions {
+            filter: Some("title = a"),
+            ..Default::default()
+        })
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "invalid_filter");
+}
@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use serde_json::json;
+use tokio::time::sleep;
+
+use crate::common::server::default_settings;
+use crate::common::Server;
+use meilisearch_http::Opt;
+
+#[actix_rt::test]
+async fn inspect_a_created_dump() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .update_settings(json!({ "filterableAttributes": ["genre"] }))
+        .await;
+    let documents = json!([
+        { "id": 1, "genre": "drama" },
+        { "id": 2, "genre": "comedy" },
+    ]);
+    index.add_documents(documents, Some("id")).await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = server.service.post("/dumps", json!(null)).await;
+    assert_eq!(code, 202, "{}", response);
+    let uid = response["uid"].as_str().unwrap().to_string();
+    for _ in 0..50 {
+        let (response, _) = server.service.get(format!("/dumps/{}/status", uid)).await;
+        if response["status"] != "in_progress" {
+            assert_eq!(response["status"], "done", "{}", response);
+            break;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+
+    let (response, code) = server
+        .service
+        .post(format!("/dumps/inspect?path={}.dump", uid), json!(null))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["dumpVersion"], "V3");
+    assert_eq!(response["importable"], true);
+    assert_eq!(response["indexes"][0]["uid"], "test");
+    assert_eq!(response["indexes"][0]["primaryKey"], "id");
+    assert_eq!(response["indexes"][0]["numberOfDocuments"], 2);
+    assert_eq!(
+        response["indexes"][0]["settings"]["filterableAttributes"],
+        json!(["genre"])
+    );
+}
+
+#[actix_rt::test]
+async fn inspect_an_unexisting_dump() {
+    let server = Server::new().await;
+
+    let (response, code) = server
+        .service
+        .post("/dumps/inspect?path=unexisting.dump", json!(null))
+        .await;
+    assert_eq!(code, 404, "{}", response);
+    assert_eq!(response["errorCode"], "not_found");
+
+    // only the dumps of the dumps directory can be inspected
+    let (response, code) = server
+        .service
+        .post("/dumps/inspect?path=../data.ms", json!(null))
+        .await;
+    assert_eq!(code, 404, "{}", response);
+
+    let (response, code) = server.service.post("/dumps/inspect", json!("hello")).await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "invalid_dump");
+}
+
+#[actix_rt::test]
+async fn prefixed_import_renumbers_the_tasks() {
+    let temp = tempfile::tempdir().unwrap();
+    let server = Server::new_with_options(default_settings(temp.path())).await;
+    let movies = server.index("movies");
+    let books = server.index("books");
+    movies.add_documents(json!([{ "id": 1 }]), Some("id")).await;
+    books.add_documents(json!([{ "id": 1 }]), Some("id")).await;
+    movies.add_documents(json!([{ "id": 2 }]), None).await;
+    movies.wait_update_id(1).await;
+    books.wait_update_id(0).await;
+
+    let (response, code) = server.service.post("/dumps", json!(null)).await;
+    assert_eq!(code, 202, "{}", response);
+    let uid = response["uid"].as_str().unwrap().to_string();
+    for _ in 0..50 {
+        let (response, _) = server.service.get(format!("/dumps/{}/status", uid)).await;
+        if response["status"] != "in_progress" {
+            assert_eq!(response["status"], "done", "{}", response);
+            break;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+
+    let import = tempfile::tempdir().unwrap();
+    let options = Opt {
+        import_dump: Some(temp.path().join("dump").join(format!("{}.dump", uid))),
+        import_dump_prefix: Some("old_".to_string()),
+        ..default_settings(import.path())
+    };
+    let server = Server::new_with_options(options).await;
+
+    // The tasks keep the order they had in the dump, whatever their index.
+    let (response, code) = server.service.get("/tasks").await;
+    assert_eq!(code, 200, "{}", response);
+    let tasks: Vec<_> = response["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|task| (task["uid"].clone(), task["indexUid"].clone()))
+        .collect();
+    assert_eq!(
+        tasks,
+        [
+            (json!(2), json!("old_movies")),
+            (json!(1), json!("old_books")),
+            (json!(0), json!("old_movies")),
+        ]
+    );
+}
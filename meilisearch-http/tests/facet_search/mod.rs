@@ -0,0 +1,69 @@
+use serde_json::json;
+
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn search_facet_values() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({"filterableAttributes": ["brand", "color"]}))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "brand": "Apple", "color": "white" },
+        { "id": 2, "brand": "apple", "color": "black" },
+        { "id": 3, "brand": ["Applied Materials", "Apple"], "color": "black" },
+        { "id": 4, "brand": "Samsung", "color": "black" },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = index
+        .facet_search(json!({ "facetName": "brand", "facetQuery": "app" }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(
+        response["facetHits"],
+        json!([
+            { "value": "Apple", "count": 3 },
+            { "value": "Applied Materials", "count": 1 },
+        ])
+    );
+
+    // with a typo, among the black documents
+    let (response, code) = index
+        .facet_search(
+            json!({ "facetName": "brand", "facetQuery": "samsnug", "filter": "color = black" }),
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(
+        response["facetHits"],
+        json!([{ "value": "Samsung", "count": 1 }])
+    );
+
+    let (response, code) = index
+        .facet_search(json!({ "facetName": "brand", "limit": 1 }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(
+        response["facetHits"],
+        json!([{ "value": "Apple", "count": 3 }])
+    );
+}
+
+#[actix_rt::test]
+async fn search_values_of_unfilterable_facet() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .add_documents(json!([{ "id": 1, "brand": "Apple" }]), None)
+        .await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = index.facet_search(json!({ "facetName": "brand" })).await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "invalid_facet");
+}
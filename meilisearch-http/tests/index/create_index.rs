@@ -14,7 +14,7 @@ async fn create_index_no_primary_key() {
     assert!(response.get("updatedAt").is_some());
     assert_eq!(response["createdAt"], response["updatedAt"]);
     assert_eq!(response["primaryKey"], Value::Null);
-    assert_eq!(response.as_object().unwrap().len(), 5);
+    assert_eq!(response.as_object().unwrap().len(), 6);
 }
 
 #[actix_rt::test]
@@ -30,7 +30,7 @@ async fn create_index_with_primary_key() {
     assert!(response.get("updatedAt").is_some());
     //assert_eq!(response["createdAt"], response["updatedAt"]);
     assert_eq!(response["primaryKey"], "primary");
-    assert_eq!(response.as_object().unwrap().len(), 5);
+    assert_eq!(response.as_object().unwrap().len(), 6);
 }
 
 #[actix_rt::test]
@@ -1,5 +1,6 @@
 use crate::common::Server;
-use serde_json::Value;
+use chrono::DateTime;
+use serde_json::{json, Value};
 
 #[actix_rt::test]
 async fn create_and_get_index() {
@@ -18,7 +19,35 @@ async fn create_and_get_index() {
     assert!(response.get("updatedAt").is_some());
     assert_eq!(response["createdAt"], response["updatedAt"]);
     assert_eq!(response["primaryKey"], Value::Null);
-    assert_eq!(response.as_object().unwrap().len(), 5);
+    assert_eq!(response["lastIndexedAt"], Value::Null);
+    assert_eq!(response.as_object().unwrap().len(), 6);
+}
+
+#[actix_rt::test]
+async fn last_indexed_at_is_set_by_documents_updates() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .update_settings(json!({ "filterableAttributes": ["id"] }))
+        .await;
+    index.wait_update_id(0).await;
+
+    // A settings update doesn't index documents.
+    let (response, _) = index.get().await;
+    assert_eq!(response["lastIndexedAt"], Value::Null);
+
+    index
+        .add_documents(json!([{ "id": 1, "title": "Carol" }]), None)
+        .await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = index.get().await;
+    assert_eq!(code, 200);
+    let last_indexed_at = response["lastIndexedAt"].as_str().unwrap();
+    assert!(DateTime::parse_from_rfc3339(last_indexed_at).is_ok());
+
+    let (response, _) = server.list_indexes().await;
+    assert_eq!(response[0]["lastIndexedAt"], last_indexed_at);
 }
 
 // TODO: partial test since we are testing error, and error is not yet fully implemented in
@@ -33,6 +62,45 @@ async fn get_unexisting_index() {
     assert_eq!(code, 404);
 }
 
+#[actix_rt::test]
+async fn get_unexisting_index_in_negotiated_format() {
+    let server = Server::new().await;
+    let (error, _code) = server.index("test").get().await;
+
+    let (body, code, headers) = server
+        .service
+        .get_accept("/indexes/test", "application/problem+json")
+        .await;
+    assert_eq!(code, 404);
+    assert_eq!(headers["content-type"], "application/problem+json");
+    let problem: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(problem["status"], 404);
+    assert_eq!(problem["title"], "Index not found");
+    assert_eq!(problem["errorCode"], "index_not_found");
+    assert_eq!(problem["type"], problem["errorLink"]);
+    assert_eq!(problem["detail"], error["message"]);
+
+    let (body, code, headers) = server
+        .service
+        .get_accept("/indexes/test", "text/plain, application/json;q=0.5")
+        .await;
+    assert_eq!(code, 404);
+    assert!(headers["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/plain"));
+    assert!(body.starts_with(error["message"].as_str().unwrap()));
+    assert!(body.contains("code: index_not_found"));
+
+    let (body, _code, headers) = server
+        .service
+        .get_accept("/indexes/test", "text/plain;q=0.1, */*")
+        .await;
+    assert_eq!(headers["content-type"], "application/json");
+    let error: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(error["errorCode"], "index_not_found");
+}
+
 #[actix_rt::test]
 async fn no_index_return_empty_list() {
     let server = Server::new().await;
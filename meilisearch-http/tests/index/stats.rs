@@ -1,5 +1,7 @@
+use meilisearch_http::Opt;
 use serde_json::json;
 
+use crate::common::server::default_settings;
 use crate::common::Server;
 
 #[actix_rt::test]
@@ -46,3 +48,98 @@ async fn stats() {
     assert_eq!(response["fieldDistribution"]["name"], 1);
     assert_eq!(response["fieldDistribution"]["age"], 1);
 }
+
+#[actix_rt::test]
+async fn facet_cardinality() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .update_settings(json!({ "filterableAttributes": ["color"] }))
+        .await;
+    index.wait_update_id(0).await;
+
+    let documents = json!([
+        { "id": 1, "color": ["red", "blue"] },
+        { "id": 2, "color": "red" },
+        { "id": 3 },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = index.stats().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["facetCardinality"], json!({ "color": 2 }));
+}
+
+#[actix_rt::test]
+async fn facet_cardinality_exceeded() {
+    let temp = tempfile::tempdir().unwrap();
+    let options = Opt {
+        max_facet_cardinality: Some(2),
+        ..default_settings(temp.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+    index
+        .update_settings(json!({ "filterableAttributes": ["id"] }))
+        .await;
+    index.wait_update_id(0).await;
+
+    let documents = json!([{ "id": 1 }, { "id": 2 }, { "id": 3 }]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = index.get_update(1).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["status"], "failed");
+    assert_eq!(response["errorCode"], "invalid_facet");
+
+    let (response, _) = index.stats().await;
+    assert_eq!(response["numberOfDocuments"], 0);
+}
+
+#[actix_rt::test]
+async fn document_quotas_exceeded() {
+    let temp = tempfile::tempdir().unwrap();
+    let options = Opt {
+        max_documents_per_index: Some(2),
+        max_documents: Some(3),
+        ..default_settings(temp.path())
+    };
+    let server = Server::new_with_options(options).await;
+
+    let index = server.index("test");
+    index
+        .add_documents(json!([{ "id": 1 }, { "id": 2 }, { "id": 3 }]), Some("id"))
+        .await;
+    index.wait_update_id(0).await;
+    let (response, _) = index.get_update(0).await;
+    assert_eq!(response["status"], "failed");
+    assert_eq!(response["errorCode"], "document_quota_exceeded");
+
+    index
+        .add_documents(json!([{ "id": 1 }, { "id": 2 }]), None)
+        .await;
+    index.wait_update_id(1).await;
+    let (response, _) = index.get_update(1).await;
+    assert_eq!(response["status"], "processed");
+
+    let other = server.index("other");
+    other
+        .add_documents(json!([{ "id": 1 }, { "id": 2 }]), Some("id"))
+        .await;
+    other.wait_update_id(0).await;
+    let (response, _) = other.get_update(0).await;
+    assert_eq!(response["status"], "failed");
+    assert_eq!(response["errorCode"], "document_quota_exceeded");
+
+    let (response, code) = index.stats().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["numberOfDocuments"], 2);
+    assert_eq!(response["maxDocuments"], 2);
+
+    let (response, code) = server.stats().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["numberOfDocuments"], 2);
+    assert_eq!(response["maxDocuments"], 3);
+}
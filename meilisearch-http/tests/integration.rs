@@ -1,11 +1,18 @@
+mod aggregate;
+mod aliases;
 mod common;
 mod documents;
+mod dumps;
+mod facet_search;
 mod index;
+mod keys;
+mod rollover;
 mod search;
 mod settings;
 mod snapshot;
 mod stats;
 mod updates;
+mod webhooks;
 
 // Tests are isolated by features in different modules to allow better readability, test
 // targetability, and improved incremental compilation times.
@@ -0,0 +1,191 @@
+use serde_json::json;
+
+use crate::common::server::default_settings;
+use crate::common::Server;
+use meilisearch_http::Opt;
+
+async fn server_with_master_key() -> (Server, tempfile::TempDir) {
+    let temp = tempfile::tempdir().unwrap();
+    let options = Opt {
+        master_key: Some(String::from("master")),
+        ..default_settings(temp.path())
+    };
+    (Server::new_with_options(options).await, temp)
+}
+
+#[actix_rt::test]
+async fn create_update_and_delete_key() {
+    let (server, _temp) = server_with_master_key().await;
+
+    let (key, code) = server
+        .service
+        .post(
+            "/keys",
+            json!({
+                "description": "Search the products",
+                "actions": ["search"],
+                "indexes": ["products_*"],
+                "expiresAt": "2100-01-01T00:00:00Z",
+            }),
+        )
+        .await;
+    assert_eq!(code, 201, "{}", key);
+    assert_eq!(key["description"], "Search the products");
+    assert_eq!(key["actions"], json!(["search"]));
+    assert_eq!(key["indexes"], json!(["products_*"]));
+    let uid = key["key"].as_str().unwrap();
+    assert_eq!(uid.len(), 32);
+
+    let (response, code) = server.service.get(format!("/keys/{}", uid)).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response, key);
+
+    let (response, code) = server.service.get("/keys").await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["results"], json!([key]));
+    assert!(response["private"].is_string());
+
+    let (response, code) = server
+        .service
+        .patch(
+            format!("/keys/{}", uid),
+            json!({ "actions": ["search", "documents.add"], "expiresAt": null }),
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["actions"], json!(["search", "documents.add"]));
+    assert_eq!(response["expiresAt"], json!(null));
+    assert_eq!(response["description"], "Search the products");
+
+    let (_response, code) = server.service.delete(format!("/keys/{}", uid)).await;
+    assert_eq!(code, 204);
+    let (response, code) = server.service.get(format!("/keys/{}", uid)).await;
+    assert_eq!(code, 404);
+    assert_eq!(response["errorCode"], "api_key_not_found");
+}
+
+#[actix_rt::test]
+async fn create_invalid_key() {
+    let (server, _temp) = server_with_master_key().await;
+
+    let (response, code) = server
+        .service
+        .post(
+            "/keys",
+            json!({ "actions": ["search"], "indexes": ["*"], "expiresAt": "2000-01-01T00:00:00Z" }),
+        )
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "invalid_api_key");
+
+    let (response, code) = server
+        .service
+        .post("/keys", json!({ "actions": [], "indexes": ["*"] }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "invalid_api_key");
+
+    let (_response, code) = server
+        .service
+        .post("/keys", json!({ "actions": ["fly"], "indexes": ["*"] }))
+        .await;
+    assert_eq!(code, 400);
+}
+
+#[actix_rt::test]
+async fn authenticate_with_key() {
+    let (server, _temp) = server_with_master_key().await;
+    let index = server.index("products_fr");
+    index.create(None).await;
+
+    let (key, _code) = server
+        .service
+        .post(
+            "/keys",
+            json!({ "actions": ["search"], "indexes": ["products_*"] }),
+        )
+        .await;
+    let key = key["key"].as_str().unwrap();
+    let headers = [("X-Meili-API-Key", key)];
+
+    let (response, code) = server
+        .service
+        .post_with_headers("/indexes/products_fr/search", json!({}), &headers)
+        .await;
+    assert_eq!(code, 200, "{}", response);
+
+    let bearer = format!("Bearer {}", key);
+    let (response, code) = server
+        .service
+        .post_with_headers(
+            "/indexes/products_fr/search",
+            json!({}),
+            &[("Authorization", &bearer)],
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+
+    // The key can't add documents, nor search the indexes it doesn't cover.
+    let (response, code) = server
+        .service
+        .post_with_headers("/indexes/products_fr/documents", json!([]), &headers)
+        .await;
+    assert_eq!(code, 403, "{}", response);
+    let (response, code) = server
+        .service
+        .post_with_headers("/indexes/movies/search", json!({}), &headers)
+        .await;
+    assert_eq!(code, 403, "{}", response);
+
+    // The aliases are checked as the index they target.
+    server.index("movies").create(None).await;
+    server
+        .service
+        .put("/aliases/products", json!({ "indexUid": "products_fr" }))
+        .await;
+    server
+        .service
+        .put("/aliases/products_movies", json!({ "indexUid": "movies" }))
+        .await;
+    let (response, code) = server
+        .service
+        .post_with_headers("/indexes/products/search", json!({}), &headers)
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    let (response, code) = server
+        .service
+        .post_with_headers("/indexes/products_movies/search", json!({}), &headers)
+        .await;
+    assert_eq!(code, 403, "{}", response);
+
+    // The indexes of the multi-search are checked one by one.
+    let (response, code) = server
+        .service
+        .post_with_headers(
+            "/multi-search",
+            json!({ "queries": [{ "indexUid": "products_fr" }] }),
+            &headers,
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    let (response, code) = server
+        .service
+        .post_with_headers(
+            "/multi-search",
+            json!({ "queries": [{ "indexUid": "products_fr" }, { "indexUid": "movies" }] }),
+            &headers,
+        )
+        .await;
+    assert_eq!(code, 403, "{}", response);
+
+    // Nor manage the keys.
+    let (response, code) = server
+        .service
+        .post_with_headers(
+            "/keys",
+            json!({ "actions": ["*"], "indexes": ["*"] }),
+            &headers,
+        )
+        .await;
+    assert_eq!(code, 403, "{}", response);
+}
@@ -0,0 +1,99 @@
+use chrono::Utc;
+use serde_json::json;
+
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn write_and_search_through_rollover_policy() {
+    let server = Server::new().await;
+
+    let (response, code) = server
+        .service
+        .put(
+            "/rollover/logs",
+            json!({
+                "period": "daily",
+                "keep": 30,
+                "template": {
+                    "primaryKey": "id",
+                    "settings": {
+                        "filterableAttributes": ["level"],
+                        "searchDefaults": { "limit": 1 },
+                    },
+                },
+            }),
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+
+    let partition_uid = format!("logs-{}", Utc::now().format("%Y-%m-%d"));
+    let (response, code) = server.index(&partition_uid).get().await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["primaryKey"], "id");
+
+    // The writes to the policy go to its current partition.
+    let (response, code) = server
+        .index("logs")
+        .add_documents(
+            json!([
+                { "id": 1, "message": "started", "level": "info" },
+                { "id": 2, "message": "started twice", "level": "error" },
+            ]),
+            None,
+        )
+        .await;
+    assert_eq!(code, 202, "{}", response);
+
+    // The search waits for the update of the current partition, whose settings are the ones of
+    // the template.
+    let update_id = response["updateId"].as_u64().unwrap();
+    server
+        .index("logs")
+        .search(
+            json!({ "q": "started", "filter": "level = error", "minUpdateId": update_id }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(response["nbHits"], 1);
+                assert_eq!(response["hits"][0]["message"], "started twice");
+            },
+        )
+        .await;
+
+    // The searches without a limit get the default one of the partitions.
+    server
+        .index("logs")
+        .search(json!({ "q": "started" }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert_eq!(response["nbHits"], 2);
+            assert_eq!(response["limit"], 1);
+            assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+        })
+        .await;
+
+    let (response, code) = server.service.get("/rollover").await;
+    assert_eq!(code, 200);
+    assert_eq!(response["logs"]["keep"], 30);
+
+    // The name of a policy can't be used by an index.
+    let (response, code) = server.index("logs").create(None).await;
+    assert_eq!(code, 400);
+    assert_eq!(response["errorCode"], "index_already_exists");
+
+    let (_, code) = server.service.delete("/rollover/logs").await;
+    assert_eq!(code, 204);
+    let (response, code) = server.service.get("/rollover/logs").await;
+    assert_eq!(code, 404);
+    assert_eq!(response["errorCode"], "rollover_policy_not_found");
+}
+
+#[actix_rt::test]
+async fn invalid_rollover_policy() {
+    let server = Server::new().await;
+
+    let (response, code) = server
+        .service
+        .put("/rollover/logs", json!({ "period": "daily", "keep": 0 }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "bad_request");
+}
@@ -26,3 +26,30 @@ async fn search_unexisting_parameter() {
         })
         .await;
 }
+
+#[actix_rt::test]
+async fn search_with_unbalanced_filter() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({"filterableAttributes": ["title"]}))
+        .await;
+    index.wait_update_id(0).await;
+
+    index
+        .search(json!({"filter": "(title = Apple"}), |response, code| {
+            assert_eq!(code, 400, "{}", response);
+            assert_eq!(response["errorCode"], "invalid_filter");
+            assert!(response["message"]
+                .as_str()
+                .unwrap()
+                .contains("at characters 0..1"));
+        })
+        .await;
+
+    let filter = format!("{}title = Apple{}", "(".repeat(1000), ")".repeat(1000));
+    let (response, code) = index.search_post(json!({ "filter": filter })).await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "invalid_filter");
+}
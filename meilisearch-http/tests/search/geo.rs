@@ -0,0 +1,97 @@
+use crate::common::Server;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+
+static DOCUMENTS: Lazy<Value> = Lazy::new(|| {
+    json!([
+        { "id": 1, "name": "Taco Truck", "_geo": { "lat": 48.8566, "lng": 2.3522 } },
+        { "id": 2, "name": "La Bella Italia", "_geo": { "lat": 48.8738, "lng": 2.2950 } },
+        { "id": 3, "name": "Crêpe Corner", "_geo": { "lat": 45.7640, "lng": 4.8357 } },
+    ])
+});
+
+#[actix_rt::test]
+async fn geo_radius_filter() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .update_settings(json!({ "filterableAttributes": ["_geo"] }))
+        .await;
+    index.add_documents(DOCUMENTS.clone(), None).await;
+    index.wait_update_id(1).await;
+
+    index
+        .search(
+            json!({ "filter": "_geoRadius(48.8566, 2.3522, 10000)" }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                let mut ids: Vec<_> = response["hits"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|hit| hit["id"].as_u64().unwrap())
+                    .collect();
+                ids.sort_unstable();
+                assert_eq!(ids, [1, 2]);
+            },
+        )
+        .await;
+}
+
+#[actix_rt::test]
+async fn geo_point_sort_returns_the_distance() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .update_settings(json!({ "sortableAttributes": ["_geo"] }))
+        .await;
+    index.add_documents(DOCUMENTS.clone(), None).await;
+    index.wait_update_id(1).await;
+
+    index
+        .search(
+            json!({ "sort": ["_geoPoint(45.7640, 4.8357):asc"] }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                let hits = response["hits"].as_array().unwrap();
+                let ids: Vec<_> = hits.iter().map(|hit| hit["id"].as_u64().unwrap()).collect();
+                assert_eq!(ids, [3, 1, 2]);
+                assert_eq!(hits[0]["_geoDistance"], 0);
+                assert!(hits[1]["_geoDistance"].as_u64().unwrap() > 300_000);
+            },
+        )
+        .await;
+}
+
+#[actix_rt::test]
+async fn geo_radius_filter_on_a_non_filterable_geo_field() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.add_documents(DOCUMENTS.clone(), None).await;
+    index.wait_update_id(0).await;
+
+    index
+        .search(
+            json!({ "filter": "_geoRadius(48.8566, 2.3522, 10000)" }),
+            |response, code| {
+                assert_eq!(code, 400, "{}", response);
+                assert_eq!(response["errorCode"], "invalid_filter");
+            },
+        )
+        .await;
+}
+
+#[actix_rt::test]
+async fn invalid_geo_field() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    // The `_geo` fields are only checked once they are filterable or sortable.
+    index
+        .update_settings(json!({ "filterableAttributes": ["_geo"] }))
+        .await;
+    index
+        .add_documents(json!([{ "id": 1, "_geo": { "lat": "north" } }]), None)
+        .await;
+    let response = index.wait_update_id(1).await;
+    assert_eq!(response["status"], "failed", "{}", response);
+}
@@ -2,8 +2,11 @@
 // should be tested in its own module to isolate tests and keep the tests readable.
 
 mod errors;
+mod geo;
 
-use crate::common::Server;
+use crate::common::server::default_settings;
+use crate::common::{GetAllDocumentsOptions, Server};
+use meilisearch_http::Opt;
 use once_cell::sync::Lazy;
 use serde_json::{json, Value};
 
@@ -117,94 +120,459 @@ async fn search_with_filter_string_notation() {
 }
 
 #[actix_rt::test]
-async fn search_with_filter_array_notation() {
+async fn search_numeric_attribute() {
     let server = Server::new().await;
     let index = server.index("test");
 
     index
-        .update_settings(json!({"filterableAttributes": ["title"]}))
+        .update_settings(json!({
+            "searchableAttributes": ["title", "ean"],
+            "searchableNumericAttributes": ["ean"],
+        }))
         .await;
 
-    let documents = DOCUMENTS.clone();
+    let documents = json!([
+        { "id": 1, "title": "Phone", "ean": 404982 },
+        { "id": 2, "title": "Laptop", "ean": 123456 },
+    ]);
     index.add_documents(documents, None).await;
     index.wait_update_id(1).await;
 
-    let (response, code) = index
-        .search_post(json!({
-            "filter": ["title = Glass"]
+    index
+        .search(json!({ "q": "404982" }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert_eq!(response["nbHits"], 1);
+            assert_eq!(response["hits"][0]["id"], 1);
+            assert!(response["hits"][0].get("_numericText.ean").is_none());
+        })
+        .await;
+
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["searchableAttributes"], json!(["title", "ean"]));
+}
+
+#[actix_rt::test]
+async fn search_with_normalized_filter() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = json!([
+        { "id": 1, "brand": "Apple" },
+        { "id": 2, "brand": "ÁPPLE" },
+        { "id": 3, "brand": "Banana" },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(0).await;
+
+    index
+        .update_settings(json!({
+            "filterableAttributes": ["id"],
+            "normalizedFilterableAttributes": ["brand"],
         }))
         .await;
-    assert_eq!(code, 200, "{}", response);
-    assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+    index.wait_update_id(1).await;
+
+    index
+        .search(json!({ "filter": "brand = apple" }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert_eq!(response["nbHits"], 2);
+            assert!(response["hits"][0].get("_normalized.brand").is_none());
+        })
+        .await;
+
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["filterableAttributes"], json!(["id"]));
+    assert_eq!(response["searchableAttributes"], json!(["*"]));
+
+    // All the attributes but the hidden ones are searchable, the new ones included.
+    index
+        .add_documents(
+            json!([{ "id": 4, "brand": "Cherry", "origin": "Spain" }]),
+            None,
+        )
+        .await;
+    index.wait_update_id(2).await;
+    index
+        .search(json!({ "q": "spain" }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert_eq!(response["nbHits"], 1);
+            assert_eq!(response["hits"][0]["id"], 4);
+        })
+        .await;
+}
+
+#[actix_rt::test]
+async fn search_with_boolean_filter_sugar() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({"filterableAttributes": ["is_published", "price"]}))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "is_published": true, "price": 1 },
+        { "id": 2, "is_published": false, "price": 0 },
+        { "id": 3, "is_published": true, "price": 1 },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(1).await;
+
+    index
+        .search(json!({ "filter": "is_published" }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert_eq!(response["nbHits"], 2);
+        })
+        .await;
 
     let (response, code) = index
-        .search_post(json!({
-            "filter": [["title = Glass", "title = \"Shazam!\"", "title = \"Escape Room\""]]
-        }))
+        .search_post(json!({ "filter": ["NOT is_published"] }))
         .await;
     assert_eq!(code, 200, "{}", response);
-    assert_eq!(response["hits"].as_array().unwrap().len(), 3);
+    assert_eq!(response["nbHits"], 1);
+    assert_eq!(response["hits"][0]["id"], 2);
+
+    // Only the attributes holding booleans can be conditions on their own.
+    let (response, code) = index.search_post(json!({ "filter": "price" })).await;
+    assert_eq!(code, 400, "{}", response);
 }
 
 #[actix_rt::test]
-async fn search_with_sort_on_numbers() {
+async fn search_waiting_for_update() {
     let server = Server::new().await;
     let index = server.index("test");
 
+    let documents = DOCUMENTS.clone();
+    let (response, code) = index.add_documents(documents, None).await;
+    assert_eq!(code, 202, "{}", response);
+    let update_id = response["updateId"].as_u64().unwrap();
+
     index
-        .update_settings(json!({"sortableAttributes": ["id"]}))
+        .search(
+            json!({ "q": "glass", "minUpdateId": update_id }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+            },
+        )
         .await;
 
-    let documents = DOCUMENTS.clone();
+    let (response, code) = index
+        .search_post(json!({ "minUpdateId": update_id + 1 }))
+        .await;
+    assert_eq!(code, 404, "{}", response);
+}
+
+#[actix_rt::test]
+async fn search_with_one_way_synonyms() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({ "synonyms": { "tv": ["television"] } }))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "title": "tv remote" },
+        { "id": 2, "title": "television remote" },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(1).await;
+
+    index
+        .search(json!({ "q": "tv" }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert_eq!(response["nbHits"], 2);
+        })
+        .await;
+
+    index
+        .search(json!({ "q": "television" }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert_eq!(response["nbHits"], 1);
+            assert_eq!(response["hits"][0]["id"], 2);
+        })
+        .await;
+}
+
+#[actix_rt::test]
+async fn search_with_debug() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({ "synonyms": { "tv": ["television"] } }))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "title": "tv remote" },
+        { "id": 2, "title": "television remote" },
+    ]);
     index.add_documents(documents, None).await;
     index.wait_update_id(1).await;
 
     index
         .search(
-            json!({
-                "sort": ["id:asc"]
-            }),
+            json!({ "q": "Remot tv", "debug": true }),
             |response, code| {
                 assert_eq!(code, 200, "{}", response);
-                assert_eq!(response["hits"].as_array().unwrap().len(), 5);
+                let debug = &response["debug"];
+                assert_eq!(debug["synonyms"], json!({ "tv": ["television"] }));
+                assert_eq!(
+                    debug["words"][0],
+                    json!({
+                        "original": "Remot",
+                        "normalized": "remot",
+                        "isStopWord": false,
+                        "isPrefix": false,
+                        "maxTypos": 1,
+                        "derivations": ["remote"],
+                        "optional": false,
+                    })
+                );
+                assert_eq!(debug["words"][1]["isPrefix"], true);
+                assert_eq!(debug["words"][1]["optional"], true);
             },
         )
         .await;
+
+    index
+        .search(json!({ "q": "tv" }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert!(response.get("debug").is_none());
+        })
+        .await;
 }
 
 #[actix_rt::test]
-async fn search_with_sort_on_strings() {
+async fn search_with_ranking_score() {
     let server = Server::new().await;
     let index = server.index("test");
 
     index
-        .update_settings(json!({"sortableAttributes": ["title"]}))
+        .update_settings(json!({ "searchableAttributes": ["title"] }))
         .await;
 
-    let documents = DOCUMENTS.clone();
+    let documents = json!([
+        { "id": 1, "title": "brown dog" },
+        { "id": 2, "title": "brown quick fox" },
+    ]);
     index.add_documents(documents, None).await;
     index.wait_update_id(1).await;
 
     index
         .search(
-            json!({
-                "sort": ["title:desc"]
-            }),
+            json!({ "q": "brown quick", "showRankingScore": true, "showRankingScoreDetails": true }),
             |response, code| {
                 assert_eq!(code, 200, "{}", response);
-                assert_eq!(response["hits"].as_array().unwrap().len(), 5);
+                let hits = response["hits"].as_array().unwrap();
+                assert_eq!(hits[0]["id"], 2);
+                assert_eq!(hits[0]["_rankingScore"], 1.0);
+                let score = hits[1]["_rankingScore"].as_f64().unwrap();
+                assert!(score > 0.0 && score < 1.0, "{}", score);
+                assert_eq!(
+                    hits[1]["_rankingScoreDetails"]["words"],
+                    json!({ "order": 0, "score": 0.5 })
+                );
             },
         )
         .await;
+
+    index
+        .search(json!({ "q": "brown" }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            assert!(response["hits"][0].get("_rankingScore").is_none());
+            assert!(response["hits"][0].get("_rankingScoreDetails").is_none());
+        })
+        .await;
 }
 
 #[actix_rt::test]
-async fn search_with_multiple_sort() {
+async fn search_on_some_attributes() {
     let server = Server::new().await;
     let index = server.index("test");
 
     index
-        .update_settings(json!({"sortableAttributes": ["id", "title"]}))
+        .update_settings(json!({ "searchableAttributes": ["title", "overview"] }))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "title": "the dog", "overview": "a wild cat", "genre": "cat" },
+        { "id": 2, "title": "the cat", "overview": "a wild dog", "genre": "dog" },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = index
+        .search_post(json!({ "q": "cat", "attributesToSearchOn": ["title"] }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 1);
+    assert_eq!(response["hits"][0]["id"], 2);
+
+    let (response, code) = index
+        .search_post(json!({ "q": "cat", "attributesToSearchOn": ["*"] }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 2);
+
+    let (response, code) = index
+        .search_post(json!({ "q": "cat", "attributesToSearchOn": ["genre"] }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "bad_request");
+}
+
+#[actix_rt::test]
+async fn search_with_typo_tolerance() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = json!([{ "id": 1, "title": "hello world" }]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = index.search_post(json!({ "q": "hollo" })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 1);
+
+    let settings = [
+        json!({ "enabled": false }),
+        json!({ "disableOnWords": ["hollo"] }),
+        json!({ "minWordSizeForTypos": { "oneTypo": 6, "twoTypos": 9 } }),
+        json!({ "disableOnAttributes": ["title"] }),
+    ];
+    for (update_id, typo_tolerance) in settings.iter().enumerate() {
+        index
+            .update_settings(json!({ "typoTolerance": typo_tolerance }))
+            .await;
+        index.wait_update_id(update_id as u64 + 1).await;
+
+        let (response, code) = index.search_post(json!({ "q": "hollo" })).await;
+        assert_eq!(code, 200, "{}", response);
+        assert_eq!(response["nbHits"], 0, "{}", typo_tolerance);
+        let (response, code) = index.search_post(json!({ "q": "hello" })).await;
+        assert_eq!(code, 200, "{}", response);
+        assert_eq!(response["nbHits"], 1, "{}", typo_tolerance);
+    }
+
+    // milli never looks for the typos of the words shorter than 5 characters.
+    index
+        .update_settings(json!({ "typoTolerance": { "minWordSizeForTypos": { "oneTypo": 4 } } }))
+        .await;
+    let response = index.wait_update_id(5).await;
+    assert_eq!(response["status"], "failed", "{}", response);
+
+    // Every word of the query is checked when they must all match, milli otherwise ignoring the
+    // last ones.
+    index
+        .update_settings(json!({ "typoTolerance": { "disableOnWords": ["wrold"] } }))
+        .await;
+    index.wait_update_id(6).await;
+    let (response, code) = index
+        .search_post(json!({ "q": "hello wrold", "matchingStrategy": "all" }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 0, "{}", response);
+    let (response, code) = index.search_post(json!({ "q": "hello wrold" })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 1, "{}", response);
+}
+
+#[actix_rt::test]
+async fn search_with_typo_tolerance_refetches_the_page() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = json!([
+        { "id": 1, "title": "hollo" },
+        { "id": 2, "title": "hallo" },
+        { "id": 3, "title": "hello" },
+    ]);
+    index.add_documents(documents, None).await;
+    index
+        .update_settings(json!({
+            "rankingRules": ["words", "sort", "typo"],
+            "sortableAttributes": ["id"],
+            "typoTolerance": { "minWordSizeForTypos": { "oneTypo": 6 } },
+        }))
+        .await;
+    index.wait_update_id(1).await;
+
+    // The documents ranked first are rejected, the page is filled by fetching the next ones.
+    let (response, code) = index
+        .search_post(json!({ "q": "hello", "sort": ["id:asc"], "limit": 1 }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"], json!([{ "id": 3, "title": "hello" }]));
+    assert_eq!(response["nbHits"], 1, "{}", response);
+}
+
+#[actix_rt::test]
+async fn search_with_search_defaults() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index.add_documents(DOCUMENTS.clone(), None).await;
+    index.wait_update_id(0).await;
+
+    let search_defaults = json!({
+        "limit": 2,
+        "attributesToHighlight": ["title"],
+        "matchingStrategy": "all",
+    });
+    let (response, code) = index
+        .update_settings(json!({ "searchDefaults": search_defaults }))
+        .await;
+    assert_eq!(code, 202, "{}", response);
+    index.wait_update_id(1).await;
+
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["searchDefaults"]["limit"], 2);
+
+    let (response, code) = index.search_post(json!({})).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 2);
+    assert_eq!(response["limit"], 2);
+
+    let (response, code) = index.search_post(json!({ "q": "captain" })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(
+        response["hits"][0]["_formatted"]["title"],
+        "<em>Captain</em> Marvel"
+    );
+
+    // The parameters of the query take precedence over the defaults.
+    let (response, code) = index
+        .search_post(json!({ "q": "captain dragon", "limit": 3 }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 0);
+    assert_eq!(response["limit"], 3);
+    let (response, code) = index
+        .search_post(json!({ "q": "captain dragon", "matchingStrategy": "last" }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 1);
+
+    let (response, code) = index
+        .update_settings(json!({ "searchDefaults": { "limit": 0 } }))
+        .await;
+    assert_eq!(code, 202, "{}", response);
+    let response = index.wait_update_id(2).await;
+    assert_eq!(response["status"], "failed", "{}", response);
+}
+
+#[actix_rt::test]
+async fn search_with_filter_array_notation() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({"filterableAttributes": ["title"]}))
         .await;
 
     let documents = DOCUMENTS.clone();
@@ -213,20 +581,28 @@ async fn search_with_multiple_sort() {
 
     let (response, code) = index
         .search_post(json!({
-            "sort": ["id:asc", "title:desc"]
+            "filter": ["title = Glass"]
         }))
         .await;
     assert_eq!(code, 200, "{}", response);
-    assert_eq!(response["hits"].as_array().unwrap().len(), 5);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+
+    let (response, code) = index
+        .search_post(json!({
+            "filter": [["title = Glass", "title = \"Shazam!\"", "title = \"Escape Room\""]]
+        }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 3);
 }
 
 #[actix_rt::test]
-async fn search_facet_distribution() {
+async fn search_with_sort_on_numbers() {
     let server = Server::new().await;
     let index = server.index("test");
 
     index
-        .update_settings(json!({"filterableAttributes": ["title"]}))
+        .update_settings(json!({"sortableAttributes": ["id"]}))
         .await;
 
     let documents = DOCUMENTS.clone();
@@ -236,34 +612,1042 @@ async fn search_facet_distribution() {
     index
         .search(
             json!({
-                "facetsDistribution": ["title"]
+                "sort": ["id:asc"]
             }),
             |response, code| {
                 assert_eq!(code, 200, "{}", response);
-                let dist = response["facetsDistribution"].as_object().unwrap();
-                assert_eq!(dist.len(), 1);
-                assert!(dist.get("title").is_some());
+                assert_eq!(response["hits"].as_array().unwrap().len(), 5);
             },
         )
         .await;
 }
 
 #[actix_rt::test]
-async fn displayed_attributes() {
+async fn search_with_sort_on_strings() {
     let server = Server::new().await;
     let index = server.index("test");
 
     index
-        .update_settings(json!({ "displayedAttributes": ["title"] }))
+        .update_settings(json!({"sortableAttributes": ["title"]}))
         .await;
 
     let documents = DOCUMENTS.clone();
     index.add_documents(documents, None).await;
     index.wait_update_id(1).await;
 
-    let (response, code) = index
-        .search_post(json!({ "attributesToRetrieve": ["title", "id"] }))
+    index
+        .search(
+            json!({
+                "sort": ["title:desc"]
+            }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                assert_eq!(response["hits"].as_array().unwrap().len(), 5);
+            },
+        )
         .await;
-    assert_eq!(code, 200, "{}", response);
-    assert!(response["hits"].get("title").is_none());
+}
+
+#[actix_rt::test]
+async fn search_with_sort_using_unicode_collation() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = json!([
+        { "id": 1, "title": "zebra" },
+        { "id": 2, "title": "Élan" },
+        { "id": 3, "title": "eagle" },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(0).await;
+
+    index
+        .update_settings(json!({
+            "sortableAttributes": ["title"],
+            "sortCollation": "unicode",
+        }))
+        .await;
+    index.wait_update_id(1).await;
+
+    index
+        .search(json!({ "sort": ["title:asc"] }), |response, code| {
+            assert_eq!(code, 200, "{}", response);
+            let ids: Vec<_> = response["hits"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|hit| hit["id"].as_u64().unwrap())
+                .collect();
+            assert_eq!(ids, vec![3, 2, 1]);
+            assert!(response["hits"][0].get("_collationKey.title").is_none());
+        })
+        .await;
+
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["sortableAttributes"], json!(["title"]));
+}
+
+#[actix_rt::test]
+async fn search_with_multiple_sort() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({"sortableAttributes": ["id", "title"]}))
+        .await;
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = index
+        .search_post(json!({
+            "sort": ["id:asc", "title:desc"]
+        }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 5);
+}
+
+#[actix_rt::test]
+async fn search_facet_distribution() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({"filterableAttributes": ["title"]}))
+        .await;
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_update_id(1).await;
+
+    index
+        .search(
+            json!({
+                "facetsDistribution": ["title"]
+            }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                let dist = response["facetsDistribution"].as_object().unwrap();
+                assert_eq!(dist.len(), 1);
+                assert!(dist.get("title").is_some());
+            },
+        )
+        .await;
+}
+
+#[actix_rt::test]
+async fn displayed_attributes() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({ "displayedAttributes": ["title"] }))
+        .await;
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = index
+        .search_post(json!({ "attributesToRetrieve": ["title", "id"] }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert!(response["hits"].get("title").is_none());
+}
+
+#[actix_rt::test]
+async fn search_with_diversity() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = json!([
+        { "id": 1, "seller": "acme" },
+        { "id": 2, "seller": "acme" },
+        { "id": 3, "seller": "acme" },
+        { "id": 4, "seller": "globex" },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = index
+        .search_post(json!({ "diversity": { "attribute": "seller", "maxPerValue": 2 } }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    let sellers: Vec<_> = response["hits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|hit| hit["seller"].as_str().unwrap())
+        .collect();
+    assert_eq!(sellers, vec!["acme", "acme", "globex", "acme"]);
+
+    // The hits are only reordered inside the page.
+    let (response, code) = index
+        .search_post(json!({ "diversity": { "attribute": "seller" }, "limit": 2 }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    let sellers: Vec<_> = response["hits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|hit| hit["seller"].as_str().unwrap())
+        .collect();
+    assert_eq!(sellers, vec!["acme", "acme"]);
+
+    let (response, code) = index
+        .search_post(json!({ "diversity": { "attribute": "seller", "maxPerValue": 0 } }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(
+        response["message"],
+        "Invalid diversity: `maxPerValue` must be at least 1."
+    );
+}
+
+#[actix_rt::test]
+async fn search_with_random_sort_and_sample() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_update_id(0).await;
+
+    let (first, code) = index.search_post(json!({ "sort": ["_random(42)"] })).await;
+    assert_eq!(code, 200, "{}", first);
+    let (second, _) = index.search_post(json!({ "sort": ["_random(42)"] })).await;
+    assert_eq!(first["hits"], second["hits"]);
+    assert_eq!(first["hits"].as_array().unwrap().len(), 5);
+
+    let (page, code) = index
+        .search_post(json!({ "sort": ["_random(42)"], "offset": 2, "limit": 2 }))
+        .await;
+    assert_eq!(code, 200, "{}", page);
+    assert_eq!(page["hits"], json!(first["hits"].as_array().unwrap()[2..4]));
+
+    let (response, code) = index.search_post(json!({ "sample": 2 })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 2);
+    assert_eq!(response["nbHits"], 5);
+
+    // The pages of a seeded sample are taken from the same sample.
+    let (response, code) = index
+        .search_post(json!({ "sample": 4, "sort": ["_random(42)"], "offset": 2, "limit": 5 }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"], page["hits"]);
+
+    let (response, code) = index
+        .search_post(json!({ "sort": ["_random(42)", "title:asc"] }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(
+        response["message"],
+        "Invalid sort `_random(42), title:asc`: `_random(seed)` must be the only sort rule and the seed must be an integer between 0 and 18446744073709551615."
+    );
+}
+
+#[actix_rt::test]
+async fn search_with_deterministic_indexing() {
+    let queries = [
+        json!({}),
+        json!({ "q": "the" }),
+        json!({ "q": "captain glass" }),
+        json!({ "filter": "title != Glass", "facetsDistribution": ["title"] }),
+        json!({ "sort": ["title:desc"] }),
+        json!({ "sample": 3 }),
+    ];
+
+    let mut outputs = Vec::new();
+    for _ in 0..2 {
+        let temp = tempfile::tempdir().unwrap();
+        let options = Opt {
+            deterministic_indexing: true,
+            ..default_settings(temp.path())
+        };
+        let server = Server::new_with_options(options).await;
+        let index = server.index("test");
+
+        index
+            .update_settings(json!({
+                "filterableAttributes": ["title"],
+                "sortableAttributes": ["title"],
+            }))
+            .await;
+        index.wait_update_id(0).await;
+        index.add_documents(DOCUMENTS.clone(), None).await;
+        index.wait_update_id(1).await;
+        // The document added back takes the docid freed by its deletion.
+        index.delete_document(299537).await;
+        index.wait_update_id(2).await;
+        index
+            .add_documents(json!([{ "title": "Captain Marvel", "id": "299537" }]), None)
+            .await;
+        index.wait_update_id(3).await;
+
+        let mut output = Vec::new();
+        for query in &queries {
+            let (mut response, code) = index.search_post(query.clone()).await;
+            assert_eq!(code, 200, "{}", response);
+            response.as_object_mut().unwrap().remove("processingTimeMs");
+            output.push(response);
+        }
+        let (documents, code) = index
+            .get_all_documents(GetAllDocumentsOptions::default())
+            .await;
+        assert_eq!(code, 200, "{}", documents);
+        output.push(documents);
+        outputs.push(serde_json::to_string(&output).unwrap());
+    }
+
+    assert_eq!(outputs[0], outputs[1]);
+}
+
+#[actix_rt::test]
+async fn multi_search() {
+    let server = Server::new().await;
+    let movies = server.index("movies");
+    movies.add_documents(DOCUMENTS.clone(), None).await;
+    movies.wait_update_id(0).await;
+    let books = server.index("books");
+    books
+        .add_documents(json!([{ "id": 1, "title": "Dune" }]), None)
+        .await;
+    books.wait_update_id(0).await;
+
+    let (response, code) = server
+        .service
+        .post(
+            "/multi-search",
+            json!({ "queries": [
+                { "indexUid": "movies", "q": "glass" },
+                { "indexUid": "books", "q": "dune", "limit": 1 },
+            ] }),
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    let results = response["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["indexUid"], "movies");
+    assert_eq!(results[0]["hits"][0]["id"], "450465");
+    assert_eq!(results[1]["indexUid"], "books");
+    assert_eq!(results[1]["limit"], 1);
+    assert_eq!(results[1]["hits"][0]["title"], "Dune");
+
+    let (response, code) = server
+        .service
+        .post(
+            "/multi-search",
+            json!({ "queries": [
+                { "indexUid": "movies", "q": "glass" },
+                { "indexUid": "unknown", "q": "glass" },
+            ] }),
+        )
+        .await;
+    assert_eq!(code, 404, "{}", response);
+    assert!(response["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("The search at position 1 failed"));
+}
+
+#[actix_rt::test]
+async fn search_with_date_histogram() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({
+            "dateAttributes": ["created_at"],
+            "filterableAttributes": ["created_at"],
+            "sortableAttributes": ["created_at"],
+        }))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "created_at": "2021-01-10T10:00:00Z" },
+        { "id": 2, "created_at": "2021-01-20T10:00:00Z" },
+        { "id": 3, "created_at": "2021-03-01T10:00:00Z" },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = index
+        .search_post(json!({
+            "filter": "created_at > 1610300000",
+            "sort": ["created_at:desc"],
+            "facetsDistribution": ["created_at:byMonth"],
+        }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 2);
+    // The dates are kept as they were given.
+    assert_eq!(response["hits"][0]["created_at"], "2021-03-01T10:00:00Z");
+    assert_eq!(response["hits"][1]["created_at"], "2021-01-20T10:00:00Z");
+    assert_eq!(
+        response["facetsDistribution"]["created_at:byMonth"],
+        json!({ "2021-01": 1, "2021-03": 1 })
+    );
+}
+
+#[actix_rt::test]
+async fn search_with_range_facets() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({ "filterableAttributes": ["price"] }))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "price": 10, "stock": 5 },
+        { "id": 2, "price": 60 },
+        { "id": 3, "price": [20, 40] },
+        { "id": 4, "price": 1000 },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = index
+        .search_post(json!({ "facetsDistribution": ["price:ranges(0,50,100,500)"] }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(
+        response["facetsDistribution"]["price:ranges(0,50,100,500)"],
+        json!({ "0..50": 2, "50..100": 1, "100..500": 0 })
+    );
+
+    let (response, code) = index
+        .search_post(json!({ "facetsDistribution": ["price:ranges(100,50)"] }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "invalid_facet");
+
+    let (response, code) = index
+        .search_post(json!({ "facetsDistribution": ["stock:ranges(0,10)"] }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "invalid_facet");
+}
+
+#[actix_rt::test]
+async fn search_with_same_element_filter() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({
+            "flattenStrategy": "flatten",
+            "filterableAttributes": ["variants.color", "variants.size"],
+        }))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "variants": [{ "color": "red", "size": "M" }, { "color": "blue", "size": "L" }] },
+        { "id": 2, "variants": [{ "color": "red", "size": "L" }, { "color": "blue", "size": "M" }] },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(1).await;
+
+    let filter = "variants.color = red AND variants.size = M";
+
+    let (response, code) = index.search_post(json!({ "filter": filter })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 2);
+
+    let (response, code) = index
+        .search_post(json!({ "filter": filter, "filterMode": "sameElement" }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 1);
+    assert_eq!(response["hits"][0]["id"], 1);
+    assert_eq!(
+        response["hits"][0]["variants.color"],
+        json!(["red", "blue"])
+    );
+
+    let (response, code) = index
+        .search_post(json!({
+            "filter": "variants.color = red OR variants.size = M",
+            "filterMode": "sameElement",
+        }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+}
+
+#[actix_rt::test]
+async fn search_highlight_with_exclusions_and_raw_attributes() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({ "rawAttributes": ["body_html"] }))
+        .await;
+
+    let documents = json!([
+        {
+            "id": 1,
+            "title": "cats",
+            "summary": "about cats",
+            "body_html": "<p>cats</p>",
+        },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(1).await;
+
+    index
+        .search(
+            json!({ "q": "cats", "attributesToHighlight": ["*", "-summary"] }),
+            |response, code| {
+                assert_eq!(code, 200, "{}", response);
+                let formatted = &response["hits"][0]["_formatted"];
+                assert_eq!(formatted["title"], "<em>cats</em>");
+                assert_eq!(formatted["summary"], "about cats");
+                assert_eq!(formatted["body_html"], "<p>cats</p>");
+            },
+        )
+        .await;
+}
+
+#[actix_rt::test]
+async fn search_with_locale_filter() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    index
+        .update_settings(json!({
+            "filterableAttributes": ["price", "published_at"],
+            "dateAttributes": ["published_at"],
+        }))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "price": 999.5, "published_at": "2021-10-01T00:00:00Z" },
+        { "id": 2, "price": 1500, "published_at": "2021-10-20T00:00:00Z" },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = index
+        .search_post(json!({ "filter": "price > 1.234,56", "locale": "fr" }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 1);
+    assert_eq!(response["hits"][0]["id"], 2);
+
+    let (response, code) = index
+        .search_post(json!({ "filter": "published_at < 10/14/2021", "locale": "en-US" }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 1);
+    assert_eq!(response["hits"][0]["id"], 1);
+
+    // The numbers not following the locale are rejected rather than misread.
+    let (response, code) = index
+        .search_post(json!({ "filter": "price > 1.5", "locale": "fr" }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "invalid_filter");
+
+    let (response, code) = index
+        .search_post(json!({ "filter": "price > 10", "locale": "xx" }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "bad_request");
+}
+
+#[actix_rt::test]
+async fn export_search_results() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = server
+        .service
+        .post_raw(
+            "/indexes/test/search/export",
+            json!({ "q": "glass", "fields": ["title"] }),
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response, "{\"title\":\"Glass\"}\n");
+
+    let (response, code) = server
+        .service
+        .post_raw(
+            "/indexes/test/search/export",
+            json!({ "format": "csv", "fields": ["id", "title", "unknown"] }),
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    let lines: Vec<_> = response.lines().collect();
+    assert_eq!(lines.len(), 6);
+    assert_eq!(lines[0], "id,title,unknown");
+    assert_eq!(lines[1], "287947,Shazam!,");
+
+    // The invalid queries fail before anything is streamed.
+    let (response, code) = server
+        .service
+        .post(
+            "/indexes/test/search/export",
+            json!({ "filter": "title = Glass" }),
+        )
+        .await;
+    assert_eq!(code, 400, "{}", response);
+}
+
+#[actix_rt::test]
+async fn search_with_restricted_key() {
+    let temp = tempfile::tempdir().unwrap();
+    let keys_path = temp.path().join("restricted-keys.json");
+    let keys = json!({
+        "restricted": { "indexes": { "test": { "allowedFields": ["title"] } } },
+        "filtered": { "indexes": { "*": { "filter": "id = 299537 OR id = 522681" } } },
+    });
+    std::fs::write(&keys_path, keys.to_string()).unwrap();
+
+    let options = Opt {
+        master_key: Some(String::from("master")),
+        restricted_keys: Some(keys_path.to_str().unwrap().parse().unwrap()),
+        ..default_settings(temp.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+
+    let documents = DOCUMENTS.clone();
+    index.add_documents(documents, None).await;
+    index
+        .update_settings(json!({ "filterableAttributes": ["id"] }))
+        .await;
+    index.wait_update_id(1).await;
+
+    let headers = [("X-Meili-API-Key", "restricted")];
+    let (response, code) = server
+        .service
+        .post_with_headers(
+            "/indexes/test/search",
+            json!({ "q": "glass", "attributesToRetrieve": ["title", "id"] }),
+            &headers,
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"], json!([{ "title": "Glass" }]));
+
+    let (response, code) = server
+        .service
+        .post_with_headers("/indexes/other/search", json!({}), &headers)
+        .await;
+    assert_eq!(code, 403, "{}", response);
+
+    // The restricted keys can only search.
+    let (response, code) = server
+        .service
+        .post_with_headers("/indexes/test/search/export", json!({}), &headers)
+        .await;
+    assert_eq!(code, 403, "{}", response);
+
+    // The restrictions apply to the pages of the scrolls too.
+    let (response, code) = server
+        .service
+        .post_with_headers(
+            "/indexes/test/search",
+            json!({ "limit": 1, "scroll": true }),
+            &headers,
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    let cursor = json!({ "cursor": response["cursor"] });
+    let (response, code) = server
+        .service
+        .post_with_headers("/indexes/other/search/scroll", cursor.clone(), &headers)
+        .await;
+    assert_eq!(code, 403, "{}", response);
+    // Only the key that started the scroll can continue it.
+    let (response, code) = server
+        .service
+        .post("/indexes/test/search/scroll", cursor.clone())
+        .await;
+    assert_eq!(code, 404, "{}", response);
+    let (response, code) = server
+        .service
+        .post_with_headers("/indexes/test/search/scroll", cursor, &headers)
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    let hit = response["hits"][0].as_object().unwrap();
+    assert!(hit.keys().all(|field| field == "title"), "{}", response);
+
+    // The restrictions apply to the keys sent as bearer tokens too.
+    let headers = [("Authorization", "Bearer restricted")];
+    let (response, code) = server
+        .service
+        .post_with_headers("/indexes/other/search", json!({}), &headers)
+        .await;
+    assert_eq!(code, 403, "{}", response);
+    let (response, code) = server
+        .service
+        .post_with_headers(
+            "/indexes/test/search",
+            json!({ "q": "glass", "attributesToRetrieve": ["title", "id"] }),
+            &headers,
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"], json!([{ "title": "Glass" }]));
+
+    // The forced filter is ANDed to the filter of the search.
+    let headers = [("X-Meili-API-Key", "filtered")];
+    let (response, code) = server
+        .service
+        .post_with_headers("/indexes/test/search", json!({}), &headers)
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 2);
+
+    let (response, code) = server
+        .service
+        .post_with_headers(
+            "/indexes/test/search",
+            json!({ "filter": "id = 522681 OR id = 287947" }),
+            &headers,
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(
+        response["hits"],
+        json!([{ "title": "Escape Room", "id": "522681" }])
+    );
+
+    let headers = [("Authorization", "Bearer filtered")];
+    let (response, code) = server
+        .service
+        .post_with_headers("/indexes/test/search", json!({}), &headers)
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 2);
+}
+
+#[actix_rt::test]
+async fn search_with_tenant_token() {
+    let temp = tempfile::tempdir().unwrap();
+    let options = Opt {
+        master_key: Some(String::from("master")),
+        ..default_settings(temp.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+
+    index.add_documents(DOCUMENTS.clone(), None).await;
+    index
+        .update_settings(json!({ "filterableAttributes": ["id"] }))
+        .await;
+    index.wait_update_id(1).await;
+
+    let (keys, code) = server.service.get("/keys").await;
+    assert_eq!(code, 200, "{}", keys);
+    let public_key = keys["public"].as_str().unwrap();
+    let token = |claims: Value| {
+        jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(public_key.as_bytes()),
+        )
+        .unwrap()
+    };
+
+    let tenant = token(json!({
+        "searchRules": { "test": { "filter": "id = 299537 OR id = 522681" } },
+        "apiKeyPrefix": &public_key[..8],
+    }));
+    let authorization = format!("Bearer {}", tenant);
+    let headers = [("Authorization", authorization.as_str())];
+
+    // The filter of the token is ANDed to the filter of the search.
+    let (response, code) = server
+        .service
+        .post_with_headers("/indexes/test/search", json!({}), &headers)
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 2);
+    let (response, code) = server
+        .service
+        .post_with_headers(
+            "/indexes/test/search",
+            json!({ "filter": "id = 522681 OR id = 287947" }),
+            &headers,
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 1);
+
+    let (response, code) = server
+        .service
+        .post_with_headers("/indexes/other/search", json!({}), &headers)
+        .await;
+    assert_eq!(code, 403, "{}", response);
+
+    // The tenant tokens can only search.
+    let (response, code) = server
+        .service
+        .post_with_headers("/indexes/test/documents", json!([{ "id": 1 }]), &headers)
+        .await;
+    assert_eq!(code, 403, "{}", response);
+
+    let expired = token(json!({
+        "searchRules": ["test"],
+        "apiKeyPrefix": &public_key[..8],
+        "exp": chrono::Utc::now().timestamp() - 60,
+    }));
+    let authorization = format!("Bearer {}", expired);
+    let (response, code) = server
+        .service
+        .post_with_headers(
+            "/indexes/test/search",
+            json!({}),
+            &[("Authorization", authorization.as_str())],
+        )
+        .await;
+    assert_eq!(code, 403, "{}", response);
+}
+
+#[actix_rt::test]
+async fn search_depth_exceeded() {
+    let temp = tempfile::tempdir().unwrap();
+    let options = Opt {
+        max_search_depth: 5,
+        ..default_settings(temp.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+    index.add_documents(DOCUMENTS.clone(), None).await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = index.search_post(json!({ "limit": 5 })).await;
+    assert_eq!(code, 200, "{}", response);
+
+    let (response, code) = index.search_post(json!({ "offset": 1, "limit": 5 })).await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "search_depth_exceeded");
+}
+
+#[actix_rt::test]
+async fn search_past_the_end_with_an_unbounded_depth() {
+    let temp = tempfile::tempdir().unwrap();
+    let options = Opt {
+        max_search_depth: usize::MAX,
+        ..default_settings(temp.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+    index.add_documents(DOCUMENTS.clone(), None).await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = index
+        .search_post(json!({ "q": "glass", "offset": usize::MAX, "limit": 20 }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"], json!([]));
+}
+
+#[actix_rt::test]
+async fn search_caps_exceeded() {
+    let temp = tempfile::tempdir().unwrap();
+    let options = Opt {
+        max_search_limit: Some(3),
+        max_search_facets: Some(1),
+        max_attributes_to_retrieve: Some(1),
+        ..default_settings(temp.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+    index.add_documents(DOCUMENTS.clone(), None).await;
+    index
+        .update_settings(json!({ "filterableAttributes": ["id", "title"] }))
+        .await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = index
+        .search_post(
+            json!({ "limit": 3, "attributesToRetrieve": ["title"], "facetsDistribution": ["id"] }),
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+
+    let (response, code) = index
+        .search_post(json!({ "limit": 4, "attributesToRetrieve": ["title"] }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "search_limit_exceeded");
+
+    let (response, code) = index
+        .search_post(
+            json!({ "limit": 1, "attributesToRetrieve": ["title"], "facetsDistribution": ["*"] }),
+        )
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "too_many_facets");
+
+    // All the displayed attributes are retrieved by default.
+    let (response, code) = index.search_post(json!({ "limit": 1 })).await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "too_many_attributes_to_retrieve");
+}
+
+#[actix_rt::test]
+async fn search_after_pagination() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .update_settings(
+            json!({ "sortableAttributes": ["rank"], "filterableAttributes": ["rank"] }),
+        )
+        .await;
+    // The pages must not stop in the middle of the documents with the same rank.
+    let documents: Vec<_> = (1..=5)
+        .rev()
+        .map(|id| json!({ "id": id, "rank": (id + 1) / 2 }))
+        .collect();
+    index.add_documents(json!(documents), None).await;
+    index.wait_update_id(1).await;
+
+    let mut ids = Vec::new();
+    let mut ranks = Vec::new();
+    let mut search_after = json!([]);
+    for _ in 0..3 {
+        let (response, code) = index
+            .search_post(json!({ "sort": ["rank:asc"], "limit": 2, "searchAfter": search_after }))
+            .await;
+        assert_eq!(code, 200, "{}", response);
+        for hit in response["hits"].as_array().unwrap() {
+            ids.push(hit["id"].as_u64().unwrap());
+            ranks.push(hit["rank"].clone());
+        }
+        search_after = response["nextSearchAfter"].clone();
+    }
+    assert_eq!(ranks, [1, 1, 2, 2, 3]);
+    ids.sort_unstable();
+    assert_eq!(ids, [1, 2, 3, 4, 5]);
+    // the last page is not full
+    assert!(search_after.is_null());
+
+    let (response, code) = index
+        .search_post(json!({ "limit": 2, "searchAfter": [] }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "bad_request");
+}
+
+#[actix_rt::test]
+async fn scroll_through_all_the_results() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents: Vec<_> = (1..=5)
+        .map(|id| json!({ "id": id, "color": "blue" }))
+        .collect();
+    index.add_documents(json!(documents), None).await;
+    index.wait_update_id(0).await;
+
+    let (mut response, code) = index
+        .search_post(json!({ "q": "blue", "limit": 2, "scroll": true }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    let mut ids = Vec::new();
+    loop {
+        assert_eq!(response["nbHits"], 5);
+        for hit in response["hits"].as_array().unwrap() {
+            ids.push(hit["id"].as_u64().unwrap());
+        }
+        let cursor = match response["cursor"].as_str() {
+            Some(cursor) => cursor.to_string(),
+            None => break,
+        };
+        let (next, code) = index
+            .service
+            .post("/indexes/test/search/scroll", json!({ "cursor": cursor }))
+            .await;
+        assert_eq!(code, 200, "{}", next);
+        response = next;
+    }
+    ids.sort_unstable();
+    assert_eq!(ids, [1, 2, 3, 4, 5]);
+
+    let (response, code) = index
+        .service
+        .post(
+            "/indexes/test/search/scroll",
+            json!({ "cursor": "unknown" }),
+        )
+        .await;
+    assert_eq!(code, 404, "{}", response);
+    assert_eq!(response["errorCode"], "scroll_not_found");
+}
+
+#[cfg(feature = "shadow-traffic")]
+#[actix_rt::test]
+async fn mirror_the_raw_searches_to_the_shadow() {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    // A shadow receiving a single search.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end().to_lowercase();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(length) = line.strip_prefix("content-length: ") {
+                content_length = length.parse().unwrap();
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n{}")
+            .unwrap();
+        sender.send((request_line, body)).unwrap();
+    });
+
+    let temp = tempfile::tempdir().unwrap();
+    let options = Opt {
+        shadow_url: Some(url),
+        shadow_traffic_percentage: 100.,
+        ..default_settings(temp.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+    index.add_documents(DOCUMENTS.clone(), None).await;
+    index.wait_update_id(0).await;
+
+    // The body is forwarded untouched, without the defaults of this instance.
+    let body = r#"{ "q": "glass",  "limit": 1 }"#;
+    let (response, code) = server.service.post_str("/indexes/test/search", body).await;
+    assert_eq!(code, 200, "{}", response);
+
+    let (request_line, mirrored) = tokio::time::timeout(Duration::from_secs(10), receiver)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(
+        request_line.starts_with("POST /indexes/test/search "),
+        "{}",
+        request_line
+    );
+    assert_eq!(mirrored, body.as_bytes());
 }
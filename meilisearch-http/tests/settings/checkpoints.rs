@@ -0,0 +1,70 @@
+use crate::common::Server;
+use serde_json::json;
+
+#[actix_rt::test]
+async fn restore_checkpoint_taken_before_settings_update() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(Some("id")).await;
+    index
+        .add_documents(
+            json!([{ "id": 1, "title": "foo" }, { "id": 2, "title": "bar" }]),
+            None,
+        )
+        .await;
+    index.wait_update_id(0).await;
+
+    // The update enabling the checkpoints is itself checkpointed.
+    index
+        .update_settings(json!({ "checkpointBeforeSettingsChange": true }))
+        .await;
+    index.wait_update_id(1).await;
+    index
+        .update_settings(json!({ "filterableAttributes": ["title"] }))
+        .await;
+    index.wait_update_id(2).await;
+
+    let (response, code) = server.service.get("/indexes/test/checkpoints").await;
+    assert_eq!(code, 200, "{}", response);
+    let update_ids: Vec<_> = response
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|checkpoint| checkpoint["updateId"].clone())
+        .collect();
+    assert_eq!(update_ids, [json!(2), json!(1)]);
+
+    let (response, code) = server
+        .service
+        .post("/indexes/test/checkpoints/2/restore", json!(null))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["uid"], "test");
+
+    let (response, _) = index.settings().await;
+    assert_eq!(response["filterableAttributes"], json!([]));
+    assert_eq!(response["checkpointBeforeSettingsChange"], json!(true));
+    let (response, _) = index.stats().await;
+    assert_eq!(response["numberOfDocuments"], 2);
+
+    let (response, code) = server
+        .service
+        .post("/indexes/test/checkpoints/0/restore", json!(null))
+        .await;
+    assert_eq!(code, 404, "{}", response);
+    assert_eq!(response["errorCode"], "checkpoint_not_found");
+}
+
+#[actix_rt::test]
+async fn no_checkpoint_by_default() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .update_settings(json!({ "filterableAttributes": ["title"] }))
+        .await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = server.service.get("/indexes/test/checkpoints").await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response, json!([]));
+}
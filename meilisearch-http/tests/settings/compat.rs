@@ -0,0 +1,63 @@
+use meilisearch_http::routes::CompatApiVersion;
+use meilisearch_http::Opt;
+use serde_json::json;
+
+use crate::common::server::default_settings;
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn old_settings_names_with_compat_api() {
+    let temp = tempfile::tempdir().unwrap();
+    let options = Opt {
+        compat_api_version: Some(CompatApiVersion::V0_21),
+        ..default_settings(temp.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+    index.create(None).await;
+
+    let (response, code) = index
+        .update_settings(json!({
+            "filterableAttributes": ["genre"],
+            "rankingRules": ["words", "typo", "desc(release_date)"],
+        }))
+        .await;
+    assert_eq!(code, 202, "{}", response);
+    index.wait_update_id(0).await;
+
+    // The ranking rules are stored in their current shape, and served in the old one.
+    let (response, code) = server.service.get("/indexes/test/settings").await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["filterableAttributes"], json!(["genre"]));
+    assert_eq!(response["rankingRules"][2], "desc(release_date)");
+    assert!(response["_warnings"].is_array());
+
+    let (response, code) = server
+        .service
+        .get("/indexes/test/settings/ranking-rules")
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response[2], "desc(release_date)");
+
+    // The `attributesForFaceting` of v0.20 was already renamed in v0.21.
+    let (_response, code) = server
+        .service
+        .get("/indexes/test/settings/attributes-for-faceting")
+        .await;
+    assert_eq!(code, 404);
+
+    let (response, code) = server.service.get("/indexes/test/updates").await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response.as_array().unwrap().len(), 1);
+    assert_eq!(response[0]["status"], "processed");
+}
+
+#[actix_rt::test]
+async fn old_routes_without_compat_api() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+
+    let (_response, code) = server.service.get("/indexes/test/updates").await;
+    assert_eq!(code, 404);
+}
@@ -24,6 +24,31 @@ static DEFAULT_SETTINGS_VALUES: Lazy<HashMap<&'static str, Value>> = Lazy::new(|
     );
     map.insert("stop_words", json!([]));
     map.insert("synonyms", json!({}));
+    map.insert("date_attributes", json!([]));
+    map.insert("flatten_strategy", json!("none"));
+    map.insert("sort_collation", json!("binary"));
+    map.insert("normalized_filterable_attributes", json!([]));
+    map.insert("searchable_numeric_attributes", json!([]));
+    map.insert("raw_attributes", json!([]));
+    map.insert("checkpoint_before_settings_change", json!(false));
+    map.insert(
+        "typo_tolerance",
+        json!({
+            "enabled": true,
+            "minWordSizeForTypos": { "oneTypo": 5, "twoTypos": 9 },
+            "disableOnWords": [],
+            "disableOnAttributes": [],
+        }),
+    );
+    map.insert(
+        "search_defaults",
+        json!({
+            "limit": null,
+            "attributesToHighlight": null,
+            "cropLength": null,
+            "matchingStrategy": null,
+        }),
+    );
     map
 });
 
@@ -42,7 +67,7 @@ async fn get_settings() {
     let (response, code) = index.settings().await;
     assert_eq!(code, 200);
     let settings = response.as_object().unwrap();
-    assert_eq!(settings.keys().len(), 8);
+    assert_eq!(settings.keys().len(), 17);
     assert_eq!(settings["displayedAttributes"], json!(["*"]));
     assert_eq!(settings["searchableAttributes"], json!(["*"]));
     assert_eq!(settings["filterableAttributes"], json!([]));
@@ -60,6 +85,15 @@ async fn get_settings() {
         ])
     );
     assert_eq!(settings["stopWords"], json!([]));
+    assert_eq!(settings["dateAttributes"], json!([]));
+    assert_eq!(settings["flattenStrategy"], json!("none"));
+    assert_eq!(settings["sortCollation"], json!("binary"));
+    assert_eq!(settings["normalizedFilterableAttributes"], json!([]));
+    assert_eq!(settings["searchableNumericAttributes"], json!([]));
+    assert_eq!(settings["rawAttributes"], json!([]));
+    assert_eq!(settings["checkpointBeforeSettingsChange"], json!(false));
+    assert_eq!(settings["typoTolerance"]["enabled"], json!(true));
+    assert_eq!(settings["searchDefaults"]["limit"], json!(null));
 }
 
 #[actix_rt::test]
@@ -94,6 +128,108 @@ async fn test_partial_update() {
     assert_eq!(response["searchableAttributes"], json!(["bar"]));
 }
 
+#[actix_rt::test]
+async fn lint_settings() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .add_documents(json!([{ "id": 1, "title": "Carol" }]), None)
+        .await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = server.service.get("/indexes/test/settings/lint").await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response, json!({ "warnings": [] }));
+
+    index
+        .update_settings(json!({ "sortableAttributes": ["price"] }))
+        .await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = server.service.get("/indexes/test/settings/lint").await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(
+        response,
+        json!({
+            "warnings": [{
+                "setting": "sortableAttributes",
+                "message": "The attribute `price` is not present in any document.",
+            }]
+        })
+    );
+
+    let (response, code) = index.search_post(json!({ "q": "carol" })).await;
+    assert_eq!(code, 200, "{}", response);
+
+    let (response, code) = server.service.get("/indexes/test/settings/lint").await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(
+        response,
+        json!({
+            "warnings": [
+                {
+                    "setting": "sortableAttributes",
+                    "message": "The attribute `price` is not present in any document.",
+                },
+                {
+                    "setting": "searchableAttributes",
+                    "message": "The attribute `id` didn't match the words of any of the 1 searches made since the launch.",
+                },
+                {
+                    "setting": "sortableAttributes",
+                    "message": "The attribute `price` wasn't sorted on by any of the 1 searches made since the launch.",
+                },
+            ]
+        })
+    );
+}
+
+#[actix_rt::test]
+async fn attribute_patterns_match_the_attributes_of_the_next_documents() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .update_settings(json!({
+            "flattenStrategy": "flatten",
+            "filterableAttributes": ["meta.*"],
+        }))
+        .await;
+    index.wait_update_id(0).await;
+
+    index
+        .add_documents(
+            json!([
+                { "id": 1, "meta": { "author": "hugo", "year": 1862 } },
+                { "id": 2, "meta": { "author": "zola", "year": 1885 } },
+            ]),
+            None,
+        )
+        .await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = index
+        .search_post(json!({ "filter": "meta.author = hugo" }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 1);
+    assert_eq!(response["hits"][0]["id"], 1);
+
+    index
+        .add_documents(json!([{ "id": 3, "meta": { "genre": "novel" } }]), None)
+        .await;
+    index.wait_update_id(2).await;
+
+    let (response, code) = index
+        .search_post(json!({ "filter": "meta.genre = novel" }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 1);
+
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["filterableAttributes"], json!(["meta.*"]));
+}
+
 #[actix_rt::test]
 async fn delete_settings_unexisting_index() {
     let server = Server::new().await;
@@ -244,5 +380,14 @@ test_setting_routes!(
     distinct_attribute,
     stop_words,
     ranking_rules,
-    synonyms
+    synonyms,
+    date_attributes,
+    flatten_strategy,
+    sort_collation,
+    normalized_filterable_attributes,
+    searchable_numeric_attributes,
+    raw_attributes,
+    checkpoint_before_settings_change,
+    typo_tolerance,
+    search_defaults
 );
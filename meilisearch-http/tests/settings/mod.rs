@@ -1,2 +1,4 @@
+mod checkpoints;
+mod compat;
 mod distinct;
 mod get_settings;
@@ -1,5 +1,7 @@
+use meilisearch_http::Opt;
 use serde_json::json;
 
+use crate::common::server::default_settings;
 use crate::common::Server;
 
 #[actix_rt::test]
@@ -20,6 +22,8 @@ async fn test_healthyness() {
     let (response, status_code) = server.service.get("/health").await;
     assert_eq!(status_code, 200);
     assert_eq!(response["status"], "available");
+    assert_eq!(response["warmup"]["mode"], "none");
+    assert_eq!(response["warmup"]["finished"], true);
 }
 
 #[actix_rt::test]
@@ -68,3 +72,70 @@ async fn stats() {
     assert_eq!(response["indexes"]["test"]["fieldDistribution"]["name"], 1);
     assert_eq!(response["indexes"]["test"]["fieldDistribution"]["age"], 1);
 }
+
+#[cfg(target_os = "linux")]
+#[actix_rt::test]
+async fn memory_stats() {
+    let server = Server::new().await;
+
+    let (response, code) = server.service.get("/debug/memory").await;
+    assert_eq!(code, 200, "{}", response);
+    assert!(response["allocated"].is_u64());
+    assert!(response["resident"].is_u64());
+    assert!(response["arenas"].is_array());
+}
+
+#[cfg(all(target_os = "linux", feature = "profiling"))]
+#[actix_rt::test]
+async fn cpu_profile() {
+    let server = Server::new().await;
+
+    let (response, code) = server
+        .service
+        .post("/debug/profile?durationSec=0", json!(null))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+
+    let (_response, code) = server
+        .service
+        .post("/debug/profile?durationSec=1", json!(null))
+        .await;
+    assert_eq!(code, 200);
+}
+
+#[actix_rt::test]
+async fn instance() {
+    let server = Server::new().await;
+    let (response, code) = server.service.get("/instance").await;
+
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["pkgVersion"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(response["env"], "development");
+    assert_eq!(response["options"]["masterKey"], json!(null));
+    assert_eq!(response["options"]["searchQueueTimeoutMs"], 1000);
+    assert!(response["features"].is_array());
+    assert_eq!(
+        response["deprecations"][0]["name"],
+        "GET /indexes/{indexUid}/updates/{updateId}"
+    );
+}
+
+#[actix_rt::test]
+async fn database_in_memory() {
+    let temp = tempfile::tempdir().unwrap();
+    let options = Opt {
+        db_in_memory: true,
+        ..default_settings(temp.path())
+    };
+    let db_path = options.db_path.clone();
+    let server = Server::new_with_options(options).await;
+
+    let index = server.index("test");
+    index.add_documents(json!([{ "id": 1 }]), None).await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = server.stats().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["indexes"]["test"]["numberOfDocuments"], 1);
+    assert!(!db_path.exists());
+}
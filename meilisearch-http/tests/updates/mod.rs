@@ -1,3 +1,8 @@
+use serde_json::json;
+
+use meilisearch_http::Opt;
+
+use crate::common::server::default_settings;
 use crate::common::Server;
 
 #[actix_rt::test]
@@ -36,24 +41,25 @@ async fn get_update_status() {
 }
 
 #[actix_rt::test]
-async fn list_updates_unexisting_index() {
+async fn list_tasks_unexisting_index() {
     let server = Server::new().await;
-    let (_response, code) = server.index("test").list_updates().await;
+    let (_response, code) = server.index("test").list_tasks().await;
     assert_eq!(code, 404);
 }
 
 #[actix_rt::test]
-async fn list_no_updates() {
+async fn list_no_tasks() {
     let server = Server::new().await;
     let index = server.index("test");
     index.create(None).await;
-    let (response, code) = index.list_updates().await;
+    let (response, code) = index.list_tasks().await;
     assert_eq!(code, 200);
-    assert!(response.as_array().unwrap().is_empty());
+    assert!(response["results"].as_array().unwrap().is_empty());
+    assert_eq!(response["next"], json!(null));
 }
 
 #[actix_rt::test]
-async fn list_updates() {
+async fn list_tasks() {
     let server = Server::new().await;
     let index = server.index("test");
     index.create(None).await;
@@ -63,7 +69,594 @@ async fn list_updates() {
             None,
         )
         .await;
-    let (response, code) = index.list_updates().await;
+    let (response, code) = index.list_tasks().await;
     assert_eq!(code, 200);
-    assert_eq!(response.as_array().unwrap().len(), 1);
+    assert_eq!(response["results"].as_array().unwrap().len(), 1);
+    assert_eq!(response["results"][0]["indexUid"], "test");
+}
+
+#[actix_rt::test]
+async fn filter_and_paginate_tasks() {
+    let server = Server::new().await;
+    let movies = server.index("movies");
+    movies.create(Some("id")).await;
+    let books = server.index("books");
+    books.create(Some("id")).await;
+
+    for id in 0..3 {
+        movies.add_documents(json!([{ "id": id }]), None).await;
+    }
+    let (response, _) = books.add_documents(json!([{ "id": 0 }]), None).await;
+    books
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    let (response, _) = movies.delete_document(0).await;
+    movies
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = server.service.get("/tasks").await;
+    assert_eq!(code, 200, "{}", response);
+    let uids: Vec<_> = response["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|task| task["uid"].as_u64().unwrap())
+        .collect();
+    assert_eq!(uids.len(), 5);
+    assert!(uids.windows(2).all(|pair| pair[0] > pair[1]));
+    assert_eq!(response["results"][0]["type"]["name"], "DocumentsDeletion");
+
+    let (response, code) = server
+        .service
+        .get("/tasks?indexUid=movies&type=DocumentsAddition&status=processed")
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    let results = response["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(|task| task["indexUid"] == "movies"));
+
+    // The index uids can be patterns.
+    let (response, code) = server.service.get("/tasks?indexUid=mov*").await;
+    assert_eq!(code, 200, "{}", response);
+    let results = response["results"].as_array().unwrap();
+    assert_eq!(results.len(), 4);
+    assert!(results.iter().all(|task| task["indexUid"] == "movies"));
+    let (response, _) = server.service.get("/tasks?indexUid=*").await;
+    assert_eq!(response["results"].as_array().unwrap().len(), 5);
+
+    let (response, code) = server.service.get("/tasks?limit=2").await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["results"].as_array().unwrap().len(), 2);
+    assert_eq!(response["from"], uids[0]);
+    assert_eq!(response["next"], uids[2]);
+    let url = format!("/tasks?limit=2&from={}", uids[4]);
+    let (response, _) = server.service.get(url).await;
+    assert_eq!(response["results"].as_array().unwrap().len(), 1);
+    assert_eq!(response["next"], json!(null));
+
+    let url = format!("/tasks/{}", uids[4]);
+    let (response, code) = server.service.get(url).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["uid"], uids[4]);
+    assert_eq!(response["indexUid"], "movies");
+    let (_response, code) = server.service.get("/tasks/4242").await;
+    assert_eq!(code, 404);
+
+    let (response, code) = server.service.get("/tasks?status=done").await;
+    assert_eq!(code, 400, "{}", response);
+}
+
+#[actix_rt::test]
+async fn update_metadata() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+
+    let metadata = json!({ "jobId": "nightly-import", "source": "crm" });
+    let header = metadata.to_string();
+    let (response, code) = server
+        .service
+        .post_with_headers(
+            "/indexes/test/documents",
+            json!([{ "id": 1 }]),
+            &[("X-Meili-Update-Metadata", header.as_str())],
+        )
+        .await;
+    assert_eq!(code, 202, "{}", response);
+    let update_id = response["updateId"].as_u64().unwrap();
+
+    let response = index.wait_update_id(update_id).await;
+    assert_eq!(response["metadata"], metadata);
+
+    let (response, code) = index.list_tasks().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["results"][0]["metadata"], metadata);
+
+    let (response, code) = server
+        .service
+        .post_with_headers(
+            "/indexes/test/documents",
+            json!([{ "id": 2 }]),
+            &[("X-Meili-Update-Metadata", "[\"not an object\"]")],
+        )
+        .await;
+    assert_eq!(code, 400);
+    assert_eq!(response["errorCode"], "invalid_update_metadata");
+
+    let (response, _) = index.add_documents(json!([{ "id": 3 }]), None).await;
+    let response = index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    assert!(response.get("metadata").is_none());
+}
+
+#[actix_rt::test]
+async fn trace_updates() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+
+    let traceparent = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+    let (response, code) = server
+        .service
+        .post_with_headers(
+            "/indexes/test/documents",
+            json!([{ "id": 1 }]),
+            &[("traceparent", traceparent), ("X-Request-Id", "import-42")],
+        )
+        .await;
+    assert_eq!(code, 202, "{}", response);
+    let update_id = response["updateId"].as_u64().unwrap();
+
+    let response = index.wait_update_id(update_id).await;
+    assert_eq!(response["status"], "processed");
+    let trace = json!({ "traceparent": traceparent, "requestId": "import-42" });
+    assert_eq!(response["trace"], trace);
+
+    let (response, code) = index.list_tasks().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["results"][0]["trace"], trace);
+
+    let (response, _) = index.add_documents(json!([{ "id": 2 }]), None).await;
+    let response = index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    assert!(response.get("trace").is_none());
+}
+
+#[actix_rt::test]
+async fn wait_batched_updates() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    for id in 0..3 {
+        index.add_documents(json!([{ "id": id }]), None).await;
+    }
+
+    let (response, code) = server
+        .service
+        .post(
+            "/indexes/test/updates/wait",
+            json!({ "uids": [0, 1, 2], "timeout": 10000 }),
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response.as_array().unwrap().len(), 3);
+    assert!(response
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|update| update["status"] == "processed"));
+
+    let (response, code) = server
+        .service
+        .post("/indexes/test/updates/wait", json!({ "uids": [42] }))
+        .await;
+    assert_eq!(code, 404, "{}", response);
+}
+
+#[actix_rt::test]
+async fn lookup_tasks_by_uid() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    for id in 0..3 {
+        index.add_documents(json!([{ "id": id }]), None).await;
+    }
+
+    let (response, code) = server.service.get("/tasks?uids=0,2,7").await;
+    assert_eq!(code, 200, "{}", response);
+    let uids: Vec<_> = response["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|task| task["uid"].clone())
+        .collect();
+    assert_eq!(uids, [json!(2), json!(0)]);
+
+    let (response, code) = server.service.get("/tasks?uids=0,a").await;
+    assert_eq!(code, 400, "{}", response);
+}
+
+#[actix_rt::test]
+async fn batch_consecutive_document_additions() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(Some("id")).await;
+    // The first addition keeps the index busy while the next ones are enqueued.
+    index
+        .add_documents(
+            serde_json::from_str(include_str!("../assets/test_set.json")).unwrap(),
+            None,
+        )
+        .await;
+    index
+        .add_documents(json!([{ "id": 1000 }, { "id": 1001 }]), None)
+        .await;
+    index
+        .add_documents(json!([{ "id": 1001, "title": "updated" }]), None)
+        .await;
+    index.update_documents(json!([{ "id": 1002 }]), None).await;
+    index
+        .add_documents(json!([{ "title": "no id" }]), None)
+        .await;
+    index.add_documents(json!([{ "id": 1003 }]), None).await;
+    index.wait_update_id(5).await;
+
+    let (response, code) = index.list_tasks().await;
+    assert_eq!(code, 200, "{}", response);
+    let updates = response["results"].as_array().unwrap();
+    let status = |id: u64| {
+        updates
+            .iter()
+            .find(|update| update["updateId"] == id)
+            .unwrap()
+    };
+    assert_eq!(status(1)["status"], "processed");
+    assert_eq!(status(1)["type"]["number"], 2);
+    assert_eq!(status(2)["status"], "processed");
+    assert_eq!(status(2)["type"]["number"], 1);
+    assert_eq!(status(3)["status"], "processed");
+    // The failure of an addition of a batch doesn't fail the other ones.
+    assert_eq!(status(4)["status"], "failed");
+    assert_eq!(status(5)["status"], "processed");
+    // The additions of a batch are processed together.
+    for id in 2..=5 {
+        assert_eq!(status(id)["processedAt"], status(1)["processedAt"]);
+    }
+    assert_ne!(status(0)["processedAt"], status(1)["processedAt"]);
+
+    let (response, code) = index.get_document(1001, None).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["title"], "updated");
+}
+
+#[actix_rt::test]
+async fn get_update_status_is_deprecated() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    index.add_documents(json!([{ "id": 1 }]), None).await;
+    index.wait_update_id(0).await;
+
+    let (response, code, headers) = server
+        .service
+        .get_with_headers("/indexes/test/updates/0")
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    let warning = headers.get("warning").unwrap().to_str().unwrap();
+    assert!(warning.starts_with("299 meilisearch "), "{}", warning);
+    assert!(warning.contains("GET /tasks/{taskUid}"), "{}", warning);
+    assert_eq!(response["_warnings"].as_array().unwrap().len(), 1);
+
+    let (response, _code, headers) = server.service.get_with_headers("/tasks/0").await;
+    assert!(headers.get("warning").is_none());
+    assert!(response.get("_warnings").is_none());
+}
+
+#[actix_rt::test]
+async fn get_update_status_waiting_for_completion() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    index
+        .add_documents(
+            serde_json::from_str(include_str!("../assets/test_set.json")).unwrap(),
+            None,
+        )
+        .await;
+
+    let (response, code) = server
+        .service
+        .get("/indexes/test/updates/0?waitForCompletion=true&timeoutMs=30000")
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["status"], "processed");
+
+    let (_response, code) = server
+        .service
+        .get("/indexes/test/updates/1?waitForCompletion=true")
+        .await;
+    assert_eq!(code, 404);
+}
+
+#[actix_rt::test]
+async fn wait_task() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    index
+        .add_documents(
+            serde_json::from_str(include_str!("../assets/test_set.json")).unwrap(),
+            None,
+        )
+        .await;
+
+    let (response, code) = server.service.get("/tasks/0/wait?timeoutMs=30000").await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["uid"], 0);
+    assert_eq!(response["indexUid"], "test");
+    assert_eq!(response["status"], "processed");
+
+    let (_response, code) = server.service.get("/tasks/1/wait").await;
+    assert_eq!(code, 404);
+}
+
+#[actix_rt::test]
+async fn get_failed_update_payload() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(Some("id")).await;
+
+    let documents = json!([{ "id": 1, "title": "kept" }, { "title": "no id" }]);
+    let (response, _) = index.add_documents(documents, None).await;
+    let update_id = response["updateId"].as_u64().unwrap();
+    let response = index.wait_update_id(update_id).await;
+    assert_eq!(response["status"], "failed");
+
+    let url = format!("/indexes/test/updates/{}/payload", update_id);
+    let (response, code) = server.service.get_raw(&url).await;
+    assert_eq!(code, 200, "{}", response);
+    let documents: Vec<serde_json::Value> = response
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(
+        documents,
+        [
+            json!({ "id": 1, "title": "kept" }),
+            json!({ "title": "no id" })
+        ]
+    );
+
+    let (response, _) = index.add_documents(json!([{ "id": 2 }]), None).await;
+    let update_id = response["updateId"].as_u64().unwrap();
+    index.wait_update_id(update_id).await;
+
+    let url = format!("/indexes/test/updates/{}/payload", update_id);
+    let (_response, code) = server.service.get(&url).await;
+    assert_eq!(code, 404);
+}
+
+#[actix_rt::test]
+async fn retry_failed_update() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+
+    // No primary key can be inferred from the documents.
+    let (response, _) = index
+        .add_documents(json!([{ "title": "Carol" }]), None)
+        .await;
+    let failed_id = response["updateId"].as_u64().unwrap();
+    let response = index.wait_update_id(failed_id).await;
+    assert_eq!(response["status"], "failed");
+
+    let (response, code) = index.update(Some("title")).await;
+    assert_eq!(code, 200, "{}", response);
+
+    let url = format!("/indexes/test/updates/{}/retry", failed_id);
+    let (response, code) = server.service.post(&url, json!(null)).await;
+    assert_eq!(code, 202, "{}", response);
+    let retry_id = response["updateId"].as_u64().unwrap();
+
+    let response = index.wait_update_id(retry_id).await;
+    assert_eq!(response["status"], "processed");
+    assert_eq!(response["retryOf"], failed_id);
+
+    let url = format!("/indexes/test/updates/{}/retry", retry_id);
+    let (response, code) = server.service.post(&url, json!(null)).await;
+    assert_eq!(code, 400);
+    assert_eq!(response["errorCode"], "update_not_retryable");
+}
+
+#[actix_rt::test]
+async fn cancel_enqueued_updates() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(Some("id")).await;
+
+    let (response, code) = server.service.post("/tasks/cancel", json!(null)).await;
+    assert_eq!(code, 400, "{}", response);
+    let (response, code) = server
+        .service
+        .post("/tasks/cancel?statuses=processed", json!(null))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+
+    let mut update_ids = Vec::new();
+    for i in 0..10 {
+        let (response, _) = index.add_documents(json!([{ "id": i }]), None).await;
+        update_ids.push(response["updateId"].as_u64().unwrap());
+    }
+
+    let (response, code) = server
+        .service
+        .post(
+            "/tasks/cancel?indexUids=test&statuses=enqueued",
+            json!(null),
+        )
+        .await;
+    assert_eq!(code, 202, "{}", response);
+    let canceled_by = response["canceledBy"].as_u64().unwrap();
+
+    // The updates processed before the cancellation are left as they are.
+    for canceled in response["canceled"].as_array().unwrap() {
+        assert_eq!(canceled["indexUid"], "test");
+        let update_id = canceled["updateId"].as_u64().unwrap();
+        let response = index.wait_update_id(update_id).await;
+        assert_eq!(response["status"], "canceled", "{}", response);
+        assert_eq!(response["canceledBy"], canceled_by);
+    }
+    let last = index.wait_update_id(*update_ids.last().unwrap()).await;
+    assert!(last["status"] == "processed" || last["status"] == "canceled");
+}
+
+#[actix_rt::test]
+async fn delete_finished_updates() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(Some("id")).await;
+
+    let (response, code) = server.service.delete("/tasks").await;
+    assert_eq!(code, 400, "{}", response);
+    let (response, code) = server.service.delete("/tasks?statuses=enqueued").await;
+    assert_eq!(code, 400, "{}", response);
+
+    let (response, _) = index.add_documents(json!([{ "id": 1 }]), None).await;
+    let addition_id = response["updateId"].as_u64().unwrap();
+    index.wait_update_id(addition_id).await;
+    let (response, _) = index.delete_document(1).await;
+    let deletion_id = response["updateId"].as_u64().unwrap();
+    index.wait_update_id(deletion_id).await;
+
+    let (response, code) = server
+        .service
+        .delete("/tasks?indexUids=test&types=DocumentsAddition&statuses=processed")
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(
+        response["deleted"],
+        json!([{ "indexUid": "test", "updateId": addition_id }])
+    );
+
+    let (_response, code) = index.get_update(addition_id).await;
+    assert_eq!(code, 404);
+    let (_response, code) = index.get_update(deletion_id).await;
+    assert_eq!(code, 200);
+}
+
+#[cfg(feature = "task-webhook")]
+#[actix_rt::test]
+async fn notify_finished_tasks_to_webhook() {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    use meilisearch_http::webhook::setup_task_webhook;
+    use meilisearch_http::Opt;
+
+    use crate::common::server::default_settings;
+
+    // A webhook receiving a single notification.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let (mut content_length, mut authorization) = (0, String::new());
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end().to_lowercase();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(length) = line.strip_prefix("content-length: ") {
+                content_length = length.parse().unwrap();
+            }
+            if let Some(value) = line.strip_prefix("authorization: ") {
+                authorization = value.to_string();
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .unwrap();
+        sender.send((authorization, body)).unwrap();
+    });
+
+    let temp = tempfile::tempdir().unwrap();
+    let options = Opt {
+        task_webhook_url: Some(url),
+        task_webhook_authorization: Some(String::from("Bearer secret")),
+        ..default_settings(temp.path())
+    };
+    let server = Server::new_with_options(options.clone()).await;
+    setup_task_webhook(server.service.meilisearch.clone(), &options)
+        .await
+        .unwrap();
+
+    let index = server.index("test");
+    index.add_documents(json!([{ "id": 1 }]), None).await;
+    index.wait_update_id(0).await;
+
+    let (authorization, body) = tokio::time::timeout(Duration::from_secs(10), receiver)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(authorization, "bearer secret");
+    let task: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(task["uid"], 0);
+    assert_eq!(task["indexUid"], "test");
+    assert_eq!(task["status"], "processed");
+}
+
+#[actix_rt::test]
+async fn control_the_update_archive() {
+    let server = Server::new().await;
+    let (response, code) = server.service.get("/archive").await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "update_archive_disabled");
+
+    let temp = tempfile::tempdir().unwrap();
+    let options = Opt {
+        update_archive_dir: Some(temp.path().join("archive")),
+        ..default_settings(temp.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+    index.add_documents(json!([{ "id": 1 }]), None).await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = server.service.get("/archive").await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["archiving"], true);
+    assert_eq!(response["updates"], 1);
+
+    let (response, code) = server
+        .service
+        .patch("/archive", json!({ "archiving": false }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["archiving"], false);
+    index.add_documents(json!([{ "id": 2 }]), None).await;
+    index.wait_update_id(1).await;
+    let (response, _) = server.service.get("/archive").await;
+    assert_eq!(response["updates"], 1);
+
+    // The archived update is known to the database, it isn't replayed.
+    let (response, code) = server
+        .service
+        .post(
+            "/archive/replay",
+            json!({ "until": "2100-01-01T00:00:00Z" }),
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["replayed"], 0);
 }
@@ -0,0 +1,94 @@
+use serde_json::json;
+
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn create_update_and_delete_webhook() {
+    let server = Server::new().await;
+
+    let (webhook, code) = server
+        .service
+        .post(
+            "/webhooks",
+            json!({
+                "url": "https://example.com/hook",
+                "authorization": "Bearer secret",
+                "indexUid": "products",
+                "events": ["documentAddition.failed", "settingsUpdate.*"],
+            }),
+        )
+        .await;
+    assert_eq!(code, 201, "{}", webhook);
+    assert_eq!(webhook["indexUid"], "products");
+    // The secret of the authorization is never returned.
+    assert_eq!(webhook["authorization"], "Bearer ***");
+    assert_eq!(
+        webhook["events"],
+        json!(["documentAddition.failed", "settingsUpdate.*"])
+    );
+    let uid = webhook["uid"].as_str().unwrap();
+
+    let (response, code) = server
+        .service
+        .patch(
+            format!("/webhooks/{}", uid),
+            json!({ "indexUid": null, "events": ["*"] }),
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["indexUid"], json!(null));
+    assert_eq!(response["events"], json!(["*"]));
+
+    let (response, code) = server.service.get("/webhooks").await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["results"].as_array().unwrap().len(), 1);
+    assert_eq!(response["results"][0]["authorization"], "Bearer ***");
+
+    let (_, code) = server.service.delete(format!("/webhooks/{}", uid)).await;
+    assert_eq!(code, 204);
+    let (response, code) = server.service.get(format!("/webhooks/{}", uid)).await;
+    assert_eq!(code, 404, "{}", response);
+    assert_eq!(response["errorCode"], "webhook_not_found");
+}
+
+#[actix_rt::test]
+async fn create_webhook_on_alias() {
+    let server = Server::new().await;
+    server.index("products_v1").create(None).await;
+    server
+        .service
+        .put("/aliases/products", json!({ "indexUid": "products_v1" }))
+        .await;
+
+    // The webhook is notified of the tasks of the index the alias pointed to.
+    let (webhook, code) = server
+        .service
+        .post(
+            "/webhooks",
+            json!({ "url": "https://example.com/hook", "indexUid": "products" }),
+        )
+        .await;
+    assert_eq!(code, 201, "{}", webhook);
+    assert_eq!(webhook["indexUid"], "products_v1");
+}
+
+#[actix_rt::test]
+async fn create_invalid_webhook() {
+    let server = Server::new().await;
+
+    let (response, code) = server
+        .service
+        .post(
+            "/webhooks",
+            json!({ "url": "https://example.com/hook", "events": ["documentAddition.enqueued"] }),
+        )
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["errorCode"], "invalid_webhook");
+
+    let (response, code) = server
+        .service
+        .post("/webhooks", json!({ "url": "example.com" }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+}
@@ -3,13 +3,40 @@ use std::io::Write;
 use std::path::Path;
 
 use flate2::{read::GzDecoder, write::GzEncoder, Compression};
-use tar::{Archive, Builder};
+use tar::{Archive, Builder, Header};
+use walkdir::WalkDir;
 
-pub fn to_tar_gz(src: impl AsRef<Path>, dest: impl AsRef<Path>) -> anyhow::Result<()> {
+use crate::io_throttle::{IoThrottle, Throttled};
+
+/// Archives the `src` directory in the gzipped tarball `dest`, its files being read at the rate
+/// of the throttle when one is given.
+pub fn to_tar_gz(
+    src: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    throttle: Option<&IoThrottle>,
+) -> anyhow::Result<()> {
     let mut f = File::create(dest)?;
     let gz_encoder = GzEncoder::new(&mut f, Compression::default());
     let mut tar_encoder = Builder::new(gz_encoder);
-    tar_encoder.append_dir_all(".", src)?;
+    match throttle {
+        Some(throttle) => {
+            let src = src.as_ref();
+            for entry in WalkDir::new(src).min_depth(1) {
+                let entry = entry?;
+                let name = Path::new(".").join(entry.path().strip_prefix(src)?);
+                if entry.file_type().is_dir() {
+                    tar_encoder.append_dir(&name, entry.path())?;
+                } else if entry.file_type().is_file() {
+                    let file = File::open(entry.path())?;
+                    let mut header = Header::new_gnu();
+                    header.set_metadata(&file.metadata()?);
+                    let reader = Throttled::new(file, Some(throttle));
+                    tar_encoder.append_data(&mut header, &name, reader)?;
+                }
+            }
+        }
+        None => tar_encoder.append_dir_all(".", src)?,
+    }
     let gz_encoder = tar_encoder.into_inner()?;
     gz_encoder.finish()?;
     f.flush()?;
@@ -24,3 +51,33 @@ pub fn from_tar_gz(src: impl AsRef<Path>, dest: impl AsRef<Path>) -> anyhow::Res
     ar.unpack(&dest)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[test]
+    fn throttled_tar_gz() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        create_dir_all(src.join("indexes")).unwrap();
+        std::fs::write(src.join("indexes/data.mdb"), vec![1u8; 4096]).unwrap();
+        std::fs::write(src.join("VERSION"), b"0.23.0").unwrap();
+
+        let archive = dir.path().join("archive.tar.gz");
+        let start = Instant::now();
+        to_tar_gz(&src, &archive, Some(&IoThrottle::new(40 * 1024))).unwrap();
+        // the 4102 bytes are read in about 100ms
+        assert!(start.elapsed() >= Duration::from_millis(90));
+
+        let dst = dir.path().join("dst");
+        from_tar_gz(&archive, &dst).unwrap();
+        assert_eq!(
+            std::fs::read(dst.join("indexes/data.mdb")).unwrap(),
+            vec![1u8; 4096]
+        );
+        assert_eq!(std::fs::read(dst.join("VERSION")).unwrap(), b"0.23.0");
+    }
+}
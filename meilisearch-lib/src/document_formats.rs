@@ -4,8 +4,11 @@ use std::io::{self, Read, Result as IoResult, Seek, Write};
 use csv::{Reader as CsvReader, StringRecordsIntoIter};
 use meilisearch_error::{Code, ErrorCode};
 use milli::documents::DocumentBatchBuilder;
+use serde::de::{Deserializer as _, SeqAccess, Visitor};
 use serde_json::{Deserializer, Map, Value};
 
+use crate::index_controller::DocumentAdditionFormat;
+
 type Result<T> = std::result::Result<T, DocumentFormatError>;
 
 #[derive(Debug)]
@@ -34,6 +37,18 @@ pub enum DocumentFormatError {
         Box<dyn std::error::Error + Send + Sync + 'static>,
         PayloadType,
     ),
+    #[error("The document at position {position} of the payload has {fields} fields, but documents are limited to {max} fields.")]
+    TooManyFields {
+        position: usize,
+        fields: usize,
+        max: usize,
+    },
+    #[error("The document at position {position} of the payload is {size} bytes large, but documents are limited to {max} bytes.")]
+    DocumentTooLarge {
+        position: usize,
+        size: usize,
+        max: usize,
+    },
 }
 
 impl ErrorCode for DocumentFormatError {
@@ -41,6 +56,8 @@ impl ErrorCode for DocumentFormatError {
         match self {
             DocumentFormatError::Internal(_) => Code::Internal,
             DocumentFormatError::MalformedPayload(_, _) => Code::MalformedPayload,
+            DocumentFormatError::TooManyFields { .. } => Code::MaxFieldsLimitExceeded,
+            DocumentFormatError::DocumentTooLarge { .. } => Code::DocumentTooLarge,
         }
     }
 }
@@ -53,12 +70,80 @@ macro_rules! malformed {
     };
 }
 
-pub fn read_csv(input: impl Read, writer: impl Write + Seek) -> Result<()> {
+/// The limits enforced on every document of a payload, so that a single malformed document can't
+/// bloat the fields of an index.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DocumentLimits {
+    /// The maximum number of fields of a document, the fields of its nested objects included.
+    pub max_fields: Option<usize>,
+    /// The maximum size of a document, in bytes of JSON.
+    pub max_size: Option<usize>,
+}
+
+impl DocumentLimits {
+    pub(crate) fn check(&self, position: usize, document: &Map<String, Value>) -> Result<()> {
+        if let Some(max) = self.max_fields {
+            let fields = count_fields(document);
+            if fields > max {
+                return Err(DocumentFormatError::TooManyFields {
+                    position,
+                    fields,
+                    max,
+                });
+            }
+        }
+
+        if let Some(max) = self.max_size {
+            let mut counter = ByteCounter(0);
+            serde_json::to_writer(&mut counter, document)
+                .map_err(|e| DocumentFormatError::Internal(Box::new(e)))?;
+            if counter.0 > max {
+                return Err(DocumentFormatError::DocumentTooLarge {
+                    position,
+                    size: counter.0,
+                    max,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Counts the fields of an object, and of the objects nested in it.
+fn count_fields(object: &Map<String, Value>) -> usize {
+    fn nested_fields(value: &Value) -> usize {
+        match value {
+            Value::Object(object) => count_fields(object),
+            Value::Array(values) => values.iter().map(nested_fields).sum(),
+            _ => 0,
+        }
+    }
+
+    object.len() + object.values().map(nested_fields).sum::<usize>()
+}
+
+/// A writer counting the bytes written to it.
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+pub fn read_csv(input: impl Read, writer: impl Write + Seek, limits: DocumentLimits) -> Result<()> {
     let mut builder = DocumentBatchBuilder::new(writer).unwrap();
 
     let iter = CsvDocumentIter::from_reader(input)?;
-    for doc in iter {
+    for (position, doc) in iter.enumerate() {
         let doc = doc?;
+        limits.check(position, &doc)?;
         builder.add_documents(doc).unwrap();
     }
     builder.finish().unwrap();
@@ -67,12 +152,17 @@ pub fn read_csv(input: impl Read, writer: impl Write + Seek) -> Result<()> {
 }
 
 /// read jsonl from input and write an obkv batch to writer.
-pub fn read_ndjson(input: impl Read, writer: impl Write + Seek) -> Result<()> {
+pub fn read_ndjson(
+    input: impl Read,
+    writer: impl Write + Seek,
+    limits: DocumentLimits,
+) -> Result<()> {
     let mut builder = DocumentBatchBuilder::new(writer)?;
     let stream = Deserializer::from_reader(input).into_iter::<Map<String, Value>>();
 
-    for value in stream {
+    for (position, value) in stream.enumerate() {
         let value = malformed!(PayloadType::Ndjson, value)?;
+        limits.check(position, &value)?;
         builder.add_documents(&value)?;
     }
 
@@ -82,17 +172,75 @@ pub fn read_ndjson(input: impl Read, writer: impl Write + Seek) -> Result<()> {
 }
 
 /// read json from input and write an obkv batch to writer.
-pub fn read_json(input: impl Read, writer: impl Write + Seek) -> Result<()> {
+pub fn read_json(
+    input: impl Read,
+    writer: impl Write + Seek,
+    limits: DocumentLimits,
+) -> Result<()> {
     let mut builder = DocumentBatchBuilder::new(writer).unwrap();
 
     let documents: Vec<Map<String, Value>> =
         malformed!(PayloadType::Json, serde_json::from_reader(input))?;
+    for (position, document) in documents.iter().enumerate() {
+        limits.check(position, document)?;
+    }
     builder.add_documents(documents).unwrap();
     builder.finish().unwrap();
 
     Ok(())
 }
 
+/// Reads the documents of a payload one at a time, for them to be checked without being indexed
+/// nor held in memory. `visit` is called with every document, and with the error of the CSV rows
+/// whose values don't match the type of their column, the rows following them being still read.
+pub fn visit_documents(
+    input: impl Read,
+    format: &DocumentAdditionFormat,
+    mut visit: impl FnMut(Result<Map<String, Value>>),
+) -> Result<()> {
+    match format {
+        DocumentAdditionFormat::Json => {
+            let mut deserializer = Deserializer::from_reader(input);
+            malformed!(
+                PayloadType::Json,
+                deserializer.deserialize_seq(DocumentsVisitor(&mut visit))
+            )?;
+            malformed!(PayloadType::Json, deserializer.end())
+        }
+        DocumentAdditionFormat::Ndjson => {
+            for document in Deserializer::from_reader(input).into_iter::<Map<String, Value>>() {
+                visit(Ok(malformed!(PayloadType::Ndjson, document)?));
+            }
+            Ok(())
+        }
+        DocumentAdditionFormat::Csv => {
+            CsvDocumentIter::from_reader(input)?.for_each(visit);
+            Ok(())
+        }
+    }
+}
+
+/// Visits the documents of a JSON array as they are deserialized.
+struct DocumentsVisitor<F>(F);
+
+impl<'de, F> Visitor<'de> for DocumentsVisitor<F>
+where
+    F: FnMut(Result<Map<String, Value>>),
+{
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of documents")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> std::result::Result<(), A::Error> {
+        while let Some(document) = seq.next_element()? {
+            (self.0)(Ok(document));
+        }
+        Ok(())
+    }
+}
+
 enum AllowedType {
     String,
     Number,
@@ -178,6 +326,83 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn document_limits() {
+        let documents = r#"{ "id": 1, "title": "Carol" }
+{ "id": 2, "title": "Kate", "meta": { "author": "Johnson", "tags": [{ "name": "a" }] } }"#;
+        let read = |limits| read_ndjson(documents.as_bytes(), io::Cursor::new(Vec::new()), limits);
+
+        assert!(read(DocumentLimits::default()).is_ok());
+        assert!(read(DocumentLimits {
+            max_fields: Some(6),
+            max_size: None,
+        })
+        .is_ok());
+        assert!(matches!(
+            read(DocumentLimits {
+                max_fields: Some(5),
+                max_size: None,
+            }),
+            Err(DocumentFormatError::TooManyFields {
+                position: 1,
+                fields: 6,
+                max: 5
+            })
+        ));
+        assert!(matches!(
+            read(DocumentLimits {
+                max_fields: None,
+                max_size: Some(40),
+            }),
+            Err(DocumentFormatError::DocumentTooLarge { position: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn visit_payloads() {
+        let visit = |payload: &str, format| {
+            let mut documents = Vec::new();
+            visit_documents(payload.as_bytes(), &format, |document| {
+                documents.push(document)
+            })
+            .map(|()| documents)
+        };
+
+        let documents = visit(
+            "[{ \"id\": 1 }, { \"id\": 2 }]",
+            DocumentAdditionFormat::Json,
+        )
+        .unwrap();
+        assert_eq!(documents.len(), 2);
+        let documents = visit(
+            "{ \"id\": 1 }\n{ \"id\": 2 }",
+            DocumentAdditionFormat::Ndjson,
+        )
+        .unwrap();
+        assert_eq!(documents.len(), 2);
+
+        let documents = visit(
+            "id,price:number\n1,10\n2,ten\n3,30",
+            DocumentAdditionFormat::Csv,
+        )
+        .unwrap();
+        assert_eq!(documents.len(), 3);
+        assert_eq!(
+            Value::Object(documents[0].as_ref().unwrap().clone()),
+            json!({ "id": "1", "price": 10.0 })
+        );
+        assert!(matches!(
+            documents[1],
+            Err(DocumentFormatError::MalformedPayload(_, PayloadType::Csv))
+        ));
+        assert!(documents[2].is_ok());
+
+        assert!(matches!(
+            visit("{ \"id\": 1 }", DocumentAdditionFormat::Json),
+            Err(DocumentFormatError::MalformedPayload(_, PayloadType::Json))
+        ));
+    }
+
     #[test]
     fn simple_csv_document() {
         let documents = r#"city,country,pop
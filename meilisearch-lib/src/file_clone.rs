@@ -0,0 +1,137 @@
+//! The copies of the snapshots made without duplicating the data when the snapshot dir is on the
+//! same filesystem as the db: the databases, still modified once snapshotted, are cloned with
+//! copy-on-write reflinks, and the update files, never modified once written, are hard linked.
+//! Both fall back to a full copy otherwise. The cloned databases are still compacted, once the
+//! writes they held resume.
+
+use std::io;
+use std::path::Path;
+
+use heed::{Env, EnvOpenOptions};
+
+use crate::io_throttle::{compacted_env_copy, copy_file, IoThrottle};
+
+/// Copies the database of `env` to the `dst` file, compacted. `lock` holds the writes to the
+/// database until it is dropped: when the raw file of the database can be cloned next to `dst`,
+/// it is dropped once the clone is made, and the clone is compacted while the writes resume.
+pub fn compacted_copy<L>(
+    env: &Env,
+    dst: &Path,
+    lock: L,
+    throttle: Option<&IoThrottle>,
+) -> heed::Result<()> {
+    let clone_dir = match dst.parent() {
+        Some(parent) => tempfile::tempdir_in(parent),
+        None => tempfile::tempdir(),
+    }
+    .map_err(heed::Error::Io)?;
+    let cloned = reflink(
+        env.path().join("data.mdb"),
+        clone_dir.path().join("data.mdb"),
+    )
+    .map_err(heed::Error::Io)?;
+    if !cloned {
+        return compacted_env_copy(env, dst, throttle);
+    }
+    drop(lock);
+
+    let clone = EnvOpenOptions::new().open(clone_dir.path())?;
+    let result = compacted_env_copy(&clone, dst, throttle);
+    clone.prepare_for_closing().wait();
+    result
+}
+
+/// Clones `src` to `dst` with a copy-on-write reflink, the two files sharing their data until one
+/// of them is modified. Returns `false`, without creating `dst`, when the filesystem doesn't
+/// support the reflinks or the two paths are on different filesystems.
+#[cfg(target_os = "linux")]
+pub fn reflink(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<bool> {
+    use std::fs::File;
+    use std::os::unix::io::AsRawFd;
+
+    /// `_IOW(0x94, 9, int)`, from `linux/fs.h`.
+    const FICLONE: u64 = 0x4004_9409;
+
+    let src_file = File::open(&src)?;
+    let dst_file = File::create(&dst)?;
+    // Safety: the two file descriptors stay open during the call.
+    let ret = unsafe { libc::ioctl(dst_file.as_raw_fd(), FICLONE as _, src_file.as_raw_fd()) };
+    if ret == 0 {
+        return Ok(true);
+    }
+
+    let error = io::Error::last_os_error();
+    drop(dst_file);
+    std::fs::remove_file(&dst)?;
+    match error.raw_os_error() {
+        Some(libc::EXDEV) | Some(libc::EOPNOTSUPP) | Some(libc::EINVAL) | Some(libc::ENOTTY) => {
+            Ok(false)
+        }
+        _ => Err(error),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn reflink(_src: impl AsRef<Path>, _dst: impl AsRef<Path>) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Hard links `dst` to `src`, or copies it when the link can't be made. The file must never be
+/// modified once linked, the changes being seen from both paths.
+pub fn hard_link_or_copy(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    throttle: Option<&IoThrottle>,
+) -> io::Result<()> {
+    match std::fs::hard_link(&src, &dst) {
+        Ok(()) => Ok(()),
+        Err(_) => copy_file(src, dst, throttle),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn clone_or_copy_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        std::fs::write(&src, b"hello").unwrap();
+
+        let dst = dir.path().join("reflinked");
+        if reflink(&src, &dst).unwrap() {
+            assert_eq!(std::fs::read(&dst).unwrap(), b"hello");
+        } else {
+            assert!(!dst.exists());
+        }
+
+        let dst = dir.path().join("linked");
+        hard_link_or_copy(&src, &dst, None).unwrap();
+        assert_eq!(std::fs::read(&dst).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn compact_the_copies() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = EnvOpenOptions::new().open(dir.path()).unwrap();
+        let db = env
+            .create_database::<heed::types::Str, heed::types::Str>(None)
+            .unwrap();
+        let mut txn = env.write_txn().unwrap();
+        db.put(&mut txn, "hello", "world").unwrap();
+        txn.commit().unwrap();
+
+        let dst_dir = tempfile::tempdir().unwrap();
+        let dst = dst_dir.path().join("data.mdb");
+        compacted_copy(&env, &dst, env.write_txn().unwrap(), None).unwrap();
+
+        let copy = EnvOpenOptions::new().open(dst_dir.path()).unwrap();
+        let db = copy
+            .open_database::<heed::types::Str, heed::types::Str>(None)
+            .unwrap()
+            .unwrap();
+        let txn = copy.read_txn().unwrap();
+        assert_eq!(db.get(&txn, "hello").unwrap(), Some("world"));
+    }
+}
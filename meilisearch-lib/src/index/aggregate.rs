@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::error::{IndexError, Result};
+use super::search::parse_filter;
+use super::Index;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AggregateQuery {
+    pub filter: Option<Value>,
+    /// The locale the numbers and dates of the filter are written in.
+    pub locale: Option<String>,
+    /// The numeric fields to compute the aggregations on, which must be filterable.
+    #[serde(default)]
+    pub fields: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldAggregation {
+    /// The number of documents holding at least one numeric value for the field.
+    pub count: u64,
+    pub sum: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// The average of the values, a value held by several documents counting once per document.
+    pub avg: Option<f64>,
+    #[serde(skip)]
+    documents: RoaringBitmap,
+    #[serde(skip)]
+    values: u64,
+}
+
+impl FieldAggregation {
+    /// Aggregates `value`, held by `documents`, the values being inserted in ascending order.
+    fn insert(&mut self, value: f64, documents: &RoaringBitmap) {
+        let occurrences = documents.len();
+        if occurrences == 0 {
+            return;
+        }
+
+        self.documents |= documents;
+        self.count = self.documents.len();
+        self.values += occurrences;
+        self.sum += value * occurrences as f64;
+        self.min.get_or_insert(value);
+        self.max = Some(value);
+        self.avg = Some(self.sum / self.values as f64);
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregateResult {
+    /// The number of documents matching the filter.
+    pub count: u64,
+    pub fields: BTreeMap<String, FieldAggregation>,
+    pub processing_time_ms: u128,
+}
+
+impl Index {
+    /// Aggregates the numeric values of filterable fields from the facet databases, where each of
+    /// them is stored once with the documents holding it.
+    pub fn aggregate(&self, query: AggregateQuery) -> Result<AggregateResult> {
+        let before_aggregate = Instant::now();
+        let rtxn = self.read_txn()?;
+
+        let filterable = self.filterable_fields(&rtxn)?;
+        if let Some(field) = query.fields.iter().find(|f| !filterable.contains(*f)) {
+            return Err(IndexError::AttributeNotAggregatable(field.clone()));
+        }
+
+        // A placeholder search gives us all the documents matching the filter.
+        let mut search = self.search(&rtxn);
+        search.limit(0);
+
+        if let Some(ref filter) = query.filter {
+            if let Some(facets) = parse_filter(filter, self, &rtxn, query.locale.as_deref())? {
+                search.filter(facets);
+            }
+        }
+
+        let candidates = search.execute()?.candidates;
+
+        let fields_ids_map = self.fields_ids_map(&rtxn)?;
+        let mut fields = BTreeMap::new();
+        for field in query.fields {
+            let mut aggregation = FieldAggregation::default();
+            if let Some(fid) = fields_ids_map.id(&field) {
+                for entry in self.facet_numbers(&rtxn, fid)? {
+                    let (value, docids) = entry?;
+                    aggregation.insert(value, &(docids & &candidates));
+                }
+            }
+            fields.insert(field, aggregation);
+        }
+
+        Ok(AggregateResult {
+            count: candidates.len(),
+            fields,
+            processing_time_ms: before_aggregate.elapsed().as_millis(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn aggregate_values() {
+        let mut aggregation = FieldAggregation::default();
+        aggregation.insert(1.0, &(1..3).collect());
+        aggregation.insert(2.5, &RoaringBitmap::new());
+        aggregation.insert(4.0, &(2..4).collect());
+
+        assert_eq!(aggregation.count, 3);
+        assert_eq!(aggregation.sum, 10.0);
+        assert_eq!(aggregation.min, Some(1.0));
+        assert_eq!(aggregation.max, Some(4.0));
+        assert_eq!(aggregation.avg, Some(2.5));
+    }
+}
@@ -0,0 +1,62 @@
+//! The listing of the documents of an index restricted by a filter and ordered by sort rules,
+//! such as to export a subset of the documents without paging through all of them.
+
+use std::str::FromStr;
+
+use milli::{obkv_to_json, AscDesc, SortError};
+use serde_json::Value;
+
+use super::collation::collate_sort_rules;
+use super::error::{IndexError, Result};
+use super::search::parse_filter;
+use super::{Document, Index};
+
+#[derive(Debug, Clone, Default)]
+pub struct DocumentsQuery {
+    pub offset: usize,
+    pub limit: usize,
+    /// The fields of the returned documents, all the displayed ones when `None`.
+    pub fields: Option<Vec<String>>,
+    /// Only returns the documents matching this filter.
+    pub filter: Option<Value>,
+    /// The sort rules of the documents, which are otherwise returned in the order of the index.
+    pub sort: Option<Vec<String>>,
+}
+
+impl Index {
+    pub fn browse_documents(&self, query: DocumentsQuery) -> Result<Vec<Document>> {
+        if query.filter.is_none() && query.sort.is_none() {
+            return self.retrieve_documents(query.offset, query.limit, query.fields);
+        }
+
+        let txn = self.read_txn()?;
+        // A placeholder search filters and sorts the documents.
+        let mut search = self.search(&txn);
+        search.offset(query.offset).limit(query.limit);
+        if let Some(ref filter) = query.filter {
+            if let Some(condition) = parse_filter(filter, self, &txn, None)? {
+                search.filter(condition);
+            }
+        }
+        if let Some(ref sort) = query.sort {
+            self.usage.record_sort(Some(sort));
+            let sort = collate_sort_rules(sort, &self.sortable_fields(&txn)?);
+            let sort = match sort.iter().map(|s| AscDesc::from_str(s)).collect() {
+                Ok(sorts) => sorts,
+                Err(asc_desc_error) => {
+                    return Err(IndexError::Milli(SortError::from(asc_desc_error).into()))
+                }
+            };
+            search.sort_criteria(sort);
+        }
+        let documents_ids = search.execute()?.documents_ids;
+
+        let fields_ids_map = self.fields_ids_map(&txn)?;
+        let fields_to_display = self.fields_to_display(&txn, &query.fields, &fields_ids_map)?;
+        let mut documents = Vec::with_capacity(documents_ids.len());
+        for (_id, obkv) in self.documents(&txn, documents_ids)? {
+            documents.push(obkv_to_json(&fields_to_display, &fields_ids_map, obkv)?);
+        }
+        Ok(documents)
+    }
+}
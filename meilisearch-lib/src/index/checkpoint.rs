@@ -0,0 +1,106 @@
+//! The copies of an index taken before its settings updates, when the
+//! `checkpointBeforeSettingsChange` setting is enabled, so that a settings change breaking the
+//! relevancy can be rolled back without reindexing the documents.
+//!
+//! The checkpoints are stored next to the database of the index, in
+//! `checkpoints/<update id>/data.mdb`, and are removed with it.
+
+use std::fs::{create_dir_all, read_dir, remove_dir_all};
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use milli::update::Setting;
+use serde::Serialize;
+
+use super::custom_settings::checkpoint_before_settings_change;
+use super::error::{IndexError, Result};
+use super::{Checked, Index, Settings};
+
+/// The number of checkpoints kept for each index, the oldest ones being removed first.
+const MAX_CHECKPOINTS: usize = 3;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    /// The id of the settings update the checkpoint was taken before.
+    pub update_id: u64,
+    pub created_at: DateTime<Utc>,
+    /// The size of the copy on disk, in bytes.
+    pub size: u64,
+}
+
+impl Index {
+    fn checkpoints_dir(&self) -> PathBuf {
+        self.inner.env.path().join("checkpoints")
+    }
+
+    /// Whether the index must be copied before applying the settings update, which is the case
+    /// if the checkpoints are enabled or if the update enables them.
+    pub(super) fn needs_checkpoint(&self, settings: &Settings<Checked>) -> Result<bool> {
+        if let Setting::Set(true) = settings.checkpoint_before_settings_change {
+            return Ok(true);
+        }
+        let txn = self.read_txn()?;
+        checkpoint_before_settings_change(self, &txn)
+    }
+
+    /// Copies the index before the update `update_id`, and removes the checkpoints exceeding
+    /// `MAX_CHECKPOINTS`.
+    pub(super) fn checkpoint(&self, update_id: u64) -> Result<()> {
+        let dst = self.checkpoints_dir().join(update_id.to_string());
+        create_dir_all(&dst)?;
+        self.inner
+            .env
+            .copy_to_path(dst.join("data.mdb"), heed::CompactionOption::Enabled)?;
+
+        for checkpoint in self.checkpoints()?.iter().skip(MAX_CHECKPOINTS) {
+            remove_dir_all(
+                self.checkpoints_dir()
+                    .join(checkpoint.update_id.to_string()),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// The checkpoints of the index, the most recent first.
+    pub fn checkpoints(&self) -> Result<Vec<Checkpoint>> {
+        let dir = self.checkpoints_dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut checkpoints = Vec::new();
+        for entry in read_dir(dir)? {
+            let entry = entry?;
+            let update_id = match entry.file_name().to_str().and_then(|s| s.parse().ok()) {
+                Some(update_id) => update_id,
+                None => continue,
+            };
+            let metadata = match entry.path().join("data.mdb").metadata() {
+                Ok(metadata) => metadata,
+                // The copy is still in progress or was interrupted.
+                Err(_) => continue,
+            };
+            checkpoints.push(Checkpoint {
+                update_id,
+                created_at: metadata.modified()?.into(),
+                size: metadata.len(),
+            });
+        }
+        checkpoints.sort_unstable_by(|a, b| b.update_id.cmp(&a.update_id));
+        Ok(checkpoints)
+    }
+
+    /// The path of the database copied before the update `update_id`.
+    pub fn checkpoint_path(&self, update_id: u64) -> Result<PathBuf> {
+        let path = self
+            .checkpoints_dir()
+            .join(update_id.to_string())
+            .join("data.mdb");
+        if path.exists() {
+            Ok(path)
+        } else {
+            Err(IndexError::CheckpointNotFound(update_id))
+        }
+    }
+}
@@ -0,0 +1,660 @@
+//! Collation and normalization of the string attributes. milli sorts and filters strings by their
+//! raw bytes, so when a collation or a normalization is configured, the attributes get hidden key
+//! attributes holding their normalized values, computed at indexing time, that the sort rules and
+//! the filters are redirected to. The numeric attributes made searchable get a hidden attribute
+//! holding their textual representation the same way, and the date attributes a hidden attribute
+//! holding their timestamps, that the sort rules and the filters on them are redirected to, their
+//! values being left as they were given.
+//!
+//! The hidden attributes are left out of the searchable attributes given as `*`, and only the
+//! documents whose hidden attributes change are indexed again when the settings change.
+
+use std::collections::{BTreeSet, HashSet};
+use std::io::{Seek, SeekFrom};
+
+use heed::{RoTxn, RwTxn};
+use log::debug;
+use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
+use milli::documents::{DocumentBatchBuilder, DocumentBatchReader};
+use milli::update::{IndexDocumentsMethod, UpdateBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::custom_settings::{
+    date_attributes, normalized_filterable_attributes, put_searchable_wildcard,
+    searchable_numeric_attributes, searchable_wildcard, sort_collation,
+};
+use super::error::Result;
+use super::updates::insert_timestamps;
+use super::Index;
+
+const COLLATION_KEY_PREFIX: &str = "_collationKey.";
+const NORMALIZED_KEY_PREFIX: &str = "_normalized.";
+const NUMERIC_TEXT_PREFIX: &str = "_numericText.";
+const TIMESTAMP_PREFIX: &str = "_timestamp.";
+
+/// How the strings of the sortable attributes are ordered.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SortCollation {
+    /// The strings are ordered by their raw bytes, `É` comes after `z`.
+    Binary,
+    /// The strings are ordered ignoring their case and accents, `É` comes along `e`.
+    Unicode,
+}
+
+impl Default for SortCollation {
+    fn default() -> Self {
+        Self::Binary
+    }
+}
+
+/// The hidden attribute holding the collation key of `attribute`.
+pub fn collation_key_field(attribute: &str) -> String {
+    format!("{}{}", COLLATION_KEY_PREFIX, attribute)
+}
+
+pub fn is_collation_key_field(name: &str) -> bool {
+    name.starts_with(COLLATION_KEY_PREFIX)
+}
+
+/// The hidden attribute holding the normalized values of `attribute`.
+pub fn normalized_key_field(attribute: &str) -> String {
+    format!("{}{}", NORMALIZED_KEY_PREFIX, attribute)
+}
+
+pub fn is_normalized_key_field(name: &str) -> bool {
+    name.starts_with(NORMALIZED_KEY_PREFIX)
+}
+
+/// The hidden attribute holding the textual representation of the numbers of `attribute`.
+pub fn numeric_text_field(attribute: &str) -> String {
+    format!("{}{}", NUMERIC_TEXT_PREFIX, attribute)
+}
+
+pub fn is_numeric_text_field(name: &str) -> bool {
+    name.starts_with(NUMERIC_TEXT_PREFIX)
+}
+
+/// The attribute whose numbers the numeric text field `name` holds, if it is one.
+pub fn numeric_text_attribute(name: &str) -> Option<&str> {
+    name.strip_prefix(NUMERIC_TEXT_PREFIX)
+}
+
+/// The hidden attribute holding the timestamps of the dates of `attribute`.
+pub fn timestamp_field(attribute: &str) -> String {
+    format!("{}{}", TIMESTAMP_PREFIX, attribute)
+}
+
+pub fn is_timestamp_field(name: &str) -> bool {
+    name.starts_with(TIMESTAMP_PREFIX)
+}
+
+/// Whether the field is one of the hidden attributes, which are never displayed.
+pub fn is_hidden_field(name: &str) -> bool {
+    is_collation_key_field(name)
+        || is_normalized_key_field(name)
+        || is_numeric_text_field(name)
+        || is_timestamp_field(name)
+}
+
+/// The fields that must be sortable in milli for the user `sortable` attributes to be sorted
+/// using the `collation`, the `dates` attributes being sorted by their timestamps.
+pub fn collated_sortable_fields(
+    sortable: impl IntoIterator<Item = String>,
+    collation: SortCollation,
+    dates: &BTreeSet<String>,
+) -> HashSet<String> {
+    let mut fields = HashSet::new();
+    for attribute in sortable {
+        if dates.contains(&attribute) {
+            fields.insert(timestamp_field(&attribute));
+        } else if collation == SortCollation::Unicode {
+            fields.insert(collation_key_field(&attribute));
+        }
+        fields.insert(attribute);
+    }
+    fields
+}
+
+/// The fields that must be filterable in milli for the user `filterable` attributes and the
+/// `normalized` attributes to be filtered on, the `dates` attributes being filtered by their
+/// timestamps.
+pub fn normalized_filterable_fields(
+    filterable: impl IntoIterator<Item = String>,
+    normalized: &BTreeSet<String>,
+    dates: &BTreeSet<String>,
+) -> HashSet<String> {
+    let mut fields: HashSet<_> = filterable.into_iter().collect();
+    let timestamps: Vec<_> = dates
+        .iter()
+        .filter(|attribute| fields.contains(*attribute))
+        .map(|attribute| timestamp_field(attribute))
+        .collect();
+    fields.extend(timestamps);
+    fields.extend(
+        normalized
+            .iter()
+            .map(|attribute| normalized_key_field(attribute)),
+    );
+    fields
+}
+
+/// The fields that must be searchable in milli for the user `searchable` attributes, `None`
+/// meaning all of them, to be searched on with their `numeric` attributes. All the attributes
+/// are given explicitly, without the hidden ones, when the `fields_ids_map` holds hidden ones.
+pub fn wildcard_searchable_fields(
+    searchable: Option<Vec<String>>,
+    numeric: &BTreeSet<String>,
+    fields_ids_map: &milli::FieldsIdsMap,
+) -> Option<Vec<String>> {
+    let holds_hidden_fields = fields_ids_map.iter().any(|(_, name)| is_hidden_field(name));
+    let searchable = match searchable {
+        None if holds_hidden_fields => Some(
+            fields_ids_map
+                .iter()
+                .map(|(_, name)| name)
+                .filter(|name| !is_hidden_field(name))
+                .map(String::from)
+                .collect(),
+        ),
+        searchable => searchable,
+    };
+    numeric_searchable_fields(searchable, numeric)
+}
+
+/// The fields that must be searchable in milli for the user `searchable` attributes, `None`
+/// meaning all of them, to be searched on with their `numeric` attributes.
+pub fn numeric_searchable_fields(
+    searchable: Option<Vec<String>>,
+    numeric: &BTreeSet<String>,
+) -> Option<Vec<String>> {
+    searchable.map(|mut searchable| {
+        let numeric_texts: Vec<_> = searchable
+            .iter()
+            .filter(|attribute| numeric.contains(*attribute))
+            .map(|attribute| numeric_text_field(attribute))
+            .collect();
+        searchable.extend(numeric_texts);
+        searchable
+    })
+}
+
+pub fn collation_analyzer(stop_words: &fst::Set<Vec<u8>>) -> Analyzer<Vec<u8>> {
+    let mut config = AnalyzerConfig::default();
+    config.stop_words(stop_words);
+    Analyzer::new(config)
+}
+
+/// The normalized form of a string, ignoring its case and accents.
+pub fn normalize_string<A: AsRef<[u8]>>(string: &str, analyzer: &Analyzer<A>) -> String {
+    let analyzed = analyzer.analyze(string);
+    analyzed
+        .tokens()
+        .map(|token| token.text().to_string())
+        .collect()
+}
+
+/// The normalized form of the strings contained in `value`.
+fn normalize_value<A: AsRef<[u8]>>(value: &Value, analyzer: &Analyzer<A>) -> Value {
+    match value {
+        Value::String(string) => Value::String(normalize_string(string, analyzer)),
+        Value::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(|value| normalize_value(value, analyzer))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+/// Replaces the keys starting with `prefix` of the document by the normalized values of the
+/// `attributes`.
+fn insert_keys<A: AsRef<[u8]>>(
+    document: &mut Map<String, Value>,
+    prefix: &str,
+    attributes: &BTreeSet<String>,
+    analyzer: &Analyzer<A>,
+) {
+    let stale_keys: Vec<_> = document
+        .keys()
+        .filter(|name| name.starts_with(prefix))
+        .cloned()
+        .collect();
+    for name in stale_keys {
+        document.remove(&name);
+    }
+
+    for attribute in attributes {
+        if let Some(value) = document.get(attribute) {
+            let key = normalize_value(value, analyzer);
+            document.insert(format!("{}{}", prefix, attribute), key);
+        }
+    }
+}
+
+/// Replaces the collation keys of the document by the ones of its `sortable` attributes.
+pub fn insert_collation_keys<A: AsRef<[u8]>>(
+    document: &mut Map<String, Value>,
+    sortable: &BTreeSet<String>,
+    analyzer: &Analyzer<A>,
+) {
+    insert_keys(document, COLLATION_KEY_PREFIX, sortable, analyzer)
+}
+
+/// Replaces the normalized keys of the document by the ones of its `normalized` attributes.
+pub fn insert_normalized_keys<A: AsRef<[u8]>>(
+    document: &mut Map<String, Value>,
+    normalized: &BTreeSet<String>,
+    analyzer: &Analyzer<A>,
+) {
+    insert_keys(document, NORMALIZED_KEY_PREFIX, normalized, analyzer)
+}
+
+/// Replaces the numeric texts of the document by the textual representations of the numbers of
+/// its `numeric` attributes, so that they can be found by a full-text search.
+pub fn insert_numeric_texts(document: &mut Map<String, Value>, numeric: &BTreeSet<String>) {
+    fn numeric_text(value: &Value) -> Option<Value> {
+        match value {
+            Value::Number(number) => Some(Value::String(number.to_string())),
+            Value::Array(values) => {
+                let texts: Vec<_> = values.iter().filter_map(numeric_text).collect();
+                (!texts.is_empty()).then(|| Value::Array(texts))
+            }
+            _ => None,
+        }
+    }
+
+    let stale_texts: Vec<_> = document
+        .keys()
+        .filter(|name| is_numeric_text_field(name))
+        .cloned()
+        .collect();
+    for name in stale_texts {
+        document.remove(&name);
+    }
+
+    for attribute in numeric {
+        if let Some(text) = document.get(attribute).and_then(numeric_text) {
+            document.insert(numeric_text_field(attribute), text);
+        }
+    }
+}
+
+/// Redirects the sort rules on the attributes having a sortable timestamp or collation key to
+/// this key.
+pub fn collate_sort_rules(sort: &[String], sortable_fields: &HashSet<String>) -> Vec<String> {
+    sort.iter()
+        .map(|rule| match rule.rsplit_once(':') {
+            Some((attribute, order)) if sortable_fields.contains(&timestamp_field(attribute)) => {
+                format!("{}:{}", timestamp_field(attribute), order)
+            }
+            Some((attribute, order))
+                if sortable_fields.contains(&collation_key_field(attribute)) =>
+            {
+                format!("{}:{}", collation_key_field(attribute), order)
+            }
+            _ => rule.clone(),
+        })
+        .collect()
+}
+
+impl Index {
+    /// The sortable attributes, without the collation keys and the timestamps.
+    pub(super) fn user_sortable_fields(&self, txn: &RoTxn) -> Result<BTreeSet<String>> {
+        Ok(self
+            .sortable_fields(txn)?
+            .into_iter()
+            .filter(|field| !is_collation_key_field(field) && !is_timestamp_field(field))
+            .collect())
+    }
+
+    /// The filterable attributes, without the normalized keys and the timestamps.
+    pub(super) fn user_filterable_fields(&self, txn: &RoTxn) -> Result<BTreeSet<String>> {
+        Ok(self
+            .filterable_fields(txn)?
+            .into_iter()
+            .filter(|field| !is_normalized_key_field(field) && !is_timestamp_field(field))
+            .collect())
+    }
+
+    /// The searchable attributes, without the numeric texts. `None` means all the attributes.
+    pub(super) fn user_searchable_fields(&self, txn: &RoTxn) -> Result<Option<Vec<String>>> {
+        if searchable_wildcard(self, txn)? {
+            return Ok(None);
+        }
+
+        Ok(self.searchable_fields(txn)?.map(|fields| {
+            fields
+                .into_iter()
+                .filter(|field| !is_numeric_text_field(field))
+                .map(String::from)
+                .collect()
+        }))
+    }
+
+    /// Makes the collation keys of the sortable attributes sortable in milli and recomputes them
+    /// for the documents whose keys change. Must be called when the sortable attributes or the
+    /// collation change.
+    pub(super) fn update_collation_keys<'a, 'b>(&'a self, txn: &mut RwTxn<'a, 'b>) -> Result<()> {
+        let collation = sort_collation(self, txn)?;
+        let dates = date_attributes(self, txn)?;
+        let sortable = self.user_sortable_fields(txn)?;
+
+        let fields = collated_sortable_fields(sortable.iter().cloned(), collation, &dates);
+        if fields != self.sortable_fields(txn)? {
+            let mut builder = UpdateBuilder::new(0).settings(txn, self);
+            builder.set_sortable_fields(fields);
+            builder.execute(|_, _| ())?;
+        }
+
+        // With the binary collation, the documents only need to be reindexed to get rid of the
+        // collation keys they may still hold. The dates are sorted by their timestamps.
+        let sortable = match collation {
+            SortCollation::Unicode => sortable.difference(&dates).cloned().collect(),
+            SortCollation::Binary => BTreeSet::new(),
+        };
+        if self.keys_up_to_date(txn, COLLATION_KEY_PREFIX, &sortable)? {
+            return Ok(());
+        }
+
+        let stop_words = fst::Set::default();
+        let analyzer = collation_analyzer(&stop_words);
+        self.rewrite_documents(txn, |document| {
+            insert_collation_keys(document, &sortable, &analyzer);
+            Ok(())
+        })?;
+        update_searchable_fields(self, txn)
+    }
+
+    /// Makes the normalized keys of the `normalized_filterable_attributes` filterable in milli and
+    /// recomputes them for the documents whose keys change. Must be called when the filterable
+    /// attributes or the normalized filterable attributes change.
+    pub(super) fn update_normalized_keys<'a, 'b>(&'a self, txn: &mut RwTxn<'a, 'b>) -> Result<()> {
+        let normalized = normalized_filterable_attributes(self, txn)?;
+        let dates = date_attributes(self, txn)?;
+        let filterable = self.user_filterable_fields(txn)?;
+
+        let fields = normalized_filterable_fields(filterable, &normalized, &dates);
+        if fields != self.filterable_fields(txn)? {
+            let mut builder = UpdateBuilder::new(0).settings(txn, self);
+            builder.set_filterable_fields(fields);
+            builder.execute(|_, _| ())?;
+        }
+
+        if self.keys_up_to_date(txn, NORMALIZED_KEY_PREFIX, &normalized)? {
+            return Ok(());
+        }
+
+        let stop_words = fst::Set::default();
+        let analyzer = collation_analyzer(&stop_words);
+        self.rewrite_documents(txn, |document| {
+            insert_normalized_keys(document, &normalized, &analyzer);
+            Ok(())
+        })?;
+        update_searchable_fields(self, txn)
+    }
+
+    /// Makes the numeric texts of the `searchable_numeric_attributes` searchable in milli and
+    /// recomputes them for the documents whose texts change. Must be called when the searchable
+    /// attributes or the searchable numeric attributes change.
+    pub(super) fn update_numeric_texts<'a, 'b>(&'a self, txn: &mut RwTxn<'a, 'b>) -> Result<()> {
+        update_searchable_fields(self, txn)?;
+
+        let numeric = searchable_numeric_attributes(self, txn)?;
+        if numeric.is_empty() && !self.holds_fields(txn, is_numeric_text_field)? {
+            return Ok(());
+        }
+
+        self.rewrite_documents(txn, |document| {
+            insert_numeric_texts(document, &numeric);
+            Ok(())
+        })?;
+        update_searchable_fields(self, txn)
+    }
+
+    /// Makes the timestamps of the `date_attributes` sortable and filterable in milli and
+    /// recomputes them for the documents whose timestamps change. Must be called when the date
+    /// attributes change.
+    pub(super) fn update_timestamps<'a, 'b>(&'a self, txn: &mut RwTxn<'a, 'b>) -> Result<()> {
+        self.update_collation_keys(txn)?;
+        self.update_normalized_keys(txn)?;
+
+        let dates = date_attributes(self, txn)?;
+        if self.keys_up_to_date(txn, TIMESTAMP_PREFIX, &dates)? {
+            return Ok(());
+        }
+
+        self.rewrite_documents(txn, |document| insert_timestamps(document, &dates))?;
+        update_searchable_fields(self, txn)
+    }
+
+    /// Whether some documents hold one of the fields matching `is_field`.
+    fn holds_fields(&self, txn: &RoTxn, is_field: impl Fn(&str) -> bool) -> Result<bool> {
+        Ok(self
+            .field_distribution(txn)?
+            .iter()
+            .any(|(name, count)| is_field(name) && *count > 0))
+    }
+
+    /// Whether every document holding one of the `attributes` holds its key starting with
+    /// `prefix`, and no document holds the key of another attribute. The keys being inserted in
+    /// every indexed document, they are then all up to date.
+    fn keys_up_to_date(
+        &self,
+        txn: &RoTxn,
+        prefix: &str,
+        attributes: &BTreeSet<String>,
+    ) -> Result<bool> {
+        let distribution = self.field_distribution(txn)?;
+        let count = |name: &str| distribution.get(name).copied().unwrap_or_default();
+        let attributes_keyed = attributes
+            .iter()
+            .all(|attribute| count(&format!("{}{}", prefix, attribute)) == count(attribute));
+        let no_stale_key = distribution.iter().all(|(name, count)| {
+            *count == 0
+                || name
+                    .strip_prefix(prefix)
+                    .map_or(true, |attribute| attributes.contains(attribute))
+        });
+        Ok(attributes_keyed && no_stale_key)
+    }
+
+    /// Reindexes the documents of the index that `rewrite` changes.
+    fn rewrite_documents<'a, 'b>(
+        &'a self,
+        txn: &mut RwTxn<'a, 'b>,
+        mut rewrite: impl FnMut(&mut Map<String, Value>) -> Result<()>,
+    ) -> Result<()> {
+        let fields_ids_map = self.fields_ids_map(txn)?;
+
+        let mut file = tempfile::tempfile()?;
+        let mut builder = DocumentBatchBuilder::new(&mut file)?;
+        let mut document = Map::new();
+        let mut rewritten = 0;
+        for entry in self.all_documents(txn)? {
+            let (_id, obkv) = entry?;
+            for (fid, content) in obkv.iter() {
+                if let Some(name) = fields_ids_map.name(fid) {
+                    document.insert(name.to_string(), serde_json::from_slice(content)?);
+                }
+            }
+
+            let original = document.clone();
+            rewrite(&mut document)?;
+            if document != original {
+                builder.add_documents(&document)?;
+                rewritten += 1;
+            }
+            document.clear();
+        }
+        builder.finish()?;
+        file.seek(SeekFrom::Start(0))?;
+
+        if rewritten == 0 {
+            return Ok(());
+        }
+
+        let reader = DocumentBatchReader::from_reader(file)?;
+        let mut builder = UpdateBuilder::new(0).index_documents(txn, self);
+        builder.index_documents_method(IndexDocumentsMethod::ReplaceDocuments);
+        builder.execute(reader, |indexing_step, update_id| {
+            debug!("update {}: {:?}", update_id, indexing_step)
+        })?;
+        debug!("{} documents rewritten", rewritten);
+
+        Ok(())
+    }
+}
+
+/// Gives milli the fields to search: the user searchable attributes and the numeric texts of the
+/// ones that are numeric, all the attributes but the hidden ones when they all are searchable.
+/// Must be called when the searchable attributes change, and when the documents bring new
+/// attributes or hidden attributes.
+pub(super) fn update_searchable_fields<'a, 'b>(
+    index: &'a milli::Index,
+    txn: &mut RwTxn<'a, 'b>,
+) -> Result<()> {
+    let numeric = searchable_numeric_attributes(index, txn)?;
+    let wildcard = searchable_wildcard(index, txn)?;
+    let current = index
+        .searchable_fields(txn)?
+        .map(|fields| fields.into_iter().map(String::from).collect::<Vec<_>>());
+    let searchable = match current {
+        _ if wildcard => None,
+        Some(ref fields) => Some(
+            fields
+                .iter()
+                .filter(|field| !is_numeric_text_field(field))
+                .cloned()
+                .collect(),
+        ),
+        None => None,
+    };
+
+    let fields_ids_map = index.fields_ids_map(txn)?;
+    let is_wildcard = searchable.is_none();
+    let fields = wildcard_searchable_fields(searchable, &numeric, &fields_ids_map);
+    if fields != current {
+        let mut builder = UpdateBuilder::new(0).settings(txn, index);
+        match fields {
+            Some(ref fields) => builder.set_searchable_fields(fields.clone()),
+            None => builder.reset_searchable_fields(),
+        }
+        builder.execute(|_, _| ())?;
+    }
+
+    let expanded = is_wildcard && fields.is_some();
+    if expanded != wildcard {
+        put_searchable_wildcard(index, txn, expanded)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_insert_keys() {
+        let stop_words = fst::Set::default();
+        let analyzer = collation_analyzer(&stop_words);
+        let sortable = vec![String::from("title"), String::from("tags")]
+            .into_iter()
+            .collect();
+
+        let mut document = json!({
+            "title": "Élan Vital",
+            "tags": ["Zèbre", "abc"],
+            "_collationKey.old": "stale",
+        });
+        insert_collation_keys(document.as_object_mut().unwrap(), &sortable, &analyzer);
+
+        assert_eq!(document["_collationKey.title"], json!("elan vital"));
+        assert_eq!(document["_collationKey.tags"], json!(["zebre", "abc"]));
+        assert!(document.get("_collationKey.old").is_none());
+
+        let normalized = vec![String::from("brand")].into_iter().collect();
+        let mut document = json!({ "brand": "Ápple", "_collationKey.brand": "apple" });
+        insert_normalized_keys(document.as_object_mut().unwrap(), &normalized, &analyzer);
+
+        assert_eq!(document["_normalized.brand"], json!("apple"));
+        assert_eq!(document["_collationKey.brand"], json!("apple"));
+    }
+
+    #[test]
+    fn test_insert_numeric_texts() {
+        let numeric = vec![String::from("ean"), String::from("codes")]
+            .into_iter()
+            .collect();
+        let mut document =
+            json!({ "ean": 404982, "codes": [1, "a", 2.5], "_numericText.old": "1" });
+        insert_numeric_texts(document.as_object_mut().unwrap(), &numeric);
+
+        assert_eq!(document["_numericText.ean"], json!("404982"));
+        assert_eq!(document["_numericText.codes"], json!(["1", "2.5"]));
+        assert!(document.get("_numericText.old").is_none());
+
+        let searchable = Some(vec![String::from("title"), String::from("ean")]);
+        assert_eq!(
+            numeric_searchable_fields(searchable, &numeric),
+            Some(vec![
+                String::from("title"),
+                String::from("ean"),
+                String::from("_numericText.ean"),
+            ])
+        );
+        assert_eq!(numeric_searchable_fields(None, &numeric), None);
+
+        // The hidden attributes are left out of `*`.
+        let mut fields_ids_map = milli::FieldsIdsMap::new();
+        assert_eq!(
+            wildcard_searchable_fields(None, &numeric, &fields_ids_map),
+            None
+        );
+        fields_ids_map.insert("title").unwrap();
+        fields_ids_map.insert("ean").unwrap();
+        fields_ids_map.insert("_numericText.ean").unwrap();
+        assert_eq!(
+            wildcard_searchable_fields(None, &numeric, &fields_ids_map),
+            Some(vec![
+                String::from("title"),
+                String::from("ean"),
+                String::from("_numericText.ean"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_collate_sort_rules() {
+        let sortable = collated_sortable_fields(
+            vec![
+                String::from("title"),
+                String::from("price"),
+                String::from("released"),
+            ],
+            SortCollation::Unicode,
+            &std::iter::once(String::from("released")).collect(),
+        );
+        let sort = vec![
+            String::from("title:asc"),
+            String::from("released:desc"),
+            String::from("_geoPoint(1, 2):desc"),
+            String::from("rank:desc"),
+        ];
+
+        assert_eq!(
+            collate_sort_rules(&sort, &sortable),
+            vec![
+                String::from("_collationKey.title:asc"),
+                String::from("_timestamp.released:desc"),
+                String::from("_geoPoint(1, 2):desc"),
+                String::from("rank:desc"),
+            ]
+        );
+    }
+}
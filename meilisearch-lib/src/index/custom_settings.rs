@@ -0,0 +1,308 @@
+//! Settings and metadata handled by meilisearch on top of milli. They are stored in the main
+//! database of the milli index, under keys that can't collide with the ones used by milli.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Utc};
+use heed::types::{SerdeJson, Str};
+use heed::{RoTxn, RwTxn};
+use milli::update::Setting;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::error::Result;
+use super::{Checked, FlattenStrategy, SearchDefaults, Settings, SortCollation, TypoTolerance};
+
+mod main_key {
+    pub const DATE_ATTRIBUTES_KEY: &str = "meilisearch-date-attributes";
+    pub const COMPOSITE_PRIMARY_KEY_KEY: &str = "meilisearch-composite-primary-key";
+    pub const FLATTEN_STRATEGY_KEY: &str = "meilisearch-flatten-strategy";
+    pub const SORT_COLLATION_KEY: &str = "meilisearch-sort-collation";
+    pub const NORMALIZED_FILTERABLE_ATTRIBUTES_KEY: &str =
+        "meilisearch-normalized-filterable-attributes";
+    pub const SEARCHABLE_NUMERIC_ATTRIBUTES_KEY: &str = "meilisearch-searchable-numeric-attributes";
+    pub const RAW_ATTRIBUTES_KEY: &str = "meilisearch-raw-attributes";
+    pub const CHECKPOINT_BEFORE_SETTINGS_CHANGE_KEY: &str =
+        "meilisearch-checkpoint-before-settings-change";
+    pub const TYPO_TOLERANCE_KEY: &str = "meilisearch-typo-tolerance";
+    pub const SEARCH_DEFAULTS_KEY: &str = "meilisearch-search-defaults";
+    pub const ATTRIBUTE_PATTERNS_KEY: &str = "meilisearch-attribute-patterns";
+    pub const FACET_CARDINALITIES_KEY: &str = "meilisearch-facet-cardinalities";
+    pub const BOOLEAN_ATTRIBUTES_KEY: &str = "meilisearch-boolean-attributes";
+    pub const SEARCHABLE_WILDCARD_KEY: &str = "meilisearch-searchable-wildcard";
+    pub const LAST_INDEXED_AT_KEY: &str = "meilisearch-last-indexed-at";
+    /// The keys under which milli stores the creation and last update dates of the index.
+    pub const MILLI_CREATED_AT_KEY: &str = "created-at";
+    pub const MILLI_UPDATED_AT_KEY: &str = "updated-at";
+}
+
+fn get<T: DeserializeOwned>(index: &milli::Index, txn: &RoTxn, key: &str) -> Result<Option<T>> {
+    Ok(index.main.get::<_, Str, SerdeJson<T>>(txn, key)?)
+}
+
+fn apply<T: Serialize>(
+    index: &milli::Index,
+    txn: &mut RwTxn,
+    key: &str,
+    setting: &Setting<T>,
+) -> Result<()> {
+    match setting {
+        Setting::Set(value) => index.main.put::<_, Str, SerdeJson<T>>(txn, key, value)?,
+        Setting::Reset => {
+            index.main.delete::<_, Str>(txn, key)?;
+        }
+        Setting::NotSet => (),
+    }
+
+    Ok(())
+}
+
+/// The attributes holding dates, whose timestamps are computed at ingestion.
+pub fn date_attributes(index: &milli::Index, txn: &RoTxn) -> Result<BTreeSet<String>> {
+    Ok(get(index, txn, main_key::DATE_ATTRIBUTES_KEY)?.unwrap_or_default())
+}
+
+/// How the objects nested in the documents are exposed.
+pub fn flatten_strategy(index: &milli::Index, txn: &RoTxn) -> Result<FlattenStrategy> {
+    Ok(get(index, txn, main_key::FLATTEN_STRATEGY_KEY)?.unwrap_or_default())
+}
+
+/// How the strings of the sortable attributes are ordered.
+pub fn sort_collation(index: &milli::Index, txn: &RoTxn) -> Result<SortCollation> {
+    Ok(get(index, txn, main_key::SORT_COLLATION_KEY)?.unwrap_or_default())
+}
+
+/// The attributes whose equality filters ignore the case and accents.
+pub fn normalized_filterable_attributes(
+    index: &milli::Index,
+    txn: &RoTxn,
+) -> Result<BTreeSet<String>> {
+    Ok(get(index, txn, main_key::NORMALIZED_FILTERABLE_ATTRIBUTES_KEY)?.unwrap_or_default())
+}
+
+/// The numeric attributes that can be found by a full-text search.
+pub fn searchable_numeric_attributes(
+    index: &milli::Index,
+    txn: &RoTxn,
+) -> Result<BTreeSet<String>> {
+    Ok(get(index, txn, main_key::SEARCHABLE_NUMERIC_ATTRIBUTES_KEY)?.unwrap_or_default())
+}
+
+/// The attributes never highlighted nor cropped, such as the attributes holding HTML.
+pub fn raw_attributes(index: &milli::Index, txn: &RoTxn) -> Result<BTreeSet<String>> {
+    Ok(get(index, txn, main_key::RAW_ATTRIBUTES_KEY)?.unwrap_or_default())
+}
+
+/// Whether the index is copied before each settings update.
+pub fn checkpoint_before_settings_change(index: &milli::Index, txn: &RoTxn) -> Result<bool> {
+    Ok(get(index, txn, main_key::CHECKPOINT_BEFORE_SETTINGS_CHANGE_KEY)?.unwrap_or_default())
+}
+
+/// How many typos the searches tolerate, and where.
+pub fn typo_tolerance(index: &milli::Index, txn: &RoTxn) -> Result<TypoTolerance> {
+    Ok(get(index, txn, main_key::TYPO_TOLERANCE_KEY)?.unwrap_or_default())
+}
+
+/// The parameters of the searches that omit them.
+pub fn search_defaults(index: &milli::Index, txn: &RoTxn) -> Result<SearchDefaults> {
+    Ok(get(index, txn, main_key::SEARCH_DEFAULTS_KEY)?.unwrap_or_default())
+}
+
+/// The attribute settings given with patterns, such as `meta.*`, as they were given. milli only
+/// knows the attributes they matched when the documents were last indexed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AttributePatterns {
+    pub displayed: Option<Vec<String>>,
+    pub searchable: Option<Vec<String>>,
+    pub filterable: Option<BTreeSet<String>>,
+}
+
+impl AttributePatterns {
+    pub fn is_empty(&self) -> bool {
+        self.displayed.is_none() && self.searchable.is_none() && self.filterable.is_none()
+    }
+}
+
+pub fn attribute_patterns(index: &milli::Index, txn: &RoTxn) -> Result<AttributePatterns> {
+    Ok(get(index, txn, main_key::ATTRIBUTE_PATTERNS_KEY)?.unwrap_or_default())
+}
+
+pub fn put_attribute_patterns(
+    index: &milli::Index,
+    txn: &mut RwTxn,
+    patterns: &AttributePatterns,
+) -> Result<()> {
+    let setting = if patterns.is_empty() {
+        Setting::Reset
+    } else {
+        Setting::Set(patterns.clone())
+    };
+    apply(index, txn, main_key::ATTRIBUTE_PATTERNS_KEY, &setting)
+}
+
+/// The attributes composing the primary key of the index, if it is a composite one.
+pub fn composite_primary_key(index: &milli::Index, txn: &RoTxn) -> Result<Option<Vec<String>>> {
+    get(index, txn, main_key::COMPOSITE_PRIMARY_KEY_KEY)
+}
+
+/// The number of distinct values of each filterable attribute, as of the last document addition
+/// or filterable attributes update.
+pub fn facet_cardinalities(index: &milli::Index, txn: &RoTxn) -> Result<BTreeMap<String, u64>> {
+    Ok(get(index, txn, main_key::FACET_CARDINALITIES_KEY)?.unwrap_or_default())
+}
+
+pub fn put_facet_cardinalities(
+    index: &milli::Index,
+    txn: &mut RwTxn,
+    cardinalities: &BTreeMap<String, u64>,
+) -> Result<()> {
+    let setting = Setting::Set(cardinalities.clone());
+    apply(index, txn, main_key::FACET_CARDINALITIES_KEY, &setting)
+}
+
+/// The filterable attributes only holding booleans, as of the last document addition or
+/// filterable attributes update.
+pub fn boolean_attributes(index: &milli::Index, txn: &RoTxn) -> Result<BTreeSet<String>> {
+    Ok(get(index, txn, main_key::BOOLEAN_ATTRIBUTES_KEY)?.unwrap_or_default())
+}
+
+pub fn put_boolean_attributes(
+    index: &milli::Index,
+    txn: &mut RwTxn,
+    attributes: &BTreeSet<String>,
+) -> Result<()> {
+    let setting = Setting::Set(attributes.clone());
+    apply(index, txn, main_key::BOOLEAN_ATTRIBUTES_KEY, &setting)
+}
+
+/// Whether all the attributes are searchable while milli is given them explicitly, for the hidden
+/// attributes to be left out.
+pub fn searchable_wildcard(index: &milli::Index, txn: &RoTxn) -> Result<bool> {
+    Ok(get(index, txn, main_key::SEARCHABLE_WILDCARD_KEY)?.unwrap_or_default())
+}
+
+pub fn put_searchable_wildcard(
+    index: &milli::Index,
+    txn: &mut RwTxn,
+    wildcard: bool,
+) -> Result<()> {
+    let setting = if wildcard {
+        Setting::Set(true)
+    } else {
+        Setting::Reset
+    };
+    apply(index, txn, main_key::SEARCHABLE_WILDCARD_KEY, &setting)
+}
+
+/// The date of the last successful documents update.
+pub fn last_indexed_at(index: &milli::Index, txn: &RoTxn) -> Result<Option<DateTime<Utc>>> {
+    get(index, txn, main_key::LAST_INDEXED_AT_KEY)
+}
+
+pub fn put_last_indexed_at(
+    index: &milli::Index,
+    txn: &mut RwTxn,
+    date: Option<DateTime<Utc>>,
+) -> Result<()> {
+    let setting = match date {
+        Some(date) => Setting::Set(date),
+        None => Setting::Reset,
+    };
+    apply(index, txn, main_key::LAST_INDEXED_AT_KEY, &setting)
+}
+
+/// Restores the creation and last update dates of an index, which milli sets to the current date
+/// whenever the index is written.
+pub fn put_index_dates(
+    index: &milli::Index,
+    txn: &mut RwTxn,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+) -> Result<()> {
+    apply(
+        index,
+        txn,
+        main_key::MILLI_CREATED_AT_KEY,
+        &Setting::Set(created_at),
+    )?;
+    apply(
+        index,
+        txn,
+        main_key::MILLI_UPDATED_AT_KEY,
+        &Setting::Set(updated_at),
+    )
+}
+
+pub fn put_composite_primary_key(
+    index: &milli::Index,
+    txn: &mut RwTxn,
+    fields: &[String],
+) -> Result<()> {
+    let setting = Setting::Set(fields.to_vec());
+    apply(index, txn, main_key::COMPOSITE_PRIMARY_KEY_KEY, &setting)
+}
+
+/// Persists the settings that milli doesn't handle, this must be called in the same transaction
+/// as the milli settings update.
+pub fn apply_custom_settings(
+    index: &milli::Index,
+    txn: &mut RwTxn,
+    settings: &Settings<Checked>,
+) -> Result<()> {
+    apply(
+        index,
+        txn,
+        main_key::DATE_ATTRIBUTES_KEY,
+        &settings.date_attributes,
+    )?;
+    apply(
+        index,
+        txn,
+        main_key::FLATTEN_STRATEGY_KEY,
+        &settings.flatten_strategy,
+    )?;
+    apply(
+        index,
+        txn,
+        main_key::SORT_COLLATION_KEY,
+        &settings.sort_collation,
+    )?;
+    apply(
+        index,
+        txn,
+        main_key::NORMALIZED_FILTERABLE_ATTRIBUTES_KEY,
+        &settings.normalized_filterable_attributes,
+    )?;
+    apply(
+        index,
+        txn,
+        main_key::SEARCHABLE_NUMERIC_ATTRIBUTES_KEY,
+        &settings.searchable_numeric_attributes,
+    )?;
+    apply(
+        index,
+        txn,
+        main_key::RAW_ATTRIBUTES_KEY,
+        &settings.raw_attributes,
+    )?;
+    apply(
+        index,
+        txn,
+        main_key::CHECKPOINT_BEFORE_SETTINGS_CHANGE_KEY,
+        &settings.checkpoint_before_settings_change,
+    )?;
+    apply(
+        index,
+        txn,
+        main_key::TYPO_TOLERANCE_KEY,
+        &settings.typo_tolerance,
+    )?;
+    apply(
+        index,
+        txn,
+        main_key::SEARCH_DEFAULTS_KEY,
+        &settings.search_defaults,
+    )?;
+
+    Ok(())
+}
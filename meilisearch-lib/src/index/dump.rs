@@ -1,31 +1,60 @@
+use std::collections::BTreeSet;
 use std::fs::{create_dir_all, File};
-use std::io::{BufReader, Seek, SeekFrom, Write};
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
-use anyhow::Context;
-use heed::{EnvOpenOptions, RoTxn};
+use chrono::{DateTime, Utc};
+use heed::{EnvOpenOptions, RoTxn, RwTxn};
 use indexmap::IndexMap;
 use milli::documents::DocumentBatchReader;
+use milli::update::Setting;
 use serde::{Deserialize, Serialize};
-
-use crate::document_formats::read_ndjson;
+use uuid::Uuid;
+
+use crate::document_formats::{read_ndjson, DocumentLimits};
+use crate::index::collation::{
+    collated_sortable_fields, normalized_filterable_fields, numeric_searchable_fields,
+    update_searchable_fields,
+};
+use crate::index::custom_settings::{
+    apply_custom_settings, attribute_patterns, composite_primary_key, last_indexed_at,
+    put_attribute_patterns, put_composite_primary_key, put_index_dates, put_last_indexed_at,
+    AttributePatterns,
+};
 use crate::index::update_handler::UpdateHandler;
 use crate::index::updates::apply_settings_to_builder;
+use crate::io_throttle::{create_file, IoThrottle};
 
 use super::error::Result;
-use super::{Index, Settings, Unchecked};
+use super::{Index, Settings, SortCollation, Unchecked};
 
 #[derive(Serialize, Deserialize)]
 struct DumpMeta {
     settings: Settings<Unchecked>,
     primary_key: Option<String>,
+    #[serde(default)]
+    composite_primary_key: Option<Vec<String>>,
+    /// The dates of the index, missing from the dumps of the older versions.
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    last_indexed_at: Option<DateTime<Utc>>,
+    /// The attribute patterns of the settings, which hold the attributes the patterns matched.
+    #[serde(default)]
+    attribute_patterns: AttributePatterns,
 }
 
 const META_FILE_NAME: &str = "meta.json";
 const DATA_FILE_NAME: &str = "documents.jsonl";
+/// The size of the documents of an index under which they are converted in memory, rather than
+/// in a temporary file, before being indexed.
+const IN_MEMORY_CONVERSION_LIMIT: u64 = 64 * 1024 * 1024;
 
 impl Index {
-    pub fn dump(&self, path: impl AsRef<Path>) -> Result<()> {
+    /// Dumps the index in `path`, the dump being written at the rate of the throttle.
+    pub fn dump(&self, path: impl AsRef<Path>, throttle: Option<&IoThrottle>) -> Result<()> {
         // acquire write txn make sure any ongoing write is finished before we start.
         let txn = self.env.write_txn()?;
         let path = path
@@ -34,15 +63,20 @@ impl Index {
 
         create_dir_all(&path)?;
 
-        self.dump_documents(&txn, &path)?;
-        self.dump_meta(&txn, &path)?;
+        self.dump_documents(&txn, &path, throttle)?;
+        self.dump_meta(&txn, &path, throttle)?;
 
         Ok(())
     }
 
-    fn dump_documents(&self, txn: &RoTxn, path: impl AsRef<Path>) -> Result<()> {
+    fn dump_documents(
+        &self,
+        txn: &RoTxn,
+        path: impl AsRef<Path>,
+        throttle: Option<&IoThrottle>,
+    ) -> Result<()> {
         let document_file_path = path.as_ref().join(DATA_FILE_NAME);
-        let mut document_file = File::create(&document_file_path)?;
+        let mut document_file = create_file(&document_file_path, throttle)?;
 
         let documents = self.all_documents(txn)?;
         let fields_ids_map = self.fields_ids_map(txn)?;
@@ -67,15 +101,26 @@ impl Index {
         Ok(())
     }
 
-    fn dump_meta(&self, txn: &RoTxn, path: impl AsRef<Path>) -> Result<()> {
+    fn dump_meta(
+        &self,
+        txn: &RoTxn,
+        path: impl AsRef<Path>,
+        throttle: Option<&IoThrottle>,
+    ) -> Result<()> {
         let meta_file_path = path.as_ref().join(META_FILE_NAME);
-        let mut meta_file = File::create(&meta_file_path)?;
+        let mut meta_file = create_file(&meta_file_path, throttle)?;
 
-        let settings = self.settings_txn(txn)?.into_unchecked();
+        let settings = self.resolved_settings_txn(txn)?.into_unchecked();
         let primary_key = self.primary_key(txn)?.map(String::from);
+        let composite_primary_key = composite_primary_key(self, txn)?;
         let meta = DumpMeta {
             settings,
             primary_key,
+            composite_primary_key,
+            created_at: Some(self.created_at(txn)?),
+            updated_at: Some(self.updated_at(txn)?),
+            last_indexed_at: last_indexed_at(self, txn)?,
+            attribute_patterns: attribute_patterns(self, txn)?,
         };
 
         serde_json::to_writer(&mut meta_file, &meta)?;
@@ -83,18 +128,15 @@ impl Index {
         Ok(())
     }
 
+    /// Loads the index dumped at `src` in the database at `dst`, with the uuid `uuid`.
     pub fn load_dump(
         src: impl AsRef<Path>,
         dst: impl AsRef<Path>,
+        uuid: Uuid,
         size: usize,
         update_handler: &UpdateHandler,
     ) -> anyhow::Result<()> {
-        let dir_name = src
-            .as_ref()
-            .file_name()
-            .with_context(|| format!("invalid dump index: {}", src.as_ref().display()))?;
-
-        let dst_dir_path = dst.as_ref().join("indexes").join(dir_name);
+        let dst_dir_path = dst.as_ref().join("indexes").join(uuid.to_string());
         create_dir_all(&dst_dir_path)?;
 
         let meta_path = src.as_ref().join(META_FILE_NAME);
@@ -102,6 +144,11 @@ impl Index {
         let DumpMeta {
             settings,
             primary_key,
+            composite_primary_key,
+            created_at,
+            updated_at,
+            last_indexed_at,
+            attribute_patterns,
         } = serde_json::from_reader(meta_file)?;
         let settings = settings.check();
 
@@ -123,25 +170,107 @@ impl Index {
 
         builder.execute(|_, _| ())?;
 
-        let document_file_path = src.as_ref().join(DATA_FILE_NAME);
-        let reader = BufReader::new(File::open(&document_file_path)?);
+        apply_custom_settings(&index, &mut txn, &settings)?;
+        put_attribute_patterns(&index, &mut txn, &attribute_patterns)?;
 
-        let mut tmp_doc_file = tempfile::tempfile()?;
+        if let Some(fields) = composite_primary_key {
+            put_composite_primary_key(&index, &mut txn, &fields)?;
+        }
 
-        read_ndjson(reader, &mut tmp_doc_file)?;
+        let dates = match settings.date_attributes {
+            Setting::Set(ref dates) => dates.clone(),
+            _ => BTreeSet::new(),
+        };
 
-        tmp_doc_file.seek(SeekFrom::Start(0))?;
+        // The dumped documents already hold their collation keys and timestamps, they only need
+        // to be sortable.
+        if let Setting::Set(ref sortable) = settings.sortable_attributes {
+            let collation = match settings.sort_collation {
+                Setting::Set(collation) => collation,
+                _ => SortCollation::default(),
+            };
+            let fields = collated_sortable_fields(sortable.iter().cloned(), collation, &dates);
+            if fields.len() != sortable.len() {
+                let builder = update_handler.update_builder(0);
+                let mut builder = builder.settings(&mut txn, &index);
+                builder.set_sortable_fields(fields);
+                builder.execute(|_, _| ())?;
+            }
+        }
+
+        // The same goes for the normalized keys and timestamps, which must be filterable.
+        if let Setting::Set(ref filterable) = settings.filterable_attributes {
+            let normalized = match settings.normalized_filterable_attributes {
+                Setting::Set(ref normalized) => normalized.clone(),
+                _ => BTreeSet::new(),
+            };
+            let fields =
+                normalized_filterable_fields(filterable.iter().cloned(), &normalized, &dates);
+            if fields.len() != filterable.len() {
+                let builder = update_handler.update_builder(0);
+                let mut builder = builder.settings(&mut txn, &index);
+                builder.set_filterable_fields(fields);
+                builder.execute(|_, _| ())?;
+            }
+        }
+
+        // And for the numeric texts, which must be searchable.
+        if let (Setting::Set(searchable), Setting::Set(numeric)) = (
+            &settings.searchable_attributes,
+            &settings.searchable_numeric_attributes,
+        ) {
+            if let Some(fields) = numeric_searchable_fields(Some(searchable.clone()), numeric) {
+                if fields.len() != searchable.len() {
+                    let builder = update_handler.update_builder(0);
+                    let mut builder = builder.settings(&mut txn, &index);
+                    builder.set_searchable_fields(fields);
+                    builder.execute(|_, _| ())?;
+                }
+            }
+        }
+
+        let document_file_path = src.as_ref().join(DATA_FILE_NAME);
+        let document_file = File::open(&document_file_path)?;
+        let documents_size = document_file.metadata()?.len();
+        let reader = BufReader::new(document_file);
+
+        fn index_documents<'i, R: Read + Seek>(
+            documents_reader: DocumentBatchReader<R>,
+            txn: &mut RwTxn<'i, '_>,
+            index: &'i milli::Index,
+            update_handler: &UpdateHandler,
+        ) -> anyhow::Result<()> {
+            //If the document file is empty, we don't perform the document addition, to prevent
+            //a primary key error to be thrown.
+            if !documents_reader.is_empty() {
+                let builder = update_handler.update_builder(0).index_documents(txn, index);
+                builder.execute(documents_reader, |_, _| ())?;
+            }
+            Ok(())
+        }
+
+        if documents_size <= IN_MEMORY_CONVERSION_LIMIT {
+            let mut documents = Cursor::new(Vec::with_capacity(documents_size as usize));
+            read_ndjson(reader, &mut documents, DocumentLimits::default())?;
+            documents.set_position(0);
+            let documents_reader = DocumentBatchReader::from_reader(documents)?;
+            index_documents(documents_reader, &mut txn, &index, update_handler)?;
+        } else {
+            let mut tmp_doc_file = tempfile::tempfile()?;
+            read_ndjson(reader, &mut tmp_doc_file, DocumentLimits::default())?;
+            tmp_doc_file.seek(SeekFrom::Start(0))?;
+            let documents_reader = DocumentBatchReader::from_reader(tmp_doc_file)?;
+            index_documents(documents_reader, &mut txn, &index, update_handler)?;
+        }
 
-        let documents_reader = DocumentBatchReader::from_reader(tmp_doc_file)?;
+        // The dumped documents hold their hidden attributes, that must not be searchable.
+        update_searchable_fields(&index, &mut txn)?;
 
-        //If the document file is empty, we don't perform the document addition, to prevent
-        //a primary key error to be thrown.
-        if !documents_reader.is_empty() {
-            let builder = update_handler
-                .update_builder(0)
-                .index_documents(&mut txn, &index);
-            builder.execute(documents_reader, |_, _| ())?;
+        // The dates are restored last, as the updates above set them to the current date.
+        if let (Some(created_at), Some(updated_at)) = (created_at, updated_at) {
+            put_index_dates(&index, &mut txn, created_at, updated_at)?;
         }
+        put_last_indexed_at(&index, &mut txn, last_indexed_at)?;
 
         txn.commit()?;
 
@@ -3,6 +3,7 @@ use std::error::Error;
 use meilisearch_error::{Code, ErrorCode};
 use serde_json::Value;
 
+use super::filter::FilterSyntaxError;
 use crate::error::MilliError;
 
 pub type Result<T> = std::result::Result<T, IndexError>;
@@ -19,13 +20,97 @@ pub enum IndexError {
     Milli(#[from] milli::Error),
     #[error("A primary key is already present. It's impossible to update it")]
     ExistingPrimaryKey,
+    #[error("Invalid sort `{0}`: `_random(seed)` must be the only sort rule and the seed must be an integer between 0 and 18446744073709551615.")]
+    InvalidRandomSort(String),
+    #[error("Invalid value `{value}` for the date attribute `{attribute}`: dates must follow the RFC 3339 format.")]
+    InvalidDate { attribute: String, value: String },
+    #[error("Invalid facet `{0}`: ranges must be described by at least two increasing numeric bounds, such as `price:ranges(0,50,100)`.")]
+    InvalidFacetRanges(String),
+    #[error("A composite primary key must be composed of at least one attribute.")]
+    EmptyCompositePrimaryKey,
+    #[error(
+        "Document doesn't have a `{0}` attribute, which is part of the composite primary key."
+    )]
+    MissingCompositeIdField(String),
+    #[error("Invalid value `{value}` for the attribute `{field}` of the composite primary key: values must be positive integers or strings only composed of alphanumeric characters and underscores (_).")]
+    InvalidCompositeIdValue { field: String, value: String },
+    #[error(
+        "Invalid filter `{0}`: `OR` and `NOT` can't be used with the sameElement filter mode."
+    )]
+    UnsupportedSameElementFilter(String),
+    #[error("Invalid attribute pattern `{0}`: `*` must be the whole pattern or follow a `.`, such as `meta.*`.")]
+    InvalidAttributePattern(String),
+    #[error("Invalid filter `{filter}` at characters {}..{}: {}.", .error.start, .error.end, .error.message)]
+    FilterSyntax {
+        filter: String,
+        error: FilterSyntaxError,
+    },
+    #[error("The filterable attribute `{attribute}` holds at least {cardinality} distinct values, but filterable attributes are limited to {max} distinct values.")]
+    FacetCardinalityExceeded {
+        attribute: String,
+        cardinality: u64,
+        max: u64,
+    },
+    #[error("The index would hold {documents} documents, but the indexes are limited to {max} documents.")]
+    IndexDocumentQuotaExceeded { documents: u64, max: u64 },
+    #[error(
+        "The instance would hold {documents} documents, but it is limited to {max} documents."
+    )]
+    InstanceDocumentQuotaExceeded { documents: u64, max: u64 },
+    #[error("Unsupported locale `{0}`: the supported languages are cs, da, de, el, en, es, fi, fr, he, hi, it, ja, ko, nb, nl, no, pl, pt, ro, ru, sv, th, tr, uk and zh.")]
+    UnsupportedLocale(String),
+    #[error("The search reaches the result {depth}, but the searches are limited to the first {max} results. Use `searchAfter` to paginate deeper.")]
+    SearchDepthExceeded { depth: usize, max: usize },
+    #[error("The search asks for {limit} hits, but the searches are limited to {max} hits.")]
+    SearchLimitExceeded { limit: usize, max: usize },
+    #[error("The search asks for the distribution of {count} facets, but the searches are limited to {max} facets.")]
+    TooManyFacets { count: usize, max: usize },
+    #[error("The hits of the search would hold {count} attributes, but the searches are limited to {max} attributes. Use `attributesToRetrieve` to choose them.")]
+    TooManyAttributesToRetrieve { count: usize, max: usize },
+    #[error("Invalid `searchAfter`: {0}.")]
+    InvalidSearchAfter(String),
+    #[error("Attribute `{0}` is not searchable, the search can't be restricted to it.")]
+    AttributeNotSearchable(String),
+    #[error("Invalid diversity: `maxPerValue` must be at least 1.")]
+    InvalidDiversity,
+    #[error("Invalid typo tolerance: {0}.")]
+    InvalidTypoTolerance(String),
+    #[error("Invalid search defaults: {0}.")]
+    InvalidSearchDefaults(String),
+    #[error("Attribute `{0}` is not filterable, its values can't be searched.")]
+    FacetNotFilterable(String),
+    #[error("Attribute `{0}` is not filterable, its values can't be aggregated.")]
+    AttributeNotAggregatable(String),
+    #[error("Attribute `{0}` is not filterable, its values can't be counted by bucket.")]
+    BucketedFacetNotFilterable(String),
+    #[error("The update was canceled.")]
+    Canceled,
+    #[error("No checkpoint was taken before the update {0}.")]
+    CheckpointNotFound(u64),
+    #[error(
+        "The filter of a deletion can't be empty, the documents are cleared by the clear route."
+    )]
+    EmptyDeletionFilter,
+    #[error("Invalid value `{value}` for the attribute `{attribute}` with the locale `{locale}`: expected {expected}.")]
+    InvalidLocalizedValue {
+        attribute: String,
+        value: String,
+        locale: String,
+        expected: String,
+    },
+    #[error("Invalid merge operator on the attribute `{field}`: {message}.")]
+    InvalidMergeOperator { field: String, message: String },
+    #[error("The merge operators can only be used by the partial updates of the documents.")]
+    MergeOperatorsInReplacement,
 }
 
 internal_error!(
     IndexError: std::io::Error,
     heed::Error,
     fst::Error,
-    serde_json::Error
+    serde_json::Error,
+    csv::Error,
+    milli::documents::Error
 );
 
 impl ErrorCode for IndexError {
@@ -36,6 +121,39 @@ impl ErrorCode for IndexError {
             IndexError::Facet(e) => e.error_code(),
             IndexError::Milli(e) => MilliError(e).error_code(),
             IndexError::ExistingPrimaryKey => Code::PrimaryKeyAlreadyPresent,
+            IndexError::InvalidRandomSort(_) => Code::Sort,
+            IndexError::InvalidDate { .. } => Code::InvalidDate,
+            IndexError::InvalidFacetRanges(_) => Code::Facet,
+            IndexError::EmptyCompositePrimaryKey => Code::BadRequest,
+            IndexError::MissingCompositeIdField(_) => Code::MissingDocumentId,
+            IndexError::InvalidCompositeIdValue { .. } => Code::InvalidDocumentId,
+            IndexError::UnsupportedSameElementFilter(_) => Code::Filter,
+            IndexError::FilterSyntax { .. } => Code::Filter,
+            IndexError::InvalidAttributePattern(_) => Code::BadRequest,
+            IndexError::FacetCardinalityExceeded { .. } => Code::Facet,
+            IndexError::IndexDocumentQuotaExceeded { .. } => Code::DocumentQuotaExceeded,
+            IndexError::InstanceDocumentQuotaExceeded { .. } => Code::DocumentQuotaExceeded,
+            IndexError::UnsupportedLocale(_) => Code::BadRequest,
+            // A canceled update is given the canceled status instead of failing.
+            IndexError::Canceled => Code::Internal,
+            IndexError::CheckpointNotFound(_) => Code::CheckpointNotFound,
+            IndexError::EmptyDeletionFilter => Code::Filter,
+            IndexError::FacetNotFilterable(_) => Code::Facet,
+            IndexError::AttributeNotAggregatable(_) => Code::Facet,
+            IndexError::BucketedFacetNotFilterable(_) => Code::Facet,
+            IndexError::SearchDepthExceeded { .. } => Code::SearchDepthExceeded,
+            IndexError::SearchLimitExceeded { .. } => Code::SearchLimitExceeded,
+            IndexError::TooManyFacets { .. } => Code::TooManyFacets,
+            IndexError::TooManyAttributesToRetrieve { .. } => Code::TooManyAttributesToRetrieve,
+            IndexError::InvalidSearchAfter(_) => Code::BadRequest,
+            IndexError::AttributeNotSearchable(_) => Code::BadRequest,
+            IndexError::InvalidDiversity => Code::BadRequest,
+            IndexError::InvalidTypoTolerance(_) => Code::BadRequest,
+            IndexError::InvalidSearchDefaults(_) => Code::BadRequest,
+            IndexError::InvalidLocalizedValue { .. } => Code::Filter,
+            IndexError::InvalidMergeOperator { .. } | IndexError::MergeOperatorsInReplacement => {
+                Code::InvalidMergeOperator
+            }
         }
     }
 }
@@ -0,0 +1,197 @@
+//! Export of the whole set of documents matched by a search, as CSV or NDJSON, for the reports
+//! that need more documents than a page of results.
+
+use std::io::Write;
+use std::str::FromStr;
+
+use milli::{obkv_to_json, AscDesc, DocumentId, FieldId, SortError};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::collation::collate_sort_rules;
+use super::error::{IndexError, Result};
+use super::search::parse_filter;
+use super::Index;
+
+/// The number of documents read from a single read of the documents database.
+const EXPORT_CHUNK_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    Ndjson,
+    Csv,
+}
+
+impl Default for ExportFormat {
+    fn default() -> Self {
+        ExportFormat::Ndjson
+    }
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Csv => "text/csv",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ExportQuery {
+    pub q: Option<String>,
+    pub filter: Option<Value>,
+    /// The locale the numbers and dates of the filter are written in.
+    pub locale: Option<String>,
+    pub sort: Option<Vec<String>>,
+    /// The exported fields, in the order of the CSV columns. All the displayed fields by default.
+    pub fields: Option<Vec<String>>,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// The documents matched by an export query, ranked, and the fields to write.
+pub struct SearchExport {
+    documents_ids: Vec<DocumentId>,
+    /// The exported fields, the ones that are unknown to the index are written empty.
+    fields: Vec<(String, Option<FieldId>)>,
+    format: ExportFormat,
+}
+
+impl Index {
+    /// Runs the search of an export. The documents are read later on, by `write_export`, so that
+    /// the invalid queries fail before anything is sent.
+    pub fn prepare_export(&self, query: ExportQuery) -> Result<SearchExport> {
+        let rtxn = self.read_txn()?;
+        let mut search = self.search(&rtxn);
+
+        if let Some(ref q) = query.q {
+            search.query(q);
+        }
+        search.limit(self.number_of_documents(&rtxn)? as usize);
+
+        if let Some(ref filter) = query.filter {
+            if let Some(condition) = parse_filter(filter, self, &rtxn, query.locale.as_deref())? {
+                search.filter(condition);
+            }
+        }
+
+        if let Some(ref sort) = query.sort {
+            let sort = collate_sort_rules(sort, &self.sortable_fields(&rtxn)?);
+            let sort = match sort.iter().map(|s| AscDesc::from_str(s)).collect() {
+                Ok(sorts) => sorts,
+                Err(asc_desc_error) => {
+                    return Err(IndexError::Milli(SortError::from(asc_desc_error).into()))
+                }
+            };
+            search.sort_criteria(sort);
+        }
+
+        let documents_ids = search.execute()?.documents_ids;
+
+        let fields_ids_map = self.fields_ids_map(&rtxn)?;
+        let displayed = self.fields_to_display(&rtxn, &None::<Vec<String>>, &fields_ids_map)?;
+        let fields = match query.fields {
+            Some(fields) => fields
+                .into_iter()
+                .map(|name| {
+                    let fid = fields_ids_map
+                        .id(&name)
+                        .filter(|fid| displayed.contains(fid));
+                    (name, fid)
+                })
+                .collect(),
+            None => displayed
+                .into_iter()
+                .filter_map(|fid| {
+                    fields_ids_map
+                        .name(fid)
+                        .map(|name| (name.to_string(), Some(fid)))
+                })
+                .collect(),
+        };
+
+        Ok(SearchExport {
+            documents_ids,
+            fields,
+            format: query.format,
+        })
+    }
+
+    /// Writes the documents of the export, in their ranking order. The documents deleted since
+    /// the search are skipped.
+    pub fn write_export(&self, export: SearchExport, writer: impl Write) -> Result<()> {
+        let rtxn = self.read_txn()?;
+        let fields_ids_map = self.fields_ids_map(&rtxn)?;
+        let fids: Vec<_> = export.fields.iter().filter_map(|(_, fid)| *fid).collect();
+        let existing = self.documents_ids(&rtxn)?;
+        let chunks = export
+            .documents_ids
+            .chunks(EXPORT_CHUNK_SIZE)
+            .map(|chunk| chunk.iter().copied().filter(|id| existing.contains(*id)));
+
+        match export.format {
+            ExportFormat::Ndjson => {
+                let mut writer = writer;
+                for chunk in chunks {
+                    for (_, obkv) in self.documents(&rtxn, chunk)? {
+                        let document = obkv_to_json(&fids, &fields_ids_map, obkv)?;
+                        serde_json::to_writer(&mut writer, &document)?;
+                        writer.write_all(b"\n")?;
+                    }
+                }
+                writer.flush()?;
+            }
+            ExportFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(writer);
+                writer.write_record(export.fields.iter().map(|(name, _)| name))?;
+                for chunk in chunks {
+                    for (_, obkv) in self.documents(&rtxn, chunk)? {
+                        let mut record = Vec::with_capacity(export.fields.len());
+                        for (_, fid) in &export.fields {
+                            let value = match fid.and_then(|fid| obkv.get(fid)) {
+                                Some(value) => serde_json::from_slice(value)?,
+                                None => Value::Null,
+                            };
+                            record.push(csv_cell(value));
+                        }
+                        writer.write_record(&record)?;
+                    }
+                }
+                writer.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The strings are written as is, the other values as JSON, `null` being an empty cell.
+fn csv_cell(value: Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s,
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn csv_cells() {
+        assert_eq!(csv_cell(json!(null)), "");
+        assert_eq!(csv_cell(json!("Harry Potter")), "Harry Potter");
+        assert_eq!(csv_cell(json!(42)), "42");
+        assert_eq!(csv_cell(json!(true)), "true");
+        assert_eq!(
+            csv_cell(json!(["fantasy", "magic"])),
+            r#"["fantasy","magic"]"#
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Detection of the filterable attributes holding too many distinct values, typically an
+//! identifier made filterable by mistake, which blows up the size of the facet databases, and of
+//! the ones only holding booleans.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use heed::{RoTxn, RwTxn};
+
+use super::custom_settings::{put_boolean_attributes, put_facet_cardinalities};
+use super::error::{IndexError, Result};
+use super::Index;
+
+/// The number of distinct values above which the values of an attribute stop being counted.
+pub const MAX_COUNTED_CARDINALITY: u64 = 100_000;
+/// The number of documents under which no warning is emitted, a small index can't tell an
+/// identifier from a legitimate facet.
+const MIN_DOCUMENTS_FOR_WARNING: u64 = 1000;
+/// The number of distinct values per document above which a filterable attribute is reported.
+const HIGH_CARDINALITY_RATIO: f64 = 0.5;
+
+impl Index {
+    /// Counts the distinct values of each filterable attribute in the facet databases, stopping
+    /// at `limit` values. A string and a number are distinct values.
+    fn compute_facet_cardinalities(
+        &self,
+        txn: &RoTxn,
+        limit: u64,
+    ) -> Result<BTreeMap<String, u64>> {
+        let fields_ids_map = self.fields_ids_map(txn)?;
+        self.user_filterable_fields(txn)?
+            .into_iter()
+            .map(|name| {
+                let cardinality = match fields_ids_map.id(&name) {
+                    Some(fid) => self.count_facet_values(txn, fid, limit)?,
+                    None => 0,
+                };
+                Ok((name, cardinality))
+            })
+            .collect()
+    }
+
+    /// Recounts and persists the cardinality of the filterable attributes, returning a warning for
+    /// each attribute looking like an identifier. Fails if an attribute holds more than
+    /// `max_cardinality` distinct values, so that the update is not committed.
+    pub(super) fn update_facet_cardinalities<'a, 'b>(
+        &'a self,
+        txn: &mut RwTxn<'a, 'b>,
+        max_cardinality: Option<u64>,
+    ) -> Result<Vec<String>> {
+        // Counting one more value than the cap is enough to know it is exceeded.
+        let limit = max_cardinality.map_or(MAX_COUNTED_CARDINALITY, |max| {
+            max.saturating_add(1).max(MAX_COUNTED_CARDINALITY)
+        });
+        let cardinalities = self.compute_facet_cardinalities(txn, limit)?;
+
+        if let Some(max) = max_cardinality {
+            if let Some((attribute, cardinality)) = cardinalities.iter().find(|(_, c)| **c > max) {
+                return Err(IndexError::FacetCardinalityExceeded {
+                    attribute: attribute.clone(),
+                    cardinality: *cardinality,
+                    max,
+                });
+            }
+        }
+
+        let documents = self.number_of_documents(txn)?;
+        let warnings = cardinalities
+            .iter()
+            .filter(|(_, cardinality)| {
+                documents >= MIN_DOCUMENTS_FOR_WARNING
+                    && **cardinality as f64 > documents as f64 * HIGH_CARDINALITY_RATIO
+            })
+            .map(|(attribute, cardinality)| {
+                format!(
+                    "The filterable attribute `{}` holds {} distinct values for {} documents, it is probably an identifier that should not be filterable.",
+                    attribute, cardinality, documents
+                )
+            })
+            .collect();
+
+        put_facet_cardinalities(self, txn, &cardinalities)?;
+
+        Ok(warnings)
+    }
+
+    /// Records the filterable attributes whose facet values are only `true` and `false`, the
+    /// strings milli stores the booleans as, for the filters to accept them as conditions on
+    /// their own.
+    pub(super) fn update_boolean_attributes<'a, 'b>(
+        &'a self,
+        txn: &mut RwTxn<'a, 'b>,
+    ) -> Result<()> {
+        let fields_ids_map = self.fields_ids_map(txn)?;
+        let mut booleans = BTreeSet::new();
+        for name in self.user_filterable_fields(txn)? {
+            let fid = match fields_ids_map.id(&name) {
+                Some(fid) => fid,
+                None => continue,
+            };
+            if self.facet_numbers(txn, fid)?.next().is_some() {
+                continue;
+            }
+
+            // There are at most two boolean values.
+            let values = self
+                .facet_strings(txn, fid)?
+                .take(3)
+                .map(|entry| entry.map(|(value, _)| value == "true" || value == "false"))
+                .collect::<Result<Vec<_>>>()?;
+            if !values.is_empty() && values.len() <= 2 && values.iter().all(|boolean| *boolean) {
+                booleans.insert(name);
+            }
+        }
+
+        put_boolean_attributes(self, txn, &booleans)
+    }
+}
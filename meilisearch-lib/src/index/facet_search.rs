@@ -0,0 +1,218 @@
+//! The search within the values of a facet, such as typing `app` to find the brands matching it
+//! along with their number of documents, for the interfaces suggesting the facet values.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Instant;
+
+use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder, DFA};
+use meilisearch_tokenizer::Analyzer;
+use roaring::RoaringBitmap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::collation::{collation_analyzer, normalize_string};
+use super::error::{IndexError, Result};
+use super::query_debug::max_typos;
+use super::search::parse_filter;
+use super::Index;
+
+pub const DEFAULT_FACET_HITS_LIMIT: usize = 20;
+
+fn default_facet_hits_limit() -> usize {
+    DEFAULT_FACET_HITS_LIMIT
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct FacetSearchQuery {
+    /// The filterable attribute whose values are searched.
+    pub facet_name: String,
+    /// The words the values must contain, the last one as a prefix, with typos. All the values
+    /// are returned when it is missing.
+    pub facet_query: Option<String>,
+    /// Only counts the documents matching this query.
+    pub q: Option<String>,
+    /// Only counts the documents matching this filter.
+    pub filter: Option<Value>,
+    /// The locale the numbers and dates of the filter are written in.
+    pub locale: Option<String>,
+    #[serde(default = "default_facet_hits_limit")]
+    pub limit: usize,
+    /// The only fields whose values can be searched, set by the restrictions of the API key the
+    /// search is made with.
+    #[serde(skip)]
+    pub allowed_fields: Option<BTreeSet<String>>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FacetHit {
+    /// The value as written in the first document holding it.
+    pub value: String,
+    /// The number of matching documents holding the value.
+    pub count: u64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FacetSearchResult {
+    /// The matching values, the most frequent first.
+    pub facet_hits: Vec<FacetHit>,
+    pub facet_query: Option<String>,
+    pub processing_time_ms: u128,
+}
+
+impl Index {
+    pub fn facet_search(&self, query: FacetSearchQuery) -> Result<FacetSearchResult> {
+        let before_search = Instant::now();
+        let rtxn = self.read_txn()?;
+
+        if !self.filterable_fields(&rtxn)?.contains(&query.facet_name) {
+            return Err(IndexError::FacetNotFilterable(query.facet_name));
+        }
+        let allowed = query
+            .allowed_fields
+            .as_ref()
+            .map_or(true, |allowed| allowed.contains(&query.facet_name));
+        let fid = match self.fields_ids_map(&rtxn)?.id(&query.facet_name) {
+            Some(fid) if allowed => fid,
+            _ => {
+                return Ok(FacetSearchResult {
+                    facet_hits: Vec::new(),
+                    facet_query: query.facet_query,
+                    processing_time_ms: before_search.elapsed().as_millis(),
+                })
+            }
+        };
+
+        // A placeholder search gives us all the documents matching the filter.
+        let mut search = self.search(&rtxn);
+        search.limit(0);
+        if let Some(ref q) = query.q {
+            search.query(q);
+        }
+        if let Some(ref filter) = query.filter {
+            if let Some(facets) = parse_filter(filter, self, &rtxn, query.locale.as_deref())? {
+                search.filter(facets);
+            }
+        }
+        let candidates = search.execute()?.candidates;
+
+        let analyzer = collation_analyzer(&fst::Set::default());
+        // The values by normalized form, with their original form and the matching documents
+        // holding them, read from the facet databases.
+        let mut values: BTreeMap<String, (String, RoaringBitmap)> = BTreeMap::new();
+        let mut insert = |value: String, docids: RoaringBitmap| {
+            let docids = docids & &candidates;
+            if !docids.is_empty() {
+                let normalized = normalize_string(&value, &analyzer);
+                *values
+                    .entry(normalized)
+                    .or_insert((value, RoaringBitmap::new()))
+                    .1 |= docids;
+            }
+        };
+        for entry in self.facet_strings(&rtxn, fid)? {
+            let (value, docids) = entry?;
+            insert(value.to_string(), docids);
+        }
+        for entry in self.facet_numbers(&rtxn, fid)? {
+            let (value, docids) = entry?;
+            insert(value.to_string(), docids);
+        }
+
+        let matcher = query
+            .facet_query
+            .as_deref()
+            .map(|facet_query| FacetQueryMatcher::new(facet_query, &analyzer));
+        let mut facet_hits: Vec<_> = values
+            .into_iter()
+            .filter(|(normalized, _)| {
+                matcher
+                    .as_ref()
+                    .map_or(true, |matcher| matcher.matches(normalized, &analyzer))
+            })
+            .map(|(_, (value, docids))| FacetHit {
+                value,
+                count: docids.len(),
+            })
+            .collect();
+        facet_hits.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+        facet_hits.truncate(query.limit);
+
+        Ok(FacetSearchResult {
+            facet_hits,
+            facet_query: query.facet_query,
+            processing_time_ms: before_search.elapsed().as_millis(),
+        })
+    }
+}
+
+/// Matches the values containing every word of the facet query, the last word being a prefix
+/// unless the query ends with a separator.
+struct FacetQueryMatcher {
+    dfas: Vec<DFA>,
+}
+
+impl FacetQueryMatcher {
+    fn new<A: AsRef<[u8]>>(facet_query: &str, analyzer: &Analyzer<A>) -> Self {
+        let analyzed = analyzer.analyze(facet_query);
+        let words: Vec<_> = analyzed
+            .tokens()
+            .filter(|token| token.is_word())
+            .map(|token| (token.text().to_string(), token.byte_end))
+            .collect();
+
+        let dfas = words
+            .iter()
+            .enumerate()
+            .map(|(i, (word, byte_end))| {
+                let builder = LevenshteinAutomatonBuilder::new(max_typos(word), true);
+                if i + 1 == words.len() && *byte_end == facet_query.len() {
+                    builder.build_prefix_dfa(word)
+                } else {
+                    builder.build_dfa(word)
+                }
+            })
+            .collect();
+
+        Self { dfas }
+    }
+
+    fn matches<A: AsRef<[u8]>>(&self, normalized: &str, analyzer: &Analyzer<A>) -> bool {
+        let analyzed = analyzer.analyze(normalized);
+        let words: Vec<_> = analyzed
+            .tokens()
+            .filter(|token| token.is_word())
+            .map(|token| token.text().to_string())
+            .collect();
+        self.dfas.iter().all(|dfa| {
+            words
+                .iter()
+                .any(|word| matches!(dfa.eval(word), Distance::Exact(_)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn facet_query_matching() {
+        let analyzer = collation_analyzer(&fst::Set::default());
+        let matches = |facet_query: &str, value: &str| {
+            let matcher = FacetQueryMatcher::new(facet_query, &analyzer);
+            matcher.matches(&normalize_string(value, &analyzer), &analyzer)
+        };
+
+        assert!(matches("app", "Apple"));
+        assert!(matches("appel", "Apple"));
+        assert!(matches("APPLE", "apple"));
+        assert!(matches("elec", "General Electric"));
+        assert!(!matches("app", "Samsung"));
+        // only the last word is a prefix
+        assert!(!matches("gen elec", "General Electric"));
+        assert!(!matches("app ", "Apple"));
+    }
+}
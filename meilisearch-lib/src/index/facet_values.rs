@@ -0,0 +1,74 @@
+//! Reading of the values of the filterable attributes from the facet databases of milli, which
+//! hold each distinct value along with its documents, without decoding the documents.
+
+use heed::types::{ByteSlice, DecodeIgnore};
+use heed::RoTxn;
+use milli::FieldId;
+use roaring::RoaringBitmap;
+
+use super::error::Result;
+use super::Index;
+
+/// The prefix of the keys of the level 0 of the facet strings database, holding the values of the
+/// attribute `fid`.
+fn level_zero_prefix(fid: FieldId) -> Vec<u8> {
+    let mut prefix = fid.to_be_bytes().to_vec();
+    prefix.push(0);
+    prefix
+}
+
+impl Index {
+    /// The string values of the attribute `fid`, as they were given in the documents, with their
+    /// documents, in the order of their normalized form.
+    pub(super) fn facet_strings<'t>(
+        &self,
+        txn: &'t RoTxn,
+        fid: FieldId,
+    ) -> Result<impl Iterator<Item = Result<(&'t str, RoaringBitmap)>> + 't> {
+        let prefix = level_zero_prefix(fid);
+        let iter = self
+            .facet_id_string_docids
+            .remap_key_type::<ByteSlice>()
+            .prefix_iter(txn, &prefix)?;
+        Ok(iter.map(|entry| {
+            let (_, (original, docids)) = entry?;
+            Ok((original, docids))
+        }))
+    }
+
+    /// The number values of the attribute `fid` with their documents, in ascending order.
+    pub(super) fn facet_numbers<'t>(
+        &self,
+        txn: &'t RoTxn,
+        fid: FieldId,
+    ) -> Result<impl Iterator<Item = Result<(f64, RoaringBitmap)>> + 't> {
+        let range = (fid, 0, f64::MIN, f64::MIN)..=(fid, 0, f64::MAX, f64::MAX);
+        let iter = self.facet_id_f64_docids.range(txn, &range)?;
+        Ok(iter.map(|entry| {
+            let ((_, _, value, _), docids) = entry?;
+            Ok((value, docids))
+        }))
+    }
+
+    /// The number of distinct values of the attribute `fid`, counted up to `limit`.
+    pub(super) fn count_facet_values(&self, txn: &RoTxn, fid: FieldId, limit: u64) -> Result<u64> {
+        let limit = limit as usize;
+        let prefix = level_zero_prefix(fid);
+        let strings = self
+            .facet_id_string_docids
+            .remap_types::<ByteSlice, DecodeIgnore>()
+            .prefix_iter(txn, &prefix)?
+            .take(limit)
+            .try_fold(0, |count, entry| entry.map(|_| count + 1))?;
+
+        let range = (fid, 0, f64::MIN, f64::MIN)..=(fid, 0, f64::MAX, f64::MAX);
+        let numbers = self
+            .facet_id_f64_docids
+            .remap_data_type::<DecodeIgnore>()
+            .range(txn, &range)?
+            .take(limit - strings)
+            .try_fold(0, |count, entry| entry.map(|_| count + 1))?;
+
+        Ok((strings + numbers) as u64)
+    }
+}
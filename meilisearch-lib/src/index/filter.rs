@@ -0,0 +1,554 @@
+//! Rewriting of the filter expressions before they are handed to milli.
+
+use std::collections::BTreeSet;
+
+use super::collation::{normalized_key_field, timestamp_field};
+use super::error::{IndexError, Result};
+use super::locale::FilterLocale;
+
+/// A token of a filter expression.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Token<'a> {
+    Word(&'a str),
+    /// A quoted string, quotes included.
+    Quoted(&'a str),
+    Operator(&'a str),
+    Punctuation(char),
+}
+
+/// Splits the expression into tokens, along with their position in the expression.
+pub(super) fn tokenize(expr: &str) -> Vec<(usize, Token)> {
+    const OPERATOR_CHARS: &[char] = &['=', '!', '<', '>'];
+    const PUNCTUATION_CHARS: &[char] = &['(', ')', ','];
+
+    let mut tokens = Vec::new();
+    let mut chars = expr.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let token = if c.is_whitespace() {
+            continue;
+        } else if PUNCTUATION_CHARS.contains(&c) {
+            Token::Punctuation(c)
+        } else if c == '"' || c == '\'' {
+            let end = chars
+                .by_ref()
+                .find(|(_, other)| *other == c)
+                .map_or(expr.len(), |(i, quote)| i + quote.len_utf8());
+            Token::Quoted(&expr[start..end])
+        } else {
+            let is_operator = OPERATOR_CHARS.contains(&c);
+            let mut end = start + c.len_utf8();
+            while let Some(&(i, other)) = chars.peek() {
+                let same_kind = if is_operator {
+                    OPERATOR_CHARS.contains(&other)
+                } else {
+                    !other.is_whitespace()
+                        && !OPERATOR_CHARS.contains(&other)
+                        && !PUNCTUATION_CHARS.contains(&other)
+                        && other != '"'
+                        && other != '\''
+                };
+                if !same_kind {
+                    break;
+                }
+                end = i + other.len_utf8();
+                chars.next();
+            }
+
+            if is_operator {
+                Token::Operator(&expr[start..end])
+            } else {
+                Token::Word(&expr[start..end])
+            }
+        };
+
+        tokens.push((start, token));
+    }
+
+    tokens
+}
+
+/// The maximum number of nested parentheses in a filter expression.
+pub const MAX_FILTER_DEPTH: usize = 100;
+
+/// A syntax error in a filter expression, spanning the characters `start..end`.
+#[derive(Debug, PartialEq)]
+pub struct FilterSyntaxError {
+    pub start: usize,
+    pub end: usize,
+    pub message: String,
+}
+
+/// Checks the structure of the expression before it is handed to milli, reporting the position of
+/// the unbalanced parentheses and quotes, of the dangling operators and of the nesting exceeding
+/// `MAX_FILTER_DEPTH`, which would otherwise overflow the stack of the parser.
+pub fn check_filter_syntax(expr: &str) -> Result<(), FilterSyntaxError> {
+    let char_position = |byte: usize| expr[..byte].chars().count();
+    let error = |start: usize, len: usize, message: &str| FilterSyntaxError {
+        start: char_position(start),
+        end: char_position(start + len),
+        message: message.to_string(),
+    };
+
+    let tokens = tokenize(expr);
+    let mut open_parentheses = Vec::new();
+
+    for (i, (start, token)) in tokens.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|i| &tokens[i].1);
+        let next = tokens.get(i + 1).map(|(_, token)| token);
+        match token {
+            Token::Punctuation('(') => {
+                open_parentheses.push(*start);
+                if open_parentheses.len() > MAX_FILTER_DEPTH {
+                    return Err(error(
+                        *start,
+                        1,
+                        &format!("more than {} nested parentheses", MAX_FILTER_DEPTH),
+                    ));
+                }
+                if let Some(Token::Punctuation(')')) = next {
+                    return Err(error(*start, 2, "expected a condition, found `()`"));
+                }
+            }
+            Token::Punctuation(')') => {
+                if open_parentheses.pop().is_none() {
+                    return Err(error(*start, 1, "unexpected `)`"));
+                }
+            }
+            Token::Quoted(quoted) => {
+                let quote = quoted.chars().next().unwrap_or('"');
+                if quoted.len() < 2 || !quoted.ends_with(quote) {
+                    return Err(error(
+                        *start,
+                        quoted.len(),
+                        "unterminated string, expected a closing quote",
+                    ));
+                }
+            }
+            Token::Operator(operator) => {
+                if matches!(next, None | Some(Token::Punctuation(')'))) {
+                    return Err(error(
+                        *start,
+                        operator.len(),
+                        "expected a value after the operator",
+                    ));
+                }
+            }
+            // A keyword following an operator is a value.
+            Token::Word(word)
+                if is_keyword(Some(token), &["AND", "OR", "NOT"])
+                    && !matches!(previous, Some(Token::Operator(_))) =>
+            {
+                if matches!(next, None | Some(Token::Punctuation(')'))) {
+                    return Err(error(
+                        *start,
+                        word.len(),
+                        "expected a condition after the keyword",
+                    ));
+                }
+                let starts_expression = matches!(previous, None | Some(Token::Punctuation('(')));
+                if starts_expression && !word.eq_ignore_ascii_case("NOT") {
+                    return Err(error(
+                        *start,
+                        word.len(),
+                        "expected a condition before the keyword",
+                    ));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    match open_parentheses.pop() {
+        Some(start) => Err(error(start, 1, "unclosed `(`, expected `)`")),
+        None => Ok(()),
+    }
+}
+
+pub(super) fn is_keyword(token: Option<&Token>, keywords: &[&str]) -> bool {
+    match token {
+        Some(Token::Word(word)) => keywords.iter().any(|k| word.eq_ignore_ascii_case(k)),
+        _ => false,
+    }
+}
+
+/// Expands the boolean attributes used as conditions on their own, so that `is_published` and
+/// `NOT is_published` respectively become `is_published = true` and `NOT is_published = true`,
+/// `true` being the facet value milli stores for the booleans. Only the `boolean_fields` are
+/// expanded, the other attributes can't be conditions on their own.
+pub fn expand_boolean_conditions(expr: &str, boolean_fields: &BTreeSet<String>) -> String {
+    let tokens = tokenize(expr);
+    let mut expanded = String::with_capacity(expr.len());
+    let mut copied = 0;
+
+    for (i, (start, token)) in tokens.iter().enumerate() {
+        let word = match token {
+            Token::Word(word) if boolean_fields.contains(*word) => word,
+            _ => continue,
+        };
+
+        let previous = i.checked_sub(1).map(|i| &tokens[i].1);
+        let next = tokens.get(i + 1).map(|(_, token)| token);
+
+        let starts_condition = matches!(previous, None | Some(Token::Punctuation('(')))
+            || is_keyword(previous, &["AND", "OR", "NOT"]);
+        let ends_condition = matches!(next, None | Some(Token::Punctuation(')')))
+            || is_keyword(next, &["AND", "OR"]);
+
+        if starts_condition && ends_condition {
+            let end = start + word.len();
+            expanded.push_str(&expr[copied..end]);
+            expanded.push_str(" = true");
+            copied = end;
+        }
+    }
+
+    expanded.push_str(&expr[copied..]);
+    expanded
+}
+
+/// Redirects the equality conditions on the `normalized` attributes to their normalized key, the
+/// compared value being normalized with `normalize`: `brand = Apple` becomes
+/// `_normalized.brand = "apple"`.
+pub fn normalize_conditions(
+    expr: &str,
+    normalized: &BTreeSet<String>,
+    normalize: impl Fn(&str) -> String,
+) -> String {
+    let tokens = tokenize(expr);
+    let mut rewritten = String::with_capacity(expr.len());
+    let mut copied = 0;
+
+    for window in tokens.windows(3) {
+        let (start, attribute, operator, value_start, value) = match window {
+            [(start, Token::Word(attribute)), (_, Token::Operator(operator)), (value_start, value)]
+                if normalized.contains(*attribute) && (*operator == "=" || *operator == "!=") =>
+            {
+                (start, attribute, operator, value_start, value)
+            }
+            _ => continue,
+        };
+
+        let (raw, unquoted) = match value {
+            Token::Word(word) => (*word, *word),
+            Token::Quoted(quoted) => (*quoted, quoted.trim_matches(|c| c == '"' || c == '\'')),
+            _ => continue,
+        };
+
+        rewritten.push_str(&expr[copied..*start]);
+        rewritten.push_str(&format!(
+            "{} {} \"{}\"",
+            normalized_key_field(attribute),
+            operator,
+            normalize(unquoted).replace('"', "")
+        ));
+        copied = value_start + raw.len();
+    }
+
+    rewritten.push_str(&expr[copied..]);
+    rewritten
+}
+
+/// Redirects the conditions on the `dates` attributes to their timestamps, the values of the
+/// conditions being timestamps: `created_at > 1634169600` becomes
+/// `_timestamp.created_at > 1634169600`.
+pub fn redirect_date_conditions(expr: &str, dates: &BTreeSet<String>) -> String {
+    let tokens = tokenize(expr);
+    let mut rewritten = String::with_capacity(expr.len());
+    let mut copied = 0;
+
+    for (i, (start, token)) in tokens.iter().enumerate() {
+        let attribute = match token {
+            Token::Word(attribute) if dates.contains(*attribute) => attribute,
+            _ => continue,
+        };
+        // The attribute of a comparison is followed by its operator, the one of a range by the
+        // lower bound and `TO`.
+        let is_condition = match (tokens.get(i + 1), tokens.get(i + 2)) {
+            (Some((_, Token::Operator(_))), _) => true,
+            (Some((_, Token::Word(_))), to) | (Some((_, Token::Quoted(_))), to) => {
+                is_keyword(to.map(|(_, t)| t), &["TO"])
+            }
+            _ => false,
+        };
+        if !is_condition {
+            continue;
+        }
+
+        rewritten.push_str(&expr[copied..*start]);
+        rewritten.push_str(&timestamp_field(attribute));
+        copied = start + attribute.len();
+    }
+
+    rewritten.push_str(&expr[copied..]);
+    rewritten
+}
+
+/// Returns the span of the value starting at the token `i` and the index of the token following
+/// it. The words separated by commas without spaces are joined, so that `1.234,56` is a value.
+fn value_span(tokens: &[(usize, Token)], i: usize) -> Option<(usize, usize, usize)> {
+    let (start, mut end) = match tokens.get(i)? {
+        (start, Token::Word(word)) => (*start, start + word.len()),
+        (start, Token::Quoted(quoted)) => return Some((*start, start + quoted.len(), i + 1)),
+        _ => return None,
+    };
+
+    let mut next = i + 1;
+    while let (Some((comma, Token::Punctuation(','))), Some((word_start, Token::Word(word)))) =
+        (tokens.get(next), tokens.get(next + 1))
+    {
+        if *comma != end || *word_start != comma + 1 {
+            break;
+        }
+        end = word_start + word.len();
+        next += 2;
+    }
+
+    Some((start, end, next))
+}
+
+/// Converts the value of a condition on `attribute` written following the `locale` to its
+/// canonical form. The values of the comparisons and ranges must be numbers, the values of the
+/// equalities are only converted when they are unquoted numbers holding separators. The values of
+/// the date attributes are converted to timestamps.
+fn localize_value(
+    raw: &str,
+    attribute: &str,
+    comparison: bool,
+    date_attributes: &BTreeSet<String>,
+    locale: &FilterLocale,
+) -> Result<Option<String>> {
+    let quoted = raw.starts_with(|c| c == '"' || c == '\'');
+    let value = raw.trim_matches(|c| c == '"' || c == '\'');
+    let error = |expected| IndexError::InvalidLocalizedValue {
+        attribute: attribute.to_string(),
+        value: value.to_string(),
+        locale: locale.tag().to_string(),
+        expected,
+    };
+
+    if date_attributes.contains(attribute) {
+        if value.parse::<i64>().is_ok() {
+            return Ok(None);
+        }
+        return match locale.parse_date(value) {
+            Some(timestamp) => Ok(Some(timestamp.to_string())),
+            None => Err(error(locale.date_format())),
+        };
+    }
+
+    if comparison {
+        match locale.parse_number(value) {
+            Some(number) => Ok(Some(number)),
+            None => Err(error(locale.number_format())),
+        }
+    } else if !quoted && value.contains(|c: char| locale.is_number_separator(c)) {
+        Ok(locale.parse_number(value))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Rewrites the numbers and dates of the conditions written following the `locale` to their
+/// canonical form: `price > 1.234,56` becomes `price > 1234.56` for `fr`, and the dates of the
+/// `date_attributes`, such as `14/10/2021`, become timestamps.
+pub fn localize_conditions(
+    expr: &str,
+    locale: &FilterLocale,
+    date_attributes: &BTreeSet<String>,
+) -> Result<String> {
+    let tokens = tokenize(expr);
+    let mut rewritten = String::with_capacity(expr.len());
+    let mut copied = 0;
+    let mut rewrite = |start: usize, end: usize, value: Option<String>| {
+        if let Some(value) = value {
+            rewritten.push_str(&expr[copied..start]);
+            rewritten.push_str(&value);
+            copied = end;
+        }
+    };
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let attribute = match tokens[i].1 {
+            Token::Word(word) if !is_keyword(Some(&tokens[i].1), &["AND", "OR", "NOT", "TO"]) => {
+                word
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+
+        match tokens.get(i + 1).map(|(_, token)| token) {
+            Some(Token::Operator(operator)) => match value_span(&tokens, i + 2) {
+                Some((start, end, next)) => {
+                    let comparison = *operator != "=" && *operator != "!=";
+                    let value = localize_value(
+                        &expr[start..end],
+                        attribute,
+                        comparison,
+                        date_attributes,
+                        locale,
+                    )?;
+                    rewrite(start, end, value);
+                    i = next;
+                }
+                None => i += 2,
+            },
+            // `attribute low TO high`
+            Some(Token::Word(_)) | Some(Token::Quoted(_)) => {
+                let range = value_span(&tokens, i + 1).and_then(|(low_start, low_end, to)| {
+                    let (to_token, high) =
+                        (tokens.get(to).map(|(_, t)| t), value_span(&tokens, to + 1)?);
+                    if is_keyword(to_token, &["TO"]) {
+                        Some(((low_start, low_end), high))
+                    } else {
+                        None
+                    }
+                });
+                match range {
+                    Some(((low_start, low_end), (high_start, high_end, next))) => {
+                        for (start, end) in vec![(low_start, low_end), (high_start, high_end)] {
+                            let value = localize_value(
+                                &expr[start..end],
+                                attribute,
+                                true,
+                                date_attributes,
+                                locale,
+                            )?;
+                            rewrite(start, end, value);
+                        }
+                        i = next;
+                    }
+                    None => i += 1,
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    rewritten.push_str(&expr[copied..]);
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_expand_boolean_conditions() {
+        let booleans: BTreeSet<_> = vec!["is_published", "in_stock"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let expand = |expr| expand_boolean_conditions(expr, &booleans);
+
+        assert_eq!(expand("is_published"), "is_published = true");
+        assert_eq!(expand("NOT is_published"), "NOT is_published = true");
+        assert_eq!(
+            expand("(is_published OR price > 10) AND not is_published"),
+            "(is_published = true OR price > 10) AND not is_published = true"
+        );
+        assert_eq!(expand("is_published = false"), "is_published = false");
+        assert_eq!(expand("price 10 TO 20"), "price 10 TO 20");
+        assert_eq!(expand("title = is_published"), "title = is_published");
+        assert_eq!(
+            expand("title = \"is_published\""),
+            "title = \"is_published\""
+        );
+        assert_eq!(
+            expand("unknown AND in_stock"),
+            "unknown AND in_stock = true"
+        );
+        assert_eq!(expand("price AND in_stock"), "price AND in_stock = true");
+    }
+
+    #[test]
+    fn test_check_filter_syntax() {
+        let position = |expr| {
+            check_filter_syntax(expr)
+                .err()
+                .map(|error| (error.start, error.end))
+        };
+
+        assert_eq!(
+            position("(title = Apple OR price > 10) AND NOT brand = 'é'"),
+            None
+        );
+        assert_eq!(position("(title = Apple"), Some((0, 1)));
+        assert_eq!(position("title = Apple)"), Some((13, 14)));
+        assert_eq!(position("title = 'Apple"), Some((8, 14)));
+        assert_eq!(position("é = 2 AND price >"), Some((16, 17)));
+        assert_eq!(position("title = Apple AND"), Some((14, 17)));
+        assert_eq!(position("OR title = Apple"), Some((0, 2)));
+        assert_eq!(position("() AND title = Apple"), Some((0, 2)));
+        assert_eq!(position("title = and"), None);
+
+        let deep = format!("{}a = 1{}", "(".repeat(1000), ")".repeat(1000));
+        let error = check_filter_syntax(&deep).unwrap_err();
+        assert_eq!(error.start, MAX_FILTER_DEPTH);
+    }
+
+    #[test]
+    fn test_normalize_conditions() {
+        let normalized = vec![String::from("brand")].into_iter().collect();
+        let normalize = |expr| normalize_conditions(expr, &normalized, str::to_lowercase);
+
+        assert_eq!(
+            normalize("brand = Apple AND price > 10"),
+            "_normalized.brand = \"apple\" AND price > 10"
+        );
+        assert_eq!(
+            normalize("(brand != 'Big Apple') OR title = Apple"),
+            "(_normalized.brand != \"big apple\") OR title = Apple"
+        );
+        assert_eq!(normalize("brand > Apple"), "brand > Apple");
+    }
+
+    #[test]
+    fn test_redirect_date_conditions() {
+        let dates = vec![String::from("created_at")].into_iter().collect();
+        let redirect = |expr| redirect_date_conditions(expr, &dates);
+
+        assert_eq!(
+            redirect("created_at > 1634169600 AND title = created_at"),
+            "_timestamp.created_at > 1634169600 AND title = created_at"
+        );
+        assert_eq!(
+            redirect("NOT (created_at 0 TO 1634169600)"),
+            "NOT (_timestamp.created_at 0 TO 1634169600)"
+        );
+        assert_eq!(redirect("created_at"), "created_at");
+    }
+
+    #[test]
+    fn test_localize_conditions() {
+        let dates = vec![String::from("published_at")].into_iter().collect();
+        let fr = FilterLocale::parse("fr").unwrap();
+        let localize = |expr| localize_conditions(expr, &fr, &dates);
+
+        assert_eq!(
+            localize("price > 1.234,56 AND title = Apple").unwrap(),
+            "price > 1234.56 AND title = Apple"
+        );
+        assert_eq!(
+            localize("price 10,5 TO 1.000").unwrap(),
+            "price 10.5 TO 1000"
+        );
+        assert_eq!(localize("sku = \"1.234\"").unwrap(), "sku = \"1.234\"");
+        assert_eq!(
+            localize("published_at >= 14/10/2021").unwrap(),
+            "published_at >= 1634169600"
+        );
+        assert_eq!(
+            localize("_geoRadius(45.4, 2.1, 100) AND price < 12,5").unwrap(),
+            "_geoRadius(45.4, 2.1, 100) AND price < 12.5"
+        );
+        assert!(matches!(
+            localize("price > 1.5"),
+            Err(IndexError::InvalidLocalizedValue { .. })
+        ));
+        assert!(localize("published_at > tomorrow").is_err());
+    }
+}
@@ -0,0 +1,316 @@
+//! Analysis of the settings of an index against its documents and the searches made since the
+//! launch, reporting the settings that have no effect, conflict with each other or that no search
+//! uses.
+
+use std::collections::BTreeSet;
+
+use milli::update::Setting;
+use serde::Serialize;
+
+use super::collation::is_hidden_field;
+use super::error::Result;
+use super::updates::pattern_prefix;
+use super::usage::UsageReport;
+use super::{Checked, Index, Settings};
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsWarning {
+    /// The setting the warning is about, named as in the settings route.
+    pub setting: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsLint {
+    pub warnings: Vec<SettingsWarning>,
+}
+
+impl Index {
+    pub fn lint_settings(&self) -> Result<SettingsLint> {
+        let txn = self.read_txn()?;
+        let settings = self.settings_txn(&txn)?;
+        let field_distribution = self.field_distribution(&txn)?;
+
+        // The attributes can't be checked against the documents before the first addition.
+        let has_documents = self.number_of_documents(&txn)? > 0;
+        let is_present = |attribute: &str| {
+            !has_documents
+                || match pattern_prefix(attribute) {
+                    Ok(Some(prefix)) => field_distribution
+                        .iter()
+                        .any(|(name, count)| name.starts_with(prefix) && *count > 0),
+                    _ => field_distribution
+                        .get(attribute)
+                        .map_or(false, |count| *count > 0),
+                }
+        };
+
+        let mut warnings = lint(&settings, is_present);
+
+        // The usage is checked against the attributes the patterns matched.
+        let resolved = self.resolved_settings_txn(&txn)?;
+        let searchable = match resolved.searchable_attributes {
+            Setting::Set(searchable) => searchable,
+            _ => field_distribution
+                .keys()
+                .filter(|name| !is_hidden_field(name))
+                .cloned()
+                .collect(),
+        };
+        let sortable = match resolved.sortable_attributes {
+            Setting::Set(sortable) => sortable,
+            _ => BTreeSet::new(),
+        };
+        let ranking_rules = match resolved.ranking_rules {
+            Setting::Set(rules) => rules,
+            _ => Vec::new(),
+        };
+        warnings.extend(lint_usage(
+            &searchable,
+            &sortable,
+            &ranking_rules,
+            &self.usage.report(),
+        ));
+
+        Ok(SettingsLint { warnings })
+    }
+}
+
+/// The attribute a `attribute:asc` or `attribute:desc` ranking rule sorts on.
+fn ranking_rule_attribute(rule: &str) -> Option<&str> {
+    rule.rsplit_once(':')
+        .filter(|(_, order)| *order == "asc" || *order == "desc")
+        .map(|(attribute, _)| attribute)
+}
+
+fn lint(settings: &Settings<Checked>, is_present: impl Fn(&str) -> bool) -> Vec<SettingsWarning> {
+    let mut warnings = Vec::new();
+    let mut warn = |setting, message| warnings.push(SettingsWarning { setting, message });
+
+    let attribute_settings: [(&'static str, Option<Vec<&String>>); 4] = [
+        (
+            "searchableAttributes",
+            set_values(&settings.searchable_attributes),
+        ),
+        (
+            "displayedAttributes",
+            set_values(&settings.displayed_attributes),
+        ),
+        (
+            "filterableAttributes",
+            set_values(&settings.filterable_attributes),
+        ),
+        (
+            "sortableAttributes",
+            set_values(&settings.sortable_attributes),
+        ),
+    ];
+    for (setting, attributes) in attribute_settings {
+        for attribute in attributes.into_iter().flatten() {
+            if !is_present(attribute) {
+                warn(
+                    setting,
+                    format!(
+                        "The attribute `{}` is not present in any document.",
+                        attribute
+                    ),
+                );
+            }
+        }
+    }
+
+    if let Setting::Set(ref attribute) = settings.distinct_attribute {
+        if !is_present(attribute) {
+            warn(
+                "distinctAttribute",
+                format!(
+                    "The attribute `{}` is not present in any document, no document is deduplicated.",
+                    attribute
+                ),
+            );
+        }
+    }
+
+    if let Setting::Set(ref rules) = settings.ranking_rules {
+        for rule in rules {
+            if let Some(attribute) = ranking_rule_attribute(rule) {
+                if !is_present(attribute) {
+                    warn(
+                        "rankingRules",
+                        format!(
+                            "The ranking rule `{}` sorts on an attribute that is not present in any document.",
+                            rule
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    if let (Setting::Set(normalized), Setting::Set(filterable)) = (
+        &settings.normalized_filterable_attributes,
+        &settings.filterable_attributes,
+    ) {
+        for attribute in normalized.difference(filterable) {
+            warn(
+                "normalizedFilterableAttributes",
+                format!(
+                    "The attribute `{}` is not filterable, its normalization has no effect.",
+                    attribute
+                ),
+            );
+        }
+    }
+
+    if let (Setting::Set(stop_words), Setting::Set(synonyms)) =
+        (&settings.stop_words, &settings.synonyms)
+    {
+        let stop_words: BTreeSet<_> = stop_words.iter().map(|w| w.to_lowercase()).collect();
+        let mut expressions: BTreeSet<_> = synonyms.keys().collect();
+        expressions.extend(synonyms.values().flatten());
+        for expression in expressions {
+            let stop_word = expression
+                .split_whitespace()
+                .map(str::to_lowercase)
+                .find(|word| stop_words.contains(word));
+            if let Some(stop_word) = stop_word {
+                warn(
+                    "synonyms",
+                    format!(
+                        "The synonym `{}` contains the stop word `{}`, which is ignored by the search.",
+                        expression, stop_word
+                    ),
+                );
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Warns about the searchable attributes the words of no query were found in, and the sortable
+/// attributes no search sorted on, but for the ones the ranking rules sort on. Nothing is reported
+/// before the first search.
+fn lint_usage(
+    searchable: &[String],
+    sortable: &BTreeSet<String>,
+    ranking_rules: &[String],
+    usage: &UsageReport,
+) -> Vec<SettingsWarning> {
+    let mut warnings = Vec::new();
+
+    if usage.word_searches > 0 {
+        for attribute in searchable.iter().filter(|a| !usage.matched.contains(*a)) {
+            warnings.push(SettingsWarning {
+                setting: "searchableAttributes",
+                message: format!(
+                    "The attribute `{}` didn't match the words of any of the {} searches made since the launch.",
+                    attribute, usage.word_searches
+                ),
+            });
+        }
+    }
+
+    if usage.searches > 0 {
+        let ranked: BTreeSet<_> = ranking_rules
+            .iter()
+            .filter_map(|rule| ranking_rule_attribute(rule))
+            .collect();
+        for attribute in sortable {
+            if !usage.sorted.contains(attribute) && !ranked.contains(attribute.as_str()) {
+                warnings.push(SettingsWarning {
+                    setting: "sortableAttributes",
+                    message: format!(
+                        "The attribute `{}` wasn't sorted on by any of the {} searches made since the launch.",
+                        attribute, usage.searches
+                    ),
+                });
+            }
+        }
+    }
+
+    warnings
+}
+
+fn set_values<'a, T>(setting: &'a Setting<T>) -> Option<Vec<&'a String>>
+where
+    &'a T: IntoIterator<Item = &'a String>,
+{
+    match setting {
+        Setting::Set(values) => Some(values.into_iter().collect()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn lint_settings() {
+        let mut settings = Settings::cleared();
+        settings.searchable_attributes = Setting::Set(vec!["title".into(), "summary".into()]);
+        settings.filterable_attributes = Setting::Set(vec!["genre".into()].into_iter().collect());
+        settings.normalized_filterable_attributes =
+            Setting::Set(vec!["genre".into(), "author".into()].into_iter().collect());
+        settings.ranking_rules = Setting::Set(vec!["words".into(), "release_date:desc".into()]);
+        settings.stop_words = Setting::Set(vec!["the".into()].into_iter().collect());
+        let mut synonyms = BTreeMap::new();
+        synonyms.insert("The Who".to_string(), vec!["who".to_string()]);
+        settings.synonyms = Setting::Set(synonyms);
+
+        let present = ["title", "genre"];
+        let warnings = lint(&settings, |attribute| present.contains(&attribute));
+        let settings: Vec<_> = warnings.iter().map(|warning| warning.setting).collect();
+        assert_eq!(
+            settings,
+            [
+                "searchableAttributes",
+                "rankingRules",
+                "normalizedFilterableAttributes",
+                "synonyms"
+            ]
+        );
+        assert_eq!(
+            warnings[0].message,
+            "The attribute `summary` is not present in any document."
+        );
+    }
+
+    #[test]
+    fn lint_usage_of_settings() {
+        let searchable = ["title".to_string(), "summary".to_string()];
+        let sortable: BTreeSet<_> = vec!["price".into(), "year".into(), "_geo".into()]
+            .into_iter()
+            .collect();
+        let ranking_rules = ["words".to_string(), "year:desc".to_string()];
+
+        let usage = UsageReport::default();
+        assert!(lint_usage(&searchable, &sortable, &ranking_rules, &usage).is_empty());
+
+        let usage = UsageReport {
+            searches: 3,
+            word_searches: 2,
+            sorted: vec!["_geo".to_string()].into_iter().collect(),
+            matched: vec!["title".to_string()].into_iter().collect(),
+        };
+        let warnings = lint_usage(&searchable, &sortable, &ranking_rules, &usage);
+        assert_eq!(
+            warnings,
+            [
+                SettingsWarning {
+                    setting: "searchableAttributes",
+                    message: "The attribute `summary` didn't match the words of any of the 2 searches made since the launch.".to_string(),
+                },
+                SettingsWarning {
+                    setting: "sortableAttributes",
+                    message: "The attribute `price` wasn't sorted on by any of the 3 searches made since the launch.".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Parsing of the numbers and dates written in the filters following the conventions of a locale,
+//! such as `1.234,56` and `14/10/2021` for `fr`, given by the `locale` hint of a search.
+
+use chrono::{DateTime, NaiveDate};
+
+use super::error::{IndexError, Result};
+
+/// The order of the day, month and year of the dates written with separators.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DateOrder {
+    DayMonthYear,
+    MonthDayYear,
+    YearMonthDay,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterLocale {
+    tag: String,
+    decimal_separator: char,
+    group_separator: char,
+    date_order: DateOrder,
+}
+
+/// The languages writing the decimal part of the numbers after a dot, the others use a comma.
+const DOT_DECIMAL_LANGUAGES: &[&str] = &["en", "he", "hi", "ja", "ko", "th", "zh"];
+const COMMA_DECIMAL_LANGUAGES: &[&str] = &[
+    "cs", "da", "de", "el", "es", "fi", "fr", "it", "nb", "nl", "no", "pl", "pt", "ro", "ru", "sv",
+    "tr", "uk",
+];
+
+impl FilterLocale {
+    /// Parses a BCP 47 language tag, of which only the language and region are used.
+    pub fn parse(tag: &str) -> Result<Self> {
+        let lowercased = tag.to_lowercase();
+        let mut subtags = lowercased.split(|c| c == '-' || c == '_');
+        let language = subtags.next().unwrap_or_default();
+        let region = subtags.next();
+
+        let (decimal_separator, group_separator) = if DOT_DECIMAL_LANGUAGES.contains(&language) {
+            ('.', ',')
+        } else if COMMA_DECIMAL_LANGUAGES.contains(&language) {
+            (',', '.')
+        } else {
+            return Err(IndexError::UnsupportedLocale(tag.to_string()));
+        };
+
+        let date_order = match (language, region) {
+            ("en", None) | ("en", Some("us")) => DateOrder::MonthDayYear,
+            ("ja", _) | ("ko", _) | ("sv", _) | ("zh", _) => DateOrder::YearMonthDay,
+            _ => DateOrder::DayMonthYear,
+        };
+
+        Ok(Self {
+            tag: tag.to_string(),
+            decimal_separator,
+            group_separator,
+            date_order,
+        })
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Whether `c` is one of the separators of the numbers of the locale.
+    pub fn is_number_separator(&self, c: char) -> bool {
+        c == self.decimal_separator || c == self.group_separator
+    }
+
+    /// Converts a number written in the format of the locale to the canonical format, `1.234,56`
+    /// becoming `1234.56` for `fr`. The digits must be grouped by three.
+    pub fn parse_number(&self, number: &str) -> Option<String> {
+        let (sign, unsigned) = match number.strip_prefix('-') {
+            Some(unsigned) => ("-", unsigned),
+            None => ("", number),
+        };
+        let (integer, fraction) = match unsigned.split_once(self.decimal_separator) {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (unsigned, None),
+        };
+
+        let is_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        let groups: Vec<_> = integer.split(self.group_separator).collect();
+        let (first, others) = groups.split_first()?;
+        let valid_groups = is_digits(first)
+            && (others.is_empty() || first.len() <= 3)
+            && others
+                .iter()
+                .all(|group| group.len() == 3 && is_digits(group));
+        if !valid_groups || !fraction.map_or(true, is_digits) {
+            return None;
+        }
+
+        let mut canonical = format!("{}{}", sign, groups.concat());
+        if let Some(fraction) = fraction {
+            canonical.push('.');
+            canonical.push_str(fraction);
+        }
+        Some(canonical)
+    }
+
+    /// Converts a date to a timestamp in seconds, as the date attributes are stored. The RFC 3339
+    /// dates and the `YYYY-MM-DD` dates are accepted along the dates in the order of the locale.
+    pub fn parse_date(&self, date: &str) -> Option<i64> {
+        if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+            return Some(date.timestamp());
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            return Some(date.and_hms(0, 0, 0).timestamp());
+        }
+
+        let parts: Vec<_> = date.split(|c| c == '/' || c == '.' || c == '-').collect();
+        let (year, month, day) = match (self.date_order, parts.as_slice()) {
+            (DateOrder::DayMonthYear, [day, month, year]) => (year, month, day),
+            (DateOrder::MonthDayYear, [month, day, year]) => (year, month, day),
+            (DateOrder::YearMonthDay, [year, month, day]) => (year, month, day),
+            _ => return None,
+        };
+        if year.len() != 4 {
+            return None;
+        }
+        let date =
+            NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, day.parse().ok()?)?;
+        Some(date.and_hms(0, 0, 0).timestamp())
+    }
+
+    /// Describes the numbers accepted in the filters, for the error messages.
+    pub fn number_format(&self) -> String {
+        format!(
+            "a number such as `-1{}234{}5`, with the digits grouped by three",
+            self.group_separator, self.decimal_separator
+        )
+    }
+
+    /// Describes the dates accepted in the filters, for the error messages.
+    pub fn date_format(&self) -> String {
+        let local = match self.date_order {
+            DateOrder::DayMonthYear => "DD/MM/YYYY",
+            DateOrder::MonthDayYear => "MM/DD/YYYY",
+            DateOrder::YearMonthDay => "YYYY/MM/DD",
+        };
+        format!(
+            "a timestamp, an RFC 3339 date, a `YYYY-MM-DD` date or a `{}` date",
+            local
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn localized_numbers() {
+        let fr = FilterLocale::parse("fr-FR").unwrap();
+        assert_eq!(fr.parse_number("1.234,56").as_deref(), Some("1234.56"));
+        assert_eq!(fr.parse_number("-12,5").as_deref(), Some("-12.5"));
+        assert_eq!(fr.parse_number("1234").as_deref(), Some("1234"));
+        assert_eq!(fr.parse_number("1.5"), None);
+        assert_eq!(fr.parse_number("1,"), None);
+
+        let en = FilterLocale::parse("en").unwrap();
+        assert_eq!(en.parse_number("1,234.56").as_deref(), Some("1234.56"));
+        assert_eq!(en.parse_number("1.234,56"), None);
+
+        assert!(FilterLocale::parse("xx").is_err());
+    }
+
+    #[test]
+    fn localized_dates() {
+        let midnight = |y, m, d| NaiveDate::from_ymd(y, m, d).and_hms(0, 0, 0).timestamp();
+
+        let fr = FilterLocale::parse("fr").unwrap();
+        assert_eq!(fr.parse_date("14/10/2021"), Some(midnight(2021, 10, 14)));
+        assert_eq!(fr.parse_date("2021-10-14"), Some(midnight(2021, 10, 14)));
+        assert_eq!(fr.parse_date("10/14/2021"), None);
+
+        let us = FilterLocale::parse("en-US").unwrap();
+        assert_eq!(us.parse_date("10/14/2021"), Some(midnight(2021, 10, 14)));
+        assert_eq!(
+            us.parse_date("2021-10-14T00:00:00Z"),
+            Some(midnight(2021, 10, 14))
+        );
+
+        let ja = FilterLocale::parse("ja").unwrap();
+        assert_eq!(ja.parse_date("2021/10/14"), Some(midnight(2021, 10, 14)));
+    }
+}
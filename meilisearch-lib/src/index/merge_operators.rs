@@ -0,0 +1,263 @@
+//! The merge operators of the partial updates, evaluated against the stored documents when the
+//! update is processed, so that the counters and arrays of the documents can be updated without
+//! reading them first, and without the races of a read-modify-write.
+//!
+//! ```json
+//! {
+//!   "id": 1,
+//!   "views": { "$inc": 1 },
+//!   "tags": { "$append": ["sale"] },
+//!   "createdAt": { "$setIfMissing": 1635000000 }
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Number, Value};
+
+use super::error::{IndexError, Result};
+
+const APPEND: &str = "$append";
+const INC: &str = "$inc";
+const SET_IF_MISSING: &str = "$setIfMissing";
+
+/// The operator of `value`, an object whose only field is one of the operators.
+fn operator(value: &Value) -> Option<(&str, &Value)> {
+    match value {
+        Value::Object(object) if object.len() == 1 => {
+            let (name, operand) = object.iter().next()?;
+            [APPEND, INC, SET_IF_MISSING]
+                .contains(&name.as_str())
+                .then(|| (name.as_str(), operand))
+        }
+        _ => None,
+    }
+}
+
+pub(super) fn is_merge_operator(value: &Value) -> bool {
+    operator(value).is_some()
+}
+
+pub(super) fn has_merge_operators(document: &Map<String, Value>) -> bool {
+    document.values().any(is_merge_operator)
+}
+
+/// Replaces the operators of `document` by their result on the fields of `current`.
+pub(super) fn apply_merge_operators(
+    document: &mut Map<String, Value>,
+    current: Option<&Map<String, Value>>,
+) -> Result<()> {
+    for (field, value) in document.iter_mut() {
+        let (name, operand) = match operator(value) {
+            Some(operator) => operator,
+            None => continue,
+        };
+        let current = current
+            .and_then(|current| current.get(field))
+            .filter(|current| !current.is_null());
+
+        let merged = match name {
+            APPEND => append(current, operand),
+            INC => increment(current, operand),
+            _ => Ok(current.unwrap_or(operand).clone()),
+        }
+        .map_err(|message| IndexError::InvalidMergeOperator {
+            field: field.clone(),
+            message,
+        })?;
+        *value = merged;
+    }
+
+    Ok(())
+}
+
+fn append(current: Option<&Value>, operand: &Value) -> std::result::Result<Value, String> {
+    let mut values = match current {
+        Some(Value::Array(values)) => values.clone(),
+        Some(current) => {
+            return Err(format!(
+                "`{}` requires an array, but the current value is `{}`",
+                APPEND, current
+            ))
+        }
+        None => Vec::new(),
+    };
+    match operand {
+        Value::Array(operand) => values.extend(operand.iter().cloned()),
+        operand => values.push(operand.clone()),
+    }
+
+    Ok(Value::Array(values))
+}
+
+fn increment(current: Option<&Value>, operand: &Value) -> std::result::Result<Value, String> {
+    let operand = match operand {
+        Value::Number(operand) => operand,
+        operand => {
+            return Err(format!(
+                "`{}` requires a number, but `{}` was given",
+                INC, operand
+            ))
+        }
+    };
+    let current = match current {
+        Some(Value::Number(current)) => current,
+        Some(current) => {
+            return Err(format!(
+                "`{}` requires a number, but the current value is `{}`",
+                INC, current
+            ))
+        }
+        None => return Ok(Value::Number(operand.clone())),
+    };
+
+    let sum = match (current.as_i64(), operand.as_i64()) {
+        (Some(current), Some(operand)) => current.checked_add(operand).map(Number::from),
+        _ => current
+            .as_f64()
+            .zip(operand.as_f64())
+            .and_then(|(current, operand)| Number::from_f64(current + operand)),
+    };
+    sum.map(Value::Number)
+        .ok_or_else(|| format!("`{} + {}` overflows", current, operand))
+}
+
+/// A document of the batch merged with the ones before it with the same id.
+struct MergedDocument {
+    fields: Map<String, Value>,
+    /// Whether the stored document was merged in, it is only read once an operator needs it.
+    with_stored: bool,
+}
+
+/// The documents of a batch, merged by id. The operators of the next documents of the batch with
+/// the same id are evaluated against them instead of against the stored documents, which don't
+/// reflect the batch yet.
+#[derive(Default)]
+pub(super) struct MergedDocuments(HashMap<String, MergedDocument>);
+
+impl MergedDocuments {
+    /// Applies the operators of `document`, `stored` returning the stored document of an id.
+    pub fn merge(
+        &mut self,
+        document: &mut Map<String, Value>,
+        id: Option<&Value>,
+        stored: impl FnOnce(&str) -> Result<Option<Map<String, Value>>>,
+    ) -> Result<()> {
+        let id = match id {
+            Some(Value::String(id)) => id.clone(),
+            Some(id) => id.to_string(),
+            // The documents without id are rejected by the indexing.
+            None => return Ok(()),
+        };
+
+        if !has_merge_operators(document) {
+            let merged = self.0.entry(id).or_insert_with(|| MergedDocument {
+                fields: Map::new(),
+                with_stored: false,
+            });
+            merged.fields.extend(document.clone());
+            return Ok(());
+        }
+
+        let current = match self.0.remove(&id) {
+            Some(merged) if merged.with_stored => Some(merged.fields),
+            Some(merged) => {
+                let mut current = stored(&id)?.unwrap_or_default();
+                current.extend(merged.fields);
+                Some(current)
+            }
+            None => stored(&id)?,
+        };
+        apply_merge_operators(document, current.as_ref())?;
+
+        let mut fields = current.unwrap_or_default();
+        fields.extend(document.clone());
+        self.0.insert(
+            id,
+            MergedDocument {
+                fields,
+                with_stored: true,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn object(value: Value) -> Map<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn apply_operators() {
+        let current = object(json!({ "id": 1, "views": 41, "tags": ["new"], "createdAt": 10 }));
+        let mut document = object(json!({
+            "id": 1,
+            "views": { "$inc": 1 },
+            "score": { "$inc": 0.5 },
+            "tags": { "$append": ["sale", "summer"] },
+            "createdAt": { "$setIfMissing": 20 },
+            "updatedAt": { "$setIfMissing": 20 },
+            "meta": { "$unknown": 1 },
+        }));
+
+        apply_merge_operators(&mut document, Some(&current)).unwrap();
+        assert_eq!(
+            Value::Object(document),
+            json!({
+                "id": 1,
+                "views": 42,
+                "score": 0.5,
+                "tags": ["new", "sale", "summer"],
+                "createdAt": 10,
+                "updatedAt": 20,
+                "meta": { "$unknown": 1 },
+            })
+        );
+
+        let mut document = object(json!({ "id": 1, "tags": { "$inc": 1 } }));
+        assert!(matches!(
+            apply_merge_operators(&mut document, Some(&current)),
+            Err(IndexError::InvalidMergeOperator { .. })
+        ));
+    }
+
+    #[test]
+    fn merge_the_documents_of_a_batch() {
+        let mut merged = MergedDocuments::default();
+        let stored = |_: &str| Ok(Some(object(json!({ "id": 1, "views": 1 }))));
+
+        let mut first = object(json!({ "id": 1, "views": { "$inc": 1 } }));
+        merged.merge(&mut first, Some(&json!(1)), stored).unwrap();
+        assert_eq!(first["views"], 2);
+
+        let mut second = object(json!({ "id": 1, "views": { "$inc": 1 } }));
+        merged
+            .merge(&mut second, Some(&json!(1)), |_| panic!("already merged"))
+            .unwrap();
+        assert_eq!(second["views"], 3);
+    }
+
+    #[test]
+    fn merge_after_a_document_without_operators() {
+        let mut merged = MergedDocuments::default();
+        let stored = || |_: &str| Ok(Some(object(json!({ "id": 1, "views": 1, "tags": ["a"] }))));
+
+        let mut first = object(json!({ "id": 1, "views": 10 }));
+        merged.merge(&mut first, Some(&json!(1)), stored()).unwrap();
+
+        let mut second =
+            object(json!({ "id": 1, "views": { "$inc": 1 }, "tags": { "$append": "b" } }));
+        merged
+            .merge(&mut second, Some(&json!(1)), stored())
+            .unwrap();
+        assert_eq!(second["views"], 11);
+        assert_eq!(second["tags"], json!(["a", "b"]));
+    }
+}
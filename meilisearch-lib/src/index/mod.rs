@@ -1,4 +1,4 @@
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fs::create_dir_all;
 use std::marker::PhantomData;
 use std::ops::Deref;
@@ -8,36 +8,100 @@ use std::sync::Arc;
 use chrono::{DateTime, Utc};
 use heed::{EnvOpenOptions, RoTxn};
 use milli::update::Setting;
-use milli::{obkv_to_json, FieldDistribution, FieldId};
+use milli::{obkv_to_json, DocumentId, FieldDistribution, FieldId};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
+pub use aggregate::{AggregateQuery, AggregateResult};
+pub use browse::DocumentsQuery;
+pub use checkpoint::Checkpoint;
+pub use collation::SortCollation;
 use error::Result;
-pub use search::{default_crop_length, SearchQuery, SearchResult, DEFAULT_SEARCH_LIMIT};
+pub use export::{ExportFormat, ExportQuery, SearchExport};
+pub use facet_search::{FacetHit, FacetSearchQuery, FacetSearchResult};
+pub use lint::{SettingsLint, SettingsWarning};
+pub use nested::{FilterMode, FlattenStrategy};
+pub use query_debug::{QueryDebug, QueryWord};
+pub use quotas::DocumentQuotas;
+pub use ranking_score::{RankingRuleScore, RankingScoreDetails};
+pub use scroll::ScrollPosition;
+pub use search::{
+    CropUnit, Diversity, HtmlFormatting, MatchingStrategy, SearchQuery, SearchResult,
+    DEFAULT_CROP_LENGTH, DEFAULT_SEARCH_LIMIT,
+};
+pub use search_defaults::SearchDefaults;
+pub use typo_tolerance::{MinWordSizeForTypos, TypoTolerance};
+use updates::COMPOSITE_ID_SEPARATOR;
 pub use updates::{apply_settings_to_builder, Checked, Facets, Settings, Unchecked};
 use uuid::Uuid;
+pub use validation::{DocumentIssue, DocumentSchema, DocumentsValidation, DocumentsValidator};
 
+use crate::file_clone::compacted_copy;
 use crate::index_controller::update_file_store::UpdateFileStore;
+use crate::io_throttle::IoThrottle;
 use crate::EnvSizer;
 
+use self::collation::is_hidden_field;
 use self::error::IndexError;
 use self::update_handler::UpdateHandler;
+use self::usage::SearchUsage;
 
 pub mod error;
 pub mod update_handler;
 
+mod aggregate;
+mod browse;
+mod checkpoint;
+mod collation;
+mod custom_settings;
 mod dump;
+mod export;
+mod facet_cardinality;
+mod facet_search;
+mod facet_values;
+mod filter;
+mod lint;
+mod locale;
+mod merge_operators;
+mod nested;
+mod query_debug;
+mod quotas;
+mod ranking_score;
+mod scroll;
 mod search;
+mod search_after;
+mod search_defaults;
+mod search_on;
+mod typo_tolerance;
 mod updates;
+mod usage;
+mod validation;
 
 pub type Document = Map<String, Value>;
 
+/// The document field holding the id synthesized from the attributes of a composite primary key.
+pub const COMPOSITE_ID_FIELD: &str = "_compositeId";
+
+/// The primary key requested at the creation of an index.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum PrimaryKey {
+    Single(String),
+    /// The document ids are synthesized from the values of these attributes.
+    Composite(Vec<String>),
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct IndexMeta {
     created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// The date of the last successful documents update, `null` until the first one.
+    #[serde(default)]
+    pub last_indexed_at: Option<DateTime<Utc>>,
     pub primary_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub composite_primary_key: Option<Vec<String>>,
 }
 
 #[derive(Serialize, Debug)]
@@ -46,11 +110,36 @@ pub struct IndexStats {
     #[serde(skip)]
     pub size: u64,
     pub number_of_documents: u64,
+    /// The maximum number of documents of the index, if there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_documents: Option<u64>,
     /// Whether the current index is performing an update. It is initially `None` when the
     /// index returns it, since it is the `UpdateStore` that knows what index is currently indexing. It is
     /// later set to either true or false, we we retrieve the information from the `UpdateStore`
     pub is_indexing: Option<bool>,
     pub field_distribution: FieldDistribution,
+    /// The number of distinct values of each filterable attribute. The values are counted up to
+    /// 100 000 and the documents deletions leave the counts unchanged.
+    pub facet_cardinality: BTreeMap<String, u64>,
+    /// The last measure of the space wasted on disk, if the bloat check is enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bloat: Option<BloatReport>,
+}
+
+/// The ratio between the on disk and compacted sizes above which a compaction is recommended.
+const COMPACTION_RECOMMENDED_RATIO: f64 = 2.0;
+
+/// Compares the size of an index on disk with the size of its data once compacted, LMDB never
+/// giving the freed pages back to the file system.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BloatReport {
+    pub on_disk_size: u64,
+    pub compacted_size: u64,
+    pub bloat_ratio: f64,
+    /// Whether the index would gain to be compacted, which a snapshot import does.
+    pub compaction_recommended: bool,
+    pub measured_at: DateTime<Utc>,
 }
 
 impl IndexMeta {
@@ -62,11 +151,15 @@ impl IndexMeta {
     fn new_txn(index: &Index, txn: &heed::RoTxn) -> Result<Self> {
         let created_at = index.created_at(txn)?;
         let updated_at = index.updated_at(txn)?;
+        let last_indexed_at = custom_settings::last_indexed_at(index, txn)?;
         let primary_key = index.primary_key(txn)?.map(String::from);
+        let composite_primary_key = custom_settings::composite_primary_key(index, txn)?;
         Ok(Self {
             created_at,
             updated_at,
+            last_indexed_at,
             primary_key,
+            composite_primary_key,
         })
     }
 }
@@ -81,6 +174,8 @@ pub struct Index {
     update_file_store: Arc<UpdateFileStore>,
     #[derivative(Debug = "ignore")]
     update_handler: Arc<UpdateHandler>,
+    #[derivative(Debug = "ignore")]
+    usage: Arc<SearchUsage>,
 }
 
 impl Deref for Index {
@@ -108,6 +203,7 @@ impl Index {
             update_file_store,
             uuid,
             update_handler,
+            usage: Arc::new(SearchUsage::default()),
         })
     }
 
@@ -117,11 +213,22 @@ impl Index {
         Ok(IndexStats {
             size: self.size(),
             number_of_documents: self.number_of_documents(&rtxn)?,
+            max_documents: self.document_quotas().max_documents_per_index,
             is_indexing: None,
             field_distribution: self.field_distribution(&rtxn)?,
+            facet_cardinality: custom_settings::facet_cardinalities(self, &rtxn)?,
+            bloat: None,
         })
     }
 
+    pub fn document_quotas(&self) -> DocumentQuotas {
+        self.update_handler.document_quotas()
+    }
+
+    pub fn max_batch_size(&self) -> usize {
+        self.update_handler.max_batch_size()
+    }
+
     pub fn meta(&self) -> Result<IndexMeta> {
         IndexMeta::new(self)
     }
@@ -131,17 +238,34 @@ impl Index {
     }
 
     pub fn settings_txn(&self, txn: &RoTxn) -> Result<Settings<Checked>> {
+        let mut settings = self.resolved_settings_txn(txn)?;
+
+        // The attribute settings given with patterns are returned as they were given.
+        let patterns = custom_settings::attribute_patterns(self, txn)?;
+        if let Some(displayed) = patterns.displayed {
+            settings.displayed_attributes = Setting::Set(displayed);
+        }
+        if let Some(searchable) = patterns.searchable {
+            settings.searchable_attributes = Setting::Set(searchable);
+        }
+        if let Some(filterable) = patterns.filterable {
+            settings.filterable_attributes = Setting::Set(filterable);
+        }
+
+        Ok(settings)
+    }
+
+    /// The settings with the attribute patterns replaced by the attributes they matched.
+    pub(crate) fn resolved_settings_txn(&self, txn: &RoTxn) -> Result<Settings<Checked>> {
         let displayed_attributes = self
             .displayed_fields(txn)?
             .map(|fields| fields.into_iter().map(String::from).collect());
 
-        let searchable_attributes = self
-            .searchable_fields(txn)?
-            .map(|fields| fields.into_iter().map(String::from).collect());
+        let searchable_attributes = self.user_searchable_fields(txn)?;
 
-        let filterable_attributes = self.filterable_fields(txn)?.into_iter().collect();
+        let filterable_attributes = self.user_filterable_fields(txn)?;
 
-        let sortable_attributes = self.sortable_fields(txn)?.into_iter().collect();
+        let sortable_attributes = self.user_sortable_fields(txn)?;
 
         let criteria = self
             .criteria(txn)?
@@ -189,6 +313,21 @@ impl Index {
                 None => Setting::Reset,
             },
             synonyms: Setting::Set(synonyms),
+            date_attributes: Setting::Set(custom_settings::date_attributes(self, txn)?),
+            flatten_strategy: Setting::Set(custom_settings::flatten_strategy(self, txn)?),
+            sort_collation: Setting::Set(custom_settings::sort_collation(self, txn)?),
+            normalized_filterable_attributes: Setting::Set(
+                custom_settings::normalized_filterable_attributes(self, txn)?,
+            ),
+            searchable_numeric_attributes: Setting::Set(
+                custom_settings::searchable_numeric_attributes(self, txn)?,
+            ),
+            raw_attributes: Setting::Set(custom_settings::raw_attributes(self, txn)?),
+            checkpoint_before_settings_change: Setting::Set(
+                custom_settings::checkpoint_before_settings_change(self, txn)?,
+            ),
+            typo_tolerance: Setting::Set(custom_settings::typo_tolerance(self, txn)?),
+            search_defaults: Setting::Set(custom_settings::search_defaults(self, txn)?),
             _kind: PhantomData,
         })
     }
@@ -230,6 +369,7 @@ impl Index {
         let fields_to_display =
             self.fields_to_display(&txn, &attributes_to_retrieve, &fields_ids_map)?;
 
+        let doc_id = self.normalize_document_id(&txn, doc_id)?;
         let internal_id = self
             .external_documents_ids(&txn)?
             .get(doc_id.as_bytes())
@@ -247,10 +387,48 @@ impl Index {
         Ok(document)
     }
 
+    /// Document ids of indexes with a composite primary key can be given as the comma separated
+    /// values of the attributes of the key. They are converted to the synthesized id here.
+    fn normalize_document_id(&self, txn: &RoTxn, doc_id: String) -> Result<String> {
+        if doc_id.contains(',') && custom_settings::composite_primary_key(self, txn)?.is_some() {
+            Ok(doc_id
+                .split(',')
+                .collect::<Vec<_>>()
+                .join(COMPOSITE_ID_SEPARATOR))
+        } else {
+            Ok(doc_id)
+        }
+    }
+
     pub fn size(&self) -> u64 {
         self.env.size()
     }
 
+    /// Calls `f` with the document id and the value of each of the `fids` fields of the requested
+    /// documents. The documents are fetched by chunks to keep the memory usage low.
+    fn for_each_field_value(
+        &self,
+        txn: &RoTxn,
+        ids: impl IntoIterator<Item = DocumentId>,
+        fids: &[FieldId],
+        mut f: impl FnMut(DocumentId, FieldId, Value),
+    ) -> Result<()> {
+        const DOCUMENTS_CHUNK_SIZE: usize = 1000;
+
+        let ids: Vec<_> = ids.into_iter().collect();
+        for chunk in ids.chunks(DOCUMENTS_CHUNK_SIZE) {
+            for (id, obkv) in self.documents(txn, chunk.iter().copied())? {
+                for fid in fids {
+                    if let Some(value) = obkv.get(*fid) {
+                        f(id, *fid, serde_json::from_slice(value)?);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn fields_to_display<S: AsRef<str>>(
         &self,
         txn: &heed::RoTxn,
@@ -259,7 +437,11 @@ impl Index {
     ) -> Result<Vec<FieldId>> {
         let mut displayed_fields_ids = match self.displayed_fields_ids(txn)? {
             Some(ids) => ids.into_iter().collect::<Vec<_>>(),
-            None => fields_ids_map.iter().map(|(id, _)| id).collect(),
+            None => fields_ids_map
+                .iter()
+                .filter(|(_, name)| !is_hidden_field(name))
+                .map(|(id, _)| id)
+                .collect(),
         };
 
         let attributes_to_retrieve_ids = match attributes_to_retrieve {
@@ -274,14 +456,36 @@ impl Index {
         Ok(displayed_fields_ids)
     }
 
-    pub fn snapshot(&self, path: impl AsRef<Path>) -> Result<()> {
+    /// Measures the bloat of the index by making a compacted copy of it. This is as expensive as
+    /// a snapshot of the index, but does not block the updates.
+    pub fn bloat_report(&self) -> Result<BloatReport> {
+        let dir = tempfile::tempdir()?;
+        let dst = dir.path().join("data.mdb");
+        self.inner
+            .env
+            .copy_to_path(&dst, heed::CompactionOption::Enabled)?;
+
+        let on_disk_size = self.size();
+        let compacted_size = std::fs::metadata(&dst)?.len();
+        let bloat_ratio = on_disk_size as f64 / compacted_size.max(1) as f64;
+
+        Ok(BloatReport {
+            on_disk_size,
+            compacted_size,
+            bloat_ratio,
+            compaction_recommended: bloat_ratio >= COMPACTION_RECOMMENDED_RATIO,
+            measured_at: Utc::now(),
+        })
+    }
+
+    pub fn snapshot(&self, path: impl AsRef<Path>, throttle: Option<&IoThrottle>) -> Result<()> {
         let mut dst = path.as_ref().join(format!("indexes/{}/", self.uuid));
         create_dir_all(&dst)?;
         dst.push("data.mdb");
-        let _txn = self.write_txn()?;
-        self.inner
-            .env
-            .copy_to_path(dst, heed::CompactionOption::Enabled)?;
+        // No write can happen while the write transaction is held, the raw file of the database
+        // is consistent.
+        let txn = self.write_txn()?;
+        compacted_copy(&self.inner.env, &dst, txn, throttle)?;
         Ok(())
     }
 }
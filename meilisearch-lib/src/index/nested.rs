@@ -0,0 +1,416 @@
+//! Handling of the objects, and arrays of objects, nested in the documents: they can be
+//! flattened into dotted attributes at ingestion, and filtered so that the conditions on the
+//! attributes of an array of objects must all match the same element of the array.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use heed::RoTxn;
+use milli::DocumentId;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::error::{IndexError, Result};
+use super::filter::{is_keyword, tokenize, Token};
+use super::Index;
+
+/// How the objects nested in the documents are exposed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FlattenStrategy {
+    /// The nested objects are kept as is, only their top level attribute can be used.
+    None,
+    /// The attributes of the nested objects are also exposed under their dotted path, e.g.
+    /// `variants.color` holds the colors of all the objects of `variants`.
+    Flatten,
+}
+
+impl Default for FlattenStrategy {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+/// How the conditions of a filter on the attributes of an array of objects are matched.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FilterMode {
+    /// Each condition can be matched by a different element of the array.
+    Any,
+    /// The conditions on the attributes of the same array must all be matched by a single
+    /// element of this array.
+    SameElement,
+}
+
+impl Default for FilterMode {
+    fn default() -> Self {
+        Self::Any
+    }
+}
+
+/// Adds the dotted attributes of the objects nested in the document. The attributes already
+/// present in the document are never overwritten.
+pub fn flatten_document(document: &mut Map<String, Value>) {
+    let mut flattened = BTreeMap::new();
+    for (key, value) in document.iter() {
+        collect_nested_values(key, value, &mut flattened);
+    }
+
+    for (key, mut values) in flattened {
+        if !values.is_empty() && !document.contains_key(&key) {
+            let value = if values.len() == 1 {
+                values.remove(0)
+            } else {
+                Value::Array(values)
+            };
+            document.insert(key, value);
+        }
+    }
+}
+
+fn collect_nested_values(path: &str, value: &Value, flattened: &mut BTreeMap<String, Vec<Value>>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                let path = format!("{}.{}", path, key);
+                let values = flattened.entry(path.clone()).or_default();
+                match value {
+                    Value::Object(_) => (),
+                    Value::Array(array) => values.extend(
+                        array
+                            .iter()
+                            .filter(|value| !matches!(value, Value::Object(_) | Value::Array(_)))
+                            .cloned(),
+                    ),
+                    value => values.push(value.clone()),
+                }
+                collect_nested_values(&path, value, flattened);
+            }
+        }
+        Value::Array(array) => {
+            for value in array {
+                collect_nested_values(path, value, flattened);
+            }
+        }
+        _ => (),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterOrEqual,
+    Lower,
+    LowerOrEqual,
+}
+
+/// A condition of a filter on an attribute of the objects nested in `parent`, e.g.
+/// `variants.color = red`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementCondition {
+    parent: String,
+    attribute: String,
+    operator: Operator,
+    value: String,
+}
+
+impl ElementCondition {
+    /// Builds a condition from its tokens, returns `None` if the condition isn't about a nested
+    /// attribute or can't be checked on a single element.
+    fn from_tokens(tokens: &[Token]) -> Option<Self> {
+        let (attribute, operator, value) = match tokens {
+            [Token::Word(attribute), Token::Operator(operator), Token::Word(value)] => {
+                (*attribute, *operator, *value)
+            }
+            [Token::Word(attribute), Token::Operator(operator), Token::Quoted(value)] => (
+                *attribute,
+                *operator,
+                value.trim_matches(|c| c == '"' || c == '\''),
+            ),
+            _ => return None,
+        };
+        let (parent, attribute) = attribute.split_once('.')?;
+        let operator = match operator {
+            "=" => Operator::Equal,
+            "!=" => Operator::NotEqual,
+            ">" => Operator::Greater,
+            ">=" => Operator::GreaterOrEqual,
+            "<" => Operator::Lower,
+            "<=" => Operator::LowerOrEqual,
+            _ => return None,
+        };
+
+        Some(Self {
+            parent: parent.to_string(),
+            attribute: attribute.to_string(),
+            operator,
+            value: value.to_string(),
+        })
+    }
+
+    /// Whether the condition is satisfied by an element of the parent array.
+    fn matches_element(&self, element: &Value) -> bool {
+        let value = self
+            .attribute
+            .split('.')
+            .try_fold(element, |value, key| value.get(key));
+
+        match (value, self.operator) {
+            (None, Operator::NotEqual) => true,
+            (None, _) => false,
+            (Some(value), Operator::NotEqual) => !self.any_value(value, |o| o == Ordering::Equal),
+            (Some(value), Operator::Equal) => self.any_value(value, |o| o == Ordering::Equal),
+            (Some(value), Operator::Greater) => self.any_value(value, |o| o == Ordering::Greater),
+            (Some(value), Operator::GreaterOrEqual) => {
+                self.any_value(value, |o| o != Ordering::Less)
+            }
+            (Some(value), Operator::Lower) => self.any_value(value, |o| o == Ordering::Less),
+            (Some(value), Operator::LowerOrEqual) => {
+                self.any_value(value, |o| o != Ordering::Greater)
+            }
+        }
+    }
+
+    /// Whether one of the values, arrays being flattened, compares to the condition value as
+    /// expected by `f`.
+    fn any_value(&self, value: &Value, f: impl Fn(Ordering) -> bool + Copy) -> bool {
+        match value {
+            Value::Array(values) => values.iter().any(|value| self.any_value(value, f)),
+            value => self.compare(value).map_or(false, f),
+        }
+    }
+
+    fn compare(&self, value: &Value) -> Option<Ordering> {
+        let number = match value {
+            Value::Number(number) => number.as_f64(),
+            Value::String(string) => string.parse::<f64>().ok(),
+            _ => None,
+        };
+
+        match (number, self.value.parse::<f64>()) {
+            (Some(number), Ok(expected)) => number.partial_cmp(&expected),
+            // strings are only compared for equality, as the filters do.
+            _ => {
+                let string = match value {
+                    Value::String(string) => string.to_lowercase(),
+                    Value::Bool(boolean) => boolean.to_string(),
+                    _ => return None,
+                };
+                (string == self.value.to_lowercase()).then(|| Ordering::Equal)
+            }
+        }
+    }
+}
+
+/// Extracts the conditions on nested attributes that must be matched by the same element. Only
+/// the conditions that are ANDed at the top level of the filter are considered, using `OR` or
+/// `NOT` in a filter string is not supported in this mode.
+pub fn parse_element_conditions(filter: &Value) -> Result<Vec<ElementCondition>> {
+    // The expressions are split by the tokenizer of the filters handed to milli.
+    let parse_expression = |expr: &str| {
+        let tokens: Vec<_> = tokenize(expr).into_iter().map(|(_, token)| token).collect();
+        if tokens
+            .iter()
+            .any(|token| is_keyword(Some(token), &["OR", "NOT"]))
+        {
+            return Err(IndexError::UnsupportedSameElementFilter(expr.to_string()));
+        }
+
+        Ok(tokens
+            .split(|token| is_keyword(Some(token), &["AND"]))
+            .filter_map(|condition| {
+                let condition: Vec<_> = condition
+                    .iter()
+                    .copied()
+                    .filter(|token| {
+                        !matches!(token, Token::Punctuation('(') | Token::Punctuation(')'))
+                    })
+                    .collect();
+                ElementCondition::from_tokens(&condition)
+            })
+            .collect::<Vec<_>>())
+    };
+
+    let mut conditions = Vec::new();
+    match filter {
+        Value::String(expr) => conditions.extend(parse_expression(expr)?),
+        Value::Array(values) => {
+            for value in values {
+                match value {
+                    Value::String(expr) => conditions.extend(parse_expression(expr)?),
+                    // An OR with a single condition is just this condition.
+                    Value::Array(ors) if ors.len() == 1 => {
+                        if let Some(Value::String(expr)) = ors.first() {
+                            conditions.extend(parse_expression(expr)?);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+        _ => (),
+    }
+
+    Ok(conditions)
+}
+
+impl Index {
+    /// Returns the documents that have no element matching all the `conditions` in one of the
+    /// arrays of objects the conditions are about.
+    pub(super) fn same_element_rejects(
+        &self,
+        rtxn: &RoTxn,
+        ids: impl IntoIterator<Item = DocumentId>,
+        conditions: &[ElementCondition],
+    ) -> Result<Vec<DocumentId>> {
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+
+        let mut groups: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for condition in conditions {
+            if let Some(fid) = fields_ids_map.id(&condition.parent) {
+                groups.entry(fid).or_default().push(condition);
+            }
+        }
+        // A single condition is always matched by a single element, milli already checked it.
+        groups.retain(|_, conditions| conditions.len() > 1);
+
+        let mut rejected = Vec::new();
+        if groups.is_empty() {
+            return Ok(rejected);
+        }
+
+        let fids: Vec<_> = groups.keys().copied().collect();
+        self.for_each_field_value(rtxn, ids, &fids, |id, fid, value| {
+            let conditions = &groups[&fid];
+            let elements = match value {
+                Value::Array(elements) => elements,
+                element => vec![element],
+            };
+
+            let matched = elements.iter().any(|element| {
+                conditions
+                    .iter()
+                    .all(|condition| condition.matches_element(element))
+            });
+
+            if !matched && rejected.last() != Some(&id) {
+                rejected.push(id);
+            }
+        })?;
+
+        Ok(rejected)
+    }
+}
+
+/// Returns the requested page of the ranked `documents_ids` kept by `keep`, the documents ids
+/// being the first `offset + limit` ones returned by the search. The search is only executed
+/// again, fetching more and more ranked documents, up to the `matched` ones, when not enough of
+/// them are kept: `keep` is called again on the documents fetched before.
+pub(super) fn ranked_documents_ids(
+    search: &mut milli::Search,
+    mut documents_ids: Vec<DocumentId>,
+    offset: usize,
+    limit: usize,
+    matched: usize,
+    mut keep: impl FnMut(DocumentId) -> Result<bool>,
+) -> Result<Vec<DocumentId>> {
+    let wanted = offset.saturating_add(limit);
+    let mut fetched = wanted.max(1);
+
+    loop {
+        let exhausted = documents_ids.len() < fetched || fetched >= matched;
+        let mut kept = Vec::new();
+        for id in documents_ids {
+            if keep(id)? {
+                kept.push(id);
+            }
+        }
+        if kept.len() >= wanted || exhausted {
+            return Ok(kept.into_iter().skip(offset).take(limit).collect());
+        }
+
+        fetched = fetched.saturating_mul(2).min(matched);
+        search.offset(0);
+        search.limit(fetched);
+        documents_ids = search.execute()?.documents_ids;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_flatten_document() {
+        let mut document = json!({
+            "id": 1,
+            "variants": [
+                { "color": "red", "size": "M", "tags": ["a", "b"] },
+                { "color": "blue", "size": "L" },
+            ],
+            "seller": { "address": { "city": "Paris" } },
+            "seller.name": "kept",
+        });
+        flatten_document(document.as_object_mut().unwrap());
+
+        assert_eq!(document["variants.color"], json!(["red", "blue"]));
+        assert_eq!(document["variants.size"], json!(["M", "L"]));
+        assert_eq!(document["variants.tags"], json!(["a", "b"]));
+        assert_eq!(document["seller.address.city"], json!("Paris"));
+        assert_eq!(document["seller.name"], json!("kept"));
+        assert!(document.get("seller.address").is_none());
+        assert_eq!(document["variants"][0]["color"], json!("red"));
+    }
+
+    #[test]
+    fn test_parse_element_conditions() {
+        let conditions = parse_element_conditions(&json!(
+            "variants.color = red AND variants.size = 'M' AND id > 2"
+        ))
+        .unwrap();
+        assert_eq!(
+            conditions,
+            vec![
+                ElementCondition {
+                    parent: String::from("variants"),
+                    attribute: String::from("color"),
+                    operator: Operator::Equal,
+                    value: String::from("red"),
+                },
+                ElementCondition {
+                    parent: String::from("variants"),
+                    attribute: String::from("size"),
+                    operator: Operator::Equal,
+                    value: String::from("M"),
+                },
+            ]
+        );
+
+        let conditions =
+            parse_element_conditions(&json!(["variants.price >= 10", ["variants.size = M"]]))
+                .unwrap();
+        assert_eq!(conditions.len(), 2);
+
+        assert!(
+            parse_element_conditions(&json!("variants.color = red OR variants.size = M")).is_err()
+        );
+    }
+
+    #[test]
+    fn test_matches_element() {
+        let element = json!({ "color": "Red", "price": 12, "sizes": ["M", "L"] });
+        let condition = |expr| parse_element_conditions(&json!(expr)).unwrap().remove(0);
+
+        assert!(condition("variants.color = red").matches_element(&element));
+        assert!(!condition("variants.color != red").matches_element(&element));
+        assert!(condition("variants.price >= 12").matches_element(&element));
+        assert!(!condition("variants.price < 12").matches_element(&element));
+        assert!(condition("variants.sizes = L").matches_element(&element));
+        assert!(condition("variants.missing != L").matches_element(&element));
+        assert!(!condition("variants.missing = L").matches_element(&element));
+    }
+}
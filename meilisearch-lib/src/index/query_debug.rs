@@ -0,0 +1,159 @@
+//! Explanation of how a query is interpreted, returned by the searches performed with
+//! `debug: true`. milli doesn't expose its query tree, the explanation is rebuilt from the same
+//! rules: the tokenization, the number of typos allowed by word length, the prefix search of the
+//! last word, the synonyms, and the optional words dropped from the end of the query when there
+//! are not enough results.
+
+use std::collections::BTreeMap;
+
+use fst::{IntoStreamer, Streamer};
+use heed::RoTxn;
+use levenshtein_automata::{LevenshteinAutomatonBuilder, DFA};
+use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
+use serde::Serialize;
+
+use super::error::Result;
+use super::Index;
+
+/// The maximum number of words of the index reported for a query word.
+const MAX_REPORTED_DERIVATIONS: usize = 20;
+/// The maximum number of words of the expressions looked up in the synonyms.
+const MAX_SYNONYM_NGRAM: usize = 3;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryDebug {
+    pub words: Vec<QueryWord>,
+    /// The synonyms of the words and of the expressions of consecutive words of the query.
+    pub synonyms: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryWord {
+    /// The word as written in the query.
+    pub original: String,
+    /// The word once normalized by the tokenizer, as it is looked up in the index.
+    pub normalized: String,
+    pub is_stop_word: bool,
+    /// Whether the word matches the words of the index it is a prefix of, which is the case of
+    /// the last word when the query doesn't end with a separator.
+    pub is_prefix: bool,
+    pub max_typos: u8,
+    /// The words of the index matched by this word, with typos or as a prefix.
+    pub derivations: Vec<String>,
+    /// Whether the documents that don't contain this word can be returned when there are not
+    /// enough results, the words being dropped from the last one.
+    pub optional: bool,
+}
+
+/// The number of typos milli allows for a word of this length.
+pub(super) fn max_typos(word: &str) -> u8 {
+    match word.chars().count() {
+        0..=4 => 0,
+        5..=8 => 1,
+        _ => 2,
+    }
+}
+
+impl Index {
+    pub(super) fn query_debug(&self, txn: &RoTxn, query: &str) -> Result<QueryDebug> {
+        let stop_words = match self.stop_words(txn)? {
+            Some(stop_words) => stop_words.map_data(|bytes| bytes.to_vec())?,
+            None => fst::Set::default(),
+        };
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+
+        let analyzed = analyzer.analyze(query);
+        let tokens: Vec<_> = analyzed
+            .tokens()
+            .filter(|token| token.is_word() || token.is_stopword())
+            .collect();
+
+        let words_fst = self.words_fst(txn)?;
+        let builders: Vec<_> = (0..=2)
+            .map(|typos| LevenshteinAutomatonBuilder::new(typos, true))
+            .collect();
+
+        let mut words = Vec::new();
+        for (i, token) in tokens.iter().enumerate() {
+            let normalized = token.text().to_string();
+            let is_last = i + 1 == tokens.len();
+            let is_prefix = is_last && token.byte_end == query.len();
+            let max_typos = max_typos(&normalized);
+
+            let builder = &builders[max_typos as usize];
+            let dfa = if is_prefix {
+                builder.build_prefix_dfa(&normalized)
+            } else {
+                builder.build_dfa(&normalized)
+            };
+            let derivations = derivations(&words_fst, &dfa, &normalized);
+
+            words.push(QueryWord {
+                original: query[token.byte_start..token.byte_end].to_string(),
+                normalized,
+                is_stop_word: token.is_stopword(),
+                is_prefix,
+                max_typos,
+                derivations,
+                // milli always keeps the first word.
+                optional: i > 0,
+            });
+        }
+
+        let index_synonyms = self.synonyms(txn)?;
+        let mut synonyms = BTreeMap::new();
+        for ngram in 1..=MAX_SYNONYM_NGRAM {
+            for window in words.windows(ngram) {
+                let expression: Vec<_> =
+                    window.iter().map(|word| word.normalized.clone()).collect();
+                if let Some(values) = index_synonyms.get(&expression) {
+                    let values = values.iter().map(|value| value.join(" ")).collect();
+                    synonyms.insert(expression.join(" "), values);
+                }
+            }
+        }
+
+        Ok(QueryDebug { words, synonyms })
+    }
+}
+
+/// Returns the words of the index matched by `dfa`, other than `word` itself.
+fn derivations<A: AsRef<[u8]>>(words_fst: &fst::Set<A>, dfa: &DFA, word: &str) -> Vec<String> {
+    let mut derivations = Vec::new();
+    let mut stream = words_fst.search(dfa).into_stream();
+    while let Some(derivation) = stream.next() {
+        if derivations.len() == MAX_REPORTED_DERIVATIONS {
+            break;
+        }
+        match std::str::from_utf8(derivation) {
+            Ok(derivation) if derivation != word => derivations.push(derivation.to_string()),
+            _ => (),
+        }
+    }
+    derivations
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn derivations_with_typos() {
+        let words_fst = fst::Set::from_iter(&["hello", "help", "helpful", "world"]).unwrap();
+        let builder = LevenshteinAutomatonBuilder::new(1, true);
+
+        let dfa = builder.build_dfa("hellp");
+        assert_eq!(derivations(&words_fst, &dfa, "hellp"), ["hello", "help"]);
+
+        let dfa = builder.build_prefix_dfa("help");
+        assert_eq!(derivations(&words_fst, &dfa, "help"), ["hello", "helpful"]);
+
+        assert_eq!(max_typos("cat"), 0);
+        assert_eq!(max_typos("kitten"), 1);
+        assert_eq!(max_typos("butterflies"), 2);
+    }
+}
@@ -0,0 +1,70 @@
+//! The limits on the number of documents of an index and of the whole instance, checked when the
+//! documents are added so that a tenant can't fill the disk shared with the others.
+
+use super::error::{IndexError, Result};
+use crate::options::IndexerOpts;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DocumentQuotas {
+    /// The maximum number of documents of an index.
+    pub max_documents_per_index: Option<u64>,
+    /// The maximum number of documents of all the indexes together.
+    pub max_documents: Option<u64>,
+}
+
+impl DocumentQuotas {
+    pub fn new(opt: &IndexerOpts) -> Self {
+        Self {
+            max_documents_per_index: opt.max_documents_per_index,
+            max_documents: opt.max_documents,
+        }
+    }
+
+    /// Checks the number of documents of an index once an addition is applied, `other_documents`
+    /// being the number of documents of the other indexes.
+    pub fn check(&self, documents: u64, other_documents: u64) -> Result<()> {
+        if let Some(max) = self.max_documents_per_index {
+            if documents > max {
+                return Err(IndexError::IndexDocumentQuotaExceeded { documents, max });
+            }
+        }
+        if let Some(max) = self.max_documents {
+            let documents = documents.saturating_add(other_documents);
+            if documents > max {
+                return Err(IndexError::InstanceDocumentQuotaExceeded { documents, max });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn document_quotas() {
+        let quotas = DocumentQuotas::default();
+        assert!(quotas.check(u64::MAX, u64::MAX).is_ok());
+
+        let quotas = DocumentQuotas {
+            max_documents_per_index: Some(10),
+            max_documents: Some(15),
+        };
+        assert!(quotas.check(10, 5).is_ok());
+        assert!(matches!(
+            quotas.check(11, 0),
+            Err(IndexError::IndexDocumentQuotaExceeded {
+                documents: 11,
+                max: 10
+            })
+        ));
+        assert!(matches!(
+            quotas.check(10, 6),
+            Err(IndexError::InstanceDocumentQuotaExceeded {
+                documents: 16,
+                max: 15
+            })
+        ));
+    }
+}
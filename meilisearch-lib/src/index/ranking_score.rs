@@ -0,0 +1,369 @@
+//! The `_rankingScore` of the hits of the searches made with `showRankingScore: true`, from 0 to
+//! 1. milli doesn't expose the scores of its ranking rules, they are rebuilt from the matches of
+//! the query words in each hit, following the same rules as the explanation of `debug: true`:
+//! the number of words matched, their typos, their proximity, the attribute they are found in
+//! and whether they match exactly. The global score combines the rules in their order, each rule
+//! only breaking the ties of the ones before it.
+//!
+//! The scores are an estimate: they are computed from the stored documents, not from the
+//! internal state of milli's ranking rules, so two hits can be returned in an order their scores
+//! don't follow. The hits are never reordered by their score.
+
+use std::collections::BTreeMap;
+
+use heed::RoTxn;
+use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder, DFA};
+use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
+use milli::{FieldId, FieldsIdsMap};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::collation::is_hidden_field;
+use super::error::Result;
+use super::query_debug::max_typos;
+use super::Index;
+
+/// The proximity milli gives to two words too far from each other, or in different attributes.
+const MAX_PROXIMITY: u32 = 8;
+/// The gap between the positions of two values of an array, as between two sentences.
+const ARRAY_POSITION_GAP: u32 = 8;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RankingRuleScore {
+    /// The position of the rule in the ranking rules.
+    pub order: usize,
+    /// The score of the hit for this rule, from 0 to 1. The sort rules don't score the hits,
+    /// they order them by `value`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+}
+
+pub type RankingScoreDetails = BTreeMap<String, RankingRuleScore>;
+
+enum Rule {
+    Words,
+    Typo,
+    Proximity,
+    Attribute,
+    Exactness,
+    /// The `sort` rule, ordering the hits by the sort rules of the query.
+    Sort,
+    /// An `attribute:asc` or `attribute:desc` rule.
+    Custom(String),
+}
+
+struct QueryWord {
+    text: String,
+    max_typos: u8,
+    dfa: DFA,
+}
+
+/// How a word of the query matches a hit.
+#[derive(Default, Clone)]
+struct WordMatch {
+    typos: u8,
+    /// The rank of the first searchable attribute holding the word.
+    attribute: usize,
+    exact: bool,
+    /// The attribute and position of each occurrence of the word.
+    positions: Vec<(usize, u32)>,
+}
+
+pub struct RankingScorer {
+    rules: Vec<(String, Rule)>,
+    words: Vec<QueryWord>,
+    /// The searchable attributes, in their order of importance.
+    searchable: Vec<FieldId>,
+    sort: Vec<String>,
+}
+
+impl RankingScorer {
+    /// The digits of a rule: its rank, and the number of ranks, the best rank being 0.
+    fn ranks(&self, rule: &Rule, matches: &[Option<WordMatch>]) -> Option<(u32, u32)> {
+        let n = self.words.len() as u32;
+        let matched = || matches.iter().flatten();
+        match rule {
+            Rule::Words => Some((n - matched().count() as u32, n)),
+            Rule::Typo => {
+                let max: u32 = self.words.iter().map(|word| word.max_typos as u32).sum();
+                let typos = matches
+                    .iter()
+                    .zip(&self.words)
+                    .map(|(m, word)| m.as_ref().map_or(word.max_typos, |m| m.typos) as u32)
+                    .sum();
+                Some((typos, max))
+            }
+            Rule::Proximity => {
+                let pairs = n.saturating_sub(1);
+                let cost: u32 = matches
+                    .windows(2)
+                    .map(|pair| match pair {
+                        [Some(a), Some(b)] => proximity(&a.positions, &b.positions) - 1,
+                        _ => MAX_PROXIMITY - 1,
+                    })
+                    .sum();
+                Some((cost, pairs * (MAX_PROXIMITY - 1)))
+            }
+            Rule::Attribute => {
+                let max = self.searchable.len().saturating_sub(1) as u32;
+                let best = matched().map(|m| m.attribute as u32).min().unwrap_or(max);
+                Some((best.min(max), max))
+            }
+            Rule::Exactness => Some((n - matched().filter(|m| m.exact).count() as u32, n)),
+            Rule::Sort | Rule::Custom(_) => None,
+        }
+    }
+
+    /// The score of a hit, and the details of each ranking rule.
+    pub fn score<A: AsRef<[u8]>>(
+        &self,
+        fields_ids_map: &FieldsIdsMap,
+        obkv: obkv::KvReaderU16,
+        analyzer: &Analyzer<A>,
+    ) -> Result<(f64, RankingScoreDetails)> {
+        let mut matches: Vec<Option<WordMatch>> = vec![None; self.words.len()];
+        if !self.words.is_empty() {
+            for (attribute, fid) in self.searchable.iter().enumerate() {
+                let value = match obkv.get(*fid) {
+                    Some(bytes) => serde_json::from_slice(bytes)?,
+                    None => continue,
+                };
+                let mut position = 0;
+                for_each_word(&value, analyzer, &mut position, &mut |word, position| {
+                    for (query_word, word_match) in self.words.iter().zip(matches.iter_mut()) {
+                        let typos = match query_word.dfa.eval(word) {
+                            Distance::Exact(typos) => typos,
+                            Distance::AtLeast(_) => continue,
+                        };
+                        let word_match = word_match.get_or_insert_with(|| WordMatch {
+                            typos,
+                            attribute,
+                            ..WordMatch::default()
+                        });
+                        word_match.typos = word_match.typos.min(typos);
+                        word_match.exact |= word == query_word.text;
+                        word_match.positions.push((attribute, position));
+                    }
+                });
+            }
+        }
+
+        let value_of = |attribute: &str| -> Result<Option<Value>> {
+            match fields_ids_map.id(attribute).and_then(|fid| obkv.get(fid)) {
+                Some(bytes) => Ok(Some(serde_json::from_slice(bytes)?)),
+                None => Ok(None),
+            }
+        };
+
+        let mut details = RankingScoreDetails::new();
+        let (mut value, mut max) = (0f64, 0f64);
+        for (order, (name, rule)) in self.rules.iter().enumerate() {
+            match rule {
+                Rule::Sort => {
+                    for sort in &self.sort {
+                        let attribute = sort.rsplit_once(':').map_or(sort.as_str(), |(a, _)| a);
+                        let value = if attribute.starts_with("_geoPoint(") {
+                            None
+                        } else {
+                            value_of(attribute)?
+                        };
+                        let score = RankingRuleScore {
+                            order,
+                            score: None,
+                            value,
+                        };
+                        details.insert(sort.clone(), score);
+                    }
+                }
+                Rule::Custom(attribute) => {
+                    let score = RankingRuleScore {
+                        order,
+                        score: None,
+                        value: value_of(attribute)?,
+                    };
+                    details.insert(name.clone(), score);
+                }
+                rule => {
+                    let (rank, ranks) = match self.ranks(rule, &matches) {
+                        Some((_, 0)) | None => (0, 0),
+                        Some(ranks) => ranks,
+                    };
+                    // The ranks of the rules are the digits of a mixed radix number.
+                    let base = ranks as f64 + 1.;
+                    value = value * base + (ranks - rank) as f64;
+                    max = max * base + ranks as f64;
+                    let score = if ranks == 0 {
+                        1.
+                    } else {
+                        (ranks - rank) as f64 / ranks as f64
+                    };
+                    let score = RankingRuleScore {
+                        order,
+                        score: Some(score),
+                        value: None,
+                    };
+                    details.insert(name.clone(), score);
+                }
+            }
+        }
+
+        let score = if max == 0. { 1. } else { value / max };
+        Ok((score, details))
+    }
+}
+
+/// The proximity of two words from their positions, as milli computes it: the distance between
+/// them, one more when the second comes first.
+fn proximity(a: &[(usize, u32)], b: &[(usize, u32)]) -> u32 {
+    let mut best = MAX_PROXIMITY;
+    for (attribute_a, position_a) in a {
+        for (attribute_b, position_b) in b {
+            if attribute_a != attribute_b {
+                continue;
+            }
+            let distance = if position_b > position_a {
+                position_b - position_a
+            } else {
+                position_a - position_b + 1
+            };
+            best = best.min(distance);
+        }
+    }
+    best.max(1)
+}
+
+pub(super) fn for_each_word<A: AsRef<[u8]>>(
+    value: &Value,
+    analyzer: &Analyzer<A>,
+    position: &mut u32,
+    f: &mut impl FnMut(&str, u32),
+) {
+    match value {
+        Value::String(string) => {
+            let analyzed = analyzer.analyze(string);
+            for token in analyzed.tokens().filter(|token| token.is_word()) {
+                f(token.text(), *position);
+                *position += 1;
+            }
+        }
+        Value::Number(number) => {
+            f(&number.to_string(), *position);
+            *position += 1;
+        }
+        Value::Array(values) => {
+            for value in values {
+                for_each_word(value, analyzer, position, f);
+                *position += ARRAY_POSITION_GAP;
+            }
+        }
+        Value::Object(object) => {
+            for value in object.values() {
+                for_each_word(value, analyzer, position, f);
+                *position += ARRAY_POSITION_GAP;
+            }
+        }
+        Value::Bool(_) | Value::Null => (),
+    }
+}
+
+impl Index {
+    pub(super) fn ranking_scorer(
+        &self,
+        txn: &RoTxn,
+        query: Option<&str>,
+        sort: Option<&[String]>,
+    ) -> Result<RankingScorer> {
+        let rules = self
+            .criteria(txn)?
+            .into_iter()
+            .map(|criterion| {
+                let name = criterion.to_string();
+                let rule = match name.as_str() {
+                    "words" => Rule::Words,
+                    "typo" => Rule::Typo,
+                    "proximity" => Rule::Proximity,
+                    "attribute" => Rule::Attribute,
+                    "exactness" => Rule::Exactness,
+                    "sort" => Rule::Sort,
+                    custom => {
+                        let attribute = custom.rsplit_once(':').map_or(custom, |(a, _)| a);
+                        Rule::Custom(attribute.to_string())
+                    }
+                };
+                (name, rule)
+            })
+            .collect();
+
+        let stop_words = match self.stop_words(txn)? {
+            Some(stop_words) => stop_words.map_data(|bytes| bytes.to_vec())?,
+            None => fst::Set::default(),
+        };
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+
+        let query = query.unwrap_or_default();
+        let analyzed = analyzer.analyze(query);
+        let tokens: Vec<_> = analyzed
+            .tokens()
+            .filter(|token| token.is_word() || token.is_stopword())
+            .collect();
+        let words = tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| token.is_word())
+            .map(|(i, token)| {
+                let text = token.text().to_string();
+                let max_typos = max_typos(&text);
+                let builder = LevenshteinAutomatonBuilder::new(max_typos, true);
+                let dfa = if i + 1 == tokens.len() && token.byte_end == query.len() {
+                    builder.build_prefix_dfa(&text)
+                } else {
+                    builder.build_dfa(&text)
+                };
+                QueryWord {
+                    text,
+                    max_typos,
+                    dfa,
+                }
+            })
+            .collect();
+
+        let fields_ids_map = self.fields_ids_map(txn)?;
+        let searchable = match self.searchable_fields(txn)? {
+            Some(fields) => fields
+                .into_iter()
+                .filter_map(|name| fields_ids_map.id(name))
+                .collect(),
+            None => fields_ids_map
+                .iter()
+                .filter(|(_, name)| !is_hidden_field(name))
+                .map(|(fid, _)| fid)
+                .collect(),
+        };
+
+        Ok(RankingScorer {
+            rules,
+            words,
+            searchable,
+            sort: sort.map(<[String]>::to_vec).unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn proximity_of_positions() {
+        assert_eq!(proximity(&[(0, 1)], &[(0, 2)]), 1);
+        assert_eq!(proximity(&[(0, 2)], &[(0, 1)]), 2);
+        assert_eq!(proximity(&[(0, 1)], &[(0, 30)]), MAX_PROXIMITY);
+        assert_eq!(proximity(&[(0, 1)], &[(1, 2)]), MAX_PROXIMITY);
+        assert_eq!(proximity(&[(0, 1), (1, 5)], &[(1, 7), (0, 4)]), 2);
+    }
+}
@@ -0,0 +1,103 @@
+//! The position of a scroll through all the documents matching a search, whose pages follow the
+//! snapshot of the candidates taken by its first search, in the order of the documents in the
+//! index, so that the documents added or updated meanwhile don't shift the pages. The words the
+//! query matched are kept along, to format the hits of the next pages without searching again.
+
+use std::fmt;
+use std::sync::Arc;
+
+use milli::{DocumentId, MatchingWords};
+
+#[derive(Clone)]
+pub struct ScrollPosition {
+    ids: Arc<Vec<DocumentId>>,
+    matching_words: Arc<MatchingWords>,
+    next: usize,
+}
+
+impl ScrollPosition {
+    pub(super) fn start(
+        ids: impl IntoIterator<Item = DocumentId>,
+        matching_words: Arc<MatchingWords>,
+    ) -> Self {
+        Self {
+            ids: Arc::new(ids.into_iter().collect()),
+            matching_words,
+            next: 0,
+        }
+    }
+
+    /// The ids of the documents of the snapshot.
+    pub(super) fn ids(&self) -> impl Iterator<Item = DocumentId> + '_ {
+        self.ids.iter().copied()
+    }
+
+    pub(super) fn matching_words(&self) -> Arc<MatchingWords> {
+        self.matching_words.clone()
+    }
+
+    /// The number of documents of the snapshot.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// The ids of the next `limit` documents of the snapshot that still exist, and the position
+    /// after them, `None` once the end of the snapshot is reached.
+    pub(super) fn page(
+        &self,
+        limit: usize,
+        exists: impl Fn(DocumentId) -> bool,
+    ) -> (Vec<DocumentId>, Option<Self>) {
+        let mut page = Vec::new();
+        let mut next = self.next;
+        while page.len() < limit && next < self.ids.len() {
+            let id = self.ids[next];
+            if exists(id) {
+                page.push(id);
+            }
+            next += 1;
+        }
+
+        let position = (next < self.ids.len()).then(|| Self {
+            ids: self.ids.clone(),
+            matching_words: self.matching_words.clone(),
+            next,
+        });
+        (page, position)
+    }
+}
+
+impl fmt::Debug for ScrollPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScrollPosition")
+            .field("len", &self.ids.len())
+            .field("next", &self.next)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scroll_pages() {
+        let position = ScrollPosition::start(vec![1, 2, 3, 4, 5], Arc::default());
+        let (page, position) = position.page(2, |_| true);
+        assert_eq!(page, [1, 2]);
+
+        // the deleted documents are skipped
+        let (page, position) = position.unwrap().page(2, |id| id != 3);
+        assert_eq!(page, [4, 5]);
+        assert!(position.is_none());
+
+        let position = ScrollPosition::start(vec![1, 2], Arc::default());
+        let (page, position) = position.page(2, |_| true);
+        assert_eq!(page, [1, 2]);
+        assert!(position.is_none());
+    }
+}
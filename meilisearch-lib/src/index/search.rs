@@ -1,20 +1,47 @@
-use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::ops::Range;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Instant;
 
+use chrono::{TimeZone, Utc};
 use either::Either;
 use heed::RoTxn;
 use indexmap::IndexMap;
-use meilisearch_tokenizer::{Analyzer, AnalyzerConfig, Token};
-use milli::{AscDesc, FieldId, FieldsIdsMap, FilterCondition, MatchingWords, SortError};
+use meilisearch_tokenizer::{Analyzer, AnalyzerConfig, SeparatorKind, Token};
+use milli::{
+    AscDesc, DocumentId, FieldId, FieldsIdsMap, FilterCondition, MatchingWords, SortError,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use regex::Regex;
+use roaring::RoaringBitmap;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
 use crate::index::error::FacetError;
 use crate::index::IndexError;
 
+use super::collation::{
+    collate_sort_rules, collation_analyzer, is_hidden_field, normalize_string, timestamp_field,
+};
+use super::custom_settings::{
+    boolean_attributes, date_attributes, normalized_filterable_attributes, raw_attributes,
+    search_defaults, typo_tolerance,
+};
 use super::error::Result;
+use super::filter::{
+    check_filter_syntax, expand_boolean_conditions, localize_conditions, normalize_conditions,
+    redirect_date_conditions,
+};
+use super::locale::FilterLocale;
+use super::nested::{parse_element_conditions, ranked_documents_ids, FilterMode};
+use super::query_debug::QueryDebug;
+use super::ranking_score::RankingScoreDetails;
+use super::scroll::ScrollPosition;
+use super::search_after::{and_search_after_filter, search_after_filter};
+use super::search_defaults::SearchDefaults;
 use super::Index;
 
 pub type Document = IndexMap<String, Value>;
@@ -27,33 +54,140 @@ pub struct MatchInfo {
 }
 
 pub const DEFAULT_SEARCH_LIMIT: usize = 20;
-const fn default_search_limit() -> usize {
-    DEFAULT_SEARCH_LIMIT
-}
+
+/// The seed of the unseeded random samples when the indexing is deterministic.
+const DETERMINISTIC_SEED: u64 = 0;
 
 pub const DEFAULT_CROP_LENGTH: usize = 200;
-pub const fn default_crop_length() -> usize {
-    DEFAULT_CROP_LENGTH
-}
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct SearchQuery {
     pub q: Option<String>,
     pub offset: Option<usize>,
-    #[serde(default = "default_search_limit")]
-    pub limit: usize,
+    /// The number of hits, [`DEFAULT_SEARCH_LIMIT`] unless the index has a default one.
+    pub limit: Option<usize>,
     pub attributes_to_retrieve: Option<BTreeSet<String>>,
     pub attributes_to_crop: Option<Vec<String>>,
-    #[serde(default = "default_crop_length")]
-    pub crop_length: usize,
+    /// The number of characters kept on each side of the first matched word of the cropped
+    /// attributes, or the maximum length of their snippets in `crop_unit` if it is given.
+    pub crop_length: Option<usize>,
+    /// Crops the attributes around the window holding the most matched words instead of their
+    /// first matched word.
+    pub crop_unit: Option<CropUnit>,
     pub attributes_to_highlight: Option<HashSet<String>>,
+    #[serde(default)]
+    pub html_formatting: HtmlFormatting,
     // Default to false
     #[serde(default = "Default::default")]
     pub matches: bool,
     pub filter: Option<Value>,
+    #[serde(default)]
+    pub filter_mode: FilterMode,
+    pub matching_strategy: Option<MatchingStrategy>,
+    /// The BCP 47 tag of the locale the numbers and dates of the filter are written in, such as
+    /// `fr` for `price > 1.234,56`.
+    pub locale: Option<String>,
     pub sort: Option<Vec<String>>,
     pub facets_distribution: Option<Vec<String>>,
+    pub diversity: Option<Diversity>,
+    /// Returns a uniformly sampled subset of this size of the matching documents instead of the
+    /// best ranked ones, paginated with `offset` and `limit`. The pages of a sample are only taken
+    /// from the same sample when it is seeded with a `_random(seed)` sort rule.
+    pub sample: Option<usize>,
+    /// Waits, for a bounded time, until the update with this id is processed, so that the
+    /// results reflect it.
+    pub min_update_id: Option<u64>,
+    /// The sort values and the id of the last hit of the previous page, as returned in
+    /// `nextSearchAfter`, to get the hits sorted after it. An empty array starts from the first
+    /// hit.
+    pub search_after: Option<Vec<Value>>,
+    /// Starts a scroll through all the matching documents, the result holding the `cursor` of
+    /// the next page. The pages of a scroll follow the order of the documents in the index, not
+    /// their ranking, and `offset` is ignored.
+    #[serde(default)]
+    pub scroll: bool,
+    /// The position of the scroll continued by this search, set by the scroll route.
+    #[serde(skip)]
+    pub scroll_position: Option<ScrollPosition>,
+    /// Returns how the query was interpreted along with the results.
+    #[serde(default)]
+    pub debug: bool,
+    /// Adds the `_rankingScore` of each hit, from 0 to 1, an estimate of how well it matches.
+    #[serde(default)]
+    pub show_ranking_score: bool,
+    /// Adds the `_rankingScoreDetails` of each hit, the score of each ranking rule.
+    #[serde(default)]
+    pub show_ranking_score_details: bool,
+    /// Restricts the search to these searchable attributes, `*` standing for all of them.
+    pub attributes_to_search_on: Option<Vec<String>>,
+    /// The only fields the hits and the facets distribution can contain, set by the restrictions
+    /// of the API key the search is made with.
+    #[serde(skip)]
+    pub allowed_fields: Option<BTreeSet<String>>,
+    /// The API key the search is made with, the only one that can continue the scroll it starts.
+    #[serde(skip)]
+    pub api_key: Option<String>,
+}
+
+/// What the `cropLength` of a search counts when the snippets are the best matching windows.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CropUnit {
+    Characters,
+    Words,
+}
+
+/// Which documents match when a query has words that are found in none of them.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchingStrategy {
+    /// The last words of the query are ignored, one at a time, until documents match.
+    Last,
+    /// Only the documents matching all the words of the query match.
+    All,
+}
+
+impl Default for MatchingStrategy {
+    fn default() -> Self {
+        Self::Last
+    }
+}
+
+/// How the HTML contained in the documents is handled in `_formatted`, so that the hits can't
+/// inject markup in the interfaces displaying the highlighted values.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HtmlFormatting {
+    /// The values are returned as they are in the documents.
+    Raw,
+    /// The values are HTML-escaped before the highlight tags are inserted.
+    Escape,
+    /// The HTML tags are removed from the values, the remaining `<` and `>` are escaped.
+    Strip,
+}
+
+impl Default for HtmlFormatting {
+    fn default() -> Self {
+        Self::Raw
+    }
+}
+
+/// Limits how many consecutive hits can share the same value for `attribute`. The hits are only
+/// reordered inside the page that is returned, the hits of the previous and next pages are not
+/// taken into account: the last hits of a page and the first ones of the next page can share the
+/// same value for more than `max_per_value` hits.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Diversity {
+    pub attribute: String,
+    /// At least 1.
+    #[serde(default = "default_max_per_value")]
+    pub max_per_value: usize,
+}
+
+const fn default_max_per_value() -> usize {
+    1
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -64,6 +198,13 @@ pub struct SearchHit {
     pub formatted: Document,
     #[serde(rename = "_matchesInfo", skip_serializing_if = "Option::is_none")]
     pub matches_info: Option<MatchesInfo>,
+    #[serde(rename = "_rankingScore", skip_serializing_if = "Option::is_none")]
+    pub ranking_score: Option<f64>,
+    #[serde(
+        rename = "_rankingScoreDetails",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub ranking_score_details: Option<RankingScoreDetails>,
 }
 
 #[derive(Serialize, Debug)]
@@ -80,6 +221,41 @@ pub struct SearchResult {
     pub facets_distribution: Option<BTreeMap<String, BTreeMap<String, u64>>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub exhaustive_facets_count: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug: Option<QueryDebug>,
+    /// The `searchAfter` of the next page, `None` when it is the last one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_search_after: Option<Vec<Value>>,
+    /// The cursor of the next page of the scroll, `None` when it is the last one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// The position of the next page of the scroll, kept by the index controller under `cursor`.
+    #[serde(skip)]
+    pub scroll: Option<ScrollPosition>,
+}
+
+impl SearchQuery {
+    /// Fills the parameters omitted by the query with the default ones of the index.
+    pub fn with_defaults(mut self, defaults: &SearchDefaults) -> Self {
+        self.limit = self.limit.or(defaults.limit);
+        if self.attributes_to_highlight.is_none() {
+            self.attributes_to_highlight = defaults
+                .attributes_to_highlight
+                .as_ref()
+                .map(|attributes| attributes.iter().cloned().collect());
+        }
+        self.crop_length = self.crop_length.or(defaults.crop_length);
+        self.matching_strategy = self.matching_strategy.or(defaults.matching_strategy);
+        self
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_SEARCH_LIMIT)
+    }
+
+    pub fn crop_length(&self) -> usize {
+        self.crop_length.unwrap_or(DEFAULT_CROP_LENGTH)
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -92,46 +268,304 @@ impl Index {
     pub fn perform_search(&self, query: SearchQuery) -> Result<SearchResult> {
         let before_search = Instant::now();
         let rtxn = self.read_txn()?;
+        let query = query.with_defaults(&search_defaults(self, &rtxn)?);
+        let limit = query.limit();
 
-        let mut search = self.search(&rtxn);
+        if query
+            .diversity
+            .as_ref()
+            .map_or(false, |diversity| diversity.max_per_value == 0)
+        {
+            return Err(IndexError::InvalidDiversity);
+        }
+        if let Some(max) = self.update_handler.max_search_limit() {
+            // A sample smaller than the limit returns fewer hits.
+            let limit = query.sample.map_or(limit, |size| size.min(limit));
+            if limit > max {
+                return Err(IndexError::SearchLimitExceeded { limit, max });
+            }
+        }
+        if let (Some(max), Some(facets)) = (
+            self.update_handler.max_search_facets(),
+            query.facets_distribution.as_ref(),
+        ) {
+            // `*` stands for all the filterable attributes.
+            let filterable = if facets.iter().any(|facet| facet == "*") {
+                self.user_filterable_fields(&rtxn)?
+            } else {
+                BTreeSet::new()
+            };
+            let requested: BTreeSet<_> = facets
+                .iter()
+                .filter(|facet| *facet != "*")
+                .chain(&filterable)
+                .collect();
+            let count = requested.len();
+            if count > max {
+                return Err(IndexError::TooManyFacets { count, max });
+            }
+        }
 
-        if let Some(ref query) = query.q {
-            search.query(query);
+        if let Some(max) = self.update_handler.max_search_depth() {
+            let depth = query.offset.unwrap_or_default().saturating_add(limit);
+            if depth > max {
+                return Err(IndexError::SearchDepthExceeded { depth, max });
+            }
         }
 
-        search.limit(query.limit);
-        search.offset(query.offset.unwrap_or_default());
+        let search_after = match query.search_after {
+            Some(ref values) => {
+                if query.q.as_deref().map_or(false, |q| !q.trim().is_empty()) {
+                    return Err(IndexError::InvalidSearchAfter(String::from(
+                        "only the placeholder searches, without `q`, are ordered by their sort rules",
+                    )));
+                }
+                let sort = query.sort.as_deref().ok_or_else(|| {
+                    IndexError::InvalidSearchAfter(String::from("the search must be sorted"))
+                })?;
+                Some((sort, search_after_filter(sort, values)?))
+            }
+            None => None,
+        };
 
-        if let Some(ref filter) = query.filter {
-            if let Some(facets) = parse_filter(filter, self, &rtxn)? {
-                search.filter(facets);
+        let (documents_ids, matching_words, candidates, scroll) = match query.scroll_position {
+            // The pages of a scroll follow the snapshot taken by its first search, that is not run
+            // again.
+            Some(ref position) => {
+                let existing = self.documents_ids(&rtxn)?;
+                let candidates = position.ids().filter(|id| existing.contains(*id)).collect();
+                let (documents_ids, scroll) = position.page(limit, |id| existing.contains(id));
+                (documents_ids, position.matching_words(), candidates, scroll)
             }
-        }
+            None => {
+                let mut search = self.search(&rtxn);
 
-        if let Some(ref sort) = query.sort {
-            let sort = match sort.iter().map(|s| AscDesc::from_str(s)).collect() {
-                Ok(sorts) => sorts,
-                Err(asc_desc_error) => {
-                    return Err(IndexError::Milli(SortError::from(asc_desc_error).into()))
+                if let Some(ref query) = query.q {
+                    search.query(query);
                 }
-            };
 
-            search.sort_criteria(sort);
-        }
+                let typo_tolerance = typo_tolerance(self, &rtxn)?;
+                if !typo_tolerance.enabled {
+                    search.authorize_typos(false);
+                }
+                if query.matching_strategy == Some(MatchingStrategy::All) {
+                    search.optional_words(false);
+                }
+
+                let random_seed = match query.sort {
+                    Some(ref sort) => parse_random_sort(sort)?,
+                    None => None,
+                };
+                let shuffle = random_seed.is_some() || query.sample.is_some();
+                let scrolling = query.scroll || query.scroll_position.is_some();
+
+                // When shuffling or scrolling, the ranked documents are thrown away, only the candidates
+                // are used. Otherwise the documents preceding the page are fetched too, for the page
+                // to be filled from the same search when some of the documents are rejected.
+                if shuffle || scrolling {
+                    search.limit(0);
+                } else {
+                    search.limit(query.offset.unwrap_or_default().saturating_add(limit));
+                    search.offset(0);
+                }
+
+                let filter = match search_after {
+                    Some((_, Some(ref after))) => Some(and_search_after_filter(
+                        query.filter.clone(),
+                        after.filter.clone(),
+                    )),
+                    _ => query.filter.clone(),
+                };
+                if let Some(ref filter) = filter {
+                    if let Some(facets) =
+                        parse_filter(filter, self, &rtxn, query.locale.as_deref())?
+                    {
+                        search.filter(facets);
+                    }
+                }
+
+                let search_on = match query.attributes_to_search_on {
+                    Some(ref attributes) => self.search_on_fields_ids(&rtxn, attributes)?,
+                    None => None,
+                };
+                // A placeholder search matches no words, it can't be restricted.
+                let search_on = search_on
+                    .filter(|_| query.q.as_deref().map_or(false, |q| !q.trim().is_empty()));
+
+                let element_conditions = match (query.filter_mode, query.filter.as_ref()) {
+                    (FilterMode::SameElement, Some(filter)) => parse_element_conditions(filter)?,
+                    _ => Vec::new(),
+                };
+
+                if let Some(sort) = query.sort.as_ref().filter(|_| random_seed.is_none()) {
+                    let sort = collate_sort_rules(sort, &self.sortable_fields(&rtxn)?);
+                    let sort = match sort.iter().map(|s| AscDesc::from_str(s)).collect() {
+                        Ok(sorts) => sorts,
+                        Err(asc_desc_error) => {
+                            return Err(IndexError::Milli(SortError::from(asc_desc_error).into()))
+                        }
+                    };
+
+                    search.sort_criteria(sort);
+                }
+
+                let milli::SearchResult {
+                    mut documents_ids,
+                    matching_words,
+                    mut candidates,
+                    ..
+                } = search.execute()?;
+                // The number of documents the search returns, rejected or not.
+                let matched = candidates.len() as usize;
+
+                let mut rejected = Vec::new();
+                let ties = search_after
+                    .as_ref()
+                    .and_then(|(_, after)| after.as_ref()?.ties.as_ref());
+                if let Some((ties, last_id)) = ties {
+                    let ties = Value::String(ties.clone());
+                    if let Some(condition) = parse_filter(&ties, self, &rtxn, None)? {
+                        let mut ties_search = self.search(&rtxn);
+                        ties_search.limit(0);
+                        ties_search.filter(condition);
+                        // The documents sorted with the same values are ranked by their id.
+                        let returned = ties_search.execute()?.candidates;
+                        rejected.extend(returned.iter().take_while(|id| id <= last_id));
+                    }
+                }
+                if !element_conditions.is_empty() {
+                    rejected.extend(self.same_element_rejects(
+                        &rtxn,
+                        candidates.iter(),
+                        &element_conditions,
+                    )?);
+                }
+                let typo_check = match query.q.as_deref().filter(|_| typo_tolerance.enabled) {
+                    Some(q) => self.typo_check(
+                        &rtxn,
+                        q,
+                        &typo_tolerance,
+                        query.matching_strategy == Some(MatchingStrategy::All),
+                    )?,
+                    None => None,
+                };
+                // The random orders and the scrolls use all the candidates, the ranked pages only
+                // the documents they fetch, that are checked while they are fetched.
+                if let Some(typo_check) = typo_check.as_ref().filter(|_| shuffle || scrolling) {
+                    for id in candidates.iter() {
+                        if typo_check.rejects(self, &rtxn, id)? {
+                            rejected.push(id);
+                        }
+                    }
+                }
+                if let Some(ref fids) = search_on {
+                    rejected.extend(self.search_on_rejects(
+                        &rtxn,
+                        candidates.iter(),
+                        fids,
+                        &matching_words,
+                    )?);
+                }
+                for id in rejected {
+                    candidates.remove(id);
+                }
+                if !shuffle && !scrolling {
+                    // Whether the fetched documents are rejected by the typo check, the documents
+                    // fetched again not being checked again.
+                    let mut typo_rejects = HashMap::new();
+                    documents_ids = ranked_documents_ids(
+                        &mut search,
+                        documents_ids,
+                        query.offset.unwrap_or_default(),
+                        limit,
+                        matched,
+                        |id| {
+                            let typo_check = match typo_check {
+                                Some(ref typo_check) if candidates.contains(id) => typo_check,
+                                _ => return Ok(candidates.contains(id)),
+                            };
+                            let rejects = match typo_rejects.entry(id) {
+                                Entry::Occupied(entry) => *entry.get(),
+                                Entry::Vacant(entry) => {
+                                    *entry.insert(typo_check.rejects(self, &rtxn, id)?)
+                                }
+                            };
+                            Ok(!rejects)
+                        },
+                    )?;
+                    // The documents that weren't fetched are not checked, they are still counted.
+                    for (id, rejects) in typo_rejects {
+                        if rejects {
+                            candidates.remove(id);
+                        }
+                    }
+                }
 
-        let milli::SearchResult {
-            documents_ids,
-            matching_words,
-            candidates,
-            ..
-        } = search.execute()?;
+                let documents_ids = if shuffle {
+                    let mut rng = match random_seed {
+                        Some(seed) => StdRng::seed_from_u64(seed),
+                        None if self.update_handler.is_deterministic() => {
+                            StdRng::seed_from_u64(DETERMINISTIC_SEED)
+                        }
+                        None => StdRng::from_entropy(),
+                    };
+
+                    // A sample is the beginning of the random order of the candidates.
+                    let offset = query.offset.unwrap_or_default();
+                    let limit = match query.sample {
+                        Some(size) => limit.min(size.saturating_sub(offset)),
+                        None => limit,
+                    };
+                    random_page(&candidates, &mut rng, offset, limit)
+                } else {
+                    documents_ids
+                };
+
+                let matching_words = Arc::new(matching_words);
+                let (documents_ids, scroll) = if query.scroll {
+                    ScrollPosition::start(candidates.iter(), matching_words.clone())
+                        .page(limit, |_| true)
+                } else {
+                    (documents_ids, None)
+                };
+                (documents_ids, matching_words, candidates, scroll)
+            }
+        };
+
+        // The next pages of a scroll are not new searches.
+        if query.scroll_position.is_none() {
+            self.record_search_usage(
+                &rtxn,
+                query.sort.as_deref(),
+                query.q.as_deref().map_or(false, |q| !q.trim().is_empty()),
+                &documents_ids,
+                &matching_words,
+            )?;
+        }
 
         let fields_ids_map = self.fields_ids_map(&rtxn).unwrap();
 
         let displayed_ids = self
             .displayed_fields_ids(&rtxn)?
             .map(|fields| fields.into_iter().collect::<BTreeSet<_>>())
-            .unwrap_or_else(|| fields_ids_map.iter().map(|(id, _)| id).collect());
+            .unwrap_or_else(|| {
+                fields_ids_map
+                    .iter()
+                    .filter(|(_, name)| !is_hidden_field(name))
+                    .map(|(id, _)| id)
+                    .collect()
+            });
+        let displayed_ids = match query.allowed_fields {
+            Some(ref allowed) => displayed_ids
+                .into_iter()
+                .filter(|id| {
+                    fields_ids_map
+                        .name(*id)
+                        .map_or(false, |name| allowed.contains(name))
+                })
+                .collect(),
+            None => displayed_ids,
+        };
 
         let fids = |attrs: &BTreeSet<String>| {
             let mut ids = BTreeSet::new();
@@ -160,11 +594,22 @@ impl Index {
             .intersection(&displayed_ids)
             .cloned()
             .collect();
+        if let Some(max) = self.update_handler.max_attributes_to_retrieve() {
+            if to_retrieve_ids.len() > max {
+                return Err(IndexError::TooManyAttributesToRetrieve {
+                    count: to_retrieve_ids.len(),
+                    max,
+                });
+            }
+        }
 
         let attr_to_highlight = query.attributes_to_highlight.unwrap_or_default();
 
         let attr_to_crop = query.attributes_to_crop.unwrap_or_default();
 
+        // The raw attributes, such as the ones holding HTML, are never highlighted nor cropped.
+        let raw_ids = fids(&raw_attributes(self, &rtxn)?);
+
         // Attributes in `formatted_options` correspond to the attributes that will be in `_formatted`
         // These attributes are:
         // - the attributes asked to be highlighted or cropped (with `attributesToCrop` or `attributesToHighlight`)
@@ -173,10 +618,11 @@ impl Index {
         let formatted_options = compute_formatted_options(
             &attr_to_highlight,
             &attr_to_crop,
-            query.crop_length,
+            query.crop_length(),
             &to_retrieve_ids,
             &fields_ids_map,
             &displayed_ids,
+            &raw_ids,
         );
 
         let stop_words = fst::Set::default();
@@ -184,24 +630,41 @@ impl Index {
         config.stop_words(&stop_words);
         let analyzer = Analyzer::new(config);
 
-        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")));
+        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")))
+            .html_formatting(query.html_formatting)
+            .crop_unit(query.crop_unit);
+
+        let scorer = (query.show_ranking_score || query.show_ranking_score_details)
+            .then(|| self.ranking_scorer(&rtxn, query.q.as_deref(), query.sort.as_deref()))
+            .transpose()?;
 
         let mut documents = Vec::new();
 
+        let diversity_fid = query
+            .diversity
+            .as_ref()
+            .and_then(|diversity| fields_ids_map.id(&diversity.attribute));
+
         let documents_iter = self.documents(&rtxn, documents_ids)?;
 
-        for (_id, obkv) in documents_iter {
+        let mut returned_ids = Vec::new();
+        for (id, obkv) in documents_iter {
+            // The raw json value is enough to tell two values apart.
+            let diversity_key = diversity_fid
+                .and_then(|fid| obkv.get(fid))
+                .map(<[u8]>::to_vec);
+
             let mut document = make_document(&to_retrieve_ids, &fields_ids_map, obkv)?;
 
             let matches_info = query
                 .matches
-                .then(|| compute_matches(&matching_words, &document, &analyzer));
+                .then(|| compute_matches(&*matching_words, &document, &analyzer));
 
             let formatted = format_fields(
                 &fields_ids_map,
                 obkv,
                 &formatter,
-                &matching_words,
+                &*matching_words,
                 &formatted_options,
             )?;
 
@@ -209,23 +672,101 @@ impl Index {
                 insert_geo_distance(sort, &mut document);
             }
 
+            let (ranking_score, ranking_score_details) = match scorer {
+                Some(ref scorer) => {
+                    let (score, details) = scorer.score(&fields_ids_map, obkv, &analyzer)?;
+                    (
+                        query.show_ranking_score.then(|| score),
+                        query.show_ranking_score_details.then(|| details),
+                    )
+                }
+                None => (None, None),
+            };
+
             let hit = SearchHit {
                 document,
                 formatted,
                 matches_info,
+                ranking_score,
+                ranking_score_details,
             };
-            documents.push(hit);
+            returned_ids.push(id);
+            documents.push((diversity_key, hit));
         }
 
-        let nb_hits = candidates.len();
+        // The next page starts after the last hit of the sort that is returned, the diversity only
+        // reorders the hits of the page.
+        let next_search_after = match (search_after, returned_ids.last()) {
+            (Some((sort, _)), Some(&last)) if returned_ids.len() == limit => {
+                self.search_after_values(&rtxn, sort, last)?
+            }
+            _ => None,
+        };
+
+        let documents = match query.diversity {
+            Some(ref diversity) => diversify_hits(documents, diversity.max_per_value),
+            None => documents.into_iter().map(|(_, hit)| hit).collect(),
+        };
+
+        // The hits of a scroll are counted in its snapshot.
+        let nb_hits = match query.scroll_position {
+            Some(ref position) => position.len() as u64,
+            None => candidates.len(),
+        };
 
         let facets_distribution = match query.facets_distribution {
-            Some(ref fields) => {
-                let mut facets_distribution = self.facets_distribution(&rtxn);
-                if fields.iter().all(|f| f != "*") {
-                    facets_distribution.facets(fields);
+            Some(ref facets) => {
+                let mut fields = Vec::new();
+                let mut bucketed = Vec::new();
+                for facet in facets {
+                    match FacetBuckets::parse_facet(facet)? {
+                        Some((attribute, buckets)) => bucketed.push((facet, attribute, buckets)),
+                        None => fields.push(facet),
+                    }
+                }
+
+                let mut distribution = if fields.is_empty() {
+                    BTreeMap::new()
+                } else {
+                    let mut facets_distribution = self.facets_distribution(&rtxn);
+                    if fields.iter().all(|f| *f != "*") {
+                        facets_distribution.facets(fields);
+                    }
+                    let mut distribution = facets_distribution
+                        .candidates(candidates.clone())
+                        .execute()?;
+                    distribution.retain(|name, _| {
+                        !is_hidden_field(name)
+                            && query
+                                .allowed_fields
+                                .as_ref()
+                                .map_or(true, |allowed| allowed.contains(name))
+                    });
+                    distribution
+                };
+
+                // The buckets are counted from the facet databases, that only hold the values of
+                // the filterable attributes.
+                let filterable = match bucketed.is_empty() {
+                    true => BTreeSet::new(),
+                    false => self.user_filterable_fields(&rtxn)?,
+                };
+                for (facet, attribute, buckets) in bucketed {
+                    if !filterable.contains(attribute) {
+                        return Err(IndexError::BucketedFacetNotFilterable(
+                            attribute.to_string(),
+                        ));
+                    }
+                    let allowed = query
+                        .allowed_fields
+                        .as_ref()
+                        .map_or(true, |allowed| allowed.contains(attribute));
+                    if !allowed {
+                        continue;
+                    }
+                    let counts = self.bucketed_facet(&rtxn, &candidates, attribute, &buckets)?;
+                    distribution.insert(facet.clone(), counts);
                 }
-                let distribution = facets_distribution.candidates(candidates).execute()?;
 
                 Some(distribution)
             }
@@ -234,21 +775,239 @@ impl Index {
 
         let exhaustive_facets_count = facets_distribution.as_ref().map(|_| false); // not implemented yet
 
+        let debug = if query.debug {
+            Some(self.query_debug(&rtxn, query.q.as_deref().unwrap_or_default())?)
+        } else {
+            None
+        };
+
         let result = SearchResult {
             exhaustive_nb_hits: false, // not implemented yet
             hits: documents,
             nb_hits,
             query: query.q.clone().unwrap_or_default(),
-            limit: query.limit,
+            limit,
             offset: query.offset.unwrap_or_default(),
             processing_time_ms: before_search.elapsed().as_millis(),
             facets_distribution,
             exhaustive_facets_count,
+            debug,
+            next_search_after,
+            cursor: None,
+            scroll,
         };
         Ok(result)
     }
 }
 
+impl Index {
+    /// Counts the `candidates` by bucket for the given attribute from the numbers of the facet
+    /// databases, the timestamps for a date histogram. A document whose attribute holds several
+    /// values is counted once in each of the corresponding buckets.
+    fn bucketed_facet(
+        &self,
+        rtxn: &RoTxn,
+        candidates: &RoaringBitmap,
+        attribute: &str,
+        buckets: &FacetBuckets,
+    ) -> Result<BTreeMap<String, u64>> {
+        let mut counts = buckets.empty_buckets();
+
+        let field = match buckets {
+            FacetBuckets::Date(_) => timestamp_field(attribute),
+            FacetBuckets::Ranges(_) => attribute.to_string(),
+        };
+        let fid = match self.fields_ids_map(rtxn)?.id(&field) {
+            Some(fid) => fid,
+            None => return Ok(counts),
+        };
+
+        let mut documents: BTreeMap<String, RoaringBitmap> = BTreeMap::new();
+        for entry in self.facet_numbers(rtxn, fid)? {
+            let (value, docids) = entry?;
+            if let Some(bucket) = buckets.bucket(value) {
+                *documents.entry(bucket).or_default() |= docids & candidates;
+            }
+        }
+        for (bucket, docids) in documents {
+            if !docids.is_empty() || counts.contains_key(&bucket) {
+                counts.insert(bucket, docids.len());
+            }
+        }
+
+        Ok(counts)
+    }
+}
+
+/// The buckets of a bucketed facet, requested with `attribute:byMonth` for a date histogram or
+/// `attribute:ranges(0,50,100)` for numeric ranges, for example.
+#[derive(Debug, Clone, PartialEq)]
+enum FacetBuckets {
+    Date(DateInterval),
+    /// The sorted bounds of the ranges, a range includes its lower bound but excludes its upper
+    /// bound.
+    Ranges(Vec<f64>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DateInterval {
+    Day,
+    Month,
+    Year,
+}
+
+impl FacetBuckets {
+    /// Splits a facet such as `created_at:byMonth` into its attribute and its buckets. Returns
+    /// `None` if the facet is a plain facet.
+    fn parse_facet(facet: &str) -> Result<Option<(&str, Self)>> {
+        if let Some((attribute, bounds)) = facet
+            .strip_suffix(')')
+            .and_then(|facet| facet.rsplit_once(":ranges("))
+        {
+            let bounds = bounds
+                .split(',')
+                .map(|bound| bound.trim().parse::<f64>().ok().filter(|b| b.is_finite()))
+                .collect::<Option<Vec<_>>>()
+                .filter(|bounds| bounds.len() >= 2)
+                .filter(|bounds| bounds.windows(2).all(|w| w[0] < w[1]))
+                .ok_or_else(|| IndexError::InvalidFacetRanges(facet.to_string()))?;
+
+            return Ok(Some((attribute, Self::Ranges(bounds))));
+        }
+
+        let (attribute, interval) = match facet.rsplit_once(':') {
+            Some(split) => split,
+            None => return Ok(None),
+        };
+        let interval = match interval {
+            "byDay" => DateInterval::Day,
+            "byMonth" => DateInterval::Month,
+            "byYear" => DateInterval::Year,
+            _ => return Ok(None),
+        };
+
+        Ok(Some((attribute, Self::Date(interval))))
+    }
+
+    /// The buckets that must appear in the distribution even when no document falls into them.
+    fn empty_buckets(&self) -> BTreeMap<String, u64> {
+        match self {
+            Self::Date(_) => BTreeMap::new(),
+            Self::Ranges(bounds) => bounds
+                .windows(2)
+                .map(|w| (format!("{}..{}", w[0], w[1]), 0))
+                .collect(),
+        }
+    }
+
+    /// Returns the bucket of a number, the dates being timestamps.
+    fn bucket(&self, value: f64) -> Option<String> {
+        match self {
+            Self::Date(interval) => {
+                let date = Utc.timestamp_opt(value as i64, 0).single()?;
+
+                let format = match interval {
+                    DateInterval::Day => "%Y-%m-%d",
+                    DateInterval::Month => "%Y-%m",
+                    DateInterval::Year => "%Y",
+                };
+
+                Some(date.format(format).to_string())
+            }
+            Self::Ranges(bounds) => bounds
+                .windows(2)
+                .find(|w| w[0] <= value && value < w[1])
+                .map(|w| format!("{}..{}", w[0], w[1])),
+        }
+    }
+}
+
+/// Looks for a `_random(seed)` sort rule, which must be the only sort rule of the query.
+fn parse_random_sort(sort: &[String]) -> Result<Option<u64>> {
+    lazy_static::lazy_static! {
+        static ref RANDOM_REGEX: Regex = Regex::new(r"^_random\(\s*(\d+)\s*\)$").unwrap();
+    };
+
+    if !sort.iter().any(|s| s.starts_with("_random")) {
+        return Ok(None);
+    }
+
+    match sort {
+        [rule] => RANDOM_REGEX
+            .captures(rule)
+            .and_then(|capture_group| capture_group[1].parse().ok())
+            .map(Some)
+            .ok_or_else(|| IndexError::InvalidRandomSort(rule.clone())),
+        _ => Err(IndexError::InvalidRandomSort(sort.join(", "))),
+    }
+}
+
+/// The documents at the positions `offset..offset + limit` of a uniformly random order of the
+/// `candidates`, the same for the same state of `rng`. Only the beginning of the order is drawn,
+/// with a Fisher-Yates shuffle stopped after `offset + limit` swaps, and the candidates are walked
+/// through once to find the documents at the drawn positions.
+fn random_page(
+    candidates: &RoaringBitmap,
+    rng: &mut StdRng,
+    offset: usize,
+    limit: usize,
+) -> Vec<DocumentId> {
+    let len = candidates.len() as usize;
+    let end = offset.saturating_add(limit).min(len);
+    // The positions that were swapped, the other ones hold their own position.
+    let mut swapped = HashMap::new();
+    let mut positions = Vec::with_capacity(end);
+    for i in 0..end {
+        let j = rng.gen_range(i..len);
+        let drawn = swapped.get(&j).copied().unwrap_or(j);
+        swapped.insert(j, swapped.get(&i).copied().unwrap_or(i));
+        positions.push(drawn);
+    }
+    let positions = &positions[offset.min(end)..];
+
+    let mut by_position: Vec<_> = (0..positions.len()).collect();
+    by_position.sort_unstable_by_key(|&rank| positions[rank]);
+    let mut ids = vec![0; positions.len()];
+    let mut walked = candidates.iter().enumerate();
+    for rank in by_position {
+        if let Some((_, id)) = walked.find(|(position, _)| *position == positions[rank]) {
+            ids[rank] = id;
+        }
+    }
+    ids
+}
+
+/// Reorders the ranked hits so that no more than `max_per_value` consecutive hits share the same
+/// key, while keeping them as close as possible to their original rank. Hits without a key are
+/// never considered as part of a run.
+fn diversify_hits<K: PartialEq, T>(hits: Vec<(Option<K>, T)>, max_per_value: usize) -> Vec<T> {
+    let mut pending: VecDeque<_> = hits.into_iter().collect();
+    let mut reranked = Vec::with_capacity(pending.len());
+    let mut last_key = None;
+    let mut run = 0;
+
+    while !pending.is_empty() {
+        // If every remaining hit would extend the current run, we have no choice but to take
+        // the best ranked one.
+        let position = pending
+            .iter()
+            .position(|(key, _)| key.is_none() || *key != last_key || run < max_per_value)
+            .unwrap_or(0);
+        let (key, hit) = pending.remove(position).expect("position is in bounds");
+
+        if key.is_some() && key == last_key {
+            run += 1;
+        } else {
+            last_key = key;
+            run = 1;
+        }
+
+        reranked.push(hit);
+    }
+
+    reranked
+}
+
 fn insert_geo_distance(sorts: &[String], document: &mut Document) {
     lazy_static::lazy_static! {
         static ref GEO_REGEX: Regex =
@@ -322,22 +1081,29 @@ fn compute_formatted_options(
     to_retrieve_ids: &BTreeSet<FieldId>,
     fields_ids_map: &FieldsIdsMap,
     displayed_ids: &BTreeSet<FieldId>,
+    raw_ids: &BTreeSet<FieldId>,
 ) -> BTreeMap<FieldId, FormatOptions> {
     let mut formatted_options = BTreeMap::new();
 
+    let mut excluded = excluded_ids(attr_to_highlight.iter(), fields_ids_map);
+    excluded.extend(raw_ids);
+    let formattable_ids = displayed_ids.difference(&excluded).copied().collect();
     add_highlight_to_formatted_options(
         &mut formatted_options,
         attr_to_highlight,
         fields_ids_map,
-        displayed_ids,
+        &formattable_ids,
     );
 
+    let mut excluded = excluded_ids(attr_to_crop.iter(), fields_ids_map);
+    excluded.extend(raw_ids);
+    let formattable_ids = displayed_ids.difference(&excluded).copied().collect();
     add_crop_to_formatted_options(
         &mut formatted_options,
         attr_to_crop,
         query_crop_length,
         fields_ids_map,
-        displayed_ids,
+        &formattable_ids,
     );
 
     // Should not return `_formatted` if no valid attributes to highlight/crop
@@ -348,13 +1114,27 @@ fn compute_formatted_options(
     formatted_options
 }
 
+/// Returns the ids of the attributes excluded with a leading `-`, as in `["*", "-body_html"]`.
+fn excluded_ids<'a>(
+    attrs: impl Iterator<Item = &'a String>,
+    fields_ids_map: &FieldsIdsMap,
+) -> BTreeSet<FieldId> {
+    attrs
+        .filter_map(|attr| attr.strip_prefix('-'))
+        .filter_map(|attr| fields_ids_map.id(attr))
+        .collect()
+}
+
 fn add_highlight_to_formatted_options(
     formatted_options: &mut BTreeMap<FieldId, FormatOptions>,
     attr_to_highlight: &HashSet<String>,
     fields_ids_map: &FieldsIdsMap,
     displayed_ids: &BTreeSet<FieldId>,
 ) {
-    for attr in attr_to_highlight {
+    for attr in attr_to_highlight
+        .iter()
+        .filter(|attr| !attr.starts_with('-'))
+    {
         let new_format = FormatOptions {
             highlight: true,
             crop: None,
@@ -382,7 +1162,7 @@ fn add_crop_to_formatted_options(
     fields_ids_map: &FieldsIdsMap,
     displayed_ids: &BTreeSet<FieldId>,
 ) {
-    for attr in attr_to_crop {
+    for attr in attr_to_crop.iter().filter(|attr| !attr.starts_with('-')) {
         let mut split = attr.rsplitn(2, ':');
         let (attr_name, attr_len) = match split.next().zip(split.next()) {
             Some((len, name)) => {
@@ -504,11 +1284,37 @@ impl Matcher for MatchingWords {
 struct Formatter<'a, A> {
     analyzer: &'a Analyzer<'a, A>,
     marks: (String, String),
+    html: HtmlFormatting,
+    crop_unit: Option<CropUnit>,
 }
 
 impl<'a, A: AsRef<[u8]>> Formatter<'a, A> {
     pub fn new(analyzer: &'a Analyzer<'a, A>, marks: (String, String)) -> Self {
-        Self { analyzer, marks }
+        Self {
+            analyzer,
+            marks,
+            html: HtmlFormatting::Raw,
+            crop_unit: None,
+        }
+    }
+
+    pub fn crop_unit(mut self, crop_unit: Option<CropUnit>) -> Self {
+        self.crop_unit = crop_unit;
+        self
+    }
+
+    pub fn html_formatting(mut self, html: HtmlFormatting) -> Self {
+        self.html = html;
+        self
+    }
+
+    /// Pushes a part of a document value to `out`, escaping it as required by the HTML formatting.
+    fn push_text(&self, out: &mut String, text: &str) {
+        match self.html {
+            HtmlFormatting::Raw => out.push_str(text),
+            HtmlFormatting::Escape => escape_html(out, text, true),
+            HtmlFormatting::Strip => escape_html(out, text, false),
+        }
     }
 
     fn format_value(
@@ -565,91 +1371,284 @@ impl<'a, A: AsRef<[u8]>> Formatter<'a, A> {
         matcher: &impl Matcher,
         format_options: FormatOptions,
     ) -> String {
+        let s = match self.html {
+            HtmlFormatting::Strip => strip_html_tags(&s),
+            _ => s,
+        };
         let analyzed = self.analyzer.analyze(&s);
 
-        let tokens: Box<dyn Iterator<Item = (&str, Token)>> = match format_options.crop {
-            Some(crop_len) => {
-                let mut buffer = Vec::new();
-                let mut tokens = analyzed.reconstruct().peekable();
-
-                while let Some((word, token)) =
-                    tokens.next_if(|(_, token)| matcher.matches(token.text()).is_none())
-                {
-                    buffer.push((word, token));
-                }
+        let tokens: Vec<_> = analyzed.reconstruct().collect();
+        let tokens = match format_options.crop {
+            Some(crop_len) => match self.crop_unit {
+                Some(unit) => &tokens[crop_window(&tokens, matcher, crop_len, unit)],
+                None => &tokens[first_match_window(&tokens, matcher, crop_len)],
+            },
+            None => &tokens[..],
+        };
 
-                match tokens.next() {
-                    Some(token) => {
-                        let mut total_len: usize = buffer.iter().map(|(word, _)| word.len()).sum();
-                        let before_iter = buffer.into_iter().skip_while(move |(word, _)| {
-                            total_len -= word.len();
-                            total_len >= crop_len
-                        });
+        // The consecutive matched words only separated by soft separators, which is the case of the
+        // synonyms and of the split words, are highlighted as a single phrase.
+        let mut out = String::new();
+        let mut in_phrase = false;
+        let mut pending_separators = String::new();
+        for (word, token) in tokens {
+            let word = *word;
+            // Check if we need to do highlighting or computed matches before calling
+            // Matcher::match since the call is expensive.
+            let matched_length = if format_options.highlight && token.is_word() {
+                matcher.matches(token.text())
+            } else {
+                None
+            };
 
-                        let mut taken_after = 0;
-                        let after_iter = tokens.take_while(move |(word, _)| {
-                            let take = taken_after < crop_len;
-                            taken_after += word.chars().count();
-                            take
-                        });
+            match matched_length {
+                Some(length) if length >= word.len() => {
+                    if in_phrase {
+                        self.push_text(&mut out, &pending_separators);
+                        pending_separators.clear();
+                    } else {
+                        out.push_str(&self.marks.0);
+                        in_phrase = true;
+                    }
+                    self.push_text(&mut out, word);
+                    continue;
+                }
+                None if in_phrase && token.is_separator() == Some(SeparatorKind::Soft) => {
+                    pending_separators.push_str(word);
+                    continue;
+                }
+                _ => (),
+            }
 
-                        let iter = before_iter.chain(Some(token)).chain(after_iter);
+            if in_phrase {
+                out.push_str(&self.marks.1);
+                self.push_text(&mut out, &pending_separators);
+                pending_separators.clear();
+                in_phrase = false;
+            }
 
-                        Box::new(iter)
+            match matched_length {
+                Some(length) => match word.get(..length).zip(word.get(length..)) {
+                    Some((head, tail)) => {
+                        out.push_str(&self.marks.0);
+                        self.push_text(&mut out, head);
+                        out.push_str(&self.marks.1);
+                        self.push_text(&mut out, tail);
                     }
-                    // If no word matches in the attribute
+                    // if we are in the middle of a character
+                    // we highlight the complete word.
                     None => {
-                        let mut count = 0;
-                        let iter = buffer.into_iter().take_while(move |(word, _)| {
-                            let take = count < crop_len;
-                            count += word.len();
-                            take
-                        });
-
-                        Box::new(iter)
+                        out.push_str(&self.marks.0);
+                        self.push_text(&mut out, word);
+                        out.push_str(&self.marks.1);
                     }
-                }
+                },
+                None => self.push_text(&mut out, word),
             }
-            None => Box::new(analyzed.reconstruct()),
-        };
+        }
 
-        tokens.fold(String::new(), |mut out, (word, token)| {
-            // Check if we need to do highlighting or computed matches before calling
-            // Matcher::match since the call is expensive.
-            if format_options.highlight && token.is_word() {
-                if let Some(length) = matcher.matches(token.text()) {
-                    match word.get(..length).zip(word.get(length..)) {
-                        Some((head, tail)) => {
-                            out.push_str(&self.marks.0);
-                            out.push_str(head);
-                            out.push_str(&self.marks.1);
-                            out.push_str(tail);
-                        }
-                        // if we are in the middle of a character
-                        // or if all the word should be highlighted,
-                        // we highlight the complete word.
-                        None => {
-                            out.push_str(&self.marks.0);
-                            out.push_str(word);
-                            out.push_str(&self.marks.1);
-                        }
-                    }
-                    return out;
-                }
+        if in_phrase {
+            out.push_str(&self.marks.1);
+            self.push_text(&mut out, &pending_separators);
+        }
+
+        if format_options.crop.is_some() && self.crop_unit.is_some() {
+            out.truncate(out.trim_end().len());
+        }
+
+        out
+    }
+}
+
+/// Returns the range of the tokens of the snippet of at most `crop_len` characters or words,
+/// holding the most matched words. The snippets starting a sentence are preferred, and they
+/// end at the end of a sentence when it follows the last matched word.
+fn crop_window(
+    tokens: &[(&str, Token)],
+    matcher: &impl Matcher,
+    crop_len: usize,
+    unit: CropUnit,
+) -> Range<usize> {
+    let cost = |(word, token): &(&str, Token)| match unit {
+        CropUnit::Characters => word.chars().count(),
+        CropUnit::Words => token.is_separator().is_none() as usize,
+    };
+    let matched: Vec<_> = tokens
+        .iter()
+        .map(|(_, token)| token.is_word() && matcher.matches(token.text()).is_some())
+        .collect();
+
+    let mut sentence_starts = Vec::with_capacity(tokens.len());
+    let mut after_hard_separator = true;
+    for (_, token) in tokens {
+        sentence_starts.push(after_hard_separator);
+        match token.is_separator() {
+            Some(SeparatorKind::Hard) => after_hard_separator = true,
+            Some(SeparatorKind::Soft) => (),
+            None => after_hard_separator = false,
+        }
+    }
+
+    // The windows always hold their first token, even if it is longer than `crop_len`.
+    let mut best: Option<((usize, bool), Range<usize>)> = None;
+    let (mut end, mut len, mut matches) = (0, 0, 0);
+    for start in 0..tokens.len() {
+        while end < tokens.len() && (end == start || len + cost(&tokens[end]) <= crop_len) {
+            len += cost(&tokens[end]);
+            matches += matched[end] as usize;
+            end += 1;
+        }
+
+        if tokens[start].1.is_separator().is_none() {
+            let score = (matches, sentence_starts[start]);
+            if best.as_ref().map_or(true, |(best, _)| score > *best) {
+                best = Some((score, start..end));
             }
-            out.push_str(word);
-            out
-        })
+        }
+
+        len -= cost(&tokens[start]);
+        matches -= matched[start] as usize;
+    }
+
+    let Range { start, mut end } = match best {
+        Some((_, window)) => window,
+        None => return 0..tokens.len(),
+    };
+
+    if end < tokens.len() {
+        let last_match = (start..end).rev().find(|i| matched[*i]).unwrap_or(start);
+        let sentence_end = (last_match..end)
+            .rev()
+            .find(|i| tokens[*i].1.is_separator() == Some(SeparatorKind::Hard));
+        if let Some(i) = sentence_end {
+            end = i + 1;
+        }
+        while end > start + 1 && tokens[end - 1].1.is_separator() == Some(SeparatorKind::Soft) {
+            end -= 1;
+        }
     }
+
+    start..end
 }
 
-fn parse_filter(facets: &Value, index: &Index, txn: &RoTxn) -> Result<Option<FilterCondition>> {
+/// Returns the range of the tokens of the snippet keeping `crop_len` characters on each side of
+/// the first matched word, or the first `crop_len` characters if no word matches.
+fn first_match_window(
+    tokens: &[(&str, Token)],
+    matcher: &impl Matcher,
+    crop_len: usize,
+) -> Range<usize> {
+    let first_match = tokens
+        .iter()
+        .position(|(_, token)| matcher.matches(token.text()).is_some());
+
+    match first_match {
+        Some(first_match) => {
+            let mut total_len: usize = tokens[..first_match]
+                .iter()
+                .map(|(word, _)| word.len())
+                .sum();
+            let start = (0..first_match)
+                .find(|i| {
+                    total_len -= tokens[*i].0.len();
+                    total_len < crop_len
+                })
+                .unwrap_or(first_match);
+
+            let mut taken_after = 0;
+            let after = tokens[first_match + 1..]
+                .iter()
+                .take_while(|(word, _)| {
+                    let take = taken_after < crop_len;
+                    taken_after += word.chars().count();
+                    take
+                })
+                .count();
+
+            start..first_match + 1 + after
+        }
+        None => {
+            let mut count = 0;
+            let end = tokens
+                .iter()
+                .take_while(|(word, _)| {
+                    let take = count < crop_len;
+                    count += word.len();
+                    take
+                })
+                .count();
+
+            0..end
+        }
+    }
+}
+
+/// Escapes the HTML special characters of `text`, the ampersands and quotes being kept as they are
+/// when `all` is false, as the entities of a stripped value are left untouched.
+fn escape_html(out: &mut String, text: &str, all: bool) {
+    for c in text.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' if all => out.push_str("&amp;"),
+            '"' if all => out.push_str("&quot;"),
+            '\'' if all => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Removes the HTML tags and comments of `s`.
+fn strip_html_tags(s: &str) -> String {
+    lazy_static::lazy_static! {
+        static ref TAG_REGEX: Regex = Regex::new(r"(?s)<!--.*?-->|</?[a-zA-Z][^<>]*>").unwrap();
+    };
+    TAG_REGEX.replace_all(s, "").into_owned()
+}
+
+/// Parses the filter of a query, the numbers and dates being written following the `locale` if
+/// there is one.
+pub(super) fn parse_filter(
+    facets: &Value,
+    index: &Index,
+    txn: &RoTxn,
+    locale: Option<&str>,
+) -> Result<Option<FilterCondition>> {
+    let boolean_fields = boolean_attributes(index, txn)?;
+    let normalized = normalized_filterable_attributes(index, txn)?;
+    let locale = locale.map(FilterLocale::parse).transpose()?;
+    let date_attributes = date_attributes(index, txn)?;
+    let filterable = index.filterable_fields(txn)?;
+    let timestamped: BTreeSet<_> = date_attributes
+        .iter()
+        .filter(|attribute| filterable.contains(&timestamp_field(attribute)))
+        .cloned()
+        .collect();
+    let stop_words = fst::Set::default();
+    let analyzer = collation_analyzer(&stop_words);
+
+    let rewrite = |expr: &str| -> Result<String> {
+        check_filter_syntax(expr).map_err(|error| IndexError::FilterSyntax {
+            filter: expr.to_string(),
+            error,
+        })?;
+        let expr = match locale {
+            Some(ref locale) => localize_conditions(expr, locale, &date_attributes)?,
+            None => expr.to_string(),
+        };
+        let expr = expand_boolean_conditions(&expr, &boolean_fields);
+        let expr = normalize_conditions(&expr, &normalized, |value| {
+            normalize_string(value, &analyzer)
+        });
+        Ok(redirect_date_conditions(&expr, &timestamped))
+    };
+
     match facets {
         Value::String(expr) => {
-            let condition = FilterCondition::from_str(txn, index, expr)?;
+            let condition = FilterCondition::from_str(txn, index, &rewrite(expr)?)?;
             Ok(Some(condition))
         }
-        Value::Array(arr) => parse_filter_array(txn, index, arr),
+        Value::Array(arr) => parse_filter_array(txn, index, arr, rewrite),
         v => Err(FacetError::InvalidExpression(&["Array"], v.clone()).into()),
     }
 }
@@ -658,16 +1657,17 @@ fn parse_filter_array(
     txn: &RoTxn,
     index: &Index,
     arr: &[Value],
+    rewrite: impl Fn(&str) -> Result<String>,
 ) -> Result<Option<FilterCondition>> {
     let mut ands = Vec::new();
     for value in arr {
         match value {
-            Value::String(s) => ands.push(Either::Right(s.clone())),
+            Value::String(s) => ands.push(Either::Right(rewrite(s)?)),
             Value::Array(arr) => {
                 let mut ors = Vec::new();
                 for value in arr {
                     match value {
-                        Value::String(s) => ors.push(s.clone()),
+                        Value::String(s) => ors.push(rewrite(s)?),
                         v => {
                             return Err(FacetError::InvalidExpression(&["String"], v.clone()).into())
                         }
@@ -789,6 +1789,92 @@ mod test {
         assert_eq!(value["author"], "J. R. R. Tolkien");
     }
 
+    #[test]
+    fn formatted_with_highlight_phrase() {
+        let stop_words = fst::Set::default();
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")));
+
+        let mut fields = FieldsIdsMap::new();
+        let title = fields.insert("title").unwrap();
+
+        let mut buf = Vec::new();
+        let mut obkv = obkv::KvWriter::new(&mut buf);
+        obkv.insert(
+            title,
+            Value::String("I love New York. New people, old town.".into())
+                .to_string()
+                .as_bytes(),
+        )
+        .unwrap();
+        obkv.finish().unwrap();
+
+        let obkv = obkv::KvReader::new(&buf);
+
+        let mut formatted_options = BTreeMap::new();
+        formatted_options.insert(
+            title,
+            FormatOptions {
+                highlight: true,
+                crop: None,
+            },
+        );
+
+        // "new york" is a synonym of the "nyc" query word.
+        let mut matching_words = BTreeMap::new();
+        matching_words.insert("new", Some(3));
+        matching_words.insert("york", Some(4));
+        matching_words.insert("town", Some(2));
+
+        let value = format_fields(
+            &fields,
+            obkv,
+            &formatter,
+            &matching_words,
+            &formatted_options,
+        )
+        .unwrap();
+
+        // The phrase is not continued over the end of the sentence.
+        assert_eq!(
+            value["title"],
+            "I love <em>New York</em>. <em>New</em> people, old <em>to</em>wn."
+        );
+    }
+
+    #[test]
+    fn formatted_with_html_formatting() {
+        let stop_words = fst::Set::default();
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+
+        let mut matching_words = BTreeMap::new();
+        matching_words.insert("cats", Some(4));
+
+        let options = FormatOptions {
+            highlight: true,
+            crop: None,
+        };
+        let value = String::from("<b onclick=\"x()\">cats</b> & dogs");
+
+        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")))
+            .html_formatting(HtmlFormatting::Escape);
+        assert_eq!(
+            formatter.format_string(value.clone(), &matching_words, options),
+            "&lt;b onclick=&quot;x()&quot;&gt;<em>cats</em>&lt;/b&gt; &amp; dogs"
+        );
+
+        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")))
+            .html_formatting(HtmlFormatting::Strip);
+        assert_eq!(
+            formatter.format_string(value, &matching_words, options),
+            "<em>cats</em> & dogs"
+        );
+    }
+
     /// https://github.com/meilisearch/MeiliSearch/issues/1368
     #[test]
     fn formatted_with_highlight_emoji() {
@@ -1299,6 +2385,46 @@ mod test {
         assert_eq!(value["author"], "J. K. Rowling");
     }
 
+    #[test]
+    fn formatted_with_crop_best_window() {
+        let stop_words = fst::Set::default();
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+        let formatter = Formatter::new(&analyzer, (String::from("<em>"), String::from("</em>")))
+            .crop_unit(Some(CropUnit::Words));
+
+        let mut matching_words = BTreeMap::new();
+        matching_words.insert("dog", Some(3));
+
+        let text = String::from(
+            "Once upon a time. The dog barks at the cat and the dog runs away. The end.",
+        );
+        let format = |crop| {
+            let options = FormatOptions {
+                highlight: true,
+                crop: Some(crop),
+            };
+            formatter.format_string(text.clone(), &matching_words, options)
+        };
+
+        // The window holding both matches is preferred.
+        assert_eq!(
+            format(8),
+            "<em>dog</em> barks at the cat and the <em>dog</em>"
+        );
+        // The window starting the sentence is preferred.
+        assert_eq!(
+            format(10),
+            "The <em>dog</em> barks at the cat and the <em>dog</em> runs"
+        );
+        // The snippet stops at the end of the sentence.
+        assert_eq!(
+            format(12),
+            "The <em>dog</em> barks at the cat and the <em>dog</em> runs away."
+        );
+    }
+
     #[test]
     fn test_compute_value_matches() {
         let text = "Call me Ishmael. Some years ago—never mind how long precisely—having little or no money in my purse, and nothing particular to interest me on shore, I thought I would sail about a little and see the watery part of the world.";
@@ -1414,4 +2540,108 @@ mod test {
         insert_geo_distance(sorters, &mut document);
         assert_eq!(document.get("_geoDistance"), None);
     }
+
+    #[test]
+    fn test_diversify_hits() {
+        let hits = vec![
+            (Some("a"), 0),
+            (Some("a"), 1),
+            (Some("a"), 2),
+            (Some("b"), 3),
+            (None, 4),
+            (Some("a"), 5),
+        ];
+
+        assert_eq!(diversify_hits(hits.clone(), 1), vec![0, 3, 1, 4, 2, 5]);
+        assert_eq!(diversify_hits(hits.clone(), 2), vec![0, 1, 3, 2, 4, 5]);
+        assert_eq!(diversify_hits(hits, 3), vec![0, 1, 2, 3, 4, 5]);
+
+        // when only one value remains, the run can't be broken.
+        let hits = vec![(Some("a"), 0), (Some("a"), 1), (Some("a"), 2)];
+        assert_eq!(diversify_hits(hits, 1), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_parse_random_sort() {
+        let sort = |rules: &[&str]| rules.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(parse_random_sort(&sort(&["title:asc"])).unwrap(), None);
+        assert_eq!(
+            parse_random_sort(&sort(&["_random(42)"])).unwrap(),
+            Some(42)
+        );
+        assert_eq!(
+            parse_random_sort(&sort(&["_random( 7 )"])).unwrap(),
+            Some(7)
+        );
+        assert_eq!(parse_random_sort(&sort(&["_random(0)"])).unwrap(), Some(0));
+        assert!(parse_random_sort(&sort(&["_random(-1)"])).is_err());
+        assert!(parse_random_sort(&sort(&["_random(18446744073709551616)"])).is_err());
+        assert!(parse_random_sort(&sort(&["_random"])).is_err());
+        assert!(parse_random_sort(&sort(&["_random(42)", "title:asc"])).is_err());
+    }
+
+    #[test]
+    fn test_random_page() {
+        let candidates: RoaringBitmap = (0..1000).step_by(3).collect();
+        let page =
+            |offset, limit| random_page(&candidates, &mut StdRng::seed_from_u64(42), offset, limit);
+
+        let all = page(0, 1000);
+        let mut sorted = all.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, candidates.iter().collect::<Vec<_>>());
+        assert_ne!(all, sorted);
+
+        // The pages follow the same order.
+        assert_eq!(page(0, 10), all[..10]);
+        assert_eq!(page(10, 10), all[10..20]);
+        assert_eq!(page(330, 10), all[330..]);
+        assert!(page(400, 10).is_empty());
+    }
+
+    #[test]
+    fn test_parse_bucketed_facet() {
+        assert_eq!(
+            FacetBuckets::parse_facet("created_at:byMonth").unwrap(),
+            Some(("created_at", FacetBuckets::Date(DateInterval::Month)))
+        );
+        assert_eq!(
+            FacetBuckets::parse_facet("a:b:byDay").unwrap(),
+            Some(("a:b", FacetBuckets::Date(DateInterval::Day)))
+        );
+        assert_eq!(
+            FacetBuckets::parse_facet("price:ranges(0, 50,100.5)").unwrap(),
+            Some(("price", FacetBuckets::Ranges(vec![0.0, 50.0, 100.5])))
+        );
+        assert_eq!(FacetBuckets::parse_facet("created_at").unwrap(), None);
+        assert_eq!(
+            FacetBuckets::parse_facet("created_at:byCentury").unwrap(),
+            None
+        );
+        assert!(FacetBuckets::parse_facet("price:ranges(0)").is_err());
+        assert!(FacetBuckets::parse_facet("price:ranges(50,0)").is_err());
+        assert!(FacetBuckets::parse_facet("price:ranges(0,cheap)").is_err());
+    }
+
+    #[test]
+    fn test_facet_buckets() {
+        let day = FacetBuckets::Date(DateInterval::Day);
+        let month = FacetBuckets::Date(DateInterval::Month);
+        let year = FacetBuckets::Date(DateInterval::Year);
+
+        // 2021-10-15T01:00:00Z
+        let timestamp = 1634259600.0;
+        assert_eq!(day.bucket(1634198400.0).as_deref(), Some("2021-10-14"));
+        assert_eq!(day.bucket(timestamp).as_deref(), Some("2021-10-15"));
+        assert_eq!(month.bucket(timestamp).as_deref(), Some("2021-10"));
+        assert_eq!(year.bucket(timestamp).as_deref(), Some("2021"));
+
+        let ranges = FacetBuckets::Ranges(vec![0.0, 50.0, 100.5]);
+        assert_eq!(ranges.bucket(0.0).as_deref(), Some("0..50"));
+        assert_eq!(ranges.bucket(50.0).as_deref(), Some("50..100.5"));
+        assert_eq!(ranges.bucket(100.5), None);
+        assert_eq!(ranges.bucket(-1.0), None);
+        assert_eq!(ranges.empty_buckets().len(), 2);
+    }
 }
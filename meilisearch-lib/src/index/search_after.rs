@@ -0,0 +1,201 @@
+//! The `searchAfter` pagination of the sorted searches: the sort values of the last hit of a page
+//! are turned into a filter keeping the documents sorted after it, so that the deep pages don't
+//! cost the ranking of all the documents before them, as an `offset` does.
+//!
+//! The documents with the same sort values are ranked by their internal id, that ends the values
+//! so that the pages don't stop in the middle of them.
+
+use std::convert::TryFrom;
+
+use heed::RoTxn;
+use milli::DocumentId;
+use serde_json::Value;
+
+use super::error::{IndexError, Result};
+use super::Index;
+
+/// The attribute and whether the order is ascending of each sort rule.
+fn parse_sort_rules(sort: &[String]) -> Result<Vec<(&str, bool)>> {
+    sort.iter()
+        .map(|rule| match rule.rsplit_once(':') {
+            Some((attribute, _)) if attribute.starts_with('_') => {
+                Err(IndexError::InvalidSearchAfter(format!(
+                    "the sort rule `{}` can't be used to paginate",
+                    rule
+                )))
+            }
+            Some((attribute, "asc")) => Ok((attribute, true)),
+            Some((attribute, "desc")) => Ok((attribute, false)),
+            _ => Err(IndexError::InvalidSearchAfter(format!(
+                "invalid sort rule `{}`",
+                rule
+            ))),
+        })
+        .collect()
+}
+
+/// Where a page of a sorted search starts.
+#[derive(Debug, PartialEq)]
+pub struct SearchAfter {
+    /// The filter keeping the documents sorted after the last hit of the previous page, and the
+    /// ones sorted with the same values when its id is known.
+    pub filter: String,
+    /// The filter of the documents with the same sort values as the last hit, and its id: the
+    /// ones whose id is not greater were already returned.
+    pub ties: Option<(String, DocumentId)>,
+}
+
+/// Where the page after the documents whose sort values are `values` starts, `None` for the first
+/// page. With the rules `a:asc, b:desc`, the documents after `[1, 2]` are the ones matching
+/// `a > 1 OR (a = 1 AND b < 2)`, and the ones after `[1, 2, 7]` are also the ones matching
+/// `a = 1 AND b = 2` whose id is greater than 7.
+pub fn search_after_filter(sort: &[String], values: &[Value]) -> Result<Option<SearchAfter>> {
+    if values.is_empty() {
+        return Ok(None);
+    }
+    let rules = parse_sort_rules(sort)?;
+    let (values, last_id) = match values.len() {
+        len if len == rules.len() => (values, None),
+        len if len == rules.len() + 1 => {
+            let id = values[rules.len()]
+                .as_u64()
+                .and_then(|id| DocumentId::try_from(id).ok())
+                .ok_or_else(|| {
+                    IndexError::InvalidSearchAfter(format!(
+                        "the last value must be the id of a document, found `{}`",
+                        values[rules.len()]
+                    ))
+                })?;
+            (&values[..rules.len()], Some(id))
+        }
+        len => {
+            return Err(IndexError::InvalidSearchAfter(format!(
+                "expected {} values, one for each sort rule and the id of the document, found {}",
+                rules.len() + 1,
+                len
+            )))
+        }
+    };
+    let values = values
+        .iter()
+        .map(|value| match value {
+            Value::Number(number) => Ok(number.to_string()),
+            value => Err(IndexError::InvalidSearchAfter(format!(
+                "the values must be numbers, found `{}`",
+                value
+            ))),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut conditions: Vec<_> = rules
+        .iter()
+        .zip(&values)
+        .enumerate()
+        .map(|(i, ((attribute, ascending), value))| {
+            let operator = if *ascending { ">" } else { "<" };
+            let mut condition: Vec<_> = rules[..i]
+                .iter()
+                .zip(&values)
+                .map(|((attribute, _), value)| format!("{} = {}", attribute, value))
+                .collect();
+            condition.push(format!("{} {} {}", attribute, operator, value));
+            format!("({})", condition.join(" AND "))
+        })
+        .collect();
+
+    let ties = last_id.map(|id| {
+        let equal: Vec<_> = rules
+            .iter()
+            .zip(&values)
+            .map(|((attribute, _), value)| format!("{} = {}", attribute, value))
+            .collect();
+        (equal.join(" AND "), id)
+    });
+    if let Some((ref equal, _)) = ties {
+        conditions.push(format!("({})", equal));
+    }
+
+    Ok(Some(SearchAfter {
+        filter: conditions.join(" OR "),
+        ties,
+    }))
+}
+
+/// Adds the `searchAfter` filter to the filter of the search.
+pub fn and_search_after_filter(filter: Option<Value>, search_after: String) -> Value {
+    match filter {
+        Some(Value::Array(mut elements)) => {
+            elements.push(Value::String(search_after));
+            Value::Array(elements)
+        }
+        Some(filter) => Value::Array(vec![filter, Value::String(search_after)]),
+        None => Value::String(search_after),
+    }
+}
+
+impl Index {
+    /// The sort values of a document followed by its id, to continue the pagination after it.
+    /// `None` if one of them is not a number, the documents after it can't be filtered then.
+    pub(super) fn search_after_values(
+        &self,
+        txn: &RoTxn,
+        sort: &[String],
+        id: DocumentId,
+    ) -> Result<Option<Vec<Value>>> {
+        let fields_ids_map = self.fields_ids_map(txn)?;
+        let (_, obkv) = match self.documents(txn, Some(id))?.into_iter().next() {
+            Some(document) => document,
+            None => return Ok(None),
+        };
+
+        let mut values = Vec::new();
+        for (attribute, _) in parse_sort_rules(sort)? {
+            let value = fields_ids_map
+                .id(attribute)
+                .and_then(|fid| obkv.get(fid))
+                .map(serde_json::from_slice::<Value>)
+                .transpose()?;
+            match value {
+                Some(value @ Value::Number(_)) => values.push(value),
+                _ => return Ok(None),
+            }
+        }
+        values.push(Value::from(id));
+        Ok(Some(values))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn search_after_filters() {
+        let sort = vec![String::from("price:asc"), String::from("id:desc")];
+        assert_eq!(search_after_filter(&sort, &[]).unwrap(), None);
+        assert_eq!(
+            search_after_filter(&sort, &[json!(10.5), json!(3)]).unwrap(),
+            Some(SearchAfter {
+                filter: String::from("(price > 10.5) OR (price = 10.5 AND id < 3)"),
+                ties: None,
+            })
+        );
+        assert_eq!(
+            search_after_filter(&sort, &[json!(10.5), json!(3), json!(7)]).unwrap(),
+            Some(SearchAfter {
+                filter: String::from(
+                    "(price > 10.5) OR (price = 10.5 AND id < 3) OR (price = 10.5 AND id = 3)"
+                ),
+                ties: Some((String::from("price = 10.5 AND id = 3"), 7)),
+            })
+        );
+
+        assert!(search_after_filter(&sort, &[json!(10)]).is_err());
+        assert!(search_after_filter(&sort, &[json!(10), json!(3), json!(-1)]).is_err());
+        assert!(search_after_filter(&sort, &[json!("10"), json!(3)]).is_err());
+        let geo = vec![String::from("_geoPoint(0,0):asc")];
+        assert!(search_after_filter(&geo, &[json!(1)]).is_err());
+    }
+}
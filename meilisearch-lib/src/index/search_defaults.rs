@@ -0,0 +1,89 @@
+//! The search parameters of an index applied to the searches that omit them, for the clients not
+//! to all repeat the same parameters in each of their requests.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::custom_settings::search_defaults;
+use super::error::{IndexError, Result};
+use super::{Index, MatchingStrategy};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SearchDefaults {
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub attributes_to_highlight: Option<BTreeSet<String>>,
+    #[serde(default)]
+    pub crop_length: Option<usize>,
+    #[serde(default)]
+    pub matching_strategy: Option<MatchingStrategy>,
+}
+
+impl SearchDefaults {
+    pub fn validate(&self) -> Result<()> {
+        if self.limit == Some(0) {
+            return Err(IndexError::InvalidSearchDefaults(String::from(
+                "the default `limit` must be greater than 0",
+            )));
+        }
+        if self.crop_length == Some(0) {
+            return Err(IndexError::InvalidSearchDefaults(String::from(
+                "the default `cropLength` must be greater than 0",
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl Index {
+    pub fn search_defaults(&self) -> Result<SearchDefaults> {
+        let txn = self.read_txn()?;
+        search_defaults(self, &txn)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::index::SearchQuery;
+
+    #[test]
+    fn defaults_only_fill_the_omitted_parameters() {
+        let defaults: SearchDefaults = serde_json::from_value(json!({
+            "limit": 5,
+            "attributesToHighlight": ["title"],
+            "matchingStrategy": "all",
+        }))
+        .unwrap();
+        assert!(defaults.validate().is_ok());
+
+        let query: SearchQuery =
+            serde_json::from_value(json!({ "q": "hello", "limit": 10 })).unwrap();
+        let query = query.with_defaults(&defaults);
+        assert_eq!(query.limit(), 10);
+        assert_eq!(
+            query.attributes_to_highlight,
+            Some(std::iter::once(String::from("title")).collect())
+        );
+        assert_eq!(query.crop_length(), crate::index::DEFAULT_CROP_LENGTH);
+        assert_eq!(query.matching_strategy, Some(MatchingStrategy::All));
+
+        let query: SearchQuery = serde_json::from_value(json!({ "q": "hello" })).unwrap();
+        assert_eq!(query.with_defaults(&defaults).limit(), 5);
+    }
+
+    #[test]
+    fn zero_defaults_are_rejected() {
+        let defaults = SearchDefaults {
+            limit: Some(0),
+            ..Default::default()
+        };
+        assert!(defaults.validate().is_err());
+    }
+}
@@ -0,0 +1,75 @@
+//! The `attributesToSearchOn` of a search, restricting it to some of the searchable attributes
+//! without changing the settings of the index. milli searches all the searchable attributes, the
+//! candidates that don't match a word of the query in one of the restricted attributes, according
+//! to the positions milli indexed the words at, are removed afterwards, the ranking of the
+//! remaining ones being left as it is.
+
+use heed::RoTxn;
+use milli::{relative_from_absolute_position, DocumentId, FieldId, MatchingWords};
+
+use super::collation::is_hidden_field;
+use super::error::{IndexError, Result};
+use super::Index;
+
+impl Index {
+    /// The ids of the attributes a search is restricted to, `None` when it is restricted to `*`,
+    /// all the searchable attributes.
+    pub(super) fn search_on_fields_ids(
+        &self,
+        txn: &RoTxn,
+        attributes: &[String],
+    ) -> Result<Option<Vec<FieldId>>> {
+        if attributes.iter().any(|attribute| attribute == "*") {
+            return Ok(None);
+        }
+
+        let searchable = self.searchable_fields(txn)?;
+        let fields_ids_map = self.fields_ids_map(txn)?;
+        let mut fids = Vec::new();
+        for attribute in attributes {
+            let is_searchable = match searchable {
+                Some(ref searchable) => searchable.contains(&attribute.as_str()),
+                None => !is_hidden_field(attribute),
+            };
+            if !is_searchable {
+                return Err(IndexError::AttributeNotSearchable(attribute.clone()));
+            }
+            // An attribute no document holds yet matches nothing.
+            if let Some(fid) = fields_ids_map.id(attribute) {
+                fids.push(fid);
+            }
+        }
+        Ok(Some(fids))
+    }
+
+    /// Returns the documents having none of the `matching_words` in the `fids` fields, read from
+    /// the positions of the words milli indexed, each of them holding the attribute it is in.
+    pub(super) fn search_on_rejects(
+        &self,
+        txn: &RoTxn,
+        ids: impl IntoIterator<Item = DocumentId>,
+        fids: &[FieldId],
+        matching_words: &MatchingWords,
+    ) -> Result<Vec<DocumentId>> {
+        let mut rejected = Vec::new();
+        for id in ids {
+            let mut matched = false;
+            for entry in self.docid_word_positions.prefix_iter(txn, &(id, ""))? {
+                let ((_, word), positions) = entry?;
+                if matching_words.matching_bytes(word).is_some()
+                    && positions
+                        .iter()
+                        .any(|position| fids.contains(&relative_from_absolute_position(position).0))
+                {
+                    matched = true;
+                    break;
+                }
+            }
+            if !matched {
+                rejected.push(id);
+            }
+        }
+
+        Ok(rejected)
+    }
+}
@@ -0,0 +1,270 @@
+//! The typo tolerance of the searches. milli can only disable the typos altogether, the sizes of
+//! the words from which it tolerates one and two typos being hardcoded to 5 and 9: the stricter
+//! settings are applied by rejecting the documents in which a query word only matches with more
+//! typos than allowed, according to the words milli indexed for them. Only the ranked documents
+//! fetched for the page are checked, the random orders and the scrolls checking all their
+//! candidates. The sizes can't be lowered below the ones of milli, that would tolerate typos milli
+//! never looks for.
+
+use std::collections::{BTreeSet, HashSet};
+
+use heed::RoTxn;
+use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder, DFA};
+use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
+use milli::{relative_from_absolute_position, DocumentId, FieldId};
+use serde::{Deserialize, Serialize};
+
+use super::error::{IndexError, Result};
+use super::query_debug::max_typos;
+use super::Index;
+
+/// The sizes from which milli tolerates one and two typos in a word.
+const DEFAULT_ONE_TYPO_WORD_SIZE: u8 = 5;
+const DEFAULT_TWO_TYPOS_WORD_SIZE: u8 = 9;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TypoTolerance {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub min_word_size_for_typos: MinWordSizeForTypos,
+    /// The query words that must be matched exactly.
+    #[serde(default)]
+    pub disable_on_words: BTreeSet<String>,
+    /// The attributes in which the query words must be matched exactly.
+    #[serde(default)]
+    pub disable_on_attributes: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct MinWordSizeForTypos {
+    #[serde(default = "default_one_typo")]
+    pub one_typo: u8,
+    #[serde(default = "default_two_typos")]
+    pub two_typos: u8,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_one_typo() -> u8 {
+    DEFAULT_ONE_TYPO_WORD_SIZE
+}
+
+fn default_two_typos() -> u8 {
+    DEFAULT_TWO_TYPOS_WORD_SIZE
+}
+
+impl Default for TypoTolerance {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_word_size_for_typos: MinWordSizeForTypos::default(),
+            disable_on_words: BTreeSet::new(),
+            disable_on_attributes: BTreeSet::new(),
+        }
+    }
+}
+
+impl Default for MinWordSizeForTypos {
+    fn default() -> Self {
+        Self {
+            one_typo: DEFAULT_ONE_TYPO_WORD_SIZE,
+            two_typos: DEFAULT_TWO_TYPOS_WORD_SIZE,
+        }
+    }
+}
+
+impl TypoTolerance {
+    pub(super) fn validate(&self) -> Result<()> {
+        let MinWordSizeForTypos {
+            one_typo,
+            two_typos,
+        } = self.min_word_size_for_typos;
+        if one_typo < DEFAULT_ONE_TYPO_WORD_SIZE {
+            return Err(IndexError::InvalidTypoTolerance(format!(
+                "`minWordSizeForTypos.oneTypo` ({}) can't be lower than {}, the size from which typos are looked for",
+                one_typo, DEFAULT_ONE_TYPO_WORD_SIZE
+            )));
+        }
+        if two_typos < DEFAULT_TWO_TYPOS_WORD_SIZE {
+            return Err(IndexError::InvalidTypoTolerance(format!(
+                "`minWordSizeForTypos.twoTypos` ({}) can't be lower than {}, the size from which two typos are looked for",
+                two_typos, DEFAULT_TWO_TYPOS_WORD_SIZE
+            )));
+        }
+        if one_typo > two_typos {
+            return Err(IndexError::InvalidTypoTolerance(format!(
+                "`minWordSizeForTypos.oneTypo` ({}) must be less than or equal to `minWordSizeForTypos.twoTypos` ({})",
+                one_typo, two_typos
+            )));
+        }
+        Ok(())
+    }
+
+    /// The number of typos tolerated in a query word.
+    fn max_typos(&self, word: &str) -> u8 {
+        if !self.enabled || self.disable_on_words.contains(word) {
+            return 0;
+        }
+        match word.chars().count() {
+            len if len >= self.min_word_size_for_typos.two_typos as usize => 2,
+            len if len >= self.min_word_size_for_typos.one_typo as usize => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// A query word milli may match with more typos than the tolerance allows.
+struct CheckedWord {
+    /// Matches the word with the typos milli tolerates.
+    milli: DFA,
+    /// Matches the word with the typos the tolerance allows.
+    tolerant: DFA,
+    /// Matches the word without typos.
+    exact: DFA,
+}
+
+/// The check of the query words milli may match with more typos than the tolerance allows.
+pub(super) struct TypoCheck {
+    checked: Vec<CheckedWord>,
+    /// The attributes in which the query words must be matched exactly.
+    exact_fids: HashSet<FieldId>,
+}
+
+impl Index {
+    /// The check of the words of `query`, none when milli can't match any of them with more typos
+    /// than `tolerance` allows. Unless `all_words` must match, only the first word of the query is
+    /// checked: milli can ignore the other ones, the documents still matching the first one.
+    pub(super) fn typo_check(
+        &self,
+        txn: &RoTxn,
+        query: &str,
+        tolerance: &TypoTolerance,
+        all_words: bool,
+    ) -> Result<Option<TypoCheck>> {
+        let stop_words = match self.stop_words(txn)? {
+            Some(stop_words) => stop_words.map_data(|bytes| bytes.to_vec())?,
+            None => fst::Set::default(),
+        };
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+
+        let fields_ids_map = self.fields_ids_map(txn)?;
+        let exact_fids: HashSet<_> = tolerance
+            .disable_on_attributes
+            .iter()
+            .filter_map(|attribute| fields_ids_map.id(attribute))
+            .collect();
+
+        let analyzed = analyzer.analyze(query);
+        let tokens: Vec<_> = analyzed
+            .tokens()
+            .filter(|token| token.is_word() || token.is_stopword())
+            .collect();
+        let mut checked = Vec::new();
+        let mut first_word = true;
+        for (position, token) in tokens.iter().enumerate() {
+            if !token.is_word() {
+                continue;
+            }
+            let optional = !first_word && !all_words;
+            first_word = false;
+
+            let word = token.text();
+            let allowed = tolerance.max_typos(word);
+            let milli_typos = max_typos(word);
+            if optional || allowed >= milli_typos && (exact_fids.is_empty() || milli_typos == 0) {
+                continue;
+            }
+
+            // The last word of the query is a prefix while it is being typed.
+            let is_prefix = position + 1 == tokens.len() && token.byte_end == query.len();
+            let dfa = |typos| {
+                let builder = LevenshteinAutomatonBuilder::new(typos, true);
+                if is_prefix {
+                    builder.build_prefix_dfa(word)
+                } else {
+                    builder.build_dfa(word)
+                }
+            };
+            checked.push(CheckedWord {
+                milli: dfa(milli_typos),
+                tolerant: dfa(allowed),
+                exact: dfa(0),
+            });
+        }
+
+        if checked.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(TypoCheck {
+            checked,
+            exact_fids,
+        }))
+    }
+}
+
+impl TypoCheck {
+    /// Whether a checked word only matches the document `id` with more typos than allowed.
+    pub(super) fn rejects(&self, index: &Index, txn: &RoTxn, id: DocumentId) -> Result<bool> {
+        let matches = |dfa: &DFA, word: &str| matches!(dfa.eval(word), Distance::Exact(_));
+        // Whether each checked word is matched by milli, and within the tolerance.
+        let mut matched = vec![(false, false); self.checked.len()];
+        for entry in index.docid_word_positions.prefix_iter(txn, &(id, ""))? {
+            let ((_, word), positions) = entry?;
+            for (checked, (by_milli, tolerated)) in self.checked.iter().zip(matched.iter_mut()) {
+                if *tolerated || !matches(&checked.milli, word) {
+                    continue;
+                }
+                *by_milli = true;
+                let in_tolerant_field = positions.iter().any(|position| {
+                    !self
+                        .exact_fids
+                        .contains(&relative_from_absolute_position(position).0)
+                });
+                *tolerated = matches(&checked.exact, word)
+                    || in_tolerant_field && matches(&checked.tolerant, word);
+            }
+        }
+        Ok(matched
+            .iter()
+            .any(|(by_milli, tolerated)| *by_milli && !tolerated))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn max_typos_of_words() {
+        let mut tolerance = TypoTolerance::default();
+        for word in &["dog", "hello", "beautiful"] {
+            assert_eq!(tolerance.max_typos(word), max_typos(word));
+        }
+
+        tolerance.min_word_size_for_typos.one_typo = 6;
+        tolerance.disable_on_words.insert(String::from("hello"));
+        assert_eq!(tolerance.max_typos("hello"), 0);
+        assert_eq!(tolerance.max_typos("world"), 0);
+        assert_eq!(tolerance.max_typos("worlds"), 1);
+        assert!(tolerance.validate().is_ok());
+
+        tolerance.min_word_size_for_typos.one_typo = 10;
+        assert!(tolerance.validate().is_err());
+
+        // milli never looks for the typos of the shorter words.
+        tolerance.min_word_size_for_typos.one_typo = 3;
+        assert!(tolerance.validate().is_err());
+        tolerance.min_word_size_for_typos = MinWordSizeForTypos {
+            one_typo: 5,
+            two_typos: 8,
+        };
+        assert!(tolerance.validate().is_err());
+    }
+}
@@ -4,6 +4,8 @@ use rayon::ThreadPool;
 
 use crate::options::IndexerOpts;
 
+use super::quotas::DocumentQuotas;
+
 pub struct UpdateHandler {
     max_nb_chunks: Option<usize>,
     chunk_compression_level: Option<u32>,
@@ -11,12 +13,20 @@ pub struct UpdateHandler {
     log_frequency: usize,
     max_memory: Option<usize>,
     chunk_compression_type: CompressionType,
+    max_facet_cardinality: Option<u64>,
+    document_quotas: DocumentQuotas,
+    deterministic: bool,
+    max_search_depth: Option<usize>,
+    max_search_limit: Option<usize>,
+    max_search_facets: Option<usize>,
+    max_attributes_to_retrieve: Option<usize>,
+    max_batch_size: usize,
 }
 
 impl UpdateHandler {
     pub fn new(opt: &IndexerOpts) -> anyhow::Result<Self> {
         let thread_pool = rayon::ThreadPoolBuilder::new()
-            .num_threads(opt.indexing_jobs.unwrap_or(num_cpus::get() / 2))
+            .num_threads(opt.indexing_threads())
             .build()?;
 
         Ok(Self {
@@ -26,9 +36,57 @@ impl UpdateHandler {
             log_frequency: opt.log_every_n,
             max_memory: opt.max_memory.map(|m| m.get_bytes() as usize),
             chunk_compression_type: opt.chunk_compression_type,
+            max_facet_cardinality: opt.max_facet_cardinality,
+            document_quotas: DocumentQuotas::new(opt),
+            deterministic: opt.deterministic_indexing,
+            max_search_depth: opt.max_search_depth,
+            max_search_limit: opt.max_search_limit,
+            max_search_facets: opt.max_search_facets,
+            max_attributes_to_retrieve: opt.max_attributes_to_retrieve,
+            max_batch_size: opt.batch_size(),
         })
     }
 
+    /// The maximum number of distinct values of a filterable attribute, the updates exceeding it
+    /// fail.
+    pub fn max_facet_cardinality(&self) -> Option<u64> {
+        self.max_facet_cardinality
+    }
+
+    pub fn document_quotas(&self) -> DocumentQuotas {
+        self.document_quotas
+    }
+
+    /// Whether the databases built from the same updates must return the same search results.
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// The maximum `offset + limit` of a search.
+    pub fn max_search_depth(&self) -> Option<usize> {
+        self.max_search_depth
+    }
+
+    /// The maximum number of hits of a search.
+    pub fn max_search_limit(&self) -> Option<usize> {
+        self.max_search_limit
+    }
+
+    /// The maximum number of attributes of the facets distribution of a search.
+    pub fn max_search_facets(&self) -> Option<usize> {
+        self.max_search_facets
+    }
+
+    /// The maximum number of attributes of the hits of a search.
+    pub fn max_attributes_to_retrieve(&self) -> Option<usize> {
+        self.max_attributes_to_retrieve
+    }
+
+    /// The maximum number of consecutive document additions of an index indexed together.
+    pub fn max_batch_size(&self) -> usize {
+        self.max_batch_size
+    }
+
     pub fn update_builder(&self, update_id: u64) -> UpdateBuilder {
         // We prepare the update by using the update builder.
         let mut update_builder = UpdateBuilder::new(update_id);
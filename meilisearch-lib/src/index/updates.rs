@@ -1,18 +1,43 @@
 use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{Seek, SeekFrom};
 use std::marker::PhantomData;
 use std::num::NonZeroUsize;
 
+use chrono::{DateTime, Utc};
 use log::{debug, info, trace};
-use milli::documents::DocumentBatchReader;
-use milli::update::{IndexDocumentsMethod, Setting, UpdateBuilder};
+use milli::documents::{DocumentBatchBuilder, DocumentBatchReader};
+use milli::obkv_to_json;
+use milli::update::{DocumentAdditionResult, IndexDocumentsMethod, Setting, UpdateBuilder};
 use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
 use uuid::Uuid;
 
 use crate::index_controller::updates::status::{Failed, Processed, Processing, UpdateResult};
 use crate::Update;
 
+use super::collation::{
+    collation_analyzer, insert_collation_keys, insert_normalized_keys, insert_numeric_texts,
+    is_hidden_field, is_timestamp_field, timestamp_field, update_searchable_fields,
+};
+use super::custom_settings::{
+    apply_custom_settings, attribute_patterns, composite_primary_key, date_attributes,
+    flatten_strategy, normalized_filterable_attributes, put_attribute_patterns,
+    put_composite_primary_key, put_last_indexed_at, put_searchable_wildcard,
+    searchable_numeric_attributes, sort_collation,
+};
 use super::error::{IndexError, Result};
-use super::{Index, IndexMeta};
+use super::merge_operators::{is_merge_operator, MergedDocuments};
+use super::nested::flatten_document;
+use super::search::parse_filter;
+use super::validation::infer_primary_key;
+use super::{
+    FlattenStrategy, Index, IndexMeta, PrimaryKey, SearchDefaults, SortCollation, TypoTolerance,
+    COMPOSITE_ID_FIELD,
+};
+
+/// Separates the values of the attributes of a composite primary key in the synthesized ids.
+pub(super) const COMPOSITE_ID_SEPARATOR: &str = "-";
 
 fn serialize_with_wildcard<S>(
     field: &Setting<Vec<String>>,
@@ -70,6 +95,26 @@ pub struct Settings<T> {
     pub synonyms: Setting<BTreeMap<String, Vec<String>>>,
     #[serde(default, skip_serializing_if = "Setting::is_not_set")]
     pub distinct_attribute: Setting<String>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub date_attributes: Setting<BTreeSet<String>>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub flatten_strategy: Setting<FlattenStrategy>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub sort_collation: Setting<SortCollation>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub normalized_filterable_attributes: Setting<BTreeSet<String>>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub searchable_numeric_attributes: Setting<BTreeSet<String>>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub raw_attributes: Setting<BTreeSet<String>>,
+    /// Whether a copy of the index is kept before each settings update, to roll it back.
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub checkpoint_before_settings_change: Setting<bool>,
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub typo_tolerance: Setting<TypoTolerance>,
+    /// The search parameters applied when the searches omit them.
+    #[serde(default, skip_serializing_if = "Setting::is_not_set")]
+    pub search_defaults: Setting<SearchDefaults>,
 
     #[serde(skip)]
     pub _kind: PhantomData<T>,
@@ -86,6 +131,15 @@ impl Settings<Checked> {
             stop_words: Setting::Reset,
             synonyms: Setting::Reset,
             distinct_attribute: Setting::Reset,
+            date_attributes: Setting::Reset,
+            flatten_strategy: Setting::Reset,
+            sort_collation: Setting::Reset,
+            normalized_filterable_attributes: Setting::Reset,
+            searchable_numeric_attributes: Setting::Reset,
+            raw_attributes: Setting::Reset,
+            checkpoint_before_settings_change: Setting::Reset,
+            typo_tolerance: Setting::Reset,
+            search_defaults: Setting::Reset,
             _kind: PhantomData,
         }
     }
@@ -100,6 +154,15 @@ impl Settings<Checked> {
             stop_words,
             synonyms,
             distinct_attribute,
+            date_attributes,
+            flatten_strategy,
+            sort_collation,
+            normalized_filterable_attributes,
+            searchable_numeric_attributes,
+            raw_attributes,
+            checkpoint_before_settings_change,
+            typo_tolerance,
+            search_defaults,
             ..
         } = self;
 
@@ -112,6 +175,15 @@ impl Settings<Checked> {
             stop_words,
             synonyms,
             distinct_attribute,
+            date_attributes,
+            flatten_strategy,
+            sort_collation,
+            normalized_filterable_attributes,
+            searchable_numeric_attributes,
+            raw_attributes,
+            checkpoint_before_settings_change,
+            typo_tolerance,
+            search_defaults,
             _kind: PhantomData,
         }
     }
@@ -150,11 +222,77 @@ impl Settings<Unchecked> {
             stop_words: self.stop_words,
             synonyms: self.synonyms,
             distinct_attribute: self.distinct_attribute,
+            date_attributes: self.date_attributes,
+            flatten_strategy: self.flatten_strategy,
+            sort_collation: self.sort_collation,
+            normalized_filterable_attributes: self.normalized_filterable_attributes,
+            searchable_numeric_attributes: self.searchable_numeric_attributes,
+            raw_attributes: self.raw_attributes,
+            checkpoint_before_settings_change: self.checkpoint_before_settings_change,
+            typo_tolerance: self.typo_tolerance,
+            search_defaults: self.search_defaults,
             _kind: PhantomData,
         }
     }
 }
 
+/// Returns the prefix of the attributes matched by an attribute pattern, `*` matching every
+/// attribute and `meta.*` the attributes nested under `meta`, or `None` if `pattern` is a plain
+/// attribute name.
+pub(super) fn pattern_prefix(pattern: &str) -> Result<Option<&str>> {
+    if !pattern.contains('*') {
+        return Ok(None);
+    }
+
+    let prefix = pattern.strip_suffix('*').unwrap_or(pattern);
+    if prefix.contains('*') || !(prefix.is_empty() || prefix.ends_with('.')) {
+        return Err(IndexError::InvalidAttributePattern(pattern.to_string()));
+    }
+
+    Ok(Some(prefix))
+}
+
+/// Whether some of `attributes` are patterns, checking that they are valid ones.
+fn contain_attribute_patterns<'a>(
+    attributes: impl IntoIterator<Item = &'a String>,
+) -> Result<bool> {
+    let mut contain = false;
+    for attribute in attributes {
+        contain |= pattern_prefix(attribute)?.is_some();
+    }
+    Ok(contain)
+}
+
+/// Replaces the patterns by the attributes they match among `fields`. A pattern can match nothing
+/// yet, the patterns being expanded again each time documents are indexed.
+fn expand_attribute_patterns<'a, C>(
+    patterns: impl IntoIterator<Item = &'a String>,
+    fields: &[&str],
+) -> Result<C>
+where
+    C: std::iter::FromIterator<String>,
+{
+    let mut expanded = Vec::new();
+    for pattern in patterns {
+        match pattern_prefix(pattern)? {
+            Some(prefix) => expanded.extend(
+                fields
+                    .iter()
+                    .filter(|field| field.starts_with(prefix) && field.len() > prefix.len())
+                    .map(|field| field.to_string()),
+            ),
+            None => expanded.push(pattern.clone()),
+        }
+    }
+
+    // The patterns can match attributes that were also listed explicitly.
+    let mut seen = BTreeSet::new();
+    Ok(expanded
+        .into_iter()
+        .filter(|field| seen.insert(field.clone()))
+        .collect())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
@@ -164,23 +302,53 @@ pub struct Facets {
 }
 
 impl Index {
-    pub fn handle_update(&self, update: Processing) -> std::result::Result<Processed, Failed> {
+    /// Applies an update, `other_documents` being the number of documents of the other indexes,
+    /// that count in the instance quota of the document additions.
+    ///
+    /// `is_canceled` is checked at the checkpoints of the processing, the update stopping with
+    /// [`IndexError::Canceled`], without committing anything, once it returns `true`.
+    pub fn handle_update(
+        &self,
+        update: Processing,
+        other_documents: u64,
+        is_canceled: impl Fn() -> bool,
+    ) -> std::result::Result<Processed, Failed> {
         let update_id = update.id();
         let update_builder = self.update_handler.update_builder(update_id);
+        let check_canceled = || {
+            if is_canceled() {
+                Err(IndexError::Canceled)
+            } else {
+                Ok(())
+            }
+        };
         let result = (|| {
+            check_canceled()?;
+            if let Update::Settings(settings) = update.meta() {
+                if self.needs_checkpoint(&settings.clone().check())? {
+                    self.checkpoint(update_id)?;
+                }
+            }
             let mut txn = self.write_txn()?;
             let result = match update.meta() {
                 Update::DocumentAddition {
                     primary_key,
                     content_uuid,
                     method,
-                } => self.update_documents(
-                    &mut txn,
-                    *method,
-                    *content_uuid,
-                    update_builder,
-                    primary_key.as_deref(),
-                ),
+                } => self
+                    .update_documents(
+                        &mut txn,
+                        *method,
+                        &[*content_uuid],
+                        update_builder,
+                        primary_key.as_deref(),
+                        &check_canceled,
+                    )
+                    .map(|counts| {
+                        UpdateResult::DocumentsAddition(DocumentAdditionResult {
+                            nb_documents: counts[0],
+                        })
+                    }),
                 Update::Settings(settings) => {
                     let settings = settings.clone().check();
                     self.update_settings(&mut txn, &settings, update_builder)
@@ -191,6 +359,10 @@ impl Index {
                     Ok(UpdateResult::Other)
                 }
                 Update::DeleteDocuments(ids) => {
+                    let ids = ids
+                        .iter()
+                        .map(|id| self.normalize_document_id(&txn, id.clone()))
+                        .collect::<Result<Vec<_>>>()?;
                     let mut builder = update_builder.delete_documents(&mut txn, self)?;
 
                     // We ignore unexisting document ids
@@ -201,23 +373,208 @@ impl Index {
                     let deleted = builder.execute()?;
                     Ok(UpdateResult::DocumentDeletion { deleted })
                 }
+                Update::DeleteByFilter { filter } => {
+                    self.delete_by_filter(&mut txn, filter, update_builder)
+                }
             };
+            let result = result.and_then(|result| {
+                check_canceled()?;
+                let warnings = self.finish_update(&mut txn, update.meta(), other_documents)?;
+                check_canceled()?;
+                Ok((result, warnings))
+            });
             if result.is_ok() {
                 txn.commit()?;
             }
             result
         })();
 
-        if let Update::DocumentAddition { content_uuid, .. } = update.from.meta() {
+        // The payload of a failed addition is kept, for it to be inspected.
+        if let (Update::DocumentAddition { content_uuid, .. }, Ok(_)) =
+            (update.from.meta(), &result)
+        {
             let _ = self.update_file_store.delete(*content_uuid);
         }
 
         match result {
-            Ok(result) => Ok(update.process(result)),
+            Ok((result, warnings)) => Ok(update.process(result, warnings)),
             Err(e) => Err(update.fail(e)),
         }
     }
 
+    /// Applies consecutive document additions made with the same method in a single indexing
+    /// pass, returning the result of each of them in the order of their ids. When the batch
+    /// fails, its additions are applied one by one for only the faulty ones to fail.
+    ///
+    /// `is_canceled` tells whether one of the additions was canceled, the batch stopping at its
+    /// next checkpoint to be indexed again without it.
+    pub fn handle_batch(
+        &self,
+        updates: Vec<Processing>,
+        other_documents: u64,
+        is_canceled: impl Fn(u64) -> bool,
+    ) -> Vec<std::result::Result<Processed, Failed>> {
+        let mut results = Vec::with_capacity(updates.len());
+        let mut batch = updates;
+        loop {
+            let (canceled, rest): (Vec<_>, Vec<_>) = batch
+                .into_iter()
+                .partition(|update| is_canceled(update.id()));
+            results.extend(
+                canceled
+                    .into_iter()
+                    .map(|update| Err(update.fail(IndexError::Canceled))),
+            );
+            batch = rest;
+
+            if batch.len() <= 1 {
+                for update in batch {
+                    let id = update.id();
+                    results.push(self.handle_update(update, other_documents, || is_canceled(id)));
+                }
+                break;
+            }
+
+            let ids: Vec<_> = batch.iter().map(Processing::id).collect();
+            let check_canceled = || {
+                if ids.iter().any(|id| is_canceled(*id)) {
+                    Err(IndexError::Canceled)
+                } else {
+                    Ok(())
+                }
+            };
+            match self.index_batch(&batch, other_documents, &check_canceled) {
+                Ok((counts, warnings)) => {
+                    for (update, nb_documents) in batch.into_iter().zip(counts) {
+                        if let Update::DocumentAddition { content_uuid, .. } = update.meta() {
+                            let _ = self.update_file_store.delete(*content_uuid);
+                        }
+                        let result = UpdateResult::DocumentsAddition(DocumentAdditionResult {
+                            nb_documents,
+                        });
+                        results.push(Ok(update.process(result, warnings.clone())));
+                    }
+                    break;
+                }
+                // Nothing was committed, the batch is indexed again without the canceled additions.
+                Err(IndexError::Canceled) => continue,
+                Err(e) => {
+                    info!(
+                        "batch of {} document additions failed, indexing them one by one: {}",
+                        batch.len(),
+                        e
+                    );
+                    for update in batch {
+                        let id = update.id();
+                        results
+                            .push(self.handle_update(update, other_documents, || is_canceled(id)));
+                    }
+                    break;
+                }
+            }
+        }
+
+        results.sort_by_key(|result| match result {
+            Ok(processed) => processed.id(),
+            Err(failed) => failed.id(),
+        });
+        results
+    }
+
+    /// Indexes the documents of the additions of a batch together, returning the number of
+    /// documents of each of them and the warnings of the indexing.
+    fn index_batch(
+        &self,
+        updates: &[Processing],
+        other_documents: u64,
+        check_canceled: &dyn Fn() -> Result<()>,
+    ) -> Result<(Vec<usize>, Vec<String>)> {
+        let mut content_uuids = Vec::with_capacity(updates.len());
+        let mut method = IndexDocumentsMethod::ReplaceDocuments;
+        let mut primary_key = None;
+        for update in updates {
+            if let Update::DocumentAddition {
+                content_uuid,
+                method: update_method,
+                primary_key: update_primary_key,
+            } = update.meta()
+            {
+                content_uuids.push(*content_uuid);
+                method = *update_method;
+                primary_key = primary_key.or_else(|| update_primary_key.as_deref());
+            }
+        }
+
+        check_canceled()?;
+        let update_builder = self.update_handler.update_builder(updates[0].id());
+        let mut txn = self.write_txn()?;
+        let counts = self.update_documents(
+            &mut txn,
+            method,
+            &content_uuids,
+            update_builder,
+            primary_key,
+            check_canceled,
+        )?;
+        check_canceled()?;
+        let warnings = self.finish_update(&mut txn, updates[0].meta(), other_documents)?;
+        check_canceled()?;
+        txn.commit()?;
+
+        Ok((counts, warnings))
+    }
+
+    /// Checks the quotas and updates the statistics of the index once `meta` is applied, returning
+    /// the warnings of the update.
+    fn finish_update<'a, 'b>(
+        &'a self,
+        txn: &mut heed::RwTxn<'a, 'b>,
+        meta: &Update,
+        other_documents: u64,
+    ) -> Result<Vec<String>> {
+        if let Update::DocumentAddition { .. } = meta {
+            let documents = self.number_of_documents(txn)?;
+            self.document_quotas().check(documents, other_documents)?;
+        }
+        if !matches!(meta, Update::Settings(_)) {
+            put_last_indexed_at(self, txn, Some(Utc::now()))?;
+        }
+        let warnings = match meta {
+            // The cardinalities are left as an upper bound by the deletions.
+            Update::DeleteDocuments(_) | Update::DeleteByFilter { .. } => Vec::new(),
+            Update::Settings(settings) if settings.filterable_attributes.is_not_set() => Vec::new(),
+            _ => {
+                self.update_boolean_attributes(txn)?;
+                self.update_facet_cardinalities(txn, self.update_handler.max_facet_cardinality())?
+            }
+        };
+        Ok(warnings)
+    }
+
+    /// Sets the primary key of a newly created index.
+    pub fn init_primary_key(&self, primary_key: PrimaryKey) -> Result<()> {
+        let (primary_key, composite_fields) = match primary_key {
+            PrimaryKey::Single(primary_key) => (primary_key, None),
+            PrimaryKey::Composite(fields) if fields.is_empty() => {
+                return Err(IndexError::EmptyCompositePrimaryKey)
+            }
+            PrimaryKey::Composite(fields) => (COMPOSITE_ID_FIELD.to_string(), Some(fields)),
+        };
+
+        let mut txn = self.write_txn()?;
+        let mut builder = UpdateBuilder::new(0).settings(&mut txn, self);
+        builder.set_primary_key(primary_key);
+        builder.execute(|_, _| ())?;
+
+        if let Some(fields) = composite_fields {
+            put_composite_primary_key(self, &mut txn, &fields)?;
+        }
+
+        txn.commit()?;
+
+        Ok(())
+    }
+
     pub fn update_primary_key(&self, primary_key: Option<String>) -> Result<IndexMeta> {
         match primary_key {
             Some(primary_key) => {
@@ -243,10 +600,11 @@ impl Index {
         &'a self,
         txn: &mut heed::RwTxn<'a, 'b>,
         method: IndexDocumentsMethod,
-        content_uuid: Uuid,
+        content_uuids: &[Uuid],
         update_builder: UpdateBuilder,
         primary_key: Option<&str>,
-    ) -> Result<UpdateResult> {
+        check_canceled: &dyn Fn() -> Result<()>,
+    ) -> Result<Vec<usize>> {
         trace!("performing document addition");
 
         // Set the primary key if not set already, ignore if already set.
@@ -259,16 +617,148 @@ impl Index {
         let indexing_callback =
             |indexing_step, update_id| debug!("update {}: {:?}", update_id, indexing_step);
 
-        let content_file = self.update_file_store.get_update(content_uuid).unwrap();
+        let mut content_files: Vec<_> = content_uuids
+            .iter()
+            .map(|uuid| self.update_file_store.get_update(*uuid).unwrap())
+            .collect();
+
+        let date_attributes = date_attributes(self, txn)?;
+        let composite_primary_key = composite_primary_key(self, txn)?;
+        let flatten = flatten_strategy(self, txn)? == FlattenStrategy::Flatten;
+        let collated_fields = match sort_collation(self, txn)? {
+            SortCollation::Unicode => Some(
+                self.user_sortable_fields(txn)?
+                    .difference(&date_attributes)
+                    .cloned()
+                    .collect(),
+            ),
+            SortCollation::Binary => None,
+        };
+        let normalized_fields = normalized_filterable_attributes(self, txn)?;
+        let numeric_fields = searchable_numeric_attributes(self, txn)?;
+        let stop_words = fst::Set::default();
+        let analyzer = collation_analyzer(&stop_words);
+
+        // The merge operators are only evaluated by the partial updates.
+        let merge = contain_merge_operators(&mut content_files)?;
+        if merge && !matches!(method, IndexDocumentsMethod::UpdateDocuments) {
+            return Err(IndexError::MergeOperatorsInReplacement);
+        }
+
+        // The payloads of a batch are merged while they are transformed.
+        let (content_file, counts) = if merge
+            || !date_attributes.is_empty()
+            || composite_primary_key.is_some()
+            || flatten
+            || collated_fields.is_some()
+            || !normalized_fields.is_empty()
+            || !numeric_fields.is_empty()
+            || content_files.len() > 1
+        {
+            let mut merged_documents = MergedDocuments::default();
+            let index_primary_key = self.primary_key(txn)?.map(String::from);
+            let fields_ids_map = self.fields_ids_map(txn)?;
+            let external_ids = self.external_documents_ids(txn)?;
+            let stored_document = |id: &str| -> Result<Option<Map<String, Value>>> {
+                let internal_id = match external_ids.get(id) {
+                    Some(internal_id) => internal_id,
+                    None => return Ok(None),
+                };
+                let fields: Vec<_> = fields_ids_map.ids().collect();
+                match self.documents(txn, std::iter::once(internal_id))?.pop() {
+                    Some((_, document)) => {
+                        Ok(Some(obkv_to_json(&fields, &fields_ids_map, document)?))
+                    }
+                    None => Ok(None),
+                }
+            };
+
+            let (content_file, counts) = transform_documents(content_files, |document| {
+                check_canceled()?;
+                // The operators are evaluated on the raw values, against the ids of the index.
+                if merge {
+                    let id = match composite_primary_key {
+                        Some(ref fields) if flatten => {
+                            let mut flattened = document.clone();
+                            flatten_document(&mut flattened);
+                            Some(Value::String(composite_id(&flattened, fields)?))
+                        }
+                        Some(ref fields) => Some(Value::String(composite_id(document, fields)?)),
+                        None => index_primary_key
+                            .clone()
+                            .or_else(|| infer_primary_key(document))
+                            .and_then(|key| document.get(&key).cloned()),
+                    };
+                    merged_documents.merge(document, id.as_ref(), stored_document)?;
+                }
+                if !date_attributes.is_empty() {
+                    insert_timestamps(document, &date_attributes)?;
+                }
+                if flatten {
+                    flatten_document(document);
+                }
+                if let Some(ref fields) = collated_fields {
+                    insert_collation_keys(document, fields, &analyzer);
+                }
+                if !normalized_fields.is_empty() {
+                    insert_normalized_keys(document, &normalized_fields, &analyzer);
+                }
+                if !numeric_fields.is_empty() {
+                    insert_numeric_texts(document, &numeric_fields);
+                }
+                if let Some(ref fields) = composite_primary_key {
+                    insert_composite_id(document, fields)?;
+                }
+                Ok(())
+            })?;
+            (content_file, Some(counts))
+        } else {
+            (content_files.remove(0), None)
+        };
+
         let reader = DocumentBatchReader::from_reader(content_file).unwrap();
 
+        // milli can't be interrupted once it indexes the documents.
+        check_canceled()?;
         let mut builder = update_builder.index_documents(txn, self);
         builder.index_documents_method(method);
         let addition = builder.execute(reader, indexing_callback)?;
 
+        // The documents can bring attributes matched by the patterns of the settings, or by the
+        // searchable attributes given as `*`.
+        self.resolve_settings_patterns(txn)?;
+        update_searchable_fields(self, txn)?;
+
         info!("document addition done: {:?}", addition);
 
-        Ok(UpdateResult::DocumentsAddition(addition))
+        Ok(counts.unwrap_or_else(|| vec![addition.nb_documents]))
+    }
+
+    fn delete_by_filter<'a, 'b>(
+        &'a self,
+        txn: &mut heed::RwTxn<'a, 'b>,
+        filter: &Value,
+        update_builder: UpdateBuilder,
+    ) -> Result<UpdateResult> {
+        trace!("performing deletion by filter");
+
+        let condition =
+            parse_filter(filter, self, txn, None)?.ok_or(IndexError::EmptyDeletionFilter)?;
+        // A placeholder search gives us all the documents matching the filter.
+        let candidates = {
+            let mut search = self.search(txn);
+            search.limit(0);
+            search.filter(condition);
+            search.execute()?.candidates
+        };
+
+        let mut builder = update_builder.delete_documents(txn, self)?;
+        candidates.iter().for_each(|id| builder.delete_document(id));
+        let deleted = builder.execute()?;
+
+        info!("deletion by filter done: {} documents deleted", deleted);
+
+        Ok(UpdateResult::DocumentDeletion { deleted })
     }
 
     fn update_settings<'a, 'b>(
@@ -277,6 +767,14 @@ impl Index {
         settings: &Settings<Checked>,
         update_builder: UpdateBuilder,
     ) -> Result<UpdateResult> {
+        let settings = &self.expand_settings_patterns(txn, settings)?;
+        if let Setting::Set(ref typo_tolerance) = settings.typo_tolerance {
+            typo_tolerance.validate()?;
+        }
+        if let Setting::Set(ref search_defaults) = settings.search_defaults {
+            search_defaults.validate()?;
+        }
+
         // We must use the write transaction of the update here.
         let mut builder = update_builder.settings(txn, self);
 
@@ -286,10 +784,306 @@ impl Index {
             debug!("update {}: {:?}", update_id, indexing_step)
         })?;
 
+        apply_custom_settings(self, txn, settings)?;
+        // The searchable attributes given to milli are the user ones again.
+        if !settings.searchable_attributes.is_not_set() {
+            put_searchable_wildcard(self, txn, false)?;
+        }
+
+        if !settings.date_attributes.is_not_set() {
+            self.update_timestamps(txn)?;
+        }
+
+        if !settings.sortable_attributes.is_not_set() || !settings.sort_collation.is_not_set() {
+            self.update_collation_keys(txn)?;
+        }
+
+        if !settings.filterable_attributes.is_not_set()
+            || !settings.normalized_filterable_attributes.is_not_set()
+        {
+            self.update_normalized_keys(txn)?;
+        }
+
+        if !settings.searchable_attributes.is_not_set()
+            || !settings.searchable_numeric_attributes.is_not_set()
+        {
+            self.update_numeric_texts(txn)?;
+        }
+
         Ok(UpdateResult::Other)
     }
 }
 
+/// Records the patterns of an attribute setting, forgetting the previous ones when the setting is
+/// given without patterns.
+fn record_patterns<C>(recorded: &mut Option<C>, setting: &Setting<C>) -> Result<()>
+where
+    C: Clone,
+    for<'a> &'a C: IntoIterator<Item = &'a String>,
+{
+    match setting {
+        Setting::Set(attributes) => {
+            *recorded = if contain_attribute_patterns(attributes)? {
+                Some(attributes.clone())
+            } else {
+                None
+            };
+        }
+        Setting::Reset => *recorded = None,
+        Setting::NotSet => (),
+    }
+    Ok(())
+}
+
+impl Index {
+    /// Records the attribute patterns of the displayed, searchable and filterable attributes,
+    /// and expands them against the attributes currently known by the index.
+    fn expand_settings_patterns<'a, 'b>(
+        &'a self,
+        txn: &mut heed::RwTxn<'a, 'b>,
+        settings: &Settings<Checked>,
+    ) -> Result<Settings<Checked>> {
+        let mut patterns = attribute_patterns(self, txn)?;
+        record_patterns(&mut patterns.displayed, &settings.displayed_attributes)?;
+        record_patterns(&mut patterns.searchable, &settings.searchable_attributes)?;
+        record_patterns(&mut patterns.filterable, &settings.filterable_attributes)?;
+        put_attribute_patterns(self, txn, &patterns)?;
+
+        let fields_ids_map = self.fields_ids_map(txn)?;
+        let fields = pattern_fields(&fields_ids_map);
+
+        let mut settings = settings.clone();
+        if let Setting::Set(ref mut displayed) = settings.displayed_attributes {
+            *displayed = expand_attribute_patterns(displayed.iter(), &fields)?;
+        }
+        if let Setting::Set(ref mut searchable) = settings.searchable_attributes {
+            *searchable = expand_attribute_patterns(searchable.iter(), &fields)?;
+        }
+        if let Setting::Set(ref mut filterable) = settings.filterable_attributes {
+            *filterable = expand_attribute_patterns(filterable.iter(), &fields)?;
+        }
+
+        Ok(settings)
+    }
+
+    /// Expands the recorded attribute patterns against the attributes of the index, updating the
+    /// settings whose attributes changed.
+    fn resolve_settings_patterns<'a, 'b>(&'a self, txn: &mut heed::RwTxn<'a, 'b>) -> Result<()> {
+        let patterns = attribute_patterns(self, txn)?;
+        if patterns.is_empty() {
+            return Ok(());
+        }
+
+        let fields_ids_map = self.fields_ids_map(txn)?;
+        let fields = pattern_fields(&fields_ids_map);
+
+        let displayed = match patterns.displayed {
+            Some(ref displayed) => {
+                let expanded: Vec<String> = expand_attribute_patterns(displayed, &fields)?;
+                let current: Option<Vec<String>> = self
+                    .displayed_fields(txn)?
+                    .map(|fields| fields.into_iter().map(String::from).collect());
+                Some(expanded).filter(|expanded| Some(expanded) != current.as_ref())
+            }
+            None => None,
+        };
+        let searchable = match patterns.searchable {
+            Some(ref searchable) => {
+                let expanded: Vec<String> = expand_attribute_patterns(searchable, &fields)?;
+                let current = self.user_searchable_fields(txn)?;
+                Some(expanded).filter(|expanded| Some(expanded) != current.as_ref())
+            }
+            None => None,
+        };
+        let filterable = match patterns.filterable {
+            Some(ref filterable) => {
+                let expanded: BTreeSet<String> = expand_attribute_patterns(filterable, &fields)?;
+                let current = self.user_filterable_fields(txn)?;
+                Some(expanded).filter(|expanded| *expanded != current)
+            }
+            None => None,
+        };
+        if displayed.is_none() && searchable.is_none() && filterable.is_none() {
+            return Ok(());
+        }
+
+        let mut builder = UpdateBuilder::new(0).settings(txn, self);
+        if let Some(ref displayed) = displayed {
+            builder.set_displayed_fields(displayed.clone());
+        }
+        if let Some(ref searchable) = searchable {
+            builder.set_searchable_fields(searchable.clone());
+        }
+        if let Some(ref filterable) = filterable {
+            builder.set_filterable_fields(filterable.iter().cloned().collect());
+        }
+        builder.execute(|_, _| ())?;
+
+        if filterable.is_some() {
+            self.update_normalized_keys(txn)?;
+        }
+        if searchable.is_some() {
+            self.update_numeric_texts(txn)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The attributes the patterns of the settings can match.
+fn pattern_fields(fields_ids_map: &milli::FieldsIdsMap) -> Vec<&str> {
+    fields_ids_map
+        .iter()
+        .map(|(_, name)| name)
+        .filter(|name| !is_hidden_field(name))
+        .collect()
+}
+
+/// Rewrites the documents batches contained in `content_files` into a single one, applying
+/// `transform` to each of their documents. Returns it with the number of documents of each of
+/// the batches.
+fn transform_documents(
+    content_files: Vec<File>,
+    mut transform: impl FnMut(&mut Map<String, Value>) -> Result<()>,
+) -> Result<(File, Vec<usize>)> {
+    let mut file = tempfile::tempfile()?;
+    let mut builder = DocumentBatchBuilder::new(&mut file)?;
+    let mut counts = Vec::with_capacity(content_files.len());
+
+    let mut document = Map::new();
+    for content_file in content_files {
+        let mut reader = DocumentBatchReader::from_reader(content_file)?;
+        let mut count = 0;
+        while let Some((index, obkv)) = reader.next_document_with_index()? {
+            for (field_id, content) in obkv.iter() {
+                if let Some(field_name) = index.get_by_left(&field_id) {
+                    document.insert(field_name.to_string(), serde_json::from_slice(content)?);
+                }
+            }
+
+            transform(&mut document)?;
+            builder.add_documents(&document)?;
+            document.clear();
+            count += 1;
+        }
+        counts.push(count);
+    }
+
+    builder.finish()?;
+    file.seek(SeekFrom::Start(0))?;
+
+    Ok((file, counts))
+}
+
+/// Whether a document of the `content_files` has a merge operator, the files being rewound once
+/// they are read.
+fn contain_merge_operators(content_files: &mut [File]) -> Result<bool> {
+    let mut found = false;
+    for content_file in content_files {
+        if !found {
+            let mut reader = DocumentBatchReader::from_reader(&mut *content_file)?;
+            while let Some((_, obkv)) = reader.next_document_with_index()? {
+                for (_, content) in obkv.iter() {
+                    // Only the objects can be operators, the other values aren't parsed.
+                    if content.first() == Some(&b'{')
+                        && is_merge_operator(&serde_json::from_slice(content)?)
+                    {
+                        found = true;
+                        break;
+                    }
+                }
+                if found {
+                    break;
+                }
+            }
+        }
+        content_file.seek(SeekFrom::Start(0))?;
+    }
+
+    Ok(found)
+}
+
+/// Replaces the timestamps of the document by the unix timestamps of the RFC3339 dates of its
+/// `date_attributes`, so they can be filtered and sorted on as numbers while the dates are kept
+/// as they were given. Values that already are numbers are considered to be timestamps.
+pub(super) fn insert_timestamps(
+    document: &mut Map<String, Value>,
+    date_attributes: &BTreeSet<String>,
+) -> Result<()> {
+    fn timestamp(value: &Value) -> std::result::Result<Value, String> {
+        match value {
+            Value::String(date) => match DateTime::parse_from_rfc3339(date) {
+                Ok(date) => Ok(Value::from(date.timestamp())),
+                Err(_) => Err(date.clone()),
+            },
+            Value::Array(values) => values.iter().map(timestamp).collect(),
+            value => Ok(value.clone()),
+        }
+    }
+
+    let stale_timestamps: Vec<_> = document
+        .keys()
+        .filter(|name| is_timestamp_field(name))
+        .cloned()
+        .collect();
+    for name in stale_timestamps {
+        document.remove(&name);
+    }
+
+    for attribute in date_attributes {
+        if let Some(value) = document.get(attribute) {
+            let timestamp = timestamp(value).map_err(|value| IndexError::InvalidDate {
+                attribute: attribute.clone(),
+                value,
+            })?;
+            document.insert(timestamp_field(attribute), timestamp);
+        }
+    }
+
+    Ok(())
+}
+
+/// Inserts the id synthesized from the values of the `fields` of the composite primary key in the
+/// document. The values must be positive integers or strings only composed of alphanumeric
+/// characters and underscores, so that two different sets of values can't give the same id.
+pub(super) fn insert_composite_id(
+    document: &mut Map<String, Value>,
+    fields: &[String],
+) -> Result<()> {
+    let id = composite_id(document, fields)?;
+    document.insert(COMPOSITE_ID_FIELD.to_string(), Value::String(id));
+
+    Ok(())
+}
+
+/// The id synthesized from the values of the `fields` of the composite primary key.
+fn composite_id(document: &Map<String, Value>, fields: &[String]) -> Result<String> {
+    let mut values = Vec::with_capacity(fields.len());
+    for field in fields {
+        let value = match document.get(field) {
+            Some(Value::Number(number)) if number.is_u64() => number.to_string(),
+            Some(Value::String(string))
+                if !string.is_empty()
+                    && string
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+            {
+                string.clone()
+            }
+            Some(value) => {
+                return Err(IndexError::InvalidCompositeIdValue {
+                    field: field.clone(),
+                    value: value.to_string(),
+                })
+            }
+            None => return Err(IndexError::MissingCompositeIdField(field.clone())),
+        };
+        values.push(value);
+    }
+
+    Ok(values.join(COMPOSITE_ID_SEPARATOR))
+}
+
 pub fn apply_settings_to_builder(
     settings: &Settings<Checked>,
     builder: &mut milli::update::Settings,
@@ -349,6 +1143,38 @@ pub fn apply_settings_to_builder(
 mod test {
     use super::*;
 
+    #[test]
+    fn test_expand_attribute_patterns() {
+        let fields = ["title", "meta.author", "meta.year", "metadata"];
+        let patterns = |patterns: &[&str]| -> Vec<String> {
+            patterns.iter().map(|pattern| pattern.to_string()).collect()
+        };
+
+        let expanded: Vec<String> =
+            expand_attribute_patterns(&patterns(&["title", "meta.*"]), &fields).unwrap();
+        assert_eq!(expanded, vec!["title", "meta.author", "meta.year"]);
+
+        let expanded: BTreeSet<String> =
+            expand_attribute_patterns(&patterns(&["meta.year", "*"]), &fields).unwrap();
+        assert_eq!(expanded.len(), 4);
+
+        // The patterns that match nothing yet are kept for the next documents.
+        let expanded: Vec<String> =
+            expand_attribute_patterns(&patterns(&["title", "tags.*"]), &fields).unwrap();
+        assert_eq!(expanded, vec!["title"]);
+        let expanded: Vec<String> = expand_attribute_patterns(&patterns(&["*"]), &[]).unwrap();
+        assert!(expanded.is_empty());
+
+        assert!(contain_attribute_patterns(&patterns(&["title", "meta.*"])).unwrap());
+        assert!(!contain_attribute_patterns(&patterns(&["title", "meta"])).unwrap());
+        for invalid in &["met*", "*.year", "meta.*.*"] {
+            assert!(matches!(
+                expand_attribute_patterns::<Vec<String>>(&patterns(&[invalid]), &fields),
+                Err(IndexError::InvalidAttributePattern(_))
+            ));
+        }
+    }
+
     #[test]
     fn test_setting_check() {
         // test no changes
@@ -361,6 +1187,15 @@ mod test {
             stop_words: Setting::NotSet,
             synonyms: Setting::NotSet,
             distinct_attribute: Setting::NotSet,
+            date_attributes: Setting::NotSet,
+            flatten_strategy: Setting::NotSet,
+            sort_collation: Setting::NotSet,
+            normalized_filterable_attributes: Setting::NotSet,
+            searchable_numeric_attributes: Setting::NotSet,
+            raw_attributes: Setting::NotSet,
+            checkpoint_before_settings_change: Setting::NotSet,
+            typo_tolerance: Setting::NotSet,
+            search_defaults: Setting::NotSet,
             _kind: PhantomData::<Unchecked>,
         };
 
@@ -382,6 +1217,15 @@ mod test {
             stop_words: Setting::NotSet,
             synonyms: Setting::NotSet,
             distinct_attribute: Setting::NotSet,
+            date_attributes: Setting::NotSet,
+            flatten_strategy: Setting::NotSet,
+            sort_collation: Setting::NotSet,
+            normalized_filterable_attributes: Setting::NotSet,
+            searchable_numeric_attributes: Setting::NotSet,
+            raw_attributes: Setting::NotSet,
+            checkpoint_before_settings_change: Setting::NotSet,
+            typo_tolerance: Setting::NotSet,
+            search_defaults: Setting::NotSet,
             _kind: PhantomData::<Unchecked>,
         };
 
@@ -389,4 +1233,49 @@ mod test {
         assert_eq!(checked.displayed_attributes, Setting::Reset);
         assert_eq!(checked.searchable_attributes, Setting::Reset);
     }
+
+    #[test]
+    fn test_insert_timestamps() {
+        let date_attributes = vec![String::from("created_at"), String::from("dates")]
+            .into_iter()
+            .collect();
+
+        let mut document = serde_json::json!({
+            "created_at": "2021-10-14T10:00:00+02:00",
+            "dates": ["1970-01-01T00:00:00Z", 42],
+            "other": "2021-10-14T10:00:00Z",
+        });
+        insert_timestamps(document.as_object_mut().unwrap(), &date_attributes).unwrap();
+        assert_eq!(
+            document,
+            serde_json::json!({
+                "created_at": "2021-10-14T10:00:00+02:00",
+                "dates": ["1970-01-01T00:00:00Z", 42],
+                "other": "2021-10-14T10:00:00Z",
+                "_timestamp.created_at": 1634198400,
+                "_timestamp.dates": [0, 42],
+            })
+        );
+
+        let mut document = serde_json::json!({ "created_at": "yesterday" });
+        assert!(insert_timestamps(document.as_object_mut().unwrap(), &date_attributes).is_err());
+    }
+
+    #[test]
+    fn test_insert_composite_id() {
+        let fields = vec![String::from("tenant_id"), String::from("sku")];
+
+        let mut document = serde_json::json!({ "tenant_id": "acme", "sku": 42 });
+        insert_composite_id(document.as_object_mut().unwrap(), &fields).unwrap();
+        assert_eq!(document[COMPOSITE_ID_FIELD], "acme-42");
+
+        let mut document = serde_json::json!({ "tenant_id": "acme" });
+        assert!(insert_composite_id(document.as_object_mut().unwrap(), &fields).is_err());
+
+        let mut document = serde_json::json!({ "tenant_id": "ac-me", "sku": 42 });
+        assert!(insert_composite_id(document.as_object_mut().unwrap(), &fields).is_err());
+
+        let mut document = serde_json::json!({ "tenant_id": "acme", "sku": -42 });
+        assert!(insert_composite_id(document.as_object_mut().unwrap(), &fields).is_err());
+    }
 }
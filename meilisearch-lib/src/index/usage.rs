@@ -0,0 +1,131 @@
+//! The attributes the searches of an index sort on and find the words of their query in, recorded
+//! in memory since the index was opened, for the lints of its settings to report the searchable
+//! and sortable attributes no search uses.
+
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use heed::RoTxn;
+use milli::{relative_from_absolute_position, DocumentId, MatchingWords};
+use parking_lot::Mutex;
+
+use super::collation::{is_hidden_field, numeric_text_attribute};
+use super::error::Result;
+use super::Index;
+
+#[derive(Debug, Default)]
+pub struct SearchUsage {
+    /// The number of searches and sorted listings of the documents recorded.
+    searches: AtomicU64,
+    /// The number of searches recorded whose query had words.
+    word_searches: AtomicU64,
+    sorted: Mutex<BTreeSet<String>>,
+    matched: Mutex<BTreeSet<String>>,
+}
+
+/// What the searches of an index used, as the lints read it.
+#[derive(Debug, Default, Clone)]
+pub struct UsageReport {
+    /// The number of searches and sorted listings of the documents.
+    pub searches: u64,
+    pub word_searches: u64,
+    /// The attributes sorted on, `_geo` for the `_geoPoint` sort rules.
+    pub sorted: BTreeSet<String>,
+    /// The attributes the words of the queries were found in, in the returned documents.
+    pub matched: BTreeSet<String>,
+}
+
+impl SearchUsage {
+    pub fn report(&self) -> UsageReport {
+        UsageReport {
+            searches: self.searches.load(Ordering::Relaxed),
+            word_searches: self.word_searches.load(Ordering::Relaxed),
+            sorted: self.sorted.lock().clone(),
+            matched: self.matched.lock().clone(),
+        }
+    }
+
+    /// Records the attributes of the sort rules of a search or of a listing of the documents.
+    pub(super) fn record_sort(&self, sort: Option<&[String]>) {
+        self.searches.fetch_add(1, Ordering::Relaxed);
+        let attributes = sort.into_iter().flatten().filter_map(|rule| {
+            let (attribute, _) = rule.rsplit_once(':')?;
+            if attribute.starts_with("_geoPoint(") {
+                Some("_geo")
+            } else if attribute.starts_with("_random") {
+                None
+            } else {
+                Some(attribute)
+            }
+        });
+        let mut sorted = self.sorted.lock();
+        for attribute in attributes {
+            if !sorted.contains(attribute) {
+                sorted.insert(attribute.to_string());
+            }
+        }
+    }
+}
+
+impl Index {
+    /// Records the usage of a search returning the `hits`. The attributes the words of the query
+    /// are in are read from the positions of the words of the hits, as long as some of the
+    /// `searchable` attributes were never matched.
+    pub(super) fn record_search_usage(
+        &self,
+        txn: &RoTxn,
+        sort: Option<&[String]>,
+        has_words: bool,
+        hits: &[DocumentId],
+        matching_words: &MatchingWords,
+    ) -> Result<()> {
+        self.usage.record_sort(sort);
+        if !has_words {
+            return Ok(());
+        }
+        self.usage.word_searches.fetch_add(1, Ordering::Relaxed);
+
+        let fields_ids_map = self.fields_ids_map(txn)?;
+        let searchable: Vec<_> = match self.searchable_fields(txn)? {
+            Some(searchable) => searchable.into_iter().map(String::from).collect(),
+            None => fields_ids_map
+                .iter()
+                .map(|(_, name)| name.to_string())
+                .collect(),
+        };
+        let all_matched = {
+            let matched = self.usage.matched.lock();
+            searchable
+                .iter()
+                .filter(|attribute| !is_hidden_field(attribute))
+                .all(|attribute| matched.contains(attribute))
+        };
+        if all_matched {
+            return Ok(());
+        }
+
+        let mut fids = BTreeSet::new();
+        for id in hits {
+            for entry in self.docid_word_positions.prefix_iter(txn, &(*id, ""))? {
+                let ((_, word), positions) = entry?;
+                if matching_words.matching_bytes(word).is_some() {
+                    fids.extend(
+                        positions
+                            .iter()
+                            .map(|position| relative_from_absolute_position(position).0),
+                    );
+                }
+            }
+        }
+
+        let mut matched = self.usage.matched.lock();
+        for fid in fids {
+            if let Some(name) = fields_ids_map.name(fid) {
+                let attribute = numeric_text_attribute(name).unwrap_or(name);
+                matched.insert(attribute.to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
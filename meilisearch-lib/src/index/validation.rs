@@ -0,0 +1,276 @@
+//! The validation of the documents of an addition without indexing them, reporting the documents
+//! the addition would reject so that the data exports can be checked before being pushed.
+//!
+//! The documents are checked as they are read from the payload: their ids, their size and number
+//! of fields, the values of the date attributes and the values of the typed CSV columns. The types
+//! of the other values aren't checked, the indexing accepting any JSON value for them.
+
+use std::collections::BTreeSet;
+
+use meilisearch_error::ErrorCode;
+use milli::UserError;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::custom_settings::{composite_primary_key, date_attributes};
+use super::error::{IndexError, Result};
+use super::updates::{insert_composite_id, insert_timestamps};
+use super::Index;
+use crate::document_formats::{DocumentFormatError, DocumentLimits};
+
+/// The field a primary key is inferred from must contain this.
+const DEFAULT_PRIMARY_KEY_NAME: &str = "id";
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentIssue {
+    /// The position of the document in the payload.
+    pub position: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_id: Option<Value>,
+    pub error_code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentsValidation {
+    pub valid: bool,
+    pub documents: usize,
+    pub primary_key: String,
+    pub errors: Vec<DocumentIssue>,
+}
+
+/// What the documents added to an index are checked against, the default for a new index.
+#[derive(Debug, Default)]
+pub struct DocumentSchema {
+    pub primary_key: Option<String>,
+    pub composite_primary_key: Option<Vec<String>>,
+    pub date_attributes: BTreeSet<String>,
+}
+
+impl Index {
+    pub fn document_schema(&self) -> Result<DocumentSchema> {
+        let txn = self.read_txn()?;
+        Ok(DocumentSchema {
+            primary_key: self.primary_key(&txn)?.map(String::from),
+            composite_primary_key: composite_primary_key(self, &txn)?,
+            date_attributes: date_attributes(self, &txn)?,
+        })
+    }
+}
+
+/// Checks the documents of a payload one at a time as their addition would.
+pub struct DocumentsValidator {
+    schema: DocumentSchema,
+    primary_key: Option<String>,
+    limits: DocumentLimits,
+    documents: usize,
+    errors: Vec<DocumentIssue>,
+}
+
+impl DocumentsValidator {
+    /// `primary_key` is the primary key of the addition, only used if the index has none yet.
+    pub fn new(
+        schema: DocumentSchema,
+        primary_key: Option<String>,
+        limits: DocumentLimits,
+    ) -> Self {
+        let primary_key = schema.primary_key.clone().or(primary_key);
+        Self {
+            schema,
+            primary_key,
+            limits,
+            documents: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Checks the next document of the payload, or reports the error it couldn't be read with.
+    pub fn push(&mut self, document: std::result::Result<Map<String, Value>, DocumentFormatError>) {
+        let position = self.documents;
+        self.documents += 1;
+
+        let document = match document {
+            Ok(document) => document,
+            Err(e) => {
+                self.errors.push(DocumentIssue {
+                    position,
+                    document_id: None,
+                    error_code: e.error_name(),
+                    message: e.to_string(),
+                });
+                return;
+            }
+        };
+        // The primary key is inferred from the first document, as the indexing does.
+        if position == 0 && self.primary_key.is_none() {
+            self.primary_key = infer_primary_key(&document);
+        }
+        let primary_key = match self.primary_key {
+            Some(ref primary_key) => primary_key,
+            None => return,
+        };
+
+        let document_id = match self.schema.composite_primary_key {
+            Some(_) => None,
+            None => document.get(primary_key).cloned(),
+        };
+        if let Err(issue) =
+            validate_document(position, &document, &self.schema, primary_key, self.limits)
+        {
+            self.errors.push(DocumentIssue {
+                position,
+                document_id,
+                ..issue
+            });
+        }
+    }
+
+    pub fn finish(self) -> Result<DocumentsValidation> {
+        let primary_key = self
+            .primary_key
+            .ok_or_else(|| IndexError::Milli(UserError::MissingPrimaryKey.into()))?;
+
+        Ok(DocumentsValidation {
+            valid: self.errors.is_empty(),
+            documents: self.documents,
+            primary_key,
+            errors: self.errors,
+        })
+    }
+}
+
+fn validate_document(
+    position: usize,
+    document: &Map<String, Value>,
+    schema: &DocumentSchema,
+    primary_key: &str,
+    limits: DocumentLimits,
+) -> std::result::Result<(), DocumentIssue> {
+    let issue = |error_code: String, message: String| DocumentIssue {
+        position,
+        document_id: None,
+        error_code,
+        message,
+    };
+    let index_issue = |e: IndexError| issue(e.error_name(), e.to_string());
+
+    limits
+        .check(position, document)
+        .map_err(|e| issue(e.error_name(), e.to_string()))?;
+
+    let mut document = document.clone();
+    match schema.composite_primary_key {
+        Some(ref fields) => insert_composite_id(&mut document, fields).map_err(index_issue)?,
+        None => match document.get(primary_key) {
+            Some(id) if is_valid_document_id(id) => (),
+            Some(id) => {
+                let error = UserError::InvalidDocumentId {
+                    document_id: id.clone(),
+                };
+                return Err(index_issue(IndexError::Milli(error.into())));
+            }
+            None => {
+                let error = UserError::MissingDocumentId {
+                    document: document.clone(),
+                };
+                return Err(index_issue(IndexError::Milli(error.into())));
+            }
+        },
+    }
+    insert_timestamps(&mut document, &schema.date_attributes).map_err(index_issue)?;
+
+    Ok(())
+}
+
+/// The ids are integers, or strings only composed of alphanumeric characters, hyphens (-) and
+/// underscores (_).
+fn is_valid_document_id(id: &Value) -> bool {
+    match id {
+        Value::Number(number) => number.is_i64() || number.is_u64(),
+        Value::String(string) => {
+            !string.is_empty()
+                && string
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        }
+        _ => false,
+    }
+}
+
+/// The first field of the first document whose name contains `id`, as the indexing infers it.
+pub(super) fn infer_primary_key(document: &Map<String, Value>) -> Option<String> {
+    document
+        .keys()
+        .find(|field| field.to_lowercase().contains(DEFAULT_PRIMARY_KEY_NAME))
+        .cloned()
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn validate(
+        documents: Value,
+        schema: DocumentSchema,
+        primary_key: Option<String>,
+    ) -> Result<DocumentsValidation> {
+        let documents: Vec<Map<String, Value>> = serde_json::from_value(documents).unwrap();
+        let mut validator = DocumentsValidator::new(schema, primary_key, DocumentLimits::default());
+        documents
+            .into_iter()
+            .for_each(|document| validator.push(Ok(document)));
+        validator.finish()
+    }
+
+    #[test]
+    fn report_invalid_documents() {
+        let schema = DocumentSchema {
+            date_attributes: std::iter::once(String::from("released")).collect(),
+            ..Default::default()
+        };
+        let documents = json!([
+            { "id": 1, "released": "2021-10-12T00:00:00Z" },
+            { "id": "a b" },
+            { "title": "no id" },
+            { "id": 4, "released": "yesterday" },
+        ]);
+
+        let validation = validate(documents, schema, None).unwrap();
+        assert!(!validation.valid);
+        assert_eq!(validation.primary_key, "id");
+        assert_eq!(validation.documents, 4);
+        let errors: Vec<_> = validation
+            .errors
+            .iter()
+            .map(|e| (e.position, e.error_code.as_str()))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (1, "invalid_document_id"),
+                (2, "missing_document_id"),
+                (3, "invalid_date"),
+            ]
+        );
+        assert_eq!(validation.errors[0].document_id, Some(json!("a b")));
+    }
+
+    #[test]
+    fn index_primary_key_takes_precedence() {
+        let schema = DocumentSchema {
+            primary_key: Some(String::from("sku")),
+            ..Default::default()
+        };
+        let documents = json!([{ "sku": "a1", "id": 1 }]);
+        let validation = validate(documents, schema, Some(String::from("id"))).unwrap();
+        assert!(validation.valid);
+        assert_eq!(validation.primary_key, "sku");
+
+        let documents = json!([{ "title": "Carol" }]);
+        assert!(validate(documents, DocumentSchema::default(), None).is_err());
+    }
+}
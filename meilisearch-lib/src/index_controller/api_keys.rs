@@ -0,0 +1,501 @@
+//! The API keys created with the master key, each of them allowed to perform some actions on some
+//! indexes until it expires. The indexes of a key are uids, or prefixes of uids ending with `*`,
+//! `*` alone standing for all the indexes.
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs::{create_dir_all, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use heed::types::{SerdeJson, Str};
+use heed::{Database, Env, EnvOpenOptions};
+use meilisearch_error::{Code, ErrorCode};
+use milli::update::Setting;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::io_throttle::{compacted_env_copy, create_file, IoThrottle};
+
+const API_KEYS_DB_PATH: &str = "api_keys";
+const API_KEYS_DUMP_FILE: &str = "data.jsonl";
+const API_KEYS_STORE_SIZE: usize = 104_857_600; // 100MiB
+const API_KEY_LENGTH: usize = 32;
+/// The index pattern of the keys allowed on all the indexes.
+const WILDCARD: &str = "*";
+
+pub type Result<T> = std::result::Result<T, ApiKeyError>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiKeyError {
+    #[error("API key `{0}` not found.")]
+    ApiKeyNotFound(String),
+    #[error("Invalid API key: {0}")]
+    InvalidApiKey(String),
+    #[error("Internal error: {0}")]
+    Internal(Box<dyn Error + Send + Sync + 'static>),
+}
+
+internal_error!(ApiKeyError: heed::Error, std::io::Error, serde_json::Error);
+
+impl ErrorCode for ApiKeyError {
+    fn error_code(&self) -> Code {
+        match self {
+            ApiKeyError::ApiKeyNotFound(_) => Code::ApiKeyNotFound,
+            ApiKeyError::InvalidApiKey(_) => Code::InvalidApiKey,
+            ApiKeyError::Internal(_) => Code::Internal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    #[serde(rename = "*")]
+    All,
+    #[serde(rename = "search")]
+    Search,
+    #[serde(rename = "documents.add")]
+    DocumentsAdd,
+    #[serde(rename = "documents.get")]
+    DocumentsGet,
+    #[serde(rename = "documents.delete")]
+    DocumentsDelete,
+    #[serde(rename = "indexes.create")]
+    IndexesCreate,
+    #[serde(rename = "indexes.get")]
+    IndexesGet,
+    #[serde(rename = "indexes.update")]
+    IndexesUpdate,
+    #[serde(rename = "indexes.delete")]
+    IndexesDelete,
+    #[serde(rename = "tasks.get")]
+    TasksGet,
+    #[serde(rename = "tasks.cancel")]
+    TasksCancel,
+    #[serde(rename = "tasks.delete")]
+    TasksDelete,
+    #[serde(rename = "settings.get")]
+    SettingsGet,
+    #[serde(rename = "settings.update")]
+    SettingsUpdate,
+    #[serde(rename = "stats.get")]
+    StatsGet,
+    #[serde(rename = "dumps.create")]
+    DumpsCreate,
+    #[serde(rename = "dumps.get")]
+    DumpsGet,
+    #[serde(rename = "version")]
+    Version,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKey {
+    pub key: String,
+    pub description: Option<String>,
+    pub actions: BTreeSet<Action>,
+    pub indexes: BTreeSet<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CreateApiKey {
+    #[serde(default)]
+    pub description: Option<String>,
+    pub actions: BTreeSet<Action>,
+    pub indexes: BTreeSet<String>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// The changes of a key, `null` removing its description or its expiration date.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PatchApiKey {
+    #[serde(default)]
+    pub description: Setting<String>,
+    #[serde(default)]
+    pub actions: Option<BTreeSet<Action>>,
+    #[serde(default)]
+    pub indexes: Option<BTreeSet<String>>,
+    #[serde(default)]
+    pub expires_at: Setting<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    fn validate(&self) -> Result<()> {
+        if self.actions.is_empty() {
+            return Err(ApiKeyError::InvalidApiKey(String::from(
+                "`actions` must contain at least one action.",
+            )));
+        }
+        if self.indexes.is_empty() {
+            return Err(ApiKeyError::InvalidApiKey(String::from(
+                "`indexes` must contain at least one index.",
+            )));
+        }
+        if let Some(pattern) = self
+            .indexes
+            .iter()
+            .find(|pattern| pattern.trim_end_matches('*').contains('*'))
+        {
+            return Err(ApiKeyError::InvalidApiKey(format!(
+                "`{}` is not a valid index pattern, `*` can only end it.",
+                pattern
+            )));
+        }
+        match self.expires_at {
+            Some(expires_at) if expires_at <= Utc::now() => {
+                Err(ApiKeyError::InvalidApiKey(format!(
+                    "`expiresAt` ({}) must be in the future.",
+                    expires_at.to_rfc3339()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at
+            .map_or(false, |expires_at| expires_at <= now)
+    }
+
+    /// Whether the key is allowed to perform `action` on the index `index_uid`, or on all the
+    /// indexes when there is none.
+    fn allows(&self, action: Action, index_uid: Option<&str>) -> bool {
+        (self.actions.contains(&Action::All) || self.actions.contains(&action))
+            && match index_uid {
+                Some(uid) => self
+                    .indexes
+                    .iter()
+                    .any(|pattern| index_matches(pattern, uid)),
+                None => self.indexes.contains(WILDCARD),
+            }
+    }
+
+    /// Whether the key is allowed to perform `action` on at least one index.
+    fn allows_action(&self, action: Action) -> bool {
+        self.actions.contains(&Action::All) || self.actions.contains(&action)
+    }
+}
+
+fn index_matches(pattern: &str, uid: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => uid.starts_with(prefix),
+        None => pattern == uid,
+    }
+}
+
+fn generate_key() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(API_KEY_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct ApiKeyStore {
+    env: Env,
+    keys: Database<Str, SerdeJson<ApiKey>>,
+}
+
+impl ApiKeyStore {
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().join(API_KEYS_DB_PATH);
+        create_dir_all(&path)?;
+        let mut options = EnvOpenOptions::new();
+        options.map_size(API_KEYS_STORE_SIZE);
+        options.max_dbs(1);
+        let env = options.open(path)?;
+        let keys = env.create_database(Some("api-keys"))?;
+        Ok(Self { env, keys })
+    }
+
+    pub fn create(&self, key: CreateApiKey) -> Result<ApiKey> {
+        let now = Utc::now();
+        let key = ApiKey {
+            key: generate_key(),
+            description: key.description,
+            actions: key.actions,
+            indexes: key.indexes,
+            expires_at: key.expires_at,
+            created_at: now,
+            updated_at: now,
+        };
+        key.validate()?;
+
+        let mut txn = self.env.write_txn()?;
+        self.keys.put(&mut txn, &key.key, &key)?;
+        txn.commit()?;
+        Ok(key)
+    }
+
+    pub fn get(&self, key: &str) -> Result<ApiKey> {
+        let txn = self.env.read_txn()?;
+        self.keys
+            .get(&txn, key)?
+            .ok_or_else(|| ApiKeyError::ApiKeyNotFound(key.to_string()))
+    }
+
+    /// Returns all the keys, from the most recently created one.
+    pub fn list(&self) -> Result<Vec<ApiKey>> {
+        let txn = self.env.read_txn()?;
+        let mut keys = self
+            .keys
+            .iter(&txn)?
+            .map(|entry| entry.map(|(_, key)| key))
+            .collect::<heed::Result<Vec<_>>>()?;
+        keys.sort_unstable_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(keys)
+    }
+
+    pub fn update(&self, key: &str, patch: PatchApiKey) -> Result<ApiKey> {
+        let mut txn = self.env.write_txn()?;
+        let mut updated = self
+            .keys
+            .get(&txn, key)?
+            .ok_or_else(|| ApiKeyError::ApiKeyNotFound(key.to_string()))?;
+
+        match patch.description {
+            Setting::Set(description) => updated.description = Some(description),
+            Setting::Reset => updated.description = None,
+            Setting::NotSet => (),
+        }
+        if let Some(actions) = patch.actions {
+            updated.actions = actions;
+        }
+        if let Some(indexes) = patch.indexes {
+            updated.indexes = indexes;
+        }
+        match patch.expires_at {
+            Setting::Set(expires_at) => updated.expires_at = Some(expires_at),
+            Setting::Reset => updated.expires_at = None,
+            Setting::NotSet => (),
+        }
+        updated.validate()?;
+        updated.updated_at = Utc::now();
+
+        self.keys.put(&mut txn, key, &updated)?;
+        txn.commit()?;
+        Ok(updated)
+    }
+
+    pub fn delete(&self, key: &str) -> Result<()> {
+        let mut txn = self.env.write_txn()?;
+        if !self.keys.delete(&mut txn, key)? {
+            return Err(ApiKeyError::ApiKeyNotFound(key.to_string()));
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Returns the keys starting with `prefix`, the ones that may have signed a tenant token.
+    pub fn with_prefix(&self, prefix: &str) -> Result<Vec<String>> {
+        let txn = self.env.read_txn()?;
+        let keys = self
+            .keys
+            .prefix_iter(&txn, prefix)?
+            .map(|entry| entry.map(|(key, _)| key.to_string()))
+            .collect::<heed::Result<_>>()?;
+        Ok(keys)
+    }
+
+    /// Returns whether `key` is an unexpired key allowed to perform `action` on the index
+    /// `index_uid`, or on all the indexes when there is none. This is called by every request
+    /// authenticated with a key of the store.
+    pub fn authorize(&self, key: &str, action: Action, index_uid: Option<&str>) -> Result<bool> {
+        let txn = self.env.read_txn()?;
+        Ok(match self.keys.get(&txn, key)? {
+            Some(key) => !key.is_expired(Utc::now()) && key.allows(action, index_uid),
+            None => false,
+        })
+    }
+
+    /// Returns whether `key` is an unexpired key allowed to perform `action` on at least one
+    /// index, for the requests whose indexes are only known once their body is read.
+    pub fn authorize_action(&self, key: &str, action: Action) -> Result<bool> {
+        let txn = self.env.read_txn()?;
+        Ok(match self.keys.get(&txn, key)? {
+            Some(key) => !key.is_expired(Utc::now()) && key.allows_action(action),
+            None => false,
+        })
+    }
+
+    pub fn snapshot(&self, path: impl AsRef<Path>, throttle: Option<&IoThrottle>) -> Result<()> {
+        let path = path.as_ref().join(API_KEYS_DB_PATH);
+        create_dir_all(&path)?;
+        compacted_env_copy(&self.env, &path.join("data.mdb"), throttle)?;
+        Ok(())
+    }
+
+    pub fn dump(&self, path: impl AsRef<Path>, throttle: Option<&IoThrottle>) -> Result<()> {
+        let dump_path = path.as_ref().join(API_KEYS_DB_PATH);
+        create_dir_all(&dump_path)?;
+        let mut dump_file = create_file(dump_path.join(API_KEYS_DUMP_FILE), throttle)?;
+
+        let txn = self.env.read_txn()?;
+        for entry in self.keys.iter(&txn)? {
+            let (_, key) = entry?;
+            serde_json::to_writer(&mut dump_file, &key)?;
+            dump_file.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Loads the keys of a dump, the dumps created before the keys holding none. The index
+    /// patterns of the keys are prefixed with `prefix`, like the uids of the indexes of the dump,
+    /// and the keys already in the database are kept. Returns the loaded keys.
+    pub fn load_dump(
+        src: impl AsRef<Path>,
+        dst: impl AsRef<Path>,
+        prefix: &str,
+    ) -> Result<Vec<String>> {
+        let mut loaded = Vec::new();
+        let src_keys = src.as_ref().join(API_KEYS_DB_PATH).join(API_KEYS_DUMP_FILE);
+        if !src_keys.exists() {
+            return Ok(loaded);
+        }
+
+        let store = Self::new(dst)?;
+        let mut txn = store.env.write_txn()?;
+        for line in BufReader::new(File::open(&src_keys)?).lines() {
+            let mut key: ApiKey = serde_json::from_str(&line?)?;
+            key.indexes = key
+                .indexes
+                .into_iter()
+                .map(|pattern| format!("{}{}", prefix, pattern))
+                .collect();
+            if store.keys.get(&txn, &key.key)?.is_none() {
+                store.keys.put(&mut txn, &key.key, &key)?;
+                loaded.push(key.key);
+            }
+        }
+        txn.commit()?;
+        store.env.prepare_for_closing().wait();
+
+        Ok(loaded)
+    }
+
+    /// Removes the keys loaded from a dump.
+    pub fn unload_dump(dst: impl AsRef<Path>, keys: &[String]) -> Result<()> {
+        let store = Self::new(dst)?;
+        let mut txn = store.env.write_txn()?;
+        for key in keys {
+            store.keys.delete(&mut txn, key)?;
+        }
+        txn.commit()?;
+        store.env.prepare_for_closing().wait();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn dump_and_load_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ApiKeyStore::new(dir.path().join("src")).unwrap();
+        let key = store
+            .create(CreateApiKey {
+                description: None,
+                actions: vec![Action::Search].into_iter().collect(),
+                indexes: vec![String::from("movies"), String::from(WILDCARD)]
+                    .into_iter()
+                    .collect(),
+                expires_at: None,
+            })
+            .unwrap();
+        let dump = dir.path().join("dump");
+        store.dump(&dump, None).unwrap();
+
+        let dst = dir.path().join("dst");
+        ApiKeyStore::load_dump(&dump, &dst, "v2_").unwrap();
+        let loaded = ApiKeyStore::new(&dst).unwrap().get(&key.key).unwrap();
+        assert_eq!(
+            loaded.indexes,
+            vec![String::from("v2_*"), String::from("v2_movies")]
+                .into_iter()
+                .collect()
+        );
+    }
+
+    #[test]
+    fn authorize_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ApiKeyStore::new(dir.path()).unwrap();
+        let key = store
+            .create(CreateApiKey {
+                description: None,
+                actions: vec![Action::Search].into_iter().collect(),
+                indexes: vec![String::from("products_*"), String::from("movies")]
+                    .into_iter()
+                    .collect(),
+                expires_at: None,
+            })
+            .unwrap();
+
+        let authorize = |action, index| store.authorize(&key.key, action, index).unwrap();
+        assert!(authorize(Action::Search, Some("movies")));
+        assert!(authorize(Action::Search, Some("products_fr")));
+        assert!(!authorize(Action::Search, Some("moviesfr")));
+        assert!(!authorize(Action::Search, None));
+        assert!(!authorize(Action::DocumentsAdd, Some("movies")));
+        assert!(!store
+            .authorize("unknown", Action::Search, Some("movies"))
+            .unwrap());
+
+        let patch = PatchApiKey {
+            actions: Some(vec![Action::All].into_iter().collect()),
+            indexes: Some(vec![String::from(WILDCARD)].into_iter().collect()),
+            ..PatchApiKey::default()
+        };
+        store.update(&key.key, patch).unwrap();
+        let authorize = |action, index| store.authorize(&key.key, action, index).unwrap();
+        assert!(authorize(Action::DocumentsAdd, Some("movies")));
+        assert!(authorize(Action::StatsGet, None));
+        assert_eq!(
+            store.with_prefix(&key.key[..8]).unwrap(),
+            vec![key.key.clone()]
+        );
+
+        store.delete(&key.key).unwrap();
+        assert!(!store
+            .authorize(&key.key, Action::Search, Some("movies"))
+            .unwrap());
+        assert!(store.get(&key.key).is_err());
+    }
+
+    #[test]
+    fn validate_keys() {
+        let mut key = ApiKey {
+            key: generate_key(),
+            description: None,
+            actions: vec![Action::Search].into_iter().collect(),
+            indexes: vec![String::from("*")].into_iter().collect(),
+            expires_at: Some(Utc::now() + Duration::days(1)),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        assert!(key.validate().is_ok());
+        assert!(!key.is_expired(Utc::now()));
+        assert!(key.is_expired(Utc::now() + Duration::days(2)));
+
+        key.indexes = vec![String::from("pro*ducts")].into_iter().collect();
+        assert!(key.validate().is_err());
+
+        key.indexes = vec![String::from("products")].into_iter().collect();
+        key.expires_at = Some(Utc::now() - Duration::days(1));
+        assert!(key.validate().is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{error, info, trace, warn};
+use parking_lot::RwLock;
+use tokio::task::spawn_blocking;
+use tokio::time::sleep;
+use uuid::Uuid;
+
+use crate::index::BloatReport;
+
+use super::index_resolver::HardStateIndexResolver;
+
+/// The last bloat report of each index.
+pub type BloatReports = Arc<RwLock<HashMap<Uuid, BloatReport>>>;
+
+pub struct BloatService {
+    index_resolver: Arc<HardStateIndexResolver>,
+    check_period: Duration,
+    reports: BloatReports,
+}
+
+impl BloatService {
+    pub fn new(
+        index_resolver: Arc<HardStateIndexResolver>,
+        check_period: Duration,
+        reports: BloatReports,
+    ) -> Self {
+        Self {
+            index_resolver,
+            check_period,
+            reports,
+        }
+    }
+
+    pub async fn run(self) {
+        info!(
+            "Index bloat check scheduled every {}s.",
+            self.check_period.as_secs()
+        );
+        loop {
+            if let Err(e) = self.check_indexes().await {
+                error!("Error while checking the index bloat: {}", e);
+            }
+            sleep(self.check_period).await;
+        }
+    }
+
+    async fn check_indexes(&self) -> anyhow::Result<()> {
+        trace!("Checking the index bloat.");
+
+        let mut reports = HashMap::new();
+        for (uid, index) in self.index_resolver.list().await? {
+            let uuid = index.uuid;
+            let report = spawn_blocking(move || index.bloat_report()).await??;
+            if report.compaction_recommended {
+                warn!(
+                    "Index {} takes {:.1} times the size of its data, consider compacting it.",
+                    uid, report.bloat_ratio
+                );
+            }
+            reports.insert(uuid, report);
+        }
+
+        // The reports of the deleted indexes are dropped along the way.
+        *self.reports.write() = reports;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use uuid::Uuid;
+
+/// The number of consecutive failures after which the searches on an index fail fast.
+const FAILURE_THRESHOLD: u32 = 5;
+/// How long the searches fail fast before a probe search is let through.
+const OPEN_DURATION: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Default)]
+struct IndexCircuit {
+    consecutive_failures: u32,
+    /// When the circuit was opened, the searches fail fast until `OPEN_DURATION` is elapsed.
+    opened_at: Option<Instant>,
+    /// Whether a probe search is being performed on a half-open circuit.
+    probing: bool,
+}
+
+/// Isolates the indexes whose searches keep failing, because of a corruption or pathological
+/// settings, so that they don't keep consuming resources while the other indexes are unaffected.
+#[derive(Debug, Default)]
+pub struct CircuitBreaker {
+    circuits: Mutex<HashMap<Uuid, IndexCircuit>>,
+}
+
+/// A search allowed on an index, whose outcome is recorded by the circuit breaker. A probe
+/// dropped without outcome, like a search whose request was canceled, lets another probe through.
+#[must_use]
+pub struct Attempt<'a> {
+    breaker: &'a CircuitBreaker,
+    uuid: Uuid,
+    probe: bool,
+    recorded: bool,
+}
+
+impl Attempt<'_> {
+    pub fn success(mut self) {
+        self.recorded = true;
+        self.breaker.record_success(self.uuid);
+    }
+
+    pub fn failure(mut self) {
+        self.recorded = true;
+        self.breaker.record_failure(self.uuid);
+    }
+}
+
+impl Drop for Attempt<'_> {
+    fn drop(&mut self) {
+        if self.probe && !self.recorded {
+            if let Some(circuit) = self.breaker.circuits.lock().get_mut(&self.uuid) {
+                circuit.probing = false;
+            }
+        }
+    }
+}
+
+impl CircuitBreaker {
+    /// Returns the attempt of a search on the index, unless the circuit is open. Once the open
+    /// duration is elapsed, a single probe search is let through, that will close or reopen the
+    /// circuit.
+    pub fn allow(&self, uuid: Uuid) -> Option<Attempt<'_>> {
+        let attempt = |probe| Attempt {
+            breaker: self,
+            uuid,
+            probe,
+            recorded: false,
+        };
+        let mut circuits = self.circuits.lock();
+        let circuit = match circuits.get_mut(&uuid) {
+            Some(circuit) => circuit,
+            None => return Some(attempt(false)),
+        };
+
+        match circuit.opened_at {
+            None => Some(attempt(false)),
+            Some(opened_at) if opened_at.elapsed() >= OPEN_DURATION && !circuit.probing => {
+                circuit.probing = true;
+                Some(attempt(true))
+            }
+            Some(_) => None,
+        }
+    }
+
+    fn record_success(&self, uuid: Uuid) {
+        self.circuits.lock().remove(&uuid);
+    }
+
+    fn record_failure(&self, uuid: Uuid) {
+        let mut circuits = self.circuits.lock();
+        let circuit = circuits.entry(uuid).or_default();
+        circuit.consecutive_failures += 1;
+        if circuit.probing || circuit.consecutive_failures >= FAILURE_THRESHOLD {
+            circuit.opened_at = Some(Instant::now());
+            circuit.probing = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fail(breaker: &CircuitBreaker, uuid: Uuid) {
+        breaker.allow(uuid).unwrap().failure();
+    }
+
+    #[test]
+    fn open_after_consecutive_failures() {
+        let breaker = CircuitBreaker::default();
+        let failing = Uuid::new_v4();
+        let healthy = Uuid::new_v4();
+
+        for _ in 0..FAILURE_THRESHOLD - 1 {
+            fail(&breaker, failing);
+        }
+        breaker.allow(failing).unwrap().success();
+
+        for _ in 0..FAILURE_THRESHOLD {
+            fail(&breaker, failing);
+        }
+        assert!(breaker.allow(failing).is_none());
+        assert!(breaker.allow(healthy).is_some());
+    }
+
+    fn open_circuit(breaker: &CircuitBreaker, uuid: Uuid) {
+        breaker.circuits.lock().insert(
+            uuid,
+            IndexCircuit {
+                consecutive_failures: FAILURE_THRESHOLD,
+                opened_at: Some(Instant::now() - OPEN_DURATION),
+                probing: false,
+            },
+        );
+    }
+
+    #[test]
+    fn half_open_probe() {
+        let breaker = CircuitBreaker::default();
+        let uuid = Uuid::new_v4();
+        open_circuit(&breaker, uuid);
+
+        // Only one probe is let through.
+        let probe = breaker.allow(uuid).unwrap();
+        assert!(breaker.allow(uuid).is_none());
+
+        probe.failure();
+        assert!(breaker.allow(uuid).is_none());
+
+        breaker.circuits.lock().get_mut(&uuid).unwrap().opened_at =
+            Some(Instant::now() - OPEN_DURATION);
+        breaker.allow(uuid).unwrap().success();
+        assert!(breaker.allow(uuid).is_some());
+        assert!(breaker.allow(uuid).is_some());
+    }
+
+    #[test]
+    fn dropped_probe() {
+        let breaker = CircuitBreaker::default();
+        let uuid = Uuid::new_v4();
+        open_circuit(&breaker, uuid);
+
+        // A probe whose search was canceled lets the next one through.
+        drop(breaker.allow(uuid).unwrap());
+        let probe = breaker.allow(uuid).unwrap();
+        assert!(breaker.allow(uuid).is_none());
+        probe.success();
+        assert!(breaker.allow(uuid).is_some());
+    }
+}
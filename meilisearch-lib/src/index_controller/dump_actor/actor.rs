@@ -10,8 +10,10 @@ use tokio::sync::{mpsc, oneshot, RwLock};
 
 use super::error::{DumpActorError, Result};
 use super::{DumpInfo, DumpMsg, DumpStatus, DumpTask};
+use crate::index_controller::api_keys::ApiKeyStore;
 use crate::index_controller::index_resolver::HardStateIndexResolver;
 use crate::index_controller::updates::UpdateSender;
+use crate::index_controller::webhooks::WebhookStore;
 
 pub const CONCURRENT_DUMP_MSG: usize = 10;
 
@@ -19,11 +21,15 @@ pub struct DumpActor {
     inbox: Option<mpsc::Receiver<DumpMsg>>,
     index_resolver: Arc<HardStateIndexResolver>,
     update: UpdateSender,
+    api_keys: ApiKeyStore,
+    webhooks: WebhookStore,
     dump_path: PathBuf,
     lock: Arc<Mutex<()>>,
     dump_infos: Arc<RwLock<HashMap<String, DumpInfo>>>,
     update_db_size: usize,
     index_db_size: usize,
+    /// The maximum number of bytes read per second when the dumps are compressed.
+    io_throttle: Option<u64>,
 }
 
 /// Generate uid from creation date
@@ -36,9 +42,12 @@ impl DumpActor {
         inbox: mpsc::Receiver<DumpMsg>,
         index_resolver: Arc<HardStateIndexResolver>,
         update: UpdateSender,
+        api_keys: ApiKeyStore,
+        webhooks: WebhookStore,
         dump_path: impl AsRef<Path>,
         index_db_size: usize,
         update_db_size: usize,
+        io_throttle: Option<u64>,
     ) -> Self {
         let dump_infos = Arc::new(RwLock::new(HashMap::new()));
         let lock = Arc::new(Mutex::new(()));
@@ -46,11 +55,14 @@ impl DumpActor {
             inbox: Some(inbox),
             index_resolver,
             update,
+            api_keys,
+            webhooks,
             dump_path: dump_path.as_ref().into(),
             dump_infos,
             lock,
             index_db_size,
             update_db_size,
+            io_throttle,
         }
     }
 
@@ -115,9 +127,12 @@ impl DumpActor {
             path: self.dump_path.clone(),
             index_resolver: self.index_resolver.clone(),
             update_handle: self.update.clone(),
+            api_keys: self.api_keys.clone(),
+            webhooks: self.webhooks.clone(),
             uid: uid.clone(),
             update_db_size: self.update_db_size,
             index_db_size: self.index_db_size,
+            io_throttle: self.io_throttle,
         };
 
         let task_result = tokio::task::spawn(task.run()).await;
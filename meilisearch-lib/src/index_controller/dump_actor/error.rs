@@ -11,6 +11,10 @@ pub enum DumpActorError {
     DumpAlreadyRunning,
     #[error("Dump `{0}` not found")]
     DumpDoesNotExist(String),
+    #[error("No dump import report, the database wasn't imported from a dump with `--ignore-dump-import-errors`")]
+    ImportReportNotFound,
+    #[error("Invalid dump: {0}")]
+    InvalidDump(String),
     #[error("Internal error: {0}")]
     Internal(Box<dyn std::error::Error + Send + Sync + 'static>),
     #[error("{0}")]
@@ -36,7 +40,9 @@ internal_error!(
     std::io::Error,
     tokio::task::JoinError,
     serde_json::error::Error,
-    tempfile::PersistError
+    tempfile::PersistError,
+    crate::index_controller::api_keys::ApiKeyError,
+    crate::index_controller::webhooks::WebhookError
 );
 
 impl ErrorCode for DumpActorError {
@@ -44,6 +50,8 @@ impl ErrorCode for DumpActorError {
         match self {
             DumpActorError::DumpAlreadyRunning => Code::DumpAlreadyInProgress,
             DumpActorError::DumpDoesNotExist(_) => Code::NotFound,
+            DumpActorError::ImportReportNotFound => Code::NotFound,
+            DumpActorError::InvalidDump(_) => Code::InvalidDump,
             DumpActorError::Internal(_) => Code::Internal,
             DumpActorError::IndexResolver(e) => e.error_code(),
             DumpActorError::UpdateLoop(e) => e.error_code(),
@@ -3,7 +3,9 @@ use std::sync::Arc;
 
 use tokio::sync::{mpsc, oneshot};
 
+use crate::index_controller::api_keys::ApiKeyStore;
 use crate::index_controller::index_resolver::HardStateIndexResolver;
+use crate::index_controller::webhooks::WebhookStore;
 
 use super::error::Result;
 use super::{DumpActor, DumpActorHandle, DumpInfo, DumpMsg};
@@ -35,17 +37,23 @@ impl DumpActorHandleImpl {
         path: impl AsRef<Path>,
         index_resolver: Arc<HardStateIndexResolver>,
         update: crate::index_controller::updates::UpdateSender,
+        api_keys: ApiKeyStore,
+        webhooks: WebhookStore,
         index_db_size: usize,
         update_db_size: usize,
+        io_throttle: Option<u64>,
     ) -> anyhow::Result<Self> {
         let (sender, receiver) = mpsc::channel(10);
         let actor = DumpActor::new(
             receiver,
             index_resolver,
             update,
+            api_keys,
+            webhooks,
             path,
             index_db_size,
             update_db_size,
+            io_throttle,
         );
 
         tokio::task::spawn(actor.run());
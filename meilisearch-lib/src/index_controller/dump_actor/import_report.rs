@@ -0,0 +1,83 @@
+//! The report of the import of a dump, listing the indexes skipped because they failed to load
+//! when the errors are ignored. It is kept in the database directory, to be checked once the
+//! instance started.
+
+use std::fs::File;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+const IMPORT_REPORT_FILE_NAME: &str = "dump-import-report.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DumpImportReport {
+    pub imported_at: DateTime<Utc>,
+    /// The number of indexes loaded.
+    pub imported_indexes: usize,
+    /// The indexes that failed to load, skipped along with their updates.
+    pub failed_indexes: Vec<FailedIndexImport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FailedIndexImport {
+    pub uid: String,
+    pub error: String,
+}
+
+impl DumpImportReport {
+    pub fn new() -> Self {
+        Self {
+            imported_at: Utc::now(),
+            imported_indexes: 0,
+            failed_indexes: Vec::new(),
+        }
+    }
+
+    /// The report of the database at `db_path`, `None` if it wasn't imported from a dump with
+    /// the errors ignored.
+    pub fn load(db_path: impl AsRef<Path>) -> anyhow::Result<Option<Self>> {
+        let path = db_path.as_ref().join(IMPORT_REPORT_FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_reader(File::open(path)?)?))
+    }
+
+    pub fn persist(&self, db_path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let file = File::create(db_path.as_ref().join(IMPORT_REPORT_FILE_NAME))?;
+        serde_json::to_writer(file, self)?;
+        Ok(())
+    }
+}
+
+impl Default for DumpImportReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn persist_and_load_reports() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(DumpImportReport::load(dir.path()).unwrap().is_none());
+
+        let mut report = DumpImportReport::new();
+        report.imported_indexes = 2;
+        report.failed_indexes.push(FailedIndexImport {
+            uid: String::from("movies"),
+            error: String::from("invalid settings"),
+        });
+        report.persist(dir.path()).unwrap();
+
+        let loaded = DumpImportReport::load(dir.path()).unwrap().unwrap();
+        assert_eq!(loaded.imported_indexes, 2);
+        assert_eq!(loaded.failed_indexes[0].uid, "movies");
+    }
+}
@@ -0,0 +1,282 @@
+//! The inspection of a dump, reading its manifest without importing it: the tarball is read as a
+//! stream, so that nothing is written on the disk, and the documents are only counted.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Component;
+
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use serde_json::Value;
+use tar::Archive;
+
+use super::error::{DumpActorError, Result};
+use super::META_FILE_NAME;
+use crate::index_controller::versioning::check_dump_version;
+
+/// The rough number of bytes of documents indexed per second when a dump is imported, used to
+/// estimate its import time.
+const ESTIMATED_IMPORT_THROUGHPUT: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DumpManifest {
+    pub dump_version: Option<String>,
+    /// The version of the engine that created the dump.
+    pub db_version: Option<String>,
+    pub dump_date: Option<DateTime<Utc>>,
+    /// Whether this instance can import the dump, the reason why it can't otherwise.
+    pub importable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub incompatibility: Option<String>,
+    pub indexes: Vec<IndexManifest>,
+    /// A rough estimate of the time needed to import the dump, with a single import job.
+    pub estimated_import_time_sec: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexManifest {
+    pub uid: String,
+    pub primary_key: Option<String>,
+    pub number_of_documents: u64,
+    /// The size of the dumped documents, in bytes.
+    pub documents_size: u64,
+    pub settings: SettingsSummary,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsSummary {
+    pub ranking_rules: Value,
+    pub searchable_attributes: Value,
+    pub filterable_attributes: Value,
+    pub sortable_attributes: Value,
+    pub distinct_attribute: Value,
+    pub number_of_stop_words: usize,
+    pub number_of_synonyms: usize,
+}
+
+impl SettingsSummary {
+    fn from_settings(settings: &Value) -> Self {
+        let get = |name: &str| settings.get(name).cloned().unwrap_or(Value::Null);
+        let len = |name: &str| match settings.get(name) {
+            Some(Value::Array(values)) => values.len(),
+            Some(Value::Object(values)) => values.len(),
+            _ => 0,
+        };
+        Self {
+            ranking_rules: get("rankingRules"),
+            searchable_attributes: get("searchableAttributes"),
+            // The dumps v1 name the filterable attributes after the faceting.
+            filterable_attributes: settings
+                .get("filterableAttributes")
+                .or_else(|| settings.get("attributesForFaceting"))
+                .cloned()
+                .unwrap_or(Value::Null),
+            sortable_attributes: get("sortableAttributes"),
+            distinct_attribute: get("distinctAttribute"),
+            number_of_stop_words: len("stopWords"),
+            number_of_synonyms: len("synonyms"),
+        }
+    }
+}
+
+/// What was read from the folder of an index in the dump.
+#[derive(Default)]
+struct DumpedIndex {
+    /// The `meta.json` of the dumps v2 and v3, or the `settings.json` of the dumps v1.
+    meta: Option<Value>,
+    number_of_documents: u64,
+    documents_size: u64,
+}
+
+/// Reads the manifest of the gzipped dump `reader`.
+pub fn inspect_dump(reader: impl Read) -> Result<DumpManifest> {
+    let mut metadata = None;
+    // The uids of the dumped indexes by uuid, missing from the dumps v1.
+    let mut uids = Vec::new();
+    let mut folders: HashMap<String, DumpedIndex> = HashMap::new();
+
+    let mut archive = Archive::new(GzDecoder::new(reader));
+    for entry in archive.entries().map_err(invalid_dump)? {
+        let entry = entry.map_err(invalid_dump)?;
+        let path: Vec<String> = entry
+            .path()
+            .map_err(invalid_dump)?
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+        let path: Vec<&str> = path.iter().map(String::as_str).collect();
+
+        match path.as_slice() {
+            [META_FILE_NAME] => metadata = Some(read_json(entry)?),
+            ["index_uuids", "data.jsonl"] => {
+                for line in BufReader::new(entry).lines() {
+                    let line = line.map_err(invalid_dump)?;
+                    let entry: Value = serde_json::from_str(&line).map_err(invalid_dump)?;
+                    if let (Some(uuid), Some(uid)) = (entry["uuid"].as_str(), entry["uid"].as_str())
+                    {
+                        uids.push((uuid.to_string(), uid.to_string()));
+                    }
+                }
+            }
+            ["indexes", folder, "meta.json"] | [folder, "settings.json"] => {
+                let folder = folder.trim_start_matches("index-").to_string();
+                folders.entry(folder).or_default().meta = Some(read_json(entry)?);
+            }
+            ["indexes", folder, "documents.jsonl"] | [folder, "documents.jsonl"] => {
+                let folder = folder.trim_start_matches("index-").to_string();
+                let index = folders.entry(folder).or_default();
+                for line in BufReader::new(entry).split(b'\n') {
+                    let line = line.map_err(invalid_dump)?;
+                    if !line.iter().all(u8::is_ascii_whitespace) {
+                        index.number_of_documents += 1;
+                    }
+                    index.documents_size += line.len() as u64 + 1;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let metadata = metadata.ok_or_else(|| {
+        DumpActorError::InvalidDump(format!("the {} file is missing", META_FILE_NAME))
+    })?;
+
+    // The dumps v1 list their indexes in their metadata, their folders being named after them.
+    let v1_indexes = metadata["indexes"].as_array().cloned().unwrap_or_default();
+    let indexes: Vec<(String, String, Option<String>)> = if uids.is_empty() {
+        v1_indexes
+            .iter()
+            .filter_map(|index| {
+                let uid = index["uid"].as_str()?.to_string();
+                let primary_key = index["primaryKey"].as_str().map(String::from);
+                Some((uid.clone(), uid, primary_key))
+            })
+            .collect()
+    } else {
+        uids.into_iter()
+            .map(|(uuid, uid)| (uuid, uid, None))
+            .collect()
+    };
+
+    let mut manifests = Vec::new();
+    for (folder, uid, primary_key) in indexes {
+        let index = folders.remove(&folder).unwrap_or_default();
+        let meta = index.meta.unwrap_or(Value::Null);
+        // The dumps v2 and v3 hold the settings along with the primary key.
+        let (settings, primary_key) = match meta.get("settings") {
+            Some(settings) => (settings, meta["primary_key"].as_str().map(String::from)),
+            None => (&meta, primary_key),
+        };
+        manifests.push(IndexManifest {
+            uid,
+            primary_key,
+            number_of_documents: index.number_of_documents,
+            documents_size: index.documents_size,
+            settings: SettingsSummary::from_settings(settings),
+        });
+    }
+    manifests.sort_unstable_by(|a, b| a.uid.cmp(&b.uid));
+
+    let db_version = metadata["dbVersion"].as_str().map(String::from);
+    let incompatibility = match db_version.as_deref() {
+        Some(version) => check_dump_version(version).err().map(|e| e.to_string()),
+        None => Some(String::from("The dump metadata has no engine version.")),
+    };
+    let documents_size: u64 = manifests.iter().map(|index| index.documents_size).sum();
+
+    Ok(DumpManifest {
+        dump_version: metadata["dumpVersion"].as_str().map(String::from),
+        db_version,
+        dump_date: metadata["dumpDate"]
+            .as_str()
+            .and_then(|date| date.parse().ok()),
+        importable: incompatibility.is_none(),
+        incompatibility,
+        indexes: manifests,
+        estimated_import_time_sec: (documents_size + ESTIMATED_IMPORT_THROUGHPUT - 1)
+            / ESTIMATED_IMPORT_THROUGHPUT,
+    })
+}
+
+fn read_json(reader: impl Read) -> Result<Value> {
+    serde_json::from_reader(reader).map_err(invalid_dump)
+}
+
+fn invalid_dump(error: impl std::fmt::Display) -> DumpActorError {
+    DumpActorError::InvalidDump(error.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use serde_json::json;
+
+    use super::*;
+
+    fn append(builder: &mut tar::Builder<impl std::io::Write>, path: &str, content: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, content).unwrap();
+    }
+
+    #[test]
+    fn inspect_dump_v3() {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        let uuid = "5a8e4fa9-4d8b-4d15-8a1b-2f1e6a8d1c3b";
+        let meta = json!({
+            "settings": { "rankingRules": ["words"], "stopWords": ["a", "the"] },
+            "primary_key": "id",
+        });
+        append(
+            &mut builder,
+            &format!("./indexes/{}/documents.jsonl", uuid),
+            b"{\"id\":1}\n{\"id\":2}\n",
+        );
+        append(
+            &mut builder,
+            &format!("./indexes/{}/meta.json", uuid),
+            meta.to_string().as_bytes(),
+        );
+        append(
+            &mut builder,
+            "./index_uuids/data.jsonl",
+            json!({ "uuid": uuid, "uid": "movies" })
+                .to_string()
+                .as_bytes(),
+        );
+        let metadata = json!({
+            "dumpVersion": "V3",
+            "dbVersion": "0.1.0",
+            "dumpDate": "2021-10-12T10:00:00Z",
+        });
+        append(
+            &mut builder,
+            "./metadata.json",
+            metadata.to_string().as_bytes(),
+        );
+        let dump = builder.into_inner().unwrap().finish().unwrap();
+
+        let manifest = inspect_dump(dump.as_slice()).unwrap();
+        assert_eq!(manifest.dump_version.as_deref(), Some("V3"));
+        assert!(!manifest.importable);
+        assert_eq!(manifest.indexes.len(), 1);
+        let index = &manifest.indexes[0];
+        assert_eq!(index.uid, "movies");
+        assert_eq!(index.primary_key.as_deref(), Some("id"));
+        assert_eq!(index.number_of_documents, 2);
+        assert_eq!(index.settings.ranking_rules, json!(["words"]));
+        assert_eq!(index.settings.number_of_stop_words, 2);
+
+        assert!(inspect_dump(&b"not a dump"[..]).is_err());
+    }
+}
@@ -11,10 +11,11 @@ use milli::update::Setting;
 use serde::{Deserialize, Deserializer, Serialize};
 use uuid::Uuid;
 
-use crate::document_formats::read_ndjson;
+use crate::document_formats::{read_ndjson, DocumentLimits};
 use crate::index::apply_settings_to_builder;
 use crate::index::update_handler::UpdateHandler;
 use crate::index_controller::dump_actor::loaders::compat::{asc_ranking_rule, desc_ranking_rule};
+use crate::index_controller::dump_actor::{DumpImportReport, FailedIndexImport};
 use crate::index_controller::index_resolver::uuid_store::HeedUuidStore;
 use crate::index_controller::{self, IndexMetadata};
 use crate::{index::Unchecked, options::IndexerOpts};
@@ -33,23 +34,70 @@ impl MetadataV1 {
         dst: impl AsRef<Path>,
         size: usize,
         indexer_options: &IndexerOpts,
-    ) -> anyhow::Result<()> {
+        ignore_errors: bool,
+    ) -> anyhow::Result<DumpImportReport> {
         let uuid_store = HeedUuidStore::new(&dst)?;
+        // With a prefix, the dump may be loaded next to the indexes of an existing database.
+        let prefix = indexer_options
+            .dump_import_prefix
+            .as_deref()
+            .unwrap_or_default();
+        for index in &self.indexes {
+            let uid = format!("{}{}", prefix, index.uid);
+            if uuid_store.get_uuid(&uid)?.is_some() {
+                anyhow::bail!(
+                    "The index `{}` of the dump already exists in the database.",
+                    uid
+                );
+            }
+        }
+
+        let mut report = DumpImportReport::new();
+        let mut loaded = Vec::new();
         for index in self.indexes {
+            let uid = format!("{}{}", prefix, index.uid);
             let uuid = Uuid::new_v4();
-            uuid_store.insert(index.uid.clone(), uuid)?;
-            let src = src.as_ref().join(index.uid);
-            load_index(
-                &src,
+            let index_src = src.as_ref().join(&index.uid);
+            let result = load_index(
+                &index_src,
                 &dst,
                 uuid,
                 index.meta.primary_key.as_deref(),
                 size,
                 indexer_options,
-            )?;
+            );
+            let partial = dst.as_ref().join(format!("indexes/{}", uuid));
+            match result {
+                Ok(()) => {
+                    uuid_store.insert(uid.clone(), uuid)?;
+                    loaded.push((uid, partial));
+                    report.imported_indexes += 1;
+                }
+                Err(e) if ignore_errors => {
+                    error!("Skipping index {}, which failed to load: {}", uid, e);
+                    if partial.exists() {
+                        std::fs::remove_dir_all(partial)?;
+                    }
+                    report.failed_indexes.push(FailedIndexImport {
+                        uid,
+                        error: e.to_string(),
+                    });
+                }
+                Err(e) => {
+                    // The indexes loaded before the failure must not be left in the database.
+                    loaded.push((uid, partial));
+                    for (uid, path) in loaded {
+                        uuid_store.delete(uid)?;
+                        if path.exists() {
+                            std::fs::remove_dir_all(path)?;
+                        }
+                    }
+                    return Err(e);
+                }
+            }
         }
 
-        Ok(())
+        Ok(report)
     }
 }
 
@@ -119,7 +167,7 @@ fn load_index(
 
     let mut tmp_doc_file = tempfile::tempfile()?;
 
-    read_ndjson(reader, &mut tmp_doc_file)?;
+    read_ndjson(reader, &mut tmp_doc_file, DocumentLimits::default())?;
 
     tmp_doc_file.seek(SeekFrom::Start(0))?;
 
@@ -170,6 +218,15 @@ impl From<Settings> for index_controller::Settings<Unchecked> {
                 None => Setting::NotSet
             },
             sortable_attributes: Setting::NotSet,
+            date_attributes: Setting::NotSet,
+            flatten_strategy: Setting::NotSet,
+            sort_collation: Setting::NotSet,
+            normalized_filterable_attributes: Setting::NotSet,
+            searchable_numeric_attributes: Setting::NotSet,
+            raw_attributes: Setting::NotSet,
+            checkpoint_before_settings_change: Setting::NotSet,
+            typo_tolerance: Setting::NotSet,
+            search_defaults: Setting::NotSet,
             ranking_rules: match settings.ranking_rules {
                 Some(Some(ranking_rules)) => Setting::Set(ranking_rules.into_iter().filter_map(|criterion| {
                     match criterion.as_str() {
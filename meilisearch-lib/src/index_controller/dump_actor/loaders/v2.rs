@@ -7,7 +7,7 @@ use tempfile::NamedTempFile;
 use uuid::Uuid;
 
 use crate::index_controller::dump_actor::loaders::compat::{asc_ranking_rule, desc_ranking_rule};
-use crate::index_controller::dump_actor::Metadata;
+use crate::index_controller::dump_actor::{DumpImportReport, Metadata};
 use crate::index_controller::updates::status::{
     Aborted, Enqueued, Failed, Processed, Processing, UpdateResult, UpdateStatus,
 };
@@ -26,7 +26,8 @@ pub fn load_dump(
     index_db_size: usize,
     update_db_size: usize,
     indexing_options: &IndexerOpts,
-) -> anyhow::Result<()> {
+    ignore_errors: bool,
+) -> anyhow::Result<DumpImportReport> {
     let indexes_path = src.as_ref().join("indexes");
 
     let dir_entries = std::fs::read_dir(indexes_path)?;
@@ -54,6 +55,7 @@ pub fn load_dump(
         index_db_size,
         update_db_size,
         indexing_options,
+        ignore_errors,
     )
 }
 
@@ -212,6 +214,10 @@ impl From<compat::Enqueued> for Enqueued {
             update_id,
             meta,
             enqueued_at,
+            metadata: None,
+            trace: None,
+            retry_of: None,
+            task_uid: None,
         }
     }
 }
@@ -227,6 +233,7 @@ impl From<compat::Processed> for Processed {
         Self {
             success: success.into(),
             processed_at,
+            warnings: Vec::new(),
             from: from.into(),
         }
     }
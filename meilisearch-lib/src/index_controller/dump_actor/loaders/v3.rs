@@ -1,11 +1,15 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-use log::info;
+use log::{error, info};
+use uuid::Uuid;
 
-use crate::index_controller::dump_actor::Metadata;
+use crate::index_controller::api_keys::ApiKeyStore;
+use crate::index_controller::dump_actor::{DumpImportReport, Metadata};
 use crate::index_controller::index_resolver::IndexResolver;
 use crate::index_controller::update_file_store::UpdateFileStore;
 use crate::index_controller::updates::store::UpdateStore;
+use crate::index_controller::webhooks::WebhookStore;
 use crate::options::IndexerOpts;
 
 pub fn load_dump(
@@ -15,17 +19,115 @@ pub fn load_dump(
     index_db_size: usize,
     update_db_size: usize,
     indexing_options: &IndexerOpts,
-) -> anyhow::Result<()> {
+    ignore_errors: bool,
+) -> anyhow::Result<DumpImportReport> {
     info!(
         "Loading dump from {}, dump database version: {}, dump version: V3",
         meta.dump_date, meta.db_version
     );
 
-    IndexResolver::load_dump(src.as_ref(), &dst, index_db_size, indexing_options)?;
-    UpdateFileStore::load_dump(src.as_ref(), &dst)?;
-    UpdateStore::load_dump(&src, &dst, update_db_size)?;
+    let (mut report, uuids) = IndexResolver::load_dump(
+        src.as_ref(),
+        &dst,
+        index_db_size,
+        indexing_options,
+        ignore_errors,
+    )?;
+
+    let mut loaded = Loaded::default();
+    let result = load_data(
+        src.as_ref(),
+        dst.as_ref(),
+        update_db_size,
+        &uuids,
+        indexing_options.dump_import_prefix.as_deref(),
+        &mut report,
+        &mut loaded,
+    );
+    if let Err(e) = result {
+        // A failed import must leave the database it is merged with as it was.
+        loaded.rollback(dst.as_ref(), update_db_size, &uuids);
+        return Err(e);
+    }
 
     info!("Loading indexes.");
 
+    Ok(report)
+}
+
+/// What an import loaded in the database, besides the indexes.
+#[derive(Default)]
+struct Loaded {
+    update_files: Vec<PathBuf>,
+    updates: bool,
+    keys: Vec<String>,
+    webhooks: Vec<Uuid>,
+}
+
+impl Loaded {
+    fn rollback(self, dst: &Path, update_db_size: usize, uuids: &HashMap<Uuid, Uuid>) {
+        if let Err(e) = IndexResolver::remove_dumped_indexes(dst, uuids.values()) {
+            error!(
+                "The indexes of the failed dump import couldn't be removed: {}",
+                e
+            );
+        }
+        for path in self.update_files {
+            if let Err(e) = std::fs::remove_file(&path) {
+                error!(
+                    "The update file {} couldn't be removed: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+        if self.updates {
+            if let Err(e) = UpdateStore::unload_dump(dst, update_db_size, uuids.values()) {
+                error!(
+                    "The updates of the failed dump import couldn't be removed: {}",
+                    e
+                );
+            }
+        }
+        if !self.keys.is_empty() {
+            if let Err(e) = ApiKeyStore::unload_dump(dst, &self.keys) {
+                error!(
+                    "The keys of the failed dump import couldn't be removed: {}",
+                    e
+                );
+            }
+        }
+        if !self.webhooks.is_empty() {
+            if let Err(e) = WebhookStore::unload_dump(dst, &self.webhooks) {
+                error!(
+                    "The webhooks of the failed dump import couldn't be removed: {}",
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// Loads the update files, updates, keys and webhooks of the dump, then the uids of its indexes,
+/// that make them reachable.
+fn load_data(
+    src: &Path,
+    dst: &Path,
+    update_db_size: usize,
+    uuids: &HashMap<Uuid, Uuid>,
+    prefix: Option<&str>,
+    report: &mut DumpImportReport,
+    loaded: &mut Loaded,
+) -> anyhow::Result<()> {
+    loaded.update_files = UpdateFileStore::load_dump(src, dst)?;
+    // The prefixed imports can be merged with an existing database, whose task uids the ones
+    // of the dump would collide with.
+    UpdateStore::load_dump(src, dst, update_db_size, uuids, prefix.is_some())?;
+    loaded.updates = true;
+    let prefix = prefix.unwrap_or_default();
+    loaded.keys = ApiKeyStore::load_dump(src, dst, prefix)?;
+    loaded.webhooks = WebhookStore::load_dump(src, dst, prefix)?;
+    IndexResolver::load_dump_uids(src, dst, uuids, prefix, report)?;
+
     Ok(())
 }
@@ -11,20 +11,28 @@ use loaders::v1::MetadataV1;
 
 pub use actor::DumpActor;
 pub use handle_impl::*;
+pub use import_report::{DumpImportReport, FailedIndexImport};
+pub use inspect::{inspect_dump, DumpManifest, IndexManifest, SettingsSummary};
 pub use message::DumpMsg;
 
-use super::index_resolver::HardStateIndexResolver;
+use super::api_keys::ApiKeyStore;
+use super::index_resolver::{is_index_uid_valid, HardStateIndexResolver};
 use super::updates::UpdateSender;
+use super::versioning::check_dump_version;
+use super::webhooks::WebhookStore;
 use crate::compression::{from_tar_gz, to_tar_gz};
 use crate::index_controller::dump_actor::error::DumpActorError;
 use crate::index_controller::dump_actor::loaders::{v2, v3};
 use crate::index_controller::updates::UpdateMsg;
+use crate::io_throttle::IoThrottle;
 use crate::options::IndexerOpts;
 use error::Result;
 
 mod actor;
 pub mod error;
 mod handle_impl;
+mod import_report;
+mod inspect;
 mod loaders;
 mod message;
 
@@ -151,6 +159,7 @@ pub fn load_dump(
     index_db_size: usize,
     update_db_size: usize,
     indexer_opts: &IndexerOpts,
+    ignore_errors: bool,
 ) -> anyhow::Result<()> {
     // Setup a temp directory path in the same path as the database, to prevent cross devices
     // references.
@@ -172,9 +181,37 @@ pub fn load_dump(
 
     let meta_path = tmp_src_path.join(META_FILE_NAME);
     let mut meta_file = File::open(&meta_path)?;
-    let meta: MetadataVersion = serde_json::from_reader(&mut meta_file)?;
-
+    // The version is checked before the metadata is parsed, since the metadata of the dumps of
+    // the newer versions may not be understood.
+    let meta: serde_json::Value = serde_json::from_reader(&mut meta_file)?;
+    match meta.get("dbVersion").and_then(|version| version.as_str()) {
+        Some(version) => check_dump_version(version)?,
+        None => anyhow::bail!("The dump metadata has no engine version."),
+    }
+    let meta: MetadataVersion = serde_json::from_value(meta)?;
+
+    // With a prefix, the indexes of the dump are loaded next to the ones of the existing
+    // database, instead of replacing it.
+    let prefix = indexer_opts.dump_import_prefix.as_deref();
+    if let Some(prefix) = prefix {
+        if prefix.is_empty() || !is_index_uid_valid(prefix) {
+            anyhow::bail!(
+                "Invalid dump import prefix `{}`, it can only be composed of alphanumeric characters, hyphens (-) and underscores (_).",
+                prefix
+            );
+        }
+    }
+    let merge = prefix.is_some() && dst_path.as_ref().exists();
     let tmp_dst = tempfile::tempdir()?;
+    let load_path = if merge {
+        info!(
+            "Loading the dump next to the indexes of the database at {}",
+            dst_path.as_ref().display()
+        );
+        dst_path.as_ref().to_owned()
+    } else {
+        tmp_dst.path().to_owned()
+    };
 
     info!(
         "Loading dump {}, dump database version: {}, dump version: {}",
@@ -185,27 +222,46 @@ pub fn load_dump(
         meta.version()
     );
 
-    match meta {
-        MetadataVersion::V1(meta) => {
-            meta.load_dump(&tmp_src_path, tmp_dst.path(), index_db_size, indexer_opts)?
-        }
+    let report = match meta {
+        MetadataVersion::V1(meta) => meta.load_dump(
+            &tmp_src_path,
+            &load_path,
+            index_db_size,
+            indexer_opts,
+            ignore_errors,
+        )?,
         MetadataVersion::V2(meta) => v2::load_dump(
             meta,
             &tmp_src_path,
-            tmp_dst.path(),
+            &load_path,
             index_db_size,
             update_db_size,
             indexer_opts,
+            ignore_errors,
         )?,
         MetadataVersion::V3(meta) => v3::load_dump(
             meta,
             &tmp_src_path,
-            tmp_dst.path(),
+            &load_path,
             index_db_size,
             update_db_size,
             indexer_opts,
+            ignore_errors,
         )?,
+    };
+    if !report.failed_indexes.is_empty() {
+        warn!(
+            "{} indexes of the dump failed to load and were skipped, see the dump import report.",
+            report.failed_indexes.len()
+        );
+    }
+    if ignore_errors {
+        report.persist(&load_path)?;
+    }
+    if merge {
+        return Ok(());
     }
+
     // Persist and atomically rename the db
     let persisted_dump = tmp_dst.into_path();
     if dst_path.as_ref().exists() {
@@ -222,9 +278,12 @@ struct DumpTask {
     path: PathBuf,
     index_resolver: Arc<HardStateIndexResolver>,
     update_handle: UpdateSender,
+    api_keys: ApiKeyStore,
+    webhooks: WebhookStore,
     uid: String,
     update_db_size: usize,
     index_db_size: usize,
+    io_throttle: Option<u64>,
 }
 
 impl DumpTask {
@@ -241,13 +300,38 @@ impl DumpTask {
         let mut meta_file = File::create(&meta_path)?;
         serde_json::to_writer(&mut meta_file, &meta)?;
 
-        let uuids = self.index_resolver.dump(temp_dump_path.clone()).await?;
-
-        UpdateMsg::dump(&self.update_handle, uuids, temp_dump_path.clone()).await?;
+        // The writes of the dump share the rate of the throttle.
+        let throttle = self.io_throttle.map(IoThrottle::new);
+        let uuids = self
+            .index_resolver
+            .dump(temp_dump_path.clone(), throttle.clone())
+            .await?;
+
+        UpdateMsg::dump(
+            &self.update_handle,
+            uuids,
+            temp_dump_path.clone(),
+            throttle.clone(),
+        )
+        .await?;
+
+        let api_keys = self.api_keys.clone();
+        let keys_path = temp_dump_path.clone();
+        let keys_throttle = throttle.clone();
+        tokio::task::spawn_blocking(move || api_keys.dump(keys_path, keys_throttle.as_ref()))
+            .await??;
+
+        let webhooks = self.webhooks.clone();
+        let webhooks_path = temp_dump_path.clone();
+        let webhooks_throttle = throttle.clone();
+        tokio::task::spawn_blocking(move || {
+            webhooks.dump(webhooks_path, webhooks_throttle.as_ref())
+        })
+        .await??;
 
         let dump_path = tokio::task::spawn_blocking(move || -> Result<PathBuf> {
             let temp_dump_file = tempfile::NamedTempFile::new()?;
-            to_tar_gz(temp_dump_path, temp_dump_file.path())
+            to_tar_gz(temp_dump_path, temp_dump_file.path(), throttle.as_ref())
                 .map_err(|e| DumpActorError::Internal(e.into()))?;
 
             let dump_path = self.path.join(self.uid).with_extension("dump");
@@ -262,3 +346,82 @@ impl DumpTask {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::fs::{create_dir_all, write};
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::index_controller::index_resolver::uuid_store::HeedUuidStore;
+
+    const DB_SIZE: usize = 100 * 1024 * 1024;
+
+    /// Writes the dump of an index `movies` whose updates are `updates`.
+    fn write_dump(dir: &Path, name: &str, updates: &str) -> PathBuf {
+        let content = dir.join(name);
+        let uuid = Uuid::new_v4();
+        let index = content.join("indexes").join(uuid.to_string());
+        create_dir_all(&index).unwrap();
+        write(
+            index.join("meta.json"),
+            r#"{ "settings": {}, "primary_key": "id" }"#,
+        )
+        .unwrap();
+        write(index.join("documents.jsonl"), "{\"id\":1}\n").unwrap();
+        create_dir_all(content.join("index_uuids")).unwrap();
+        write(
+            content.join("index_uuids").join("data.jsonl"),
+            format!("{{\"uuid\":\"{}\",\"uid\":\"movies\"}}\n", uuid),
+        )
+        .unwrap();
+        create_dir_all(content.join("updates")).unwrap();
+        write(content.join("updates").join("data.jsonl"), updates).unwrap();
+        let meta = MetadataVersion::new_v3(DB_SIZE, DB_SIZE);
+        serde_json::to_writer(File::create(content.join(META_FILE_NAME)).unwrap(), &meta).unwrap();
+
+        let path = dir.join(name).with_extension("dump");
+        to_tar_gz(&content, &path, None).unwrap();
+        path
+    }
+
+    fn load_with_prefix(dst: &Path, dump: &Path, prefix: &str) -> anyhow::Result<()> {
+        let indexer_opts = IndexerOpts {
+            dump_import_prefix: Some(prefix.to_string()),
+            ..IndexerOpts::default()
+        };
+        load_dump(dst, dump, DB_SIZE, DB_SIZE, &indexer_opts, false)
+    }
+
+    #[test]
+    fn merge_prefixed_imports() {
+        let dir = tempfile::tempdir().unwrap();
+        let dst = dir.path().join("data.ms");
+        let dump = write_dump(dir.path(), "valid", "");
+        let invalid = write_dump(dir.path(), "invalid", "not an update\n");
+
+        // The same dump can be imported twice, its index taking a new uuid each time.
+        load_with_prefix(&dst, &dump, "a_").unwrap();
+        load_with_prefix(&dst, &dump, "b_").unwrap();
+        // A failed import leaves nothing behind.
+        assert!(load_with_prefix(&dst, &invalid, "c_").is_err());
+
+        let uuids = HeedUuidStore::new(&dst).unwrap();
+        let a = uuids.get_uuid("a_movies").unwrap().unwrap();
+        let b = uuids.get_uuid("b_movies").unwrap().unwrap();
+        assert_ne!(a, b);
+        assert!(uuids.get_uuid("c_movies").unwrap().is_none());
+
+        let mut indexes: Vec<_> = dst
+            .join("indexes")
+            .read_dir()
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        indexes.sort();
+        let mut expected = vec![a.to_string().into(), b.to_string().into()];
+        expected.sort();
+        assert_eq!(indexes, expected);
+    }
+}
@@ -0,0 +1,158 @@
+//! The operations of the engine behind a trait, so that the applications embedding it can mock it
+//! in their tests or put another implementation, such as a proxy to a remote instance, behind the
+//! same interface.
+
+use crate::index::{
+    Checked, Document, IndexStats, PrimaryKey, SearchQuery, SearchResult, Settings,
+};
+
+use super::error::Result;
+use super::updates::status::{UpdateContext, UpdateStatus};
+use super::{IndexController, IndexMetadata, Stats, Update};
+
+#[async_trait::async_trait]
+pub trait Engine: Send + Sync {
+    async fn list_indexes(&self) -> Result<Vec<IndexMetadata>>;
+
+    async fn get_index(&self, uid: String) -> Result<IndexMetadata>;
+
+    async fn create_index(
+        &self,
+        uid: String,
+        primary_key: Option<PrimaryKey>,
+    ) -> Result<IndexMetadata>;
+
+    async fn delete_index(&self, uid: String) -> Result<()>;
+
+    /// Enqueues an update of the index, the documents additions and the settings updates among
+    /// others, creating the index if it doesn't exist and `create_index` is set.
+    async fn register_update(
+        &self,
+        uid: String,
+        update: Update,
+        create_index: bool,
+        context: UpdateContext,
+    ) -> Result<UpdateStatus>;
+
+    async fn update_status(&self, uid: String, id: u64) -> Result<UpdateStatus>;
+
+    async fn all_update_status(&self, uid: String) -> Result<Vec<UpdateStatus>>;
+
+    async fn settings(&self, uid: String) -> Result<Settings<Checked>>;
+
+    async fn documents(
+        &self,
+        uid: String,
+        offset: usize,
+        limit: usize,
+        attributes_to_retrieve: Option<Vec<String>>,
+    ) -> Result<Vec<Document>>;
+
+    async fn document(
+        &self,
+        uid: String,
+        doc_id: String,
+        attributes_to_retrieve: Option<Vec<String>>,
+    ) -> Result<Document>;
+
+    async fn search(&self, uid: String, query: SearchQuery) -> Result<SearchResult>;
+
+    async fn get_index_stats(&self, uid: String) -> Result<IndexStats>;
+
+    async fn get_all_stats(&self) -> Result<Stats>;
+}
+
+#[async_trait::async_trait]
+impl Engine for IndexController {
+    async fn list_indexes(&self) -> Result<Vec<IndexMetadata>> {
+        IndexController::list_indexes(self).await
+    }
+
+    async fn get_index(&self, uid: String) -> Result<IndexMetadata> {
+        IndexController::get_index(self, uid).await
+    }
+
+    async fn create_index(
+        &self,
+        uid: String,
+        primary_key: Option<PrimaryKey>,
+    ) -> Result<IndexMetadata> {
+        IndexController::create_index(self, uid, primary_key).await
+    }
+
+    async fn delete_index(&self, uid: String) -> Result<()> {
+        IndexController::delete_index(self, uid).await
+    }
+
+    async fn register_update(
+        &self,
+        uid: String,
+        update: Update,
+        create_index: bool,
+        context: UpdateContext,
+    ) -> Result<UpdateStatus> {
+        IndexController::register_update(self, uid, update, create_index, context).await
+    }
+
+    async fn update_status(&self, uid: String, id: u64) -> Result<UpdateStatus> {
+        IndexController::update_status(self, uid, id).await
+    }
+
+    async fn all_update_status(&self, uid: String) -> Result<Vec<UpdateStatus>> {
+        IndexController::all_update_status(self, uid).await
+    }
+
+    async fn settings(&self, uid: String) -> Result<Settings<Checked>> {
+        IndexController::settings(self, uid).await
+    }
+
+    async fn documents(
+        &self,
+        uid: String,
+        offset: usize,
+        limit: usize,
+        attributes_to_retrieve: Option<Vec<String>>,
+    ) -> Result<Vec<Document>> {
+        IndexController::documents(self, uid, offset, limit, attributes_to_retrieve).await
+    }
+
+    async fn document(
+        &self,
+        uid: String,
+        doc_id: String,
+        attributes_to_retrieve: Option<Vec<String>>,
+    ) -> Result<Document> {
+        IndexController::document(self, uid, doc_id, attributes_to_retrieve).await
+    }
+
+    async fn search(&self, uid: String, query: SearchQuery) -> Result<SearchResult> {
+        IndexController::search(self, uid, query).await
+    }
+
+    async fn get_index_stats(&self, uid: String) -> Result<IndexStats> {
+        IndexController::get_index_stats(self, uid).await
+    }
+
+    async fn get_all_stats(&self) -> Result<Stats> {
+        IndexController::get_all_stats(self).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn index_uids(engine: &impl Engine) -> Vec<String> {
+        let indexes = engine.list_indexes().await.unwrap();
+        indexes.into_iter().map(|index| index.uid).collect()
+    }
+
+    #[actix_rt::test]
+    async fn index_controller_engine() {
+        let index_controller = IndexController::new_for_tests().unwrap();
+        Engine::create_index(&index_controller, String::from("test"), None)
+            .await
+            .unwrap();
+        assert_eq!(index_uids(&index_controller).await, ["test"]);
+    }
+}
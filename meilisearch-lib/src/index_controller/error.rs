@@ -6,9 +6,12 @@ use tokio::task::JoinError;
 
 use crate::index::error::IndexError;
 
+use super::api_keys::ApiKeyError;
 use super::dump_actor::error::DumpActorError;
 use super::index_resolver::error::IndexResolverError;
+use super::update_file_store::UpdateFileStoreError;
 use super::updates::error::UpdateLoopError;
+use super::webhooks::WebhookError;
 
 pub type Result<T> = std::result::Result<T, IndexControllerError>;
 
@@ -24,11 +27,38 @@ pub enum IndexControllerError {
     DumpActor(#[from] DumpActorError),
     #[error("{0}")]
     IndexError(#[from] IndexError),
+    #[error("{0}")]
+    ApiKey(#[from] ApiKeyError),
+    #[error("{0}")]
+    Webhook(#[from] WebhookError),
+    #[error("Update {0} was not processed in time, the search can't reflect it.")]
+    UpdateWaitTimeout(u64),
+    #[error("Too many searches are being processed, retry later.")]
+    SearchOverloaded,
+    #[error("Index `{0}` is temporarily unavailable after repeated failures.")]
+    IndexUnavailable(String),
+    #[error("Document {0} is routed to no index by the routing rules.")]
+    UnroutableDocument(usize),
+    #[error("Invalid rollover policy: {0}")]
+    InvalidRolloverPolicy(String),
+    #[error("Update {0} has no payload, it is not a document addition or it was processed.")]
+    MissingUpdatePayload(u64),
+    #[error("The search at position {position} failed: {error}")]
+    MultiSearch {
+        position: usize,
+        error: Box<IndexControllerError>,
+    },
+    #[error("Scroll `{0}` not found, it ended or expired.")]
+    ScrollNotFound(String),
     #[error("Internal error: {0}")]
     Internal(Box<dyn Error + Send + Sync + 'static>),
 }
 
-internal_error!(IndexControllerError: JoinError);
+internal_error!(
+    IndexControllerError: JoinError,
+    serde_json::Error,
+    UpdateFileStoreError
+);
 
 impl ErrorCode for IndexControllerError {
     fn error_code(&self) -> Code {
@@ -38,6 +68,16 @@ impl ErrorCode for IndexControllerError {
             IndexControllerError::UpdateLoop(e) => e.error_code(),
             IndexControllerError::DumpActor(e) => e.error_code(),
             IndexControllerError::IndexError(e) => e.error_code(),
+            IndexControllerError::ApiKey(e) => e.error_code(),
+            IndexControllerError::Webhook(e) => e.error_code(),
+            IndexControllerError::UpdateWaitTimeout(_) => Code::UpdateWaitTimeout,
+            IndexControllerError::SearchOverloaded => Code::SearchOverloaded,
+            IndexControllerError::IndexUnavailable(_) => Code::IndexUnavailable,
+            IndexControllerError::UnroutableDocument(_) => Code::UnroutableDocument,
+            IndexControllerError::InvalidRolloverPolicy(_) => Code::BadRequest,
+            IndexControllerError::MissingUpdatePayload(_) => Code::UpdatePayloadNotFound,
+            IndexControllerError::MultiSearch { error, .. } => error.error_code(),
+            IndexControllerError::ScrollNotFound(_) => Code::ScrollNotFound,
             IndexControllerError::Internal(_) => Code::Internal,
         }
     }
@@ -16,6 +16,12 @@ pub enum IndexResolverError {
     IndexAlreadyExists,
     #[error("Index {0} not found")]
     UnexistingIndex(String),
+    #[error("Alias `{0}` is not found.")]
+    UnexistingAlias(String),
+    #[error("`{0}` is the uid of an index, it can't be an alias.")]
+    AliasOfExistingIndex(String),
+    #[error("Rollover policy `{0}` is not found.")]
+    UnexistingRolloverPolicy(String),
     #[error("A primary key is already present. It's impossible to update it")]
     ExistingPrimaryKey,
     #[error("Internal Error: {0}")]
@@ -55,6 +61,9 @@ impl ErrorCode for IndexResolverError {
             IndexResolverError::IndexError(e) => e.error_code(),
             IndexResolverError::IndexAlreadyExists => Code::IndexAlreadyExists,
             IndexResolverError::UnexistingIndex(_) => Code::IndexNotFound,
+            IndexResolverError::UnexistingAlias(_) => Code::AliasNotFound,
+            IndexResolverError::AliasOfExistingIndex(_) => Code::IndexAlreadyExists,
+            IndexResolverError::UnexistingRolloverPolicy(_) => Code::RolloverPolicyNotFound,
             IndexResolverError::ExistingPrimaryKey => Code::PrimaryKeyAlreadyPresent,
             IndexResolverError::Internal(_) => Code::Internal,
             IndexResolverError::Milli(e) => MilliError(e).error_code(),
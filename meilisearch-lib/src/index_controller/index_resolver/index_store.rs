@@ -2,7 +2,6 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use milli::update::UpdateBuilder;
 use tokio::fs;
 use tokio::sync::RwLock;
 use tokio::task::spawn_blocking;
@@ -10,7 +9,7 @@ use uuid::Uuid;
 
 use super::error::{IndexResolverError, Result};
 use crate::index::update_handler::UpdateHandler;
-use crate::index::Index;
+use crate::index::{Index, PrimaryKey};
 use crate::index_controller::update_file_store::UpdateFileStore;
 use crate::options::IndexerOpts;
 
@@ -18,9 +17,11 @@ type AsyncMap<K, V> = Arc<RwLock<HashMap<K, V>>>;
 
 #[async_trait::async_trait]
 pub trait IndexStore {
-    async fn create(&self, uuid: Uuid, primary_key: Option<String>) -> Result<Index>;
+    async fn create(&self, uuid: Uuid, primary_key: Option<PrimaryKey>) -> Result<Index>;
     async fn get(&self, uuid: Uuid) -> Result<Option<Index>>;
     async fn delete(&self, uuid: Uuid) -> Result<Option<Index>>;
+    /// Creates an index from a copy of the database of another index.
+    async fn restore(&self, uuid: Uuid, src: PathBuf) -> Result<Index>;
 }
 
 pub struct MapIndexStore {
@@ -53,7 +54,7 @@ impl MapIndexStore {
 
 #[async_trait::async_trait]
 impl IndexStore for MapIndexStore {
-    async fn create(&self, uuid: Uuid, primary_key: Option<String>) -> Result<Index> {
+    async fn create(&self, uuid: Uuid, primary_key: Option<PrimaryKey>) -> Result<Index> {
         // We need to keep the lock until we are sure the db file has been opened correclty, to
         // ensure that another db is not created at the same time.
         let mut lock = self.index_store.write().await;
@@ -72,13 +73,7 @@ impl IndexStore for MapIndexStore {
         let index = spawn_blocking(move || -> Result<Index> {
             let index = Index::open(path, index_size, file_store, uuid, update_handler)?;
             if let Some(primary_key) = primary_key {
-                let mut txn = index.write_txn()?;
-
-                let mut builder = UpdateBuilder::new(0).settings(&mut txn, &index);
-                builder.set_primary_key(primary_key);
-                builder.execute(|_, _| ())?;
-
-                txn.commit()?;
+                index.init_primary_key(primary_key)?;
             }
             Ok(index)
         })
@@ -120,4 +115,22 @@ impl IndexStore for MapIndexStore {
         let index = self.index_store.write().await.remove(&uuid);
         Ok(index)
     }
+
+    async fn restore(&self, uuid: Uuid, src: PathBuf) -> Result<Index> {
+        let path = self.path.join(format!("{}", uuid));
+        if path.exists() {
+            return Err(IndexResolverError::IndexAlreadyExists);
+        }
+        fs::create_dir_all(&path).await?;
+        fs::copy(src, path.join("data.mdb")).await?;
+
+        let index_size = self.index_size;
+        let file_store = self.update_file_store.clone();
+        let update_handler = self.update_handler.clone();
+        let index =
+            spawn_blocking(move || Index::open(path, index_size, file_store, uuid, update_handler))
+                .await??;
+        self.index_store.write().await.insert(uuid, index.clone());
+        Ok(index)
+    }
 }
@@ -2,15 +2,24 @@ pub mod error;
 mod index_store;
 pub mod uuid_store;
 
+use std::collections::{BTreeSet, HashMap};
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
 
 use error::{IndexResolverError, Result};
 use index_store::{IndexStore, MapIndexStore};
+use log::{error, info};
+use parking_lot::Mutex;
+use rayon::prelude::*;
 use uuid::Uuid;
 use uuid_store::{HeedUuidStore, UuidStore};
 
 use crate::{
-    index::{update_handler::UpdateHandler, Index},
+    index::{update_handler::UpdateHandler, Index, PrimaryKey},
+    index_controller::dump_actor::{DumpImportReport, FailedIndexImport},
+    index_controller::rollover::RolloverPolicy,
+    io_throttle::IoThrottle,
     options::IndexerOpts,
 };
 
@@ -32,23 +41,142 @@ pub struct IndexResolver<U, I> {
 }
 
 impl IndexResolver<HeedUuidStore, MapIndexStore> {
+    /// Like [`IndexResolver::resolve_alias`], looked up synchronously for the authentication of
+    /// the requests.
+    pub fn resolve_alias_sync(&self, uid: String) -> Result<String> {
+        self.index_uuid_store.resolve_alias(uid)
+    }
+
+    /// Loads the indexes of a dump, returning the report of the import along with the uuid each
+    /// loaded index has in the database. When `ignore_errors` is set, the indexes failing to load
+    /// are skipped, and listed by the report. With a prefix, the dump may be loaded next to the
+    /// indexes of an existing database, its indexes taking new uuids.
+    ///
+    /// The indexes are only reachable once their uids are loaded by
+    /// [`IndexResolver::load_dump_uids`].
     pub fn load_dump(
         src: impl AsRef<Path>,
         dst: impl AsRef<Path>,
         index_db_size: usize,
         indexer_opts: &IndexerOpts,
-    ) -> anyhow::Result<()> {
-        HeedUuidStore::load_dump(&src, &dst)?;
-
+        ignore_errors: bool,
+    ) -> anyhow::Result<(DumpImportReport, HashMap<Uuid, Uuid>)> {
+        let dst = dst.as_ref();
         let indexes_path = src.as_ref().join("indexes");
-        let indexes = indexes_path.read_dir()?;
+        let fresh_uuids = indexer_opts.dump_import_prefix.is_some();
+        let mut indexes = Vec::new();
+        for entry in indexes_path.read_dir()? {
+            let path = entry?.path();
+            let dir_name = path.file_name().unwrap_or_default().to_string_lossy();
+            let uuid = Uuid::parse_str(&dir_name)
+                .map_err(|_| anyhow::anyhow!("Invalid index {} in the dump.", dir_name))?;
+            let db_uuid = if fresh_uuids { Uuid::new_v4() } else { uuid };
+            indexes.push((path, uuid, db_uuid));
+        }
+
+        let prefix = indexer_opts
+            .dump_import_prefix
+            .as_deref()
+            .unwrap_or_default();
+        HeedUuidStore::check_dump_uids(&src, dst, prefix)?;
+
+        // The indexes are loaded in parallel, sharing the indexing threads and memory.
+        let jobs = indexer_opts.dump_import_jobs().min(indexes.len().max(1));
+        let mut indexer_opts = indexer_opts.clone();
+        indexer_opts.max_memory = indexer_opts.max_memory.divided(jobs);
+        let update_handler = UpdateHandler::new(&indexer_opts)?;
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .thread_name(|i| format!("dump-import-{}", i))
+            .build()?;
+
+        info!("Loading {} indexes, {} at a time.", indexes.len(), jobs);
+        let loaded = AtomicUsize::new(0);
+        let failures = Mutex::new(Vec::new());
+        let result = pool.install(|| {
+            indexes
+                .par_iter()
+                .try_for_each(|(path, uuid, db_uuid)| -> anyhow::Result<()> {
+                    let started_at = Instant::now();
+                    match Index::load_dump(path, dst, *db_uuid, index_db_size, &update_handler) {
+                        Ok(()) => {
+                            let loaded = loaded.fetch_add(1, Ordering::Relaxed) + 1;
+                            info!(
+                                "Loaded index {} ({}/{}) in {:.2?}.",
+                                uuid,
+                                loaded,
+                                indexes.len(),
+                                started_at.elapsed()
+                            );
+                        }
+                        Err(e) if ignore_errors => {
+                            error!("Skipping index {}, which failed to load: {}", uuid, e);
+                            Self::remove_dumped_indexes(dst, std::iter::once(db_uuid))?;
+                            failures.lock().push((*uuid, e.to_string()));
+                        }
+                        Err(e) => return Err(e.context(format!("loading index {}", uuid))),
+                    }
+                    Ok(())
+                })
+        });
+        if let Err(e) = result {
+            // The indexes loaded before the failure must not be left in the database.
+            Self::remove_dumped_indexes(dst, indexes.iter().map(|(_, _, db_uuid)| db_uuid))?;
+            return Err(e);
+        }
+
+        let failures = failures.into_inner();
+        let uuids = indexes
+            .into_iter()
+            .filter(|(_, uuid, _)| failures.iter().all(|(failed, _)| failed != uuid))
+            .map(|(_, uuid, db_uuid)| (uuid, db_uuid))
+            .collect();
+
+        let mut report = DumpImportReport::new();
+        report.imported_indexes = loaded.into_inner();
+        report.failed_indexes = failures
+            .into_iter()
+            .map(|(uuid, error)| FailedIndexImport {
+                uid: uuid.to_string(),
+                error,
+            })
+            .collect();
+
+        Ok((report, uuids))
+    }
 
-        let update_handler = UpdateHandler::new(indexer_opts)?;
-        for index in indexes {
-            let index = index?;
-            Index::load_dump(&index.path(), &dst, index_db_size, &update_handler)?;
+    /// Loads the uids of the indexes of a dump loaded by [`IndexResolver::load_dump`], prefixed
+    /// with `prefix`, naming the indexes that failed to load in the `report`.
+    pub fn load_dump_uids(
+        src: impl AsRef<Path>,
+        dst: impl AsRef<Path>,
+        uuids: &HashMap<Uuid, Uuid>,
+        prefix: &str,
+        report: &mut DumpImportReport,
+    ) -> anyhow::Result<()> {
+        let skipped_uids = HeedUuidStore::load_dump(&src, dst, uuids, prefix)?;
+        for failed in &mut report.failed_indexes {
+            let uid = Uuid::parse_str(&failed.uid)
+                .ok()
+                .and_then(|uuid| skipped_uids.get(&uuid));
+            if let Some(uid) = uid {
+                failed.uid = uid.clone();
+            }
         }
+        Ok(())
+    }
 
+    /// Removes the databases of the indexes with these uuids, loaded from a dump.
+    pub fn remove_dumped_indexes<'a>(
+        dst: impl AsRef<Path>,
+        uuids: impl IntoIterator<Item = &'a Uuid>,
+    ) -> std::io::Result<()> {
+        for uuid in uuids {
+            let path = dst.as_ref().join("indexes").join(uuid.to_string());
+            if path.exists() {
+                std::fs::remove_dir_all(path)?;
+            }
+        }
         Ok(())
     }
 }
@@ -65,8 +193,15 @@ where
         }
     }
 
-    pub async fn dump(&self, path: impl AsRef<Path>) -> Result<Vec<Index>> {
-        let uuids = self.index_uuid_store.dump(path.as_ref().to_owned()).await?;
+    pub async fn dump(
+        &self,
+        path: impl AsRef<Path>,
+        throttle: Option<IoThrottle>,
+    ) -> Result<Vec<Index>> {
+        let uuids = self
+            .index_uuid_store
+            .dump(path.as_ref().to_owned(), throttle)
+            .await?;
         let mut indexes = Vec::new();
         for uuid in uuids {
             indexes.push(self.get_index_by_uuid(uuid).await?);
@@ -79,10 +214,14 @@ where
         Ok(self.index_uuid_store.get_size().await?)
     }
 
-    pub async fn snapshot(&self, path: impl AsRef<Path>) -> Result<Vec<Index>> {
+    pub async fn snapshot(
+        &self,
+        path: impl AsRef<Path>,
+        throttle: Option<IoThrottle>,
+    ) -> Result<Vec<Index>> {
         let uuids = self
             .index_uuid_store
-            .snapshot(path.as_ref().to_owned())
+            .snapshot(path.as_ref().to_owned(), throttle)
             .await?;
         let mut indexes = Vec::new();
         for uuid in uuids {
@@ -92,7 +231,11 @@ where
         Ok(indexes)
     }
 
-    pub async fn create_index(&self, uid: String, primary_key: Option<String>) -> Result<Index> {
+    pub async fn create_index(
+        &self,
+        uid: String,
+        primary_key: Option<PrimaryKey>,
+    ) -> Result<Index> {
         if !is_index_uid_valid(&uid) {
             return Err(IndexResolverError::BadlyFormatted(uid));
         }
@@ -128,6 +271,45 @@ where
         }
     }
 
+    /// Replaces the index by the copy of its database taken before the settings update
+    /// `update_id`, as a new index under the same uid. Returns the uuid of the replaced index,
+    /// whose database is deleted, along with the restored index.
+    pub async fn restore_checkpoint(&self, uid: String, update_id: u64) -> Result<(Uuid, Index)> {
+        let uuid = self.get_uuid(uid.clone()).await?;
+        let checkpoint = self
+            .get_index_by_uuid(uuid)
+            .await?
+            .checkpoint_path(update_id)?;
+
+        let restored_uuid = Uuid::new_v4();
+        let restored = self.index_store.restore(restored_uuid, checkpoint).await?;
+        if self
+            .index_uuid_store
+            .replace(uid.clone(), restored_uuid)
+            .await?
+            .is_none()
+        {
+            // The index was deleted meanwhile.
+            let _ = self.index_store.delete(restored_uuid).await;
+            return Err(IndexResolverError::UnexistingIndex(uid));
+        }
+        self.index_store.delete(uuid).await?;
+
+        Ok((uuid, restored))
+    }
+
+    /// The number of documents of all the indexes but the one with this uuid.
+    pub async fn count_other_documents(&self, uuid: Uuid) -> Result<u64> {
+        let mut documents = 0;
+        for (_, index) in self.list().await? {
+            if index.uuid != uuid {
+                let rtxn = index.read_txn()?;
+                documents += index.number_of_documents(&rtxn)?;
+            }
+        }
+        Ok(documents)
+    }
+
     pub async fn get_index_by_uuid(&self, uuid: Uuid) -> Result<Index> {
         // TODO: Handle this error better.
         self.index_store
@@ -153,6 +335,84 @@ where
         }
     }
 
+    pub async fn put_alias(&self, alias: String, uid: String) -> Result<()> {
+        if !is_index_uid_valid(&alias) {
+            return Err(IndexResolverError::BadlyFormatted(alias));
+        }
+        self.index_uuid_store.put_alias(alias, uid).await
+    }
+
+    pub async fn delete_alias(&self, alias: String) -> Result<String> {
+        match self.index_uuid_store.delete_alias(alias.clone()).await? {
+            Some(uid) => Ok(uid),
+            None => Err(IndexResolverError::UnexistingAlias(alias)),
+        }
+    }
+
+    pub async fn get_alias(&self, alias: String) -> Result<String> {
+        let uid = self.index_uuid_store.resolve_alias(alias.clone()).await?;
+        if uid == alias {
+            return Err(IndexResolverError::UnexistingAlias(alias));
+        }
+        Ok(uid)
+    }
+
+    pub async fn list_aliases(&self) -> Result<Vec<(String, String)>> {
+        self.index_uuid_store.list_aliases().await
+    }
+
+    /// Returns the uid of the index targeted by `uid` if it is an alias, `uid` itself otherwise.
+    pub async fn resolve_alias(&self, uid: String) -> Result<String> {
+        self.index_uuid_store.resolve_alias(uid).await
+    }
+
+    pub async fn put_rollover_policy(&self, name: String, policy: RolloverPolicy) -> Result<()> {
+        if !is_index_uid_valid(&name) {
+            return Err(IndexResolverError::BadlyFormatted(name));
+        }
+        self.index_uuid_store
+            .put_rollover_policy(name, policy)
+            .await
+    }
+
+    pub async fn get_rollover_policy(&self, name: String) -> Result<Option<RolloverPolicy>> {
+        self.index_uuid_store.get_rollover_policy(name).await
+    }
+
+    pub async fn delete_rollover_policy(&self, name: String) -> Result<Option<RolloverPolicy>> {
+        self.index_uuid_store.delete_rollover_policy(name).await
+    }
+
+    pub async fn list_rollover_policies(&self) -> Result<Vec<(String, RolloverPolicy)>> {
+        self.index_uuid_store.list_rollover_policies().await
+    }
+
+    /// Returns the uids of the indexes matched by the `patterns`, sorted and deduplicated. The
+    /// patterns without a `*` are returned as they are, so that a missing index can be reported.
+    pub async fn resolve_patterns(&self, patterns: &[String]) -> Result<Vec<String>> {
+        let index_uids = if patterns.iter().any(|pattern| pattern.contains('*')) {
+            self.index_uuid_store.list().await?
+        } else {
+            Vec::new()
+        };
+
+        let mut uids = BTreeSet::new();
+        for pattern in patterns {
+            if !pattern.contains('*') {
+                uids.insert(pattern.clone());
+                continue;
+            }
+
+            let matched = index_uids
+                .iter()
+                .filter(|(uid, _)| uid_matches_pattern(pattern, uid))
+                .map(|(uid, _)| uid.clone());
+            uids.extend(matched);
+        }
+
+        Ok(uids.into_iter().collect())
+    }
+
     pub async fn get_uuid(&self, uid: String) -> Result<Uuid> {
         match self.index_uuid_store.get_uuid(uid).await? {
             (_, Some(uuid)) => Ok(uuid),
@@ -161,7 +421,54 @@ where
     }
 }
 
-fn is_index_uid_valid(uid: &str) -> bool {
+pub(crate) fn is_index_uid_valid(uid: &str) -> bool {
     uid.chars()
         .all(|x| x.is_ascii_alphanumeric() || x == '-' || x == '_')
 }
+
+/// Whether `uid` is matched by `pattern`, in which `*` matches any sequence of characters, such as
+/// `logs-2021-*` for time-partitioned indexes.
+fn uid_matches_pattern(pattern: &str, uid: &str) -> bool {
+    let mut parts = pattern.split('*');
+    // There is always a first part, that must be a prefix of the uid.
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match uid.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let mut parts: Vec<_> = parts.collect();
+    let last = match parts.pop() {
+        Some(last) => last,
+        // There is no `*` in the pattern.
+        None => return rest.is_empty(),
+    };
+
+    for part in parts {
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn match_uid_patterns() {
+        assert!(uid_matches_pattern("logs-2021-*", "logs-2021-01"));
+        assert!(uid_matches_pattern("logs-2021-*", "logs-2021-"));
+        assert!(!uid_matches_pattern("logs-2021-*", "logs-2022-01"));
+        assert!(uid_matches_pattern("logs-*-eu", "logs-2021-01-eu"));
+        assert!(!uid_matches_pattern("logs-*-eu", "logs-2021-01-us"));
+        assert!(uid_matches_pattern("*", "movies"));
+        assert!(uid_matches_pattern("*movies*", "old-movies-v2"));
+        assert!(!uid_matches_pattern("a*a", "a"));
+        assert!(uid_matches_pattern("movies", "movies"));
+        assert!(!uid_matches_pattern("movies", "movies-v2"));
+    }
+}
@@ -1,14 +1,16 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs::{create_dir_all, File};
 use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 
-use heed::types::{ByteSlice, Str};
-use heed::{CompactionOption, Database, Env, EnvOpenOptions};
+use heed::types::{ByteSlice, SerdeJson, Str};
+use heed::{Database, Env, EnvOpenOptions};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use super::error::{IndexResolverError, Result};
+use crate::index_controller::rollover::RolloverPolicy;
+use crate::io_throttle::{compacted_env_copy, create_file, IoThrottle};
 use crate::EnvSizer;
 
 const UUID_STORE_SIZE: usize = 1_073_741_824; //1GiB
@@ -19,7 +21,21 @@ struct DumpEntry {
     uid: String,
 }
 
+#[derive(Serialize, Deserialize)]
+struct AliasDumpEntry {
+    alias: String,
+    uid: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RolloverDumpEntry {
+    name: String,
+    policy: RolloverPolicy,
+}
+
 const UUIDS_DB_PATH: &str = "index_uuids";
+const ALIASES_DUMP_FILE: &str = "aliases.jsonl";
+const ROLLOVER_DUMP_FILE: &str = "rollover.jsonl";
 
 #[async_trait::async_trait]
 pub trait UuidStore: Sized {
@@ -29,15 +45,30 @@ pub trait UuidStore: Sized {
     async fn delete(&self, uid: String) -> Result<Option<Uuid>>;
     async fn list(&self) -> Result<Vec<(String, Uuid)>>;
     async fn insert(&self, name: String, uuid: Uuid) -> Result<()>;
-    async fn snapshot(&self, path: PathBuf) -> Result<HashSet<Uuid>>;
+    async fn replace(&self, name: String, uuid: Uuid) -> Result<Option<Uuid>>;
+    async fn snapshot(&self, path: PathBuf, throttle: Option<IoThrottle>) -> Result<HashSet<Uuid>>;
     async fn get_size(&self) -> Result<u64>;
-    async fn dump(&self, path: PathBuf) -> Result<HashSet<Uuid>>;
+    async fn dump(&self, path: PathBuf, throttle: Option<IoThrottle>) -> Result<HashSet<Uuid>>;
+    /// Points `alias` to the index `uid`, replacing its previous target atomically.
+    async fn put_alias(&self, alias: String, uid: String) -> Result<()>;
+    async fn delete_alias(&self, alias: String) -> Result<Option<String>>;
+    async fn list_aliases(&self) -> Result<Vec<(String, String)>>;
+    /// Returns the uid of the index targeted by `uid` if it is an alias, `uid` itself otherwise.
+    async fn resolve_alias(&self, uid: String) -> Result<String>;
+    async fn put_rollover_policy(&self, name: String, policy: RolloverPolicy) -> Result<()>;
+    async fn get_rollover_policy(&self, name: String) -> Result<Option<RolloverPolicy>>;
+    async fn delete_rollover_policy(&self, name: String) -> Result<Option<RolloverPolicy>>;
+    async fn list_rollover_policies(&self) -> Result<Vec<(String, RolloverPolicy)>>;
 }
 
 #[derive(Clone)]
 pub struct HeedUuidStore {
     env: Env,
     db: Database<Str, ByteSlice>,
+    /// The uids of the indexes targeted by the aliases.
+    aliases: Database<Str, Str>,
+    /// The rollover policies, by the name prefixing their partitions.
+    rollover_policies: Database<Str, SerdeJson<RolloverPolicy>>,
 }
 
 impl HeedUuidStore {
@@ -46,10 +77,17 @@ impl HeedUuidStore {
         create_dir_all(&path)?;
         let mut options = EnvOpenOptions::new();
         options.map_size(UUID_STORE_SIZE); // 1GB
-        options.max_dbs(1);
+        options.max_dbs(3);
         let env = options.open(path)?;
         let db = env.create_database(Some("uuids"))?;
-        Ok(Self { env, db })
+        let aliases = env.create_database(Some("aliases"))?;
+        let rollover_policies = env.create_database(Some("rollover-policies"))?;
+        Ok(Self {
+            env,
+            db,
+            aliases,
+            rollover_policies,
+        })
     }
 
     pub fn get_uuid(&self, name: &str) -> Result<Option<Uuid>> {
@@ -73,6 +111,19 @@ impl HeedUuidStore {
             Some(uuid) => {
                 let uuid = Uuid::from_slice(uuid)?;
                 db.delete(&mut txn, &uid)?;
+
+                // The aliases of a deleted index are deleted with it.
+                let mut aliases = Vec::new();
+                for entry in self.aliases.iter(&txn)? {
+                    let (alias, target) = entry?;
+                    if target == uid {
+                        aliases.push(alias.to_string());
+                    }
+                }
+                for alias in aliases {
+                    self.aliases.delete(&mut txn, &alias)?;
+                }
+
                 txn.commit()?;
                 Ok(Some(uuid))
             }
@@ -98,7 +149,10 @@ impl HeedUuidStore {
         let db = self.db;
         let mut txn = env.write_txn()?;
 
-        if db.get(&txn, &name)?.is_some() {
+        if db.get(&txn, &name)?.is_some()
+            || self.aliases.get(&txn, &name)?.is_some()
+            || self.rollover_policies.get(&txn, &name)?.is_some()
+        {
             return Err(IndexResolverError::IndexAlreadyExists);
         }
 
@@ -107,7 +161,109 @@ impl HeedUuidStore {
         Ok(())
     }
 
-    pub fn snapshot(&self, mut path: PathBuf) -> Result<HashSet<Uuid>> {
+    /// Points an existing index uid to another index, its aliases following it. Returns the uuid
+    /// of the replaced index.
+    pub fn replace(&self, name: String, uuid: Uuid) -> Result<Option<Uuid>> {
+        let env = self.env.clone();
+        let db = self.db;
+        let mut txn = env.write_txn()?;
+        let replaced = match db.get(&txn, &name)? {
+            Some(replaced) => Uuid::from_slice(replaced)?,
+            None => return Ok(None),
+        };
+
+        db.put(&mut txn, &name, uuid.as_bytes())?;
+        txn.commit()?;
+        Ok(Some(replaced))
+    }
+
+    pub fn put_alias(&self, alias: String, uid: String) -> Result<()> {
+        let mut txn = self.env.write_txn()?;
+
+        if self.db.get(&txn, &alias)?.is_some()
+            || self.rollover_policies.get(&txn, &alias)?.is_some()
+        {
+            return Err(IndexResolverError::AliasOfExistingIndex(alias));
+        }
+        if self.db.get(&txn, &uid)?.is_none() {
+            return Err(IndexResolverError::UnexistingIndex(uid));
+        }
+
+        self.aliases.put(&mut txn, &alias, &uid)?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    pub fn delete_alias(&self, alias: String) -> Result<Option<String>> {
+        let mut txn = self.env.write_txn()?;
+        let uid = self.aliases.get(&txn, &alias)?.map(String::from);
+        if uid.is_some() {
+            self.aliases.delete(&mut txn, &alias)?;
+            txn.commit()?;
+        }
+        Ok(uid)
+    }
+
+    pub fn list_aliases(&self) -> Result<Vec<(String, String)>> {
+        let txn = self.env.read_txn()?;
+        let mut aliases = Vec::new();
+        for entry in self.aliases.iter(&txn)? {
+            let (alias, uid) = entry?;
+            aliases.push((alias.to_string(), uid.to_string()));
+        }
+        Ok(aliases)
+    }
+
+    pub fn resolve_alias(&self, uid: String) -> Result<String> {
+        let txn = self.env.read_txn()?;
+        match self.aliases.get(&txn, &uid)? {
+            Some(target) => Ok(target.to_string()),
+            None => Ok(uid),
+        }
+    }
+
+    pub fn put_rollover_policy(&self, name: String, policy: RolloverPolicy) -> Result<()> {
+        let mut txn = self.env.write_txn()?;
+
+        if self.db.get(&txn, &name)?.is_some() || self.aliases.get(&txn, &name)?.is_some() {
+            return Err(IndexResolverError::IndexAlreadyExists);
+        }
+
+        self.rollover_policies.put(&mut txn, &name, &policy)?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    pub fn get_rollover_policy(&self, name: &str) -> Result<Option<RolloverPolicy>> {
+        let txn = self.env.read_txn()?;
+        Ok(self.rollover_policies.get(&txn, name)?)
+    }
+
+    pub fn delete_rollover_policy(&self, name: String) -> Result<Option<RolloverPolicy>> {
+        let mut txn = self.env.write_txn()?;
+        let policy = self.rollover_policies.get(&txn, &name)?;
+        if policy.is_some() {
+            self.rollover_policies.delete(&mut txn, &name)?;
+            txn.commit()?;
+        }
+        Ok(policy)
+    }
+
+    pub fn list_rollover_policies(&self) -> Result<Vec<(String, RolloverPolicy)>> {
+        let txn = self.env.read_txn()?;
+        let mut policies = Vec::new();
+        for entry in self.rollover_policies.iter(&txn)? {
+            let (name, policy) = entry?;
+            policies.push((name.to_string(), policy));
+        }
+        Ok(policies)
+    }
+
+    pub fn snapshot(
+        &self,
+        mut path: PathBuf,
+        throttle: Option<&IoThrottle>,
+    ) -> Result<HashSet<Uuid>> {
         let env = self.env.clone();
         let db = self.db;
         // Write transaction to acquire a lock on the database.
@@ -124,7 +280,7 @@ impl HeedUuidStore {
             path.push(UUIDS_DB_PATH);
             create_dir_all(&path).unwrap();
             path.push("data.mdb");
-            env.copy_to_path(path, CompactionOption::Enabled)?;
+            compacted_env_copy(&env, &path, throttle)?;
         }
         Ok(entries)
     }
@@ -133,11 +289,11 @@ impl HeedUuidStore {
         Ok(self.env.size())
     }
 
-    pub fn dump(&self, path: PathBuf) -> Result<HashSet<Uuid>> {
+    pub fn dump(&self, path: PathBuf, throttle: Option<&IoThrottle>) -> Result<HashSet<Uuid>> {
         let dump_path = path.join(UUIDS_DB_PATH);
         create_dir_all(&dump_path)?;
         let dump_file_path = dump_path.join("data.jsonl");
-        let mut dump_file = File::create(&dump_file_path)?;
+        let mut dump_file = create_file(&dump_file_path, throttle)?;
         let mut uuids = HashSet::new();
 
         let txn = self.env.read_txn()?;
@@ -153,10 +309,84 @@ impl HeedUuidStore {
             uuids.insert(uuid);
         }
 
+        let mut aliases_file = create_file(dump_path.join(ALIASES_DUMP_FILE), throttle)?;
+        for entry in self.aliases.iter(&txn)? {
+            let (alias, uid) = entry?;
+            let entry = AliasDumpEntry {
+                alias: alias.to_string(),
+                uid: uid.to_string(),
+            };
+            serde_json::to_writer(&mut aliases_file, &entry)?;
+            aliases_file.write_all(b"\n")?;
+        }
+
+        let mut rollover_file = create_file(dump_path.join(ROLLOVER_DUMP_FILE), throttle)?;
+        for entry in self.rollover_policies.iter(&txn)? {
+            let (name, policy) = entry?;
+            let entry = RolloverDumpEntry {
+                name: name.to_string(),
+                policy,
+            };
+            serde_json::to_writer(&mut rollover_file, &entry)?;
+            rollover_file.write_all(b"\n")?;
+        }
+
         Ok(uuids)
     }
 
-    pub fn load_dump(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<()> {
+    /// Fails if one of the uids or aliases of the dump, once prefixed with `prefix`, is already
+    /// used by the database at `dst`, into which the dump is then loaded.
+    pub fn check_dump_uids(
+        src: impl AsRef<Path>,
+        dst: impl AsRef<Path>,
+        prefix: &str,
+    ) -> anyhow::Result<()> {
+        let db = Self::new(dst)?;
+        let txn = db.env.read_txn()?;
+        let is_used = |name: &str| -> heed::Result<bool> {
+            Ok(db.db.get(&txn, name)?.is_some()
+                || db.aliases.get(&txn, name)?.is_some()
+                || db.rollover_policies.get(&txn, name)?.is_some())
+        };
+
+        let mut names = Vec::new();
+        let src_indexes = src.as_ref().join(UUIDS_DB_PATH).join("data.jsonl");
+        for line in BufReader::new(File::open(&src_indexes)?).lines() {
+            let DumpEntry { uid, .. } = serde_json::from_str(&line?)?;
+            names.push(uid);
+        }
+        let src_aliases = src.as_ref().join(UUIDS_DB_PATH).join(ALIASES_DUMP_FILE);
+        if src_aliases.exists() {
+            for line in BufReader::new(File::open(&src_aliases)?).lines() {
+                let AliasDumpEntry { alias, .. } = serde_json::from_str(&line?)?;
+                names.push(alias);
+            }
+        }
+
+        for name in names {
+            let name = format!("{}{}", prefix, name);
+            if is_used(&name)? {
+                anyhow::bail!(
+                    "The index `{}` of the dump already exists in the database.",
+                    name
+                );
+            }
+        }
+        drop(txn);
+        db.env.prepare_for_closing().wait();
+
+        Ok(())
+    }
+
+    /// Loads the uids of the dumped indexes that were loaded, with the uuid `uuids` maps their
+    /// dumped uuid to. The uids of the skipped indexes are returned. The uids, aliases and
+    /// rollover policies are prefixed with `prefix`.
+    pub fn load_dump(
+        src: impl AsRef<Path>,
+        dst: impl AsRef<Path>,
+        uuids: &HashMap<Uuid, Uuid>,
+        prefix: &str,
+    ) -> Result<HashMap<Uuid, String>> {
         let uuid_resolver_path = dst.as_ref().join(UUIDS_DB_PATH);
         std::fs::create_dir_all(&uuid_resolver_path)?;
 
@@ -167,24 +397,53 @@ impl HeedUuidStore {
 
         let db = Self::new(dst)?;
         let mut txn = db.env.write_txn()?;
+        let mut skipped_uids = HashMap::new();
 
         loop {
             match indexes.read_line(&mut line) {
                 Ok(0) => break,
                 Ok(_) => {
                     let DumpEntry { uuid, uid } = serde_json::from_str(&line)?;
-                    db.db.put(&mut txn, &uid, uuid.as_bytes())?;
+                    let uid = format!("{}{}", prefix, uid);
+                    match uuids.get(&uuid) {
+                        Some(db_uuid) => db.db.put(&mut txn, &uid, db_uuid.as_bytes())?,
+                        None => {
+                            skipped_uids.insert(uuid, uid);
+                        }
+                    }
                 }
                 Err(e) => return Err(e.into()),
             }
 
             line.clear();
         }
+
+        // The dumps created before the aliases don't hold any.
+        let src_aliases = src.as_ref().join(UUIDS_DB_PATH).join(ALIASES_DUMP_FILE);
+        if src_aliases.exists() {
+            for line in BufReader::new(File::open(&src_aliases)?).lines() {
+                let AliasDumpEntry { alias, uid } = serde_json::from_str(&line?)?;
+                let (alias, uid) = (format!("{}{}", prefix, alias), format!("{}{}", prefix, uid));
+                // The aliases of the skipped indexes are skipped with them.
+                if !skipped_uids.values().any(|skipped| *skipped == uid) {
+                    db.aliases.put(&mut txn, &alias, &uid)?;
+                }
+            }
+        }
+
+        let src_rollover = src.as_ref().join(UUIDS_DB_PATH).join(ROLLOVER_DUMP_FILE);
+        if src_rollover.exists() {
+            for line in BufReader::new(File::open(&src_rollover)?).lines() {
+                let RolloverDumpEntry { name, policy } = serde_json::from_str(&line?)?;
+                let name = format!("{}{}", prefix, name);
+                db.rollover_policies.put(&mut txn, &name, &policy)?;
+            }
+        }
         txn.commit()?;
 
         db.env.prepare_for_closing().wait();
 
-        Ok(())
+        Ok(skipped_uids)
     }
 }
 
@@ -210,17 +469,62 @@ impl UuidStore for HeedUuidStore {
         tokio::task::spawn_blocking(move || this.insert(name, uuid)).await?
     }
 
-    async fn snapshot(&self, path: PathBuf) -> Result<HashSet<Uuid>> {
+    async fn replace(&self, name: String, uuid: Uuid) -> Result<Option<Uuid>> {
         let this = self.clone();
-        tokio::task::spawn_blocking(move || this.snapshot(path)).await?
+        tokio::task::spawn_blocking(move || this.replace(name, uuid)).await?
+    }
+
+    async fn snapshot(&self, path: PathBuf, throttle: Option<IoThrottle>) -> Result<HashSet<Uuid>> {
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || this.snapshot(path, throttle.as_ref())).await?
     }
 
     async fn get_size(&self) -> Result<u64> {
         self.get_size()
     }
 
-    async fn dump(&self, path: PathBuf) -> Result<HashSet<Uuid>> {
+    async fn dump(&self, path: PathBuf, throttle: Option<IoThrottle>) -> Result<HashSet<Uuid>> {
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || this.dump(path, throttle.as_ref())).await?
+    }
+
+    async fn put_alias(&self, alias: String, uid: String) -> Result<()> {
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || this.put_alias(alias, uid)).await?
+    }
+
+    async fn delete_alias(&self, alias: String) -> Result<Option<String>> {
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || this.delete_alias(alias)).await?
+    }
+
+    async fn list_aliases(&self) -> Result<Vec<(String, String)>> {
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || this.list_aliases()).await?
+    }
+
+    async fn resolve_alias(&self, uid: String) -> Result<String> {
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || this.resolve_alias(uid)).await?
+    }
+
+    async fn put_rollover_policy(&self, name: String, policy: RolloverPolicy) -> Result<()> {
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || this.put_rollover_policy(name, policy)).await?
+    }
+
+    async fn get_rollover_policy(&self, name: String) -> Result<Option<RolloverPolicy>> {
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || this.get_rollover_policy(&name)).await?
+    }
+
+    async fn delete_rollover_policy(&self, name: String) -> Result<Option<RolloverPolicy>> {
+        let this = self.clone();
+        tokio::task::spawn_blocking(move || this.delete_rollover_policy(name)).await?
+    }
+
+    async fn list_rollover_policies(&self) -> Result<Vec<(String, RolloverPolicy)>> {
         let this = self.clone();
-        tokio::task::spawn_blocking(move || this.dump(path)).await?
+        tokio::task::spawn_blocking(move || this.list_rollover_policies()).await?
     }
 }
@@ -1,50 +1,112 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
-use std::path::{Path, PathBuf};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use actix_web::error::PayloadError;
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use futures::Stream;
 use log::info;
-use milli::update::IndexDocumentsMethod;
+use meilisearch_error::{Code, ErrorCode};
+use milli::update::{IndexDocumentsMethod, Setting};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tempfile::TempDir;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
 use tokio::task::spawn_blocking;
-use tokio::time::sleep;
+use tokio::time::{sleep, timeout_at};
 use uuid::Uuid;
 
 use dump_actor::DumpActorHandle;
-pub use dump_actor::{DumpInfo, DumpStatus};
+pub use dump_actor::{
+    DumpImportReport, DumpInfo, DumpManifest, DumpStatus, FailedIndexImport, IndexManifest,
+    SettingsSummary,
+};
 use snapshot::load_snapshot;
 
+use crate::document_formats::{visit_documents, DocumentLimits};
 use crate::index::error::Result as IndexResult;
 use crate::index::{
-    Checked, Document, IndexMeta, IndexStats, SearchQuery, SearchResult, Settings, Unchecked,
+    AggregateQuery, AggregateResult, Checked, Checkpoint, Document, DocumentQuotas, DocumentSchema,
+    DocumentsQuery, DocumentsValidation, DocumentsValidator, ExportQuery, FacetSearchQuery,
+    FacetSearchResult, IndexMeta, IndexStats, PrimaryKey, SearchQuery, SearchResult, Settings,
+    SettingsLint, Unchecked, DEFAULT_SEARCH_LIMIT,
 };
 use crate::index_controller::index_resolver::create_index_resolver;
 use crate::index_controller::snapshot::SnapshotService;
-use crate::options::IndexerOpts;
-use error::Result;
+use crate::options::{IndexerOpts, WarmupMode};
+use error::{IndexControllerError, Result};
 
+use self::api_keys::{Action, ApiKey, ApiKeyStore, CreateApiKey, PatchApiKey};
+use self::bloat::{BloatReports, BloatService};
+use self::circuit_breaker::CircuitBreaker;
+use self::dump_actor::error::DumpActorError;
 use self::dump_actor::load_dump;
+use self::export::ChannelWriter;
 use self::index_resolver::error::IndexResolverError;
-use self::index_resolver::HardStateIndexResolver;
-use self::updates::status::UpdateStatus;
-use self::updates::UpdateMsg;
+use self::index_resolver::{is_index_uid_valid, HardStateIndexResolver};
+use self::rollover::{ensure_partition, RolloverPolicy, RolloverService};
+use self::routing::DocumentRouting;
+use self::scrolls::Scrolls;
+use self::search_queue::SearchQueue;
+use self::task_retention::TaskRetentionService;
+use self::update_file_store::UpdateFileStore;
+use self::updates::error::UpdateLoopError;
+use self::updates::status::{StatusKind, UpdateContext, UpdateStatus};
+use self::updates::{CancelFilter, DeleteFilter, StreamReader, TaskFilter, UpdateKind, UpdateMsg};
+use self::warmup::WarmupProgress;
+pub use self::warmup::WarmupStatus;
+use self::webhooks::{CreateWebhook, PatchWebhook, Webhook, WebhookStore};
 
+pub mod api_keys;
+mod bloat;
+mod circuit_breaker;
 mod dump_actor;
+pub mod engine;
 pub mod error;
+mod export;
 mod index_resolver;
+pub mod multi_search;
+pub mod rollover;
+pub mod routing;
+mod scrolls;
+mod search_queue;
 mod snapshot;
+mod task_retention;
 pub mod update_file_store;
 pub mod updates;
+mod versioning;
+mod warmup;
+pub mod webhooks;
+
+/// How long a search waits in the search queue by default.
+const DEFAULT_SEARCH_QUEUE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long a search waits for the update it must reflect to be processed.
+const MAX_UPDATE_WAIT: Duration = Duration::from_secs(5);
+/// The longest a client can wait for a batch of updates to be processed.
+const MAX_UPDATES_WAIT_TIMEOUT: Duration = Duration::from_secs(60);
+/// The number of chunks of an export written ahead of the client.
+const EXPORT_CHANNEL_CAPACITY: usize = 8;
+/// The maximum size of the databases of the controllers created by `new_for_tests`.
+const TEST_DATABASE_SIZE: usize = 100 * 1024 * 1024;
 
 pub type Payload = Box<
     dyn Stream<Item = std::result::Result<Bytes, PayloadError>> + Send + Sync + 'static + Unpin,
 >;
 
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Alias {
+    pub alias: String,
+    pub index_uid: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct IndexMetadata {
@@ -56,6 +118,56 @@ pub struct IndexMetadata {
     pub meta: IndexMeta,
 }
 
+/// The updates canceled by a cancellation.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatesCancellation {
+    pub canceled_by: u64,
+    pub canceled: Vec<IndexUpdateId>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatesDeletion {
+    pub deleted: Vec<IndexUpdateId>,
+}
+
+/// The filters of a listing of the tasks, the ones left to `None` matching all of them.
+#[derive(Debug, Default)]
+pub struct TasksQuery {
+    pub uids: Option<HashSet<u64>>,
+    pub index_uids: Option<Vec<String>>,
+    pub statuses: Option<HashSet<StatusKind>>,
+    pub kinds: Option<HashSet<UpdateKind>>,
+    pub enqueued_after: Option<DateTime<Utc>>,
+    pub enqueued_before: Option<DateTime<Utc>>,
+    pub finished_after: Option<DateTime<Utc>>,
+    pub finished_before: Option<DateTime<Utc>>,
+}
+
+/// An update, identified by its uid among the updates of all the indexes.
+#[derive(Debug)]
+pub struct Task {
+    pub uid: u64,
+    pub index_uid: String,
+    pub status: UpdateStatus,
+}
+
+#[derive(Debug)]
+pub struct Tasks {
+    /// The tasks, from the most recent one.
+    pub results: Vec<Task>,
+    /// The uid to list the next tasks from, `None` on the last page.
+    pub next: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexUpdateId {
+    pub index_uid: String,
+    pub update_id: u64,
+}
+
 #[derive(Clone, Debug)]
 pub struct IndexSettings {
     pub uid: Option<String>,
@@ -67,6 +179,24 @@ pub struct IndexController {
     index_resolver: Arc<HardStateIndexResolver>,
     update_sender: updates::UpdateSender,
     dump_handle: dump_actor::DumpActorHandleImpl,
+    /// The directory the dumps are created in.
+    dump_path: PathBuf,
+    warmup: Arc<WarmupProgress>,
+    bloat_reports: BloatReports,
+    search_queue: Option<Arc<SearchQueue>>,
+    circuit_breaker: Arc<CircuitBreaker>,
+    scrolls: Arc<Scrolls>,
+    /// The report of the import of the dump the database was created from, if its errors were
+    /// ignored.
+    dump_import_report: Option<DumpImportReport>,
+    update_file_store: UpdateFileStore,
+    document_quotas: DocumentQuotas,
+    document_limits: DocumentLimits,
+    api_keys: ApiKeyStore,
+    webhooks: WebhookStore,
+    /// The directory of the databases of a controller built by `build_in_temp_dir`, removed once
+    /// the controller and its clones are dropped.
+    temp_dir: Option<Arc<TempDir>>,
 }
 
 #[derive(Debug)]
@@ -91,6 +221,11 @@ impl fmt::Display for DocumentAdditionFormat {
 pub struct Stats {
     pub database_size: u64,
     pub last_update: Option<DateTime<Utc>>,
+    /// The number of documents of all the indexes together.
+    pub number_of_documents: u64,
+    /// The maximum number of documents of the instance, if there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_documents: Option<u64>,
     pub indexes: BTreeMap<String, IndexStats>,
 }
 
@@ -99,6 +234,9 @@ pub struct Stats {
 #[derivative(Debug)]
 pub enum Update {
     DeleteDocuments(Vec<String>),
+    DeleteByFilter {
+        filter: Value,
+    },
     ClearDocuments,
     Settings(Settings<Unchecked>),
     DocumentAddition {
@@ -122,6 +260,17 @@ pub struct IndexControllerBuilder {
     schedule_snapshot: bool,
     dump_src: Option<PathBuf>,
     dump_dst: Option<PathBuf>,
+    ignore_dump_import_errors: bool,
+    update_archive_dir: Option<PathBuf>,
+    replay_archive_until: Option<DateTime<Utc>>,
+    warmup: WarmupMode,
+    bloat_check_interval: Option<Duration>,
+    max_concurrent_searches: Option<usize>,
+    search_queue_timeout: Option<Duration>,
+    max_fields_per_document: Option<usize>,
+    max_document_size: Option<usize>,
+    snapshot_io_throttle: Option<u64>,
+    task_retention: Option<Duration>,
 }
 
 impl IndexControllerBuilder {
@@ -152,10 +301,12 @@ impl IndexControllerBuilder {
                 index_size,
                 update_store_size,
                 &indexer_options,
+                self.ignore_dump_import_errors,
             )?;
         }
 
         std::fs::create_dir_all(db_path.as_ref())?;
+        let dump_import_report = DumpImportReport::load(&db_path)?;
 
         let index_resolver = Arc::new(create_index_resolver(
             &db_path,
@@ -163,46 +314,127 @@ impl IndexControllerBuilder {
             &indexer_options,
         )?);
 
+        let archive = self
+            .update_archive_dir
+            .map(updates::archive::UpdateArchive::new)
+            .transpose()?;
+
+        if self.replay_archive_until.is_some() && archive.is_none() {
+            anyhow::bail!("Update archive directory not provided.");
+        }
+
+        let document_limits = DocumentLimits {
+            max_fields: self.max_fields_per_document,
+            max_size: self.max_document_size,
+        };
         #[allow(unreachable_code)]
-        let update_sender =
-            updates::create_update_handler(index_resolver.clone(), &db_path, update_store_size)?;
+        let update_sender = updates::create_update_handler(
+            index_resolver.clone(),
+            &db_path,
+            update_store_size,
+            archive,
+            self.replay_archive_until,
+            document_limits,
+        )?;
+
+        let api_keys = ApiKeyStore::new(&db_path)?;
+        let webhooks = WebhookStore::new(&db_path)?;
 
         let dump_path = self
             .dump_dst
             .ok_or_else(|| anyhow::anyhow!("Missing dump directory path"))?;
         let dump_handle = dump_actor::DumpActorHandleImpl::new(
-            dump_path,
+            dump_path.clone(),
             index_resolver.clone(),
             update_sender.clone(),
+            api_keys.clone(),
+            webhooks.clone(),
             index_size,
             update_store_size,
+            self.snapshot_io_throttle,
         )?;
 
         if self.schedule_snapshot {
             let snapshot_service = SnapshotService::new(
                 index_resolver.clone(),
                 update_sender.clone(),
+                api_keys.clone(),
+                webhooks.clone(),
                 self.snapshot_interval
                     .ok_or_else(|| anyhow::anyhow!("Snapshot interval not provided."))?,
                 self.snapshot_dir
                     .ok_or_else(|| anyhow::anyhow!("Snapshot path not provided."))?,
-                db_path
-                    .as_ref()
-                    .file_name()
-                    .map(|n| n.to_owned().into_string().expect("invalid path"))
-                    .unwrap_or_else(|| String::from("data.ms")),
+                db_path.as_ref().to_owned(),
+                self.snapshot_io_throttle,
             );
 
             tokio::task::spawn(snapshot_service.run());
         }
 
+        let bloat_reports = BloatReports::default();
+        if let Some(interval) = self.bloat_check_interval {
+            let bloat_service =
+                BloatService::new(index_resolver.clone(), interval, bloat_reports.clone());
+            tokio::task::spawn(bloat_service.run());
+        }
+
+        if let Some(retention) = self.task_retention {
+            let retention_service = TaskRetentionService::new(update_sender.clone(), retention);
+            tokio::task::spawn(retention_service.run());
+        }
+
+        let rollover_service = RolloverService::new(index_resolver.clone(), update_sender.clone());
+        tokio::task::spawn(rollover_service.run());
+
+        let search_queue = self.max_concurrent_searches.map(|max_concurrent_searches| {
+            let timeout = self
+                .search_queue_timeout
+                .unwrap_or(DEFAULT_SEARCH_QUEUE_TIMEOUT);
+            Arc::new(SearchQueue::new(max_concurrent_searches, timeout))
+        });
+
+        let warmup = Arc::new(WarmupProgress::new(self.warmup));
+        if self.warmup != WarmupMode::None {
+            tokio::task::spawn(warmup.clone().run(index_resolver.clone()));
+        }
+
         Ok(IndexController {
             index_resolver,
             update_sender,
             dump_handle,
+            dump_path,
+            warmup,
+            bloat_reports,
+            search_queue,
+            circuit_breaker: Arc::default(),
+            scrolls: Arc::default(),
+            dump_import_report,
+            update_file_store: UpdateFileStore::new(&db_path)?,
+            document_quotas: DocumentQuotas::new(&indexer_options),
+            document_limits,
+            api_keys,
+            webhooks,
+            temp_dir: None,
         })
     }
 
+    /// Builds an index controller whose databases, and dumps unless a dump directory is set, are
+    /// stored in a new temporary directory, removed once the controller and its clones are
+    /// dropped.
+    pub fn build_in_temp_dir(
+        mut self,
+        indexer_options: IndexerOpts,
+    ) -> anyhow::Result<IndexController> {
+        let temp_dir = tempfile::tempdir()?;
+        if self.dump_dst.is_none() {
+            self.dump_dst = Some(temp_dir.path().join("dumps"));
+        }
+
+        let mut index_controller = self.build(temp_dir.path().join("data.ms"), indexer_options)?;
+        index_controller.temp_dir = Some(Arc::new(temp_dir));
+        Ok(index_controller)
+    }
+
     /// Set the index controller builder's max update store size.
     pub fn set_max_update_store_size(&mut self, max_update_store_size: usize) -> &mut Self {
         self.max_update_store_size.replace(max_update_store_size);
@@ -235,6 +467,12 @@ impl IndexControllerBuilder {
         self
     }
 
+    /// Skips the indexes of the imported dump that fail to load, rather than aborting the import.
+    pub fn set_ignore_dump_import_errors(&mut self, ignore_dump_import_errors: bool) -> &mut Self {
+        self.ignore_dump_import_errors = ignore_dump_import_errors;
+        self
+    }
+
     /// Set the index controller builder's dump src.
     pub fn set_dump_src(&mut self, dump_src: PathBuf) -> &mut Self {
         self.dump_src.replace(dump_src);
@@ -259,6 +497,67 @@ impl IndexControllerBuilder {
         self
     }
 
+    /// Set the directory where every accepted update is archived.
+    pub fn set_update_archive_dir(&mut self, update_archive_dir: PathBuf) -> &mut Self {
+        self.update_archive_dir.replace(update_archive_dir);
+        self
+    }
+
+    /// Set the date up to which the archived updates are replayed at startup.
+    pub fn set_replay_archive_until(&mut self, until: DateTime<Utc>) -> &mut Self {
+        self.replay_archive_until.replace(until);
+        self
+    }
+
+    /// Set how much of the indexes is loaded in memory at startup.
+    pub fn set_warmup(&mut self, warmup: WarmupMode) -> &mut Self {
+        self.warmup = warmup;
+        self
+    }
+
+    /// Set the maximum number of bytes copied per second when the snapshots and dumps are created.
+    pub fn set_snapshot_io_throttle(&mut self, bytes_per_sec: u64) -> &mut Self {
+        self.snapshot_io_throttle.replace(bytes_per_sec);
+        self
+    }
+
+    /// Set the interval between two measures of the space wasted by the indexes.
+    pub fn set_bloat_check_interval(&mut self, interval: Duration) -> &mut Self {
+        self.bloat_check_interval.replace(interval);
+        self
+    }
+
+    /// Set how long the finished updates are kept before being deleted, forever by default.
+    pub fn set_task_retention(&mut self, retention: Duration) -> &mut Self {
+        self.task_retention.replace(retention);
+        self
+    }
+
+    /// Set the maximum number of searches performed concurrently, the others wait in a queue.
+    pub fn set_max_concurrent_searches(&mut self, max_concurrent_searches: usize) -> &mut Self {
+        self.max_concurrent_searches
+            .replace(max_concurrent_searches);
+        self
+    }
+
+    /// Set how long a search can wait in the queue before being rejected.
+    pub fn set_search_queue_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.search_queue_timeout.replace(timeout);
+        self
+    }
+
+    /// Set the maximum number of fields of the added documents.
+    pub fn set_max_fields_per_document(&mut self, max_fields: usize) -> &mut Self {
+        self.max_fields_per_document.replace(max_fields);
+        self
+    }
+
+    /// Set the maximum size, in bytes, of the added documents.
+    pub fn set_max_document_size(&mut self, max_size: usize) -> &mut Self {
+        self.max_document_size.replace(max_size);
+        self
+    }
+
     /// Set the index controller builder's schedule snapshot.
     pub fn set_schedule_snapshot(&mut self) -> &mut Self {
         self.schedule_snapshot = true;
@@ -271,22 +570,57 @@ impl IndexController {
         IndexControllerBuilder::default()
     }
 
+    /// An index controller with small databases stored in a temporary directory, for the tests of
+    /// the applications embedding the engine. It must be created within a tokio runtime.
+    pub fn new_for_tests() -> anyhow::Result<Self> {
+        let mut builder = Self::builder();
+        builder
+            .set_max_index_size(TEST_DATABASE_SIZE)
+            .set_max_update_store_size(TEST_DATABASE_SIZE);
+        let indexer_options = IndexerOpts {
+            indexing_jobs: Some(1),
+            ..IndexerOpts::default()
+        };
+        builder.build_in_temp_dir(indexer_options)
+    }
+
+    /// The temporary directory of the databases, for the controllers built in one.
+    pub fn temp_dir(&self) -> Option<&Path> {
+        self.temp_dir.as_deref().map(TempDir::path)
+    }
+
     pub async fn register_update(
         &self,
         uid: String,
         update: Update,
         create_index: bool,
+        context: UpdateContext,
     ) -> Result<UpdateStatus> {
+        // The writes to a rollover policy go to its current partition.
+        let uid = match self.index_resolver.get_rollover_policy(uid.clone()).await? {
+            Some(policy) => {
+                ensure_partition(&self.index_resolver, &self.update_sender, &uid, &policy).await?
+            }
+            None => uid,
+        };
+
         match self.index_resolver.get_uuid(uid).await {
             Ok(uuid) => {
-                let update_result = UpdateMsg::update(&self.update_sender, uuid, update).await?;
+                let update_result =
+                    UpdateMsg::update_with_context(&self.update_sender, uuid, update, context)
+                        .await?;
                 Ok(update_result)
             }
             Err(IndexResolverError::UnexistingIndex(name)) => {
                 if create_index {
                     let index = self.index_resolver.create_index(name, None).await?;
-                    let update_result =
-                        UpdateMsg::update(&self.update_sender, index.uuid, update).await?;
+                    let update_result = UpdateMsg::update_with_context(
+                        &self.update_sender,
+                        index.uuid,
+                        update,
+                        context,
+                    )
+                    .await?;
                     Ok(update_result)
                 } else {
                     Err(IndexResolverError::UnexistingIndex(name).into())
@@ -296,18 +630,467 @@ impl IndexController {
         }
     }
 
+    /// Adds the documents to the indexes they are routed to, creating the missing indexes from the
+    /// template of the routing. Returns the update registered on each index, all of them carrying
+    /// the metadata and the trace of the addition.
+    ///
+    /// The routing is not atomic: the uids of the target indexes are checked and the missing
+    /// indexes created before any addition is registered, but if the registration of an addition
+    /// fails, the indexes created and the additions registered before it are kept.
+    pub async fn route_documents(
+        &self,
+        routing: DocumentRouting,
+        documents: Vec<Document>,
+        method: IndexDocumentsMethod,
+        context: UpdateContext,
+    ) -> Result<BTreeMap<String, UpdateStatus>> {
+        let routed = routing.route(documents)?;
+        if let Some(uid) = routed.keys().find(|uid| !is_index_uid_valid(uid)) {
+            return Err(IndexResolverError::BadlyFormatted(uid.clone()).into());
+        }
+
+        for uid in routed.keys() {
+            match self.index_resolver.get_uuid(uid.clone()).await {
+                Ok(_) => (),
+                Err(IndexResolverError::UnexistingIndex(_)) => {
+                    let primary_key = routing.template.primary_key.clone();
+                    self.create_index(uid.clone(), primary_key).await?;
+                    if let Some(ref settings) = routing.template.settings {
+                        let update = Update::Settings(settings.clone());
+                        self.register_update(uid.clone(), update, false, UpdateContext::default())
+                            .await?;
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let mut updates = BTreeMap::new();
+        for (uid, documents) in routed {
+            let content = Bytes::from(serde_json::to_vec(&documents)?);
+            let update = Update::DocumentAddition {
+                payload: Box::new(futures::stream::iter(vec![Ok(content)])),
+                primary_key: None,
+                method,
+                format: DocumentAdditionFormat::Json,
+            };
+            let status = self
+                .register_update(uid.clone(), update, false, context.clone())
+                .await?;
+            updates.insert(uid, status);
+        }
+
+        Ok(updates)
+    }
+
+    /// Checks the documents of an addition as the index would, without registering the addition.
+    /// The documents the addition would reject are reported with their error.
+    pub async fn validate_documents(
+        &self,
+        uid: String,
+        payload: Payload,
+        format: DocumentAdditionFormat,
+        primary_key: Option<String>,
+    ) -> Result<DocumentsValidation> {
+        let uid = self.index_resolver.resolve_alias(uid).await?;
+        // The additions create the missing indexes, their documents are checked as the first ones.
+        let schema = match self.index_resolver.get_index(uid).await {
+            Ok(index) => spawn_blocking(move || index.document_schema()).await??,
+            Err(IndexResolverError::UnexistingIndex(_)) => DocumentSchema::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut reader = BufReader::new(StreamReader::new(payload));
+        let limits = self.document_limits;
+        spawn_blocking(move || -> Result<_> {
+            reader.fill_buf().map_err(UpdateLoopError::from)?;
+            if reader.buffer().is_empty() {
+                return Err(UpdateLoopError::MissingPayload(format).into());
+            }
+
+            let mut validator = DocumentsValidator::new(schema, primary_key, limits);
+            visit_documents(reader, &format, |document| validator.push(document))
+                .map_err(UpdateLoopError::from)?;
+            Ok(validator.finish()?)
+        })
+        .await?
+    }
+
+    pub fn warmup_status(&self) -> WarmupStatus {
+        self.warmup.status()
+    }
+
     pub async fn update_status(&self, uid: String, id: u64) -> Result<UpdateStatus> {
         let uuid = self.index_resolver.get_uuid(uid).await?;
         let result = UpdateMsg::get_update(&self.update_sender, uuid, id).await?;
         Ok(result)
     }
 
+    /// Returns the status of the update `id` once it is processed or failed, or when `timeout`
+    /// elapses, being notified of the completed updates rather than polling their status.
+    pub async fn wait_update_status(
+        &self,
+        uid: String,
+        id: u64,
+        timeout: Duration,
+    ) -> Result<UpdateStatus> {
+        let uuid = self.index_resolver.get_uuid(uid).await?;
+        // Subscribing before reading the status, the completion can't be missed in between.
+        let mut completions = UpdateMsg::subscribe(&self.update_sender).await?;
+        let deadline = tokio::time::Instant::now() + timeout.min(MAX_UPDATES_WAIT_TIMEOUT);
+        loop {
+            let status = UpdateMsg::get_update(&self.update_sender, uuid, id).await?;
+            if !matches!(
+                status,
+                UpdateStatus::Enqueued(_) | UpdateStatus::Processing(_)
+            ) {
+                return Ok(status);
+            }
+
+            loop {
+                match timeout_at(deadline, completions.recv()).await {
+                    Ok(Ok(completed)) if completed == (uuid, id) => break,
+                    Ok(Ok(_)) => (),
+                    // Some completions were missed, the status must be read again.
+                    Ok(Err(RecvError::Lagged(_))) => break,
+                    Ok(Err(RecvError::Closed)) | Err(_) => return Ok(status),
+                }
+            }
+        }
+    }
+
+    /// Waits until the update `id` of the index is done being processed, successfully or not.
+    async fn wait_for_update(&self, uid: String, id: u64) -> Result<()> {
+        match self.wait_update_status(uid, id, MAX_UPDATE_WAIT).await? {
+            UpdateStatus::Enqueued(_) | UpdateStatus::Processing(_) => {
+                Err(IndexControllerError::UpdateWaitTimeout(id))
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub async fn all_update_status(&self, uid: String) -> Result<Vec<UpdateStatus>> {
         let uuid = self.index_resolver.get_uuid(uid).await?;
         let result = UpdateMsg::list_updates(&self.update_sender, uuid).await?;
         Ok(result)
     }
 
+    /// Enqueues a failed update again, as a new update referencing it.
+    pub async fn retry_update(&self, uid: String, id: u64) -> Result<UpdateStatus> {
+        let uuid = self.index_resolver.get_uuid(uid).await?;
+        let status = UpdateMsg::retry(&self.update_sender, uuid, id).await?;
+        Ok(status)
+    }
+
+    /// Cancels the updates of the `index_uids` whose id is among `update_ids`, all of them when
+    /// `None`, that are enqueued or processing depending on `enqueued` and `processing`. The
+    /// processing update stops at the next checkpoint of its processing.
+    pub async fn cancel_updates(
+        &self,
+        index_uids: Option<Vec<String>>,
+        update_ids: Option<Vec<u64>>,
+        enqueued: bool,
+        processing: bool,
+    ) -> Result<UpdatesCancellation> {
+        let filter = CancelFilter {
+            index_uuids: self.index_uuids(index_uids).await?,
+            update_ids: update_ids.map(|ids| ids.into_iter().collect()),
+            enqueued,
+            processing,
+        };
+        let cancellation = UpdateMsg::cancel(&self.update_sender, filter).await?;
+        Ok(UpdatesCancellation {
+            canceled_by: cancellation.canceled_by,
+            canceled: self.index_update_ids(cancellation.canceled).await?,
+        })
+    }
+
+    /// Deletes the finished updates of the `index_uids` with one of the `statuses` and `kinds`,
+    /// all of them when `None`, finished before `finished_before`.
+    pub async fn delete_updates(
+        &self,
+        index_uids: Option<Vec<String>>,
+        statuses: Option<HashSet<StatusKind>>,
+        kinds: Option<HashSet<UpdateKind>>,
+        finished_before: Option<DateTime<Utc>>,
+    ) -> Result<UpdatesDeletion> {
+        let filter = DeleteFilter {
+            index_uuids: self.index_uuids(index_uids).await?,
+            statuses,
+            kinds,
+            finished_before,
+        };
+        let deleted = UpdateMsg::delete_finished(&self.update_sender, filter).await?;
+        Ok(UpdatesDeletion {
+            deleted: self.index_update_ids(deleted).await?,
+        })
+    }
+
+    /// Lists the tasks matching `query` from the most recent one, starting from the task uid
+    /// `from`, the last one when `None`.
+    pub async fn list_tasks(
+        &self,
+        query: TasksQuery,
+        from: Option<u64>,
+        limit: usize,
+    ) -> Result<Tasks> {
+        let filter = TaskFilter {
+            uids: query.uids,
+            index_uuids: self.index_uuids(query.index_uids).await?,
+            statuses: query.statuses,
+            kinds: query.kinds,
+            enqueued_after: query.enqueued_after,
+            enqueued_before: query.enqueued_before,
+            finished_after: query.finished_after,
+            finished_before: query.finished_before,
+        };
+        // One more task is listed to know where the next page starts.
+        let mut tasks = UpdateMsg::list_tasks(&self.update_sender, filter, from, limit + 1).await?;
+        let next = if tasks.len() > limit {
+            tasks.pop().and_then(|(_, status)| status.task_uid())
+        } else {
+            None
+        };
+
+        let uids = self.index_uids_by_uuid().await?;
+        let results = tasks
+            .into_iter()
+            .filter_map(|(uuid, status)| {
+                Some(Task {
+                    uid: status.task_uid()?,
+                    index_uid: uids.get(&uuid)?.clone(),
+                    status,
+                })
+            })
+            .collect();
+        Ok(Tasks { results, next })
+    }
+
+    pub async fn get_task(&self, uid: u64) -> Result<Task> {
+        let (uuid, status) = UpdateMsg::get_task(&self.update_sender, uid).await?;
+        let index_uid = self
+            .index_uids_by_uuid()
+            .await?
+            .remove(&uuid)
+            .ok_or(UpdateLoopError::UnexistingTask(uid))?;
+        Ok(Task {
+            uid,
+            index_uid,
+            status,
+        })
+    }
+
+    /// Returns the task `uid` once it is finished, or when `timeout` elapses.
+    pub async fn wait_task(&self, uid: u64, timeout: Duration) -> Result<Task> {
+        let task = self.get_task(uid).await?;
+        if !matches!(
+            task.status,
+            UpdateStatus::Enqueued(_) | UpdateStatus::Processing(_)
+        ) {
+            return Ok(task);
+        }
+        let status = self
+            .wait_update_status(task.index_uid.clone(), task.status.id(), timeout)
+            .await?;
+        Ok(Task { status, ..task })
+    }
+
+    /// Returns the finished tasks following the task uid `after`, all of them when `None`, by
+    /// increasing uid, with the uid to list the next ones after, `None` when there are none yet.
+    /// The tasks finished after a task that is still pending are only returned once it finishes,
+    /// for a cursor over the task uids to never miss one.
+    pub async fn finished_tasks_after(
+        &self,
+        after: Option<u64>,
+        limit: usize,
+    ) -> Result<(Vec<Task>, Option<u64>)> {
+        let (tasks, last) =
+            UpdateMsg::finished_tasks_after(&self.update_sender, after, limit).await?;
+        // The tasks of the deleted indexes are skipped.
+        let uids = self.index_uids_by_uuid().await?;
+        let tasks = tasks
+            .into_iter()
+            .filter_map(|(uid, uuid, status)| {
+                Some(Task {
+                    uid,
+                    index_uid: uids.get(&uuid)?.clone(),
+                    status,
+                })
+            })
+            .collect();
+        Ok((tasks, last))
+    }
+
+    /// Returns the uid to list the finished tasks after, for them to be the ones finished from
+    /// now on.
+    pub async fn last_finished_task_uid(&self) -> Result<Option<u64>> {
+        Ok(UpdateMsg::last_finished_task_uid(&self.update_sender).await?)
+    }
+
+    /// Returns the state of the update archive, failing when the archive mode is disabled.
+    pub async fn archive_info(&self) -> Result<updates::archive::ArchiveInfo> {
+        Ok(UpdateMsg::archive_info(&self.update_sender).await?)
+    }
+
+    /// Pauses or resumes the archiving of the accepted updates.
+    pub async fn set_archiving(&self, archiving: bool) -> Result<updates::archive::ArchiveInfo> {
+        Ok(UpdateMsg::set_archiving(&self.update_sender, archiving).await?)
+    }
+
+    /// Re-registers the archived updates enqueued up to `until` that are missing from the
+    /// database, as `--replay-archive-until` does at launch. Returns their number.
+    pub async fn replay_archive(&self, until: DateTime<Utc>) -> Result<usize> {
+        Ok(UpdateMsg::replay_archive(&self.update_sender, until).await?)
+    }
+
+    /// Returns a stream yielding each time tasks finish.
+    pub async fn task_completions(&self) -> Result<impl Stream<Item = ()>> {
+        let mut completions = UpdateMsg::subscribe(&self.update_sender).await?;
+        Ok(async_stream::stream! {
+            loop {
+                match completions.recv().await {
+                    Ok(_) | Err(RecvError::Lagged(_)) => yield (),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    async fn index_uids_by_uuid(&self) -> Result<HashMap<Uuid, String>> {
+        Ok(self
+            .index_resolver
+            .list()
+            .await?
+            .into_iter()
+            .map(|(uid, index)| (index.uuid, uid))
+            .collect())
+    }
+
+    async fn index_uuids(&self, uids: Option<Vec<String>>) -> Result<Option<HashSet<Uuid>>> {
+        match uids {
+            Some(uids) => {
+                let uids = self.index_resolver.resolve_patterns(&uids).await?;
+                let mut uuids = HashSet::new();
+                for uid in uids {
+                    uuids.insert(self.index_resolver.get_uuid(uid).await?);
+                }
+                Ok(Some(uuids))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Names the index of each update and sorts them, the updates of the deleted indexes being
+    /// skipped.
+    async fn index_update_ids(&self, ids: Vec<(Uuid, u64)>) -> Result<Vec<IndexUpdateId>> {
+        let uids = self.index_uids_by_uuid().await?;
+        let mut ids: Vec<_> = ids
+            .into_iter()
+            .filter_map(|(uuid, update_id)| {
+                Some(IndexUpdateId {
+                    index_uid: uids.get(&uuid)?.clone(),
+                    update_id,
+                })
+            })
+            .collect();
+        ids.sort_unstable_by(|a, b| (&a.index_uid, a.update_id).cmp(&(&b.index_uid, b.update_id)));
+        Ok(ids)
+    }
+
+    /// Streams the documents of a document addition as NDJSON. The payload of an addition is kept
+    /// until it is processed, or until its index is deleted if it failed.
+    pub async fn update_payload(
+        &self,
+        uid: String,
+        id: u64,
+    ) -> Result<impl Stream<Item = Result<Bytes>>> {
+        let content_uuid = match self.update_status(uid, id).await? {
+            UpdateStatus::Processed(_) | UpdateStatus::Aborted(_) | UpdateStatus::Canceled(_) => {
+                None
+            }
+            status => match status.meta() {
+                updates::store::Update::DocumentAddition { content_uuid, .. } => {
+                    Some(*content_uuid)
+                }
+                _ => None,
+            },
+        };
+        // The payloads of the additions that failed before they were kept are gone.
+        let content_uuid = content_uuid
+            .filter(|uuid| self.update_file_store.contains(*uuid))
+            .ok_or(IndexControllerError::MissingUpdatePayload(id))?;
+
+        let update_file_store = self.update_file_store.clone();
+        let (sender, receiver) = mpsc::channel(EXPORT_CHANNEL_CAPACITY);
+        spawn_blocking(move || {
+            let writer = ChannelWriter::new(sender.clone());
+            if let Err(e) = update_file_store.write_ndjson(content_uuid, writer) {
+                let _ = sender.blocking_send(Err(e.into()));
+            }
+        });
+
+        Ok(futures::stream::unfold(receiver, |mut receiver| async {
+            receiver.recv().await.map(|chunk| (chunk, receiver))
+        }))
+    }
+
+    /// Returns the updates of the index whose id is among `ids`, the unknown ids being skipped.
+    pub async fn update_statuses(&self, uid: String, ids: &[u64]) -> Result<Vec<UpdateStatus>> {
+        let updates = self.all_update_status(uid).await?;
+        Ok(updates
+            .into_iter()
+            .filter(|update| ids.contains(&update.id()))
+            .collect())
+    }
+
+    /// Waits until all the updates `ids` of the index are done being processed, successfully or
+    /// not, or until `timeout` elapses. Returns their last known statuses either way, being
+    /// notified of the completed updates rather than polling their statuses.
+    pub async fn wait_for_updates(
+        &self,
+        uid: String,
+        ids: Vec<u64>,
+        timeout: Duration,
+    ) -> Result<Vec<UpdateStatus>> {
+        let uuid = self.index_resolver.get_uuid(uid.clone()).await?;
+        // Subscribing before reading the statuses, the completions can't be missed in between.
+        let mut completions = UpdateMsg::subscribe(&self.update_sender).await?;
+        let deadline = tokio::time::Instant::now() + timeout.min(MAX_UPDATES_WAIT_TIMEOUT);
+        loop {
+            let updates = self.update_statuses(uid.clone(), &ids).await?;
+            if let Some(id) = ids
+                .iter()
+                .find(|id| !updates.iter().any(|update| update.id() == **id))
+            {
+                return Err(UpdateLoopError::UnexistingUpdate(*id).into());
+            }
+
+            let mut pending: HashSet<_> = updates
+                .iter()
+                .filter(|update| {
+                    matches!(
+                        update,
+                        UpdateStatus::Enqueued(_) | UpdateStatus::Processing(_)
+                    )
+                })
+                .map(UpdateStatus::id)
+                .collect();
+            if pending.is_empty() {
+                return Ok(updates);
+            }
+
+            while !pending.is_empty() {
+                match timeout_at(deadline, completions.recv()).await {
+                    Ok(Ok((completed, id))) if completed == uuid => drop(pending.remove(&id)),
+                    Ok(Ok(_)) => (),
+                    // Some completions were missed, the statuses must be read again.
+                    Ok(Err(RecvError::Lagged(_))) => break,
+                    Ok(Err(RecvError::Closed)) | Err(_) => return Ok(updates),
+                }
+            }
+        }
+    }
+
     pub async fn list_indexes(&self) -> Result<Vec<IndexMetadata>> {
         let indexes = self.index_resolver.list().await?;
         let mut ret = Vec::new();
@@ -326,11 +1109,19 @@ impl IndexController {
     }
 
     pub async fn settings(&self, uid: String) -> Result<Settings<Checked>> {
+        let uid = self.index_resolver.resolve_alias(uid).await?;
         let index = self.index_resolver.get_index(uid).await?;
         let settings = spawn_blocking(move || index.settings()).await??;
         Ok(settings)
     }
 
+    pub async fn lint_settings(&self, uid: String) -> Result<SettingsLint> {
+        let uid = self.index_resolver.resolve_alias(uid).await?;
+        let index = self.index_resolver.get_index(uid).await?;
+        let lint = spawn_blocking(move || index.lint_settings()).await??;
+        Ok(lint)
+    }
+
     pub async fn documents(
         &self,
         uid: String,
@@ -338,6 +1129,7 @@ impl IndexController {
         limit: usize,
         attributes_to_retrieve: Option<Vec<String>>,
     ) -> Result<Vec<Document>> {
+        let uid = self.index_resolver.resolve_alias(uid).await?;
         let index = self.index_resolver.get_index(uid).await?;
         let documents =
             spawn_blocking(move || index.retrieve_documents(offset, limit, attributes_to_retrieve))
@@ -345,12 +1137,25 @@ impl IndexController {
         Ok(documents)
     }
 
+    /// The documents of the index matching the filter of the query, in the order of its sort.
+    pub async fn browse_documents(
+        &self,
+        uid: String,
+        query: DocumentsQuery,
+    ) -> Result<Vec<Document>> {
+        let uid = self.index_resolver.resolve_alias(uid).await?;
+        let index = self.index_resolver.get_index(uid).await?;
+        let documents = spawn_blocking(move || index.browse_documents(query)).await??;
+        Ok(documents)
+    }
+
     pub async fn document(
         &self,
         uid: String,
         doc_id: String,
         attributes_to_retrieve: Option<Vec<String>>,
     ) -> Result<Document> {
+        let uid = self.index_resolver.resolve_alias(uid).await?;
         let index = self.index_resolver.get_index(uid).await?;
         let document =
             spawn_blocking(move || index.retrieve_document(doc_id, attributes_to_retrieve))
@@ -379,12 +1184,243 @@ impl IndexController {
     }
 
     pub async fn search(&self, uid: String, query: SearchQuery) -> Result<SearchResult> {
+        match self.index_resolver.get_rollover_policy(uid.clone()).await? {
+            Some(policy) => self.search_partitions(uid, &policy, query).await,
+            None => {
+                let scroll_query = query.scroll.then(|| query.clone());
+                let mut result = self.search_index(uid.clone(), query).await?;
+                if let (Some(query), Some(position)) = (scroll_query, result.scroll.take()) {
+                    let owner = query.api_key.clone();
+                    let query = SearchQuery {
+                        scroll: false,
+                        scroll_position: Some(position),
+                        min_update_id: None,
+                        ..query
+                    };
+                    result.cursor = Some(self.scrolls.open(uid, owner, query));
+                }
+                Ok(result)
+            }
+        }
+    }
+
+    /// Returns the next page of the scroll with this cursor, started by a search with `scroll`
+    /// made with the key `api_key`. `restrict` applies the restrictions of the key of the request
+    /// to this page only.
+    pub async fn scroll(
+        &self,
+        uid: String,
+        cursor: String,
+        api_key: Option<String>,
+        restrict: impl FnOnce(&mut SearchQuery),
+    ) -> Result<SearchResult> {
+        let mut query = self
+            .scrolls
+            .take(&uid, api_key.as_deref(), &cursor)
+            .ok_or_else(|| IndexControllerError::ScrollNotFound(cursor.clone()))?;
+        let next_query = query.clone();
+        restrict(&mut query);
+        let mut result = self.search_index(uid.clone(), query).await?;
+        if let Some(position) = result.scroll.take() {
+            let next_query = SearchQuery {
+                scroll_position: Some(position),
+                ..next_query
+            };
+            self.scrolls
+                .insert(cursor.clone(), uid, api_key, next_query);
+            result.cursor = Some(cursor);
+        }
+        Ok(result)
+    }
+
+    /// Searches the partitions of a rollover policy from the newest to the oldest, as if they
+    /// were a single index: the hits of the newer partitions come first.
+    async fn search_partitions(
+        &self,
+        name: String,
+        policy: &RolloverPolicy,
+        query: SearchQuery,
+    ) -> Result<SearchResult> {
+        let before_search = Instant::now();
+        let uids: Vec<_> = self
+            .index_resolver
+            .list()
+            .await?
+            .into_iter()
+            .map(|(uid, _)| uid)
+            .collect();
+
+        // The writes to the policy go to its current partition, that the update ids returned
+        // are the ones of.
+        let current = policy.partition_uid(&name, Utc::now());
+        let partitions = policy.partitions(&name, uids.iter());
+        // Without a limit, the page holds as many hits as the searches of the newest partition,
+        // whose hits come first.
+        let mut limit = query.limit;
+        if let (None, Some(newest)) = (limit, partitions.first()) {
+            let index = self.index_resolver.get_index(newest.clone()).await?;
+            limit = spawn_blocking(move || index.search_defaults())
+                .await??
+                .limit;
+        }
+        let mut offset = query.offset.unwrap_or_default();
+        let mut remaining = limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+        let mut combined = SearchResult {
+            hits: Vec::new(),
+            nb_hits: 0,
+            exhaustive_nb_hits: true,
+            query: query.q.clone().unwrap_or_default(),
+            limit: remaining,
+            offset,
+            processing_time_ms: 0,
+            facets_distribution: None,
+            exhaustive_facets_count: None,
+            debug: None,
+            next_search_after: None,
+            cursor: None,
+            scroll: None,
+        };
+
+        for uid in partitions {
+            let partition_query = SearchQuery {
+                offset: Some(offset),
+                limit: Some(remaining),
+                // The update ids are specific to each partition.
+                min_update_id: query.min_update_id.filter(|_| uid == current),
+                // The scrolls follow the documents of a single index.
+                scroll: false,
+                ..query.clone()
+            };
+            let result = self.search_index(uid, partition_query).await?;
+
+            offset = offset.saturating_sub(result.nb_hits as usize);
+            remaining -= result.hits.len();
+            combined.hits.extend(result.hits);
+            combined.nb_hits += result.nb_hits;
+            combined.exhaustive_nb_hits &= result.exhaustive_nb_hits;
+
+            if let Some(distribution) = result.facets_distribution {
+                let combined_distribution = combined
+                    .facets_distribution
+                    .get_or_insert_with(Default::default);
+                for (facet, values) in distribution {
+                    let combined_values = combined_distribution.entry(facet).or_default();
+                    for (value, count) in values {
+                        *combined_values.entry(value).or_default() += count;
+                    }
+                }
+            }
+            if let Some(exhaustive) = result.exhaustive_facets_count {
+                let combined_exhaustive = combined.exhaustive_facets_count.get_or_insert(true);
+                *combined_exhaustive &= exhaustive;
+            }
+            combined.debug = combined.debug.or(result.debug);
+            combined.next_search_after = result.next_search_after;
+        }
+
+        combined.processing_time_ms = before_search.elapsed().as_millis();
+        Ok(combined)
+    }
+
+    async fn search_index(&self, uid: String, query: SearchQuery) -> Result<SearchResult> {
+        let uid = self.index_resolver.resolve_alias(uid).await?;
+        if let Some(update_id) = query.min_update_id {
+            self.wait_for_update(uid.clone(), update_id).await?;
+        }
+
+        // The search is admitted with the parameters it is performed with.
         let index = self.index_resolver.get_index(uid.clone()).await?;
-        let result = spawn_blocking(move || index.perform_search(query)).await??;
+        let defaults = {
+            let index = index.clone();
+            spawn_blocking(move || index.search_defaults()).await??
+        };
+        let query = query.with_defaults(&defaults);
+
+        // The permit is held until the search is done.
+        let _permit = match self.search_queue {
+            Some(ref queue) => Some(
+                queue
+                    .admit(&query)
+                    .await
+                    .ok_or(IndexControllerError::SearchOverloaded)?,
+            ),
+            None => None,
+        };
+
+        let uuid = index.uuid;
+        let attempt = match self.circuit_breaker.allow(uuid) {
+            Some(attempt) => attempt,
+            None => return Err(IndexControllerError::IndexUnavailable(uid)),
+        };
+
+        // Only the internal errors and panics are failures of the index, the invalid queries
+        // are not.
+        match spawn_blocking(move || index.perform_search(query)).await {
+            Ok(Ok(result)) => {
+                attempt.success();
+                Ok(result)
+            }
+            Ok(Err(e)) => {
+                if matches!(e.error_code(), Code::Internal) {
+                    attempt.failure();
+                } else {
+                    attempt.success();
+                }
+                Err(e.into())
+            }
+            Err(e) => {
+                attempt.failure();
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Streams all the documents matched by the query. The errors of the query are returned before
+    /// anything is streamed, an error while reading the documents ends the stream.
+    pub async fn export_search(
+        &self,
+        uid: String,
+        query: ExportQuery,
+    ) -> Result<impl Stream<Item = Result<Bytes>>> {
+        let uid = self.index_resolver.resolve_alias(uid).await?;
+        let index = self.index_resolver.get_index(uid).await?;
+
+        let search_index = index.clone();
+        let export = spawn_blocking(move || search_index.prepare_export(query)).await??;
+
+        let (sender, receiver) = mpsc::channel(EXPORT_CHANNEL_CAPACITY);
+        spawn_blocking(move || {
+            let writer = ChannelWriter::new(sender.clone());
+            if let Err(e) = index.write_export(export, writer) {
+                let _ = sender.blocking_send(Err(e.into()));
+            }
+        });
+
+        Ok(futures::stream::unfold(receiver, |mut receiver| async {
+            receiver.recv().await.map(|chunk| (chunk, receiver))
+        }))
+    }
+
+    pub async fn aggregate(&self, uid: String, query: AggregateQuery) -> Result<AggregateResult> {
+        let uid = self.index_resolver.resolve_alias(uid).await?;
+        let index = self.index_resolver.get_index(uid).await?;
+        let result = spawn_blocking(move || index.aggregate(query)).await??;
+        Ok(result)
+    }
+
+    pub async fn facet_search(
+        &self,
+        uid: String,
+        query: FacetSearchQuery,
+    ) -> Result<FacetSearchResult> {
+        let uid = self.index_resolver.resolve_alias(uid).await?;
+        let index = self.index_resolver.get_index(uid).await?;
+        let result = spawn_blocking(move || index.facet_search(query)).await??;
         Ok(result)
     }
 
     pub async fn get_index(&self, uid: String) -> Result<IndexMetadata> {
+        let uid = self.index_resolver.resolve_alias(uid).await?;
         let index = self.index_resolver.get_index(uid.clone()).await?;
         let uuid = index.uuid;
         let meta = spawn_blocking(move || index.meta()).await??;
@@ -397,13 +1433,46 @@ impl IndexController {
         Ok(meta)
     }
 
+    /// The copies of the index taken before its settings updates, the most recent first.
+    pub async fn index_checkpoints(&self, uid: String) -> Result<Vec<Checkpoint>> {
+        let uid = self.index_resolver.resolve_alias(uid).await?;
+        let index = self.index_resolver.get_index(uid).await?;
+        let checkpoints = spawn_blocking(move || index.checkpoints()).await??;
+        Ok(checkpoints)
+    }
+
+    /// Rolls the index back to the state it had before the settings update `update_id`. The
+    /// restored index gets a new uuid, and the pending updates of the replaced one are moved to
+    /// it, its finished updates being discarded.
+    pub async fn restore_checkpoint(&self, uid: String, update_id: u64) -> Result<IndexMetadata> {
+        let uid = self.index_resolver.resolve_alias(uid).await?;
+        let (replaced, index) = self
+            .index_resolver
+            .restore_checkpoint(uid.clone(), update_id)
+            .await?;
+
+        let uuid = index.uuid;
+        UpdateMsg::move_pending(&self.update_sender, replaced, uuid).await?;
+        UpdateMsg::delete(&self.update_sender, replaced).await?;
+
+        let meta = spawn_blocking(move || index.meta()).await??;
+        Ok(IndexMetadata {
+            uuid,
+            name: uid.clone(),
+            uid,
+            meta,
+        })
+    }
+
     pub async fn get_index_stats(&self, uid: String) -> Result<IndexStats> {
         let update_infos = UpdateMsg::get_info(&self.update_sender).await?;
+        let uid = self.index_resolver.resolve_alias(uid).await?;
         let index = self.index_resolver.get_index(uid).await?;
         let uuid = index.uuid;
         let mut stats = spawn_blocking(move || index.stats()).await??;
         // Check if the currently indexing update is from our index.
         stats.is_indexing = Some(Some(uuid) == update_infos.processing);
+        stats.bloat = self.bloat_reports.read().get(&uuid).cloned();
         Ok(stats)
     }
 
@@ -411,6 +1480,7 @@ impl IndexController {
         let update_infos = UpdateMsg::get_info(&self.update_sender).await?;
         let mut database_size = self.index_resolver.get_uuids_size().await? + update_infos.size;
         let mut last_update: Option<DateTime<_>> = None;
+        let mut number_of_documents = 0;
         let mut indexes = BTreeMap::new();
 
         for (index_uid, index) in self.index_resolver.list().await? {
@@ -423,6 +1493,7 @@ impl IndexController {
             .await??;
 
             database_size += stats.size;
+            number_of_documents += stats.number_of_documents;
 
             last_update = last_update.map_or(Some(meta.updated_at), |last| {
                 Some(last.max(meta.updated_at))
@@ -430,6 +1501,7 @@ impl IndexController {
 
             // Check if the currently indexing update is from our index.
             stats.is_indexing = Some(Some(uuid) == update_infos.processing);
+            stats.bloat = self.bloat_reports.read().get(&uuid).cloned();
 
             indexes.insert(index_uid, stats);
         }
@@ -437,6 +1509,8 @@ impl IndexController {
         Ok(Stats {
             database_size,
             last_update,
+            number_of_documents,
+            max_documents: self.document_quotas.max_documents,
             indexes,
         })
     }
@@ -449,10 +1523,44 @@ impl IndexController {
         Ok(self.dump_handle.dump_info(uid).await?)
     }
 
+    /// Reads the manifest of an uploaded dump, without importing it.
+    pub async fn inspect_dump(&self, payload: Payload) -> Result<DumpManifest> {
+        let reader = BufReader::new(StreamReader::new(payload));
+        let manifest = spawn_blocking(move || dump_actor::inspect_dump(reader)).await??;
+        Ok(manifest)
+    }
+
+    /// Reads the manifest of the dump `file_name` of the dumps directory, without importing it.
+    pub async fn inspect_dump_file(&self, file_name: String) -> Result<DumpManifest> {
+        let mut components = Path::new(&file_name).components();
+        let path = match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => self.dump_path.join(&file_name),
+            _ => return Err(DumpActorError::DumpDoesNotExist(file_name).into()),
+        };
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(DumpActorError::DumpDoesNotExist(file_name).into())
+            }
+            Err(e) => return Err(DumpActorError::from(e).into()),
+        };
+        let reader = BufReader::new(file);
+        let manifest = spawn_blocking(move || dump_actor::inspect_dump(reader)).await??;
+        Ok(manifest)
+    }
+
+    /// The report of the import of the dump the database was created from, listing the indexes
+    /// that were skipped.
+    pub fn dump_import_report(&self) -> Result<DumpImportReport> {
+        self.dump_import_report
+            .clone()
+            .ok_or_else(|| DumpActorError::ImportReportNotFound.into())
+    }
+
     pub async fn create_index(
         &self,
         uid: String,
-        primary_key: Option<String>,
+        primary_key: Option<PrimaryKey>,
     ) -> Result<IndexMetadata> {
         let index = self
             .index_resolver
@@ -473,6 +1581,167 @@ impl IndexController {
         Ok(meta)
     }
 
+    /// Points `alias` to the index `uid`, the read routes given the alias reading this index. An
+    /// existing alias is repointed atomically.
+    pub async fn put_alias(&self, alias: String, uid: String) -> Result<Alias> {
+        self.index_resolver
+            .put_alias(alias.clone(), uid.clone())
+            .await?;
+        Ok(Alias {
+            alias,
+            index_uid: uid,
+        })
+    }
+
+    pub async fn get_alias(&self, alias: String) -> Result<Alias> {
+        let uid = self.index_resolver.get_alias(alias.clone()).await?;
+        Ok(Alias {
+            alias,
+            index_uid: uid,
+        })
+    }
+
+    pub async fn create_api_key(&self, key: CreateApiKey) -> Result<ApiKey> {
+        let store = self.api_keys.clone();
+        Ok(spawn_blocking(move || store.create(key)).await??)
+    }
+
+    pub async fn get_api_key(&self, key: String) -> Result<ApiKey> {
+        let store = self.api_keys.clone();
+        Ok(spawn_blocking(move || store.get(&key)).await??)
+    }
+
+    pub async fn list_api_keys(&self) -> Result<Vec<ApiKey>> {
+        let store = self.api_keys.clone();
+        Ok(spawn_blocking(move || store.list()).await??)
+    }
+
+    pub async fn update_api_key(&self, key: String, patch: PatchApiKey) -> Result<ApiKey> {
+        let store = self.api_keys.clone();
+        Ok(spawn_blocking(move || store.update(&key, patch)).await??)
+    }
+
+    pub async fn delete_api_key(&self, key: String) -> Result<()> {
+        let store = self.api_keys.clone();
+        Ok(spawn_blocking(move || store.delete(&key)).await??)
+    }
+
+    /// Returns whether the API key `key` allows `action` on the index `index_uid`, or on all the
+    /// indexes when there is none. An alias is checked as the index it targets. The keys are
+    /// looked up synchronously, for the authentication of the requests.
+    pub fn authorize_api_key(
+        &self,
+        key: &str,
+        action: Action,
+        index_uid: Option<&str>,
+    ) -> Result<bool> {
+        let index_uid = index_uid
+            .map(|uid| self.index_resolver.resolve_alias_sync(uid.to_string()))
+            .transpose()?;
+        Ok(self.api_keys.authorize(key, action, index_uid.as_deref())?)
+    }
+
+    /// Returns whether the API key `key` allows `action` on at least one index, looked up like in
+    /// [`Self::authorize_api_key`].
+    pub fn authorize_api_key_action(&self, key: &str, action: Action) -> Result<bool> {
+        Ok(self.api_keys.authorize_action(key, action)?)
+    }
+
+    /// Returns the API keys starting with `prefix`, looked up synchronously like in
+    /// [`Self::authorize_api_key`].
+    pub fn api_keys_with_prefix(&self, prefix: &str) -> Result<Vec<String>> {
+        Ok(self.api_keys.with_prefix(prefix)?)
+    }
+
+    /// Registers a webhook, notified of the tasks finishing from now on.
+    pub async fn create_webhook(&self, mut webhook: CreateWebhook) -> Result<Webhook> {
+        // The tasks are notified with the uid of their index, an alias is resolved once.
+        if let Some(index_uid) = webhook.index_uid.take() {
+            webhook.index_uid = Some(self.index_resolver.resolve_alias(index_uid).await?);
+        }
+        let cursor = self.last_finished_task_uid().await?;
+        let store = self.webhooks.clone();
+        Ok(spawn_blocking(move || store.create(webhook, cursor)).await??)
+    }
+
+    pub async fn get_webhook(&self, uid: Uuid) -> Result<Webhook> {
+        let store = self.webhooks.clone();
+        Ok(spawn_blocking(move || store.get(uid)).await??)
+    }
+
+    pub async fn list_webhooks(&self) -> Result<Vec<Webhook>> {
+        let store = self.webhooks.clone();
+        Ok(spawn_blocking(move || store.list()).await??)
+    }
+
+    pub async fn update_webhook(&self, uid: Uuid, mut patch: PatchWebhook) -> Result<Webhook> {
+        if let Setting::Set(index_uid) = patch.index_uid {
+            patch.index_uid = Setting::Set(self.index_resolver.resolve_alias(index_uid).await?);
+        }
+        let store = self.webhooks.clone();
+        Ok(spawn_blocking(move || store.update(uid, patch)).await??)
+    }
+
+    pub async fn delete_webhook(&self, uid: Uuid) -> Result<()> {
+        let store = self.webhooks.clone();
+        Ok(spawn_blocking(move || store.delete(uid)).await??)
+    }
+
+    /// Returns the uid of the last task notified by the notifier `name`.
+    pub async fn webhook_cursor(&self, name: String) -> Result<Option<u64>> {
+        let store = self.webhooks.clone();
+        Ok(spawn_blocking(move || store.cursor(&name)).await??)
+    }
+
+    pub async fn put_webhook_cursor(&self, name: String, task_uid: u64) -> Result<()> {
+        let store = self.webhooks.clone();
+        Ok(spawn_blocking(move || store.put_cursor(&name, task_uid)).await??)
+    }
+
+    pub async fn list_aliases(&self) -> Result<Vec<Alias>> {
+        let aliases = self.index_resolver.list_aliases().await?;
+        Ok(aliases
+            .into_iter()
+            .map(|(alias, index_uid)| Alias { alias, index_uid })
+            .collect())
+    }
+
+    pub async fn delete_alias(&self, alias: String) -> Result<()> {
+        self.index_resolver.delete_alias(alias).await?;
+        Ok(())
+    }
+
+    pub async fn put_rollover_policy(&self, name: String, policy: RolloverPolicy) -> Result<()> {
+        policy.validate()?;
+        self.index_resolver
+            .put_rollover_policy(name.clone(), policy.clone())
+            .await?;
+        // The current partition is created right away rather than at the first write.
+        ensure_partition(&self.index_resolver, &self.update_sender, &name, &policy).await?;
+        Ok(())
+    }
+
+    pub async fn get_rollover_policy(&self, name: String) -> Result<RolloverPolicy> {
+        self.index_resolver
+            .get_rollover_policy(name.clone())
+            .await?
+            .ok_or_else(|| IndexResolverError::UnexistingRolloverPolicy(name).into())
+    }
+
+    pub async fn list_rollover_policies(&self) -> Result<BTreeMap<String, RolloverPolicy>> {
+        let policies = self.index_resolver.list_rollover_policies().await?;
+        Ok(policies.into_iter().collect())
+    }
+
+    /// Deletes the policy, its partitions are kept as regular indexes.
+    pub async fn delete_rollover_policy(&self, name: String) -> Result<()> {
+        self.index_resolver
+            .delete_rollover_policy(name.clone())
+            .await?
+            .ok_or(IndexResolverError::UnexistingRolloverPolicy(name))?;
+        Ok(())
+    }
+
     pub async fn delete_index(&self, uid: String) -> Result<()> {
         let uuid = self.index_resolver.delete_index(uid).await?;
 
@@ -497,3 +1766,24 @@ pub async fn get_arc_ownership_blocking<T>(mut item: Arc<T>) -> T {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[actix_rt::test]
+    async fn temp_dir_removed_on_drop() {
+        let index_controller = IndexController::new_for_tests().unwrap();
+        let temp_dir = index_controller.temp_dir().unwrap().to_path_buf();
+        assert!(temp_dir.exists());
+
+        index_controller
+            .create_index(String::from("test"), None)
+            .await
+            .unwrap();
+        assert_eq!(index_controller.list_indexes().await.unwrap().len(), 1);
+
+        drop(index_controller);
+        assert!(!temp_dir.exists());
+    }
+}
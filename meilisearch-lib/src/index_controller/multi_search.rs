@@ -0,0 +1,88 @@
+//! The searches of several indexes made at once, for the pages showing the results of several
+//! indexes without a round trip per index.
+
+use futures::future::try_join_all;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+
+use crate::index::{SearchQuery, SearchResult};
+
+use super::error::{IndexControllerError, Result};
+use super::IndexController;
+
+/// A search with the uid of the searched index, written as the body of the search with an extra
+/// `indexUid` field.
+#[derive(Debug, Clone)]
+pub struct IndexSearchQuery {
+    pub index_uid: String,
+    pub query: SearchQuery,
+}
+
+/// The search query rejects the unknown fields, so the `indexUid` is removed before the rest of
+/// the body is read as a search query.
+impl<'de> Deserialize<'de> for IndexSearchQuery {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let mut body = Map::<String, Value>::deserialize(deserializer)?;
+        let index_uid = match body.remove("indexUid") {
+            Some(Value::String(uid)) => uid,
+            Some(_) => return Err(D::Error::custom("`indexUid` must be a string")),
+            None => return Err(D::Error::missing_field("indexUid")),
+        };
+        let query = serde_json::from_value(Value::Object(body)).map_err(D::Error::custom)?;
+        Ok(Self { index_uid, query })
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexSearchResult {
+    pub index_uid: String,
+    #[serde(flatten)]
+    pub result: SearchResult,
+}
+
+impl IndexController {
+    /// Runs the searches concurrently and returns their results in the same order. Fails with
+    /// the error of the first search failing, along with its position.
+    pub async fn multi_search(
+        &self,
+        queries: Vec<IndexSearchQuery>,
+    ) -> Result<Vec<IndexSearchResult>> {
+        let searches = queries.into_iter().enumerate().map(
+            |(position, IndexSearchQuery { index_uid, query })| async move {
+                match self.search(index_uid.clone(), query).await {
+                    Ok(result) => Ok(IndexSearchResult { index_uid, result }),
+                    Err(error) => Err(IndexControllerError::MultiSearch {
+                        position,
+                        error: Box::new(error),
+                    }),
+                }
+            },
+        );
+        try_join_all(searches).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn deserialize_index_search_query() {
+        let query: IndexSearchQuery =
+            serde_json::from_value(json!({ "indexUid": "movies", "q": "harry", "limit": 5 }))
+                .unwrap();
+        assert_eq!(query.index_uid, "movies");
+        assert_eq!(query.query.q.as_deref(), Some("harry"));
+        assert_eq!(query.query.limit, Some(5));
+
+        let missing_uid = serde_json::from_value::<IndexSearchQuery>(json!({ "q": "harry" }));
+        assert!(missing_uid.is_err());
+        let unknown_field =
+            serde_json::from_value::<IndexSearchQuery>(json!({ "indexUid": "movies", "x": 1 }));
+        assert!(unknown_field.is_err());
+    }
+}
@@ -0,0 +1,302 @@
+//! Management of time-partitioned indexes: a rollover policy named `logs` writes the documents in
+//! the partition of the current period, `logs-2021-10-14` for a daily policy, creates the
+//! partitions from a template and deletes the ones older than the retention of the policy.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveDate, Utc};
+use log::{error, info, trace};
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+
+use super::error::{IndexControllerError, Result};
+use super::index_resolver::error::IndexResolverError;
+use super::index_resolver::HardStateIndexResolver;
+use super::routing::IndexTemplate;
+use super::updates::{UpdateMsg, UpdateSender};
+use super::Update;
+
+/// How often the partitions are created and the expired ones deleted.
+const ROLLOVER_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RolloverPeriod {
+    Daily,
+    Monthly,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RolloverPolicy {
+    pub period: RolloverPeriod,
+    /// The number of periods whose partitions are kept, the current one included.
+    pub keep: usize,
+    #[serde(default)]
+    pub template: IndexTemplate,
+}
+
+impl RolloverPeriod {
+    fn format(&self) -> &'static str {
+        match self {
+            RolloverPeriod::Daily => "%Y-%m-%d",
+            RolloverPeriod::Monthly => "%Y-%m",
+        }
+    }
+
+    /// The first day of the period containing `date`.
+    fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            RolloverPeriod::Daily => date,
+            RolloverPeriod::Monthly => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    /// The first day of the period `count` periods before the one starting at `start`.
+    fn periods_before(&self, start: NaiveDate, count: usize) -> NaiveDate {
+        match self {
+            RolloverPeriod::Daily => start - ChronoDuration::days(count as i64),
+            RolloverPeriod::Monthly => {
+                let months = start.year() as i64 * 12 + start.month0() as i64 - count as i64;
+                NaiveDate::from_ymd((months / 12) as i32, (months % 12) as u32 + 1, 1)
+            }
+        }
+    }
+
+    /// Parses the date of a partition, the first day of its period.
+    fn parse(&self, date: &str) -> Option<NaiveDate> {
+        match self {
+            RolloverPeriod::Daily => NaiveDate::parse_from_str(date, "%Y-%m-%d").ok(),
+            RolloverPeriod::Monthly => {
+                NaiveDate::parse_from_str(&format!("{}-01", date), "%Y-%m-%d").ok()
+            }
+        }
+    }
+}
+
+impl RolloverPolicy {
+    pub fn validate(&self) -> Result<()> {
+        if self.keep == 0 {
+            return Err(IndexControllerError::InvalidRolloverPolicy(String::from(
+                "`keep` must be at least 1.",
+            )));
+        }
+        Ok(())
+    }
+
+    /// The uid of the partition of the period containing `now`.
+    pub fn partition_uid(&self, name: &str, now: DateTime<Utc>) -> String {
+        format!("{}-{}", name, now.format(self.period.format()))
+    }
+
+    /// Returns the date of `uid` if it names a partition of the policy.
+    fn partition_date(&self, name: &str, uid: &str) -> Option<NaiveDate> {
+        let date = uid.strip_prefix(name)?.strip_prefix('-')?;
+        self.period.parse(date)
+    }
+
+    /// Returns the partitions of the policy among `uids`, from the newest to the oldest.
+    pub fn partitions<'a>(
+        &self,
+        name: &str,
+        uids: impl Iterator<Item = &'a String>,
+    ) -> Vec<String> {
+        let mut partitions: Vec<_> = uids
+            .filter_map(|uid| {
+                self.partition_date(name, uid)
+                    .map(|date| (date, uid.clone()))
+            })
+            .collect();
+        partitions.sort_unstable_by(|a, b| b.cmp(a));
+        partitions.into_iter().map(|(_, uid)| uid).collect()
+    }
+
+    /// Returns the partitions among `uids` that are older than the retention of the policy.
+    pub fn expired_partitions<'a>(
+        &self,
+        name: &str,
+        uids: impl Iterator<Item = &'a String>,
+        now: DateTime<Utc>,
+    ) -> Vec<String> {
+        let current = self.period.start(now.date().naive_utc());
+        let oldest_kept = self
+            .period
+            .periods_before(current, self.keep.saturating_sub(1));
+        uids.filter(|uid| {
+            self.partition_date(name, uid)
+                .map_or(false, |date| date < oldest_kept)
+        })
+        .cloned()
+        .collect()
+    }
+}
+
+/// Creates the current partition of the policy if it doesn't exist, returning its uid. The
+/// settings of the template are registered before the partition is returned, a partition whose
+/// settings couldn't be registered is deleted for the next write or check to create it again.
+pub async fn ensure_partition(
+    index_resolver: &HardStateIndexResolver,
+    update_sender: &UpdateSender,
+    name: &str,
+    policy: &RolloverPolicy,
+) -> Result<String> {
+    let uid = policy.partition_uid(name, Utc::now());
+    match index_resolver.get_uuid(uid.clone()).await {
+        Ok(_) => return Ok(uid),
+        Err(IndexResolverError::UnexistingIndex(_)) => (),
+        Err(e) => return Err(e.into()),
+    }
+
+    let primary_key = policy.template.primary_key.clone();
+    let index = match index_resolver.create_index(uid.clone(), primary_key).await {
+        Ok(index) => index,
+        // The partition was created by a concurrent write.
+        Err(IndexResolverError::IndexAlreadyExists) => return Ok(uid),
+        Err(e) => return Err(e.into()),
+    };
+    info!("Created the partition {} of {}.", uid, name);
+
+    if let Some(ref settings) = policy.template.settings {
+        let update = Update::Settings(settings.clone());
+        if let Err(e) = UpdateMsg::update(update_sender, index.uuid, update).await {
+            if let Err(e) = index_resolver.delete_index(uid.clone()).await {
+                error!("The partition {} couldn't be deleted: {}", uid, e);
+            }
+            return Err(e.into());
+        }
+    }
+
+    Ok(uid)
+}
+
+pub struct RolloverService {
+    index_resolver: Arc<HardStateIndexResolver>,
+    update_sender: UpdateSender,
+}
+
+impl RolloverService {
+    pub fn new(index_resolver: Arc<HardStateIndexResolver>, update_sender: UpdateSender) -> Self {
+        Self {
+            index_resolver,
+            update_sender,
+        }
+    }
+
+    pub async fn run(self) {
+        loop {
+            match self.apply_policies().await {
+                Ok(errors) => {
+                    for (name, e) in errors {
+                        error!("Error while rolling the partitions of {} over: {}", name, e);
+                    }
+                }
+                Err(e) => error!("Error while rolling the partitions over: {}", e),
+            }
+            sleep(ROLLOVER_CHECK_INTERVAL).await;
+        }
+    }
+
+    /// Applies every policy, the failure of one of them doesn't prevent the others from being
+    /// applied. Returns the errors of each policy.
+    async fn apply_policies(&self) -> Result<Vec<(String, IndexControllerError)>> {
+        let policies = self.index_resolver.list_rollover_policies().await?;
+        if policies.is_empty() {
+            return Ok(Vec::new());
+        }
+        trace!("Rolling the partitions over.");
+
+        let uids: Vec<_> = self
+            .index_resolver
+            .list()
+            .await?
+            .into_iter()
+            .map(|(uid, _)| uid)
+            .collect();
+
+        let mut errors = Vec::new();
+        for (name, policy) in policies {
+            if let Err(e) =
+                ensure_partition(&self.index_resolver, &self.update_sender, &name, &policy).await
+            {
+                errors.push((name.clone(), e));
+            }
+
+            for uid in policy.expired_partitions(&name, uids.iter(), Utc::now()) {
+                match self.delete_partition(&uid).await {
+                    Ok(()) => info!("Deleted the expired partition {} of {}.", uid, name),
+                    Err(e) => errors.push((name.clone(), e)),
+                }
+            }
+        }
+
+        Ok(errors)
+    }
+
+    async fn delete_partition(&self, uid: &str) -> Result<()> {
+        let uuid = self.index_resolver.delete_index(uid.to_string()).await?;
+        UpdateMsg::delete(&self.update_sender, uuid).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn policy(period: RolloverPeriod, keep: usize) -> RolloverPolicy {
+        RolloverPolicy {
+            period,
+            keep,
+            template: IndexTemplate::default(),
+        }
+    }
+
+    #[test]
+    fn daily_partitions() {
+        let policy = policy(RolloverPeriod::Daily, 2);
+        let now = Utc.ymd(2021, 10, 14).and_hms(12, 0, 0);
+        assert_eq!(policy.partition_uid("logs", now), "logs-2021-10-14");
+
+        let uids: Vec<_> = [
+            "logs-2021-10-12",
+            "logs-2021-10-14",
+            "logs-2021-10-13",
+            "logs-old",
+        ]
+        .iter()
+        .map(|uid| uid.to_string())
+        .collect();
+        assert_eq!(
+            policy.partitions("logs", uids.iter()),
+            ["logs-2021-10-14", "logs-2021-10-13", "logs-2021-10-12"]
+        );
+        assert_eq!(
+            policy.expired_partitions("logs", uids.iter(), now),
+            ["logs-2021-10-12"]
+        );
+    }
+
+    #[test]
+    fn monthly_partitions() {
+        let policy = policy(RolloverPeriod::Monthly, 3);
+        let now = Utc.ymd(2021, 2, 14).and_hms(12, 0, 0);
+        assert_eq!(policy.partition_uid("logs", now), "logs-2021-02");
+
+        let uids: Vec<_> = [
+            "logs-2020-11",
+            "logs-2020-12",
+            "logs-2021-01",
+            "logs-2021-02",
+        ]
+        .iter()
+        .map(|uid| uid.to_string())
+        .collect();
+        assert_eq!(
+            policy.expired_partitions("logs", uids.iter(), now),
+            ["logs-2020-11"]
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Routing of the documents of a single addition to several indexes, depending on the values of
+//! their fields, such as `articles-{lang}` to partition the articles by language.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::index::{Document, PrimaryKey, Settings, Unchecked};
+
+use super::error::{IndexControllerError, Result};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RoutingRule {
+    pub field: String,
+    /// The uid of the target index, in which `{field}` is replaced by the value of the field.
+    pub index_uid: String,
+}
+
+/// The primary key and settings of the indexes created by a routed addition.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct IndexTemplate {
+    pub primary_key: Option<PrimaryKey>,
+    pub settings: Option<Settings<Unchecked>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentRouting {
+    /// The rules are tried in order, a document is routed by the first rule whose field it holds.
+    pub rules: Vec<RoutingRule>,
+    /// The index of the documents routed by no rule, they are rejected if there is none.
+    pub fallback_index_uid: Option<String>,
+    #[serde(default)]
+    pub template: IndexTemplate,
+}
+
+impl RoutingRule {
+    fn index_uid(&self, document: &Document) -> Option<String> {
+        let value = match document.get(&self.field)? {
+            Value::String(s) if !s.is_empty() => s.clone(),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => return None,
+        };
+        let placeholder = format!("{{{}}}", self.field);
+        Some(self.index_uid.replace(&placeholder, &value))
+    }
+}
+
+impl DocumentRouting {
+    /// Splits the documents by target index, keeping their order.
+    pub fn route(&self, documents: Vec<Document>) -> Result<BTreeMap<String, Vec<Document>>> {
+        let mut routed: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for (position, document) in documents.into_iter().enumerate() {
+            let uid = self
+                .rules
+                .iter()
+                .find_map(|rule| rule.index_uid(&document))
+                .or_else(|| self.fallback_index_uid.clone())
+                .ok_or(IndexControllerError::UnroutableDocument(position))?;
+            routed.entry(uid).or_default().push(document);
+        }
+
+        Ok(routed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn document(value: Value) -> Document {
+        match value {
+            Value::Object(object) => object,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn route_documents() {
+        let routing: DocumentRouting = serde_json::from_value(json!({
+            "rules": [
+                { "field": "lang", "indexUid": "articles-{lang}" },
+                { "field": "kind", "indexUid": "{kind}s" },
+            ],
+        }))
+        .unwrap();
+
+        let documents = vec![
+            document(json!({ "id": 1, "lang": "en" })),
+            document(json!({ "id": 2, "lang": "fr", "kind": "page" })),
+            document(json!({ "id": 3, "kind": "page" })),
+            document(json!({ "id": 4, "lang": "en" })),
+        ];
+        let routed = routing.route(documents).unwrap();
+        let ids = |uid: &str| -> Vec<_> { routed[uid].iter().map(|d| d["id"].clone()).collect() };
+        assert_eq!(routed.len(), 3);
+        assert_eq!(ids("articles-en"), [json!(1), json!(4)]);
+        assert_eq!(ids("articles-fr"), [json!(2)]);
+        assert_eq!(ids("pages"), [json!(3)]);
+
+        let unroutable = vec![document(json!({ "id": 5, "lang": null }))];
+        assert!(matches!(
+            routing.route(unroutable.clone()),
+            Err(IndexControllerError::UnroutableDocument(0))
+        ));
+
+        let routing = DocumentRouting {
+            fallback_index_uid: Some(String::from("articles")),
+            ..routing
+        };
+        assert!(routing.route(unroutable).unwrap().contains_key("articles"));
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use rand::Rng;
+
+use crate::index::SearchQuery;
+
+/// How long a scroll is kept after its last page was returned.
+const SCROLL_KEEP_ALIVE: Duration = Duration::from_secs(5 * 60);
+/// The maximum number of scrolls kept at once, the ones expiring first being dropped to make room
+/// for the new ones.
+const MAX_OPEN_SCROLLS: usize = 100;
+/// The maximum number of scrolls an API key keeps at once, so that a single key can't drop the
+/// scrolls of the others.
+const MAX_OPEN_SCROLLS_PER_KEY: usize = 10;
+
+#[derive(Debug)]
+struct Scroll {
+    index_uid: String,
+    /// The API key that started the scroll, the only one that can continue it.
+    owner: Option<String>,
+    /// The query of the next page, holding the position of the scroll.
+    query: SearchQuery,
+    expires_at: Instant,
+}
+
+/// The scrolls in progress by cursor. They are kept in memory, a restart ends them.
+#[derive(Debug, Default)]
+pub struct Scrolls {
+    scrolls: Mutex<HashMap<String, Scroll>>,
+}
+
+impl Scrolls {
+    /// Keeps the query of the next page of a new scroll of the key `owner` and returns its
+    /// cursor.
+    pub fn open(&self, index_uid: String, owner: Option<String>, query: SearchQuery) -> String {
+        let cursor = format!("{:032x}", rand::thread_rng().gen::<u128>());
+        self.insert(cursor.clone(), index_uid, owner, query);
+        cursor
+    }
+
+    pub fn insert(
+        &self,
+        cursor: String,
+        index_uid: String,
+        owner: Option<String>,
+        query: SearchQuery,
+    ) {
+        let mut scrolls = self.scrolls.lock();
+        let now = Instant::now();
+        scrolls.retain(|_, scroll| scroll.expires_at > now);
+        let owned = scrolls
+            .values()
+            .filter(|scroll| scroll.owner == owner)
+            .count();
+        let first_expiring = if owned >= MAX_OPEN_SCROLLS_PER_KEY {
+            first_expiring(&scrolls, |scroll| scroll.owner == owner)
+        } else if scrolls.len() >= MAX_OPEN_SCROLLS {
+            first_expiring(&scrolls, |_| true)
+        } else {
+            None
+        };
+        if let Some(cursor) = first_expiring {
+            scrolls.remove(&cursor);
+        }
+
+        let scroll = Scroll {
+            index_uid,
+            owner,
+            query,
+            expires_at: now + SCROLL_KEEP_ALIVE,
+        };
+        scrolls.insert(cursor, scroll);
+    }
+
+    /// Removes the scroll of the index `index_uid` started by the key `owner` with this cursor and
+    /// returns the query of its next page, `None` if it doesn't exist or expired.
+    pub fn take(&self, index_uid: &str, owner: Option<&str>, cursor: &str) -> Option<SearchQuery> {
+        let mut scrolls = self.scrolls.lock();
+        match scrolls.get(cursor) {
+            Some(scroll)
+                if scroll.index_uid == index_uid
+                    && scroll.owner.as_deref() == owner
+                    && scroll.expires_at > Instant::now() =>
+            {
+                scrolls.remove(cursor).map(|scroll| scroll.query)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The cursor of the scroll expiring first among the ones matching `filter`.
+fn first_expiring(
+    scrolls: &HashMap<String, Scroll>,
+    filter: impl Fn(&Scroll) -> bool,
+) -> Option<String> {
+    scrolls
+        .iter()
+        .filter(|(_, scroll)| filter(scroll))
+        .min_by_key(|(_, scroll)| scroll.expires_at)
+        .map(|(cursor, _)| cursor.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn open_and_take_scrolls() {
+        let scrolls = Scrolls::default();
+        let query: SearchQuery = serde_json::from_str(r#"{ "q": "hello" }"#).unwrap();
+        let cursor = scrolls.open(String::from("movies"), Some(String::from("key")), query);
+
+        assert!(scrolls.take("books", Some("key"), &cursor).is_none());
+        // only the key that started the scroll can continue it
+        assert!(scrolls.take("movies", Some("other"), &cursor).is_none());
+        assert!(scrolls.take("movies", None, &cursor).is_none());
+        let query = scrolls.take("movies", Some("key"), &cursor).unwrap();
+        assert_eq!(query.q.as_deref(), Some("hello"));
+        // a page can only be returned once
+        assert!(scrolls.take("movies", Some("key"), &cursor).is_none());
+    }
+
+    #[test]
+    fn limit_the_scrolls_of_each_key() {
+        let scrolls = Scrolls::default();
+        let query: SearchQuery = serde_json::from_str("{}").unwrap();
+        let other = scrolls.open(String::from("movies"), None, query.clone());
+        let cursors: Vec<_> = (0..=MAX_OPEN_SCROLLS_PER_KEY)
+            .map(|_| {
+                scrolls.open(
+                    String::from("movies"),
+                    Some(String::from("key")),
+                    query.clone(),
+                )
+            })
+            .collect();
+
+        // the key dropped its own first scroll, not the one of the other key
+        assert!(scrolls.take("movies", Some("key"), &cursors[0]).is_none());
+        assert!(scrolls.take("movies", Some("key"), &cursors[1]).is_some());
+        assert!(scrolls.take("movies", None, &other).is_some());
+    }
+}
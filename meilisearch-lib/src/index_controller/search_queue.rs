@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::timeout;
+
+use crate::index::{SearchQuery, DEFAULT_SEARCH_LIMIT};
+
+/// Bounds the number of searches performed concurrently. A search waiting for too long is
+/// rejected instead, so that an overloaded instance sheds load rather than slowing every search
+/// down. A part of the slots is reserved to the cheap searches.
+pub struct SearchQueue {
+    shared: Semaphore,
+    reserved: Semaphore,
+    max_wait: Duration,
+}
+
+impl SearchQueue {
+    pub fn new(max_concurrent_searches: usize, max_wait: Duration) -> Self {
+        let max_concurrent_searches = max_concurrent_searches.max(1);
+        let reserved = max_concurrent_searches / 4;
+        Self {
+            shared: Semaphore::new(max_concurrent_searches - reserved),
+            reserved: Semaphore::new(reserved),
+            max_wait,
+        }
+    }
+
+    /// Waits for a slot to perform the search, returns `None` if none was freed in time.
+    pub async fn admit(&self, query: &SearchQuery) -> Option<SemaphorePermit<'_>> {
+        let permit = if is_cheap(query) {
+            timeout(self.max_wait, async {
+                tokio::select! {
+                    permit = self.shared.acquire() => permit,
+                    permit = self.reserved.acquire() => permit,
+                }
+            })
+            .await
+        } else {
+            timeout(self.max_wait, self.shared.acquire()).await
+        };
+
+        // The semaphores are never closed.
+        permit.ok().and_then(Result::ok)
+    }
+}
+
+/// A search is cheap when it computes no facet distribution and returns a few hits.
+fn is_cheap(query: &SearchQuery) -> bool {
+    query.facets_distribution.is_none() && query.limit() <= DEFAULT_SEARCH_LIMIT
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn cheap_searches_use_the_reserved_slots() {
+        let queue = SearchQueue::new(4, Duration::from_millis(10));
+        let cheap: SearchQuery = serde_json::from_value(json!({ "q": "hello" })).unwrap();
+        let expensive: SearchQuery =
+            serde_json::from_value(json!({ "q": "hello", "facetsDistribution": ["*"] })).unwrap();
+
+        let mut permits = Vec::new();
+        for _ in 0..3 {
+            permits.push(queue.admit(&expensive).await.unwrap());
+        }
+        assert!(queue.admit(&expensive).await.is_none());
+
+        permits.push(queue.admit(&cheap).await.unwrap());
+        assert!(queue.admit(&cheap).await.is_none());
+
+        permits.pop();
+        assert!(queue.admit(&cheap).await.is_some());
+    }
+}
@@ -10,32 +10,46 @@ use tokio::time::sleep;
 
 use crate::compression::from_tar_gz;
 use crate::index_controller::updates::UpdateMsg;
+use crate::io_throttle::IoThrottle;
 
+use super::api_keys::ApiKeyStore;
 use super::index_resolver::HardStateIndexResolver;
 use super::updates::UpdateSender;
+use super::versioning::{check_snapshot_version_file, write_version_file};
+use super::webhooks::WebhookStore;
 
 pub struct SnapshotService {
     index_resolver: Arc<HardStateIndexResolver>,
     update_sender: UpdateSender,
+    api_keys: ApiKeyStore,
+    webhooks: WebhookStore,
     snapshot_period: Duration,
     snapshot_path: PathBuf,
-    db_name: String,
+    db_path: PathBuf,
+    /// The maximum number of bytes read per second when the snapshots are compressed.
+    io_throttle: Option<u64>,
 }
 
 impl SnapshotService {
     pub fn new(
         index_resolver: Arc<HardStateIndexResolver>,
         update_sender: UpdateSender,
+        api_keys: ApiKeyStore,
+        webhooks: WebhookStore,
         snapshot_period: Duration,
         snapshot_path: PathBuf,
-        db_name: String,
+        db_path: PathBuf,
+        io_throttle: Option<u64>,
     ) -> Self {
         Self {
             index_resolver,
             update_sender,
+            api_keys,
+            webhooks,
             snapshot_period,
             snapshot_path,
-            db_name,
+            db_path,
+            io_throttle,
         }
     }
 
@@ -57,27 +71,58 @@ impl SnapshotService {
 
         let snapshot_dir = self.snapshot_path.clone();
         fs::create_dir_all(&snapshot_dir).await?;
-        let temp_snapshot_dir = spawn_blocking(tempfile::tempdir).await??;
+        // The snapshot is made next to the db, on the same filesystem, for its files to be
+        // cloned rather than copied.
+        let db_dir = match self.db_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_owned(),
+            _ => PathBuf::from("."),
+        };
+        let temp_snapshot_dir = spawn_blocking(move || tempfile::tempdir_in(db_dir)).await??;
         let temp_snapshot_path = temp_snapshot_dir.path().to_owned();
 
+        // The copies of the snapshot share the rate of the throttle.
+        let throttle = self.io_throttle.map(IoThrottle::new);
         let indexes = self
             .index_resolver
-            .snapshot(temp_snapshot_path.clone())
+            .snapshot(temp_snapshot_path.clone(), throttle.clone())
             .await?;
 
         if indexes.is_empty() {
             return Ok(());
         }
 
-        UpdateMsg::snapshot(&self.update_sender, temp_snapshot_path.clone(), indexes).await?;
-
-        let snapshot_path = self
-            .snapshot_path
-            .join(format!("{}.snapshot", self.db_name));
+        UpdateMsg::snapshot(
+            &self.update_sender,
+            temp_snapshot_path.clone(),
+            indexes,
+            throttle.clone(),
+        )
+        .await?;
+        let api_keys = self.api_keys.clone();
+        let keys_path = temp_snapshot_path.clone();
+        let keys_throttle = throttle.clone();
+        spawn_blocking(move || api_keys.snapshot(keys_path, keys_throttle.as_ref())).await??;
+        let webhooks = self.webhooks.clone();
+        let webhooks_path = temp_snapshot_path.clone();
+        let webhooks_throttle = throttle.clone();
+        spawn_blocking(move || webhooks.snapshot(webhooks_path, webhooks_throttle.as_ref()))
+            .await??;
+        write_version_file(&temp_snapshot_path)?;
+
+        let db_name = self
+            .db_path
+            .file_name()
+            .map(|n| n.to_owned().into_string().expect("invalid path"))
+            .unwrap_or_else(|| String::from("data.ms"));
+        let snapshot_path = self.snapshot_path.join(format!("{}.snapshot", db_name));
         let snapshot_path = spawn_blocking(move || -> anyhow::Result<PathBuf> {
-            let temp_snapshot_file = tempfile::NamedTempFile::new()?;
+            let temp_snapshot_file = tempfile::NamedTempFile::new_in(&snapshot_dir)?;
             let temp_snapshot_file_path = temp_snapshot_file.path().to_owned();
-            crate::compression::to_tar_gz(temp_snapshot_path, temp_snapshot_file_path)?;
+            crate::compression::to_tar_gz(
+                temp_snapshot_path,
+                temp_snapshot_file_path,
+                throttle.as_ref(),
+            )?;
             temp_snapshot_file.persist(&snapshot_path)?;
             Ok(snapshot_path)
         })
@@ -96,7 +141,9 @@ pub fn load_snapshot(
     ignore_missing_snapshot: bool,
 ) -> anyhow::Result<()> {
     if !db_path.as_ref().exists() && snapshot_path.as_ref().exists() {
-        match from_tar_gz(snapshot_path, &db_path) {
+        match from_tar_gz(snapshot_path, &db_path)
+            .and_then(|()| check_snapshot_version_file(&db_path))
+        {
             Ok(()) => Ok(()),
             Err(e) => {
                 //clean created db folder
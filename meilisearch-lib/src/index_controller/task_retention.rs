@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use log::{error, info, trace};
+use tokio::time::sleep;
+
+use super::updates::{DeleteFilter, UpdateMsg, UpdateSender};
+
+/// How often the updates finished for longer than the retention are deleted.
+const RETENTION_CHECK_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+/// Deletes the finished updates once they are older than the retention, for the update store not
+/// to grow without bound.
+pub struct TaskRetentionService {
+    update_sender: UpdateSender,
+    retention: Duration,
+}
+
+impl TaskRetentionService {
+    pub fn new(update_sender: UpdateSender, retention: Duration) -> Self {
+        Self {
+            update_sender,
+            retention,
+        }
+    }
+
+    pub async fn run(self) {
+        info!(
+            "Finished updates kept for {} days.",
+            self.retention.as_secs() / (24 * 60 * 60)
+        );
+        loop {
+            if let Err(e) = self.delete_expired().await {
+                error!("Error while deleting the expired updates: {}", e);
+            }
+            sleep(RETENTION_CHECK_PERIOD).await;
+        }
+    }
+
+    async fn delete_expired(&self) -> anyhow::Result<()> {
+        trace!("Deleting the expired updates.");
+
+        let retention = chrono::Duration::from_std(self.retention)?;
+        let filter = DeleteFilter {
+            finished_before: Some(Utc::now() - retention),
+            ..DeleteFilter::default()
+        };
+        let deleted = UpdateMsg::delete_finished(&self.update_sender, filter).await?;
+        if !deleted.is_empty() {
+            info!("Deleted {} expired updates.", deleted.len());
+        }
+
+        Ok(())
+    }
+}
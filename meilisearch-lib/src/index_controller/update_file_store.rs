@@ -10,7 +10,9 @@ use uuid::Uuid;
 
 const UPDATE_FILES_PATH: &str = "updates/updates_files";
 
-use crate::document_formats::read_ndjson;
+use crate::document_formats::{read_ndjson, DocumentLimits};
+use crate::file_clone::hard_link_or_copy;
+use crate::io_throttle::{IoThrottle, Throttled};
 
 pub struct UpdateFile {
     path: PathBuf,
@@ -69,13 +71,15 @@ pub struct UpdateFileStore {
 }
 
 impl UpdateFileStore {
-    pub fn load_dump(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> anyhow::Result<()> {
+    /// Loads the update files of a dump, returning the paths of the loaded files.
+    pub fn load_dump(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> anyhow::Result<Vec<PathBuf>> {
         let src_update_files_path = src.as_ref().join(UPDATE_FILES_PATH);
         let dst_update_files_path = dst.as_ref().join(UPDATE_FILES_PATH);
+        let mut loaded = Vec::new();
 
         // No update files to load
         if !src_update_files_path.exists() {
-            return Ok(());
+            return Ok(loaded);
         }
 
         create_dir_all(&dst_update_files_path)?;
@@ -90,11 +94,12 @@ impl UpdateFileStore {
                 .to_str()
                 .ok_or_else(|| anyhow::anyhow!("invalid update file name"))?;
             let dst_path = dst_update_files_path.join(file_uuid);
-            let dst_file = BufWriter::new(File::create(dst_path)?);
-            read_ndjson(update_file, dst_file)?;
+            let dst_file = BufWriter::new(File::create(&dst_path)?);
+            loaded.push(dst_path);
+            read_ndjson(update_file, dst_file, DocumentLimits::default())?;
         }
 
-        Ok(())
+        Ok(loaded)
     }
 
     pub fn new(path: impl AsRef<Path>) -> Result<Self> {
@@ -122,26 +127,52 @@ impl UpdateFileStore {
         Ok(file)
     }
 
-    /// Copies the content of the update file pointed to by `uuid` to the `dst` directory.
-    pub fn snapshot(&self, uuid: Uuid, dst: impl AsRef<Path>) -> Result<()> {
+    /// Copies the content of the update file pointed to by `uuid` to the `dst` directory. The
+    /// update files are never modified once written, they are hard linked when possible.
+    pub fn snapshot(
+        &self,
+        uuid: Uuid,
+        dst: impl AsRef<Path>,
+        throttle: Option<&IoThrottle>,
+    ) -> Result<()> {
         let src = self.path.join(uuid.to_string());
         let mut dst = dst.as_ref().join(UPDATE_FILES_PATH);
         std::fs::create_dir_all(&dst)?;
         dst.push(uuid.to_string());
-        std::fs::copy(src, dst)?;
+        hard_link_or_copy(src, dst, throttle)?;
+        Ok(())
+    }
+
+    /// Copies the update file pointed to by `uuid` from the `src` directory, where it was copied
+    /// by `snapshot`, back to the store.
+    pub fn restore(&self, uuid: Uuid, src: impl AsRef<Path>) -> Result<()> {
+        let src = src.as_ref().join(UPDATE_FILES_PATH).join(uuid.to_string());
+        std::fs::copy(src, self.path.join(uuid.to_string()))?;
         Ok(())
     }
 
     /// Peforms a dump of the given update file uuid into the provided dump path.
-    pub fn dump(&self, uuid: Uuid, dump_path: impl AsRef<Path>) -> Result<()> {
+    pub fn dump(
+        &self,
+        uuid: Uuid,
+        dump_path: impl AsRef<Path>,
+        throttle: Option<&IoThrottle>,
+    ) -> Result<()> {
         let uuid_string = uuid.to_string();
-        let update_file_path = self.path.join(&uuid_string);
         let mut dst = dump_path.as_ref().join(UPDATE_FILES_PATH);
         std::fs::create_dir_all(&dst)?;
         dst.push(&uuid_string);
 
-        let update_file = File::open(update_file_path)?;
         let mut dst_file = NamedTempFile::new()?;
+        self.write_ndjson(uuid, Throttled::new(&mut dst_file, throttle))?;
+        dst_file.persist(dst)?;
+
+        Ok(())
+    }
+
+    /// Writes the documents of the given update file uuid to `writer`, one JSON object per line.
+    pub fn write_ndjson(&self, uuid: Uuid, mut writer: impl Write) -> Result<()> {
+        let update_file = File::open(self.path.join(uuid.to_string()))?;
         let mut document_reader = DocumentBatchReader::from_reader(update_file)?;
 
         let mut document_buffer = Map::new();
@@ -155,16 +186,30 @@ impl UpdateFileStore {
                 }
             }
 
-            serde_json::to_writer(&mut dst_file, &document_buffer)?;
-            dst_file.write_all(b"\n")?;
+            serde_json::to_writer(&mut writer, &document_buffer)?;
+            writer.write_all(b"\n")?;
             document_buffer.clear();
         }
 
-        dst_file.persist(dst)?;
+        writer.flush()?;
 
         Ok(())
     }
 
+    /// Copies the update file pointed to by `uuid` to a new update file, returning its uuid.
+    pub fn copy(&self, uuid: Uuid) -> Result<Uuid> {
+        let copy = Uuid::new_v4();
+        std::fs::copy(
+            self.path.join(uuid.to_string()),
+            self.path.join(copy.to_string()),
+        )?;
+        Ok(copy)
+    }
+
+    pub fn contains(&self, uuid: Uuid) -> bool {
+        self.path.join(uuid.to_string()).exists()
+    }
+
     pub fn get_size(&self, uuid: Uuid) -> Result<u64> {
         Ok(self.get_update(uuid)?.metadata()?.len())
     }
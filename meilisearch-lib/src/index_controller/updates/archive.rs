@@ -0,0 +1,227 @@
+//! Continuous archive of the accepted updates, allowing to recover the state of the database at
+//! any point in time by replaying the archive on top of a snapshot. The archiving can be paused
+//! and the archive replayed while the instance runs.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use chrono::{DateTime, TimeZone, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tempfile::NamedTempFile;
+use uuid::Uuid;
+
+use super::error::Result;
+use super::status::{Enqueued, TraceContext, UpdateContext};
+use super::store::{Update, UpdateStore};
+use crate::index_controller::update_file_store::UpdateFileStore;
+
+/// The metadata stored in the archive for each accepted update.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveEntry {
+    pub index_uuid: Uuid,
+    pub update_id: u64,
+    pub enqueued_at: DateTime<Utc>,
+    pub update: Update,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<TraceContext>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_of: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveInfo {
+    /// Whether the accepted updates are archived, the archiving being paused otherwise.
+    pub archiving: bool,
+    /// The number of archived updates.
+    pub updates: usize,
+    pub oldest_enqueued_at: Option<DateTime<Utc>>,
+    pub latest_enqueued_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct UpdateArchive {
+    path: PathBuf,
+    archiving: Arc<AtomicBool>,
+}
+
+impl UpdateArchive {
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_owned();
+        std::fs::create_dir_all(&path)?;
+        Ok(Self {
+            path,
+            archiving: Arc::new(AtomicBool::new(true)),
+        })
+    }
+
+    pub fn is_archiving(&self) -> bool {
+        self.archiving.load(Ordering::Relaxed)
+    }
+
+    /// Pauses or resumes the archiving of the accepted updates. The updates accepted while it is
+    /// paused can't be replayed.
+    pub fn set_archiving(&self, archiving: bool) {
+        self.archiving.store(archiving, Ordering::Relaxed);
+    }
+
+    pub fn info(&self) -> Result<ArchiveInfo> {
+        let paths = self.paths()?;
+        let enqueued_at = |path: Option<&PathBuf>| {
+            let name = path?.file_name()?.to_str()?;
+            let (nanos, _) = name.split_once('-')?;
+            Some(Utc.timestamp_nanos(nanos.parse().ok()?))
+        };
+
+        Ok(ArchiveInfo {
+            archiving: self.is_archiving(),
+            updates: paths.len(),
+            oldest_enqueued_at: enqueued_at(paths.first()),
+            latest_enqueued_at: enqueued_at(paths.last()),
+        })
+    }
+
+    /// Copies the payload of an update to the archive. This must be done before the update is
+    /// registered, since its payload is deleted once processed.
+    pub fn archive_payload(
+        &self,
+        update_file_store: &UpdateFileStore,
+        update: &Update,
+    ) -> Result<()> {
+        if let Update::DocumentAddition { content_uuid, .. } = update {
+            update_file_store.snapshot(*content_uuid, &self.path, None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the metadata of a registered update to the archive.
+    pub fn archive(&self, index_uuid: Uuid, enqueued: &Enqueued) -> Result<()> {
+        let entry = ArchiveEntry {
+            index_uuid,
+            update_id: enqueued.id(),
+            enqueued_at: enqueued.enqueued_at,
+            update: enqueued.meta().clone(),
+            metadata: enqueued.metadata().cloned(),
+            trace: enqueued.trace.clone(),
+            retry_of: enqueued.retry_of,
+        };
+
+        let name = format!(
+            "{:020}-{}-{:020}.json",
+            entry.enqueued_at.timestamp_nanos(),
+            index_uuid,
+            entry.update_id
+        );
+        let mut file = NamedTempFile::new_in(&self.path)?;
+        serde_json::to_writer(&mut file, &entry)?;
+        file.persist(self.path.join(name)).map_err(|e| e.error)?;
+
+        Ok(())
+    }
+
+    /// Returns the paths of the archived entries, in their order of arrival.
+    fn paths(&self) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            if path.extension().map_or(false, |ext| ext == "json") {
+                paths.push(path);
+            }
+        }
+        // The file names start with the enqueue timestamp.
+        paths.sort();
+
+        Ok(paths)
+    }
+
+    /// Returns the archived entries enqueued up to `until`, in their order of arrival.
+    fn entries(&self, until: DateTime<Utc>) -> Result<Vec<ArchiveEntry>> {
+        let mut entries = Vec::new();
+        for path in self.paths()? {
+            let entry: ArchiveEntry = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+            if entry.enqueued_at > until {
+                break;
+            }
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+
+    /// Re-registers the archived updates enqueued up to `until` that are unknown to the update
+    /// store, typically after the restoration of a snapshot taken before them. Returns the number
+    /// of replayed updates.
+    ///
+    /// The indexes are not part of the archive: the updates of an index created after the
+    /// snapshot will fail to be processed.
+    pub fn replay(
+        &self,
+        store: &UpdateStore,
+        update_file_store: &UpdateFileStore,
+        until: DateTime<Utc>,
+    ) -> Result<usize> {
+        let mut replayed = 0;
+        for entry in self.entries(until)? {
+            if store.meta(entry.index_uuid, entry.update_id)?.is_some() {
+                continue;
+            }
+
+            if let Update::DocumentAddition { content_uuid, .. } = entry.update {
+                update_file_store.restore(content_uuid, &self.path)?;
+            }
+
+            let context = UpdateContext {
+                metadata: entry.metadata,
+                trace: entry.trace,
+            };
+            store.register_update(entry.index_uuid, entry.update, context, entry.retry_of)?;
+            replayed += 1;
+        }
+
+        info!(
+            "Replayed {} updates from the archive {:?}.",
+            replayed, self.path
+        );
+
+        Ok(replayed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn archived_entries_are_ordered_and_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = UpdateArchive::new(dir.path()).unwrap();
+        let index_uuid = Uuid::new_v4();
+
+        let first = Enqueued::new(Update::ClearDocuments, 0);
+        let second = Enqueued::new(Update::DeleteDocuments(vec![String::from("1")]), 1);
+        let mut third = Enqueued::new(Update::ClearDocuments, 2);
+        third.enqueued_at = second.enqueued_at + chrono::Duration::hours(1);
+
+        archive.archive(index_uuid, &third).unwrap();
+        archive.archive(index_uuid, &first).unwrap();
+        archive.archive(index_uuid, &second).unwrap();
+
+        let entries = archive.entries(second.enqueued_at).unwrap();
+        let ids: Vec<_> = entries.iter().map(|entry| entry.update_id).collect();
+        assert_eq!(ids, vec![0, 1]);
+
+        let info = archive.info().unwrap();
+        assert_eq!(info.updates, 3);
+        assert_eq!(info.oldest_enqueued_at, Some(first.enqueued_at));
+        assert_eq!(info.latest_enqueued_at, Some(third.enqueued_at));
+    }
+}
@@ -15,6 +15,8 @@ pub type Result<T> = std::result::Result<T, UpdateLoopError>;
 pub enum UpdateLoopError {
     #[error("Update {0} not found.")]
     UnexistingUpdate(u64),
+    #[error("Task {0} not found.")]
+    UnexistingTask(u64),
     #[error("Internal error: {0}")]
     Internal(Box<dyn Error + Send + Sync + 'static>),
     #[error(
@@ -28,6 +30,10 @@ pub enum UpdateLoopError {
     PayloadError(#[from] actix_web::error::PayloadError),
     #[error("A {0} payload is missing.")]
     MissingPayload(DocumentAdditionFormat),
+    #[error("Update {0} can't be retried, only the failed updates can, along with their payload.")]
+    NotRetryable(u64),
+    #[error("The update archive is disabled, it is enabled with `--update-archive-dir`.")]
+    ArchiveDisabled,
 }
 
 impl<T> From<tokio::sync::mpsc::error::SendError<T>> for UpdateLoopError
@@ -56,7 +62,7 @@ internal_error!(
 impl ErrorCode for UpdateLoopError {
     fn error_code(&self) -> Code {
         match self {
-            Self::UnexistingUpdate(_) => Code::NotFound,
+            Self::UnexistingUpdate(_) | Self::UnexistingTask(_) => Code::NotFound,
             Self::Internal(_) => Code::Internal,
             //Self::IndexActor(e) => e.error_code(),
             Self::FatalUpdateStoreError => Code::Internal,
@@ -66,6 +72,8 @@ impl ErrorCode for UpdateLoopError {
                 _ => Code::Internal,
             },
             Self::MissingPayload(_) => Code::MissingPayload,
+            Self::NotRetryable(_) => Code::UpdateNotRetryable,
+            Self::ArchiveDisabled => Code::UpdateArchiveDisabled,
         }
     }
 }
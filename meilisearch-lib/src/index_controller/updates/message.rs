@@ -1,11 +1,16 @@
 use std::path::PathBuf;
 
-use tokio::sync::{mpsc, oneshot};
+use chrono::{DateTime, Utc};
+use tokio::sync::{broadcast, mpsc, oneshot};
 use uuid::Uuid;
 
 use crate::index::Index;
+use crate::io_throttle::IoThrottle;
 
+use super::archive::ArchiveInfo;
 use super::error::Result;
+use super::status::UpdateContext;
+use super::store::{CancelFilter, Cancellation, DeleteFilter, TaskFilter};
 use super::{Update, UpdateStatus, UpdateStoreInfo};
 
 #[derive(Debug)]
@@ -13,6 +18,12 @@ pub enum UpdateMsg {
     Update {
         uuid: Uuid,
         update: Update,
+        context: UpdateContext,
+        ret: oneshot::Sender<Result<UpdateStatus>>,
+    },
+    Retry {
+        uuid: Uuid,
+        id: u64,
         ret: oneshot::Sender<Result<UpdateStatus>>,
     },
     ListUpdates {
@@ -28,19 +39,66 @@ pub enum UpdateMsg {
         uuid: Uuid,
         ret: oneshot::Sender<Result<()>>,
     },
+    MovePending {
+        from: Uuid,
+        to: Uuid,
+        ret: oneshot::Sender<Result<()>>,
+    },
+    Cancel {
+        filter: CancelFilter,
+        ret: oneshot::Sender<Result<Cancellation>>,
+    },
+    DeleteFinished {
+        filter: DeleteFilter,
+        ret: oneshot::Sender<Result<Vec<(Uuid, u64)>>>,
+    },
+    ListTasks {
+        filter: TaskFilter,
+        from: Option<u64>,
+        limit: usize,
+        ret: oneshot::Sender<Result<Vec<(Uuid, UpdateStatus)>>>,
+    },
+    GetTask {
+        task_uid: u64,
+        ret: oneshot::Sender<Result<(Uuid, UpdateStatus)>>,
+    },
     Snapshot {
         indexes: Vec<Index>,
         path: PathBuf,
+        throttle: Option<IoThrottle>,
         ret: oneshot::Sender<Result<()>>,
     },
     Dump {
         indexes: Vec<Index>,
         path: PathBuf,
+        throttle: Option<IoThrottle>,
         ret: oneshot::Sender<Result<()>>,
     },
     GetInfo {
         ret: oneshot::Sender<Result<UpdateStoreInfo>>,
     },
+    Subscribe {
+        ret: oneshot::Sender<broadcast::Receiver<(Uuid, u64)>>,
+    },
+    GetArchiveInfo {
+        ret: oneshot::Sender<Result<ArchiveInfo>>,
+    },
+    SetArchiving {
+        archiving: bool,
+        ret: oneshot::Sender<Result<ArchiveInfo>>,
+    },
+    ReplayArchive {
+        until: DateTime<Utc>,
+        ret: oneshot::Sender<Result<usize>>,
+    },
+    FinishedTasksAfter {
+        after: Option<u64>,
+        limit: usize,
+        ret: oneshot::Sender<Result<(Vec<(u64, Uuid, UpdateStatus)>, Option<u64>)>>,
+    },
+    LastFinishedTaskUid {
+        ret: oneshot::Sender<Result<Option<u64>>>,
+    },
 }
 
 impl UpdateMsg {
@@ -48,9 +106,15 @@ impl UpdateMsg {
         sender: &mpsc::Sender<Self>,
         path: PathBuf,
         indexes: Vec<Index>,
+        throttle: Option<IoThrottle>,
     ) -> Result<()> {
         let (ret, rcv) = oneshot::channel();
-        let msg = Self::Snapshot { path, indexes, ret };
+        let msg = Self::Snapshot {
+            path,
+            indexes,
+            throttle,
+            ret,
+        };
         sender.send(msg).await?;
         rcv.await?
     }
@@ -59,9 +123,15 @@ impl UpdateMsg {
         sender: &mpsc::Sender<Self>,
         indexes: Vec<Index>,
         path: PathBuf,
+        throttle: Option<IoThrottle>,
     ) -> Result<()> {
         let (ret, rcv) = oneshot::channel();
-        let msg = Self::Dump { path, indexes, ret };
+        let msg = Self::Dump {
+            path,
+            indexes,
+            throttle,
+            ret,
+        };
         sender.send(msg).await?;
         rcv.await?
     }
@@ -69,9 +139,30 @@ impl UpdateMsg {
         sender: &mpsc::Sender<Self>,
         uuid: Uuid,
         update: Update,
+    ) -> Result<UpdateStatus> {
+        Self::update_with_context(sender, uuid, update, UpdateContext::default()).await
+    }
+
+    pub async fn update_with_context(
+        sender: &mpsc::Sender<Self>,
+        uuid: Uuid,
+        update: Update,
+        context: UpdateContext,
     ) -> Result<UpdateStatus> {
         let (ret, rcv) = oneshot::channel();
-        let msg = Self::Update { uuid, update, ret };
+        let msg = Self::Update {
+            uuid,
+            update,
+            context,
+            ret,
+        };
+        sender.send(msg).await?;
+        rcv.await?
+    }
+
+    pub async fn retry(sender: &mpsc::Sender<Self>, uuid: Uuid, id: u64) -> Result<UpdateStatus> {
+        let (ret, rcv) = oneshot::channel();
+        let msg = Self::Retry { uuid, id, ret };
         sender.send(msg).await?;
         rcv.await?
     }
@@ -104,10 +195,119 @@ impl UpdateMsg {
         rcv.await?
     }
 
+    pub async fn archive_info(sender: &mpsc::Sender<Self>) -> Result<ArchiveInfo> {
+        let (ret, rcv) = oneshot::channel();
+        let msg = Self::GetArchiveInfo { ret };
+        sender.send(msg).await?;
+        rcv.await?
+    }
+
+    pub async fn set_archiving(
+        sender: &mpsc::Sender<Self>,
+        archiving: bool,
+    ) -> Result<ArchiveInfo> {
+        let (ret, rcv) = oneshot::channel();
+        let msg = Self::SetArchiving { archiving, ret };
+        sender.send(msg).await?;
+        rcv.await?
+    }
+
+    pub async fn replay_archive(
+        sender: &mpsc::Sender<Self>,
+        until: DateTime<Utc>,
+    ) -> Result<usize> {
+        let (ret, rcv) = oneshot::channel();
+        let msg = Self::ReplayArchive { until, ret };
+        sender.send(msg).await?;
+        rcv.await?
+    }
+
+    /// Returns a receiver notified of the index and id of the updates completed from now on.
+    pub async fn subscribe(
+        sender: &mpsc::Sender<Self>,
+    ) -> Result<broadcast::Receiver<(Uuid, u64)>> {
+        let (ret, rcv) = oneshot::channel();
+        let msg = Self::Subscribe { ret };
+        sender.send(msg).await?;
+        Ok(rcv.await?)
+    }
+
+    /// Returns the finished tasks following the task uid `after`, in order.
+    pub async fn finished_tasks_after(
+        sender: &mpsc::Sender<Self>,
+        after: Option<u64>,
+        limit: usize,
+    ) -> Result<(Vec<(u64, Uuid, UpdateStatus)>, Option<u64>)> {
+        let (ret, rcv) = oneshot::channel();
+        let msg = Self::FinishedTasksAfter { after, limit, ret };
+        sender.send(msg).await?;
+        rcv.await?
+    }
+
+    pub async fn last_finished_task_uid(sender: &mpsc::Sender<Self>) -> Result<Option<u64>> {
+        let (ret, rcv) = oneshot::channel();
+        let msg = Self::LastFinishedTaskUid { ret };
+        sender.send(msg).await?;
+        rcv.await?
+    }
+
     pub async fn delete(sender: &mpsc::Sender<Self>, uuid: Uuid) -> Result<()> {
         let (ret, rcv) = oneshot::channel();
         let msg = Self::DeleteIndex { ret, uuid };
         sender.send(msg).await?;
         rcv.await?
     }
+
+    /// Moves the pending updates of the index `from` to the index `to`.
+    pub async fn move_pending(sender: &mpsc::Sender<Self>, from: Uuid, to: Uuid) -> Result<()> {
+        let (ret, rcv) = oneshot::channel();
+        let msg = Self::MovePending { from, to, ret };
+        sender.send(msg).await?;
+        rcv.await?
+    }
+
+    pub async fn cancel(sender: &mpsc::Sender<Self>, filter: CancelFilter) -> Result<Cancellation> {
+        let (ret, rcv) = oneshot::channel();
+        let msg = Self::Cancel { filter, ret };
+        sender.send(msg).await?;
+        rcv.await?
+    }
+
+    /// Deletes the finished updates matching `filter`, returning their index and id.
+    pub async fn delete_finished(
+        sender: &mpsc::Sender<Self>,
+        filter: DeleteFilter,
+    ) -> Result<Vec<(Uuid, u64)>> {
+        let (ret, rcv) = oneshot::channel();
+        let msg = Self::DeleteFinished { filter, ret };
+        sender.send(msg).await?;
+        rcv.await?
+    }
+
+    pub async fn list_tasks(
+        sender: &mpsc::Sender<Self>,
+        filter: TaskFilter,
+        from: Option<u64>,
+        limit: usize,
+    ) -> Result<Vec<(Uuid, UpdateStatus)>> {
+        let (ret, rcv) = oneshot::channel();
+        let msg = Self::ListTasks {
+            filter,
+            from,
+            limit,
+            ret,
+        };
+        sender.send(msg).await?;
+        rcv.await?
+    }
+
+    pub async fn get_task(
+        sender: &mpsc::Sender<Self>,
+        task_uid: u64,
+    ) -> Result<(Uuid, UpdateStatus)> {
+        let (ret, rcv) = oneshot::channel();
+        let msg = Self::GetTask { task_uid, ret };
+        sender.send(msg).await?;
+        rcv.await?
+    }
 }
@@ -1,5 +1,7 @@
+pub mod archive;
 pub mod error;
 mod message;
+mod panic_report;
 pub mod status;
 pub mod store;
 
@@ -11,6 +13,7 @@ use std::sync::Arc;
 use actix_web::error::PayloadError;
 use async_stream::stream;
 use bytes::Bytes;
+use chrono::{DateTime, Utc};
 use futures::{Stream, StreamExt};
 use log::trace;
 use milli::update::IndexDocumentsMethod;
@@ -18,13 +21,16 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
+use self::archive::{ArchiveInfo, UpdateArchive};
 use self::error::{Result, UpdateLoopError};
 pub use self::message::UpdateMsg;
+pub use self::store::{CancelFilter, Cancellation, DeleteFilter, TaskFilter, UpdateKind};
 use self::store::{UpdateStore, UpdateStoreInfo};
-use crate::document_formats::{read_csv, read_json, read_ndjson};
+use crate::document_formats::{read_csv, read_json, read_ndjson, DocumentLimits};
 use crate::index::{Index, Settings, Unchecked};
 use crate::index_controller::update_file_store::UpdateFileStore;
-use status::UpdateStatus;
+use crate::io_throttle::IoThrottle;
+use status::{UpdateContext, UpdateStatus};
 
 use super::index_resolver::HardStateIndexResolver;
 use super::{DocumentAdditionFormat, Update};
@@ -35,10 +41,21 @@ pub fn create_update_handler(
     index_resolver: Arc<HardStateIndexResolver>,
     db_path: impl AsRef<Path>,
     update_store_size: usize,
+    archive: Option<UpdateArchive>,
+    replay_until: Option<DateTime<Utc>>,
+    document_limits: DocumentLimits,
 ) -> anyhow::Result<UpdateSender> {
     let path = db_path.as_ref().to_owned();
     let (sender, receiver) = mpsc::channel(100);
-    let actor = UpdateLoop::new(update_store_size, receiver, path, index_resolver)?;
+    let actor = UpdateLoop::new(
+        update_store_size,
+        receiver,
+        path,
+        index_resolver,
+        archive,
+        replay_until,
+        document_limits,
+    )?;
 
     tokio::task::spawn(actor.run());
 
@@ -46,13 +63,13 @@ pub fn create_update_handler(
 }
 
 /// A wrapper type to implement read on a `Stream<Result<Bytes, Error>>`.
-struct StreamReader<S> {
+pub(crate) struct StreamReader<S> {
     stream: S,
     current: Option<Bytes>,
 }
 
 impl<S> StreamReader<S> {
-    fn new(stream: S) -> Self {
+    pub(crate) fn new(stream: S) -> Self {
         Self {
             stream,
             current: None,
@@ -91,6 +108,10 @@ pub struct UpdateLoop {
     store: Arc<UpdateStore>,
     inbox: Option<mpsc::Receiver<UpdateMsg>>,
     update_file_store: UpdateFileStore,
+    /// Where the accepted updates are archived, if the archive mode is enabled.
+    archive: Option<UpdateArchive>,
+    /// The limits enforced on the documents of the payloads.
+    document_limits: DocumentLimits,
     must_exit: Arc<AtomicBool>,
 }
 
@@ -100,6 +121,9 @@ impl UpdateLoop {
         inbox: mpsc::Receiver<UpdateMsg>,
         path: impl AsRef<Path>,
         index_resolver: Arc<HardStateIndexResolver>,
+        archive: Option<UpdateArchive>,
+        replay_until: Option<DateTime<Utc>>,
+        document_limits: DocumentLimits,
     ) -> anyhow::Result<Self> {
         let path = path.as_ref().to_owned();
         std::fs::create_dir_all(&path)?;
@@ -118,6 +142,10 @@ impl UpdateLoop {
             update_file_store.clone(),
         )?;
 
+        if let (Some(archive), Some(until)) = (&archive, replay_until) {
+            archive.replay(&store, &update_file_store, until)?;
+        }
+
         let inbox = Some(inbox);
 
         Ok(Self {
@@ -125,6 +153,8 @@ impl UpdateLoop {
             inbox,
             must_exit,
             update_file_store,
+            archive,
+            document_limits,
         })
     }
 
@@ -157,8 +187,16 @@ impl UpdateLoop {
         stream
             .for_each_concurrent(Some(10), |msg| async {
                 match msg {
-                    Update { uuid, update, ret } => {
-                        let _ = ret.send(self.handle_update(uuid, update).await);
+                    Update {
+                        uuid,
+                        update,
+                        context,
+                        ret,
+                    } => {
+                        let _ = ret.send(self.handle_update(uuid, update, context).await);
+                    }
+                    Retry { uuid, id, ret } => {
+                        let _ = ret.send(self.handle_retry(uuid, id).await);
                     }
                     ListUpdates { uuid, ret } => {
                         let _ = ret.send(self.handle_list_updates(uuid).await);
@@ -169,21 +207,74 @@ impl UpdateLoop {
                     DeleteIndex { uuid, ret } => {
                         let _ = ret.send(self.handle_delete(uuid).await);
                     }
-                    Snapshot { indexes, path, ret } => {
-                        let _ = ret.send(self.handle_snapshot(indexes, path).await);
+                    MovePending { from, to, ret } => {
+                        let _ = ret.send(self.handle_move_pending(from, to).await);
+                    }
+                    Cancel { filter, ret } => {
+                        let _ = ret.send(self.handle_cancel(filter).await);
+                    }
+                    DeleteFinished { filter, ret } => {
+                        let _ = ret.send(self.handle_delete_finished(filter).await);
+                    }
+                    ListTasks {
+                        filter,
+                        from,
+                        limit,
+                        ret,
+                    } => {
+                        let _ = ret.send(self.handle_list_tasks(filter, from, limit).await);
+                    }
+                    GetTask { task_uid, ret } => {
+                        let _ = ret.send(self.handle_get_task(task_uid).await);
+                    }
+                    Snapshot {
+                        indexes,
+                        path,
+                        throttle,
+                        ret,
+                    } => {
+                        let _ = ret.send(self.handle_snapshot(indexes, path, throttle).await);
                     }
                     GetInfo { ret } => {
                         let _ = ret.send(self.handle_get_info().await);
                     }
-                    Dump { indexes, path, ret } => {
-                        let _ = ret.send(self.handle_dump(indexes, path).await);
+                    Dump {
+                        indexes,
+                        path,
+                        throttle,
+                        ret,
+                    } => {
+                        let _ = ret.send(self.handle_dump(indexes, path, throttle).await);
+                    }
+                    Subscribe { ret } => {
+                        let _ = ret.send(self.store.subscribe());
+                    }
+                    GetArchiveInfo { ret } => {
+                        let _ = ret.send(self.handle_archive_info().await);
+                    }
+                    SetArchiving { archiving, ret } => {
+                        let _ = ret.send(self.handle_set_archiving(archiving).await);
+                    }
+                    ReplayArchive { until, ret } => {
+                        let _ = ret.send(self.handle_replay_archive(until).await);
+                    }
+                    FinishedTasksAfter { after, limit, ret } => {
+                        let _ = ret.send(self.handle_finished_tasks_after(after, limit).await);
+                    }
+                    LastFinishedTaskUid { ret } => {
+                        let _ = ret.send(self.handle_last_finished_task_uid().await);
                     }
                 }
             })
             .await;
     }
 
-    async fn handle_update(&self, index_uuid: Uuid, update: Update) -> Result<UpdateStatus> {
+    async fn handle_update(
+        &self,
+        index_uuid: Uuid,
+        update: Update,
+        context: UpdateContext,
+    ) -> Result<UpdateStatus> {
         let registration = match update {
             Update::DocumentAddition {
                 payload,
@@ -193,6 +284,7 @@ impl UpdateLoop {
             } => {
                 let mut reader = BufReader::new(StreamReader::new(payload));
                 let (content_uuid, mut update_file) = self.update_file_store.new_update()?;
+                let limits = self.document_limits;
                 tokio::task::spawn_blocking(move || -> Result<_> {
                     // check if the payload is empty, and return an error
                     reader.fill_buf()?;
@@ -201,9 +293,13 @@ impl UpdateLoop {
                     }
 
                     match format {
-                        DocumentAdditionFormat::Json => read_json(reader, &mut *update_file)?,
-                        DocumentAdditionFormat::Csv => read_csv(reader, &mut *update_file)?,
-                        DocumentAdditionFormat::Ndjson => read_ndjson(reader, &mut *update_file)?,
+                        DocumentAdditionFormat::Json => {
+                            read_json(reader, &mut *update_file, limits)?
+                        }
+                        DocumentAdditionFormat::Csv => read_csv(reader, &mut *update_file, limits)?,
+                        DocumentAdditionFormat::Ndjson => {
+                            read_ndjson(reader, &mut *update_file, limits)?
+                        }
                     }
 
                     update_file.persist()?;
@@ -221,16 +317,114 @@ impl UpdateLoop {
             Update::Settings(settings) => store::Update::Settings(settings),
             Update::ClearDocuments => store::Update::ClearDocuments,
             Update::DeleteDocuments(ids) => store::Update::DeleteDocuments(ids),
+            Update::DeleteByFilter { filter } => store::Update::DeleteByFilter { filter },
         };
 
         let store = self.store.clone();
-        let status =
-            tokio::task::spawn_blocking(move || store.register_update(index_uuid, registration))
-                .await??;
+        let archive = self.active_archive();
+        let update_file_store = self.update_file_store.clone();
+        let status = tokio::task::spawn_blocking(move || -> Result<_> {
+            if let Some(ref archive) = archive {
+                archive.archive_payload(&update_file_store, &registration)?;
+            }
+
+            let status = store.register_update(index_uuid, registration, context, None)?;
+
+            if let Some(ref archive) = archive {
+                archive.archive(index_uuid, &status)?;
+            }
+
+            Ok(status)
+        })
+        .await??;
+
+        Ok(status.into())
+    }
+
+    /// Enqueues a failed update again, with the same payload, metadata and trace.
+    async fn handle_retry(&self, index_uuid: Uuid, id: u64) -> Result<UpdateStatus> {
+        let store = self.store.clone();
+        let archive = self.active_archive();
+        let update_file_store = self.update_file_store.clone();
+        let status = tokio::task::spawn_blocking(move || -> Result<_> {
+            let enqueued = match store.meta(index_uuid, id)? {
+                Some(UpdateStatus::Failed(failed)) => failed.from.from,
+                Some(_) => return Err(UpdateLoopError::NotRetryable(id)),
+                None => return Err(UpdateLoopError::UnexistingUpdate(id)),
+            };
+
+            let registration = match enqueued.meta {
+                store::Update::DocumentAddition {
+                    primary_key,
+                    method,
+                    content_uuid,
+                } => {
+                    if !update_file_store.contains(content_uuid) {
+                        return Err(UpdateLoopError::NotRetryable(id));
+                    }
+                    // The payload of the failed update is kept with it.
+                    store::Update::DocumentAddition {
+                        primary_key,
+                        method,
+                        content_uuid: update_file_store.copy(content_uuid)?,
+                    }
+                }
+                update => update,
+            };
+
+            if let Some(ref archive) = archive {
+                archive.archive_payload(&update_file_store, &registration)?;
+            }
+
+            let context = enqueued.context();
+            let status = store.register_update(index_uuid, registration, context, Some(id))?;
+
+            if let Some(ref archive) = archive {
+                archive.archive(index_uuid, &status)?;
+            }
+
+            Ok(status)
+        })
+        .await??;
 
         Ok(status.into())
     }
 
+    /// The archive the accepted updates are archived in, none when the archiving is paused.
+    fn active_archive(&self) -> Option<UpdateArchive> {
+        self.archive
+            .clone()
+            .filter(|archive| archive.is_archiving())
+    }
+
+    async fn handle_archive_info(&self) -> Result<ArchiveInfo> {
+        let archive = self
+            .archive
+            .clone()
+            .ok_or(UpdateLoopError::ArchiveDisabled)?;
+        tokio::task::spawn_blocking(move || archive.info()).await?
+    }
+
+    async fn handle_set_archiving(&self, archiving: bool) -> Result<ArchiveInfo> {
+        let archive = self
+            .archive
+            .clone()
+            .ok_or(UpdateLoopError::ArchiveDisabled)?;
+        archive.set_archiving(archiving);
+        tokio::task::spawn_blocking(move || archive.info()).await?
+    }
+
+    async fn handle_replay_archive(&self, until: DateTime<Utc>) -> Result<usize> {
+        let archive = self
+            .archive
+            .clone()
+            .ok_or(UpdateLoopError::ArchiveDisabled)?;
+        let store = self.store.clone();
+        let update_file_store = self.update_file_store.clone();
+        tokio::task::spawn_blocking(move || archive.replay(&store, &update_file_store, until))
+            .await?
+    }
+
     async fn handle_list_updates(&self, uuid: Uuid) -> Result<Vec<UpdateStatus>> {
         let update_store = self.store.clone();
         tokio::task::spawn_blocking(move || {
@@ -259,19 +453,81 @@ impl UpdateLoop {
         Ok(())
     }
 
-    async fn handle_snapshot(&self, indexes: Vec<Index>, path: PathBuf) -> Result<()> {
+    async fn handle_move_pending(&self, from: Uuid, to: Uuid) -> Result<()> {
+        let store = self.store.clone();
+        tokio::task::spawn_blocking(move || store.move_pending(from, to)).await?
+    }
+
+    async fn handle_cancel(&self, filter: CancelFilter) -> Result<Cancellation> {
+        let store = self.store.clone();
+        tokio::task::spawn_blocking(move || store.cancel(&filter)).await?
+    }
+
+    async fn handle_delete_finished(&self, filter: DeleteFilter) -> Result<Vec<(Uuid, u64)>> {
+        let store = self.store.clone();
+        tokio::task::spawn_blocking(move || store.delete_finished(&filter)).await?
+    }
+
+    async fn handle_list_tasks(
+        &self,
+        filter: TaskFilter,
+        from: Option<u64>,
+        limit: usize,
+    ) -> Result<Vec<(Uuid, UpdateStatus)>> {
+        let store = self.store.clone();
+        tokio::task::spawn_blocking(move || store.tasks(&filter, from, limit)).await?
+    }
+
+    async fn handle_finished_tasks_after(
+        &self,
+        after: Option<u64>,
+        limit: usize,
+    ) -> Result<(Vec<(u64, Uuid, UpdateStatus)>, Option<u64>)> {
+        let store = self.store.clone();
+        Ok(tokio::task::spawn_blocking(move || store.finished_tasks_after(after, limit)).await??)
+    }
+
+    async fn handle_last_finished_task_uid(&self) -> Result<Option<u64>> {
+        let store = self.store.clone();
+        Ok(tokio::task::spawn_blocking(move || store.last_finished_task_uid()).await??)
+    }
+
+    async fn handle_get_task(&self, task_uid: u64) -> Result<(Uuid, UpdateStatus)> {
+        let store = self.store.clone();
+        tokio::task::spawn_blocking(move || {
+            store
+                .task(task_uid)?
+                .ok_or(UpdateLoopError::UnexistingTask(task_uid))
+        })
+        .await?
+    }
+
+    async fn handle_snapshot(
+        &self,
+        indexes: Vec<Index>,
+        path: PathBuf,
+        throttle: Option<IoThrottle>,
+    ) -> Result<()> {
         let update_store = self.store.clone();
 
-        tokio::task::spawn_blocking(move || update_store.snapshot(indexes, path)).await??;
+        tokio::task::spawn_blocking(move || {
+            update_store.snapshot(indexes, path, throttle.as_ref())
+        })
+        .await??;
 
         Ok(())
     }
 
-    async fn handle_dump(&self, indexes: Vec<Index>, path: PathBuf) -> Result<()> {
+    async fn handle_dump(
+        &self,
+        indexes: Vec<Index>,
+        path: PathBuf,
+        throttle: Option<IoThrottle>,
+    ) -> Result<()> {
         let update_store = self.store.clone();
 
         tokio::task::spawn_blocking(move || -> Result<()> {
-            update_store.dump(&indexes, path.to_path_buf())?;
+            update_store.dump(&indexes, path.to_path_buf(), throttle.as_ref())?;
             Ok(())
         })
         .await??;
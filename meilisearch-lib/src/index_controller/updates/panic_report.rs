@@ -0,0 +1,112 @@
+//! Isolation of the panics happening while processing an update, so that a panic in milli fails
+//! the update instead of bringing the update loop down.
+
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+use backtrace::Backtrace;
+use meilisearch_error::{Code, ErrorCode};
+
+thread_local! {
+    /// The location and backtrace hash of the last panic of the thread, set by the panic hook.
+    static LAST_PANIC: RefCell<Option<(Option<String>, String)>> = RefCell::new(None);
+}
+
+#[derive(Debug)]
+pub struct PanicReport {
+    pub message: String,
+    /// The file, line and column where the panic happened.
+    pub location: Option<String>,
+    /// A hash of the symbols of the backtrace, identifying the panics happening in the same
+    /// conditions without exposing the whole backtrace.
+    pub backtrace_hash: String,
+}
+
+impl fmt::Display for PanicReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The update panicked: {}", self.message)?;
+        if let Some(ref location) = self.location {
+            write!(f, " at {}", location)?;
+        }
+        write!(f, " (backtrace {}).", self.backtrace_hash)
+    }
+}
+
+impl std::error::Error for PanicReport {}
+
+impl ErrorCode for PanicReport {
+    fn error_code(&self) -> Code {
+        Code::UpdatePanicked
+    }
+}
+
+fn backtrace_hash(backtrace: &Backtrace) -> String {
+    // The addresses change from one run to another, the symbol names don't.
+    let mut hasher = DefaultHasher::new();
+    for frame in backtrace.frames() {
+        for symbol in frame.symbols() {
+            if let Some(name) = symbol.name() {
+                name.to_string().hash(&mut hasher);
+            }
+        }
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// Chains a hook recording the panic location and backtrace to the current panic hook.
+fn install_hook() {
+    static HOOK: Once = Once::new();
+    HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let location = info
+                .location()
+                .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+            let hash = backtrace_hash(&Backtrace::new());
+            LAST_PANIC.with(|last| *last.borrow_mut() = Some((location, hash)));
+            previous(info);
+        }));
+    });
+}
+
+/// Runs `f`, returning a report of the panic if it panicked.
+pub fn catch_panic<T>(f: impl FnOnce() -> T) -> Result<T, PanicReport> {
+    install_hook();
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        let message = match payload.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => match payload.downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => String::from("unknown panic payload"),
+            },
+        };
+        let (location, backtrace_hash) = LAST_PANIC
+            .with(|last| last.borrow_mut().take())
+            .unwrap_or_else(|| (None, String::from("unknown")));
+
+        PanicReport {
+            message,
+            location,
+            backtrace_hash,
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn report_panic() {
+        let report = catch_panic(|| panic!("index {} is corrupted", "movies")).unwrap_err();
+        assert_eq!(report.message, "index movies is corrupted");
+        assert!(report.location.unwrap().contains("panic_report.rs"));
+        assert_ne!(report.backtrace_hash, "unknown");
+
+        assert_eq!(catch_panic(|| 42).unwrap(), 42);
+    }
+}
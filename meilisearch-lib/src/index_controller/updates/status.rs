@@ -1,3 +1,4 @@
+use std::fmt;
 use std::{error::Error, fmt::Display};
 
 use chrono::{DateTime, Utc};
@@ -5,6 +6,7 @@ use chrono::{DateTime, Utc};
 use meilisearch_error::{Code, ErrorCode};
 use milli::update::{DocumentAdditionResult, IndexDocumentsMethod};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use crate::{
     index::{Settings, Unchecked},
@@ -33,12 +35,56 @@ pub enum UpdateMeta {
     Settings(Settings<Unchecked>),
 }
 
+/// The request an update was enqueued by, for the update to be traced from the service that
+/// sent it to the end of its processing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceContext {
+    /// The W3C `traceparent` header of the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traceparent: Option<String>,
+    /// The `X-Request-Id` header of the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl fmt::Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "traceparent: {}, request id: {}",
+            self.traceparent.as_deref().unwrap_or("-"),
+            self.request_id.as_deref().unwrap_or("-")
+        )
+    }
+}
+
+/// What the client attaches to the update it enqueues.
+#[derive(Debug, Clone, Default)]
+pub struct UpdateContext {
+    pub metadata: Option<Value>,
+    pub trace: Option<TraceContext>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Enqueued {
     pub update_id: u64,
     pub meta: Update,
     pub enqueued_at: DateTime<Utc>,
+    /// The JSON object attached to the update by the client that enqueued it, such as the id of
+    /// the job that sent it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<TraceContext>,
+    /// The id of the failed update this update is a retry of.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_of: Option<u64>,
+    /// The uid of the update among the tasks of all the indexes, the updates enqueued before the
+    /// tasks were introduced having none until they are indexed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_uid: Option<u64>,
 }
 
 impl Enqueued {
@@ -47,6 +93,10 @@ impl Enqueued {
             enqueued_at: Utc::now(),
             meta,
             update_id,
+            metadata: None,
+            trace: None,
+            retry_of: None,
+            task_uid: None,
         }
     }
 
@@ -64,6 +114,15 @@ impl Enqueued {
         }
     }
 
+    pub fn cancel(self, canceled_by: u64) -> Canceled {
+        Canceled {
+            from: self,
+            started_processing_at: None,
+            canceled_at: Utc::now(),
+            canceled_by,
+        }
+    }
+
     pub fn meta(&self) -> &Update {
         &self.meta
     }
@@ -71,6 +130,17 @@ impl Enqueued {
     pub fn id(&self) -> u64 {
         self.update_id
     }
+
+    pub fn metadata(&self) -> Option<&Value> {
+        self.metadata.as_ref()
+    }
+
+    pub fn context(&self) -> UpdateContext {
+        UpdateContext {
+            metadata: self.metadata.clone(),
+            trace: self.trace.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -78,6 +148,9 @@ impl Enqueued {
 pub struct Processed {
     pub success: UpdateResult,
     pub processed_at: DateTime<Utc>,
+    /// The problems noticed while processing the update, that didn't make it fail.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
     #[serde(flatten)]
     pub from: Processing,
 }
@@ -109,11 +182,20 @@ impl Processing {
         self.from.meta()
     }
 
-    pub fn process(self, success: UpdateResult) -> Processed {
+    pub fn process(self, success: UpdateResult, warnings: Vec<String>) -> Processed {
         Processed {
             success,
             from: self,
             processed_at: Utc::now(),
+            warnings,
+        }
+    }
+
+    /// Cancels the update, stopped at a checkpoint of its processing before it was committed.
+    pub fn cancel(self, canceled_by: u64) -> Canceled {
+        Canceled {
+            started_processing_at: Some(self.started_processing_at),
+            ..self.from.cancel(canceled_by)
         }
     }
 
@@ -147,6 +229,29 @@ impl Aborted {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Canceled {
+    #[serde(flatten)]
+    pub from: Enqueued,
+    /// When the update was canceled while it was processing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_processing_at: Option<DateTime<Utc>>,
+    pub canceled_at: DateTime<Utc>,
+    /// The id of the cancellation that canceled this update.
+    pub canceled_by: u64,
+}
+
+impl Canceled {
+    pub fn id(&self) -> u64 {
+        self.from.id()
+    }
+
+    pub fn meta(&self) -> &Update {
+        self.from.meta()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Failed {
@@ -181,6 +286,17 @@ impl Failed {
     }
 }
 
+/// The statuses of the updates, without their content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StatusKind {
+    Processing,
+    Enqueued,
+    Processed,
+    Aborted,
+    Canceled,
+    Failed,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "status", rename_all = "camelCase")]
 pub enum UpdateStatus {
@@ -188,6 +304,7 @@ pub enum UpdateStatus {
     Enqueued(Enqueued),
     Processed(Processed),
     Aborted(Aborted),
+    Canceled(Canceled),
     Failed(Failed),
 }
 
@@ -198,6 +315,7 @@ impl UpdateStatus {
             UpdateStatus::Enqueued(u) => u.id(),
             UpdateStatus::Processed(u) => u.id(),
             UpdateStatus::Aborted(u) => u.id(),
+            UpdateStatus::Canceled(u) => u.id(),
             UpdateStatus::Failed(u) => u.id(),
         }
     }
@@ -208,10 +326,93 @@ impl UpdateStatus {
             UpdateStatus::Enqueued(u) => u.meta(),
             UpdateStatus::Processed(u) => u.meta(),
             UpdateStatus::Aborted(u) => u.meta(),
+            UpdateStatus::Canceled(u) => u.meta(),
             UpdateStatus::Failed(u) => u.meta(),
         }
     }
 
+    /// The update as it was enqueued.
+    pub fn enqueued(&self) -> &Enqueued {
+        match self {
+            UpdateStatus::Processing(u) => &u.from,
+            UpdateStatus::Enqueued(u) => u,
+            UpdateStatus::Processed(u) => &u.from.from,
+            UpdateStatus::Aborted(u) => &u.from,
+            UpdateStatus::Canceled(u) => &u.from,
+            UpdateStatus::Failed(u) => &u.from.from,
+        }
+    }
+
+    pub fn enqueued_mut(&mut self) -> &mut Enqueued {
+        match self {
+            UpdateStatus::Processing(u) => &mut u.from,
+            UpdateStatus::Enqueued(u) => u,
+            UpdateStatus::Processed(u) => &mut u.from.from,
+            UpdateStatus::Aborted(u) => &mut u.from,
+            UpdateStatus::Canceled(u) => &mut u.from,
+            UpdateStatus::Failed(u) => &mut u.from.from,
+        }
+    }
+
+    pub fn task_uid(&self) -> Option<u64> {
+        self.enqueued().task_uid
+    }
+
+    pub fn enqueued_at(&self) -> DateTime<Utc> {
+        self.enqueued().enqueued_at
+    }
+
+    /// The metadata the update was enqueued with.
+    pub fn metadata(&self) -> Option<&Value> {
+        match self {
+            UpdateStatus::Processing(u) => u.from.metadata(),
+            UpdateStatus::Enqueued(u) => u.metadata(),
+            UpdateStatus::Processed(u) => u.from.from.metadata(),
+            UpdateStatus::Aborted(u) => u.from.metadata(),
+            UpdateStatus::Canceled(u) => u.from.metadata(),
+            UpdateStatus::Failed(u) => u.from.from.metadata(),
+        }
+    }
+
+    /// The request the update was enqueued by, if it was traced.
+    pub fn trace(&self) -> Option<&TraceContext> {
+        self.enqueued().trace.as_ref()
+    }
+
+    /// The id of the update this update is a retry of.
+    pub fn retry_of(&self) -> Option<u64> {
+        match self {
+            UpdateStatus::Processing(u) => u.from.retry_of,
+            UpdateStatus::Enqueued(u) => u.retry_of,
+            UpdateStatus::Processed(u) => u.from.from.retry_of,
+            UpdateStatus::Aborted(u) => u.from.retry_of,
+            UpdateStatus::Canceled(u) => u.from.retry_of,
+            UpdateStatus::Failed(u) => u.from.from.retry_of,
+        }
+    }
+
+    pub fn kind(&self) -> StatusKind {
+        match self {
+            UpdateStatus::Processing(_) => StatusKind::Processing,
+            UpdateStatus::Enqueued(_) => StatusKind::Enqueued,
+            UpdateStatus::Processed(_) => StatusKind::Processed,
+            UpdateStatus::Aborted(_) => StatusKind::Aborted,
+            UpdateStatus::Canceled(_) => StatusKind::Canceled,
+            UpdateStatus::Failed(_) => StatusKind::Failed,
+        }
+    }
+
+    /// When the update was processed, failed, aborted or canceled, `None` until then.
+    pub fn finished_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            UpdateStatus::Processing(_) | UpdateStatus::Enqueued(_) => None,
+            UpdateStatus::Processed(u) => Some(u.processed_at),
+            UpdateStatus::Aborted(u) => Some(u.aborted_at),
+            UpdateStatus::Canceled(u) => Some(u.canceled_at),
+            UpdateStatus::Failed(u) => Some(u.failed_at),
+        }
+    }
+
     pub fn processed(&self) -> Option<&Processed> {
         match self {
             UpdateStatus::Processed(p) => Some(p),
@@ -232,6 +433,12 @@ impl From<Aborted> for UpdateStatus {
     }
 }
 
+impl From<Canceled> for UpdateStatus {
+    fn from(other: Canceled) -> Self {
+        Self::Canceled(other)
+    }
+}
+
 impl From<Processed> for UpdateStatus {
     fn from(other: Processed) -> Self {
         Self::Processed(other)
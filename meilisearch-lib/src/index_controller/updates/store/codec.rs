@@ -3,6 +3,8 @@ use std::{borrow::Cow, convert::TryInto, mem::size_of};
 use heed::{BytesDecode, BytesEncode};
 use uuid::Uuid;
 
+use crate::index_controller::updates::status::StatusKind;
+
 pub struct NextIdCodec;
 
 pub enum NextIdKey {
@@ -84,3 +86,53 @@ impl<'a> BytesDecode<'a> for UpdateKeyCodec {
         Some((uuid, update_id))
     }
 }
+
+pub struct TaskStatusCodec;
+
+impl TaskStatusCodec {
+    fn status_byte(status: StatusKind) -> u8 {
+        match status {
+            StatusKind::Processing => 0,
+            StatusKind::Enqueued => 1,
+            StatusKind::Processed => 2,
+            StatusKind::Aborted => 3,
+            StatusKind::Canceled => 4,
+            StatusKind::Failed => 5,
+        }
+    }
+
+    fn byte_status(byte: u8) -> Option<StatusKind> {
+        match byte {
+            0 => Some(StatusKind::Processing),
+            1 => Some(StatusKind::Enqueued),
+            2 => Some(StatusKind::Processed),
+            3 => Some(StatusKind::Aborted),
+            4 => Some(StatusKind::Canceled),
+            5 => Some(StatusKind::Failed),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> BytesEncode<'a> for TaskStatusCodec {
+    type EItem = (StatusKind, u64);
+
+    fn bytes_encode((status, task_uid): &'a Self::EItem) -> Option<Cow<'a, [u8]>> {
+        let mut bytes = Vec::with_capacity(1 + size_of::<u64>());
+        bytes.push(Self::status_byte(*status));
+        bytes.extend_from_slice(&task_uid.to_be_bytes());
+        Some(Cow::Owned(bytes))
+    }
+}
+
+impl<'a> BytesDecode<'a> for TaskStatusCodec {
+    type DItem = (StatusKind, u64);
+
+    fn bytes_decode(bytes: &'a [u8]) -> Option<Self::DItem> {
+        let status = Self::byte_status(*bytes.first()?)?;
+        let task_uid_bytes = bytes.get(1..)?.try_into().ok()?;
+        let task_uid = u64::from_be_bytes(task_uid_bytes);
+
+        Some((status, task_uid))
+    }
+}
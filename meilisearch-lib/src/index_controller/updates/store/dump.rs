@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs::{create_dir_all, File};
 use std::io::{BufReader, Write};
 use std::path::{Path, PathBuf};
@@ -17,6 +17,7 @@ use crate::{
         update_file_store::UpdateFileStore,
         updates::status::{Enqueued, UpdateStatus},
     },
+    io_throttle::{IoThrottle, Throttled},
     Update,
 };
 
@@ -27,7 +28,12 @@ pub struct UpdateEntry {
 }
 
 impl UpdateStore {
-    pub fn dump(&self, indexes: &[Index], path: PathBuf) -> Result<()> {
+    pub fn dump(
+        &self,
+        indexes: &[Index],
+        path: PathBuf,
+        throttle: Option<&IoThrottle>,
+    ) -> Result<()> {
         let state_lock = self.state.write();
         state_lock.swap(State::Dumping);
 
@@ -36,11 +42,11 @@ impl UpdateStore {
 
         let uuids = indexes.iter().map(|i| i.uuid).collect();
 
-        self.dump_updates(&txn, &uuids, &path)?;
+        self.dump_updates(&txn, &uuids, &path, throttle)?;
 
         indexes
             .par_iter()
-            .try_for_each(|index| index.dump(&path))
+            .try_for_each(|index| index.dump(&path, throttle))
             .unwrap();
 
         Ok(())
@@ -51,11 +57,14 @@ impl UpdateStore {
         txn: &RoTxn,
         uuids: &HashSet<Uuid>,
         path: impl AsRef<Path>,
+        throttle: Option<&IoThrottle>,
     ) -> Result<()> {
         let mut dump_data_file = NamedTempFile::new()?;
 
-        self.dump_pending(txn, uuids, &mut dump_data_file, &path)?;
-        self.dump_completed(txn, uuids, &mut dump_data_file)?;
+        let mut writer = Throttled::new(&mut dump_data_file, throttle);
+        self.dump_pending(txn, uuids, &mut writer, &path, throttle)?;
+        self.dump_completed(txn, uuids, &mut writer)?;
+        drop(writer);
 
         let mut dst_path = path.as_ref().join("updates");
         create_dir_all(&dst_path)?;
@@ -71,6 +80,7 @@ impl UpdateStore {
         uuids: &HashSet<Uuid>,
         mut file: impl Write,
         dst_path: impl AsRef<Path>,
+        throttle: Option<&IoThrottle>,
     ) -> Result<()> {
         let pendings = self.pending_queue.iter(txn)?.lazily_decode_data();
 
@@ -85,7 +95,7 @@ impl UpdateStore {
                 } = update
                 {
                     self.update_file_store
-                        .dump(content_uuid, &dst_path)
+                        .dump(content_uuid, &dst_path, throttle)
                         .unwrap();
                 }
 
@@ -125,10 +135,17 @@ impl UpdateStore {
         Ok(())
     }
 
+    /// Loads the dumped updates, except the ones of the `skipped` indexes.
+    /// Loads the updates of the dump at `src` of the loaded indexes, under the uuid `uuids` maps
+    /// their dumped uuid to. With `fresh_task_uids`, the updates are loaded next to the tasks of
+    /// an existing database and take the next task uids, in the order of their task uids in the
+    /// dump.
     pub fn load_dump(
         src: impl AsRef<Path>,
         dst: impl AsRef<Path>,
         db_size: usize,
+        uuids: &HashMap<Uuid, Uuid>,
+        fresh_task_uids: bool,
     ) -> anyhow::Result<()> {
         let mut options = EnvOpenOptions::new();
         options.map_size(db_size as usize);
@@ -145,13 +162,50 @@ impl UpdateStore {
         let stream = Deserializer::from_reader(update_data).into_iter::<UpdateEntry>();
         let mut wtxn = store.env.write_txn()?;
 
-        for entry in stream {
-            let UpdateEntry { uuid, update } = entry?;
-            store.register_raw_updates(&mut wtxn, &update, uuid)?;
+        if fresh_task_uids {
+            let mut entries = Vec::new();
+            for entry in stream {
+                let UpdateEntry { uuid, update } = entry?;
+                if let Some(db_uuid) = uuids.get(&uuid) {
+                    entries.push((*db_uuid, update));
+                }
+            }
+            entries.sort_by_key(|(_, update)| (update.task_uid(), update.enqueued_at()));
+            for (uuid, mut update) in entries {
+                update.enqueued_mut().task_uid = None;
+                store.register_raw_updates(&mut wtxn, update, uuid)?;
+            }
+        } else {
+            for entry in stream {
+                let UpdateEntry { uuid, update } = entry?;
+                if let Some(db_uuid) = uuids.get(&uuid) {
+                    store.register_raw_updates(&mut wtxn, update, *db_uuid)?;
+                }
+            }
         }
 
         wtxn.commit()?;
 
         Ok(())
     }
+
+    /// Removes the updates of the indexes with these uuids, loaded from a dump.
+    pub fn unload_dump<'a>(
+        dst: impl AsRef<Path>,
+        db_size: usize,
+        uuids: impl IntoIterator<Item = &'a Uuid>,
+    ) -> anyhow::Result<()> {
+        let mut options = EnvOpenOptions::new();
+        options.map_size(db_size as usize);
+
+        // The update files are removed with the ones of the dump.
+        let tmp = TempDir::new()?;
+        let update_file_store = UpdateFileStore::new(tmp.path())?;
+        let (store, _) = UpdateStore::new(options, &dst, update_file_store)?;
+        for uuid in uuids {
+            store.delete_all(*uuid)?;
+        }
+
+        Ok(())
+    }
 }
@@ -2,36 +2,46 @@ mod codec;
 pub mod dump;
 
 use std::fs::create_dir_all;
+use std::iter::Peekable;
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     path::PathBuf,
     time::Duration,
 };
 
 use arc_swap::ArcSwap;
-use heed::types::{ByteSlice, OwnedType, SerdeJson};
+use chrono::Utc;
+use heed::types::{ByteSlice, OwnedType, SerdeJson, Unit};
 use heed::zerocopy::U64;
-use heed::{CompactionOption, Database, Env, EnvOpenOptions};
-use log::error;
+use heed::{Database, Env, EnvOpenOptions};
+use log::{error, info};
+use meilisearch_error::ErrorCode;
 use parking_lot::{Mutex, MutexGuard};
 use rayon::prelude::*;
+use serde_json::Value;
 use tokio::runtime::Handle;
-use tokio::sync::mpsc;
 use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{broadcast, mpsc};
 use tokio::time::timeout;
 use uuid::Uuid;
 
 use codec::*;
 
 use super::error::Result;
-use super::status::{Enqueued, Processing};
+use super::panic_report::catch_panic;
+use super::status::{Enqueued, Failed, Processed, Processing, StatusKind, UpdateContext};
+use crate::file_clone::compacted_copy;
 use crate::index::Index;
 use crate::index_controller::updates::*;
+use crate::io_throttle::IoThrottle;
 use crate::EnvSizer;
 
+/// The number of completions buffered for a waiter before it misses some.
+const COMPLETION_CHANNEL_CAPACITY: usize = 100;
+
 #[allow(clippy::upper_case_acronyms)]
 type BEU64 = U64<heed::byteorder::BE>;
 
@@ -39,6 +49,10 @@ type BEU64 = U64<heed::byteorder::BE>;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Update {
     DeleteDocuments(Vec<String>),
+    /// Deletes the documents matching the filter.
+    DeleteByFilter {
+        filter: Value,
+    },
     DocumentAddition {
         primary_key: Option<String>,
         method: IndexDocumentsMethod,
@@ -48,6 +62,181 @@ pub enum Update {
     ClearDocuments,
 }
 
+/// The updates a cancellation applies to, the filters left to `None` matching all of them.
+#[derive(Debug, Clone, Default)]
+pub struct CancelFilter {
+    pub index_uuids: Option<HashSet<Uuid>>,
+    pub update_ids: Option<HashSet<u64>>,
+    pub enqueued: bool,
+    pub processing: bool,
+}
+
+impl CancelFilter {
+    fn matches(&self, index_uuid: Uuid, update_id: u64) -> bool {
+        self.index_uuids
+            .as_ref()
+            .map_or(true, |uuids| uuids.contains(&index_uuid))
+            && self
+                .update_ids
+                .as_ref()
+                .map_or(true, |ids| ids.contains(&update_id))
+    }
+}
+
+#[derive(Debug)]
+pub struct Cancellation {
+    /// The id of the cancellation, taken from the global update ids.
+    pub canceled_by: u64,
+    /// The index and id of the canceled updates. The processing one is only canceled at the next
+    /// checkpoint of its processing, it is processed if it doesn't reach one.
+    pub canceled: Vec<(Uuid, u64)>,
+}
+
+/// The types of the updates, without their content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UpdateKind {
+    ClearAll,
+    DocumentsAddition,
+    DocumentsPartial,
+    DocumentsDeletion,
+    Settings,
+}
+
+impl Update {
+    pub fn kind(&self) -> UpdateKind {
+        match self {
+            Update::DeleteDocuments(_) | Update::DeleteByFilter { .. } => {
+                UpdateKind::DocumentsDeletion
+            }
+            Update::DocumentAddition {
+                method: IndexDocumentsMethod::ReplaceDocuments,
+                ..
+            } => UpdateKind::DocumentsAddition,
+            Update::DocumentAddition { .. } => UpdateKind::DocumentsPartial,
+            Update::Settings(_) => UpdateKind::Settings,
+            Update::ClearDocuments => UpdateKind::ClearAll,
+        }
+    }
+}
+
+/// The finished updates a deletion applies to, the filters left to `None` matching all of them.
+#[derive(Debug, Clone, Default)]
+pub struct DeleteFilter {
+    pub index_uuids: Option<HashSet<Uuid>>,
+    pub statuses: Option<HashSet<StatusKind>>,
+    pub kinds: Option<HashSet<UpdateKind>>,
+    /// Only the updates finished before this date.
+    pub finished_before: Option<DateTime<Utc>>,
+}
+
+impl DeleteFilter {
+    fn matches(&self, index_uuid: Uuid, update: &UpdateStatus) -> bool {
+        let finished_before = match (self.finished_before, update.finished_at()) {
+            (Some(date), Some(finished_at)) => finished_at < date,
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        finished_before
+            && self
+                .index_uuids
+                .as_ref()
+                .map_or(true, |uuids| uuids.contains(&index_uuid))
+            && self
+                .statuses
+                .as_ref()
+                .map_or(true, |statuses| statuses.contains(&update.kind()))
+            && self
+                .kinds
+                .as_ref()
+                .map_or(true, |kinds| kinds.contains(&update.meta().kind()))
+    }
+}
+
+/// The tasks a listing returns, the filters left to `None` matching all of them.
+#[derive(Debug, Clone, Default)]
+pub struct TaskFilter {
+    pub uids: Option<HashSet<u64>>,
+    pub index_uuids: Option<HashSet<Uuid>>,
+    pub statuses: Option<HashSet<StatusKind>>,
+    pub kinds: Option<HashSet<UpdateKind>>,
+    pub enqueued_after: Option<DateTime<Utc>>,
+    pub enqueued_before: Option<DateTime<Utc>>,
+    pub finished_after: Option<DateTime<Utc>>,
+    pub finished_before: Option<DateTime<Utc>>,
+}
+
+impl TaskFilter {
+    fn matches(&self, index_uuid: Uuid, update: &UpdateStatus) -> bool {
+        let enqueued_at = update.enqueued_at();
+        let finished_at = update.finished_at();
+        self.uids.as_ref().map_or(true, |uids| {
+            update.task_uid().map_or(false, |uid| uids.contains(&uid))
+        }) && self.enqueued_after.map_or(true, |date| enqueued_at > date)
+            && self.enqueued_before.map_or(true, |date| enqueued_at < date)
+            && self
+                .finished_after
+                .map_or(true, |date| finished_at.map_or(false, |at| at > date))
+            && self
+                .finished_before
+                .map_or(true, |date| finished_at.map_or(false, |at| at < date))
+            && self
+                .index_uuids
+                .as_ref()
+                .map_or(true, |uuids| uuids.contains(&index_uuid))
+            && self
+                .statuses
+                .as_ref()
+                .map_or(true, |statuses| statuses.contains(&update.kind()))
+            && self
+                .kinds
+                .as_ref()
+                .map_or(true, |kinds| kinds.contains(&update.meta().kind()))
+    }
+
+    /// Whether the filter can match an update with the status `status`.
+    fn accepts(&self, status: StatusKind) -> bool {
+        self.statuses
+            .as_ref()
+            .map_or(true, |statuses| statuses.contains(&status))
+    }
+}
+
+type TaskUids<'a> = Box<dyn Iterator<Item = heed::Result<u64>> + 'a>;
+
+/// The union of lists of task uids sorted by decreasing uid, in the same order. The lists must be
+/// disjoint, as the uids of the different statuses or indexes are.
+struct DescendingUnion<'a> {
+    lists: Vec<Peekable<TaskUids<'a>>>,
+}
+
+impl<'a> DescendingUnion<'a> {
+    fn new(lists: Vec<TaskUids<'a>>) -> Self {
+        Self {
+            lists: lists.into_iter().map(Iterator::peekable).collect(),
+        }
+    }
+}
+
+impl Iterator for DescendingUnion<'_> {
+    type Item = heed::Result<u64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The errors come first, for the listing to stop on them.
+        let mut best: Option<(usize, u64)> = None;
+        for (i, list) in self.lists.iter_mut().enumerate() {
+            match list.peek() {
+                Some(Err(_)) => return list.next(),
+                Some(Ok(uid)) if best.map_or(true, |(_, best)| *uid > best) => {
+                    best = Some((i, *uid))
+                }
+                _ => (),
+            }
+        }
+        let (i, _) = best?;
+        self.lists[i].next()
+    }
+}
+
 #[derive(Debug)]
 pub struct UpdateStoreInfo {
     /// Size of the update store in bytes.
@@ -91,10 +280,30 @@ impl StateLock {
     }
 }
 
+/// Fails all the updates of a batch with the same `error`.
+fn fail_batch(
+    updates: Vec<Processing>,
+    error: impl ErrorCode,
+) -> Vec<std::result::Result<Processed, Failed>> {
+    let (msg, code) = (error.to_string(), error.error_code());
+    updates
+        .into_iter()
+        .map(|from| {
+            Err(Failed {
+                from,
+                msg: msg.clone(),
+                code,
+                failed_at: Utc::now(),
+            })
+        })
+        .collect()
+}
+
 #[allow(clippy::large_enum_variant)]
 pub enum State {
     Idle,
-    Processing(Uuid, Processing),
+    /// The updates of an index processed together, in the order of their ids.
+    Processing(Uuid, Vec<Processing>),
     Snapshoting,
     Dumping,
 }
@@ -115,10 +324,26 @@ pub struct UpdateStore {
     /// |    Uuid  |   id    |
     /// | 16-bytes | 8-bytes |
     updates: Database<UpdateKeyCodec, SerdeJson<UpdateStatus>>,
+    /// Map the uid of each finished update to its index and id. The tasks still in the pending
+    /// queue are found there, its keys starting with their uid.
+    task_ids: Database<OwnedType<BEU64>, UpdateKeyCodec>,
+    /// The uids of the finished updates by status, to list them without going through the
+    /// updates of the other statuses.
+    /// |  status |  task_uid |
+    /// | 1-byte  |  8-bytes  |
+    tasks_by_status: Database<TaskStatusCodec, Unit>,
+    /// The uids of the finished updates by index, in the order of the uids.
+    /// |   Uuid   | task_uid |
+    /// | 16-bytes | 8-bytes  |
+    tasks_by_index: Database<UpdateKeyCodec, Unit>,
     /// Indicates the current state of the update store,
     state: Arc<StateLock>,
     /// Wake up the loop when a new event occurs.
     notification_sender: mpsc::Sender<()>,
+    /// Notified of the index and id of each update once it is processed or failed.
+    completion_sender: broadcast::Sender<(Uuid, u64)>,
+    /// The id of the cancellation of each processing update that was canceled.
+    processing_canceled_by: Arc<Mutex<HashMap<u64, u64>>>,
     update_file_store: UpdateFileStore,
     path: PathBuf,
 }
@@ -129,7 +354,7 @@ impl UpdateStore {
         path: impl AsRef<Path>,
         update_file_store: UpdateFileStore,
     ) -> anyhow::Result<(Self, mpsc::Receiver<()>)> {
-        options.max_dbs(5);
+        options.max_dbs(8);
 
         let update_path = path.as_ref().join("updates");
         std::fs::create_dir_all(&update_path)?;
@@ -137,10 +362,14 @@ impl UpdateStore {
         let pending_queue = env.create_database(Some("pending-queue"))?;
         let next_update_id = env.create_database(Some("next-update-id"))?;
         let updates = env.create_database(Some("updates"))?;
+        let task_ids = env.create_database(Some("task-ids"))?;
+        let tasks_by_status = env.create_database(Some("tasks-by-status"))?;
+        let tasks_by_index = env.create_database(Some("tasks-by-index"))?;
 
         let state = Arc::new(StateLock::from_state(State::Idle));
 
         let (notification_sender, notification_receiver) = mpsc::channel(1);
+        let (completion_sender, _) = broadcast::channel(COMPLETION_CHANNEL_CAPACITY);
 
         Ok((
             Self {
@@ -148,8 +377,13 @@ impl UpdateStore {
                 pending_queue,
                 next_update_id,
                 updates,
+                task_ids,
+                tasks_by_status,
+                tasks_by_index,
                 state,
                 notification_sender,
+                completion_sender,
+                processing_canceled_by: Arc::new(Mutex::new(HashMap::new())),
                 path: path.as_ref().to_owned(),
                 update_file_store,
             },
@@ -166,6 +400,7 @@ impl UpdateStore {
     ) -> anyhow::Result<Arc<Self>> {
         let (update_store, mut notification_receiver) =
             Self::new(options, path, update_file_store)?;
+        update_store.index_legacy_tasks()?;
         let update_store = Arc::new(update_store);
 
         // Send a first notification to trigger the process.
@@ -215,6 +450,86 @@ impl UpdateStore {
         Ok(update_store)
     }
 
+    /// Gives a task uid to the finished updates made before the tasks were introduced, in the
+    /// order they were enqueued. The pending ones take the global id they are queued with.
+    fn index_legacy_tasks(&self) -> heed::Result<()> {
+        let mut txn = self.env.write_txn()?;
+        if !self.task_ids.is_empty(&txn)? || self.updates.is_empty(&txn)? {
+            return Ok(());
+        }
+
+        let mut legacy = Vec::new();
+        for entry in self.updates.iter(&txn)? {
+            let ((uuid, _), update) = entry?;
+            if update.task_uid().is_none() {
+                legacy.push((uuid, update));
+            }
+        }
+        legacy.sort_by_key(|(_, update)| update.enqueued_at());
+
+        for (uuid, mut update) in legacy {
+            let task_uid = self.reserve_task_uid(&mut txn, None)?;
+            update.enqueued_mut().task_uid = Some(task_uid);
+            self.put_finished(&mut txn, uuid, &update)?;
+        }
+
+        txn.commit()
+    }
+
+    /// Returns `task_uid`, making sure the next global update ids come after it, or the next
+    /// global update id when `None`.
+    fn reserve_task_uid(&self, txn: &mut heed::RwTxn, task_uid: Option<u64>) -> heed::Result<u64> {
+        let next_id = self
+            .next_update_id
+            .get(txn, &NextIdKey::Global)?
+            .map(U64::get)
+            .unwrap_or_default();
+        let task_uid = task_uid.unwrap_or(next_id);
+
+        self.next_update_id.put(
+            txn,
+            &NextIdKey::Global,
+            &BEU64::new(next_id.max(task_uid + 1)),
+        )?;
+
+        Ok(task_uid)
+    }
+
+    /// Writes the finished `update` of `index_uuid` and the lookups of its task uid.
+    fn put_finished(
+        &self,
+        txn: &mut heed::RwTxn,
+        index_uuid: Uuid,
+        update: &UpdateStatus,
+    ) -> heed::Result<()> {
+        self.updates.put(txn, &(index_uuid, update.id()), update)?;
+        if let Some(task_uid) = update.task_uid() {
+            self.task_ids
+                .put(txn, &BEU64::new(task_uid), &(index_uuid, update.id()))?;
+            self.tasks_by_status
+                .put(txn, &(update.kind(), task_uid), &())?;
+            self.tasks_by_index.put(txn, &(index_uuid, task_uid), &())?;
+        }
+        Ok(())
+    }
+
+    /// Removes the lookups of the task uids of the deleted finished updates.
+    fn delete_task_lookups(
+        &self,
+        txn: &mut heed::RwTxn,
+        deleted: &[(Uuid, UpdateStatus)],
+    ) -> heed::Result<()> {
+        for (index_uuid, update) in deleted {
+            if let Some(task_uid) = update.task_uid() {
+                self.task_ids.delete(txn, &BEU64::new(task_uid))?;
+                self.tasks_by_status
+                    .delete(txn, &(update.kind(), task_uid))?;
+                self.tasks_by_index.delete(txn, &(*index_uuid, task_uid))?;
+            }
+        }
+        Ok(())
+    }
+
     /// Returns the next global update id and the next update id for a given `index_uuid`.
     fn next_update_id(&self, txn: &mut heed::RwTxn, index_uuid: Uuid) -> heed::Result<(u64, u64)> {
         let global_id = self
@@ -251,10 +566,20 @@ impl UpdateStore {
 
     /// Registers the update content in the pending store and the meta
     /// into the pending-meta store. Returns the new unique update id.
-    pub fn register_update(&self, index_uuid: Uuid, update: Update) -> heed::Result<Enqueued> {
+    pub fn register_update(
+        &self,
+        index_uuid: Uuid,
+        update: Update,
+        context: UpdateContext,
+        retry_of: Option<u64>,
+    ) -> heed::Result<Enqueued> {
         let mut txn = self.env.write_txn()?;
         let (global_id, update_id) = self.next_update_id(&mut txn, index_uuid)?;
-        let meta = Enqueued::new(update, update_id);
+        let mut meta = Enqueued::new(update, update_id);
+        meta.metadata = context.metadata;
+        meta.trace = context.trace;
+        meta.retry_of = retry_of;
+        meta.task_uid = Some(global_id);
 
         self.pending_queue
             .put(&mut txn, &(global_id, index_uuid, update_id), &meta)?;
@@ -265,30 +590,37 @@ impl UpdateStore {
             panic!("Update store loop exited");
         }
 
+        if let Some(ref trace) = meta.trace {
+            info!(
+                "Update {} of index {} is enqueued ({}).",
+                update_id, index_uuid, trace
+            );
+        }
+
         Ok(meta)
     }
 
     /// Push already processed update in the UpdateStore without triggering the notification
-    /// process. This is useful for the dumps.
+    /// process. This is useful for the dumps. The task uid of the update is kept, the updates
+    /// dumped before the tasks were introduced taking the next one.
     pub fn register_raw_updates(
         &self,
         wtxn: &mut heed::RwTxn,
-        update: &UpdateStatus,
+        mut update: UpdateStatus,
         index_uuid: Uuid,
     ) -> heed::Result<()> {
+        let _update_id = self.next_update_id_raw(wtxn, index_uuid)?;
+        let task_uid = self.reserve_task_uid(wtxn, update.task_uid())?;
+        update.enqueued_mut().task_uid = Some(task_uid);
         match update {
             UpdateStatus::Enqueued(enqueued) => {
-                let (global_id, _update_id) = self.next_update_id(wtxn, index_uuid)?;
                 self.pending_queue.remap_key_type::<PendingKeyCodec>().put(
                     wtxn,
-                    &(global_id, index_uuid, enqueued.id()),
-                    enqueued,
+                    &(task_uid, index_uuid, enqueued.id()),
+                    &enqueued,
                 )?;
             }
-            _ => {
-                let _update_id = self.next_update_id_raw(wtxn, index_uuid)?;
-                self.updates.put(wtxn, &(index_uuid, update.id()), update)?;
-            }
+            update => self.put_finished(wtxn, index_uuid, &update)?,
         }
         Ok(())
     }
@@ -296,6 +628,9 @@ impl UpdateStore {
     /// Executes the user provided function on the next pending update (the one with the lowest id).
     /// This is asynchronous as it let the user process the update with a read-only txn and
     /// only writing the result meta to the processed-meta store *after* it has been processed.
+    ///
+    /// The document additions of an index following it are processed along with it, see
+    /// [`UpdateStore::next_batch`].
     fn process_pending_update(
         &self,
         index_resolver: Arc<HardStateIndexResolver>,
@@ -308,14 +643,40 @@ impl UpdateStore {
         // If there is a pending update we process and only keep
         // a reader while processing it, not a writer.
         match first_meta {
-            Some(((global_id, index_uuid, _), pending)) => {
-                let processing = pending.processing();
+            Some(((global_id, index_uuid, _), mut pending)) => {
+                pending.task_uid.get_or_insert(global_id);
+                let max_batch_size = match Handle::current()
+                    .block_on(index_resolver.get_index_by_uuid(index_uuid))
+                {
+                    Ok(index) => index.max_batch_size(),
+                    Err(_) => 1,
+                };
+                let batch = self.next_batch(global_id, index_uuid, pending, max_batch_size)?;
                 // Acquire the state lock and set the current state to processing.
                 // txn must *always* be acquired after state lock, or it will dead lock.
                 let state = self.state.write();
-                state.swap(State::Processing(index_uuid, processing.clone()));
+                let batch = {
+                    // A cancellation either removes the update from the queue before it starts,
+                    // or sees it processing.
+                    let mut canceled_by = self.processing_canceled_by.lock();
+                    let rtxn = self.env.read_txn()?;
+                    let mut kept = Vec::with_capacity(batch.len());
+                    for (global_id, processing) in batch {
+                        let key = (global_id, index_uuid, processing.id());
+                        if self.pending_queue.get(&rtxn, &key)?.is_some() {
+                            kept.push((global_id, processing));
+                        }
+                    }
+                    if kept.is_empty() {
+                        return Ok(Some(()));
+                    }
+                    canceled_by.clear();
+                    let processing = kept.iter().map(|(_, p)| p.clone()).collect();
+                    state.swap(State::Processing(index_uuid, processing));
+                    kept
+                };
 
-                let result = self.perform_update(processing, index_resolver, index_uuid, global_id);
+                let result = self.perform_updates(batch, index_resolver, index_uuid);
 
                 state.swap(State::Idle);
 
@@ -325,42 +686,288 @@ impl UpdateStore {
         }
     }
 
-    fn perform_update(
+    /// Returns the pending updates of the index `index_uuid` processed together, with their global
+    /// id, starting with `first`, its oldest one: the document additions following it with the
+    /// same method and primary key, up to `max_batch_size` of them. The updates of the other
+    /// indexes are left in the queue, any other update of the index ends the batch.
+    fn next_batch(
+        &self,
+        first_global_id: u64,
+        index_uuid: Uuid,
+        first: Enqueued,
+        max_batch_size: usize,
+    ) -> heed::Result<Vec<(u64, Processing)>> {
+        let (method, primary_key) = match first.meta() {
+            Update::DocumentAddition {
+                method,
+                primary_key,
+                ..
+            } if max_batch_size > 1 => (*method, primary_key.clone()),
+            _ => return Ok(vec![(first_global_id, first.processing())]),
+        };
+
+        // The additions of a batch are processed together, from the same time.
+        let first = first.processing();
+        let started_processing_at = first.started_processing_at;
+        let mut batch = vec![(first_global_id, first)];
+        let txn = self.env.read_txn()?;
+        for entry in self.pending_queue.iter(&txn)?.lazily_decode_data() {
+            let ((global_id, uuid, _), pending) = entry?;
+            if global_id <= first_global_id || uuid != index_uuid {
+                continue;
+            }
+            let mut pending = pending.decode()?;
+            match pending.meta() {
+                Update::DocumentAddition {
+                    method: other_method,
+                    primary_key: other_primary_key,
+                    ..
+                } if *other_method == method
+                    && (other_primary_key.is_none() || *other_primary_key == primary_key) => {}
+                _ => break,
+            }
+            pending.task_uid.get_or_insert(global_id);
+            let processing = Processing {
+                started_processing_at,
+                ..pending.processing()
+            };
+            batch.push((global_id, processing));
+            if batch.len() >= max_batch_size {
+                break;
+            }
+        }
+
+        Ok(batch)
+    }
+
+    fn perform_updates(
         &self,
-        processing: Processing,
+        batch: Vec<(u64, Processing)>,
         index_resolver: Arc<HardStateIndexResolver>,
         index_uuid: Uuid,
-        global_id: u64,
     ) -> Result<Option<()>> {
-        // Process the pending update using the provided user function.
+        // Process the pending updates using the provided user function.
         let handle = Handle::current();
-        let update_id = processing.id();
-        //IndexMsg::update(index_resolver, index_uuid, processing.clone()
-        let result = match handle.block_on(index_resolver.get_index_by_uuid(index_uuid)) {
-            Ok(index) => index.handle_update(processing),
-            Err(e) => Err(processing.fail(e)),
+        let processings: Vec<_> = batch.iter().map(|(_, p)| p.clone()).collect();
+        let results: Vec<std::result::Result<Processed, Failed>> = match handle
+            .block_on(index_resolver.get_index_by_uuid(index_uuid))
+        {
+            Ok(index) => {
+                // The other indexes are only counted for the additions under an instance quota.
+                let other_documents = match processings[0].meta() {
+                    Update::DocumentAddition { .. }
+                        if index.document_quotas().max_documents.is_some() =>
+                    {
+                        handle.block_on(index_resolver.count_other_documents(index_uuid))
+                    }
+                    _ => Ok(0),
+                };
+                match other_documents {
+                    Ok(other_documents) => {
+                        let updates = processings.clone();
+                        let is_canceled = |id| self.processing_canceled_by.lock().contains_key(&id);
+                        match catch_panic(move || {
+                            index.handle_batch(updates, other_documents, is_canceled)
+                        }) {
+                            Ok(results) => results,
+                            Err(report) => {
+                                let ids: Vec<_> = processings.iter().map(Processing::id).collect();
+                                error!("Updates {:?} of index {}: {}", ids, index_uuid, report);
+                                fail_batch(processings, report)
+                            }
+                        }
+                    }
+                    Err(e) => fail_batch(processings, e),
+                }
+            }
+            Err(e) => fail_batch(processings, e),
         };
 
-        // Once the pending update have been successfully processed
+        // An update only fails once canceled when it stopped at a checkpoint.
+        let canceled_by = self.processing_canceled_by.lock().clone();
+
+        // Once the pending updates have been successfully processed
         // we must remove the content from the pending and processing stores and
         // write the *new* meta to the processed-meta store and commit.
         let mut wtxn = self.env.write_txn()?;
-        self.pending_queue
-            .delete(&mut wtxn, &(global_id, index_uuid, update_id))?;
-
-        let result = match result {
-            Ok(res) => res.into(),
-            Err(res) => res.into(),
-        };
+        let mut finished = Vec::with_capacity(batch.len());
+        // The updates of a batch are committed together, at the same time.
+        let finished_at = Utc::now();
+        // The results are in the order of the update ids, as the batch.
+        for ((global_id, processing), result) in batch.iter().zip(results) {
+            self.pending_queue
+                .delete(&mut wtxn, &(*global_id, index_uuid, processing.id()))?;
+
+            let result: UpdateStatus = match result {
+                Ok(mut res) => {
+                    res.processed_at = finished_at;
+                    res.into()
+                }
+                Err(mut res) => match canceled_by.get(&res.id()) {
+                    Some(canceled_by) => res.from.cancel(*canceled_by).into(),
+                    None => {
+                        res.failed_at = finished_at;
+                        res.into()
+                    }
+                },
+            };
 
-        self.updates
-            .put(&mut wtxn, &(index_uuid, update_id), &result)?;
+            self.put_finished(&mut wtxn, index_uuid, &result)?;
+            finished.push(result);
+        }
 
         wtxn.commit()?;
 
+        for result in finished {
+            // Unlike the payloads of the failed additions, the ones of the canceled additions are
+            // removed.
+            if let UpdateStatus::Canceled(canceled) = &result {
+                if let Update::DocumentAddition { content_uuid, .. } = canceled.meta() {
+                    let _ = self.update_file_store.delete(*content_uuid);
+                }
+            }
+
+            if let Some(trace) = result.trace() {
+                info!(
+                    "Update {} of index {} is {:?} ({}).",
+                    result.id(),
+                    index_uuid,
+                    result.kind(),
+                    trace
+                );
+            }
+
+            // There is no error when nobody waits for an update.
+            let _ = self.completion_sender.send((index_uuid, result.id()));
+        }
+
         Ok(Some(()))
     }
 
+    /// Cancels the enqueued and processing updates matching `filter`. The enqueued updates are
+    /// removed from the queue, the processing one stops at the next checkpoint of its processing.
+    pub fn cancel(&self, filter: &CancelFilter) -> Result<Cancellation> {
+        // Held during the whole cancellation, for no update to start processing meanwhile.
+        let mut processing_canceled_by = self.processing_canceled_by.lock();
+        let mut txn = self.env.write_txn()?;
+
+        let canceled_by = self
+            .next_update_id
+            .get(&txn, &NextIdKey::Global)?
+            .map(U64::get)
+            .unwrap_or_default();
+        self.next_update_id
+            .put(&mut txn, &NextIdKey::Global, &BEU64::new(canceled_by + 1))?;
+
+        let mut canceled = Vec::new();
+        // The processing update is still in the pending queue.
+        let processing: Vec<_> = match *self.state.read() {
+            State::Processing(uuid, ref processing) => {
+                processing.iter().map(|p| (uuid, p.id())).collect()
+            }
+            _ => Vec::new(),
+        };
+        for &(uuid, id) in &processing {
+            if filter.processing && filter.matches(uuid, id) {
+                processing_canceled_by.insert(id, canceled_by);
+                canceled.push((uuid, id));
+            }
+        }
+
+        let mut canceled_updates = Vec::new();
+        if filter.enqueued {
+            let mut pendings = self.pending_queue.iter_mut(&mut txn)?.lazily_decode_data();
+            while let Some(Ok(((global_id, uuid, id), pending))) = pendings.next() {
+                if processing.contains(&(uuid, id)) || !filter.matches(uuid, id) {
+                    continue;
+                }
+                let mut pending = pending.decode()?;
+                pending.task_uid.get_or_insert(global_id);
+
+                //Invariant check: we can only delete the current entry when we don't hold
+                //references to it anymore. This must be done after we have retrieved its content.
+                unsafe {
+                    pendings.del_current()?;
+                }
+
+                canceled_updates.push((uuid, pending.cancel(canceled_by)));
+            }
+        }
+
+        let mut uuids_to_remove = Vec::new();
+        for (uuid, update) in canceled_updates {
+            if let Update::DocumentAddition { content_uuid, .. } = update.meta() {
+                uuids_to_remove.push(*content_uuid);
+            }
+            canceled.push((uuid, update.id()));
+            self.put_finished(&mut txn, uuid, &update.into())?;
+        }
+
+        txn.commit()?;
+        drop(processing_canceled_by);
+
+        uuids_to_remove.iter().for_each(|uuid| {
+            let _ = self.update_file_store.delete(*uuid);
+        });
+        // The processing updates complete once they stop.
+        for &(uuid, id) in &canceled {
+            if !processing.contains(&(uuid, id)) {
+                let _ = self.completion_sender.send((uuid, id));
+            }
+        }
+
+        Ok(Cancellation {
+            canceled_by,
+            canceled,
+        })
+    }
+
+    /// Deletes the finished updates matching `filter`, along with the kept payloads of the failed
+    /// additions, and returns their index and id. The pending updates are never deleted.
+    pub fn delete_finished(&self, filter: &DeleteFilter) -> Result<Vec<(Uuid, u64)>> {
+        let mut txn = self.env.write_txn()?;
+        let mut deleted = Vec::new();
+        let mut uuids_to_remove = Vec::new();
+
+        let mut updates = self.updates.iter_mut(&mut txn)?.lazily_decode_data();
+        while let Some(Ok(((uuid, _), update))) = updates.next() {
+            let update = update.decode()?;
+            if !filter.matches(uuid, &update) {
+                continue;
+            }
+            if let UpdateStatus::Failed(failed) = &update {
+                if let Update::DocumentAddition { content_uuid, .. } = failed.meta() {
+                    uuids_to_remove.push(*content_uuid);
+                }
+            }
+
+            unsafe {
+                updates.del_current()?;
+            }
+
+            deleted.push((uuid, update));
+        }
+
+        drop(updates);
+
+        self.delete_task_lookups(&mut txn, &deleted)?;
+        txn.commit()?;
+
+        uuids_to_remove.iter().for_each(|uuid| {
+            let _ = self.update_file_store.delete(*uuid);
+        });
+
+        Ok(deleted
+            .into_iter()
+            .map(|(uuid, update)| (uuid, update.id()))
+            .collect())
+    }
+
+    /// Returns a receiver notified of the updates completed from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<(Uuid, u64)> {
+        self.completion_sender.subscribe()
+    }
+
     /// List the updates for `index_uuid`.
     pub fn list(&self, index_uuid: Uuid) -> Result<Vec<UpdateStatus>> {
         let mut update_list = BTreeMap::<u64, UpdateStatus>::new();
@@ -385,10 +992,12 @@ impl UpdateStore {
             update_list.insert(update.id(), update);
         }
 
-        // If the currently processing update is from this index, replace the corresponding pending update with this one.
+        // If the currently processing updates are from this index, replace the corresponding pending updates with them.
         match *self.state.read() {
             State::Processing(uuid, ref processing) if uuid == index_uuid => {
-                update_list.insert(processing.id(), processing.clone().into());
+                for processing in processing {
+                    update_list.insert(processing.id(), processing.clone().into());
+                }
             }
             _ => (),
         }
@@ -396,14 +1005,213 @@ impl UpdateStore {
         Ok(update_list.into_iter().map(|(_, v)| v).collect())
     }
 
+    /// Lists the tasks matching `filter` from the most recent one, starting from the task uid
+    /// `from`, the last one when `None`, and stopping after `limit` of them.
+    pub fn tasks(
+        &self,
+        filter: &TaskFilter,
+        from: Option<u64>,
+        limit: usize,
+    ) -> Result<Vec<(Uuid, UpdateStatus)>> {
+        let from = from.unwrap_or(u64::MAX);
+        let txn = self.env.read_txn()?;
+
+        // The pending updates, the processing one included, are all in the short pending queue.
+        let mut pendings = Vec::new();
+        if filter.accepts(StatusKind::Enqueued) || filter.accepts(StatusKind::Processing) {
+            let processing = match *self.state.read() {
+                State::Processing(uuid, ref processing) => Some((uuid, processing.clone())),
+                _ => None,
+            };
+            for entry in self.pending_queue.rev_iter(&txn)?.lazily_decode_data() {
+                let ((global_id, uuid, id), pending) = entry?;
+                if global_id > from {
+                    continue;
+                }
+                let processing_update = processing
+                    .as_ref()
+                    .filter(|(processing_uuid, _)| *processing_uuid == uuid)
+                    .and_then(|(_, processing)| processing.iter().find(|p| p.id() == id));
+                let update: UpdateStatus = match processing_update {
+                    Some(processing) => processing.clone().into(),
+                    None => {
+                        let mut pending = pending.decode()?;
+                        pending.task_uid.get_or_insert(global_id);
+                        pending.into()
+                    }
+                };
+                if filter.matches(uuid, &update) {
+                    pendings.push((global_id, uuid, update));
+                }
+            }
+        }
+
+        // The finished updates are found from the lookup that narrows them the most.
+        let finished_statuses: Vec<_> = [
+            StatusKind::Processed,
+            StatusKind::Failed,
+            StatusKind::Aborted,
+            StatusKind::Canceled,
+        ]
+        .iter()
+        .copied()
+        .filter(|status| filter.accepts(*status))
+        .collect();
+        let mut task_uids: Vec<TaskUids> = Vec::new();
+        match (&filter.statuses, &filter.index_uuids) {
+            _ if filter.uids.is_some() => {
+                let mut uids: Vec<_> = filter
+                    .uids
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .filter(|uid| *uid <= from)
+                    .collect();
+                uids.sort_unstable_by(|a, b| b.cmp(a));
+                task_uids.push(Box::new(uids.into_iter().map(Ok)));
+            }
+            (Some(_), _) => {
+                for status in finished_statuses {
+                    let range = (status, 0)..=(status, from);
+                    let uids = self.tasks_by_status.rev_range(&txn, &range)?;
+                    task_uids.push(Box::new(uids.map(|entry| entry.map(|((_, uid), _)| uid))));
+                }
+            }
+            (None, Some(uuids)) => {
+                for uuid in uuids {
+                    let range = (*uuid, 0)..=(*uuid, from);
+                    let uids = self.tasks_by_index.rev_range(&txn, &range)?;
+                    task_uids.push(Box::new(uids.map(|entry| entry.map(|((_, uid), _)| uid))));
+                }
+            }
+            (None, None) => {
+                let range = ..=BEU64::new(from);
+                let uids = self.task_ids.rev_range(&txn, &range)?;
+                task_uids.push(Box::new(uids.map(|entry| entry.map(|(uid, _)| uid.get()))));
+            }
+        }
+        let mut finished = DescendingUnion::new(task_uids).filter_map(|uid| {
+            let update = uid.and_then(|uid| {
+                let (uuid, id) = match self.task_ids.get(&txn, &BEU64::new(uid))? {
+                    Some(ids) => ids,
+                    None => return Ok(None),
+                };
+                Ok(self.updates.get(&txn, &(uuid, id))?.map(|u| (uid, uuid, u)))
+            });
+            match update {
+                Ok(Some((uid, uuid, update))) if filter.matches(uuid, &update) => {
+                    Some(Ok((uid, uuid, update)))
+                }
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            }
+        });
+
+        // Both lists are sorted by decreasing task uid, they are merged in the same order.
+        let mut tasks = Vec::new();
+        let mut pendings = pendings.into_iter().peekable();
+        let mut next_finished = finished.next().transpose()?;
+        while tasks.len() < limit {
+            let take_pending = match (pendings.peek(), &next_finished) {
+                (Some((pending_uid, _, _)), Some((finished_uid, _, _))) => {
+                    pending_uid > finished_uid
+                }
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+            if take_pending {
+                if let Some((_, uuid, update)) = pendings.next() {
+                    tasks.push((uuid, update));
+                }
+            } else if let Some((_, uuid, update)) = next_finished.take() {
+                tasks.push((uuid, update));
+                next_finished = finished.next().transpose()?;
+            }
+        }
+
+        Ok(tasks)
+    }
+
+    /// Returns the task with the uid `task_uid`, and its index, or `None` if it doesn't exist.
+    pub fn task(&self, task_uid: u64) -> heed::Result<Option<(Uuid, UpdateStatus)>> {
+        if let State::Processing(uuid, ref processing) = *self.state.read() {
+            if let Some(processing) = processing
+                .iter()
+                .find(|p| p.from.task_uid == Some(task_uid))
+            {
+                return Ok(Some((uuid, processing.clone().into())));
+            }
+        }
+
+        let txn = self.env.read_txn()?;
+        if let Some((uuid, id)) = self.task_ids.get(&txn, &BEU64::new(task_uid))? {
+            return Ok(self.updates.get(&txn, &(uuid, id))?.map(|u| (uuid, u)));
+        }
+
+        for entry in self.pending_queue.iter(&txn)?.lazily_decode_data() {
+            let ((global_id, uuid, _), pending) = entry?;
+            if global_id == task_uid {
+                let mut pending = pending.decode()?;
+                pending.task_uid = Some(global_id);
+                return Ok(Some((uuid, pending.into())));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the finished tasks following the task uid `after`, all of them when `None`, by
+    /// increasing task uid. Stops after `limit` of them, or before the first pending task for the
+    /// tasks to be returned in the order of their uids. The uid of the last task looked at is
+    /// returned with them.
+    pub fn finished_tasks_after(
+        &self,
+        after: Option<u64>,
+        limit: usize,
+    ) -> heed::Result<(Vec<(u64, Uuid, UpdateStatus)>, Option<u64>)> {
+        let txn = self.env.read_txn()?;
+        let start = after.map_or(0, |after| after.saturating_add(1));
+        let end = self.first_pending_task_uid(&txn)?.unwrap_or(u64::MAX);
+        let range = BEU64::new(start)..BEU64::new(end);
+
+        let (mut tasks, mut last) = (Vec::new(), None);
+        for entry in self.task_ids.range(&txn, &range)?.take(limit) {
+            let (uid, (uuid, id)) = entry?;
+            if let Some(update) = self.updates.get(&txn, &(uuid, id))? {
+                tasks.push((uid.get(), uuid, update));
+            }
+            last = Some(uid.get());
+        }
+
+        Ok((tasks, last))
+    }
+
+    /// Returns the uid of the last finished task preceding all the pending tasks.
+    pub fn last_finished_task_uid(&self) -> heed::Result<Option<u64>> {
+        let txn = self.env.read_txn()?;
+        let end = self.first_pending_task_uid(&txn)?.unwrap_or(u64::MAX);
+        let range = ..BEU64::new(end);
+        let last = self.task_ids.rev_range(&txn, &range)?.next().transpose()?;
+        Ok(last.map(|(uid, _)| uid.get()))
+    }
+
+    /// The pending updates, the processing ones included, are ordered by their task uid.
+    fn first_pending_task_uid(&self, txn: &heed::RoTxn) -> heed::Result<Option<u64>> {
+        Ok(self
+            .pending_queue
+            .first(txn)?
+            .map(|((global_id, _, _), _)| global_id))
+    }
+
     /// Returns the update associated meta or `None` if the update doesn't exist.
     pub fn meta(&self, index_uuid: Uuid, update_id: u64) -> heed::Result<Option<UpdateStatus>> {
         // Check if the update is the one currently processing
         match *self.state.read() {
-            State::Processing(uuid, ref processing)
-                if uuid == index_uuid && processing.id() == update_id =>
-            {
-                return Ok(Some(processing.clone().into()));
+            State::Processing(uuid, ref processing) if uuid == index_uuid => {
+                if let Some(processing) = processing.iter().find(|p| p.id() == update_id) {
+                    return Ok(Some(processing.clone().into()));
+                }
             }
             _ => (),
         }
@@ -439,7 +1247,8 @@ impl UpdateStore {
 
         let mut pendings = self.pending_queue.iter_mut(&mut txn)?.lazily_decode_data();
 
-        while let Some(Ok(((_, uuid, _), pending))) = pendings.next() {
+        while let Some(entry) = pendings.next() {
+            let ((_, uuid, _), pending) = entry?;
             if uuid == index_uuid {
                 let pending = pending.decode()?;
                 if let Update::DocumentAddition { content_uuid, .. } = pending.meta() {
@@ -462,14 +1271,28 @@ impl UpdateStore {
             .prefix_iter_mut(&mut txn, index_uuid.as_bytes())?
             .lazily_decode_data();
 
-        while let Some(_) = updates.next() {
+        let mut deleted = Vec::new();
+        while let Some(entry) = updates.next() {
+            let (_, update) = entry?;
+            let update = update.decode()?;
+            // The payloads of the failed additions are kept until their index is deleted.
+            if let UpdateStatus::Failed(failed) = &update {
+                if let Update::DocumentAddition { content_uuid, .. } = failed.meta() {
+                    uuids_to_remove.push(*content_uuid);
+                }
+            }
+
             unsafe {
                 updates.del_current()?;
             }
+
+            deleted.push((index_uuid, update));
         }
 
         drop(updates);
 
+        self.delete_task_lookups(&mut txn, &deleted)?;
+
         txn.commit()?;
 
         // If the currently processing update is from our index, we wait until it is
@@ -491,7 +1314,45 @@ impl UpdateStore {
         Ok(())
     }
 
-    pub fn snapshot(&self, indexes: Vec<Index>, path: impl AsRef<Path>) -> Result<()> {
+    /// Moves the pending updates of `from` to the index `to`, the index replacing it. They keep
+    /// their task uid and their order, but take the next update ids of `to`. If the currently
+    /// processing update is for `from`, the call blocks until the update is terminated.
+    pub fn move_pending(&self, from: Uuid, to: Uuid) -> Result<()> {
+        let _state_lock = self.state.write();
+        let mut txn = self.env.write_txn()?;
+
+        let mut moved = Vec::new();
+        for entry in self.pending_queue.iter(&txn)? {
+            let ((global_id, uuid, id), pending) = entry?;
+            if uuid == from {
+                moved.push((global_id, id, pending));
+            }
+        }
+        for (global_id, id, mut pending) in moved {
+            self.pending_queue
+                .delete(&mut txn, &(global_id, from, id))?;
+            pending.update_id = self.next_update_id_raw(&mut txn, to)?;
+            self.pending_queue
+                .put(&mut txn, &(global_id, to, pending.update_id), &pending)?;
+        }
+
+        txn.commit()?;
+
+        if let Err(TrySendError::Closed(())) = self.notification_sender.try_send(()) {
+            panic!("Update store loop exited");
+        }
+
+        Ok(())
+    }
+
+    /// Snapshots the updates and the `indexes` in `path`, the copies being made at the rate of
+    /// the throttle.
+    pub fn snapshot(
+        &self,
+        indexes: Vec<Index>,
+        path: impl AsRef<Path>,
+        throttle: Option<&IoThrottle>,
+    ) -> Result<()> {
         let state_lock = self.state.write();
         state_lock.swap(State::Snapshoting);
 
@@ -504,9 +1365,6 @@ impl UpdateStore {
         create_dir_all(&update_path)?;
         let db_path = update_path.join("data.mdb");
 
-        // create db snapshot
-        self.env.copy_to_path(&db_path, CompactionOption::Enabled)?;
-
         let pendings = self.pending_queue.iter(&txn)?.lazily_decode_data();
 
         let uuids: HashSet<_> = indexes.iter().map(|i| i.uuid).collect();
@@ -519,16 +1377,20 @@ impl UpdateStore {
                 } = pending.decode()?
                 {
                     self.update_file_store
-                        .snapshot(content_uuid, &path)
+                        .snapshot(content_uuid, &path, throttle)
                         .unwrap();
                 }
             }
         }
 
+        // create db snapshot. The updates can be registered again once the raw file of the
+        // database is cloned, they are not processed until the end of the snapshot.
+        compacted_copy(&self.env, &db_path, txn, throttle)?;
+
         let path = path.as_ref().to_owned();
         indexes
             .par_iter()
-            .try_for_each(|index| index.snapshot(path.clone()))
+            .try_for_each(|index| index.snapshot(path.clone(), throttle))
             .unwrap();
 
         Ok(())
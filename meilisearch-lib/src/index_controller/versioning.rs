@@ -0,0 +1,163 @@
+//! Checks of the engine version a dump or a snapshot was created with before it is imported, so
+//! that an unsupported source fails early, naming both versions, rather than halfway through its
+//! import.
+
+use std::fs;
+use std::path::Path;
+
+use log::warn;
+
+/// The file holding the version of the engine that created a snapshot.
+pub const VERSION_FILE_NAME: &str = "VERSION";
+
+/// The oldest engine version whose dumps the loaders can read.
+const OLDEST_DUMP_VERSION: Version = (0, 13, 0);
+
+/// A `major.minor.patch` version.
+type Version = (u64, u64, u64);
+
+#[derive(Debug, thiserror::Error)]
+pub enum VersionError {
+    #[error("The {kind} was created by MeiliSearch v{source_version}, which can't be imported by MeiliSearch v{current_version}. {suggestion}")]
+    Unsupported {
+        kind: &'static str,
+        source_version: String,
+        current_version: &'static str,
+        suggestion: String,
+    },
+    #[error("The {0} has an invalid engine version: `{1}`.")]
+    InvalidVersion(&'static str, String),
+}
+
+fn current_version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
+/// Parses a version such as `0.23.0` or `0.23.0-rc.1`, the pre-release being ignored.
+fn parse_version(version: &str) -> Option<Version> {
+    let mut parts = version.trim().splitn(3, '.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    let patch = parts.next()?;
+    let patch_end = patch
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or_else(|| patch.len());
+    let patch = patch[..patch_end].parse().ok()?;
+    Some((major, minor, patch))
+}
+
+fn format_version((major, minor, patch): Version) -> String {
+    format!("{}.{}.{}", major, minor, patch)
+}
+
+/// Checks that a dump created by the engine `version` can be imported: the dumps of the older
+/// versions are upgraded by the loaders, but not the ones of the newer versions.
+pub fn check_dump_version(version: &str) -> Result<(), VersionError> {
+    let source = parse_version(version)
+        .ok_or_else(|| VersionError::InvalidVersion("dump", version.to_string()))?;
+    let current = parse_version(current_version()).expect("invalid package version");
+
+    let suggestion = if (source.0, source.1) > (current.0, current.1) {
+        format!(
+            "Upgrade this instance to v{}.{} or later to import it.",
+            source.0, source.1
+        )
+    } else if source < OLDEST_DUMP_VERSION {
+        format!(
+            "Import it in MeiliSearch v{} first, then import a new dump of it.",
+            format_version(OLDEST_DUMP_VERSION)
+        )
+    } else {
+        return Ok(());
+    };
+
+    Err(VersionError::Unsupported {
+        kind: "dump",
+        source_version: version.to_string(),
+        current_version: current_version(),
+        suggestion,
+    })
+}
+
+/// Checks that a snapshot created by the engine `version` can be imported: the snapshots are
+/// copies of the databases, whose format may change with every minor version.
+pub fn check_snapshot_version(version: &str) -> Result<(), VersionError> {
+    let source = parse_version(version)
+        .ok_or_else(|| VersionError::InvalidVersion("snapshot", version.to_string()))?;
+    let current = parse_version(current_version()).expect("invalid package version");
+
+    if (source.0, source.1) == (current.0, current.1) {
+        return Ok(());
+    }
+
+    Err(VersionError::Unsupported {
+        kind: "snapshot",
+        source_version: version.to_string(),
+        current_version: current_version(),
+        suggestion: format!(
+            "Create a dump with MeiliSearch v{} and import it with `--import-dump` instead.",
+            version.trim()
+        ),
+    })
+}
+
+/// Writes the version of the engine in the snapshot being created in `path`.
+pub fn write_version_file(path: impl AsRef<Path>) -> std::io::Result<()> {
+    fs::write(path.as_ref().join(VERSION_FILE_NAME), current_version())
+}
+
+/// Checks the version of the snapshot imported in `path`. The snapshots created before the
+/// version was written in them are imported as is.
+pub fn check_snapshot_version_file(path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let version_path = path.as_ref().join(VERSION_FILE_NAME);
+    if !version_path.exists() {
+        warn!("The snapshot has no version file, its version can't be checked.");
+        return Ok(());
+    }
+
+    let version = fs::read_to_string(version_path)?;
+    check_snapshot_version(&version)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_versions() {
+        assert_eq!(parse_version("0.23.0"), Some((0, 23, 0)));
+        assert_eq!(parse_version("0.24.1-rc.2\n"), Some((0, 24, 1)));
+        assert_eq!(parse_version("0.23"), None);
+        assert_eq!(parse_version("latest"), None);
+    }
+
+    #[test]
+    fn dump_versions() {
+        let current = parse_version(current_version()).unwrap();
+
+        assert!(check_dump_version(current_version()).is_ok());
+        assert!(check_dump_version("0.13.0").is_ok());
+        assert!(check_dump_version("0.12.9").is_err());
+        let newer = format!("{}.{}.0", current.0, current.1 + 1);
+        assert!(matches!(
+            check_dump_version(&newer),
+            Err(VersionError::Unsupported { .. })
+        ));
+        assert!(matches!(
+            check_dump_version("unknown"),
+            Err(VersionError::InvalidVersion(..))
+        ));
+    }
+
+    #[test]
+    fn snapshot_versions() {
+        let current = parse_version(current_version()).unwrap();
+
+        assert!(check_snapshot_version(current_version()).is_ok());
+        let patch = format!("{}.{}.{}", current.0, current.1, current.2 + 1);
+        assert!(check_snapshot_version(&patch).is_ok());
+        let older = format!("{}.{}.0", current.0, current.1.saturating_sub(1));
+        assert!(check_snapshot_version(&older).is_err());
+    }
+}
@@ -0,0 +1,93 @@
+use std::fs::File;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use log::{error, info};
+use serde::Serialize;
+use tokio::task::spawn_blocking;
+use walkdir::WalkDir;
+
+use super::index_resolver::HardStateIndexResolver;
+use crate::index::Index;
+use crate::options::WarmupMode;
+
+/// Tracks the advancement of the warmup of the indexes.
+#[derive(Debug, Default)]
+pub struct WarmupProgress {
+    mode: WarmupMode,
+    total_indexes: AtomicUsize,
+    warmed_indexes: AtomicUsize,
+    finished: AtomicBool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarmupStatus {
+    pub mode: WarmupMode,
+    pub finished: bool,
+    pub total_indexes: usize,
+    pub warmed_indexes: usize,
+}
+
+impl WarmupProgress {
+    pub fn new(mode: WarmupMode) -> Self {
+        Self {
+            mode,
+            finished: AtomicBool::new(mode == WarmupMode::None),
+            ..Default::default()
+        }
+    }
+
+    pub fn status(&self) -> WarmupStatus {
+        WarmupStatus {
+            mode: self.mode,
+            finished: self.finished.load(Ordering::Relaxed),
+            total_indexes: self.total_indexes.load(Ordering::Relaxed),
+            warmed_indexes: self.warmed_indexes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Opens every index and loads it in memory according to the warmup mode.
+    pub async fn run(self: Arc<Self>, index_resolver: Arc<HardStateIndexResolver>) {
+        info!("Warming up the indexes ({:?}).", self.mode);
+
+        // Listing the indexes opens them, which is all the meta warmup needs besides reading
+        // their metadata.
+        match index_resolver.list().await {
+            Ok(indexes) => {
+                self.total_indexes.store(indexes.len(), Ordering::Relaxed);
+                for (uid, index) in indexes {
+                    let mode = self.mode;
+                    match spawn_blocking(move || warm_index(&index, mode)).await {
+                        Ok(Ok(())) => (),
+                        Ok(Err(e)) => error!("Error while warming up the index {}: {}", uid, e),
+                        Err(e) => error!("Error while warming up the index {}: {}", uid, e),
+                    }
+                    self.warmed_indexes.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            Err(e) => error!("Error while listing the indexes to warm up: {}", e),
+        }
+
+        self.finished.store(true, Ordering::Relaxed);
+        info!("Warmup finished.");
+    }
+}
+
+fn warm_index(index: &Index, mode: WarmupMode) -> anyhow::Result<()> {
+    index.stats()?;
+    index.settings()?;
+
+    if mode == WarmupMode::Full {
+        // Reading the files through the OS puts them in the page cache, the memory mapped pages
+        // are then served without reading the disk.
+        for entry in WalkDir::new(index.env.path()) {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                std::io::copy(&mut File::open(entry.path())?, &mut std::io::sink())?;
+            }
+        }
+    }
+
+    Ok(())
+}
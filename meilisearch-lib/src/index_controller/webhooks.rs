@@ -0,0 +1,481 @@
+//! The webhooks notified of the finished tasks, each of them registered for the tasks of an index,
+//! or of all the indexes, and filtered by the events of the tasks. The event of a task is its type
+//! followed by its status, such as `documentAddition.failed`, and the filters of a webhook are
+//! events whose type or status can be `*`, such as `settingsUpdate.*`.
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs::{create_dir_all, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use heed::types::{SerdeJson, Str};
+use heed::{Database, Env, EnvOpenOptions};
+use meilisearch_error::{Code, ErrorCode};
+use milli::update::{IndexDocumentsMethod, Setting};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::updates::status::UpdateStatus;
+use super::Task;
+use crate::io_throttle::{compacted_env_copy, create_file, IoThrottle};
+use crate::Update;
+
+const WEBHOOKS_DB_PATH: &str = "webhooks";
+const WEBHOOKS_DUMP_FILE: &str = "webhooks.jsonl";
+const WEBHOOKS_STORE_SIZE: usize = 104_857_600; // 100MiB
+/// The filter of the webhooks notified of all the events.
+const ANY_EVENT: &str = "*";
+
+const TASK_TYPES: [&str; 5] = [
+    "documentAddition",
+    "documentPartial",
+    "documentDeletion",
+    "clearAll",
+    "settingsUpdate",
+];
+const TASK_STATUSES: [&str; 3] = ["processed", "failed", "canceled"];
+
+pub type Result<T> = std::result::Result<T, WebhookError>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("Webhook `{0}` not found.")]
+    WebhookNotFound(Uuid),
+    #[error("Invalid webhook: {0}")]
+    InvalidWebhook(String),
+    #[error("Internal error: {0}")]
+    Internal(Box<dyn Error + Send + Sync + 'static>),
+}
+
+internal_error!(WebhookError: heed::Error, std::io::Error, serde_json::Error);
+
+impl ErrorCode for WebhookError {
+    fn error_code(&self) -> Code {
+        match self {
+            WebhookError::WebhookNotFound(_) => Code::WebhookNotFound,
+            WebhookError::InvalidWebhook(_) => Code::InvalidWebhook,
+            WebhookError::Internal(_) => Code::Internal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    pub uid: Uuid,
+    pub url: String,
+    /// The value of the `Authorization` header of the notifications.
+    pub authorization: Option<String>,
+    /// The index whose tasks are notified, `None` for all the indexes.
+    pub index_uid: Option<String>,
+    pub events: BTreeSet<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn default_events() -> BTreeSet<String> {
+    std::iter::once(String::from(ANY_EVENT)).collect()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CreateWebhook {
+    pub url: String,
+    #[serde(default)]
+    pub authorization: Option<String>,
+    #[serde(default)]
+    pub index_uid: Option<String>,
+    #[serde(default = "default_events")]
+    pub events: BTreeSet<String>,
+}
+
+/// The changes of a webhook, `null` removing its authorization or its index.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct PatchWebhook {
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub authorization: Setting<String>,
+    #[serde(default)]
+    pub index_uid: Setting<String>,
+    #[serde(default)]
+    pub events: Option<BTreeSet<String>>,
+}
+
+impl Webhook {
+    fn validate(&self) -> Result<()> {
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            return Err(WebhookError::InvalidWebhook(format!(
+                "`{}` is not an HTTP URL.",
+                self.url
+            )));
+        }
+        if self.events.is_empty() {
+            return Err(WebhookError::InvalidWebhook(String::from(
+                "`events` must contain at least one event.",
+            )));
+        }
+        if let Some(event) = self.events.iter().find(|event| !is_valid_filter(event)) {
+            return Err(WebhookError::InvalidWebhook(format!(
+                "`{}` is not a valid event, expected `<type>.<status>` with a type among {}, a status among {}, or `*`.",
+                event,
+                TASK_TYPES.join(", "),
+                TASK_STATUSES.join(", "),
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether the webhook is notified of `task`.
+    pub fn matches(&self, task: &Task) -> bool {
+        let index_matches = self
+            .index_uid
+            .as_ref()
+            .map_or(true, |uid| *uid == task.index_uid);
+        index_matches
+            && match task_event(&task.status) {
+                Some((task_type, status)) => self
+                    .events
+                    .iter()
+                    .any(|filter| filter_matches(filter, task_type, status)),
+                None => false,
+            }
+    }
+}
+
+fn is_valid_filter(filter: &str) -> bool {
+    match filter.split_once('.') {
+        Some((task_type, status)) => {
+            (task_type == ANY_EVENT || TASK_TYPES.contains(&task_type))
+                && (status == ANY_EVENT || TASK_STATUSES.contains(&status))
+        }
+        None => filter == ANY_EVENT,
+    }
+}
+
+fn filter_matches(filter: &str, task_type: &str, status: &str) -> bool {
+    match filter.split_once('.') {
+        Some((filter_type, filter_status)) => {
+            (filter_type == ANY_EVENT || filter_type == task_type)
+                && (filter_status == ANY_EVENT || filter_status == status)
+        }
+        None => filter == ANY_EVENT,
+    }
+}
+
+/// The type and the status of a finished task, `None` for the tasks that aren't finished.
+fn task_event(status: &UpdateStatus) -> Option<(&'static str, &'static str)> {
+    let task_status = match status {
+        UpdateStatus::Processed(_) => "processed",
+        UpdateStatus::Failed(_) => "failed",
+        UpdateStatus::Aborted(_) | UpdateStatus::Canceled(_) => "canceled",
+        UpdateStatus::Enqueued(_) | UpdateStatus::Processing(_) => return None,
+    };
+    let task_type = match status.meta() {
+        Update::DocumentAddition {
+            method: IndexDocumentsMethod::UpdateDocuments,
+            ..
+        } => "documentPartial",
+        Update::DocumentAddition { .. } => "documentAddition",
+        Update::DeleteDocuments(_) | Update::DeleteByFilter { .. } => "documentDeletion",
+        Update::ClearDocuments => "clearAll",
+        Update::Settings(_) => "settingsUpdate",
+    };
+    Some((task_type, task_status))
+}
+
+#[derive(Clone)]
+pub struct WebhookStore {
+    env: Env,
+    webhooks: Database<Str, SerdeJson<Webhook>>,
+    /// The uid of the last task notified, by notifier, for the notifications to resume where
+    /// they stopped.
+    cursors: Database<Str, SerdeJson<u64>>,
+}
+
+impl WebhookStore {
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().join(WEBHOOKS_DB_PATH);
+        create_dir_all(&path)?;
+        let mut options = EnvOpenOptions::new();
+        options.map_size(WEBHOOKS_STORE_SIZE);
+        options.max_dbs(2);
+        let env = options.open(path)?;
+        let webhooks = env.create_database(Some("webhooks"))?;
+        let cursors = env.create_database(Some("cursors"))?;
+        Ok(Self {
+            env,
+            webhooks,
+            cursors,
+        })
+    }
+
+    /// Registers `webhook`, notified of the tasks following the task uid `cursor`.
+    pub fn create(&self, webhook: CreateWebhook, cursor: Option<u64>) -> Result<Webhook> {
+        let now = Utc::now();
+        let webhook = Webhook {
+            uid: Uuid::new_v4(),
+            url: webhook.url,
+            authorization: webhook.authorization,
+            index_uid: webhook.index_uid,
+            events: webhook.events,
+            created_at: now,
+            updated_at: now,
+        };
+        webhook.validate()?;
+
+        let mut txn = self.env.write_txn()?;
+        self.webhooks
+            .put(&mut txn, &webhook.uid.to_string(), &webhook)?;
+        if let Some(cursor) = cursor {
+            self.cursors
+                .put(&mut txn, &webhook.uid.to_string(), &cursor)?;
+        }
+        txn.commit()?;
+        Ok(webhook)
+    }
+
+    pub fn get(&self, uid: Uuid) -> Result<Webhook> {
+        let txn = self.env.read_txn()?;
+        self.webhooks
+            .get(&txn, &uid.to_string())?
+            .ok_or(WebhookError::WebhookNotFound(uid))
+    }
+
+    /// Returns all the webhooks, from the oldest one.
+    pub fn list(&self) -> Result<Vec<Webhook>> {
+        let txn = self.env.read_txn()?;
+        let mut webhooks = self
+            .webhooks
+            .iter(&txn)?
+            .map(|entry| entry.map(|(_, webhook)| webhook))
+            .collect::<heed::Result<Vec<_>>>()?;
+        webhooks.sort_unstable_by(|a, b| a.created_at.cmp(&b.created_at));
+        Ok(webhooks)
+    }
+
+    pub fn update(&self, uid: Uuid, patch: PatchWebhook) -> Result<Webhook> {
+        let mut txn = self.env.write_txn()?;
+        let mut updated = self
+            .webhooks
+            .get(&txn, &uid.to_string())?
+            .ok_or(WebhookError::WebhookNotFound(uid))?;
+
+        if let Some(url) = patch.url {
+            updated.url = url;
+        }
+        match patch.authorization {
+            Setting::Set(authorization) => updated.authorization = Some(authorization),
+            Setting::Reset => updated.authorization = None,
+            Setting::NotSet => (),
+        }
+        match patch.index_uid {
+            Setting::Set(index_uid) => updated.index_uid = Some(index_uid),
+            Setting::Reset => updated.index_uid = None,
+            Setting::NotSet => (),
+        }
+        if let Some(events) = patch.events {
+            updated.events = events;
+        }
+        updated.validate()?;
+        updated.updated_at = Utc::now();
+
+        self.webhooks.put(&mut txn, &uid.to_string(), &updated)?;
+        txn.commit()?;
+        Ok(updated)
+    }
+
+    pub fn delete(&self, uid: Uuid) -> Result<()> {
+        let mut txn = self.env.write_txn()?;
+        if !self.webhooks.delete(&mut txn, &uid.to_string())? {
+            return Err(WebhookError::WebhookNotFound(uid));
+        }
+        self.cursors.delete(&mut txn, &uid.to_string())?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    pub fn cursor(&self, name: &str) -> Result<Option<u64>> {
+        let txn = self.env.read_txn()?;
+        Ok(self.cursors.get(&txn, name)?)
+    }
+
+    pub fn put_cursor(&self, name: &str, task_uid: u64) -> Result<()> {
+        let mut txn = self.env.write_txn()?;
+        self.cursors.put(&mut txn, name, &task_uid)?;
+        txn.commit()?;
+        Ok(())
+    }
+
+    pub fn snapshot(&self, path: impl AsRef<Path>, throttle: Option<&IoThrottle>) -> Result<()> {
+        let path = path.as_ref().join(WEBHOOKS_DB_PATH);
+        create_dir_all(&path)?;
+        compacted_env_copy(&self.env, &path.join("data.mdb"), throttle)?;
+        Ok(())
+    }
+
+    /// Dumps the webhooks, without their cursors: the task uids of a dump can be renumbered when
+    /// it is imported.
+    pub fn dump(&self, path: impl AsRef<Path>, throttle: Option<&IoThrottle>) -> Result<()> {
+        let dump_path = path.as_ref().join(WEBHOOKS_DB_PATH);
+        create_dir_all(&dump_path)?;
+        let mut dump_file = create_file(dump_path.join(WEBHOOKS_DUMP_FILE), throttle)?;
+
+        let txn = self.env.read_txn()?;
+        for entry in self.webhooks.iter(&txn)? {
+            let (_, webhook) = entry?;
+            serde_json::to_writer(&mut dump_file, &webhook)?;
+            dump_file.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Loads the webhooks of a dump, the dumps created before the webhooks holding none. The
+    /// indexes of the webhooks are prefixed with `prefix`, like the uids of the indexes of the
+    /// dump, and the webhooks already in the database are kept. Returns the loaded webhooks.
+    pub fn load_dump(
+        src: impl AsRef<Path>,
+        dst: impl AsRef<Path>,
+        prefix: &str,
+    ) -> Result<Vec<Uuid>> {
+        let mut loaded = Vec::new();
+        let src_webhooks = src.as_ref().join(WEBHOOKS_DB_PATH).join(WEBHOOKS_DUMP_FILE);
+        if !src_webhooks.exists() {
+            return Ok(loaded);
+        }
+
+        let store = Self::new(dst)?;
+        let mut txn = store.env.write_txn()?;
+        for line in BufReader::new(File::open(&src_webhooks)?).lines() {
+            let mut webhook: Webhook = serde_json::from_str(&line?)?;
+            webhook.index_uid = webhook
+                .index_uid
+                .map(|index_uid| format!("{}{}", prefix, index_uid));
+            let uid = webhook.uid.to_string();
+            if store.webhooks.get(&txn, &uid)?.is_none() {
+                store.webhooks.put(&mut txn, &uid, &webhook)?;
+                loaded.push(webhook.uid);
+            }
+        }
+        txn.commit()?;
+        store.env.prepare_for_closing().wait();
+
+        Ok(loaded)
+    }
+
+    /// Removes the webhooks loaded from a dump.
+    pub fn unload_dump(dst: impl AsRef<Path>, webhooks: &[Uuid]) -> Result<()> {
+        let store = Self::new(dst)?;
+        let mut txn = store.env.write_txn()?;
+        for uid in webhooks {
+            store.webhooks.delete(&mut txn, &uid.to_string())?;
+        }
+        txn.commit()?;
+        store.env.prepare_for_closing().wait();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn validate_event_filters() {
+        assert!(is_valid_filter("*"));
+        assert!(is_valid_filter("documentAddition.failed"));
+        assert!(is_valid_filter("settingsUpdate.*"));
+        assert!(is_valid_filter("*.canceled"));
+        assert!(!is_valid_filter("documentAddition"));
+        assert!(!is_valid_filter("documentAddition.enqueued"));
+        assert!(!is_valid_filter("indexCreation.*"));
+
+        assert!(filter_matches("*", "clearAll", "processed"));
+        assert!(filter_matches(
+            "settingsUpdate.*",
+            "settingsUpdate",
+            "failed"
+        ));
+        assert!(!filter_matches("settingsUpdate.*", "clearAll", "failed"));
+        assert!(filter_matches(
+            "documentAddition.failed",
+            "documentAddition",
+            "failed"
+        ));
+        assert!(!filter_matches(
+            "documentAddition.failed",
+            "documentAddition",
+            "processed"
+        ));
+    }
+
+    #[test]
+    fn store_webhooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = WebhookStore::new(dir.path()).unwrap();
+        let webhook = store
+            .create(
+                CreateWebhook {
+                    url: String::from("https://example.com/hook"),
+                    authorization: None,
+                    index_uid: Some(String::from("movies")),
+                    events: default_events(),
+                },
+                None,
+            )
+            .unwrap();
+        assert_eq!(store.list().unwrap().len(), 1);
+
+        let patch = PatchWebhook {
+            index_uid: Setting::Reset,
+            events: Some(std::iter::once(String::from("settingsUpdate.*")).collect()),
+            ..PatchWebhook::default()
+        };
+        let updated = store.update(webhook.uid, patch).unwrap();
+        assert_eq!(updated.index_uid, None);
+        assert_eq!(store.get(webhook.uid).unwrap().events, updated.events);
+
+        let patch = PatchWebhook {
+            url: Some(String::from("ftp://example.com")),
+            ..PatchWebhook::default()
+        };
+        assert!(store.update(webhook.uid, patch).is_err());
+
+        store.delete(webhook.uid).unwrap();
+        assert!(store.get(webhook.uid).is_err());
+
+        assert_eq!(store.cursor("notifier").unwrap(), None);
+        store.put_cursor("notifier", 42).unwrap();
+        assert_eq!(store.cursor("notifier").unwrap(), Some(42));
+    }
+
+    #[test]
+    fn dump_and_load_webhooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = WebhookStore::new(dir.path().join("src")).unwrap();
+        let webhook = store
+            .create(
+                CreateWebhook {
+                    url: String::from("https://example.com/hook"),
+                    authorization: None,
+                    index_uid: Some(String::from("movies")),
+                    events: default_events(),
+                },
+                None,
+            )
+            .unwrap();
+        let dump = dir.path().join("dump");
+        store.dump(&dump, None).unwrap();
+
+        let dst = dir.path().join("dst");
+        let loaded = WebhookStore::load_dump(&dump, &dst, "v2_").unwrap();
+        assert_eq!(loaded, vec![webhook.uid]);
+        let store = WebhookStore::new(&dst).unwrap();
+        let loaded = store.get(webhook.uid).unwrap();
+        assert_eq!(loaded.index_uid.as_deref(), Some("v2_movies"));
+    }
+}
@@ -0,0 +1,192 @@
+//! The limit of the rate of the I/O of the snapshots and the dumps, so that the copies made in the
+//! background don't evict the pages of the databases from the page cache and slow the searches
+//! down. A snapshot or a dump shares a single throttle between all the files it reads and writes,
+//! some of them being copied in parallel.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use heed::{CompactionOption, Env};
+use parking_lot::Mutex;
+
+#[derive(Debug, Clone)]
+pub struct IoThrottle {
+    bytes_per_sec: u64,
+    start: Instant,
+    bytes: Arc<Mutex<u64>>,
+}
+
+impl IoThrottle {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec,
+            start: Instant::now(),
+            bytes: Arc::new(Mutex::new(0)),
+        }
+    }
+
+    /// Sleeps until reading or writing `bytes` more bytes keeps the rate under the limit.
+    fn consume(&self, bytes: usize) {
+        let expected = {
+            let mut total = self.bytes.lock();
+            *total += bytes as u64;
+            Duration::from_secs_f64(*total as f64 / self.bytes_per_sec as f64)
+        };
+        let elapsed = self.start.elapsed();
+        if expected > elapsed {
+            std::thread::sleep(expected - elapsed);
+        }
+    }
+}
+
+/// A reader or a writer whose I/O is throttled, when a throttle is given.
+pub struct Throttled<'a, T> {
+    inner: T,
+    throttle: Option<&'a IoThrottle>,
+}
+
+impl<'a, T> Throttled<'a, T> {
+    pub fn new(inner: T, throttle: Option<&'a IoThrottle>) -> Self {
+        Self { inner, throttle }
+    }
+
+    fn consume(&self, bytes: usize) {
+        if let Some(throttle) = self.throttle {
+            throttle.consume(bytes);
+        }
+    }
+}
+
+impl<R: Read> Read for Throttled<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+impl<W: Write> Write for Throttled<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.consume(written);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Creates the file at `path`, its writes being throttled.
+pub fn create_file<'a>(
+    path: impl AsRef<Path>,
+    throttle: Option<&'a IoThrottle>,
+) -> io::Result<Throttled<'a, File>> {
+    Ok(Throttled::new(File::create(path)?, throttle))
+}
+
+/// Copies the `src` file to `dst`, its reads being throttled.
+pub fn copy_file(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    throttle: Option<&IoThrottle>,
+) -> io::Result<()> {
+    let mut reader = Throttled::new(File::open(src)?, throttle);
+    let mut dst = File::create(dst)?;
+    io::copy(&mut reader, &mut dst)?;
+    Ok(())
+}
+
+/// Copies the database of `env` to the `dst` file, compacted. LMDB writing the copy at once, it
+/// is written to a pipe that is read at the rate of the throttle.
+#[cfg(target_os = "linux")]
+pub fn compacted_env_copy(
+    env: &Env,
+    dst: &Path,
+    throttle: Option<&IoThrottle>,
+) -> heed::Result<()> {
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+
+    let throttle = match throttle {
+        Some(throttle) => throttle.clone(),
+        None => return env.copy_to_path(dst, CompactionOption::Enabled).map(drop),
+    };
+
+    let mut fds = [0; 2];
+    // Safety: `fds` can hold the two file descriptors of the pipe.
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(heed::Error::Io(io::Error::last_os_error()));
+    }
+    // Safety: the file descriptors were just created, they are only owned by these files.
+    let (reader, writer) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+    let mut dst = File::create(dst).map_err(heed::Error::Io)?;
+
+    let copy = std::thread::spawn(move || -> io::Result<()> {
+        io::copy(&mut Throttled::new(reader, Some(&throttle)), &mut dst)?;
+        dst.sync_all()
+    });
+    let result = env.copy_to_fd(writer.as_raw_fd(), CompactionOption::Enabled);
+    // The copy of the pipe ends once its writing end is closed.
+    drop(writer);
+    let copied = copy.join().unwrap_or_else(|_| {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            "the copy of the database panicked",
+        ))
+    });
+
+    result?;
+    copied.map_err(heed::Error::Io)
+}
+
+/// The copies of the databases are only throttled on linux.
+#[cfg(not(target_os = "linux"))]
+pub fn compacted_env_copy(
+    env: &Env,
+    dst: &Path,
+    _throttle: Option<&IoThrottle>,
+) -> heed::Result<()> {
+    env.copy_to_path(dst, CompactionOption::Enabled).map(drop)
+}
+
+#[cfg(test)]
+mod test {
+    use heed::EnvOpenOptions;
+
+    use super::*;
+
+    #[test]
+    fn throttled_copies() {
+        let dir = tempfile::tempdir().unwrap();
+        let env = EnvOpenOptions::new().open(dir.path()).unwrap();
+        let db = env
+            .create_database::<heed::types::Str, heed::types::Str>(None)
+            .unwrap();
+        let mut txn = env.write_txn().unwrap();
+        db.put(&mut txn, "hello", "world").unwrap();
+        txn.commit().unwrap();
+
+        let dst_dir = tempfile::tempdir().unwrap();
+        let dst = dst_dir.path().join("data.mdb");
+        let throttle = IoThrottle::new(1024 * 1024);
+        compacted_env_copy(&env, &dst, Some(&throttle)).unwrap();
+
+        let copy = EnvOpenOptions::new().open(dst_dir.path()).unwrap();
+        let db = copy
+            .open_database::<heed::types::Str, heed::types::Str>(None)
+            .unwrap()
+            .unwrap();
+        let txn = copy.read_txn().unwrap();
+        assert_eq!(db.get(&txn, "hello").unwrap(), Some("world"));
+
+        let throttle = IoThrottle::new(40 * 1024);
+        let start = Instant::now();
+        let mut file = create_file(dir.path().join("written"), Some(&throttle)).unwrap();
+        file.write_all(&[1u8; 4096]).unwrap();
+        // the 4096 bytes are written in about 100ms
+        assert!(start.elapsed() >= Duration::from_millis(90));
+    }
+}
@@ -5,12 +5,15 @@ pub mod options;
 pub mod index;
 pub mod index_controller;
 
+pub use index_controller::engine::Engine;
 pub use index_controller::{updates::store::Update, IndexController as MeiliSearch};
 
 pub use milli;
 
 mod compression;
 mod document_formats;
+mod file_clone;
+mod io_throttle;
 
 use walkdir::WalkDir;
 
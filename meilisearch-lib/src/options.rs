@@ -3,6 +3,7 @@ use std::{ops::Deref, str::FromStr};
 
 use byte_unit::{Byte, ByteError};
 use milli::CompressionType;
+use serde::Serialize;
 use structopt::StructOpt;
 use sysinfo::{RefreshKind, System, SystemExt};
 
@@ -41,6 +42,58 @@ pub struct IndexerOpts {
     /// Number of parallel jobs for indexing, defaults to # of CPUs.
     #[structopt(long)]
     pub indexing_jobs: Option<usize>,
+
+    /// The maximum number of distinct values of a filterable attribute. The updates making an
+    /// attribute exceed it fail instead of blowing up the size of the index.
+    #[structopt(long)]
+    pub max_facet_cardinality: Option<u64>,
+
+    /// The maximum number of documents of an index, the additions exceeding it fail.
+    #[structopt(long)]
+    pub max_documents_per_index: Option<u64>,
+
+    /// The maximum number of documents of all the indexes together, the additions exceeding it
+    /// fail.
+    #[structopt(long)]
+    pub max_documents: Option<u64>,
+
+    /// Indexes the documents on a single thread and the document additions one at a time, loads
+    /// the indexes of a dump one after the other and seeds the unseeded random samples, so that
+    /// two databases built from the same updates, or the same dump, return the same search
+    /// results, but for their processing time.
+    #[structopt(long)]
+    pub deterministic_indexing: bool,
+
+    /// The maximum `offset + limit` of a search, the deeper pages being reached with
+    /// `searchAfter`.
+    #[structopt(long)]
+    pub max_search_depth: Option<usize>,
+
+    /// The maximum `limit` of a search.
+    #[structopt(long)]
+    pub max_search_limit: Option<usize>,
+
+    /// The maximum number of attributes of the `facetsDistribution` of a search.
+    #[structopt(long)]
+    pub max_search_facets: Option<usize>,
+
+    /// The maximum number of attributes of the hits of a search.
+    #[structopt(long)]
+    pub max_attributes_to_retrieve: Option<usize>,
+
+    /// The maximum number of consecutive document additions of an index indexed together, in a
+    /// single indexing pass.
+    #[structopt(long)]
+    pub max_batch_size: Option<usize>,
+
+    /// The number of indexes of a dump loaded in parallel.
+    #[structopt(long)]
+    pub dump_import_jobs: Option<usize>,
+
+    /// The prefix added to the uids of the indexes of a dump, which are then loaded next to the
+    /// indexes of the database instead of replacing them.
+    #[structopt(long)]
+    pub dump_import_prefix: Option<String>,
 }
 
 impl Default for IndexerOpts {
@@ -52,6 +105,17 @@ impl Default for IndexerOpts {
             chunk_compression_type: CompressionType::None,
             chunk_compression_level: None,
             indexing_jobs: None,
+            max_facet_cardinality: None,
+            max_documents_per_index: None,
+            max_documents: None,
+            deterministic_indexing: false,
+            max_search_depth: None,
+            max_search_limit: None,
+            max_search_facets: None,
+            max_attributes_to_retrieve: None,
+            max_batch_size: None,
+            dump_import_jobs: None,
+            dump_import_prefix: None,
         }
     }
 }
@@ -99,16 +163,185 @@ impl MaxMemory {
     pub fn unlimited() -> Self {
         Self(None)
     }
+
+    /// The memory of each of `parts` indexers sharing this one.
+    pub fn divided(self, parts: usize) -> Self {
+        Self(
+            self.0
+                .map(|memory| Byte::from_bytes(memory.get_bytes() / parts.max(1) as u128)),
+        )
+    }
 }
 
-/// Returns the total amount of bytes available or `None` if this system isn't supported.
+/// The number of indexes of a dump loaded in parallel by default.
+const DEFAULT_DUMP_IMPORT_JOBS: usize = 4;
+
+/// The number of consecutive document additions indexed together by default.
+const DEFAULT_MAX_BATCH_SIZE: usize = 100;
+
+impl IndexerOpts {
+    /// The number of indexes of a dump loaded in parallel, a single one when the indexing is
+    /// deterministic.
+    pub fn dump_import_jobs(&self) -> usize {
+        if self.deterministic_indexing {
+            return 1;
+        }
+        self.dump_import_jobs
+            .unwrap_or(DEFAULT_DUMP_IMPORT_JOBS)
+            .max(1)
+    }
+
+    /// The number of threads used to index the documents, a single one when the indexing is
+    /// deterministic.
+    pub fn indexing_threads(&self) -> usize {
+        if self.deterministic_indexing {
+            return 1;
+        }
+        self.indexing_jobs.unwrap_or_else(|| available_cpus() / 2)
+    }
+
+    /// The maximum number of consecutive document additions of an index indexed together, a
+    /// single one when the indexing is deterministic: the batches depend on the additions
+    /// enqueued while the previous update is processed.
+    pub fn batch_size(&self) -> usize {
+        if self.deterministic_indexing {
+            return 1;
+        }
+        self.max_batch_size.unwrap_or(DEFAULT_MAX_BATCH_SIZE).max(1)
+    }
+}
+
+/// Returns the total amount of bytes available or `None` if this system isn't supported. In a
+/// container, this is the memory limit of its cgroup when lower than the memory of the host.
 fn total_memory_bytes() -> Option<u64> {
     if System::IS_SUPPORTED {
         let memory_kind = RefreshKind::new().with_memory();
         let mut system = System::new_with_specifics(memory_kind);
         system.refresh_memory();
-        Some(system.total_memory() * 1024) // KiB into bytes
+        let host_memory = system.total_memory() * 1024; // KiB into bytes
+        Some(cgroup_memory_limit().map_or(host_memory, |limit| limit.min(host_memory)))
+    } else {
+        None
+    }
+}
+
+/// Returns the number of CPUs available, taking the CPU quota of the cgroup into account.
+pub fn available_cpus() -> usize {
+    let cpus = num_cpus::get();
+    cgroup_cpu_quota().map_or(cpus, |quota| (quota.ceil() as usize).clamp(1, cpus))
+}
+
+fn read_cgroup_file(path: &str) -> Option<String> {
+    std::fs::read_to_string(path).ok()
+}
+
+/// The memory limit of the cgroup, in bytes, read from cgroup v2 then v1.
+fn cgroup_memory_limit() -> Option<u64> {
+    read_cgroup_file("/sys/fs/cgroup/memory.max")
+        .or_else(|| read_cgroup_file("/sys/fs/cgroup/memory/memory.limit_in_bytes"))
+        .and_then(|limit| parse_cgroup_memory_limit(&limit))
+}
+
+/// The CPU quota of the cgroup, as a number of CPUs, read from cgroup v2 then v1.
+fn cgroup_cpu_quota() -> Option<f64> {
+    match read_cgroup_file("/sys/fs/cgroup/cpu.max") {
+        Some(cpu_max) => parse_cgroup_v2_cpu_max(&cpu_max),
+        None => {
+            let quota = read_cgroup_file("/sys/fs/cgroup/cpu/cpu.cfs_quota_us")?;
+            let period = read_cgroup_file("/sys/fs/cgroup/cpu/cpu.cfs_period_us")?;
+            parse_cgroup_cpu_quota(&quota, &period)
+        }
+    }
+}
+
+/// Parses a memory limit, `max` meaning there is no limit.
+fn parse_cgroup_memory_limit(limit: &str) -> Option<u64> {
+    limit.trim().parse().ok()
+}
+
+/// Parses the `<quota> <period>` content of the cgroup v2 `cpu.max` file.
+fn parse_cgroup_v2_cpu_max(cpu_max: &str) -> Option<f64> {
+    let mut parts = cpu_max.split_whitespace();
+    let quota = parts.next()?;
+    let period = parts.next()?;
+    parse_cgroup_cpu_quota(quota, period)
+}
+
+/// Parses a CPU quota and period, in microseconds. The quota is `max` or `-1` when unlimited.
+fn parse_cgroup_cpu_quota(quota: &str, period: &str) -> Option<f64> {
+    let quota: f64 = quota.trim().parse().ok()?;
+    let period: f64 = period.trim().parse().ok()?;
+    if quota > 0.0 && period > 0.0 {
+        Some(quota / period)
     } else {
         None
     }
 }
+
+/// How much of the indexes is loaded in memory at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WarmupMode {
+    None,
+    /// Opens the indexes and reads their metadata.
+    Meta,
+    /// Reads the whole content of the indexes.
+    Full,
+}
+
+impl Default for WarmupMode {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+impl FromStr for WarmupMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "meta" => Ok(Self::Meta),
+            "full" => Ok(Self::Full),
+            other => Err(format!("invalid warmup mode `{}`", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_cgroup_limits() {
+        assert_eq!(parse_cgroup_memory_limit("536870912\n"), Some(536870912));
+        assert_eq!(parse_cgroup_memory_limit("max\n"), None);
+
+        assert_eq!(parse_cgroup_v2_cpu_max("150000 100000\n"), Some(1.5));
+        assert_eq!(parse_cgroup_v2_cpu_max("max 100000\n"), None);
+        assert_eq!(parse_cgroup_cpu_quota("-1\n", "100000\n"), None);
+        assert_eq!(parse_cgroup_cpu_quota("200000\n", "100000\n"), Some(2.0));
+    }
+
+    #[test]
+    fn deterministic_indexing_threads() {
+        let opt = IndexerOpts {
+            indexing_jobs: Some(4),
+            dump_import_jobs: Some(4),
+            max_batch_size: Some(4),
+            deterministic_indexing: true,
+            ..IndexerOpts::default()
+        };
+        assert_eq!(opt.indexing_threads(), 1);
+        assert_eq!(opt.dump_import_jobs(), 1);
+        assert_eq!(opt.batch_size(), 1);
+    }
+
+    #[test]
+    fn divided_max_memory() {
+        let memory = MaxMemory(Some(Byte::from_bytes(1000)));
+        assert_eq!(*memory.divided(4), Some(Byte::from_bytes(250)));
+        assert_eq!(*memory.divided(0), Some(Byte::from_bytes(1000)));
+        assert_eq!(*MaxMemory::unlimited().divided(4), None);
+    }
+}